@@ -28,10 +28,13 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
     // those fields individually (so they can be added and removed independently of eachother) using
     // JSON. The `nested` fields should also implement the `Params` trait and their fields will be
     // inherited and added to this field's param mapping list. The order follows the declaration
-    // order We'll also enforce that there are no duplicate keys for `id` fields at compile time.
+    // order unless it's overridden with `display_order`. We'll also enforce that there are no
+    // duplicate keys for `id` fields at compile time.
     // TODO: This duplication check doesn't work for nested fields since we don't know anything
     //       about the fields on the nested structs
     let mut params: Vec<Param> = Vec::new();
+    // The `display_order` attribute for each element in `params`, if the field had one
+    let mut param_display_orders: Vec<Option<usize>> = Vec::new();
     let mut persistent_fields: Vec<PersistentField> = Vec::new();
     for field in fields.named {
         let field_name = match &field.ident {
@@ -40,10 +43,61 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
         };
 
         // All attributes are mutually exclusive. If we encounter multiple or duplicate attributes,
-        // then we'll error out.
+        // then we'll error out. The exceptions are `display_order` and `group`, which modify how
+        // the parameters defined by the other attributes are shown in the host.
         let mut processed_attribute = false;
+        let num_params = params.len();
+        let mut display_order: Option<(usize, proc_macro2::Span)> = None;
+        let mut single_group: Option<syn::LitStr> = None;
         for attr in &field.attrs {
-            if attr.path.is_ident("id") {
+            if attr.path.is_ident("display_order") {
+                match attr.parse_meta() {
+                    Ok(syn::Meta::NameValue(syn::MetaNameValue {
+                        lit: syn::Lit::Int(n),
+                        ..
+                    })) if display_order.is_none() => match n.base10_parse() {
+                        Ok(n) => display_order = Some((n, attr.span())),
+                        Err(err) => return err.to_compile_error().into(),
+                    },
+                    _ => {
+                        return syn::Error::new(
+                            attr.span(),
+                            "The display_order attribute should be a key-value pair with a \
+                             non-negative integer argument and it may only be used once: \
+                             #[display_order = 2]",
+                        )
+                        .to_compile_error()
+                        .into()
+                    }
+                }
+            } else if attr.path.is_ident("group") {
+                match attr.parse_meta() {
+                    Ok(syn::Meta::NameValue(syn::MetaNameValue {
+                        lit: syn::Lit::Str(s),
+                        ..
+                    })) if single_group.is_none() => {
+                        if s.value().is_empty() || s.value().contains('/') {
+                            return syn::Error::new(
+                                s.span(),
+                                "Group names cannot be empty or contain slashes",
+                            )
+                            .to_compile_error()
+                            .into();
+                        }
+
+                        single_group = Some(s);
+                    }
+                    _ => {
+                        return syn::Error::new(
+                            attr.span(),
+                            "The group attribute should be a key-value pair with a string \
+                             argument and it may only be used once: #[group = \"Filter\"]",
+                        )
+                        .to_compile_error()
+                        .into()
+                    }
+                }
+            } else if attr.path.is_ident("id") {
                 match attr.parse_meta() {
                     Ok(syn::Meta::NameValue(syn::MetaNameValue {
                         lit: syn::Lit::Str(s),
@@ -76,6 +130,7 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
                         params.push(Param::Single {
                             id: s,
                             field: field_name.clone(),
+                            group: None,
                         });
 
                         processed_attribute = true;
@@ -133,10 +188,12 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
                 };
             } else if attr.path.is_ident("nested") {
                 // This one is more complicated. Supports an `array` attribute, an `id_prefix =
-                // "foo"` attribute, and a `group = "group name"` attribute. All are optional, and
-                // the first two are mutually exclusive.
+                // "foo"` attribute, an `id_format = "foo{index}_{id}"` attribute, and a `group =
+                // "group name"` attribute. All are optional, the first two are mutually exclusive,
+                // and `id_format` can only be used together with `array`.
                 let mut nested_array = false;
                 let mut nested_id_prefix: Option<syn::LitStr> = None;
+                let mut nested_id_format: Option<syn::LitStr> = None;
                 let mut nested_group: Option<syn::LitStr> = None;
                 match attr.parse_meta() {
                     // In this case it's a plain `#[nested]` attribute without parameters
@@ -170,6 +227,28 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
                                 )) if path.is_ident("id_prefix") => {
                                     nested_id_prefix = Some(s.clone());
                                 }
+                                syn::NestedMeta::Meta(syn::Meta::NameValue(
+                                    syn::MetaNameValue {
+                                        path,
+                                        lit: syn::Lit::Str(s),
+                                        ..
+                                    },
+                                )) if path.is_ident("id_format") => {
+                                    let id_format = s.value();
+                                    if id_format.matches("{id}").count() != 1
+                                        || !id_format.contains("{index}")
+                                    {
+                                        return syn::Error::new(
+                                            s.span(),
+                                            "The ID format must contain a single '{id}' and at \
+                                             least one '{index}' placeholder",
+                                        )
+                                        .to_compile_error()
+                                        .into();
+                                    } else {
+                                        nested_id_format = Some(s.clone());
+                                    }
+                                }
                                 syn::NestedMeta::Meta(syn::Meta::NameValue(
                                     syn::MetaNameValue {
                                         path,
@@ -212,18 +291,40 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
                         return syn::Error::new(
                             attr.span(),
                             "The nested attribute should be a list in the following format: \
-                             #[nested([array | id_prefix = \"foo\"], [group = \"group name\"])]",
+                             #[nested([array, [id_format = \"foo{index}_{id}\"] | id_prefix = \
+                             \"foo\"], [group = \"group name\"])]",
                         )
                         .to_compile_error()
                         .into()
                     }
                 };
 
+                if nested_id_format.is_some() && !nested_array {
+                    return syn::Error::new(
+                        attr.span(),
+                        "'id_format' can only be used together with 'array'",
+                    )
+                    .to_compile_error()
+                    .into();
+                }
+
                 params.push(Param::Nested(match (nested_array, nested_id_prefix) {
-                    (true, None) => NestedParams::Array {
-                        field: field_name.clone(),
-                        group: nested_group,
-                    },
+                    (true, None) => {
+                        // The format is split at the `{id}` placeholder so the original IDs can
+                        // be recovered again when deserializing persistent fields
+                        let id_format = nested_id_format
+                            .map(|s| s.value())
+                            .unwrap_or_else(|| String::from(DEFAULT_ARRAY_ID_FORMAT));
+                        let (id_format_prefix, id_format_suffix) =
+                            id_format.split_once("{id}").unwrap();
+
+                        NestedParams::Array {
+                            field: field_name.clone(),
+                            id_format_prefix: id_format_prefix.to_owned(),
+                            id_format_suffix: id_format_suffix.to_owned(),
+                            group: nested_group,
+                        }
+                    }
                     (false, Some(id_prefix)) => NestedParams::Prefixed {
                         field: field_name.clone(),
                         id_prefix,
@@ -246,12 +347,64 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
                 processed_attribute = true;
             }
         }
+
+        // The modifier attributes only make sense on fields that define parameters
+        let defines_params = params.len() > num_params;
+        match (params.last_mut(), single_group) {
+            (Some(Param::Single { group, .. }), Some(single_group)) if defines_params => {
+                *group = Some(single_group);
+            }
+            (_, Some(single_group)) => {
+                return syn::Error::new(
+                    single_group.span(),
+                    "The group attribute can only be used together with the id attribute, use \
+                     #[nested(group = \"...\")] for nested parameter objects",
+                )
+                .to_compile_error()
+                .into();
+            }
+            (_, None) => (),
+        }
+        match display_order {
+            Some((display_order, _)) if defines_params => {
+                param_display_orders.push(Some(display_order))
+            }
+            Some((_, span)) => {
+                return syn::Error::new(
+                    span,
+                    "The display_order attribute can only be used together with the id or nested \
+                     attributes",
+                )
+                .to_compile_error()
+                .into();
+            }
+            None if defines_params => param_display_orders.push(None),
+            None => (),
+        }
     }
 
+    // Parameters without an explicit display order use their position in the declaration order.
+    // If an explicit display order is the same as another parameter's position, then the explicit
+    // one comes first. Remaining ties are resolved using the declaration order since the sort is
+    // stable. This only changes the order the parameters are reported in, the IDs (and thus the
+    // hashes the wrappers derive from them) stay the same.
+    let mut ordered_params: Vec<((usize, bool), &Param)> = params
+        .iter()
+        .zip(param_display_orders)
+        .enumerate()
+        .map(|(idx, (param, display_order))| {
+            (
+                (display_order.unwrap_or(idx), display_order.is_none()),
+                param,
+            )
+        })
+        .collect();
+    ordered_params.sort_by_key(|(sort_key, _)| *sort_key);
+
     // The next step is build the gathered information into tokens that can be spliced into a
     // `Params` implementation
     let param_map_tokens = {
-        let param_mapping_tokens = params.iter().map(|p| p.param_map_tokens());
+        let param_mapping_tokens = ordered_params.iter().map(|(_, p)| p.param_map_tokens());
 
         quote! {
             // This may not be in scope otherwise, used to call .as_ptr()
@@ -353,26 +506,36 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
                             self.#field.deserialize_fields(&matching_fields);
                         },
                     ),
-                    NestedParams::Array { field, .. } => (
+                    NestedParams::Array {
+                        field,
+                        id_format_prefix,
+                        id_format_suffix,
+                        ..
+                    } => (
                         quote! {
                             for (field_idx, field) in self.#field.iter().enumerate() {
-                                let idx = field_idx + 1;
-                                let suffixed = field
+                                let idx = (field_idx + 1).to_string();
+                                let prefix = #id_format_prefix.replace("{index}", &idx);
+                                let suffix = #id_format_suffix.replace("{index}", &idx);
+                                let formatted = field
                                     .serialize_fields()
                                     .into_iter()
-                                    .map(|(key, value)| (format!("{}_{}", key, idx), value));
+                                    .map(|(key, value)| (format!("{}{}{}", prefix, key, suffix), value));
 
-                                serialized.extend(suffixed);
+                                serialized.extend(formatted);
                             }
                         },
                         quote! {
                             for (field_idx, field) in self.#field.iter().enumerate() {
-                                let idx = field_idx + 1;
-                                let suffix = format!("_{}", idx);
+                                let idx = (field_idx + 1).to_string();
+                                let prefix = #id_format_prefix.replace("{index}", &idx);
+                                let suffix = #id_format_suffix.replace("{index}", &idx);
                                 let matching_fields = serialized
                                     .iter()
                                     .filter_map(|(key, value)| {
-                                        let original_key = key.strip_suffix(&suffix)?;
+                                        let original_key = key
+                                            .strip_prefix(prefix.as_str())?
+                                            .strip_suffix(suffix.as_str())?;
                                         Some((original_key.to_owned(), value.to_owned()))
                                     })
                                     .collect();
@@ -430,6 +593,10 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
     .into()
 }
 
+/// The ID format used for `#[nested(array)]` fields without an explicit `id_format`. `{id}` is
+/// replaced by the nested parameter's ID, and `{index}` is replaced by the one-based array index.
+const DEFAULT_ARRAY_ID_FORMAT: &str = "{id}_{index}";
+
 /// A parameter defined on this struct using the `#[id = "..."]` attribute, or another object that
 /// also implements `Params` tagged with one of the variations on the `#[nested]` attribute.
 #[derive(Debug)]
@@ -440,6 +607,8 @@ enum Param {
         field: syn::Ident,
         /// The parameter's unique ID.
         id: syn::LitStr,
+        /// The group set with the `#[group = "..."]` attribute, if any.
+        group: Option<syn::LitStr>,
     },
     /// Another struct also implementing `Params`. This object's parameters are inlined in the
     /// parameter list.
@@ -451,7 +620,18 @@ impl Param {
     /// parameter map.
     fn param_map_tokens(&self) -> proc_macro2::TokenStream {
         match self {
-            Param::Single { field, id } => {
+            Param::Single {
+                field,
+                id,
+                group: Some(group),
+            } => {
+                quote! { [(String::from(#id), self.#field.as_ptr(), String::from(#group))] }
+            }
+            Param::Single {
+                field,
+                id,
+                group: None,
+            } => {
                 quote! { [(String::from(#id), self.#field.as_ptr(), String::new())] }
             }
             Param::Nested(params) => params.param_map_tokens(),
@@ -485,10 +665,17 @@ enum NestedParams {
         group: Option<syn::LitStr>,
     },
     /// This field is an array-like data structure containing nested parameter structs. The
-    /// parameter `foo` will get the new parameter ID `foo_{array_idx + 1}`, and if the group name
-    /// is set then the group will be `{group_name} {array_idx + 1}`.
+    /// parameter `foo` will get a new parameter ID based on the `id_format`, which defaults to
+    /// `foo_{array_idx + 1}`. If the group name is set then the group will be
+    /// `{group_name} {array_idx + 1}`.
     Array {
         field: syn::Ident,
+        /// The part of the ID format before the `{id}` placeholder. May contain `{index}`
+        /// placeholders.
+        id_format_prefix: String,
+        /// The part of the ID format after the `{id}` placeholder. May contain `{index}`
+        /// placeholders.
+        id_format_suffix: String,
         group: Option<syn::LitStr>,
     },
 }
@@ -551,13 +738,17 @@ impl NestedParams {
             // a suffix matching the array index.
             NestedParams::Array {
                 field,
+                id_format_prefix,
+                id_format_suffix,
                 group: Some(group),
             } => quote! {
                 self.#field.iter().enumerate().flat_map(|(idx, params)| {
                     let idx = idx + 1;
+                    let prefix = #id_format_prefix.replace("{index}", &idx.to_string());
+                    let suffix = #id_format_suffix.replace("{index}", &idx.to_string());
 
                     params.param_map().into_iter().map(move |(param_id, param_ptr, nested_group_name)| {
-                        let param_id = format!("{}_{}", param_id, idx);
+                        let param_id = format!("{}{}{}", prefix, param_id, suffix);
                         let group = format!("{} {}", #group, idx);
 
                         // Note that this is different from the other variants
@@ -569,12 +760,19 @@ impl NestedParams {
                     })
                 })
             },
-            NestedParams::Array { field, group: None } => quote! {
+            NestedParams::Array {
+                field,
+                id_format_prefix,
+                id_format_suffix,
+                group: None,
+            } => quote! {
                 self.#field.iter().enumerate().flat_map(|(idx, params)| {
                     let idx = idx + 1;
+                    let prefix = #id_format_prefix.replace("{index}", &idx.to_string());
+                    let suffix = #id_format_suffix.replace("{index}", &idx.to_string());
 
                     params.param_map().into_iter().map(move |(param_id, param_ptr, nested_group_name)| {
-                        let param_id = format!("{}_{}", param_id, idx);
+                        let param_id = format!("{}{}{}", prefix, param_id, suffix);
 
                         (param_id, param_ptr, nested_group_name)
                     })
@@ -109,6 +109,115 @@ impl Default for NestedArrayParams {
     }
 }
 
+#[derive(Params)]
+struct BandParams {
+    #[id = "gain"]
+    pub gain: FloatParam,
+
+    #[id = "freq"]
+    pub freq: FloatParam,
+
+    #[persist = "solo"]
+    pub solo: std::sync::atomic::AtomicBool,
+}
+
+impl Default for BandParams {
+    fn default() -> Self {
+        BandParams {
+            gain: FloatParam::new(
+                "Gain",
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                },
+            ),
+            freq: FloatParam::new(
+                "Frequency",
+                1000.0,
+                FloatRange::Linear {
+                    min: 20.0,
+                    max: 20_000.0,
+                },
+            ),
+            solo: std::sync::atomic::AtomicBool::new(false),
+        }
+    }
+}
+
+#[derive(Default, Params)]
+struct FormattedArrayParams {
+    #[nested(array, id_format = "band{index}_{id}", group = "Band")]
+    pub bands: [BandParams; 4],
+}
+
+#[derive(Params)]
+struct OscillatorParams {
+    #[id = "freq"]
+    pub freq: FloatParam,
+
+    #[id = "wave"]
+    pub wave: IntParam,
+
+    #[persist = "phase-reset"]
+    pub phase_reset: std::sync::atomic::AtomicBool,
+}
+
+impl Default for OscillatorParams {
+    fn default() -> Self {
+        OscillatorParams {
+            freq: FloatParam::new(
+                "Frequency",
+                440.0,
+                FloatRange::Linear {
+                    min: 20.0,
+                    max: 20_000.0,
+                },
+            ),
+            wave: IntParam::new("Waveform", 0, IntRange::Linear { min: 0, max: 3 }),
+            phase_reset: std::sync::atomic::AtomicBool::new(false),
+        }
+    }
+}
+
+#[derive(Default, Params)]
+struct SynthParams {
+    #[nested(id_prefix = "osc1", group = "Oscillator 1")]
+    pub osc1: OscillatorParams,
+
+    #[nested(id_prefix = "osc2", group = "Oscillator 2")]
+    pub osc2: OscillatorParams,
+}
+
+#[derive(Params)]
+struct ReorderedParams {
+    #[id = "one"]
+    pub one: BoolParam,
+
+    #[id = "two"]
+    #[display_order = 3]
+    #[group = "Output"]
+    pub two: FloatParam,
+
+    #[nested(id_prefix = "nested", group = "Nested")]
+    pub nested: FlatParams,
+
+    #[display_order = 0]
+    #[id = "three"]
+    pub three: IntParam,
+}
+
+impl Default for ReorderedParams {
+    fn default() -> Self {
+        ReorderedParams {
+            one: BoolParam::new("one", true),
+            two: FloatParam::new("two", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 }),
+            nested: FlatParams::default(),
+            three: IntParam::new("three", 0, IntRange::Linear { min: 0, max: 100 }),
+        }
+    }
+}
+
 mod param_order {
     use super::*;
 
@@ -209,6 +318,151 @@ mod param_order {
             ]
         );
     }
+
+    #[test]
+    fn display_order() {
+        let p = ReorderedParams::default();
+
+        // `three` moves to the front, and `two` ends up after the nested parameters since those
+        // keep their declaration index. Nested objects are moved as a whole.
+        let param_ids: Vec<String> = p.param_map().into_iter().map(|(id, _, _)| id).collect();
+        assert_eq!(
+            param_ids,
+            [
+                "three",
+                "one",
+                "nested_one",
+                "nested_two",
+                "nested_three",
+                "two"
+            ]
+        );
+    }
+
+    #[test]
+    fn display_order_keeps_params() {
+        let p = ReorderedParams::default();
+
+        // The display order only affects the order, so the IDs and the parameters they point to
+        // are the same as without the override
+        for (id, param_ptr, _) in p.param_map() {
+            let expected_ptr = match id.as_str() {
+                "one" => p.one.as_ptr(),
+                "two" => p.two.as_ptr(),
+                "three" => p.three.as_ptr(),
+                "nested_one" => p.nested.one.as_ptr(),
+                "nested_two" => p.nested.two.as_ptr(),
+                "nested_three" => p.nested.three.as_ptr(),
+                id => panic!("Unexpected parameter ID {id}"),
+            };
+            assert_eq!(param_ptr, expected_ptr);
+        }
+    }
+}
+
+mod prefixed_copies {
+    use super::*;
+    use std::collections::HashSet;
+    use std::sync::atomic::Ordering;
+
+    #[test]
+    fn unique_ids_and_groups() {
+        let p = SynthParams::default();
+        let param_map = p.param_map();
+
+        let ids_groups: Vec<(&str, &str)> = param_map
+            .iter()
+            .map(|(id, _, group)| (id.as_str(), group.as_str()))
+            .collect();
+        assert_eq!(
+            ids_groups,
+            [
+                ("osc1_freq", "Oscillator 1"),
+                ("osc1_wave", "Oscillator 1"),
+                ("osc2_freq", "Oscillator 2"),
+                ("osc2_wave", "Oscillator 2"),
+            ]
+        );
+
+        // Both copies should point to their own parameters
+        let param_ptrs: HashSet<ParamPtr> = param_map.iter().map(|(_, ptr, _)| *ptr).collect();
+        assert_eq!(param_ptrs.len(), 4);
+        assert_eq!(param_map[0].1, p.osc1.freq.as_ptr());
+        assert_eq!(param_map[2].1, p.osc2.freq.as_ptr());
+    }
+
+    #[test]
+    fn persistent_fields() {
+        let p = SynthParams::default();
+        p.osc2.phase_reset.store(true, Ordering::Relaxed);
+
+        let serialized = p.serialize_fields();
+        assert_eq!(serialized.len(), 2);
+        assert_eq!(serialized["osc1_phase-reset"], "false");
+        assert_eq!(serialized["osc2_phase-reset"], "true");
+
+        let restored = SynthParams::default();
+        restored.deserialize_fields(&serialized);
+        assert!(!restored.osc1.phase_reset.load(Ordering::Relaxed));
+        assert!(restored.osc2.phase_reset.load(Ordering::Relaxed));
+    }
+}
+
+mod formatted_array {
+    use super::*;
+    use std::sync::atomic::Ordering;
+
+    #[test]
+    fn unique_ids() {
+        let p = FormattedArrayParams::default();
+
+        let param_ids: Vec<String> = p.param_map().into_iter().map(|(id, _, _)| id).collect();
+        assert_eq!(
+            param_ids,
+            [
+                "band1_gain",
+                "band1_freq",
+                "band2_gain",
+                "band2_freq",
+                "band3_gain",
+                "band3_freq",
+                "band4_gain",
+                "band4_freq",
+            ]
+        );
+
+        let param_groups: Vec<String> = p
+            .param_map()
+            .into_iter()
+            .map(|(_, _, group)| group)
+            .collect();
+        assert_eq!(
+            param_groups,
+            ["Band 1", "Band 1", "Band 2", "Band 2", "Band 3", "Band 3", "Band 4", "Band 4"]
+        );
+    }
+
+    #[test]
+    fn persistent_fields_roundtrip() {
+        let p = FormattedArrayParams::default();
+        p.bands[2].solo.store(true, Ordering::Relaxed);
+
+        let serialized = p.serialize_fields();
+        assert_eq!(
+            serialized.keys().collect::<Vec<_>>(),
+            ["band1_solo", "band2_solo", "band3_solo", "band4_solo"]
+        );
+        assert_eq!(serialized["band3_solo"], "true");
+
+        let restored = FormattedArrayParams::default();
+        restored.deserialize_fields(&serialized);
+        let solos: Vec<bool> = restored
+            .bands
+            .iter()
+            .map(|band| band.solo.load(Ordering::Relaxed))
+            .collect();
+        assert_eq!(solos, [false, false, true, false]);
+    }
 }
 
 mod param_groups {
@@ -315,4 +569,26 @@ mod param_groups {
             ]
         );
     }
+
+    #[test]
+    fn single_param_group() {
+        let p = ReorderedParams::default();
+
+        let param_ids_groups: Vec<(String, String)> = p
+            .param_map()
+            .into_iter()
+            .map(|(id, _, group)| (id, group))
+            .collect();
+        assert_eq!(
+            param_ids_groups,
+            [
+                (String::from("three"), String::from("")),
+                (String::from("one"), String::from("")),
+                (String::from("nested_one"), String::from("Nested")),
+                (String::from("nested_two"), String::from("Nested")),
+                (String::from("nested_three"), String::from("Nested")),
+                (String::from("two"), String::from("Output")),
+            ]
+        );
+    }
 }
@@ -1,8 +1,15 @@
 use std::collections::BTreeMap;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
+use nih_plug::params::persist::ArcSwap;
 use nih_plug::prelude::*;
 
+#[derive(Params, Default)]
+struct SharedParams {
+    #[persist = "path"]
+    pub path: Arc<ArcSwap<String>>,
+}
+
 #[derive(Params, Default)]
 struct WrapperParams {
     #[nested(id_prefix = "foo")]
@@ -41,6 +48,32 @@ unsafe impl Params for InnerParams {
 }
 
 mod persist {
+    mod arc_swap {
+        use super::super::*;
+
+        #[test]
+        fn serialize() {
+            let params = SharedParams::default();
+            params.path.store(Arc::new(String::from("/foo/bar.wav")));
+
+            let serialized = params.serialize_fields();
+            assert_eq!(serialized.len(), 1);
+            assert_eq!(serialized["path"], "\"/foo/bar.wav\"");
+        }
+
+        #[test]
+        fn deserialize() {
+            let mut serialized = BTreeMap::new();
+            serialized.insert(String::from("path"), String::from("\"/foo/bar.wav\""));
+
+            let params = SharedParams::default();
+            // Another thread holding on to the field should see the new value
+            let shared_path = params.path.clone();
+            params.deserialize_fields(&serialized);
+            assert_eq!(shared_path.load().as_str(), "/foo/bar.wav");
+        }
+    }
+
     mod nested_prefix {
 
         use super::super::*;
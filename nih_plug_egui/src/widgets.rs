@@ -6,7 +6,10 @@
 //! to copy the widgets and modify them to your personal taste.
 
 pub mod generic_ui;
+mod param_knob;
 mod param_slider;
 pub mod util;
 
+pub use param_knob::ParamKnob;
 pub use param_slider::ParamSlider;
+pub use util::Detents;
@@ -4,9 +4,16 @@
 use std::sync::Arc;
 
 use egui_baseview::egui::{self, TextStyle, Ui, Vec2};
-use nih_plug::prelude::{Param, ParamFlags, ParamPtr, ParamSetter, Params};
+use nih_plug::prelude::{
+    Editor, Param, ParamFlags, ParamPtr, ParamSetter, ParamVisibility, Params,
+};
 
 use super::ParamSlider;
+use crate::{create_egui_editor, EguiState};
+
+/// Stepped parameters with more steps than this are drawn as sliders by [`GenericControls`]
+/// instead of as dropdowns, since the dropdowns would become unwieldy.
+const MAX_DROPDOWN_STEPS: usize = 64;
 
 /// A widget that can be used to create a generic UI with. This is used in conjuction with empty
 /// structs to emulate existential types.
@@ -31,6 +38,16 @@ pub trait ParamWidget {
 /// Create a generic UI using [`ParamSlider`]s.
 pub struct GenericSlider;
 
+/// Create a generic UI using toggle buttons for parameters with two steps like [`BoolParam`]s,
+/// dropdowns for other stepped parameters like [`EnumParam`]s and [`IntParam`]s, and
+/// [`ParamSlider`]s for continuous parameters. The dropdown entries use the parameters' own value
+/// formatters.
+///
+/// [`BoolParam`]: nih_plug::prelude::BoolParam
+/// [`EnumParam`]: nih_plug::prelude::EnumParam
+/// [`IntParam`]: nih_plug::prelude::IntParam
+pub struct GenericControls;
+
 /// Create a scrollable generic UI using the specified widget. Takes up all the remaining vertical
 /// space.
 pub fn create(
@@ -38,6 +55,20 @@ pub fn create(
     params: Arc<impl Params>,
     setter: &ParamSetter,
     widget: impl ParamWidget,
+) {
+    create_with_visibility(ui, params, setter, widget, |_, _| ParamVisibility::Visible);
+}
+
+/// The same as [`create()`], but `visibility` decides whether each parameter is shown, greyed out,
+/// or hidden. It receives the parameters object and a parameter's ID, and since egui redraws the
+/// entire GUI every frame it's evaluated again using the current parameter values on every frame.
+/// This only affects the generic UI, see [`ParamVisibility`].
+pub fn create_with_visibility<P: Params>(
+    ui: &mut Ui,
+    params: Arc<P>,
+    setter: &ParamSetter,
+    widget: impl ParamWidget,
+    visibility: impl Fn(&P, &str) -> ParamVisibility,
 ) {
     let padding = Vec2::splat(ui.text_style_height(&TextStyle::Body) * 0.2);
     egui::containers::ScrollArea::vertical()
@@ -45,28 +76,92 @@ pub fn create(
         .auto_shrink([false, false])
         .show(ui, |ui| {
             let mut first_widget = true;
-            for (_, param_ptr, _) in params.param_map().into_iter() {
+            for (param_id, param_ptr, _) in params.param_map().into_iter() {
                 let flags = unsafe { param_ptr.flags() };
                 if flags.contains(ParamFlags::HIDE_IN_GENERIC_UI) {
                     continue;
                 }
 
+                let param_visibility = visibility(&params, &param_id);
+                if param_visibility == ParamVisibility::Hidden {
+                    continue;
+                }
+
                 // This list looks weird without a little padding
                 if !first_widget {
                     ui.allocate_space(padding);
                 }
 
-                ui.label(unsafe { param_ptr.name() });
-                unsafe { widget.add_widget_raw(ui, &param_ptr, setter) };
+                ui.add_enabled_ui(param_visibility == ParamVisibility::Visible, |ui| {
+                    ui.label(unsafe { param_ptr.name() });
+                    unsafe { widget.add_widget_raw(ui, &param_ptr, setter) };
+                });
 
                 first_widget = false;
             }
         });
 }
 
+/// Create an [`Editor`] that only contains a generic UI for `params` using [`GenericControls`].
+/// This can be returned from `Plugin::editor()` for plugins that don't have a GUI of their own,
+/// for instance to be able to change their parameters in the standalone application.
+pub fn create_editor<P: Params>(
+    params: Arc<P>,
+    egui_state: Arc<EguiState>,
+) -> Option<Box<dyn Editor>> {
+    create_egui_editor(
+        egui_state,
+        (),
+        |_, _| {},
+        move |egui_ctx, setter, _| {
+            egui::CentralPanel::default().show(egui_ctx, |ui| {
+                create(ui, params.clone(), setter, GenericControls);
+            });
+        },
+    )
+}
+
 impl ParamWidget for GenericSlider {
     fn add_widget<P: Param>(&self, ui: &mut Ui, param: &P, setter: &ParamSetter) {
         // Make these sliders a bit wider, else they look a bit odd
         ui.add(ParamSlider::for_param(param, setter).with_width(100.0));
     }
 }
+
+impl ParamWidget for GenericControls {
+    fn add_widget<P: Param>(&self, ui: &mut Ui, param: &P, setter: &ParamSetter) {
+        match param.step_count() {
+            Some(1) => {
+                let mut enabled = param.modulated_normalized_value() >= 0.5;
+                if ui.toggle_value(&mut enabled, param.to_string()).changed() {
+                    set_normalized_value(param, setter, if enabled { 1.0 } else { 0.0 });
+                }
+            }
+            Some(step_count) if step_count <= MAX_DROPDOWN_STEPS => {
+                let current_step =
+                    (param.modulated_normalized_value() * step_count as f32).round() as usize;
+                egui::ComboBox::from_id_salt(param.as_ptr())
+                    .selected_text(param.to_string())
+                    .show_ui(ui, |ui| {
+                        for step in 0..=step_count {
+                            let normalized = step as f32 / step_count as f32;
+                            let label = param.normalized_value_to_string(normalized, true);
+                            if ui.selectable_label(step == current_step, label).clicked()
+                                && step != current_step
+                            {
+                                set_normalized_value(param, setter, normalized);
+                            }
+                        }
+                    });
+            }
+            _ => GenericSlider.add_widget(ui, param, setter),
+        }
+    }
+}
+
+/// Set a parameter to a new value as a single gesture.
+fn set_normalized_value<P: Param>(param: &P, setter: &ParamSetter, normalized: f32) {
+    setter.begin_set_parameter(param);
+    setter.set_parameter_normalized(param, normalized);
+    setter.end_set_parameter(param);
+}
@@ -0,0 +1,212 @@
+use std::f32::consts::PI;
+use std::sync::LazyLock;
+
+use egui_baseview::egui::{self, vec2, Pos2, Response, Sense, Shape, Stroke, Ui, Widget};
+use nih_plug::prelude::{Param, ParamSetter};
+
+use super::util::{self, Detents};
+
+/// Dragging the knob by this many pixels changes the normalized parameter value from zero to one.
+const DRAG_RANGE: f32 = 200.0;
+/// When shift+dragging a parameter, one pixel dragged corresponds to this much change in the
+/// noramlized parameter. This matches the slider.
+const GRANULAR_DRAG_MULTIPLIER: f32 = 0.0015;
+
+/// The knob's angle at the parameter's minimum value, in radians clockwise from the positive x-axis.
+/// The knob sweeps 270 degrees from the bottom left to the bottom right.
+const MIN_ANGLE: f32 = 0.75 * PI;
+/// The angle between the parameter's minimum and maximum values.
+const ANGLE_RANGE: f32 = 1.5 * PI;
+
+static DRAG_NORMALIZED_VALUE_MEMORY_ID: LazyLock<egui::Id> =
+    LazyLock::new(|| egui::Id::new((file!(), 0)));
+
+/// A rotary knob for a parameter. Dragging the knob up or down changes the value. Like
+/// [`ParamSlider`][super::ParamSlider], the knob supports double click and control click to
+/// reset, shift+drag for granular dragging, and optional detents that can be added with
+/// [`with_detents()`][Self::with_detents()].
+///
+/// TODO: Add WidgetInfo annotations for accessibility
+#[must_use = "You should put this widget in an ui with `ui.add(widget);`"]
+pub struct ParamKnob<'a, P: Param> {
+    param: &'a P,
+    setter: &'a ParamSetter<'a>,
+
+    draw_value: bool,
+    diameter: Option<f32>,
+    detents: Detents,
+}
+
+impl<'a, P: Param> ParamKnob<'a, P> {
+    /// Create a new knob for a parameter. Use the other methods to modify the knob before passing
+    /// it to [`Ui::add()`].
+    pub fn for_param(param: &'a P, setter: &'a ParamSetter<'a>) -> Self {
+        Self {
+            param,
+            setter,
+
+            draw_value: true,
+            diameter: None,
+            detents: Detents::default(),
+        }
+    }
+
+    /// Don't draw the parameter's current value below the knob.
+    pub fn without_value(mut self) -> Self {
+        self.draw_value = false;
+        self
+    }
+
+    /// Set a custom diameter for the knob.
+    pub fn with_diameter(mut self, diameter: f32) -> Self {
+        self.diameter = Some(diameter);
+        self
+    }
+
+    /// Add detents at the specified normalized values. This works the same way as
+    /// [`ParamSlider::with_detents()`][super::ParamSlider::with_detents()]. Because the knob is
+    /// dragged relative to its current value, the knob keeps track of where it would have been
+    /// without snapping, so dragging past a detent works the same way as with the slider.
+    pub fn with_detents(self, detents: impl Into<Vec<f32>>, snap_distance: f32) -> Self {
+        self.with_detent_config(Detents::new(detents, snap_distance))
+    }
+
+    /// The same as [`with_detents()`][Self::with_detents()], but with a [`Detents`] object. This
+    /// can be used to only snap while Alt is held down.
+    pub fn with_detent_config(mut self, detents: Detents) -> Self {
+        self.detents = detents;
+        self
+    }
+
+    fn normalized_value(&self) -> f32 {
+        self.param.modulated_normalized_value()
+    }
+
+    fn set_normalized_value(&self, normalized: f32) {
+        // This snaps to the nearest plain value if the parameter is stepped in some way
+        let value = self.param.preview_plain(normalized);
+        if value != self.param.modulated_plain_value() {
+            self.setter.set_parameter(self.param, value);
+        }
+    }
+
+    /// Begin and end drag still need to be called when using this.
+    fn reset_param(&self) {
+        self.setter
+            .set_parameter(self.param, self.param.default_plain_value());
+    }
+
+    fn knob_ui(&self, ui: &Ui, response: &mut Response) {
+        // The unsnapped value the knob would have without detents or stepped parameters. Dragging
+        // is relative, so it would otherwise get stuck on the first detent or step.
+        let drag_value_id = response.id.with(*DRAG_NORMALIZED_VALUE_MEMORY_ID);
+        if response.drag_started() {
+            let normalized_value = self.normalized_value();
+            ui.memory_mut(|mem| mem.data.insert_temp(drag_value_id, normalized_value));
+        }
+
+        if response.interact_pointer_pos().is_some() {
+            util::ensure_gesture(ui, response, self.setter, self.param);
+            let modifiers = ui.input(|i| i.modifiers);
+            if modifiers.command {
+                // Like double clicking, Ctrl+Click should reset the parameter
+                self.reset_param();
+                response.mark_changed();
+            } else if response.dragged() {
+                let drag_multiplier = if modifiers.shift {
+                    GRANULAR_DRAG_MULTIPLIER
+                } else {
+                    1.0 / DRAG_RANGE
+                };
+                let drag_value = ui
+                    .memory(|mem| mem.data.get_temp(drag_value_id))
+                    .unwrap_or_else(|| self.normalized_value());
+                let drag_value =
+                    (drag_value - (response.drag_delta().y * drag_multiplier)).clamp(0.0, 1.0);
+                ui.memory_mut(|mem| mem.data.insert_temp(drag_value_id, drag_value));
+
+                // Granular dragging ignores the detents, just like with the slider
+                if modifiers.shift {
+                    self.set_normalized_value(drag_value);
+                } else {
+                    self.set_normalized_value(self.detents.snap(drag_value, &modifiers));
+                }
+                response.mark_changed();
+            }
+        }
+        if response.double_clicked() {
+            util::ensure_gesture(ui, response, self.setter, self.param);
+            self.reset_param();
+            response.mark_changed();
+        }
+        util::end_gesture_if_released(ui, response, self.setter, self.param);
+
+        if ui.is_rect_visible(response.rect) {
+            let center = response.rect.center();
+            let radius = response.rect.width().min(response.rect.height()) / 2.0 - 2.0;
+            let point_at = |angle: f32, radius: f32| -> Pos2 {
+                center + (vec2(angle.cos(), angle.sin()) * radius)
+            };
+            let arc = |from: f32, to: f32| -> Vec<Pos2> {
+                const NUM_SEGMENTS: usize = 48;
+                (0..=NUM_SEGMENTS)
+                    .map(|i| {
+                        point_at(
+                            from + ((to - from) * (i as f32 / NUM_SEGMENTS as f32)),
+                            radius,
+                        )
+                    })
+                    .collect()
+            };
+
+            let track_width = (radius * 0.2).max(2.0);
+            ui.painter()
+                .circle_filled(center, radius, ui.visuals().widgets.inactive.bg_fill);
+            ui.painter().add(Shape::line(
+                arc(MIN_ANGLE, MIN_ANGLE + ANGLE_RANGE),
+                Stroke::new(track_width, ui.visuals().widgets.active.bg_fill),
+            ));
+
+            let value_angle = MIN_ANGLE + (self.normalized_value() * ANGLE_RANGE);
+            let filled_color = if response.dragged() {
+                util::add_hsv(ui.visuals().selection.bg_fill, 0.0, -0.1, 0.1)
+            } else {
+                ui.visuals().selection.bg_fill
+            };
+            ui.painter().add(Shape::line(
+                arc(MIN_ANGLE, value_angle),
+                Stroke::new(track_width, filled_color),
+            ));
+            ui.painter().line_segment(
+                [
+                    point_at(value_angle, radius * 0.3),
+                    point_at(value_angle, radius),
+                ],
+                Stroke::new(
+                    track_width / 2.0,
+                    ui.visuals().widgets.active.fg_stroke.color,
+                ),
+            );
+        }
+    }
+}
+
+impl<P: Param> Widget for ParamKnob<'_, P> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let diameter = self
+            .diameter
+            .unwrap_or_else(|| ui.spacing().interact_size.y * 2.0);
+
+        ui.vertical(|ui| {
+            let mut response =
+                ui.allocate_response(vec2(diameter, diameter), Sense::click_and_drag());
+            self.knob_ui(ui, &mut response);
+            if self.draw_value {
+                ui.label(self.param.to_string());
+            }
+
+            response
+        })
+        .inner
+    }
+}
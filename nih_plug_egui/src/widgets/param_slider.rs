@@ -8,7 +8,7 @@ use egui_baseview::egui::{
 use nih_plug::prelude::{Param, ParamSetter};
 use parking_lot::Mutex;
 
-use super::util;
+use super::util::{self, Detents};
 
 /// When shift+dragging a parameter, one pixel dragged corresponds to this much change in the
 /// noramlized parameter.
@@ -21,7 +21,9 @@ static VALUE_ENTRY_MEMORY_ID: LazyLock<egui::Id> = LazyLock::new(|| egui::Id::ne
 
 /// A slider widget similar to [`egui::widgets::Slider`] that knows about NIH-plug parameters ranges
 /// and can get values for it. The slider supports double click and control click to reset,
-/// shift+drag for granular dragging, text value entry by clicking on the value text.
+/// shift+drag for granular dragging, text value entry by clicking on the value text. Optional
+/// detents can be added with [`with_detents()`][Self::with_detents()]. See [`ParamKnob`][super::ParamKnob]
+/// for a rotary version of this widget.
 ///
 /// TODO: Vertical orientation
 /// TODO: Check below for more input methods that should be added
@@ -35,6 +37,7 @@ pub struct ParamSlider<'a, P: Param> {
 
     draw_value: bool,
     slider_width: Option<f32>,
+    detents: Detents,
 
     /// Will be set in the `ui()` function so we can request keyboard input focus on Alt+click.
     keyboard_focus_id: Option<egui::Id>,
@@ -50,6 +53,7 @@ impl<'a, P: Param> ParamSlider<'a, P> {
 
             draw_value: true,
             slider_width: None,
+            detents: Detents::default(),
 
             keyboard_focus_id: None,
         }
@@ -67,6 +71,26 @@ impl<'a, P: Param> ParamSlider<'a, P> {
         self
     }
 
+    /// Add detents at the specified normalized values. While dragging the slider, the value snaps
+    /// to a detent when it's within `snap_distance` of it, with `snap_distance` also being a
+    /// normalized value. A value exactly halfway between two detents that are both in range snaps
+    /// to the lower detent. Holding Alt while dragging temporarily disables snapping, and holding
+    /// Shift for granular dragging or entering a value by text ignores the detents. Use a snap
+    /// distance of 1.0 to always snap to the nearest detent.
+    ///
+    /// For instance, semitone detents on a pitch parameter can be added by converting each
+    /// semitone to a normalized value using [`Param::preview_normalized()`].
+    pub fn with_detents(self, detents: impl Into<Vec<f32>>, snap_distance: f32) -> Self {
+        self.with_detent_config(Detents::new(detents, snap_distance))
+    }
+
+    /// The same as [`with_detents()`][Self::with_detents()], but with a [`Detents`] object. This
+    /// can be used to only snap while Alt is held down.
+    pub fn with_detent_config(mut self, detents: Detents) -> Self {
+        self.detents = detents;
+        self
+    }
+
     fn plain_value(&self) -> P::Plain {
         self.param.modulated_plain_value()
     }
@@ -181,10 +205,12 @@ impl<'a, P: Param> ParamSlider<'a, P> {
         if response.drag_started() {
             // When beginning a drag or dragging normally, reset the memory used to keep track of
             // our granular drag
-            self.begin_drag();
             Self::set_drag_amount_memory(ui, 0.0);
         }
         if let Some(click_pos) = response.interact_pointer_pos() {
+            // Clicks also change the value, so the gesture starts as soon as the mouse button is
+            // pressed rather than when egui considers the interaction to be a drag
+            util::ensure_gesture(ui, response, self.setter, self.param);
             if ui.input(|i| i.modifiers.command) {
                 // Like double clicking, Ctrl+Click should reset the parameter
                 self.reset_param();
@@ -202,18 +228,18 @@ impl<'a, P: Param> ParamSlider<'a, P> {
             } else {
                 let proportion =
                     emath::remap_clamp(click_pos.x, response.rect.x_range(), 0.0..=1.0) as f64;
-                self.set_normalized_value(proportion as f32);
+                let modifiers = ui.input(|i| i.modifiers);
+                self.set_normalized_value(self.detents.snap(proportion as f32, &modifiers));
                 response.mark_changed();
                 Self::set_drag_amount_memory(ui, 0.0);
             }
         }
         if response.double_clicked() {
+            util::ensure_gesture(ui, response, self.setter, self.param);
             self.reset_param();
             response.mark_changed();
         }
-        if response.drag_stopped() {
-            self.end_drag();
-        }
+        util::end_gesture_if_released(ui, response, self.setter, self.param);
 
         // And finally draw the thing
         if ui.is_rect_visible(response.rect) {
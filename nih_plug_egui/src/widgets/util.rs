@@ -1,6 +1,11 @@
 //! Utilities for creating these widgets.
 
-use egui_baseview::egui::{self, Color32};
+use std::sync::LazyLock;
+
+use egui_baseview::egui::{self, Color32, Response, Ui};
+use nih_plug::prelude::{Param, ParamSetter};
+
+static GESTURE_ACTIVE_MEMORY_ID: LazyLock<egui::Id> = LazyLock::new(|| egui::Id::new((file!(), 0)));
 
 /// Additively modify the hue, saturation, and lightness [0, 1] values of a color.
 pub fn add_hsv(color: Color32, h: f32, s: f32, v: f32) -> Color32 {
@@ -19,3 +24,133 @@ pub fn scale_hsv(color: Color32, h: f32, s: f32, v: f32) -> Color32 {
     hsv.v *= v;
     hsv.into()
 }
+
+/// Start an automation gesture for `param` if the widget `response` belongs to doesn't already have
+/// an active gesture. The gesture is ended again in [`end_gesture_if_released()`].
+pub(crate) fn ensure_gesture<P: Param>(
+    ui: &Ui,
+    response: &Response,
+    setter: &ParamSetter,
+    param: &P,
+) {
+    let id = response.id.with(*GESTURE_ACTIVE_MEMORY_ID);
+    if !ui.memory(|mem| mem.data.get_temp(id).unwrap_or(false)) {
+        setter.begin_set_parameter(param);
+        ui.memory_mut(|mem| mem.data.insert_temp(id, true));
+    }
+}
+
+/// End the gesture started by [`ensure_gesture()`] once the pointer is no longer held down on the
+/// widget. Unlike [`Response::drag_stopped()`], this also ends the gesture when the drag gets
+/// interrupted, for instance because the window lost focus.
+pub(crate) fn end_gesture_if_released<P: Param>(
+    ui: &Ui,
+    response: &Response,
+    setter: &ParamSetter,
+    param: &P,
+) {
+    let id = response.id.with(*GESTURE_ACTIVE_MEMORY_ID);
+    if !response.is_pointer_button_down_on()
+        && ui.memory(|mem| mem.data.get_temp(id).unwrap_or(false))
+    {
+        setter.end_set_parameter(param);
+        ui.memory_mut(|mem| mem.data.insert_temp(id, false));
+    }
+}
+
+/// Detents that parameter widgets like [`ParamSlider`][super::ParamSlider] and
+/// [`ParamKnob`][super::ParamKnob] snap to while dragging. The detents are normalized `[0, 1]`
+/// parameter values, and the value snaps to the closest detent within the snap distance using
+/// [`nih_plug::util::snap_to_detent()`]. A value exactly halfway between two detents that are both
+/// in range snaps to the lower detent. By default snapping is enabled and holding Alt while
+/// dragging temporarily disables it. Use [`disabled_by_default()`][Self::disabled_by_default()] to
+/// flip this around.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detents {
+    /// Normalized detent positions, sorted in ascending order.
+    positions: Vec<f32>,
+    /// The normalized distance from a detent at which the value snaps to that detent.
+    snap_distance: f32,
+    /// Whether snapping is active without holding Alt.
+    enabled_by_default: bool,
+}
+
+impl Default for Detents {
+    fn default() -> Self {
+        Self::new(Vec::new(), 0.0)
+    }
+}
+
+impl Detents {
+    /// Detents at the normalized values in `positions` that the value snaps to when it's within
+    /// `snap_distance` of them. Use a snap distance of 1.0 to always snap to the nearest detent.
+    pub fn new(positions: impl Into<Vec<f32>>, snap_distance: f32) -> Self {
+        let mut positions = positions.into();
+        positions.sort_by(f32::total_cmp);
+
+        Self {
+            positions,
+            snap_distance: snap_distance.max(0.0),
+            enabled_by_default: true,
+        }
+    }
+
+    /// Only snap while Alt is held down, instead of snapping unless Alt is held down.
+    pub fn disabled_by_default(mut self) -> Self {
+        self.enabled_by_default = false;
+        self
+    }
+
+    /// The normalized detent positions, sorted in ascending order.
+    pub fn positions(&self) -> &[f32] {
+        &self.positions
+    }
+
+    /// Whether snapping should be applied with these modifiers held down.
+    pub fn is_active(&self, modifiers: &egui::Modifiers) -> bool {
+        self.enabled_by_default != modifiers.alt
+    }
+
+    /// Snap a normalized value to the closest detent if snapping is active with these modifiers.
+    pub fn snap(&self, normalized: f32, modifiers: &egui::Modifiers) -> f32 {
+        if self.is_active(modifiers) {
+            nih_plug::util::snap_to_detent(normalized, &self.positions, self.snap_distance)
+        } else {
+            normalized
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui_baseview::egui::Modifiers;
+
+    use super::*;
+
+    #[test]
+    fn detent_snapping() {
+        let detents = Detents::new([0.75, 0.25, 0.5], 0.05);
+        assert_eq!(detents.positions(), [0.25, 0.5, 0.75]);
+        assert_eq!(detents.snap(0.48, &Modifiers::NONE), 0.5);
+        assert_eq!(detents.snap(0.4, &Modifiers::NONE), 0.4);
+        // Shift is used for granular dragging and doesn't affect snapping
+        assert_eq!(detents.snap(0.72, &Modifiers::SHIFT), 0.75);
+
+        // Exactly halfway between two detents snaps to the lower detent
+        let detents = Detents::new([0.25, 0.75], 0.25);
+        assert_eq!(detents.snap(0.5, &Modifiers::NONE), 0.25);
+    }
+
+    #[test]
+    fn alt_toggles_snapping() {
+        let detents = Detents::new([0.5], 0.05);
+        assert_eq!(detents.snap(0.48, &Modifiers::ALT), 0.48);
+
+        let detents = detents.disabled_by_default();
+        assert_eq!(detents.snap(0.48, &Modifiers::NONE), 0.48);
+        assert_eq!(detents.snap(0.48, &Modifiers::ALT), 0.5);
+
+        // Without any detents there's nothing to snap to
+        assert_eq!(Detents::default().snap(0.48, &Modifiers::NONE), 0.48);
+    }
+}
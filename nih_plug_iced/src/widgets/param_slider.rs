@@ -9,8 +9,9 @@ use crate::backend::Renderer;
 use crate::renderer::Renderer as GraphicsRenderer;
 use crate::text::Renderer as TextRenderer;
 use crate::{
-    alignment, event, keyboard, layout, mouse, renderer, text, touch, Background, Clipboard, Color,
-    Element, Event, Font, Layout, Length, Point, Rectangle, Shell, Size, TextInput, Vector, Widget,
+    alignment, event, keyboard, layout, mouse, renderer, text, touch, window, Background,
+    Clipboard, Color, Element, Event, Font, Layout, Length, Point, Rectangle, Shell, Size,
+    TextInput, Vector, Widget,
 };
 
 use super::util;
@@ -356,6 +357,15 @@ impl<'a, P: Param> Widget<ParamMessage, Renderer> for ParamSlider<'a, P> {
                     return event::Status::Captured;
                 }
             }
+            // We won't receive the button release event if the window loses focus mid-drag, but the
+            // gesture still needs to be ended or the host may keep the parameter in touch mode
+            Event::Window(window::Event::Unfocused) => {
+                if self.state.drag_active {
+                    shell.publish(ParamMessage::EndSetParameter(self.param.as_ptr()));
+
+                    self.state.drag_active = false;
+                }
+            }
             Event::Mouse(mouse::Event::CursorMoved { .. })
             | Event::Touch(touch::Event::FingerMoved { .. }) => {
                 // Don't do anything when we just reset the parameter because that would be weird
@@ -0,0 +1,405 @@
+//! Auxiliary windows that can be opened from the editor, like detachable panels or a larger
+//! spectrum analyzer view.
+//!
+//! The host only knows about the editor's main window. Auxiliary windows are separate top level
+//! windows that run their own event loop on their own thread. Parameter widgets in an auxiliary
+//! window work exactly like they do in the main window, and they receive the same parameter change
+//! notifications. Auxiliary windows are always closed together with the main window, and they're
+//! not reopened automatically when the editor gets opened again.
+//!
+//! The plugin wrappers expect the [`GuiContext`] to only be used from the editor's main thread, and
+//! VST3 hosts don't allow parameter changes from other threads. The [`GuiContext`] passed to an
+//! auxiliary window's app function therefore doesn't call the wrapper directly. Parameter changes
+//! and state changes are instead sent to the main window, which applies them during its next idle
+//! callback. This also means that a parameter's value only changes after that callback.
+//!
+//! # Limitations
+//!
+//! - Auxiliary windows are currently not supported on macOS, where windows can only be created from
+//!   the main thread. Trying to open one there only logs an error.
+//! - [`RawParamEvent::BufferConfigChanged`] events are only sent to the main window.
+//! - The [`ViziaState`][crate::ViziaState]'s size and scale factor only apply to the main window.
+//!   Auxiliary windows always open with their initial size.
+
+use baseview::WindowScalePolicy;
+use crossbeam::channel::{self, Receiver, Sender};
+use nih_plug::debug::*;
+use nih_plug::prelude::util::ProcessLoad;
+use nih_plug::prelude::{GuiContext, ParamIndication, ParamPtr, PluginApi, PluginState};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use vizia::prelude::*;
+
+use crate::editor::{apply_theming, emit_param_change_events};
+use crate::widgets::{self, ParamSubscriptions};
+use crate::ViziaTheming;
+
+/// An auxiliary window that can be opened from the editor by emitting an
+/// [`AuxiliaryWindowEvent`]. Pass these to
+/// [`create_vizia_editor_with_auxiliary_windows()`][crate::create_vizia_editor_with_auxiliary_windows()].
+///
+/// ```ignore
+/// let windows = vec![AuxiliaryWindow::new(
+///     "analyzer",
+///     "Spectrum Analyzer",
+///     (800, 400),
+///     move |cx, _| {
+///         AnalyzerData { spectrum: spectrum.clone() }.build(cx);
+///         Analyzer::new(cx, AnalyzerData::spectrum);
+///     },
+/// )];
+///
+/// // And then from anywhere in the main window:
+/// Button::new(cx, |cx| Label::new(cx, "Analyzer"))
+///     .on_press(|cx| cx.emit(AuxiliaryWindowEvent::Toggle("analyzer")));
+/// ```
+#[derive(Clone)]
+pub struct AuxiliaryWindow {
+    /// A unique identifier for this window, used in [`AuxiliaryWindowEvent`]s.
+    id: &'static str,
+    /// The window's title.
+    title: String,
+    /// The window's initial size in logical pixels.
+    size: (u32, u32),
+    /// The window's app function. This works the same way as the main window's app function.
+    app: Arc<dyn Fn(&mut Context, Arc<dyn GuiContext>) + 'static + Send + Sync>,
+}
+
+/// Events for opening and closing [`AuxiliaryWindow`]s. These can be emitted from anywhere in the
+/// editor's main window, and they are handled automatically by `nih_plug_vizia`. Events for unknown
+/// IDs are ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuxiliaryWindowEvent {
+    /// Open the window with this ID. Does nothing if the window is already open.
+    Open(&'static str),
+    /// Close the window with this ID. Does nothing if the window is not open.
+    Close(&'static str),
+    /// Open the window with this ID if it is closed, or close it if it is open.
+    Toggle(&'static str),
+}
+
+/// The auxiliary windows that are currently open, indexed by their IDs.
+pub(crate) type OpenAuxiliaryWindows = Arc<Mutex<HashMap<&'static str, Arc<OpenAuxiliaryWindow>>>>;
+
+/// State shared between the editor and an open auxiliary window's thread.
+pub(crate) struct OpenAuxiliaryWindow {
+    /// Set when the window should close itself during its next idle callback.
+    close_requested: AtomicBool,
+    /// The same as the editor's `emit_parameters_changed_event`, but for this window.
+    emit_parameters_changed_event: AtomicBool,
+    /// The views in this window that subscribed to parameter changes. These are separate from the
+    /// main window's subscriptions since every window has its own entity tree.
+    param_subscriptions: ParamSubscriptions,
+}
+
+/// Handles [`AuxiliaryWindowEvent`]s in the main window.
+pub(crate) struct AuxiliaryWindowModel {
+    pub windows: Arc<[AuxiliaryWindow]>,
+    pub open_windows: OpenAuxiliaryWindows,
+    /// The context passed to the auxiliary windows. This is an [`AuxiliaryGuiContext`].
+    pub context: Arc<dyn GuiContext>,
+    pub theming: ViziaTheming,
+}
+
+/// The calls made to the [`AuxiliaryGuiContext`]s that still need to be made to the editor's
+/// actual [`GuiContext`] from the main window's thread.
+#[derive(Clone)]
+pub(crate) struct PendingContextCalls {
+    sender: Sender<ContextCall>,
+    receiver: Receiver<ContextCall>,
+}
+
+/// A [`GuiContext`] call made from an auxiliary window's thread.
+enum ContextCall {
+    BeginSetParameter(ParamPtr),
+    SetParameterNormalized(ParamPtr, f32),
+    EndSetParameter(ParamPtr),
+    SetParametersNormalized(Vec<(ParamPtr, f32)>, bool),
+    SetState(PluginState),
+}
+
+/// The [`GuiContext`] used by auxiliary windows. Calls that change the plugin's parameters or state
+/// are sent to the main window's thread instead of being made directly. See the module
+/// documentation.
+pub(crate) struct AuxiliaryGuiContext {
+    /// The editor's actual context. Only functions that don't change anything are called directly.
+    context: Arc<dyn GuiContext>,
+    pending_calls: Sender<ContextCall>,
+}
+
+impl AuxiliaryWindow {
+    /// Create an auxiliary window. The `id` is used to open and close the window using
+    /// [`AuxiliaryWindowEvent`]s and it needs to be unique within the editor. `size` is the
+    /// window's initial size in logical pixels. The `app` function works the same way as the main
+    /// window's app function. Any data the window needs to share with the main window, like
+    /// analyzer data from the plugin, should be cloned into the closure.
+    pub fn new<F>(id: &'static str, title: impl Into<String>, size: (u32, u32), app: F) -> Self
+    where
+        F: Fn(&mut Context, Arc<dyn GuiContext>) + 'static + Send + Sync,
+    {
+        Self {
+            id,
+            title: title.into(),
+            size,
+            app: Arc::new(app),
+        }
+    }
+
+    /// The window's ID.
+    pub fn id(&self) -> &'static str {
+        self.id
+    }
+}
+
+impl Default for PendingContextCalls {
+    fn default() -> Self {
+        let (sender, receiver) = channel::unbounded();
+
+        Self { sender, receiver }
+    }
+}
+
+impl PendingContextCalls {
+    /// Make all calls the auxiliary windows have made since the last time this was called. This
+    /// must be called from the editor's main thread.
+    pub fn apply(&self, context: &dyn GuiContext) {
+        for call in self.receiver.try_iter() {
+            unsafe {
+                match call {
+                    ContextCall::BeginSetParameter(param) => context.raw_begin_set_parameter(param),
+                    ContextCall::SetParameterNormalized(param, normalized) => {
+                        context.raw_set_parameter_normalized(param, normalized)
+                    }
+                    ContextCall::EndSetParameter(param) => context.raw_end_set_parameter(param),
+                    ContextCall::SetParametersNormalized(values, reset_smoothers) => {
+                        context.raw_set_parameters_normalized(&values, reset_smoothers)
+                    }
+                    ContextCall::SetState(state) => context.set_state(state),
+                }
+            }
+        }
+    }
+}
+
+impl AuxiliaryGuiContext {
+    /// Create a context for the auxiliary windows that forwards its calls to `context` through
+    /// `pending_calls`.
+    pub fn new(context: Arc<dyn GuiContext>, pending_calls: &PendingContextCalls) -> Self {
+        Self {
+            context,
+            pending_calls: pending_calls.sender.clone(),
+        }
+    }
+
+    fn send(&self, call: ContextCall) {
+        // The receiver is owned by the editor, which outlives the auxiliary windows
+        let _ = self.pending_calls.send(call);
+    }
+}
+
+impl GuiContext for AuxiliaryGuiContext {
+    fn plugin_api(&self) -> PluginApi {
+        self.context.plugin_api()
+    }
+
+    fn request_resize(&self) -> bool {
+        // The host doesn't know about auxiliary windows, so it can't resize them
+        false
+    }
+
+    unsafe fn raw_begin_set_parameter(&self, param: ParamPtr) {
+        self.send(ContextCall::BeginSetParameter(param));
+    }
+
+    unsafe fn raw_set_parameter_normalized(&self, param: ParamPtr, normalized: f32) {
+        self.send(ContextCall::SetParameterNormalized(param, normalized));
+    }
+
+    unsafe fn raw_end_set_parameter(&self, param: ParamPtr) {
+        self.send(ContextCall::EndSetParameter(param));
+    }
+
+    unsafe fn raw_set_parameters_normalized(
+        &self,
+        values: &[(ParamPtr, f32)],
+        reset_smoothers: bool,
+    ) {
+        self.send(ContextCall::SetParametersNormalized(
+            values.to_vec(),
+            reset_smoothers,
+        ));
+    }
+
+    fn get_state(&self) -> PluginState {
+        self.context.get_state()
+    }
+
+    fn set_state(&self, state: PluginState) {
+        self.send(ContextCall::SetState(state));
+    }
+
+    fn process_load(&self) -> Option<Arc<ProcessLoad>> {
+        self.context.process_load()
+    }
+
+    fn param_indication(&self, param: ParamPtr) -> ParamIndication {
+        self.context.param_indication(param)
+    }
+}
+
+impl Model for AuxiliaryWindowModel {
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|window_event, meta| {
+            let (id, open) = match *window_event {
+                AuxiliaryWindowEvent::Open(id) => (id, true),
+                AuxiliaryWindowEvent::Close(id) => (id, false),
+                AuxiliaryWindowEvent::Toggle(id) => {
+                    let is_open = self.open_windows.lock().unwrap().contains_key(id);
+                    (id, !is_open)
+                }
+            };
+
+            if open {
+                self.open(id);
+            } else {
+                self.close(id);
+            }
+
+            meta.consume();
+        });
+    }
+}
+
+impl AuxiliaryWindowModel {
+    /// Open the window with the given ID on a new thread if it is not already open.
+    fn open(&self, id: &'static str) {
+        let Some(window) = self.windows.iter().find(|window| window.id == id).cloned() else {
+            nih_debug_assert_failure!("Unknown auxiliary window '{id}'");
+            return;
+        };
+
+        if cfg!(target_os = "macos") {
+            nih_error!("Auxiliary windows are not supported on macOS, not opening '{id}'");
+            return;
+        }
+
+        let open_window = {
+            let mut open_windows = self.open_windows.lock().unwrap();
+            if open_windows.contains_key(id) {
+                return;
+            }
+
+            let open_window = Arc::new(OpenAuxiliaryWindow {
+                close_requested: AtomicBool::new(false),
+                emit_parameters_changed_event: AtomicBool::new(false),
+                param_subscriptions: Default::default(),
+            });
+            open_windows.insert(id, open_window.clone());
+
+            open_window
+        };
+
+        let open_windows = self.open_windows.clone();
+        let context = self.context.clone();
+        let theming = self.theming;
+        let result = std::thread::Builder::new()
+            .name(format!("{id} window"))
+            .spawn(move || {
+                run_window(window, open_window.clone(), context, theming);
+
+                // The window may also have been closed by the user, in which case it is still in
+                // the map. If it has been reopened in the meantime, then that entry must be kept.
+                let mut open_windows = open_windows.lock().unwrap();
+                if open_windows
+                    .get(id)
+                    .map_or(false, |entry| Arc::ptr_eq(entry, &open_window))
+                {
+                    open_windows.remove(id);
+                }
+            });
+
+        if let Err(err) = result {
+            nih_error!("Could not spawn the thread for auxiliary window '{id}': {err}");
+            self.open_windows.lock().unwrap().remove(id);
+        }
+    }
+
+    /// Request the window with the given ID to close. The window closes itself during its next
+    /// idle callback.
+    fn close(&self, id: &'static str) {
+        if let Some(open_window) = self.open_windows.lock().unwrap().remove(id) {
+            open_window.close_requested.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Create and run an auxiliary window's event loop. This blocks until the window is closed.
+fn run_window(
+    window: AuxiliaryWindow,
+    open_window: Arc<OpenAuxiliaryWindow>,
+    context: Arc<dyn GuiContext>,
+    theming: ViziaTheming,
+) {
+    let param_subscriptions = open_window.param_subscriptions.clone();
+    let app = window.app.clone();
+    let mut application = Application::new(move |cx| {
+        apply_theming(cx, theming);
+
+        // Parameter events are handled the same way as in the main window. Resizing the window does
+        // not affect the `ViziaState`, so there's no `WindowModel` here.
+        widgets::ParamModel {
+            context: context.clone(),
+            param_subscriptions: param_subscriptions.clone(),
+        }
+        .build(cx);
+
+        app(cx, context.clone())
+    })
+    .title(&window.title)
+    .with_scale_policy(WindowScalePolicy::SystemScaleFactor)
+    .inner_size(window.size)
+    .on_idle(move |cx| {
+        if open_window.close_requested.load(Ordering::Relaxed) {
+            cx.emit(WindowEvent::WindowClose);
+            return;
+        }
+
+        emit_param_change_events(
+            cx,
+            &open_window.emit_parameters_changed_event,
+            &open_window.param_subscriptions,
+        );
+    });
+
+    if theming == ViziaTheming::None {
+        application = application.ignore_default_theme();
+    }
+
+    application.run();
+}
+
+/// Forward a parameter change to all open auxiliary windows. `id` is `None` when all parameter
+/// values may have changed.
+pub(crate) fn notify_param_changed(open_windows: &OpenAuxiliaryWindows, id: Option<&str>) {
+    for open_window in open_windows.lock().unwrap().values() {
+        open_window
+            .emit_parameters_changed_event
+            .store(true, Ordering::Relaxed);
+        for (_, changes) in open_window.param_subscriptions.lock().unwrap().iter() {
+            if let Some(changes) = changes.upgrade() {
+                match id {
+                    Some(id) => changes.notify(id),
+                    None => changes.notify_all(),
+                };
+            }
+        }
+    }
+}
+
+/// Request all open auxiliary windows to close. Called when the editor's main window closes.
+pub(crate) fn close_all(open_windows: &OpenAuxiliaryWindows) {
+    for (_, open_window) in open_windows.lock().unwrap().drain() {
+        open_window.close_requested.store(true, Ordering::Relaxed);
+    }
+}
@@ -3,12 +3,16 @@
 use baseview::{WindowHandle, WindowScalePolicy};
 use crossbeam::atomic::AtomicCell;
 use nih_plug::debug::*;
-use nih_plug::prelude::{Editor, GuiContext, ParentWindowHandle};
+use nih_plug::prelude::{BufferConfig, Editor, GuiContext, ParentWindowHandle};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use vizia::prelude::*;
 
-use crate::widgets::RawParamEvent;
+use crate::auxiliary_windows::{
+    self, AuxiliaryGuiContext, AuxiliaryWindow, AuxiliaryWindowModel, OpenAuxiliaryWindows,
+    PendingContextCalls,
+};
+use crate::widgets::{ParamChangeEvent, ParamSubscriptions, RawParamEvent};
 use crate::{assets, widgets, ViziaState, ViziaTheming};
 
 /// An [`Editor`] implementation that calls an vizia draw loop.
@@ -29,6 +33,22 @@ pub(crate) struct ViziaEditor {
     /// to compute a property in an event handler. Like when positioning an element based on the
     /// display value's width.
     pub(crate) emit_parameters_changed_event: Arc<AtomicBool>,
+    /// A new buffer config the editor should be notified about during the next idle callback. Set
+    /// in the `buffer_config_changed()` implementation.
+    pub(crate) pending_buffer_config: Arc<AtomicCell<Option<BufferConfig>>>,
+    /// The views that subscribed to coalesced parameter change notifications using
+    /// [`ParamChangeEvent::Subscribe`]. The parameter change callbacks mark the parameters as
+    /// changed, and the idle callback sends the changes to the subscribed views once per frame.
+    pub(crate) param_subscriptions: ParamSubscriptions,
+
+    /// The auxiliary windows that can be opened from the editor.
+    pub(crate) auxiliary_windows: Arc<[AuxiliaryWindow]>,
+    /// The auxiliary windows that are currently open. These receive the same parameter change
+    /// notifications as the main window, and they're closed together with the main window.
+    pub(crate) open_auxiliary_windows: OpenAuxiliaryWindows,
+    /// The parameter and state changes made from auxiliary windows. These are applied from the main
+    /// window's idle callback so the `GuiContext` is only ever used from the main window's thread.
+    pub(crate) pending_auxiliary_context_calls: PendingContextCalls,
 }
 
 impl Editor for ViziaEditor {
@@ -40,28 +60,32 @@ impl Editor for ViziaEditor {
         let app = self.app.clone();
         let vizia_state = self.vizia_state.clone();
         let theming = self.theming;
+        let auxiliary_windows = self.auxiliary_windows.clone();
+        let open_auxiliary_windows = self.open_auxiliary_windows.clone();
+        let auxiliary_context: Arc<dyn GuiContext> = Arc::new(AuxiliaryGuiContext::new(
+            context.clone(),
+            &self.pending_auxiliary_context_calls,
+        ));
+        // The app function takes ownership of the context
+        let idle_context = context.clone();
+        let handle_context = context.clone();
+
+        // The entities from a previous editor instance are no longer valid
+        let param_subscriptions = self.param_subscriptions.clone();
+        param_subscriptions.lock().unwrap().clear();
 
         let (unscaled_width, unscaled_height) = vizia_state.inner_logical_size();
         let system_scaling_factor = self.scaling_factor.load();
         let user_scale_factor = vizia_state.user_scale_factor();
 
         let mut application = Application::new(move |cx| {
-            // Set some default styles to match the iced integration
-            if theming >= ViziaTheming::Custom {
-                cx.set_default_font(&[assets::NOTO_SANS]);
-                if let Err(err) = cx.add_stylesheet(include_style!("assets/theme.css")) {
-                    nih_error!("Failed to load stylesheet: {err:?}")
-                }
-
-                // There doesn't seem to be any way to bundle styles with a widget, so we'll always
-                // include the style sheet for our custom widgets at context creation
-                widgets::register_theme(cx);
-            }
+            apply_theming(cx, theming);
 
             // Any widget can change the parameters by emitting `ParamEvent` events. This model will
             // handle them automatically.
             widgets::ParamModel {
                 context: context.clone(),
+                param_subscriptions: param_subscriptions.clone(),
             }
             .build(cx);
 
@@ -76,6 +100,17 @@ impl Editor for ViziaEditor {
             }
             .build(cx);
 
+            // Auxiliary windows are opened and closed by emitting `AuxiliaryWindowEvent`s
+            if !auxiliary_windows.is_empty() {
+                AuxiliaryWindowModel {
+                    windows: auxiliary_windows.clone(),
+                    open_windows: open_auxiliary_windows.clone(),
+                    context: auxiliary_context.clone(),
+                    theming,
+                }
+                .build(cx);
+            }
+
             app(cx, context.clone())
         })
         .with_scale_policy(
@@ -87,16 +122,20 @@ impl Editor for ViziaEditor {
         .user_scale_factor(user_scale_factor)
         .on_idle({
             let emit_parameters_changed_event = self.emit_parameters_changed_event.clone();
+            let pending_buffer_config = self.pending_buffer_config.clone();
+            let param_subscriptions = self.param_subscriptions.clone();
+            let pending_auxiliary_context_calls = self.pending_auxiliary_context_calls.clone();
             move |cx| {
-                if emit_parameters_changed_event
-                    .compare_exchange(true, false, Ordering::AcqRel, Ordering::Relaxed)
-                    .is_ok()
-                {
+                pending_auxiliary_context_calls.apply(&*idle_context);
+
+                if let Some(buffer_config) = pending_buffer_config.take() {
                     cx.emit_custom(
-                        Event::new(RawParamEvent::ParametersChanged)
+                        Event::new(RawParamEvent::BufferConfigChanged(buffer_config))
                             .propagate(Propagation::Subtree),
                     );
                 }
+
+                emit_param_change_events(cx, &emit_parameters_changed_event, &param_subscriptions);
             }
         });
 
@@ -110,6 +149,9 @@ impl Editor for ViziaEditor {
         self.vizia_state.open.store(true, Ordering::Release);
         Box::new(ViziaEditorHandle {
             vizia_state: self.vizia_state.clone(),
+            open_auxiliary_windows: self.open_auxiliary_windows.clone(),
+            pending_auxiliary_context_calls: self.pending_auxiliary_context_calls.clone(),
+            context: handle_context,
             window,
         })
     }
@@ -133,28 +175,111 @@ impl Editor for ViziaEditor {
         true
     }
 
-    fn param_value_changed(&self, _id: &str, _normalized_value: f32) {
+    fn param_value_changed(&self, id: &str, _normalized_value: f32) {
         // This will cause a future idle callback to send a parameters changed event.
         // NOTE: We could add an event containing the parameter's ID and the normalized value, but
         //       these events aren't really necessary for Vizia.
         self.emit_parameters_changed_event
             .store(true, Ordering::Relaxed);
+        for (_, changes) in self.param_subscriptions.lock().unwrap().iter() {
+            if let Some(changes) = changes.upgrade() {
+                changes.notify(id);
+            }
+        }
+        auxiliary_windows::notify_param_changed(&self.open_auxiliary_windows, Some(id));
     }
 
-    fn param_modulation_changed(&self, _id: &str, _modulation_offset: f32) {
+    fn param_modulation_changed(&self, id: &str, _modulation_offset: f32) {
         self.emit_parameters_changed_event
             .store(true, Ordering::Relaxed);
+        for (_, changes) in self.param_subscriptions.lock().unwrap().iter() {
+            if let Some(changes) = changes.upgrade() {
+                changes.notify(id);
+            }
+        }
+        auxiliary_windows::notify_param_changed(&self.open_auxiliary_windows, Some(id));
     }
 
     fn param_values_changed(&self) {
         self.emit_parameters_changed_event
             .store(true, Ordering::Relaxed);
+        for (_, changes) in self.param_subscriptions.lock().unwrap().iter() {
+            if let Some(changes) = changes.upgrade() {
+                changes.notify_all();
+            }
+        }
+        auxiliary_windows::notify_param_changed(&self.open_auxiliary_windows, None);
+    }
+
+    fn buffer_config_changed(&self, buffer_config: &BufferConfig) {
+        // This is sent as an event during the next idle callback
+        self.pending_buffer_config.store(Some(*buffer_config));
+    }
+}
+
+/// Set up `nih_plug_vizia`'s fonts and styles for a window depending on the theming level. Used for
+/// both the main window and for auxiliary windows.
+pub(crate) fn apply_theming(cx: &mut Context, theming: ViziaTheming) {
+    // Set some default styles to match the iced integration
+    if theming >= ViziaTheming::Custom {
+        cx.set_default_font(&[assets::NOTO_SANS]);
+        if let Err(err) = cx.add_stylesheet(include_style!("assets/theme.css")) {
+            nih_error!("Failed to load stylesheet: {err:?}")
+        }
+
+        // There doesn't seem to be any way to bundle styles with a widget, so we'll always include
+        // the style sheet for our custom widgets at context creation
+        widgets::register_theme(cx);
+    }
+}
+
+/// Send the parameter change events for a window from its idle callback. This emits a
+/// [`RawParamEvent::ParametersChanged`] event if `emit_parameters_changed_event` was set, and a
+/// [`ParamChangeEvent::Changed`] event to every subscribed view with changed parameters.
+pub(crate) fn emit_param_change_events(
+    cx: &mut Context,
+    emit_parameters_changed_event: &AtomicBool,
+    param_subscriptions: &ParamSubscriptions,
+) {
+    if emit_parameters_changed_event
+        .compare_exchange(true, false, Ordering::AcqRel, Ordering::Relaxed)
+        .is_ok()
+    {
+        cx.emit_custom(
+            Event::new(RawParamEvent::ParametersChanged).propagate(Propagation::Subtree),
+        );
+    }
+
+    // Views that have been removed have dropped their subscriptions, so they can be unsubscribed
+    let mut param_subscriptions = param_subscriptions.lock().unwrap();
+    param_subscriptions.retain(|(_, changes)| changes.strong_count() > 0);
+
+    // Changes are coalesced, so each subscribed view gets at most one event per frame
+    for (entity, changes) in param_subscriptions.iter() {
+        let Some(changes) = changes.upgrade() else {
+            continue;
+        };
+        let changed_param_ids = changes.take_changed();
+        if !changed_param_ids.is_empty() {
+            let changed_param_ids = changed_param_ids.into_iter().map(String::from).collect();
+            cx.emit_custom(
+                Event::new(ParamChangeEvent::Changed(changed_param_ids))
+                    .target(*entity)
+                    .propagate(Propagation::Direct),
+            );
+        }
     }
 }
 
 /// The window handle used for [`ViziaEditor`].
 struct ViziaEditorHandle {
     vizia_state: Arc<ViziaState>,
+    /// Auxiliary windows are closed together with the main window.
+    open_auxiliary_windows: OpenAuxiliaryWindows,
+    /// Changes made from the auxiliary windows after the main window's last idle callback are
+    /// applied when the editor closes so gestures are not left open.
+    pending_auxiliary_context_calls: PendingContextCalls,
+    context: Arc<dyn GuiContext>,
     window: WindowHandle,
 }
 
@@ -165,6 +290,8 @@ unsafe impl Send for ViziaEditorHandle {}
 impl Drop for ViziaEditorHandle {
     fn drop(&mut self) {
         self.vizia_state.open.store(false, Ordering::Release);
+        auxiliary_windows::close_all(&self.open_auxiliary_windows);
+        self.pending_auxiliary_context_calls.apply(&*self.context);
         // XXX: This should automatically happen when the handle gets dropped, but apparently not
         self.window.close();
     }
@@ -16,6 +16,7 @@ use vizia::prelude::*;
 pub use vizia;
 
 pub mod assets;
+pub mod auxiliary_windows;
 mod editor;
 pub mod vizia_assets;
 pub mod widgets;
@@ -46,6 +47,22 @@ pub fn create_vizia_editor<F>(
     theming: ViziaTheming,
     app: F,
 ) -> Option<Box<dyn Editor>>
+where
+    F: Fn(&mut Context, Arc<dyn GuiContext>) + 'static + Send + Sync,
+{
+    create_vizia_editor_with_auxiliary_windows(vizia_state, theming, Vec::new(), app)
+}
+
+/// The same as [`create_vizia_editor()`], but with additional windows that can be opened from the
+/// editor by emitting [`AuxiliaryWindowEvent`][auxiliary_windows::AuxiliaryWindowEvent]s. These
+/// windows share the editor's [`GuiContext`] and they're closed together with the editor. See the
+/// [`auxiliary_windows`] module for more information and the current limitations.
+pub fn create_vizia_editor_with_auxiliary_windows<F>(
+    vizia_state: Arc<ViziaState>,
+    theming: ViziaTheming,
+    auxiliary_windows: Vec<auxiliary_windows::AuxiliaryWindow>,
+    app: F,
+) -> Option<Box<dyn Editor>>
 where
     F: Fn(&mut Context, Arc<dyn GuiContext>) + 'static + Send + Sync,
 {
@@ -63,6 +80,12 @@ where
         scaling_factor: AtomicCell::new(Some(1.0)),
 
         emit_parameters_changed_event: Arc::new(AtomicBool::new(false)),
+        pending_buffer_config: Arc::new(AtomicCell::new(None)),
+        param_subscriptions: Default::default(),
+
+        auxiliary_windows: auxiliary_windows.into(),
+        open_auxiliary_windows: Default::default(),
+        pending_auxiliary_context_calls: Default::default(),
     }))
 }
 
@@ -7,24 +7,34 @@
 
 use crossbeam::atomic::AtomicCell;
 use nih_plug::debug::*;
-use nih_plug::prelude::{GuiContext, Param, ParamPtr};
-use std::sync::Arc;
+use nih_plug::prelude::{BufferConfig, GuiContext, Param, ParamChanges, ParamPtr};
+use std::sync::{Arc, Mutex, Weak};
 use vizia::context::TreeProps;
 use vizia::prelude::*;
 
 use super::ViziaState;
 
+mod cpu_meter;
+mod curve_view;
+mod eq_graph;
+mod file_drop_zone;
 mod generic_ui;
 pub mod param_base;
 mod param_button;
+mod param_dropdown;
 mod param_slider;
 mod peak_meter;
 mod resize_handle;
 pub mod util;
 
+pub use cpu_meter::CpuMeter;
+pub use curve_view::{Curve, CurveAxes, CurveView};
+pub use eq_graph::{EqBand, EqGraph, EqGraphExt};
+pub use file_drop_zone::FileDropZone;
 pub use generic_ui::GenericUi;
 pub use param_button::{ParamButton, ParamButtonExt};
-pub use param_slider::{ParamSlider, ParamSliderExt, ParamSliderStyle};
+pub use param_dropdown::{ParamDropdown, ParamIcons};
+pub use param_slider::{ParamSlider, ParamSliderExt, ParamSliderStyle, SliderTick};
 pub use peak_meter::PeakMeter;
 pub use resize_handle::ResizeHandle;
 
@@ -70,8 +80,105 @@ pub enum RawParamEvent {
     /// Sent by the wrapper to indicate that one or more parameter values have changed. Useful when
     /// using properties based on a parameter's value that are computed inside of an event handler.
     ParametersChanged,
+    /// Sent by the wrapper when the plugin has been initialized with a different buffer config
+    /// while the editor is open, for instance after a sample rate change. Views that display data
+    /// computed on the audio thread can use this to rescale or clear that data.
+    BufferConfigChanged(BufferConfig),
 }
 
+/// Coalesced notifications for views that cache data computed from parameter values, like an EQ's
+/// frequency response curve. Instead of reacting to every [`RawParamEvent::ParametersChanged`]
+/// event, a view can subscribe to the parameters it depends on and only recompute its data when one
+/// of those parameters has changed. This includes automation and preset changes from the host.
+///
+/// The view stores a [`ParamSubscription`] in one of its fields. Once the view is removed and the
+/// subscription is dropped together with it, the view no longer receives any notifications.
+///
+/// ```
+/// # use nih_plug_vizia::vizia::prelude::*;
+/// # use nih_plug_vizia::widgets::{ParamChangeEvent, ParamSubscription};
+/// struct Curve {
+///     points: Vec<f32>,
+///     param_subscription: ParamSubscription,
+/// }
+///
+/// impl Curve {
+///     pub fn new(cx: &mut Context) -> Handle<'_, Self> {
+///         let param_subscription = ParamSubscription::new(["freq", "q"]);
+///
+///         Self {
+///             points: Vec::new(),
+///             param_subscription: param_subscription.clone(),
+///         }
+///         .build(cx, |cx| {
+///             // The view's entity is the event's origin, so this must be emitted from inside of
+///             // the view's build function
+///             cx.emit(ParamChangeEvent::Subscribe(param_subscription));
+///         })
+///     }
+/// }
+///
+/// impl View for Curve {
+///     fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+///         event.map(|param_change_event, _| {
+///             if let ParamChangeEvent::Changed(_) = param_change_event {
+///                 // Recompute `self.points` here
+///                 cx.needs_redraw();
+///             }
+///         });
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamChangeEvent {
+    /// Subscribe the view that emits this event to changes to the subscription's parameters,
+    /// replacing its previous subscription. Emit this using `cx.emit()` from the view's build
+    /// function. The view stays subscribed for as long as a clone of the subscription is alive.
+    Subscribe(ParamSubscription),
+    /// Sent directly to a subscribed view during the GUI's idle callback with the sorted IDs of the
+    /// subscribed parameters that have changed since the previous idle callback. A parameter is
+    /// reported at most once per frame no matter how often its value changed. This is also sent
+    /// once right after subscribing so the view can compute its initial data.
+    Changed(Vec<String>),
+}
+
+/// A view's subscription to changes to a set of parameters, registered by emitting
+/// [`ParamChangeEvent::Subscribe`]. The editor only holds on to a weak reference, so this should be
+/// stored in the subscribing view. The view is unsubscribed when the last clone of this object is
+/// dropped, which happens automatically when the view is removed.
+#[derive(Debug, Clone)]
+pub struct ParamSubscription {
+    changes: Arc<ParamChanges>,
+}
+
+impl ParamSubscription {
+    /// Create a subscription to changes to the parameters with these IDs. These are the same IDs
+    /// used in the plugin's [`Params`][nih_plug::prelude::Params] struct.
+    pub fn new<I, S>(param_ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            changes: Arc::new(ParamChanges::new(param_ids)),
+        }
+    }
+}
+
+impl PartialEq for ParamSubscription {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.changes, &other.changes)
+    }
+}
+
+impl Eq for ParamSubscription {}
+
+/// The views that have subscribed to parameter changes using [`ParamChangeEvent::Subscribe`],
+/// along with weak references to their subscriptions. Shared between [`ParamModel`] and the editor
+/// so the editor's parameter change callbacks can mark the parameters as changed. The subscriptions
+/// of views that have since been removed are pruned during the idle callback.
+pub(crate) type ParamSubscriptions = Arc<Mutex<Vec<(Entity, Weak<ParamChanges>)>>>;
+
 /// Events that directly interact with the [`GuiContext`]. Used to trigger resizes.
 pub enum GuiContextEvent {
     /// Resize the window to match the current size reported by the [`ViziaState`]'s size function.
@@ -114,9 +221,12 @@ pub enum GuiContextEvent {
 }
 
 /// Handles parameter updates for VIZIA GUIs. Registered in
-/// [`ViziaEditor::spawn()`][super::ViziaEditor::spawn()].
+/// [`ViziaEditor::spawn()`][super::ViziaEditor::spawn()]. Widgets can use the `context` lens to
+/// query things like the host's parameter indications.
+#[derive(Lens)]
 pub(crate) struct ParamModel {
     pub context: Arc<dyn GuiContext>,
+    pub param_subscriptions: ParamSubscriptions,
 }
 
 /// Handles interactions through `WindowEvent` for VIZIA GUIs by updating the `ViziaState`.
@@ -145,6 +255,18 @@ impl Model for ParamModel {
             RawParamEvent::EndSetParameter(p) => unsafe { self.context.raw_end_set_parameter(p) },
             // This can be used by widgets to be notified when parameter values have changed
             RawParamEvent::ParametersChanged => (),
+            RawParamEvent::BufferConfigChanged(_) => (),
+        });
+
+        event.map(|param_change_event, meta| {
+            if let ParamChangeEvent::Subscribe(param_subscription) = param_change_event {
+                let mut param_subscriptions = self.param_subscriptions.lock().unwrap();
+                param_subscriptions.retain(|(entity, _)| *entity != meta.origin);
+                param_subscriptions
+                    .push((meta.origin, Arc::downgrade(&param_subscription.changes)));
+
+                meta.consume();
+            }
         });
     }
 }
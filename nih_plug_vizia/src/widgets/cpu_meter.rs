@@ -0,0 +1,87 @@
+//! A simple widget showing the plugin's CPU usage.
+
+use nih_plug::prelude::util::ProcessLoad;
+use std::sync::Arc;
+use std::time::Duration;
+use vizia::prelude::*;
+
+/// How often the meter reads the current load from the [`ProcessLoad`].
+const UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Shows the average time spent in the plugin's process function as a percentage of the processed
+/// audio's duration, along with the peak load since the meter was last updated. The load is polled
+/// a couple times per second. This needs NIH-plug's `process_profiling` feature to be enabled, and
+/// it shows `n/a` otherwise.
+///
+/// The meter reads and then resets [`ProcessLoad::max()`], so there should only be a single
+/// `CpuMeter` per editor. The text is drawn by a regular `label` element inside of the
+/// `cpu-meter` element.
+#[derive(Lens)]
+pub struct CpuMeter {
+    process_load: Option<Arc<ProcessLoad>>,
+    /// The formatted load, updated every [`UPDATE_INTERVAL`].
+    text: String,
+}
+
+enum CpuMeterEvent {
+    /// Read the current load and update the text.
+    Update,
+}
+
+impl CpuMeter {
+    /// Creates a new [`CpuMeter`] for a [`ProcessLoad`] obtained from
+    /// [`GuiContext::process_load()`][nih_plug::prelude::GuiContext::process_load()]. Like the
+    /// lens for the [`PeakMeter`][super::PeakMeter], the lens usually points to a field in the
+    /// editor's data model. The lens is only read once when the meter is created.
+    pub fn new<L>(cx: &mut Context, process_load: L) -> Handle<'_, Self>
+    where
+        L: Lens<Target = Option<Arc<ProcessLoad>>>,
+    {
+        let process_load = process_load.get(cx);
+        let text = format_load(process_load.as_deref());
+
+        Self { process_load, text }.build(cx, |cx| {
+            Label::new(cx, CpuMeter::text);
+
+            let timer = cx.add_timer(UPDATE_INTERVAL, None, |cx, action| {
+                if let TimerAction::Tick(_) = action {
+                    cx.emit(CpuMeterEvent::Update);
+                }
+            });
+            cx.start_timer(timer);
+        })
+    }
+}
+
+impl View for CpuMeter {
+    fn element(&self) -> Option<&'static str> {
+        Some("cpu-meter")
+    }
+
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|cpu_meter_event, meta| match cpu_meter_event {
+            CpuMeterEvent::Update => {
+                self.text = format_load(self.process_load.as_deref());
+                meta.consume();
+            }
+        });
+    }
+}
+
+/// Format the average and peak loads as percentages. This resets the peak load so the next update
+/// shows the peak since this one.
+fn format_load(process_load: Option<&ProcessLoad>) -> String {
+    match process_load {
+        Some(process_load) => {
+            let text = format!(
+                "CPU {:.1}% (peak {:.1}%)",
+                process_load.average() * 100.0,
+                process_load.max() * 100.0
+            );
+            process_load.reset_max();
+
+            text
+        }
+        None => String::from("CPU n/a"),
+    }
+}
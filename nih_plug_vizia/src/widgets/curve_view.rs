@@ -0,0 +1,516 @@
+//! A frequency response display for EQs and filters.
+
+use std::cell::RefCell;
+use vizia::prelude::*;
+use vizia::vg;
+
+use super::util::SnapGrid;
+use super::{ParamChangeEvent, ParamSubscription};
+
+/// The number of points a function curve is evaluated at per logical pixel of the view's width.
+const POINTS_PER_PIXEL: f32 = 0.5;
+
+/// A curve drawn by a [`CurveView`]. This is either computed from a function that maps a frequency
+/// in Hertz to a gain in decibels, or it's a fixed set of `(frequency, dB)` points. Function curves
+/// are recomputed when one of the view's subscribed parameters changes.
+pub struct Curve {
+    source: CurveSource,
+    /// The curve's color. Uses the view's font color if not set.
+    color: Option<Color>,
+    /// The curve's stroke width in logical pixels.
+    width: f32,
+}
+
+enum CurveSource {
+    Function(Box<dyn Fn(f32) -> f32>),
+    Points(Vec<(f32, f32)>),
+}
+
+/// The frequency and decibel ranges displayed by a [`CurveView`], along with the grid lines and
+/// their labels. The frequency axis is always logarithmic.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CurveAxes {
+    /// The frequency in Hertz at the left edge of the view.
+    min_frequency: f32,
+    /// The frequency in Hertz at the right edge of the view.
+    max_frequency: f32,
+    /// The gain in decibels at the bottom of the view.
+    min_db: f32,
+    /// The gain in decibels at the top of the view.
+    max_db: f32,
+    /// The frequencies to draw vertical grid lines at.
+    frequency_ticks: Vec<f32>,
+    /// The decibel values to draw horizontal grid lines at.
+    db_ticks: Vec<f32>,
+    /// Whether to label the grid lines.
+    show_labels: bool,
+}
+
+/// Draws one or more frequency response curves on top of a grid with a logarithmic frequency axis
+/// and a linear decibel axis. This is meant for EQ and filter displays, where the curve for every
+/// band is often drawn together with the summed response.
+///
+/// Function curves are evaluated at roughly one point per two logical pixels and the results are
+/// cached, so redrawing the view is cheap. The cached points are only recomputed when the view is
+/// resized or when one of the parameters passed to [`new()`][Self::new()] changes, using
+/// [`ParamChangeEvent`]'s coalesced notifications.
+///
+/// ```ignore
+/// CurveView::new(
+///     cx,
+///     CurveAxes::default().with_db_range(-18.0, 18.0),
+///     ["band1_freq", "band1_gain", "band2_freq", "band2_gain"],
+///     vec![
+///         Curve::from_fn(move |freq| band_response(&params.band1, freq))
+///             .with_color(Color::rgba(255, 255, 255, 96)),
+///         Curve::from_fn(move |freq| band_response(&params.band2, freq))
+///             .with_color(Color::rgba(255, 255, 255, 96)),
+///         Curve::from_fn(move |freq| total_response(&params, freq)).with_width(2.0),
+///     ],
+/// );
+/// ```
+///
+/// The background and border are styled like any other element. Grid lines use the view's outline
+/// color, and the labels can be styled using the `.curve-view__label` class.
+pub struct CurveView {
+    axes: CurveAxes,
+    curves: Vec<Curve>,
+
+    /// The curves' points in view-relative `[0, 1]` coordinates, along with the view's size when
+    /// they were computed. Cleared when a subscribed parameter changes.
+    cached_points: RefCell<Option<((f32, f32), Vec<Vec<(f32, f32)>>)>>,
+    /// Keeps this view subscribed to changes to the parameters the curves depend on until it's
+    /// removed. `None` if there are no such parameters.
+    _param_subscription: Option<ParamSubscription>,
+}
+
+impl Curve {
+    /// A curve computed from a function that takes a frequency in Hertz and returns a gain in
+    /// decibels. The function usually captures the plugin's parameters to compute the response of
+    /// the current filter settings.
+    pub fn from_fn(f: impl Fn(f32) -> f32 + 'static) -> Self {
+        Self {
+            source: CurveSource::Function(Box::new(f)),
+            color: None,
+            width: 1.0,
+        }
+    }
+
+    /// A curve through a fixed set of `(frequency, dB)` points, sorted by frequency. The curve is
+    /// smoothed between the points.
+    pub fn from_points(points: impl IntoIterator<Item = (f32, f32)>) -> Self {
+        Self {
+            source: CurveSource::Points(points.into_iter().collect()),
+            color: None,
+            width: 1.0,
+        }
+    }
+
+    /// Draw the curve with this color instead of the view's font color.
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Set the curve's stroke width in logical pixels. Defaults to one pixel.
+    pub fn with_width(mut self, width: f32) -> Self {
+        self.width = width;
+        self
+    }
+}
+
+impl Default for CurveAxes {
+    /// The audible range from 20 Hz to 20 kHz with a ±24 dB range.
+    fn default() -> Self {
+        Self::new(20.0, 20_000.0, -24.0, 24.0)
+    }
+}
+
+impl CurveAxes {
+    /// Axes for the given frequency range in Hertz and decibel range. This uses grid lines at 1-2-5
+    /// steps for the frequencies and at evenly spaced decibel values.
+    pub fn new(min_frequency: f32, max_frequency: f32, min_db: f32, max_db: f32) -> Self {
+        nih_plug::nih_debug_assert!(min_frequency > 0.0 && max_frequency > min_frequency);
+        nih_plug::nih_debug_assert!(max_db > min_db);
+
+        Self {
+            min_frequency,
+            max_frequency,
+            min_db,
+            max_db,
+            frequency_ticks: default_frequency_ticks(min_frequency, max_frequency),
+            db_ticks: default_db_ticks(min_db, max_db),
+            show_labels: true,
+        }
+    }
+
+    /// Change the displayed decibel range. This also resets the decibel grid lines.
+    pub fn with_db_range(mut self, min_db: f32, max_db: f32) -> Self {
+        nih_plug::nih_debug_assert!(max_db > min_db);
+
+        self.min_db = min_db;
+        self.max_db = max_db;
+        self.db_ticks = default_db_ticks(min_db, max_db);
+        self
+    }
+
+    /// Draw vertical grid lines at these frequencies instead of at the default 1-2-5 steps.
+    pub fn with_frequency_ticks(mut self, ticks: impl IntoIterator<Item = f32>) -> Self {
+        self.frequency_ticks = ticks.into_iter().collect();
+        self
+    }
+
+    /// Draw horizontal grid lines at these decibel values instead of the default ones.
+    pub fn with_db_ticks(mut self, ticks: impl IntoIterator<Item = f32>) -> Self {
+        self.db_ticks = ticks.into_iter().collect();
+        self
+    }
+
+    /// Don't label the grid lines.
+    pub fn without_labels(mut self) -> Self {
+        self.show_labels = false;
+        self
+    }
+
+    /// Map a frequency in Hertz to a `[0, 1]` horizontal position, where 0 is the left edge.
+    pub fn frequency_to_x(&self, frequency: f32) -> f32 {
+        (frequency / self.min_frequency).log2() / (self.max_frequency / self.min_frequency).log2()
+    }
+
+    /// The inverse of [`frequency_to_x()`][Self::frequency_to_x()].
+    pub fn x_to_frequency(&self, x: f32) -> f32 {
+        self.min_frequency * (self.max_frequency / self.min_frequency).powf(x)
+    }
+
+    /// Map a gain in decibels to a `[0, 1]` vertical position, where 0 is the top edge.
+    pub fn db_to_y(&self, db: f32) -> f32 {
+        1.0 - ((db - self.min_db) / (self.max_db - self.min_db))
+    }
+
+    /// A [`SnapGrid`] with vertical lines at every octave around 1 kHz and horizontal lines at the
+    /// decibel grid lines. This can be used with
+    /// [`EqGraphExt::with_snap_grid()`][super::EqGraphExt::with_snap_grid()].
+    pub fn octave_snap_grid(&self) -> SnapGrid {
+        let min_octave = (self.min_frequency / 1000.0).log2().ceil() as i32;
+        let max_octave = (self.max_frequency / 1000.0).log2().floor() as i32;
+
+        SnapGrid::new(
+            (min_octave..=max_octave)
+                .map(|octave| self.frequency_to_x(1000.0 * 2.0f32.powi(octave))),
+            self.db_ticks.iter().map(|&db| self.db_to_y(db)),
+        )
+    }
+
+    /// The inverse of [`db_to_y()`][Self::db_to_y()].
+    pub fn y_to_db(&self, y: f32) -> f32 {
+        self.min_db + ((1.0 - y) * (self.max_db - self.min_db))
+    }
+}
+
+impl CurveView {
+    /// Creates a new [`CurveView`] that draws `curves` using `axes`. Function curves are recomputed
+    /// whenever one of the parameters with the IDs in `param_ids` changes. The curves are drawn in
+    /// order, so the summed response should usually come last.
+    pub fn new<S: Into<String>>(
+        cx: &mut Context,
+        axes: CurveAxes,
+        param_ids: impl IntoIterator<Item = S>,
+        curves: Vec<Curve>,
+    ) -> Handle<'_, Self> {
+        let param_ids: Vec<String> = param_ids.into_iter().map(|id| id.into()).collect();
+        let param_subscription = (!param_ids.is_empty()).then(|| ParamSubscription::new(param_ids));
+        let labels = axes.show_labels.then(|| axes.clone());
+
+        Self {
+            axes,
+            curves,
+
+            cached_points: RefCell::new(None),
+            _param_subscription: param_subscription.clone(),
+        }
+        .build(cx, move |cx| {
+            if let Some(param_subscription) = param_subscription {
+                cx.emit(ParamChangeEvent::Subscribe(param_subscription));
+            }
+
+            if let Some(axes) = labels {
+                for &frequency in &axes.frequency_ticks {
+                    let x = axes.frequency_to_x(frequency);
+                    if (0.0..1.0).contains(&x) {
+                        Label::new(cx, &format_frequency(frequency))
+                            .class("curve-view__label")
+                            .class("curve-view__label--frequency")
+                            .position_type(PositionType::SelfDirected)
+                            .left(Percentage(x * 100.0))
+                            .top(Stretch(1.0))
+                            .hoverable(false);
+                    }
+                }
+
+                for &db in &axes.db_ticks {
+                    let y = axes.db_to_y(db);
+                    if y > 0.0 && y < 1.0 {
+                        Label::new(cx, &format_db(db))
+                            .class("curve-view__label")
+                            .class("curve-view__label--db")
+                            .position_type(PositionType::SelfDirected)
+                            .top(Percentage(y * 100.0))
+                            .hoverable(false);
+                    }
+                }
+            }
+        })
+    }
+
+    /// Compute the points for every curve in `[0, 1]` view coordinates for a view that's `width`
+    /// logical pixels wide.
+    fn compute_points(&self, width: f32) -> Vec<Vec<(f32, f32)>> {
+        let num_points = ((width * POINTS_PER_PIXEL).ceil() as usize).max(2);
+
+        self.curves
+            .iter()
+            .map(|curve| match &curve.source {
+                CurveSource::Function(f) => (0..num_points)
+                    .map(|i| {
+                        let x = i as f32 / (num_points - 1) as f32;
+                        (x, self.axes.db_to_y(f(self.axes.x_to_frequency(x))))
+                    })
+                    .collect(),
+                CurveSource::Points(points) => points
+                    .iter()
+                    .map(|&(frequency, db)| {
+                        (self.axes.frequency_to_x(frequency), self.axes.db_to_y(db))
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+impl View for CurveView {
+    fn element(&self) -> Option<&'static str> {
+        Some("curve-view")
+    }
+
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|param_change_event, _| {
+            if let ParamChangeEvent::Changed(_) = param_change_event {
+                *self.cached_points.get_mut() = None;
+                cx.needs_redraw();
+            }
+        });
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &Canvas) {
+        let bounds = cx.bounds();
+        if bounds.w == 0.0 || bounds.h == 0.0 {
+            return;
+        }
+
+        let opacity = cx.opacity();
+        let border_width = cx.border_width();
+        let paint_for = |color: Color| {
+            let rgba = color.get_rgba();
+            let mut paint = vg::Paint::default();
+            paint.set_color(vg::Color::from_argb(
+                (rgba.alpha as f32 * opacity) as u8,
+                rgba.red,
+                rgba.green,
+                rgba.blue,
+            ));
+            paint.set_anti_alias(true);
+            paint
+        };
+
+        let mut background_path = vg::Path::new();
+        background_path.add_rect(
+            vg::Rect::from_xywh(
+                bounds.x + border_width / 2.0,
+                bounds.y + border_width / 2.0,
+                bounds.w - border_width,
+                bounds.h - border_width,
+            ),
+            None,
+        );
+        let mut background_paint = paint_for(cx.background_color());
+        background_paint.set_style(vg::PaintStyle::Fill);
+        canvas.draw_path(&background_path, &background_paint);
+
+        // Grid lines are snapped to the physical pixel grid so they don't look blurry
+        let dpi_scale = cx.logical_to_physical(1.0).floor().max(1.0);
+        let mut grid_path = vg::Path::new();
+        for &frequency in &self.axes.frequency_ticks {
+            let x = self.axes.frequency_to_x(frequency);
+            if x > 0.0 && x < 1.0 {
+                let x = (bounds.x + bounds.w * x).round() + (dpi_scale / 2.0);
+                grid_path.move_to((x, bounds.top()));
+                grid_path.line_to((x, bounds.bottom()));
+            }
+        }
+        for &db in &self.axes.db_ticks {
+            let y = self.axes.db_to_y(db);
+            if y > 0.0 && y < 1.0 {
+                let y = (bounds.y + bounds.h * y).round() + (dpi_scale / 2.0);
+                grid_path.move_to((bounds.left(), y));
+                grid_path.line_to((bounds.right(), y));
+            }
+        }
+        let mut grid_paint = paint_for(cx.outline_color());
+        grid_paint.set_stroke_width(dpi_scale);
+        grid_paint.set_style(vg::PaintStyle::Stroke);
+        canvas.draw_path(&grid_path, &grid_paint);
+
+        // The points are only recomputed after a parameter change or a resize
+        let logical_size = (
+            cx.physical_to_logical(bounds.w),
+            cx.physical_to_logical(bounds.h),
+        );
+        let mut cached_points = self.cached_points.borrow_mut();
+        if !matches!(&*cached_points, Some((size, _)) if *size == logical_size) {
+            *cached_points = None;
+        }
+        let (_, points) = cached_points
+            .get_or_insert_with(|| (logical_size, self.compute_points(logical_size.0)));
+
+        canvas.save();
+        canvas.clip_rect(
+            vg::Rect::from_xywh(bounds.x, bounds.y, bounds.w, bounds.h),
+            None,
+            Some(true),
+        );
+        for (curve, points) in self.curves.iter().zip(points) {
+            let screen_points: Vec<(f32, f32)> = points
+                .iter()
+                .map(|&(x, y)| (bounds.x + bounds.w * x, bounds.y + bounds.h * y))
+                .collect();
+
+            let mut curve_paint = paint_for(curve.color.unwrap_or_else(|| cx.font_color()));
+            curve_paint.set_stroke_width(cx.logical_to_physical(curve.width));
+            curve_paint.set_style(vg::PaintStyle::Stroke);
+            canvas.draw_path(&smooth_path(&screen_points), &curve_paint);
+        }
+        canvas.restore();
+
+        let mut border_paint = paint_for(cx.border_color());
+        border_paint.set_stroke_width(border_width);
+        border_paint.set_style(vg::PaintStyle::Stroke);
+        canvas.draw_path(&background_path, &border_paint);
+    }
+}
+
+/// Build a smooth path through `points` using Catmull-Rom splines.
+fn smooth_path(points: &[(f32, f32)]) -> vg::Path {
+    let mut path = vg::Path::new();
+    let Some(&first) = points.first() else {
+        return path;
+    };
+
+    path.move_to(first);
+    for i in 0..points.len() - 1 {
+        let p0 = points[i.saturating_sub(1)];
+        let p1 = points[i];
+        let p2 = points[i + 1];
+        let p3 = points[(i + 2).min(points.len() - 1)];
+
+        path.cubic_to(
+            (p1.0 + (p2.0 - p0.0) / 6.0, p1.1 + (p2.1 - p0.1) / 6.0),
+            (p2.0 - (p3.0 - p1.0) / 6.0, p2.1 - (p3.1 - p1.1) / 6.0),
+            p2,
+        );
+    }
+
+    path
+}
+
+/// Grid lines at 1-2-5 steps for every decade between `min_frequency` and `max_frequency`.
+fn default_frequency_ticks(min_frequency: f32, max_frequency: f32) -> Vec<f32> {
+    let mut ticks = Vec::new();
+    let mut decade = 10.0f32.powf(min_frequency.log10().floor());
+    while decade <= max_frequency {
+        for step in [1.0, 2.0, 5.0] {
+            let frequency = decade * step;
+            if frequency > min_frequency && frequency < max_frequency {
+                ticks.push(frequency);
+            }
+        }
+
+        decade *= 10.0;
+    }
+
+    ticks
+}
+
+/// Evenly spaced grid lines, including 0 dB, with about six lines in total.
+fn default_db_ticks(min_db: f32, max_db: f32) -> Vec<f32> {
+    let step = [3.0, 6.0, 12.0, 24.0]
+        .into_iter()
+        .find(|step| (max_db - min_db) / step <= 8.0)
+        .unwrap_or(48.0);
+
+    let first_tick = (min_db / step).floor() as i32;
+    let last_tick = (max_db / step).ceil() as i32;
+    (first_tick..=last_tick)
+        .map(|tick| tick as f32 * step)
+        .filter(|db| *db > min_db && *db < max_db)
+        .collect()
+}
+
+/// Format a grid line's frequency like `200` or `2k`.
+fn format_frequency(frequency: f32) -> String {
+    if frequency >= 1000.0 {
+        format!("{}k", frequency / 1000.0)
+    } else {
+        format!("{frequency}")
+    }
+}
+
+/// Format a grid line's gain like `+6` or `-12`.
+fn format_db(db: f32) -> String {
+    if db > 0.0 {
+        format!("+{db}")
+    } else {
+        format!("{db}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn axis_mapping() {
+        let axes = CurveAxes::default();
+
+        assert_eq!(axes.frequency_to_x(20.0), 0.0);
+        assert!((axes.frequency_to_x(20_000.0) - 1.0).abs() < 1e-6);
+        assert!((axes.x_to_frequency(axes.frequency_to_x(1000.0)) - 1000.0).abs() < 1e-2);
+
+        assert_eq!(axes.db_to_y(24.0), 0.0);
+        assert_eq!(axes.db_to_y(0.0), 0.5);
+        assert_eq!(axes.db_to_y(-24.0), 1.0);
+        assert_eq!(axes.y_to_db(axes.db_to_y(6.0)), 6.0);
+    }
+
+    #[test]
+    fn default_ticks() {
+        assert_eq!(
+            default_frequency_ticks(20.0, 20_000.0),
+            [50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0, 10_000.0]
+        );
+        assert_eq!(
+            default_db_ticks(-24.0, 24.0),
+            [-18.0, -12.0, -6.0, 0.0, 6.0, 12.0, 18.0]
+        );
+        assert_eq!(default_db_ticks(-6.0, 6.0), [-3.0, 0.0, 3.0]);
+    }
+
+    #[test]
+    fn labels() {
+        assert_eq!(format_frequency(50.0), "50");
+        assert_eq!(format_frequency(2000.0), "2k");
+        assert_eq!(format_frequency(2500.0), "2.5k");
+        assert_eq!(format_db(6.0), "+6");
+        assert_eq!(format_db(0.0), "0");
+        assert_eq!(format_db(-12.0), "-12");
+    }
+}
@@ -0,0 +1,510 @@
+//! An interactive EQ display with draggable nodes for every band.
+
+use nih_plug::prelude::Param;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use vizia::prelude::*;
+use vizia::vg;
+
+use super::param_base::ParamWidgetBase;
+use super::util::{self, ModifiersExt, SnapGrid};
+use super::{Curve, CurveAxes, CurveView, RawParamEvent};
+
+/// The radius of a band's node in logical pixels.
+const NODE_RADIUS: f32 = 5.0;
+/// Nodes can be grabbed when the mouse cursor is within this many logical pixels of the node's
+/// center. This is a bit larger than the node itself to make small nodes easier to grab.
+const NODE_HIT_RADIUS: f32 = 9.0;
+
+/// An EQ band's frequency, gain, and Q parameters, controlled by dragging the band's node on an
+/// [`EqGraph`]. The frequency parameter's plain values must be in Hertz and the gain parameter's
+/// plain values must be in decibels, since that's how the node is positioned on the graph.
+pub struct EqBand {
+    frequency: ParamWidgetBase,
+    gain: ParamWidgetBase,
+    q: ParamWidgetBase,
+}
+
+/// A [`CurveView`] with a draggable node for every [`EqBand`]. Dragging a node changes the band's
+/// frequency and gain, and scrolling while hovering over a node changes the band's Q. Shift+scroll
+/// uses finer steps. Double clicking or right clicking a node resets all three parameters to their
+/// default values. Every interaction is wrapped in automation gestures, and all values are clamped
+/// to the parameters' ranges.
+///
+/// Clicking on a node selects its band. When nodes overlap, the selected band's node is always
+/// drawn on top and it wins the hit test, followed by the node closest to the mouse cursor.
+/// Scrolling outside of any node changes the selected band's Q. Clicking on empty space deselects
+/// the band and calls the callback set with [`EqGraphExt::on_add_band()`] with the clicked
+/// frequency and gain, which can be used to enable an unused band at that position.
+///
+/// ```ignore
+/// EqGraph::new(
+///     cx,
+///     CurveAxes::default(),
+///     param_ids,
+///     vec![Curve::from_fn(move |freq| total_response(&params, freq)).with_width(2.0)],
+///     vec![
+///         EqBand::new(cx, Data::params, |p| &p.band1.freq, |p| &p.band1.gain, |p| &p.band1.q),
+///         EqBand::new(cx, Data::params, |p| &p.band2.freq, |p| &p.band2.gain, |p| &p.band2.q),
+///     ],
+/// );
+/// ```
+///
+/// Use [`EqGraphExt::with_snap_grid()`] to snap the nodes to a grid while dragging, for instance to
+/// octaves with [`CurveAxes::octave_snap_grid()`]. Holding Alt while dragging toggles snapping.
+///
+/// The nodes use the `.eq-graph__nodes` element's font color for their outlines and selected
+/// node's fill, and its background color for the fill of the other nodes. The snap grid, if any,
+/// uses its outline color.
+pub struct EqGraph {
+    axes: CurveAxes,
+    bands: Rc<[EqBand]>,
+    /// The index of the selected band in `bands`. Shared with [`EqGraphNodes`].
+    selected_band: Rc<Cell<Option<usize>>>,
+    /// The grid the nodes snap to while dragging, if any. Shared with [`EqGraphNodes`], which draws
+    /// the grid.
+    snap_grid: Rc<RefCell<Option<SnapGrid>>>,
+
+    /// The band whose node is currently being dragged, if any.
+    drag_active: Option<NodeDrag>,
+    /// The number of (fractional) scrolled lines that have not yet been turned into Q parameter
+    /// changes. This is needed to support trackpads with smooth scrolling.
+    scrolled_lines: f32,
+
+    /// Called when the user clicks on empty space, with the clicked frequency and gain.
+    on_add_band: Option<Box<dyn Fn(&mut EventContext, f32, f32)>>,
+}
+
+/// An active node drag.
+#[derive(Debug, Clone, Copy)]
+struct NodeDrag {
+    /// The index of the band being dragged.
+    band_idx: usize,
+    /// The distance between the mouse cursor and the node's center in physical pixels when the
+    /// drag was started. This prevents the node from jumping to the cursor.
+    offset: (f32, f32),
+}
+
+/// Draws the nodes on top of the curve view. This is a separate view because a view's children are
+/// drawn on top of the view itself.
+struct EqGraphNodes {
+    axes: CurveAxes,
+    bands: Rc<[EqBand]>,
+    selected_band: Rc<Cell<Option<usize>>>,
+    snap_grid: Rc<RefCell<Option<SnapGrid>>>,
+}
+
+impl EqBand {
+    /// Bind a band to its frequency, gain, and Q parameters. See
+    /// [`ParamSlider`][super::ParamSlider] for more information on the lens and mapping functions.
+    pub fn new<L, Params, PFreq, PGain, PQ, FMapFreq, FMapGain, FMapQ>(
+        cx: &mut Context,
+        params: L,
+        params_to_frequency: FMapFreq,
+        params_to_gain: FMapGain,
+        params_to_q: FMapQ,
+    ) -> Self
+    where
+        L: Lens<Target = Params> + Clone,
+        Params: 'static,
+        PFreq: Param + 'static,
+        PGain: Param + 'static,
+        PQ: Param + 'static,
+        FMapFreq: Fn(&Params) -> &PFreq + Copy + 'static,
+        FMapGain: Fn(&Params) -> &PGain + Copy + 'static,
+        FMapQ: Fn(&Params) -> &PQ + Copy + 'static,
+    {
+        Self {
+            frequency: ParamWidgetBase::new(cx, params.clone(), params_to_frequency),
+            gain: ParamWidgetBase::new(cx, params.clone(), params_to_gain),
+            q: ParamWidgetBase::new(cx, params, params_to_q),
+        }
+    }
+
+    /// The node's position in `[0, 1]` view coordinates, where `(0, 0)` is the top left corner.
+    /// Positions outside of the axes' ranges are clamped to the view's edges.
+    fn node_position(&self, axes: &CurveAxes) -> (f32, f32) {
+        (
+            axes.frequency_to_x(self.frequency.unmodulated_plain_value())
+                .clamp(0.0, 1.0),
+            axes.db_to_y(self.gain.unmodulated_plain_value())
+                .clamp(0.0, 1.0),
+        )
+    }
+
+    /// Should be called at the start of a drag operation.
+    fn begin_set_parameters(&self, cx: &mut EventContext) {
+        self.gain.begin_set_parameter(cx);
+        self.frequency.begin_set_parameter(cx);
+    }
+
+    /// Move the band to a position in `[0, 1]` view coordinates. The values are clamped to the
+    /// parameters' ranges. `begin_set_parameters()` needs to be called first.
+    fn set_position(&self, cx: &mut EventContext, axes: &CurveAxes, (x, y): (f32, f32)) {
+        let gain_db = axes.y_to_db(y);
+        self.gain
+            .set_normalized_value(cx, self.gain.preview_normalized(gain_db));
+        let frequency = axes.x_to_frequency(x);
+        self.frequency
+            .set_normalized_value(cx, self.frequency.preview_normalized(frequency));
+    }
+
+    /// Should be called at the end of a drag operation.
+    fn end_set_parameters(&self, cx: &mut EventContext) {
+        self.frequency.end_set_parameter(cx);
+        self.gain.end_set_parameter(cx);
+    }
+
+    /// Reset all of the band's parameters to their default values.
+    fn reset_parameters(&self, cx: &mut EventContext) {
+        for param_base in [&self.q, &self.gain, &self.frequency] {
+            param_base.begin_set_parameter(cx);
+            param_base.set_normalized_value(cx, param_base.default_normalized_value());
+            param_base.end_set_parameter(cx);
+        }
+    }
+}
+
+impl EqGraph {
+    /// Creates a new [`EqGraph`]. The first four arguments are the same as for
+    /// [`CurveView::new()`]. The curves are usually computed from the same parameters as the
+    /// `bands`, so those parameters' IDs should also be included in `param_ids`.
+    pub fn new<S: Into<String>>(
+        cx: &mut Context,
+        axes: CurveAxes,
+        param_ids: impl IntoIterator<Item = S>,
+        curves: Vec<Curve>,
+        bands: Vec<EqBand>,
+    ) -> Handle<'_, Self> {
+        let bands: Rc<[EqBand]> = bands.into();
+        let selected_band = Rc::new(Cell::new(None));
+        let snap_grid = Rc::new(RefCell::new(None));
+
+        Self {
+            axes: axes.clone(),
+            bands: bands.clone(),
+            selected_band: selected_band.clone(),
+            snap_grid: snap_grid.clone(),
+
+            drag_active: None,
+            scrolled_lines: 0.0,
+
+            on_add_band: None,
+        }
+        .build(cx, move |cx| {
+            // The graph itself handles the mouse events, so neither child should be hoverable
+            CurveView::new(cx, axes.clone(), param_ids, curves)
+                .position_type(PositionType::SelfDirected)
+                .width(Stretch(1.0))
+                .height(Stretch(1.0))
+                .hoverable(false);
+            EqGraphNodes {
+                axes,
+                bands,
+                selected_band,
+                snap_grid,
+            }
+            .build(cx, |_| ())
+            .class("eq-graph__nodes")
+            .position_type(PositionType::SelfDirected)
+            .width(Stretch(1.0))
+            .height(Stretch(1.0))
+            .hoverable(false);
+        })
+    }
+
+    /// Find the band whose node is under the mouse cursor.
+    fn band_at_cursor(&self, cx: &EventContext) -> Option<usize> {
+        let bounds = cx.cache.get_bounds(cx.current());
+        let nodes: Vec<(f32, f32)> = self
+            .bands
+            .iter()
+            .map(|band| {
+                let (x, y) = band.node_position(&self.axes);
+                (bounds.x + bounds.w * x, bounds.y + bounds.h * y)
+            })
+            .collect();
+
+        hit_test(
+            &nodes,
+            self.selected_band.get(),
+            (cx.mouse().cursor_x, cx.mouse().cursor_y),
+            NODE_HIT_RADIUS * cx.scale_factor(),
+        )
+    }
+
+    /// Convert a physical mouse coordinate to `[0, 1]` view coordinates.
+    fn view_position(cx: &EventContext, (x, y): (f32, f32)) -> (f32, f32) {
+        (
+            util::remap_current_entity_x_coordinate(cx, x),
+            util::remap_current_entity_y_coordinate(cx, y),
+        )
+    }
+
+    /// Change a band's Q parameter based on the scrolled lines.
+    fn scroll_q(&mut self, cx: &mut EventContext, band_idx: usize, scroll_y: f32) {
+        self.scrolled_lines += scroll_y;
+        if self.scrolled_lines.abs() < 1.0 {
+            return;
+        }
+
+        let q = &self.bands[band_idx].q;
+        let use_finer_steps = cx.modifiers().shift();
+        q.begin_set_parameter(cx);
+
+        let mut current_value = q.unmodulated_normalized_value();
+        while self.scrolled_lines >= 1.0 {
+            current_value = q.next_normalized_step(current_value, use_finer_steps);
+            q.set_normalized_value(cx, current_value);
+            self.scrolled_lines -= 1.0;
+        }
+
+        while self.scrolled_lines <= -1.0 {
+            current_value = q.previous_normalized_step(current_value, use_finer_steps);
+            q.set_normalized_value(cx, current_value);
+            self.scrolled_lines += 1.0;
+        }
+
+        q.end_set_parameter(cx);
+    }
+}
+
+impl View for EqGraph {
+    fn element(&self) -> Option<&'static str> {
+        Some("eq-graph")
+    }
+
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|param_event, _| {
+            // The nodes need to be redrawn when the host or another widget changes the parameters
+            if let RawParamEvent::ParametersChanged = param_event {
+                cx.needs_redraw();
+            }
+        });
+
+        event.map(|window_event, meta| match *window_event {
+            WindowEvent::MouseDown(MouseButton::Left) => {
+                match self.band_at_cursor(cx) {
+                    Some(band_idx) if cx.modifiers().command() => {
+                        // Ctrl+Click resets the band, just like with the other parameter widgets
+                        self.selected_band.set(Some(band_idx));
+                        self.bands[band_idx].reset_parameters(cx);
+                    }
+                    Some(band_idx) => {
+                        let bounds = cx.cache.get_bounds(cx.current());
+                        let (x, y) = self.bands[band_idx].node_position(&self.axes);
+                        let offset = (
+                            cx.mouse().cursor_x - (bounds.x + bounds.w * x),
+                            cx.mouse().cursor_y - (bounds.y + bounds.h * y),
+                        );
+
+                        self.selected_band.set(Some(band_idx));
+                        self.drag_active = Some(NodeDrag { band_idx, offset });
+                        cx.capture();
+                        cx.set_active(true);
+
+                        self.bands[band_idx].begin_set_parameters(cx);
+                    }
+                    None => {
+                        self.selected_band.set(None);
+
+                        if let Some(on_add_band) = &self.on_add_band {
+                            let (x, y) =
+                                Self::view_position(cx, (cx.mouse().cursor_x, cx.mouse().cursor_y));
+                            on_add_band(cx, self.axes.x_to_frequency(x), self.axes.y_to_db(y));
+                        }
+                    }
+                }
+
+                cx.needs_redraw();
+                meta.consume();
+            }
+            WindowEvent::MouseDoubleClick(MouseButton::Left)
+            | WindowEvent::MouseDown(MouseButton::Right) => {
+                if let Some(band_idx) = self.band_at_cursor(cx) {
+                    self.selected_band.set(Some(band_idx));
+                    self.bands[band_idx].reset_parameters(cx);
+
+                    cx.needs_redraw();
+                    meta.consume();
+                }
+            }
+            WindowEvent::MouseUp(MouseButton::Left) => {
+                if let Some(NodeDrag { band_idx, .. }) = self.drag_active.take() {
+                    cx.release();
+                    cx.set_active(false);
+
+                    self.bands[band_idx].end_set_parameters(cx);
+
+                    meta.consume();
+                }
+            }
+            WindowEvent::MouseMove(x, y) => {
+                if let Some(NodeDrag { band_idx, offset }) = self.drag_active {
+                    let mut position = Self::view_position(cx, (x - offset.0, y - offset.1));
+                    if let Some(snap_grid) = &*self.snap_grid.borrow() {
+                        if snap_grid.is_active(cx.modifiers()) {
+                            let bounds = cx.cache.get_bounds(cx.current());
+                            let dpi_scale = cx.scale_factor();
+                            position = snap_grid
+                                .snap(position, (bounds.w / dpi_scale, bounds.h / dpi_scale));
+                        }
+                    }
+
+                    self.bands[band_idx].set_position(cx, &self.axes, position);
+
+                    cx.needs_redraw();
+                }
+            }
+            WindowEvent::MouseScroll(_scroll_x, scroll_y) => {
+                let band_idx = match self.drag_active {
+                    Some(NodeDrag { band_idx, .. }) => Some(band_idx),
+                    None => self.band_at_cursor(cx).or(self.selected_band.get()),
+                };
+
+                if let Some(band_idx) = band_idx {
+                    self.scroll_q(cx, band_idx, scroll_y);
+                    meta.consume();
+                }
+            }
+            _ => {}
+        });
+    }
+}
+
+impl View for EqGraphNodes {
+    fn draw(&self, cx: &mut DrawContext, canvas: &Canvas) {
+        let bounds = cx.bounds();
+        if bounds.w == 0.0 || bounds.h == 0.0 {
+            return;
+        }
+
+        let opacity = cx.opacity();
+        let paint_for = |color: Color, style: vg::PaintStyle| {
+            let rgba = color.get_rgba();
+            let mut paint = vg::Paint::default();
+            paint.set_color(vg::Color::from_argb(
+                (rgba.alpha as f32 * opacity) as u8,
+                rgba.red,
+                rgba.green,
+                rgba.blue,
+            ));
+            paint.set_style(style);
+            paint.set_anti_alias(true);
+            paint
+        };
+
+        if let Some(snap_grid) = &*self.snap_grid.borrow() {
+            let dpi_scale = cx.logical_to_physical(1.0).floor().max(1.0);
+            let mut grid_paint = paint_for(cx.outline_color(), vg::PaintStyle::Stroke);
+            grid_paint.set_stroke_width(dpi_scale);
+            snap_grid.draw(canvas, bounds, dpi_scale, &grid_paint);
+        }
+
+        let font_color = cx.font_color();
+        let outline_paint = {
+            let mut paint = paint_for(font_color, vg::PaintStyle::Stroke);
+            paint.set_stroke_width(cx.logical_to_physical(1.0));
+            paint
+        };
+        let fill_paint = paint_for(cx.background_color(), vg::PaintStyle::Fill);
+        let selected_fill_paint = paint_for(font_color, vg::PaintStyle::Fill);
+
+        // The selected node is drawn last so it ends up on top, matching the hit testing
+        let selected_band = self.selected_band.get();
+        let draw_order = (0..self.bands.len())
+            .filter(|idx| Some(*idx) != selected_band)
+            .chain(selected_band);
+        let radius = cx.logical_to_physical(NODE_RADIUS);
+        for band_idx in draw_order {
+            let (x, y) = self.bands[band_idx].node_position(&self.axes);
+            let mut path = vg::Path::new();
+            path.add_circle(
+                (bounds.x + bounds.w * x, bounds.y + bounds.h * y),
+                radius,
+                None,
+            );
+
+            if Some(band_idx) == selected_band {
+                canvas.draw_path(&path, &selected_fill_paint);
+            } else {
+                canvas.draw_path(&path, &fill_paint);
+            }
+            canvas.draw_path(&path, &outline_paint);
+        }
+    }
+}
+
+/// Extension methods for [`EqGraph`] handles.
+pub trait EqGraphExt {
+    /// Called when the user clicks on empty space in the graph, with the clicked frequency in Hertz
+    /// and gain in decibels. This can be used to enable an unused band at that position.
+    fn on_add_band<F>(self, callback: F) -> Self
+    where
+        F: Fn(&mut EventContext, f32, f32) + 'static;
+
+    /// Snap the nodes to this grid while dragging them. Holding Alt while dragging toggles
+    /// snapping. The grid is drawn on top of the curves.
+    fn with_snap_grid(self, snap_grid: SnapGrid) -> Self;
+}
+
+impl EqGraphExt for Handle<'_, EqGraph> {
+    fn on_add_band<F>(self, callback: F) -> Self
+    where
+        F: Fn(&mut EventContext, f32, f32) + 'static,
+    {
+        self.modify(|graph: &mut EqGraph| graph.on_add_band = Some(Box::new(callback)))
+    }
+
+    fn with_snap_grid(self, snap_grid: SnapGrid) -> Self {
+        self.modify(|graph: &mut EqGraph| *graph.snap_grid.borrow_mut() = Some(snap_grid))
+    }
+}
+
+/// Find the node at `position` within `radius`. When multiple nodes overlap, the selected node
+/// wins, followed by the node closest to `position`. For nodes at the same distance, the node that
+/// is drawn last wins.
+fn hit_test(
+    nodes: &[(f32, f32)],
+    selected: Option<usize>,
+    (x, y): (f32, f32),
+    radius: f32,
+) -> Option<usize> {
+    let distance = |idx: usize| {
+        let (node_x, node_y) = nodes[idx];
+        ((node_x - x).powi(2) + (node_y - y).powi(2)).sqrt()
+    };
+
+    if let Some(selected) = selected {
+        if selected < nodes.len() && distance(selected) <= radius {
+            return Some(selected);
+        }
+    }
+
+    (0..nodes.len())
+        .filter(|idx| distance(*idx) <= radius)
+        .fold(None, |closest: Option<usize>, idx| match closest {
+            Some(closest) if distance(closest) < distance(idx) => Some(closest),
+            _ => Some(idx),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hit_testing() {
+        let nodes = [(10.0, 10.0), (14.0, 10.0), (100.0, 50.0)];
+
+        assert_eq!(hit_test(&nodes, None, (50.0, 50.0), 9.0), None);
+        assert_eq!(hit_test(&nodes, None, (100.0, 55.0), 9.0), Some(2));
+        // Overlapping nodes pick the closest node, or the selected node if it's in range
+        assert_eq!(hit_test(&nodes, None, (11.0, 10.0), 9.0), Some(0));
+        assert_eq!(hit_test(&nodes, None, (13.0, 10.0), 9.0), Some(1));
+        assert_eq!(hit_test(&nodes, Some(1), (11.0, 10.0), 9.0), Some(1));
+        assert_eq!(hit_test(&nodes, Some(2), (11.0, 10.0), 9.0), Some(0));
+        // With equal distances the topmost node wins
+        assert_eq!(hit_test(&nodes, None, (12.0, 10.0), 9.0), Some(1));
+    }
+}
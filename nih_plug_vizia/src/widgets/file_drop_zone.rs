@@ -0,0 +1,143 @@
+//! A drop target for files dragged onto the plugin's GUI.
+
+use std::path::{Path, PathBuf};
+use vizia::prelude::*;
+
+/// A container that accepts files dragged onto it from the file manager or the host's browser.
+/// This is useful for sample players and convolution plugins that load files from disk. Every
+/// dropped file with a supported extension is passed to the `on_drop` callback, which would usually
+/// kick off a [`BackgroundTask`][nih_plug::prelude::Plugin::BackgroundTask] to load the file. When
+/// multiple files are dropped at once, the callback is called once for every supported file.
+///
+/// While a file is being dragged over the drop zone it gets the `drag-over` class, which is removed
+/// again when the file is dropped or when the drag leaves the drop zone. After a drop, the drop
+/// zone gets either the `file-drop-zone--accepted` or the `file-drop-zone--rejected` class
+/// depending on whether any of the files were supported. That class is removed again when the
+/// cursor leaves the drop zone.
+///
+/// ```ignore
+/// FileDropZone::new(
+///     cx,
+///     ["wav", "flac"],
+///     |cx, path| cx.emit(AppEvent::LoadSample(path)),
+///     |cx| {
+///         Label::new(cx, "Drop a sample here");
+///     },
+/// );
+/// ```
+///
+/// Dropping files depends on support from vizia's baseview backend for the current platform. If
+/// the platform does not support it, then the drop zone acts as a regular container.
+pub struct FileDropZone {
+    /// The lowercase file extensions, without a leading period, this drop zone accepts. If this is
+    /// empty, then all files are accepted.
+    extensions: Vec<String>,
+    /// Called for every supported file that's dropped on the drop zone.
+    on_drop: Box<dyn Fn(&mut EventContext, PathBuf)>,
+
+    /// Whether we're currently receiving the files for a single drop. Dropping multiple files
+    /// results in one event per file without any cursor movement in between.
+    receiving_drop: bool,
+    /// Whether any of the files from the current or last drop was supported.
+    drop_accepted: bool,
+}
+
+impl FileDropZone {
+    /// Create a drop zone containing `content` that accepts files with one of the `extensions`.
+    /// These are matched case insensitively and should not contain a leading period. Pass an empty
+    /// list to accept all files.
+    pub fn new<S, F>(
+        cx: &mut Context,
+        extensions: impl IntoIterator<Item = S>,
+        on_drop: impl Fn(&mut EventContext, PathBuf) + 'static,
+        content: F,
+    ) -> Handle<'_, Self>
+    where
+        S: AsRef<str>,
+        F: FnOnce(&mut Context),
+    {
+        Self {
+            extensions: extensions
+                .into_iter()
+                .map(|extension| extension.as_ref().trim_start_matches('.').to_lowercase())
+                .collect(),
+            on_drop: Box::new(on_drop),
+
+            receiving_drop: false,
+            drop_accepted: false,
+        }
+        .build(cx, content)
+    }
+}
+
+impl View for FileDropZone {
+    fn element(&self) -> Option<&'static str> {
+        Some("file-drop-zone")
+    }
+
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|window_event, meta| match window_event {
+            WindowEvent::Drop(DropData::File(path)) => {
+                if !self.receiving_drop {
+                    self.receiving_drop = true;
+                    self.drop_accepted = false;
+                }
+
+                if is_supported(path, &self.extensions) {
+                    self.drop_accepted = true;
+                    (self.on_drop)(cx, path.clone());
+                }
+
+                // With multiple files the drop counts as accepted if any of the files was supported
+                cx.toggle_class("drag-over", false);
+                cx.toggle_class("file-drop-zone--accepted", self.drop_accepted);
+                cx.toggle_class("file-drop-zone--rejected", !self.drop_accepted);
+
+                meta.consume();
+            }
+            // Dragging a file over the window moves the cursor like usual, but the dragged file is
+            // also stored as drop data until it's dropped or the drag leaves the window
+            WindowEvent::MouseEnter | WindowEvent::MouseMove(_, _) => {
+                self.receiving_drop = false;
+                cx.toggle_class("drag-over", cx.has_drop_data());
+            }
+            WindowEvent::MouseLeave => {
+                cx.toggle_class("drag-over", false);
+                cx.toggle_class("file-drop-zone--accepted", false);
+                cx.toggle_class("file-drop-zone--rejected", false);
+            }
+            _ => {}
+        });
+    }
+}
+
+/// Whether `path` has one of `extensions`, ignoring case. An empty list of extensions accepts all
+/// files.
+fn is_supported(path: &Path, extensions: &[String]) -> bool {
+    if extensions.is_empty() {
+        return true;
+    }
+
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => extensions
+            .iter()
+            .any(|supported| extension.eq_ignore_ascii_case(supported)),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn supported_extensions() {
+        let extensions = vec![String::from("wav"), String::from("flac")];
+
+        assert!(is_supported(Path::new("/samples/kick.wav"), &extensions));
+        assert!(is_supported(Path::new("C:\\IRs\\Hall.FLAC"), &extensions));
+        assert!(!is_supported(Path::new("/samples/kick.mp3"), &extensions));
+        assert!(!is_supported(Path::new("/samples/wav"), &extensions));
+        assert!(is_supported(Path::new("/samples/kick"), &[]));
+    }
+}
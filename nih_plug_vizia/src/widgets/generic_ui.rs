@@ -1,9 +1,12 @@
 //! Generic UIs for NIH-plug using VIZIA.
 
-use nih_plug::prelude::{ParamFlags, ParamPtr, Params};
+use nih_plug::debug::*;
+use nih_plug::prelude::{ParamFlags, ParamPtr, ParamVisibility, Params};
+use std::collections::HashMap;
+use std::sync::Arc;
 use vizia::prelude::*;
 
-use super::{ParamSlider, ParamSliderExt, ParamSliderStyle};
+use super::{ParamDropdown, ParamIcons, ParamSlider, ParamSliderExt, ParamSliderStyle};
 
 /// Shows a generic UI for a [`Params`] object. For additional flexibility you can either use the
 /// [`new()`][`Self::new()`] method to have the generic UI decide which widget to use for your
@@ -41,6 +44,116 @@ impl GenericUi {
         })
     }
 
+    /// The same as [`new()`][Self::new()], but the parameters in `icons` are shown as a
+    /// [`ParamDropdown`] with an icon for every value instead of as a slider. `icons` maps
+    /// parameter IDs to the icons for those parameters' values. Parameters without icons use the
+    /// standard widgets.
+    ///
+    /// ```ignore
+    /// GenericUi::new_with_icons(
+    ///     cx,
+    ///     Data::params,
+    ///     [(
+    ///         String::from("waveform"),
+    ///         ParamIcons::for_enum(TABLER_ICONS, waveform_icon),
+    ///     )],
+    /// );
+    /// ```
+    pub fn new_with_icons<L, PsRef, Ps>(
+        cx: &mut Context,
+        params: L,
+        icons: impl IntoIterator<Item = (String, ParamIcons)>,
+    ) -> Handle<'_, GenericUi>
+    where
+        L: Lens<Target = PsRef> + Clone,
+        PsRef: AsRef<Ps> + 'static,
+        Ps: Params + 'static,
+    {
+        // The icons are supplied by parameter ID, but the widget function only receives the
+        // parameter's pointer
+        let mut icons: HashMap<String, ParamIcons> = icons.into_iter().collect();
+        let param_map = params.map(|params| params.as_ref().param_map()).get(cx);
+        let icons_by_ptr: HashMap<ParamPtr, ParamIcons> = param_map
+            .into_iter()
+            .filter_map(|(param_id, param_ptr, _)| Some((param_ptr, icons.remove(&param_id)?)))
+            .collect();
+        nih_debug_assert!(
+            icons.is_empty(),
+            "Icons were supplied for unknown parameters: {:?}",
+            icons.keys()
+        );
+
+        Self::new_custom(cx, params, move |cx, param_ptr| {
+            HStack::new(cx, |cx| {
+                Label::new(cx, unsafe { param_ptr.name() }).class("label");
+
+                match icons_by_ptr.get(&param_ptr) {
+                    Some(param_icons) => {
+                        Self::draw_dropdown(cx, params, param_ptr, param_icons.clone())
+                    }
+                    None => Self::draw_widget(cx, params, param_ptr),
+                }
+            })
+            .class("row");
+        })
+    }
+
+    /// The same as [`new()`][Self::new()], but `visibility` decides whether each parameter is
+    /// shown, greyed out, or hidden. It receives the parameters object and a parameter's ID, and it
+    /// is called on the GUI thread again whenever a parameter value changes so parameters can
+    /// depend on other parameters. This only affects the generic UI, see [`ParamVisibility`].
+    ///
+    /// ```ignore
+    /// GenericUi::new_with_visibility(cx, Data::params, |params: &MyParams, param_id| {
+    ///     match param_id {
+    ///         "sc_freq" if !params.sidechain.value() => ParamVisibility::Hidden,
+    ///         _ => ParamVisibility::Visible,
+    ///     }
+    /// });
+    /// ```
+    pub fn new_with_visibility<L, PsRef, Ps>(
+        cx: &mut Context,
+        params: L,
+        visibility: impl Fn(&Ps, &str) -> ParamVisibility + 'static,
+    ) -> Handle<'_, GenericUi>
+    where
+        L: Lens<Target = PsRef> + Clone,
+        PsRef: AsRef<Ps> + 'static,
+        Ps: Params + 'static,
+    {
+        // Like with the icons, the predicate needs the parameter's ID
+        let param_map = params.map(|params| params.as_ref().param_map()).get(cx);
+        let param_ids: HashMap<ParamPtr, String> = param_map
+            .into_iter()
+            .map(|(param_id, param_ptr, _)| (param_ptr, param_id))
+            .collect();
+        let visibility = Arc::new(visibility);
+
+        Self::new_custom(cx, params.clone(), move |cx, param_ptr| {
+            let param_visibility = {
+                let param_id = param_ids[&param_ptr].clone();
+                let visibility = visibility.clone();
+
+                params
+                    .clone()
+                    .map(move |params| visibility(params.as_ref(), &param_id))
+            };
+
+            HStack::new(cx, |cx| {
+                Label::new(cx, unsafe { param_ptr.name() }).class("label");
+
+                Self::draw_widget(cx, params.clone(), param_ptr);
+            })
+            .class("row")
+            .display(
+                param_visibility
+                    .clone()
+                    .map(|visibility| *visibility != ParamVisibility::Hidden),
+            )
+            .disabled(param_visibility.map(|visibility| *visibility == ParamVisibility::Disabled));
+        })
+    }
+
     /// Creates a new [`GenericUi`] for all provided parameters using a custom closure that receives
     /// a function that should draw some widget for each parameter.
     pub fn new_custom<L, PsRef, Ps>(
@@ -99,6 +212,40 @@ impl GenericUi {
         })
         .class("widget");
     }
+
+    /// Draw a [`ParamDropdown`] with icons for a stepped parameter. This can be used together with
+    /// `.new_custom()` to show icons for some parameters.
+    pub fn draw_dropdown<L, PsRef, Ps>(
+        cx: &mut Context,
+        params: L,
+        param_ptr: ParamPtr,
+        icons: ParamIcons,
+    ) where
+        L: Lens<Target = PsRef> + Clone,
+        PsRef: AsRef<Ps> + 'static,
+        Ps: Params + 'static,
+    {
+        nih_debug_assert!(
+            unsafe { param_ptr.step_count() }.is_some(),
+            "Dropdowns only work with stepped parameters"
+        );
+
+        unsafe {
+            match param_ptr {
+                ParamPtr::FloatParam(p) => {
+                    ParamDropdown::with_icons(cx, params, move |_| &*p, icons)
+                }
+                ParamPtr::IntParam(p) => ParamDropdown::with_icons(cx, params, move |_| &*p, icons),
+                ParamPtr::BoolParam(p) => {
+                    ParamDropdown::with_icons(cx, params, move |_| &*p, icons)
+                }
+                ParamPtr::EnumParam(p) => {
+                    ParamDropdown::with_icons(cx, params, move |_| &*p, icons)
+                }
+            }
+        }
+        .class("widget");
+    }
 }
 
 impl View for GenericUi {
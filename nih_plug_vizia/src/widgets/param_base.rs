@@ -243,5 +243,7 @@ impl ParamWidgetBase {
     param_ptr_forward!(pub fn string_to_normalized_value(&self, string: &str) -> Option<f32>);
     param_ptr_forward!(pub fn preview_normalized(&self, plain: f32) -> f32);
     param_ptr_forward!(pub fn preview_plain(&self, normalized: f32) -> f32);
+    param_ptr_forward!(pub fn plain_value_to_string(&self, plain: f32, include_unit: bool) -> String);
+    param_ptr_forward!(pub fn string_to_plain_value(&self, string: &str) -> Option<f32>);
     param_ptr_forward!(pub fn flags(&self) -> ParamFlags);
 }
@@ -0,0 +1,251 @@
+//! A dropdown for selecting a stepped parameter's value, optionally with an icon for every value.
+
+use nih_plug::prelude::{Enum, Param};
+use vizia::prelude::*;
+
+use super::param_base::ParamWidgetBase;
+
+/// Icons for a stepped parameter's values, shown in front of the values' labels in a
+/// [`ParamDropdown`]. Every icon is a string that's drawn using an icon font, usually a single
+/// glyph. That font needs to be registered with vizia before it can be used, see
+/// [`vizia_assets`][crate::vizia_assets]. Values without an icon only show their label.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParamIcons {
+    /// The name of the icon font's font family.
+    font_family: String,
+    /// The icon for every step, starting at the parameter's minimum value.
+    icons: Vec<Option<String>>,
+}
+
+/// A dropdown for selecting a stepped parameter's value. Clicking on the widget shows a list of all
+/// of the parameter's values, and clicking on one of those values selects it. This is mostly meant
+/// for [`EnumParam`][nih_plug::prelude::EnumParam]s like filter type or waveform selectors, but it
+/// also works for other stepped parameters. Use [`with_icons()`][Self::with_icons()] to show an
+/// icon next to every value.
+#[derive(Lens)]
+pub struct ParamDropdown {
+    param_base: ParamWidgetBase,
+
+    /// Whether the list of values is currently shown.
+    open: bool,
+}
+
+enum ParamDropdownEvent {
+    /// Show or hide the list of values.
+    Toggle,
+    /// Set the parameter to the value for this step and close the list.
+    Select(usize),
+}
+
+impl ParamIcons {
+    /// Icons for a stepped parameter's values, starting at the parameter's minimum value. `None`
+    /// entries, or entries past the end of `icons`, don't get an icon.
+    pub fn new<S: Into<String>>(
+        font_family: impl Into<String>,
+        icons: impl IntoIterator<Item = Option<S>>,
+    ) -> Self {
+        Self {
+            font_family: font_family.into(),
+            icons: icons
+                .into_iter()
+                .map(|icon| icon.map(|icon| icon.into()))
+                .collect(),
+        }
+    }
+
+    /// Icons for an [`EnumParam`][nih_plug::prelude::EnumParam]'s variants. `icon` is called for
+    /// every variant, and it may return `None` to not show an icon for that variant.
+    ///
+    /// ```
+    /// # use nih_plug::prelude::Enum;
+    /// # use nih_plug_vizia::vizia_assets::TABLER_ICONS;
+    /// # use nih_plug_vizia::widgets::ParamIcons;
+    /// #[derive(Enum, PartialEq)]
+    /// enum Waveform {
+    ///     Sine,
+    ///     Square,
+    ///     Noise,
+    /// }
+    ///
+    /// let icons = ParamIcons::for_enum(TABLER_ICONS, |waveform| match waveform {
+    ///     Waveform::Sine => Some("\u{eb95}"),
+    ///     Waveform::Square => Some("\u{eb96}"),
+    ///     Waveform::Noise => None,
+    /// });
+    /// assert_eq!(icons.icon(1), Some("\u{eb96}"));
+    /// assert_eq!(icons.icon(2), None);
+    /// ```
+    pub fn for_enum<T: Enum>(
+        font_family: impl Into<String>,
+        icon: impl Fn(T) -> Option<&'static str>,
+    ) -> Self {
+        Self::new(
+            font_family,
+            (0..T::variants().len()).map(|idx| icon(T::from_index(idx))),
+        )
+    }
+
+    /// The name of the icon font's font family.
+    pub fn font_family(&self) -> &str {
+        &self.font_family
+    }
+
+    /// The icon for a step, starting at zero for the parameter's minimum value.
+    pub fn icon(&self, step: usize) -> Option<&str> {
+        self.icons.get(step)?.as_deref()
+    }
+}
+
+impl ParamDropdown {
+    /// Creates a new [`ParamDropdown`] for the given parameter. See
+    /// [`ParamSlider`][super::ParamSlider] for more information on this function's arguments.
+    pub fn new<L, Params, P, FMap>(
+        cx: &mut Context,
+        params: L,
+        params_to_param: FMap,
+    ) -> Handle<'_, Self>
+    where
+        L: Lens<Target = Params> + Clone,
+        Params: 'static,
+        P: Param + 'static,
+        FMap: Fn(&Params) -> &P + Copy + 'static,
+    {
+        Self::build_dropdown(cx, params, params_to_param, None)
+    }
+
+    /// The same as [`new()`][Self::new()], but with an icon in front of every value's label.
+    pub fn with_icons<L, Params, P, FMap>(
+        cx: &mut Context,
+        params: L,
+        params_to_param: FMap,
+        icons: ParamIcons,
+    ) -> Handle<'_, Self>
+    where
+        L: Lens<Target = Params> + Clone,
+        Params: 'static,
+        P: Param + 'static,
+        FMap: Fn(&Params) -> &P + Copy + 'static,
+    {
+        Self::build_dropdown(cx, params, params_to_param, Some(icons))
+    }
+
+    fn build_dropdown<L, Params, P, FMap>(
+        cx: &mut Context,
+        params: L,
+        params_to_param: FMap,
+        icons: Option<ParamIcons>,
+    ) -> Handle<'_, Self>
+    where
+        L: Lens<Target = Params> + Clone,
+        Params: 'static,
+        P: Param + 'static,
+        FMap: Fn(&Params) -> &P + Copy + 'static,
+    {
+        Self {
+            param_base: ParamWidgetBase::new(cx, params.clone(), params_to_param),
+
+            open: false,
+        }
+        .build(
+            cx,
+            ParamWidgetBase::build_view(params, params_to_param, move |cx, param_data| {
+                let num_steps = param_data.param().step_count().unwrap_or(0);
+                let labels: Vec<String> = (0..=num_steps)
+                    .map(|step| {
+                        param_data
+                            .param()
+                            .normalized_value_to_string(step_to_normalized(step, num_steps), true)
+                    })
+                    .collect();
+
+                let current_step = param_data.make_lens(move |param| {
+                    normalized_to_step(param.unmodulated_normalized_value(), num_steps)
+                });
+                Binding::new(cx, current_step, {
+                    let labels = labels.clone();
+                    let icons = icons.clone();
+                    move |cx, step| {
+                        let step = step.get(cx);
+                        build_entry(cx, icons.as_ref(), step, &labels[step])
+                            .class("current")
+                            .on_press(|cx| cx.emit(ParamDropdownEvent::Toggle));
+                    }
+                });
+
+                Binding::new(cx, Self::open, move |cx, open| {
+                    if open.get(cx) {
+                        VStack::new(cx, |cx| {
+                            for (step, label) in labels.iter().enumerate() {
+                                build_entry(cx, icons.as_ref(), step, label)
+                                    .class("entry")
+                                    .on_press(move |cx| cx.emit(ParamDropdownEvent::Select(step)));
+                            }
+                        })
+                        .class("popup")
+                        .position_type(PositionType::SelfDirected)
+                        .top(Percentage(100.0));
+                    }
+                });
+            }),
+        )
+    }
+}
+
+impl View for ParamDropdown {
+    fn element(&self) -> Option<&'static str> {
+        Some("param-dropdown")
+    }
+
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|dropdown_event, meta| match *dropdown_event {
+            ParamDropdownEvent::Toggle => {
+                self.open = !self.open;
+                meta.consume();
+            }
+            ParamDropdownEvent::Select(step) => {
+                let num_steps = self.param_base.step_count().unwrap_or(0);
+
+                self.param_base.begin_set_parameter(cx);
+                self.param_base
+                    .set_normalized_value(cx, step_to_normalized(step, num_steps));
+                self.param_base.end_set_parameter(cx);
+
+                self.open = false;
+                meta.consume();
+            }
+        });
+    }
+}
+
+/// Build a row containing a value's icon, if it has one, and its label.
+fn build_entry<'a>(
+    cx: &'a mut Context,
+    icons: Option<&ParamIcons>,
+    step: usize,
+    label: &str,
+) -> Handle<'a, HStack> {
+    HStack::new(cx, |cx| {
+        if let Some(icons) = icons {
+            if let Some(icon) = icons.icon(step) {
+                Label::new(cx, icon)
+                    .font_family(vec![FamilyOwned::Name(String::from(icons.font_family()))])
+                    .class("icon")
+                    .hoverable(false);
+            }
+        }
+
+        Label::new(cx, label).class("label").hoverable(false);
+    })
+}
+
+fn step_to_normalized(step: usize, num_steps: usize) -> f32 {
+    if num_steps == 0 {
+        0.0
+    } else {
+        step as f32 / num_steps as f32
+    }
+}
+
+fn normalized_to_step(normalized: f32, num_steps: usize) -> usize {
+    ((normalized * num_steps as f32).round() as usize).min(num_steps)
+}
@@ -1,10 +1,11 @@
 //! A slider that integrates with NIH-plug's [`Param`] types.
 
-use nih_plug::prelude::Param;
+use nih_plug::prelude::{AutomationIndication, IndicationColor, Param, ParamIndication};
 use vizia::prelude::*;
 
 use super::param_base::ParamWidgetBase;
 use super::util::{self, ModifiersExt};
+use super::ParamModel;
 
 /// When shift+dragging a parameter, one pixel dragged corresponds to this much change in the
 /// normalized parameter.
@@ -12,6 +13,16 @@ const GRANULAR_DRAG_MULTIPLIER: f32 = 0.1;
 
 /// A slider that integrates with NIH-plug's [`Param`] types. Use the
 /// [`set_style()`][ParamSliderExt::set_style()] method to change how the value gets displayed.
+///
+/// If the host indicates that the parameter is automated or mapped to a controller (this only works
+/// for CLAP plugins with hosts that support the `param-indication` extension), then a small
+/// indicator is drawn on the slider's left edge using the host's color for that indication.
+///
+/// Tick marks can be added using [`with_ticks()`][ParamSliderExt::with_ticks()],
+/// [`with_value_ticks()`][ParamSliderExt::with_value_ticks()], or
+/// [`with_decibel_ticks()`][ParamSliderExt::with_decibel_ticks()]. These are purely visual. Ticks
+/// have the `ticks__tick` class, ticks with a label also have the `ticks__tick--major` class, and
+/// their labels have the `ticks__label` class.
 #[derive(Lens)]
 pub struct ParamSlider {
     param_base: ParamWidgetBase,
@@ -37,6 +48,17 @@ pub struct ParamSlider {
     style: ParamSliderStyle,
     /// A specific label to use instead of displaying the parameter's value.
     label_override: Option<String>,
+    /// Tick marks drawn on top of the slider's fill.
+    ticks: Vec<SliderTick>,
+}
+
+/// A tick mark drawn on a [`ParamSlider`]. Set these using [`ParamSliderExt::with_ticks()`].
+#[derive(Debug, Clone, PartialEq, Data)]
+pub struct SliderTick {
+    /// The tick's position on the slider as a normalized parameter value.
+    pub normalized_value: f32,
+    /// An optional label for the tick. Ticks with a label are drawn as major ticks.
+    pub label: Option<String>,
 }
 
 /// How the [`ParamSlider`] should display its values. Set this using
@@ -112,6 +134,7 @@ impl ParamSlider {
             scrolled_lines: 0.0,
             style: ParamSliderStyle::Centered,
             label_override: None,
+            ticks: Vec::new(),
         }
         .build(
             cx,
@@ -146,6 +169,12 @@ impl ParamSlider {
                         Self::compute_modulation_fill_start_delta(style, param)
                     });
 
+                    // The host's automation and mapping indication for this parameter. This is
+                    // updated whenever the wrapper sends a parameters changed event.
+                    let param_ptr = param_data.param().as_ptr();
+                    let indication_lens =
+                        ParamModel::context.map(move |context| context.param_indication(param_ptr));
+
                     // This is used to draw labels for `CurrentStepLabeled`
                     let make_preview_value_lens = move |normalized_value| {
                         param_data.make_lens(move |param| {
@@ -170,6 +199,8 @@ impl ParamSlider {
                                         fill_start_delta_lens,
                                         modulation_start_delta_lens,
                                     );
+                                    Self::ticks_view(cx, ParamSlider::ticks);
+                                    Self::indication_view(cx, indication_lens);
                                     Self::slider_label_view(
                                         cx,
                                         param_data.param(),
@@ -252,6 +283,59 @@ impl ParamSlider {
             .hoverable(false);
     }
 
+    /// Create the tick marks and their labels.
+    fn ticks_view(cx: &mut Context, ticks_lens: impl Lens<Target = Vec<SliderTick>>) {
+        Binding::new(cx, ticks_lens, |cx, ticks_lens| {
+            for tick in ticks_lens.get(cx) {
+                let left = Percentage(tick.normalized_value.clamp(0.0, 1.0) * 100.0);
+
+                Element::new(cx)
+                    .class("ticks__tick")
+                    .toggle_class("ticks__tick--major", tick.label.is_some())
+                    .left(left)
+                    .hoverable(false);
+                if let Some(label) = &tick.label {
+                    Label::new(cx, label)
+                        .class("ticks__label")
+                        .overflow(Overflow::Visible)
+                        .left(left)
+                        .hoverable(false);
+                }
+            }
+        });
+    }
+
+    /// Create the automation and mapping indicator. This is only visible when the host indicates
+    /// something for the parameter.
+    fn indication_view(cx: &mut Context, indication_lens: impl Lens<Target = ParamIndication>) {
+        Element::new(cx)
+            .class("indicator")
+            .height(Stretch(1.0))
+            .visibility(indication_lens.map(|indication| !indication.is_none()))
+            .background_color(indication_lens.map(Self::indication_color))
+            .hoverable(false);
+    }
+
+    /// The color used for the indicator. The host's automation color takes precedence over its
+    /// mapping color, and if the host did not specify a color then a default color for the
+    /// indication is used.
+    fn indication_color(indication: &ParamIndication) -> Color {
+        let to_color =
+            |color: IndicationColor| Color::rgba(color.red, color.green, color.blue, color.alpha);
+
+        match (indication.automation, indication.automation_color) {
+            (AutomationIndication::None, _) => indication
+                .mapping_color
+                .map(to_color)
+                .unwrap_or_else(|| Color::rgb(0x42, 0xa5, 0xf5)),
+            (_, Some(color)) => to_color(color),
+            (AutomationIndication::Present, None) => Color::rgb(0xc4, 0xc4, 0xc4),
+            (AutomationIndication::Playing, None) => Color::rgb(0x4c, 0xaf, 0x50),
+            (AutomationIndication::Recording, None) => Color::rgb(0xe5, 0x39, 0x35),
+            (AutomationIndication::Overriding, None) => Color::rgb(0xff, 0x98, 0x00),
+        }
+    }
+
     /// Create the text part of the slider. Shown on top of the fill using a `ZStack`.
     fn slider_label_view<P: Param, L: Lens<Target = String>>(
         cx: &mut Context,
@@ -417,6 +501,15 @@ impl ParamSlider {
 
         self.param_base.set_normalized_value(cx, normalized_value);
     }
+
+    /// End the current drag operation and its parameter automation gesture. This does not release
+    /// the mouse capture since this is also used when the capture has already been lost.
+    fn end_drag(&mut self, cx: &mut EventContext) {
+        self.drag_active = false;
+        cx.set_active(false);
+
+        self.param_base.end_set_parameter(cx);
+    }
 }
 
 impl View for ParamSlider {
@@ -467,6 +560,12 @@ impl View for ParamSlider {
                     // The `!self.text_input_active` check shouldn't be needed, but the textbox does
                     // not consume the mouse down event. So clicking on the textbox to move the
                     // cursor would also change the slider.
+                    if self.drag_active {
+                        // We missed the end of the last drag somehow, so that gesture needs to be
+                        // ended before starting a new one
+                        self.end_drag(cx);
+                    }
+
                     self.drag_active = true;
                     cx.capture();
                     // NOTE: Otherwise we don't get key up events
@@ -507,15 +606,21 @@ impl View for ParamSlider {
             }
             WindowEvent::MouseUp(MouseButton::Left) => {
                 if self.drag_active {
-                    self.drag_active = false;
                     cx.release();
-                    cx.set_active(false);
-
-                    self.param_base.end_set_parameter(cx);
+                    self.end_drag(cx);
 
                     meta.consume();
                 }
             }
+            // If the drag gets interrupted because the slider lost mouse capture or focus (for
+            // instance when the window loses focus mid-drag), then we won't get a mouse up event.
+            // The gesture still needs to be ended in that case, or the host may keep the parameter
+            // in touch mode.
+            WindowEvent::MouseCaptureOutEvent | WindowEvent::FocusOut => {
+                if self.drag_active {
+                    self.end_drag(cx);
+                }
+            }
             WindowEvent::MouseMove(x, _y) => {
                 if self.drag_active {
                     // If shift is being held then the drag should be more granular instead of
@@ -617,6 +722,31 @@ pub trait ParamSliderExt {
     /// Manually set a fixed label for the slider instead of displaying the current value. This is
     /// currently not reactive.
     fn with_label(self, value: impl Into<String>) -> Self;
+
+    /// Draw tick marks at these normalized positions. Ticks with a label are drawn as major ticks.
+    /// This replaces any previously set ticks.
+    fn with_ticks(self, ticks: impl IntoIterator<Item = SliderTick>) -> Self;
+
+    /// Draw tick marks at these plain parameter values, optionally with a label. This replaces any
+    /// previously set ticks. Useful for parameters that are already expressed in decibels, like an
+    /// EQ band's gain:
+    ///
+    /// ```ignore
+    /// ParamSlider::new(cx, Data::params, |params| &params.band_gain_db).with_value_ticks(
+    ///     [-12.0, -6.0, 0.0, 6.0, 12.0].map(|db| (db, Some(format!("{db}")))),
+    /// );
+    /// ```
+    fn with_value_ticks(self, ticks: impl IntoIterator<Item = (f32, Option<String>)>) -> Self;
+
+    /// Draw tick marks at these decibel values for a parameter that stores a linear gain value, like
+    /// the gain parameters in NIH-plug's examples. The ticks in `labeled_ticks_db` are drawn as
+    /// major ticks labeled with their decibel value. This replaces any previously set ticks.
+    ///
+    /// ```ignore
+    /// ParamSlider::new(cx, Data::params, |params| &params.gain)
+    ///     .with_decibel_ticks(&[-18.0, -12.0, -6.0, 0.0, 6.0, 12.0, 18.0], &[-12.0, 0.0, 12.0]);
+    /// ```
+    fn with_decibel_ticks(self, ticks_db: &[f32], labeled_ticks_db: &[f32]) -> Self;
 }
 
 impl ParamSliderExt for Handle<'_, ParamSlider> {
@@ -633,4 +763,45 @@ impl ParamSliderExt for Handle<'_, ParamSlider> {
             param_slider.label_override = Some(value.into())
         })
     }
+
+    fn with_ticks(self, ticks: impl IntoIterator<Item = SliderTick>) -> Self {
+        self.modify(|param_slider: &mut ParamSlider| {
+            param_slider.ticks = ticks.into_iter().collect()
+        })
+    }
+
+    fn with_value_ticks(self, ticks: impl IntoIterator<Item = (f32, Option<String>)>) -> Self {
+        self.modify(|param_slider: &mut ParamSlider| {
+            param_slider.ticks = ticks
+                .into_iter()
+                .map(|(plain_value, label)| SliderTick {
+                    normalized_value: param_slider.param_base.preview_normalized(plain_value),
+                    label,
+                })
+                .collect()
+        })
+    }
+
+    fn with_decibel_ticks(self, ticks_db: &[f32], labeled_ticks_db: &[f32]) -> Self {
+        let mut all_ticks_db: Vec<f32> = ticks_db.iter().chain(labeled_ticks_db).copied().collect();
+        all_ticks_db.sort_by(f32::total_cmp);
+        all_ticks_db.dedup();
+
+        self.with_value_ticks(all_ticks_db.into_iter().map(|tick_db| {
+            let label = labeled_ticks_db
+                .contains(&tick_db)
+                .then(|| format_decibel_tick(tick_db));
+
+            (nih_plug::util::db_to_gain(tick_db), label)
+        }))
+    }
+}
+
+/// Format a decibel value for a tick label. Positive values get an explicit plus sign.
+fn format_decibel_tick(tick_db: f32) -> String {
+    if tick_db > 0.0 {
+        format!("+{tick_db}")
+    } else {
+        format!("{tick_db}")
+    }
 }
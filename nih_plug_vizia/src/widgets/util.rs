@@ -1,6 +1,7 @@
 //! Utilities for writing VIZIA widgets.
 
 use vizia::prelude::*;
+use vizia::vg;
 
 /// An extension trait for [`Modifiers`] that adds platform-independent getters.
 pub trait ModifiersExt {
@@ -79,3 +80,135 @@ pub fn remap_current_entity_y_coordinate(cx: &EventContext, y_coord: f32) -> f32
     let height = cx.cache.get_height(cx.current()) - (border_width * 2.0);
     ((y_coord - y_pos) / height).clamp(0.0, 1.0)
 }
+
+/// A grid for 2D widgets like X-Y pads and EQ graphs that handles can snap to. The grid lines are
+/// stored as `[0, 1]` positions within the widget, where `(0, 0)` is the top left corner. Snapping
+/// uses the same detent logic as the GUI integrations' sliders, see
+/// [`nih_plug::util::snap_to_detent()`].
+///
+/// The widget's edges always act as grid lines, so a handle within the snap distance of an edge
+/// snaps to that edge. This way the parameters' minimum and maximum values can always be reached
+/// while snapping. By default snapping is enabled and holding Alt while dragging temporarily
+/// disables it. Use [`disabled_by_default()`][Self::disabled_by_default()] to flip this around.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapGrid {
+    /// The x-coordinates of the vertical grid lines.
+    x_lines: Vec<f32>,
+    /// The y-coordinates of the horizontal grid lines.
+    y_lines: Vec<f32>,
+    /// The distance in logical pixels from a grid line at which a handle snaps to that line.
+    snap_distance: f32,
+    /// Whether snapping is active without holding Alt.
+    enabled_by_default: bool,
+}
+
+impl SnapGrid {
+    /// A grid with vertical lines at `x_lines` and horizontal lines at `y_lines`, both given as
+    /// `[0, 1]` positions within the widget. Positions outside of that range are ignored.
+    pub fn new(
+        x_lines: impl IntoIterator<Item = f32>,
+        y_lines: impl IntoIterator<Item = f32>,
+    ) -> Self {
+        let in_range = |t: &f32| (0.0..=1.0).contains(t);
+
+        Self {
+            x_lines: x_lines.into_iter().filter(in_range).collect(),
+            y_lines: y_lines.into_iter().filter(in_range).collect(),
+            snap_distance: 6.0,
+            enabled_by_default: true,
+        }
+    }
+
+    /// A grid that divides the widget into `x_divisions` columns and `y_divisions` rows.
+    pub fn evenly_spaced(x_divisions: usize, y_divisions: usize) -> Self {
+        let lines = |divisions: usize| {
+            let divisions = divisions.max(1);
+            (1..divisions).map(move |i| i as f32 / divisions as f32)
+        };
+
+        Self::new(lines(x_divisions), lines(y_divisions))
+    }
+
+    /// Change the distance in logical pixels from a grid line at which handles snap to that line.
+    /// Defaults to six pixels.
+    pub fn with_snap_distance(mut self, snap_distance: f32) -> Self {
+        self.snap_distance = snap_distance.max(0.0);
+        self
+    }
+
+    /// Only snap while Alt is held down, instead of snapping unless Alt is held down.
+    pub fn disabled_by_default(mut self) -> Self {
+        self.enabled_by_default = false;
+        self
+    }
+
+    /// The x-coordinates of the vertical grid lines.
+    pub fn x_lines(&self) -> &[f32] {
+        &self.x_lines
+    }
+
+    /// The y-coordinates of the horizontal grid lines.
+    pub fn y_lines(&self) -> &[f32] {
+        &self.y_lines
+    }
+
+    /// Whether snapping should be applied with these modifiers held down.
+    pub fn is_active(&self, modifiers: &Modifiers) -> bool {
+        self.enabled_by_default != modifiers.alt()
+    }
+
+    /// Snap a `[0, 1]` position within a widget that's `size` logical pixels large to the grid.
+    /// Both axes are snapped independently.
+    pub fn snap(&self, (x, y): (f32, f32), (width, height): (f32, f32)) -> (f32, f32) {
+        let snap_axis = |t: f32, lines: &[f32], length: f32| {
+            if length <= 0.0 {
+                return t;
+            }
+
+            let t = nih_plug::util::snap_to_detent(t, lines, self.snap_distance / length);
+            // The edges are only used as a fallback so they don't take precedence over nearby lines
+            nih_plug::util::snap_to_detent(t, &[0.0, 1.0], self.snap_distance / length)
+        };
+
+        (
+            snap_axis(x, &self.x_lines, width),
+            snap_axis(y, &self.y_lines, height),
+        )
+    }
+
+    /// Draw the grid lines within `bounds`. The lines are snapped to the physical pixel grid.
+    /// `dpi_scale` should be the integer DPI scale, see the peak meter widget's implementation.
+    pub fn draw(&self, canvas: &Canvas, bounds: BoundingBox, dpi_scale: f32, paint: &vg::Paint) {
+        let mut path = vg::Path::new();
+        for &x in &self.x_lines {
+            let x = (bounds.x + bounds.w * x).round() + (dpi_scale / 2.0);
+            path.move_to((x, bounds.top()));
+            path.line_to((x, bounds.bottom()));
+        }
+        for &y in &self.y_lines {
+            let y = (bounds.y + bounds.h * y).round() + (dpi_scale / 2.0);
+            path.move_to((bounds.left(), y));
+            path.line_to((bounds.right(), y));
+        }
+
+        canvas.draw_path(&path, paint);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snap_grid() {
+        let grid = SnapGrid::evenly_spaced(4, 2);
+        assert_eq!(grid.x_lines(), [0.25, 0.5, 0.75]);
+        assert_eq!(grid.y_lines(), [0.5]);
+
+        // Six pixels on a 100x100 pixel widget
+        assert_eq!(grid.snap((0.27, 0.45), (100.0, 100.0)), (0.25, 0.5));
+        assert_eq!(grid.snap((0.35, 0.3), (100.0, 100.0)), (0.35, 0.3));
+        // The edges are always part of the grid
+        assert_eq!(grid.snap((0.97, 0.04), (100.0, 100.0)), (1.0, 0.0));
+    }
+}
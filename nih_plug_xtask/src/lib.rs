@@ -8,8 +8,12 @@ use std::process::Command;
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 
+mod signing;
 mod symbols;
 mod util;
+mod validate;
+
+pub use signing::SigningOptions;
 
 /// Re-export for the main function.
 pub use anyhow::Result;
@@ -20,10 +24,26 @@ fn build_usage_string(command_name: &str) -> String {
   {command_name} bundle <package> [--release]
   {command_name} bundle -p <package1> -p <package2> ... [--release]
 
+  {command_name} bundle <package> --universal [--release]  (macOS only, ignored elsewhere)
   {command_name} bundle-universal <package> [--release]  (macOS only)
   {command_name} bundle-universal -p <package1> -p <package2> ... [--release]  (macOS only)
 
-  All other 'cargo build' options are supported, including '--target' and '--profile'."
+  All other 'cargo build' options are supported, including '--target' and '--profile'.
+
+  {command_name} validate <package>
+  {command_name} validate -p <package1> -p <package2> ...
+
+  Validates the package's bundled CLAP and VST3 plugins for the native target with
+  'clap-validator' and the VST3 SDK's 'validator' if they're installed. Otherwise the plugin
+  is loaded, instantiated, and used to process a single block as a smoke test. Standalone
+  bundles are run with '--info' to check their capabilities summary. Skipped bundles count
+  as failures.
+
+  On macOS, bundles can be signed with '--codesign-identity <identity>'. Adding '--notarize'
+  also notarizes the bundles using the keychain profile named by the
+  {keychain_profile_env} environment variable. The profile can be created with
+  'xcrun notarytool store-credentials <profile>'.",
+        keychain_profile_env = signing::NOTARIZE_KEYCHAIN_PROFILE_ENV,
     )
 }
 
@@ -31,9 +51,51 @@ fn build_usage_string(command_name: &str) -> String {
 /// `bundler.toml` alongside the workspace's main `Cargo.toml` file.
 type BundlerConfig = HashMap<String, PackageConfig>;
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
 struct PackageConfig {
+    /// The human readable name used for the bundles. Defaults to the package's name.
     name: Option<String>,
+    /// The formats that should be bundled. If this is set, then bundling fails if the package does
+    /// not support one of these formats. Defaults to all formats supported by the package.
+    formats: Option<Vec<BundleFormat>>,
+    /// The plugin's vendor. Used for the copyright notice in macOS bundles.
+    vendor: Option<String>,
+    /// The plugin's website. Added to the copyright notice in macOS bundles.
+    url: Option<String>,
+    /// The bundle identifier for macOS bundles. Defaults to `com.nih-plug.<package_name>`.
+    identifier: Option<String>,
+    /// The version for macOS bundles. Defaults to `1.0.0`.
+    version: Option<String>,
+}
+
+/// The formats that can be enabled in `bundler.toml`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum BundleFormat {
+    Clap,
+    Vst2,
+    Vst3,
+    Standalone,
+}
+
+impl PackageConfig {
+    /// Whether the format should be bundled if the package supports it.
+    fn bundles_format(&self, format: BundleFormat) -> bool {
+        match &self.formats {
+            Some(formats) => formats.contains(&format),
+            None => true,
+        }
+    }
+
+    /// Whether the format was explicitly enabled in `bundler.toml`, in which case the package must
+    /// support it.
+    fn requires_format(&self, format: BundleFormat) -> bool {
+        match &self.formats {
+            Some(formats) => formats.contains(&format),
+            None => false,
+        }
+    }
 }
 
 /// The target we're generating a plugin for. This can be either the native target or a cross
@@ -94,51 +156,68 @@ pub fn main_with_args(command_name: &str, args: impl IntoIterator<Item = String>
             // multiple packages can be built in parallel if we pass all of these flags to a single
             // `cargo build` we'll first build all of these packages and only then bundle them.
             let (packages, other_args) = split_bundle_args(args, &usage_string)?;
+            let (signing_options, other_args) = signing::split_signing_args(other_args)?;
+
+            // `bundle --universal` is the same as `bundle-universal`, except that it falls back to
+            // a regular build on other platforms so the same command can be used everywhere
+            let (universal, other_args) = split_universal_arg(other_args);
+            if universal {
+                if cfg!(target_os = "macos") {
+                    return build_and_bundle_universal(
+                        &format!("{command_name} bundle --universal"),
+                        target_dir,
+                        packages,
+                        other_args,
+                        &signing_options,
+                    );
+                } else {
+                    eprintln!(
+                        "WARNING: Universal binaries can only be built on macOS, ignoring the \
+                         '--universal' option"
+                    );
+                }
+            }
 
             // As explained above, for efficiency's sake this is a two step process
             build(&packages, &other_args)?;
 
-            bundle(target_dir, &packages[0], &other_args, false)?;
+            bundle_and_sign(
+                target_dir,
+                &packages[0],
+                &other_args,
+                false,
+                &signing_options,
+            )?;
             for package in packages.into_iter().skip(1) {
-                bundle(target_dir, &package, &other_args, false)?;
+                bundle_and_sign(target_dir, &package, &other_args, false, &signing_options)?;
             }
 
             Ok(())
         }
         "bundle-universal" => {
-            // The same as `--bundle`, but builds universal binaries for macOS Cargo will also error
-            // out on duplicate `--target` options, but it seems like a good idea to preemptively
-            // abort the bundling process if that happens
             let (packages, other_args) = split_bundle_args(args, &usage_string)?;
+            let (signing_options, other_args) = signing::split_signing_args(other_args)?;
 
-            for arg in &other_args {
-                if arg == "--target" || arg.starts_with("--target=") {
-                    anyhow::bail!(
-                        "'{command_name} xtask bundle-universal' is incompatible with the '{arg}' \
-                         option."
-                    )
-                }
+            build_and_bundle_universal(
+                &format!("{command_name} bundle-universal"),
+                target_dir,
+                packages,
+                other_args,
+                &signing_options,
+            )
+        }
+        "validate" => {
+            let (packages, other_args) = split_bundle_args(args, &usage_string)?;
+            if let Some(arg) = other_args.first() {
+                anyhow::bail!("Unexpected argument '{arg}'\n\n{usage_string}");
             }
 
-            // We can just use the regular build function here. There's sadly no way to build both
-            // targets in parallel, so this will likely take twice as logn as a regular build.
-            // TODO: Explicitly specifying the target even on the native target causes a rebuild in
-            //       the target `target/<target_triple>` directory. This makes bundling much simpler
-            //       because there's no conditional logic required based on the current platform,
-            //       but it does waste some resources and requires a rebuild if the native target
-            //       was already built.
-            let mut x86_64_args = other_args.clone();
-            x86_64_args.push(String::from("--target=x86_64-apple-darwin"));
-            build(&packages, &x86_64_args)?;
-            let mut aarch64_args = other_args.clone();
-            aarch64_args.push(String::from("--target=aarch64-apple-darwin"));
-            build(&packages, &aarch64_args)?;
-
-            // This `true` indicates a universal build. This will cause the two sets of built
-            // binaries to beq lipo'd together into universal binaries before bundling
-            bundle(target_dir, &packages[0], &other_args, true)?;
-            for package in packages.into_iter().skip(1) {
-                bundle(target_dir, &package, &other_args, true)?;
+            let mut all_passed = true;
+            for package in &packages {
+                all_passed &= validate::validate(target_dir, package)?;
+            }
+            if !all_passed {
+                anyhow::bail!("Validation failed");
             }
 
             Ok(())
@@ -150,6 +229,53 @@ pub fn main_with_args(command_name: &str, args: impl IntoIterator<Item = String>
     }
 }
 
+/// The same as `bundle`, but builds universal binaries for macOS. This builds the package for both
+/// `x86_64-apple-darwin` and `aarch64-apple-darwin` and then lipos the results together. If either
+/// target has not been installed through rustup yet, then the user is asked to install them first.
+/// `command` is the command the user ran, used in error messages.
+fn build_and_bundle_universal(
+    command: &str,
+    target_dir: &Path,
+    packages: Vec<String>,
+    other_args: Vec<String>,
+    signing_options: &SigningOptions,
+) -> Result<()> {
+    // Cargo will also error out on duplicate `--target` options, but it seems like a good idea to
+    // preemptively abort the bundling process if that happens
+    for arg in &other_args {
+        if arg == "--target" || arg.starts_with("--target=") {
+            anyhow::bail!(
+                "'{command}' always builds for both x86_64-apple-darwin and aarch64-apple-darwin, \
+                 so it cannot be combined with the '{arg}' option."
+            )
+        }
+    }
+
+    util::ensure_rustup_targets(&["x86_64-apple-darwin", "aarch64-apple-darwin"])?;
+
+    // We can just use the regular build function here. There's sadly no way to build both targets
+    // in parallel, so this will likely take twice as logn as a regular build.
+    // TODO: Explicitly specifying the target even on the native target causes a rebuild in the
+    //       target `target/<target_triple>` directory. This makes bundling much simpler because
+    //       there's no conditional logic required based on the current platform, but it does waste
+    //       some resources and requires a rebuild if the native target was already built.
+    let mut x86_64_args = other_args.clone();
+    x86_64_args.push(String::from("--target=x86_64-apple-darwin"));
+    build(&packages, &x86_64_args)?;
+    let mut aarch64_args = other_args.clone();
+    aarch64_args.push(String::from("--target=aarch64-apple-darwin"));
+    build(&packages, &aarch64_args)?;
+
+    // This `true` indicates a universal build. This will cause the two sets of built binaries to
+    // beq lipo'd together into universal binaries before bundling
+    bundle_and_sign(target_dir, &packages[0], &other_args, true, signing_options)?;
+    for package in packages.into_iter().skip(1) {
+        bundle_and_sign(target_dir, &package, &other_args, true, signing_options)?;
+    }
+
+    Ok(())
+}
+
 /// Change the current directory into the Cargo workspace's root.
 ///
 /// This is using a heuristic to find the workspace root. It considers all ancestor directories of
@@ -217,6 +343,26 @@ pub fn build(packages: &[String], args: &[String]) -> Result<()> {
 /// specified instead, then this will assume both `x86_64-apple-darwin` and `aarch64-apple-darwin`
 /// have been built and it will try to lipo those together instead.
 pub fn bundle(target_dir: &Path, package: &str, args: &[String], universal: bool) -> Result<()> {
+    bundle_and_sign(
+        target_dir,
+        package,
+        args,
+        universal,
+        &SigningOptions::default(),
+    )
+}
+
+/// The same as [`bundle()`], but when bundling for macOS this signs the bundles with the identity
+/// from `signing_options` and optionally notarizes them instead of self-signing them. This returns
+/// an error if signing or notarization fails.
+pub fn bundle_and_sign(
+    target_dir: &Path,
+    package: &str,
+    args: &[String],
+    universal: bool,
+    signing_options: &SigningOptions,
+) -> Result<()> {
+    let package_config = load_package_config(package)?;
     let mut build_type_dir = "debug";
     let mut cross_compile_target: Option<String> = None;
     for arg_idx in (0..args.len()).rev() {
@@ -281,14 +427,21 @@ pub fn bundle(target_dir: &Path, package: &str, args: &[String], universal: bool
         if !build_bin && !build_lib {
             anyhow::bail!("Could not find built libraries for universal build.");
         }
+        if !build_bin && package_config.requires_format(BundleFormat::Standalone) {
+            anyhow::bail!(
+                "'{package}' is configured to be bundled as a standalone application in \
+                 'bundler.toml', but it does not have a binary target"
+            );
+        }
 
         eprintln!();
-        if build_bin {
+        if build_bin && package_config.bundles_format(BundleFormat::Standalone) {
             bundle_binary(
                 target_dir,
                 package,
                 &[&x86_64_bin_path, &aarch64_bin_path],
                 CompilationTarget::MacOSUniversal,
+                signing_options,
             )?;
         }
         if build_lib {
@@ -297,6 +450,7 @@ pub fn bundle(target_dir: &Path, package: &str, args: &[String], universal: bool
                 package,
                 &[&x86_64_lib_path, &aarch64_lib_path],
                 CompilationTarget::MacOSUniversal,
+                signing_options,
             )?;
         }
     } else {
@@ -319,12 +473,31 @@ to your Cargo.toml file?"#,
             );
         }
 
+        if !bin_path.exists() && package_config.requires_format(BundleFormat::Standalone) {
+            anyhow::bail!(
+                "'{package}' is configured to be bundled as a standalone application in \
+                 'bundler.toml', but it does not have a binary target"
+            );
+        }
+
         eprintln!();
-        if bin_path.exists() {
-            bundle_binary(target_dir, package, &[&bin_path], compilation_target)?;
+        if bin_path.exists() && package_config.bundles_format(BundleFormat::Standalone) {
+            bundle_binary(
+                target_dir,
+                package,
+                &[&bin_path],
+                compilation_target,
+                signing_options,
+            )?;
         }
         if lib_path.exists() {
-            bundle_plugin(target_dir, package, &[&lib_path], compilation_target)?;
+            bundle_plugin(
+                target_dir,
+                package,
+                &[&lib_path],
+                compilation_target,
+                signing_options,
+            )?;
         }
     }
 
@@ -339,12 +512,14 @@ fn bundle_binary(
     package: &str,
     bin_paths: &[&Path],
     compilation_target: CompilationTarget,
+    signing_options: &SigningOptions,
 ) -> Result<()> {
     let bundle_home_dir = bundle_home(target_dir);
-    let bundle_name = match load_bundler_config()?.and_then(|c| c.get(package).cloned()) {
-        Some(PackageConfig { name: Some(name) }) => name,
-        _ => package.to_string(),
-    };
+    let package_config = load_package_config(package)?;
+    let bundle_name = package_config
+        .name
+        .clone()
+        .unwrap_or_else(|| package.to_string());
 
     // On MacOS the standalone target needs to be in a bundle
     let standalone_bundle_binary_name =
@@ -385,7 +560,7 @@ fn bundle_binary(
         compilation_target,
         BundleType::Binary,
     )?;
-    maybe_codesign(&standalone_bundle_home, compilation_target);
+    signing::maybe_sign_and_notarize(&standalone_bundle_home, compilation_target, signing_options)?;
 
     eprintln!(
         "Created a standalone bundle at '{}'",
@@ -403,12 +578,14 @@ fn bundle_plugin(
     package: &str,
     lib_paths: &[&Path],
     compilation_target: CompilationTarget,
+    signing_options: &SigningOptions,
 ) -> Result<()> {
     let bundle_home_dir = bundle_home(target_dir);
-    let bundle_name = match load_bundler_config()?.and_then(|c| c.get(package).cloned()) {
-        Some(PackageConfig { name: Some(name) }) => name,
-        _ => package.to_string(),
-    };
+    let package_config = load_package_config(package)?;
+    let bundle_name = package_config
+        .name
+        .clone()
+        .unwrap_or_else(|| package.to_string());
 
     // We'll detect the plugin formats supported by the plugin binary and create bundled accordingly.
     // If `lib_path` contains paths to multiple plugins that need to be combined into a macOS
@@ -426,6 +603,21 @@ fn bundle_plugin(
         .with_context(|| format!("Could not parse '{}'", first_lib_path.display()))?;
     let bundle_vst3 = symbols::exported(first_lib_path, "GetPluginFactory")
         .with_context(|| format!("Could not parse '{}'", first_lib_path.display()))?;
+    for (format, format_name, exported) in [
+        (BundleFormat::Clap, "CLAP", bundle_clap),
+        (BundleFormat::Vst2, "VST2", bundle_vst2),
+        (BundleFormat::Vst3, "VST3", bundle_vst3),
+    ] {
+        if !exported && package_config.requires_format(format) {
+            anyhow::bail!(
+                "'{package}' is configured to be bundled as a {format_name} plugin in \
+                 'bundler.toml', but it does not export a {format_name} plugin"
+            );
+        }
+    }
+    let bundle_clap = bundle_clap && package_config.bundles_format(BundleFormat::Clap);
+    let bundle_vst2 = bundle_vst2 && package_config.bundles_format(BundleFormat::Vst2);
+    let bundle_vst3 = bundle_vst3 && package_config.bundles_format(BundleFormat::Vst3);
     let bundled_plugin = bundle_clap || bundle_vst2 || bundle_vst3;
 
     if bundle_clap {
@@ -452,7 +644,7 @@ fn bundle_plugin(
             compilation_target,
             BundleType::Plugin,
         )?;
-        maybe_codesign(&clap_bundle_home, compilation_target);
+        signing::maybe_sign_and_notarize(&clap_bundle_home, compilation_target, signing_options)?;
 
         eprintln!("Created a CLAP bundle at '{}'", clap_bundle_home.display());
     }
@@ -480,7 +672,7 @@ fn bundle_plugin(
             compilation_target,
             BundleType::Plugin,
         )?;
-        maybe_codesign(&vst2_bundle_home, compilation_target);
+        signing::maybe_sign_and_notarize(&vst2_bundle_home, compilation_target, signing_options)?;
 
         eprintln!("Created a VST2 bundle at '{}'", vst2_bundle_home.display());
     }
@@ -507,11 +699,16 @@ fn bundle_plugin(
             compilation_target,
             BundleType::Plugin,
         )?;
-        maybe_codesign(vst3_bundle_home, compilation_target);
+        signing::maybe_sign_and_notarize(vst3_bundle_home, compilation_target, signing_options)?;
 
         eprintln!("Created a VST3 bundle at '{}'", vst3_bundle_home.display());
     }
-    if !bundled_plugin {
+    if !bundled_plugin && package_config.formats.is_some() {
+        eprintln!(
+            "Not creating any plugin bundles because no plugin formats are enabled in \
+             'bundler.toml'"
+        )
+    } else if !bundled_plugin {
         eprintln!("Not creating any plugin bundles because the package does not export any plugins")
     }
 
@@ -548,6 +745,14 @@ fn load_bundler_config() -> Result<Option<BundlerConfig>> {
     Ok(Some(result))
 }
 
+/// Get a package's configuration from the `bundler.toml` file, or the default configuration if the
+/// package is not listed there.
+fn load_package_config(package: &str) -> Result<PackageConfig> {
+    Ok(load_bundler_config()?
+        .and_then(|mut config| config.remove(package))
+        .unwrap_or_default())
+}
+
 /// Split the `xtask bundle` arguments into a list of packages and a list of other arguments. The
 /// package vector either contains just the first argument, or if the arguments iterator starts with
 /// one or more occurences of `-p <package>` then this will contain all those packages.
@@ -575,6 +780,14 @@ fn split_bundle_args(
     Ok((packages, other_args))
 }
 
+/// Remove the `--universal` option from the bundler's arguments, returning whether it was present.
+fn split_universal_arg(args: Vec<String>) -> (bool, Vec<String>) {
+    let (universal_args, other_args): (Vec<_>, Vec<_>) =
+        args.into_iter().partition(|arg| arg == "--universal");
+
+    (!universal_args.is_empty(), other_args)
+}
+
 /// The target we're compiling for. This is used to determine the paths and options for creating
 /// plugin bundles.
 fn compilation_target(cross_compile_target: Option<&str>) -> Result<CompilationTarget> {
@@ -750,8 +963,24 @@ pub fn maybe_create_macos_bundle_metadata(
         BundleType::Binary => "APPL",
     };
 
-    // TODO: May want to add bundler.toml fields for the identifier, version and signature at some
-    //       point.
+    let package_config = load_package_config(package)?;
+    let identifier = package_config
+        .identifier
+        .unwrap_or_else(|| format!("com.nih-plug.{package}"));
+    let version = package_config.version.as_deref().unwrap_or("1.0.0");
+    let copyright = match (package_config.vendor, package_config.url) {
+        (Some(vendor), Some(url)) => format!("{vendor}, {url}"),
+        (vendor, url) => vendor.or(url).unwrap_or_default(),
+    };
+
+    // These values come from `bundler.toml` and may contain characters that have a special meaning
+    // in XML
+    let display_name = xml_escape(display_name);
+    let identifier = xml_escape(&identifier);
+    let version = xml_escape(version);
+    let copyright = xml_escape(&copyright);
+
+    // TODO: May want to add a bundler.toml field for the signature at some point.
     fs::write(
         bundle_home.join("Contents").join("PkgInfo"),
         format!("{package_type}????"),
@@ -769,7 +998,7 @@ pub fn maybe_create_macos_bundle_metadata(
     <key>CFBundleIconFile</key>
     <string></string>
     <key>CFBundleIdentifier</key>
-    <string>com.nih-plug.{package}</string>
+    <string>{identifier}</string>
     <key>CFBundleName</key>
     <string>{display_name}</string>
     <key>CFBundleDisplayName</key>
@@ -779,11 +1008,11 @@ pub fn maybe_create_macos_bundle_metadata(
     <key>CFBundleSignature</key>
     <string>????</string>
     <key>CFBundleShortVersionString</key>
-    <string>1.0.0</string>
+    <string>{version}</string>
     <key>CFBundleVersion</key>
-    <string>1.0.0</string>
+    <string>{version}</string>
     <key>NSHumanReadableCopyright</key>
-    <string></string>
+    <string>{copyright}</string>
     <key>NSHighResolutionCapable</key>
     <true/>
   </dict>
@@ -795,6 +1024,23 @@ pub fn maybe_create_macos_bundle_metadata(
     Ok(())
 }
 
+/// Escape the characters that cannot appear literally in XML text.
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
 /// If compiling for macOS, try to self-sign the bundle at the given path. This shouldn't be
 /// necessary, but AArch64 macOS is stricter about these things and sometimes self built plugins may
 /// not load otherwise. Presumably in combination with hardened runtimes.
@@ -822,3 +1068,81 @@ pub fn maybe_codesign(bundle_home: &Path, target: CompilationTarget) {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_bundler_config() {
+        let config: BundlerConfig = toml::from_str(
+            r#"
+            [gain]
+            name = "Gain"
+            formats = ["clap", "vst3", "standalone"]
+            vendor = "NIH-plug"
+            url = "https://github.com/robbert-vdh/nih-plug"
+            identifier = "com.nih-plug.gain"
+            version = "1.2.3"
+
+            [minimal]
+            "#,
+        )
+        .unwrap();
+
+        let gain = &config["gain"];
+        assert_eq!(gain.name.as_deref(), Some("Gain"));
+        assert_eq!(
+            gain.formats,
+            Some(vec![
+                BundleFormat::Clap,
+                BundleFormat::Vst3,
+                BundleFormat::Standalone
+            ])
+        );
+        assert_eq!(gain.vendor.as_deref(), Some("NIH-plug"));
+        assert_eq!(
+            gain.url.as_deref(),
+            Some("https://github.com/robbert-vdh/nih-plug")
+        );
+        assert_eq!(gain.identifier.as_deref(), Some("com.nih-plug.gain"));
+        assert_eq!(gain.version.as_deref(), Some("1.2.3"));
+        assert!(gain.bundles_format(BundleFormat::Clap));
+        assert!(!gain.bundles_format(BundleFormat::Vst2));
+        assert!(gain.requires_format(BundleFormat::Vst3));
+
+        // Everything is optional, and without a format list every supported format is bundled
+        let minimal = &config["minimal"];
+        assert_eq!(minimal.name, None);
+        assert!(minimal.bundles_format(BundleFormat::Vst2));
+        assert!(!minimal.requires_format(BundleFormat::Vst2));
+    }
+
+    #[test]
+    fn reject_invalid_bundler_config() {
+        let unknown_key = toml::from_str::<BundlerConfig>(
+            r#"
+            [gain]
+            nmae = "Gain"
+            "#,
+        );
+        assert!(unknown_key.unwrap_err().to_string().contains("nmae"));
+
+        let unknown_format = toml::from_str::<BundlerConfig>(
+            r#"
+            [gain]
+            formats = ["clap", "au"]
+            "#,
+        );
+        assert!(unknown_format.unwrap_err().to_string().contains("au"));
+    }
+
+    #[test]
+    fn escape_xml() {
+        assert_eq!(xml_escape("Plain Vendor"), "Plain Vendor");
+        assert_eq!(
+            xml_escape(r#"Tom & Jerry's <"Plugins">"#),
+            "Tom &amp; Jerry&apos;s &lt;&quot;Plugins&quot;&gt;"
+        );
+    }
+}
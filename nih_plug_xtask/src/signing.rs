@@ -0,0 +1,292 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::CompilationTarget;
+
+/// The environment variable containing the name of the keychain profile used for notarization.
+/// The profile is created once with `xcrun notarytool store-credentials <profile>`, which stores the
+/// Apple ID, team ID, and app-specific password in the keychain. This way the password never needs
+/// to be passed on the command line, where it would show up in process listings.
+pub const NOTARIZE_KEYCHAIN_PROFILE_ENV: &str = "NIH_PLUG_NOTARIZE_KEYCHAIN_PROFILE";
+
+/// Options for codesigning and notarizing macOS bundles. These are parsed from the bundler's
+/// command line arguments by [`split_signing_args()`]. When no identity is set, bundles are
+/// self-signed instead.
+#[derive(Debug, Clone, Default)]
+pub struct SigningOptions {
+    /// The identity to sign the bundles with, e.g. `Developer ID Application: Foo Bar (ABCDE12345)`.
+    pub identity: Option<String>,
+    /// Whether to submit the signed bundles for notarization and to staple the resulting tickets to
+    /// the bundles. The credentials are read from the keychain profile named by the
+    /// [`NOTARIZE_KEYCHAIN_PROFILE_ENV`] environment variable.
+    pub notarize: bool,
+}
+
+/// The parts of `xcrun notarytool submit --output-format json`'s output we care about.
+#[derive(Debug, Deserialize)]
+struct NotarytoolSubmission {
+    id: String,
+    status: String,
+}
+
+/// Remove the `--codesign-identity <identity>` and `--notarize` options from the bundler's
+/// arguments, since these should not be passed to `cargo build`.
+pub fn split_signing_args(args: Vec<String>) -> Result<(SigningOptions, Vec<String>)> {
+    let mut options = SigningOptions::default();
+    let mut other_args = Vec::with_capacity(args.len());
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--codesign-identity" => {
+                options.identity = Some(
+                    args.next()
+                        .context("Missing identity after --codesign-identity")?,
+                );
+            }
+            "--notarize" => options.notarize = true,
+            arg if arg.starts_with("--codesign-identity=") => {
+                options.identity = arg.strip_prefix("--codesign-identity=").map(String::from);
+            }
+            _ => other_args.push(arg),
+        }
+    }
+
+    if options.notarize && options.identity.is_none() {
+        anyhow::bail!(
+            "'--notarize' requires a signing identity to be set with '--codesign-identity'"
+        );
+    }
+
+    Ok((options, other_args))
+}
+
+/// If compiling for macOS, sign the bundle at the given path with the configured identity and
+/// optionally notarize it. This falls back to self-signing the bundle with
+/// [`maybe_codesign()`][crate::maybe_codesign()] if no identity has been set. Does nothing when not
+/// compiling for macOS, and only prints a warning when signing with an identity on another
+/// platform since Apple's tools are not available there.
+pub fn maybe_sign_and_notarize(
+    bundle_home: &Path,
+    target: CompilationTarget,
+    options: &SigningOptions,
+) -> Result<()> {
+    if !matches!(
+        target,
+        CompilationTarget::MacOS(_) | CompilationTarget::MacOSUniversal
+    ) {
+        return Ok(());
+    }
+
+    let Some(identity) = &options.identity else {
+        crate::maybe_codesign(bundle_home, target);
+        return Ok(());
+    };
+    if !cfg!(target_os = "macos") {
+        eprintln!(
+            "WARNING: Not signing '{}' since codesigning is only supported on macOS",
+            bundle_home.display()
+        );
+        return Ok(());
+    }
+
+    codesign(bundle_home, identity)?;
+    if options.notarize {
+        notarize(bundle_home)?;
+    }
+
+    Ok(())
+}
+
+/// Sign a bundle with the hardened runtime enabled and with a secure timestamp, as required for
+/// notarization.
+fn codesign(bundle_home: &Path, identity: &str) -> Result<()> {
+    let status = Command::new("codesign")
+        .arg("--force")
+        .arg("--sign")
+        .arg(identity)
+        .arg("--options")
+        .arg("runtime")
+        .arg("--timestamp")
+        .arg(bundle_home)
+        .status()
+        .context("Could not call the 'codesign' binary")?;
+    if !status.success() {
+        anyhow::bail!(
+            "Could not sign '{}' with the identity '{identity}'. Run 'security find-identity -v \
+             -p codesigning' to list the available identities.",
+            bundle_home.display()
+        );
+    }
+
+    eprintln!("Signed '{}' as '{identity}'", bundle_home.display());
+
+    Ok(())
+}
+
+/// Submit a signed bundle for notarization, wait for the result, and staple the ticket to the
+/// bundle.
+fn notarize(bundle_home: &Path) -> Result<()> {
+    let keychain_profile = notarize_keychain_profile()?;
+
+    // The notary service only accepts archives, disk images, and installer packages
+    let mut archive_name = bundle_home
+        .file_name()
+        .context("Malformed bundle path")?
+        .to_owned();
+    archive_name.push(".zip");
+    let archive_path = bundle_home.with_file_name(archive_name);
+    let status = Command::new("ditto")
+        .arg("-c")
+        .arg("-k")
+        .arg("--keepParent")
+        .arg(bundle_home)
+        .arg(&archive_path)
+        .status()
+        .context("Could not call the 'ditto' binary")?;
+    if !status.success() {
+        anyhow::bail!(
+            "Could not archive '{}' for notarization",
+            bundle_home.display()
+        );
+    }
+
+    eprintln!(
+        "Submitting '{}' for notarization, this may take a while...",
+        bundle_home.display()
+    );
+    let output = Command::new("xcrun")
+        .arg("notarytool")
+        .arg("submit")
+        .arg(&archive_path)
+        .arg("--keychain-profile")
+        .arg(&keychain_profile)
+        .arg("--wait")
+        .arg("--output-format")
+        .arg("json")
+        .output()
+        .context("Could not call 'xcrun notarytool'")?;
+    // The archive is no longer needed regardless of whether the submission succeeded
+    let _ = fs::remove_file(&archive_path);
+
+    let submission: NotarytoolSubmission =
+        serde_json::from_slice(&output.stdout).with_context(|| {
+            format!(
+                "Could not submit '{}' for notarization: {}",
+                bundle_home.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+        })?;
+    if submission.status != "Accepted" {
+        anyhow::bail!(
+            "Notarization of '{}' failed with status '{}'. Run 'xcrun notarytool log {} \
+             --keychain-profile {keychain_profile}' for more information.",
+            bundle_home.display(),
+            submission.status,
+            submission.id
+        );
+    }
+
+    let status = Command::new("xcrun")
+        .arg("stapler")
+        .arg("staple")
+        .arg(bundle_home)
+        .status()
+        .context("Could not call 'xcrun stapler'")?;
+    if !status.success() {
+        anyhow::bail!(
+            "'{}' was notarized, but the ticket could not be stapled to the bundle",
+            bundle_home.display()
+        );
+    }
+
+    eprintln!("Notarized '{}'", bundle_home.display());
+
+    Ok(())
+}
+
+/// Get the name of the keychain profile containing the notarization credentials from the
+/// [`NOTARIZE_KEYCHAIN_PROFILE_ENV`] environment variable.
+fn notarize_keychain_profile() -> Result<String> {
+    std::env::var(NOTARIZE_KEYCHAIN_PROFILE_ENV)
+        .ok()
+        .filter(|profile| !profile.is_empty())
+        .with_context(|| {
+            format!(
+                "The '{NOTARIZE_KEYCHAIN_PROFILE_ENV}' environment variable needs to be set for \
+                 notarization. Run 'xcrun notarytool store-credentials <profile>' to store the \
+                 credentials in the keychain."
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn signing_args_are_removed_from_the_cargo_args() {
+        let (options, other_args) = split_signing_args(args(&[
+            "gain",
+            "--release",
+            "--codesign-identity",
+            "Developer ID Application: Foo Bar (ABCDE12345)",
+            "--notarize",
+            "--features=simd",
+        ]))
+        .unwrap();
+        // Identities contain spaces and parentheses, the shell already took care of the quoting
+        assert_eq!(
+            options.identity.as_deref(),
+            Some("Developer ID Application: Foo Bar (ABCDE12345)")
+        );
+        assert!(options.notarize);
+        assert_eq!(other_args, ["gain", "--release", "--features=simd"]);
+
+        let (options, other_args) = split_signing_args(args(&[
+            "gain",
+            "--codesign-identity=Developer ID Application: Foo Bar (ABCDE12345)",
+        ]))
+        .unwrap();
+        assert_eq!(
+            options.identity.as_deref(),
+            Some("Developer ID Application: Foo Bar (ABCDE12345)")
+        );
+        assert!(!options.notarize);
+        assert_eq!(other_args, ["gain"]);
+
+        let (options, other_args) = split_signing_args(args(&["gain", "--release"])).unwrap();
+        assert_eq!(options.identity, None);
+        assert!(!options.notarize);
+        assert_eq!(other_args, ["gain", "--release"]);
+    }
+
+    #[test]
+    fn reject_invalid_signing_args() {
+        assert!(split_signing_args(args(&["gain", "--codesign-identity"])).is_err());
+        // Notarization requires a proper signing identity
+        assert!(split_signing_args(args(&["gain", "--notarize"])).is_err());
+    }
+
+    #[test]
+    fn notarization_uses_the_keychain_profile_from_the_environment() {
+        std::env::remove_var(NOTARIZE_KEYCHAIN_PROFILE_ENV);
+        let error = notarize_keychain_profile().unwrap_err().to_string();
+        assert!(error.contains("xcrun notarytool store-credentials"));
+
+        std::env::set_var(NOTARIZE_KEYCHAIN_PROFILE_ENV, "");
+        assert!(notarize_keychain_profile().is_err());
+
+        std::env::set_var(NOTARIZE_KEYCHAIN_PROFILE_ENV, "nih-plug-notary");
+        assert_eq!(notarize_keychain_profile().unwrap(), "nih-plug-notary");
+
+        std::env::remove_var(NOTARIZE_KEYCHAIN_PROFILE_ENV);
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::{Context, Result};
 use std::fs;
+use std::io::{IsTerminal, Write};
 use std::path::Path;
 use std::process::Command;
 
@@ -64,3 +65,64 @@ pub fn lipo(inputs: &[&Path], target: &Path) -> Result<()> {
         Ok(())
     }
 }
+
+/// Make sure the targets have been installed through rustup. If any of them are missing, then the
+/// user is asked whether they should be installed. This does nothing if rustup is not available, in
+/// which case Cargo will complain about the missing targets instead.
+pub fn ensure_rustup_targets(targets: &[&str]) -> Result<()> {
+    let output = match Command::new("rustup")
+        .args(["target", "list", "--installed"])
+        .output()
+    {
+        Ok(output) if output.status.success() => output,
+        _ => return Ok(()),
+    };
+
+    let installed_targets = String::from_utf8_lossy(&output.stdout);
+    let missing_targets: Vec<&str> = targets
+        .iter()
+        .copied()
+        .filter(|target| !installed_targets.lines().any(|line| line.trim() == *target))
+        .collect();
+    if missing_targets.is_empty() {
+        return Ok(());
+    }
+
+    let install_command = format!("rustup target add {}", missing_targets.join(" "));
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!(
+            "The {} target(s) are not installed. You can install them with '{install_command}'.",
+            missing_targets.join(", ")
+        );
+    }
+
+    eprint!(
+        "The {} target(s) are not installed. Install them now with '{install_command}'? [y/N] ",
+        missing_targets.join(", ")
+    );
+    let _ = std::io::stderr().flush();
+    let mut answer = String::new();
+    std::io::stdin()
+        .read_line(&mut answer)
+        .context("Could not read from STDIN")?;
+    if !matches!(answer.trim(), "y" | "Y" | "yes") {
+        anyhow::bail!(
+            "Not building universal binaries without the {} target(s)",
+            missing_targets.join(", ")
+        );
+    }
+
+    let status = Command::new("rustup")
+        .args(["target", "add"])
+        .args(&missing_targets)
+        .status()
+        .context("Could not call rustup to install the missing targets")?;
+    if !status.success() {
+        anyhow::bail!(
+            "Could not install the {} target(s)",
+            missing_targets.join(", ")
+        );
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,602 @@
+use anyhow::{Context, Result};
+use clap_sys::audio_buffer::clap_audio_buffer;
+use clap_sys::entry::clap_plugin_entry;
+use clap_sys::events::{clap_event_header, clap_input_events, clap_output_events};
+use clap_sys::ext::audio_ports::{
+    clap_audio_port_info, clap_plugin_audio_ports, CLAP_EXT_AUDIO_PORTS,
+};
+use clap_sys::ext::params::{clap_param_info, clap_plugin_params, CLAP_EXT_PARAMS};
+use clap_sys::factory::plugin_factory::{clap_plugin_factory, CLAP_PLUGIN_FACTORY_ID};
+use clap_sys::host::clap_host;
+use clap_sys::plugin::clap_plugin;
+use clap_sys::process::{clap_process, CLAP_PROCESS_ERROR};
+use clap_sys::version::CLAP_VERSION;
+use libloading::Library;
+use serde::Deserialize;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Command;
+
+use crate::{
+    bundle_home, clap_bundle_library_name, compilation_target, load_package_config,
+    standalone_bundle_binary_name, vst3_bundle_library_name,
+};
+
+mod vst3;
+
+/// The name of the CLAP validator's binary.
+const CLAP_VALIDATOR: &str = "clap-validator";
+/// The name of the VST3 SDK's validator binary.
+const VST3_VALIDATOR: &str = "validator";
+
+/// The sample rate used for the smoke test.
+const SMOKE_TEST_SAMPLE_RATE: f64 = 48000.0;
+/// The block size used for the smoke test's single process call.
+const SMOKE_TEST_BLOCK_SIZE: u32 = 512;
+/// The version of NIH-plug's plugin capabilities summary this validator understands.
+const CAPABILITIES_FORMAT_VERSION: u32 = 2;
+
+/// The result of validating a single bundle.
+enum Outcome {
+    Passed,
+    Failed(String),
+    Skipped(String),
+}
+
+/// Validate a package's CLAP and VST3 bundles, which should already have been created with
+/// [`bundle()`][crate::bundle()] for the native target. CLAP plugins are validated using
+/// `clap-validator` and VST3 plugins are validated using the VST3 SDK's `validator` if those are
+/// available in the `PATH`. If a validator is not available, then the plugin is instead loaded
+/// in-process, instantiated, and used to process a single block of audio. This catches gross
+/// breakage, but it's not a replacement for the real validators. If the package also has a
+/// standalone bundle, then the capabilities summary printed by its `--info` option is checked as
+/// well.
+///
+/// Returns whether all bundles passed validation. Bundles that were skipped because they could not
+/// be validated do not count as passing.
+pub fn validate(target_dir: &Path, package: &str) -> Result<bool> {
+    let compilation_target = compilation_target(None)?;
+    let bundle_home_dir = bundle_home(target_dir);
+    let bundle_name = load_package_config(package)?
+        .name
+        .unwrap_or_else(|| package.to_string());
+
+    let clap_library_path =
+        bundle_home_dir.join(clap_bundle_library_name(&bundle_name, compilation_target));
+    let clap_bundle_path = bundle_home_dir.join(format!("{bundle_name}.clap"));
+    let vst3_library_path =
+        bundle_home_dir.join(vst3_bundle_library_name(&bundle_name, compilation_target));
+    let vst3_bundle_path = bundle_home_dir.join(format!("{bundle_name}.vst3"));
+    let standalone_path = bundle_home_dir.join(standalone_bundle_binary_name(
+        &bundle_name,
+        compilation_target,
+    ));
+    if !clap_library_path.exists() && !vst3_library_path.exists() && !standalone_path.exists() {
+        anyhow::bail!(
+            "Could not find a CLAP, VST3, or standalone bundle for '{package}' in '{}'. Make sure \
+             to bundle the plugin for the native target first.",
+            bundle_home_dir.display()
+        );
+    }
+
+    let mut passed = true;
+    if clap_library_path.exists() {
+        let outcome = validate_clap(CLAP_VALIDATOR, &clap_bundle_path, &clap_library_path);
+        passed &= report(&clap_bundle_path, outcome);
+    }
+    if vst3_library_path.exists() {
+        let outcome = validate_vst3(VST3_VALIDATOR, &vst3_bundle_path, &vst3_library_path);
+        passed &= report(&vst3_bundle_path, outcome);
+    }
+    if standalone_path.exists() {
+        let info_path = bundle_home_dir.join(format!("{bundle_name}-capabilities.json"));
+        let outcome = validate_capabilities(&standalone_path, &info_path);
+        passed &= report(&standalone_path, outcome);
+    }
+
+    Ok(passed)
+}
+
+/// Print the outcome of a validation, returning `false` if the validation failed or was skipped.
+fn report(bundle_path: &Path, outcome: Outcome) -> bool {
+    match outcome {
+        Outcome::Passed => {
+            eprintln!("PASSED: '{}'", bundle_path.display());
+            true
+        }
+        Outcome::Failed(reason) => {
+            eprintln!("FAILED: '{}': {reason}", bundle_path.display());
+            false
+        }
+        Outcome::Skipped(reason) => {
+            eprintln!("SKIPPED: '{}': {reason}", bundle_path.display());
+            false
+        }
+    }
+}
+
+/// Validate a CLAP bundle using `validator`, falling back to a smoke test if it's not installed.
+fn validate_clap(validator: &str, bundle_path: &Path, library_path: &Path) -> Outcome {
+    let mut command = Command::new(validator);
+    command.arg("validate").arg(bundle_path);
+    match run_validator(command) {
+        Ok(Some(true)) => Outcome::Passed,
+        Ok(Some(false)) => Outcome::Failed(format!("'{validator}' reported errors")),
+        Ok(None) => {
+            eprintln!(
+                "'{validator}' was not found, running a smoke test for '{}' instead",
+                bundle_path.display()
+            );
+
+            match unsafe { clap_smoke_test(library_path) } {
+                Ok(()) => Outcome::Passed,
+                Err(err) => Outcome::Failed(format!("{err:#}")),
+            }
+        }
+        Err(err) => Outcome::Failed(format!("{err:#}")),
+    }
+}
+
+/// Validate a VST3 bundle using `validator`, falling back to a smoke test if it's not installed.
+fn validate_vst3(validator: &str, bundle_path: &Path, library_path: &Path) -> Outcome {
+    let mut command = Command::new(validator);
+    command.arg(bundle_path);
+    match run_validator(command) {
+        Ok(Some(true)) => Outcome::Passed,
+        Ok(Some(false)) => Outcome::Failed(format!("'{validator}' reported errors")),
+        Ok(None) => {
+            eprintln!(
+                "The VST3 SDK's '{validator}' was not found, running a smoke test for '{}' instead",
+                bundle_path.display()
+            );
+
+            match unsafe { vst3::vst3_smoke_test(library_path) } {
+                Ok(()) => Outcome::Passed,
+                Err(err) => Outcome::Failed(format!("{err:#}")),
+            }
+        }
+        Err(err) => Outcome::Failed(format!("{err:#}")),
+    }
+}
+
+/// The parts of NIH-plug's `PluginCapabilities` summary that are checked here. The xtask does not
+/// depend on NIH-plug itself, so this only mirrors the fields it needs.
+#[derive(Debug, Deserialize)]
+struct Capabilities {
+    format_version: u32,
+    name: String,
+    formats: Vec<CapabilitiesFormat>,
+    audio_io_layouts: Vec<serde_json::Value>,
+    param_count: usize,
+    automatable_param_count: usize,
+    has_editor: bool,
+    latency_samples: Option<u32>,
+}
+
+/// A single entry from the summary's `formats` list. Only the format's name is checked.
+#[derive(Debug, Deserialize)]
+struct CapabilitiesFormat {
+    format: String,
+}
+
+/// Run a standalone binary with `--info` and check the capabilities summary it writes to
+/// `info_path`. The summary is written to a file since the plugin may also print other things to
+/// STDOUT.
+fn validate_capabilities(standalone_path: &Path, info_path: &Path) -> Outcome {
+    match Command::new(standalone_path)
+        .arg("--info")
+        .arg("--info-file")
+        .arg(info_path)
+        .output()
+    {
+        Ok(output) if output.status.success() => (),
+        Ok(output) => {
+            return Outcome::Failed(format!(
+                "'--info' exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+        }
+        Err(err) => return Outcome::Failed(format!("Could not run the standalone binary: {err}")),
+    }
+
+    let json = match fs::read(info_path) {
+        Ok(json) => json,
+        Err(err) => {
+            return Outcome::Failed(format!(
+                "Could not read the '--info' output from '{}': {err}",
+                info_path.display()
+            ))
+        }
+    };
+    let _ = fs::remove_file(info_path);
+    let capabilities: Capabilities = match serde_json::from_slice(&json) {
+        Ok(capabilities) => capabilities,
+        Err(err) => return Outcome::Failed(format!("Could not parse the '--info' output: {err}")),
+    };
+    if capabilities.format_version != CAPABILITIES_FORMAT_VERSION {
+        return Outcome::Skipped(format!(
+            "unsupported capabilities format version {}",
+            capabilities.format_version
+        ));
+    }
+    if capabilities.automatable_param_count > capabilities.param_count {
+        return Outcome::Failed(String::from(
+            "the plugin reports more automatable parameters than parameters",
+        ));
+    }
+    if !capabilities
+        .formats
+        .iter()
+        .any(|format| format.format == "standalone")
+    {
+        return Outcome::Failed(String::from(
+            "the summary does not list the standalone format",
+        ));
+    }
+
+    let formats: Vec<&str> = capabilities
+        .formats
+        .iter()
+        .map(|format| format.format.as_str())
+        .collect();
+    eprintln!(
+        "'{}': formats {}, {} audio IO layout(s), {} parameter(s) ({} automatable), {}, {} \
+         samples of latency",
+        capabilities.name,
+        formats.join(", "),
+        capabilities.audio_io_layouts.len(),
+        capabilities.param_count,
+        capabilities.automatable_param_count,
+        if capabilities.has_editor {
+            "with an editor"
+        } else {
+            "without an editor"
+        },
+        capabilities.latency_samples.unwrap_or(0)
+    );
+
+    Outcome::Passed
+}
+
+/// Run an external validator. Returns `None` if the validator is not installed, or whether the
+/// validation succeeded otherwise.
+fn run_validator(mut command: Command) -> Result<Option<bool>> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    match command.status() {
+        Ok(status) => Ok(Some(status.success())),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("Could not run '{program}'")),
+    }
+}
+
+/// Load a CLAP plugin library, and instantiate, activate, and process a single block of silence
+/// with every plugin it contains.
+///
+/// # Safety
+///
+/// This runs arbitrary code from the library.
+unsafe fn clap_smoke_test(library_path: &Path) -> Result<()> {
+    let library = Library::new(library_path)
+        .with_context(|| format!("Could not load '{}'", library_path.display()))?;
+    let entry: *const clap_plugin_entry = *library
+        .get::<*const clap_plugin_entry>(b"clap_entry\0")
+        .context("The library does not export 'clap_entry'")?;
+    // The plugin may have spawned threads that are still running after `deinit()`, so unloading
+    // the library again could cause crashes
+    std::mem::forget(library);
+
+    let entry = &*entry;
+    let library_path_cstr = CString::new(library_path.to_string_lossy().as_bytes())
+        .context("The library path contains null bytes")?;
+    if !required(entry.init, "clap_plugin_entry::init()")?(library_path_cstr.as_ptr()) {
+        anyhow::bail!("'clap_plugin_entry::init()' returned false");
+    }
+
+    let result = clap_smoke_test_factory(entry);
+    required(entry.deinit, "clap_plugin_entry::deinit()")?();
+
+    result
+}
+
+unsafe fn clap_smoke_test_factory(entry: &clap_plugin_entry) -> Result<()> {
+    let factory = required(entry.get_factory, "clap_plugin_entry::get_factory()")?(
+        CLAP_PLUGIN_FACTORY_ID.as_ptr(),
+    ) as *const clap_plugin_factory;
+    if factory.is_null() {
+        anyhow::bail!("The library does not have a plugin factory");
+    }
+
+    let num_plugins = required(
+        (*factory).get_plugin_count,
+        "clap_plugin_factory::get_plugin_count()",
+    )?(factory);
+    if num_plugins == 0 {
+        anyhow::bail!("The plugin factory does not contain any plugins");
+    }
+
+    for plugin_idx in 0..num_plugins {
+        let descriptor = required(
+            (*factory).get_plugin_descriptor,
+            "clap_plugin_factory::get_plugin_descriptor()",
+        )?(factory, plugin_idx);
+        if descriptor.is_null() || (*descriptor).id.is_null() {
+            anyhow::bail!("Missing plugin descriptor for plugin {plugin_idx}");
+        }
+
+        let plugin_id = CStr::from_ptr((*descriptor).id);
+        clap_smoke_test_plugin(factory, plugin_id)
+            .with_context(|| format!("Smoke test failed for '{}'", plugin_id.to_string_lossy()))?;
+    }
+
+    Ok(())
+}
+
+unsafe fn clap_smoke_test_plugin(
+    factory: *const clap_plugin_factory,
+    plugin_id: &CStr,
+) -> Result<()> {
+    let host = clap_host {
+        clap_version: CLAP_VERSION,
+        host_data: std::ptr::null_mut(),
+        name: c"NIH-plug smoke test".as_ptr(),
+        vendor: c"NIH-plug".as_ptr(),
+        url: c"https://github.com/robbert-vdh/nih-plug".as_ptr(),
+        version: c"0.1.0".as_ptr(),
+        get_extension: Some(host_get_extension),
+        request_restart: Some(host_request),
+        request_process: Some(host_request),
+        request_callback: Some(host_request),
+    };
+
+    let plugin = required(
+        (*factory).create_plugin,
+        "clap_plugin_factory::create_plugin()",
+    )?(factory, &host, plugin_id.as_ptr());
+    if plugin.is_null() {
+        anyhow::bail!("'clap_plugin_factory::create_plugin()' returned a null pointer");
+    }
+
+    let result = if required((*plugin).init, "clap_plugin::init()")?(plugin) {
+        clap_smoke_test_initialized_plugin(plugin)
+    } else {
+        Err(anyhow::anyhow!("'clap_plugin::init()' returned false"))
+    };
+    required((*plugin).destroy, "clap_plugin::destroy()")?(plugin);
+
+    result
+}
+
+unsafe fn clap_smoke_test_initialized_plugin(plugin: *const clap_plugin) -> Result<()> {
+    let get_extension = required((*plugin).get_extension, "clap_plugin::get_extension()")?;
+
+    let params = get_extension(plugin, CLAP_EXT_PARAMS.as_ptr()) as *const clap_plugin_params;
+    if !params.is_null() {
+        let num_params = required((*params).count, "clap_plugin_params::count()")?(plugin);
+        for param_idx in 0..num_params {
+            let mut info: clap_param_info = std::mem::zeroed();
+            if !required((*params).get_info, "clap_plugin_params::get_info()")?(
+                plugin, param_idx, &mut info,
+            ) {
+                anyhow::bail!("Could not query parameter {param_idx}");
+            }
+
+            let mut value = 0.0;
+            if !required((*params).get_value, "clap_plugin_params::get_value()")?(
+                plugin, info.id, &mut value,
+            ) {
+                anyhow::bail!("Could not query the value of parameter {}", info.id);
+            }
+            if !value.is_finite() || value < info.min_value || value > info.max_value {
+                anyhow::bail!(
+                    "Parameter {} has value {value}, which is outside of its range [{}, {}]",
+                    info.id,
+                    info.min_value,
+                    info.max_value
+                );
+            }
+        }
+    }
+
+    // The plugin's audio ports determine the buffers passed to the process function
+    let audio_ports =
+        get_extension(plugin, CLAP_EXT_AUDIO_PORTS.as_ptr()) as *const clap_plugin_audio_ports;
+    let num_channels = |is_input: bool| -> Result<Vec<u32>> {
+        if audio_ports.is_null() {
+            return Ok(Vec::new());
+        }
+
+        let num_ports =
+            required((*audio_ports).count, "clap_plugin_audio_ports::count()")?(plugin, is_input);
+        (0..num_ports)
+            .map(|port_idx| {
+                let mut info: clap_audio_port_info = std::mem::zeroed();
+                if required((*audio_ports).get, "clap_plugin_audio_ports::get()")?(
+                    plugin, port_idx, is_input, &mut info,
+                ) {
+                    Ok(info.channel_count)
+                } else {
+                    Err(anyhow::anyhow!("Could not query audio port {port_idx}"))
+                }
+            })
+            .collect()
+    };
+    let mut inputs = AudioBuffers::new(&num_channels(true)?);
+    let mut outputs = AudioBuffers::new(&num_channels(false)?);
+
+    if !required((*plugin).activate, "clap_plugin::activate()")?(
+        plugin,
+        SMOKE_TEST_SAMPLE_RATE,
+        1,
+        SMOKE_TEST_BLOCK_SIZE,
+    ) {
+        anyhow::bail!("'clap_plugin::activate()' returned false");
+    }
+    if !required(
+        (*plugin).start_processing,
+        "clap_plugin::start_processing()",
+    )?(plugin)
+    {
+        required((*plugin).deactivate, "clap_plugin::deactivate()")?(plugin);
+        anyhow::bail!("'clap_plugin::start_processing()' returned false");
+    }
+
+    let in_events = clap_input_events {
+        ctx: std::ptr::null_mut(),
+        size: Some(input_events_size),
+        get: Some(input_events_get),
+    };
+    let out_events = clap_output_events {
+        ctx: std::ptr::null_mut(),
+        try_push: Some(output_events_try_push),
+    };
+    let process = clap_process {
+        steady_time: 0,
+        frames_count: SMOKE_TEST_BLOCK_SIZE,
+        transport: std::ptr::null(),
+        audio_inputs: inputs.clap_buffers.as_ptr(),
+        audio_outputs: outputs.clap_buffers.as_mut_ptr(),
+        audio_inputs_count: inputs.clap_buffers.len() as u32,
+        audio_outputs_count: outputs.clap_buffers.len() as u32,
+        in_events: &in_events,
+        out_events: &out_events,
+    };
+    let status = required((*plugin).process, "clap_plugin::process()")?(plugin, &process);
+
+    required((*plugin).stop_processing, "clap_plugin::stop_processing()")?(plugin);
+    required((*plugin).deactivate, "clap_plugin::deactivate()")?(plugin);
+
+    if status == CLAP_PROCESS_ERROR {
+        anyhow::bail!("'clap_plugin::process()' returned an error");
+    }
+    if !outputs.is_finite() {
+        anyhow::bail!("The plugin produced NaN or infinite output samples from silence");
+    }
+
+    Ok(())
+}
+
+/// Audio buffers for every port of one direction, along with the CLAP buffer structs pointing to
+/// them.
+struct AudioBuffers {
+    /// The sample data, indexed by `[port][channel][sample]`. This is only accessed through
+    /// `channel_pointers`.
+    _samples: Vec<Vec<Vec<f32>>>,
+    /// Pointers to the channels in `samples`, one vector per port.
+    channel_pointers: Vec<Vec<*mut f32>>,
+    clap_buffers: Vec<clap_audio_buffer>,
+}
+
+impl AudioBuffers {
+    fn new(num_channels: &[u32]) -> Self {
+        let mut samples: Vec<Vec<Vec<f32>>> = num_channels
+            .iter()
+            .map(|&num_channels| {
+                vec![vec![0.0; SMOKE_TEST_BLOCK_SIZE as usize]; num_channels as usize]
+            })
+            .collect();
+        let mut channel_pointers: Vec<Vec<*mut f32>> = samples
+            .iter_mut()
+            .map(|port| {
+                port.iter_mut()
+                    .map(|channel| channel.as_mut_ptr())
+                    .collect()
+            })
+            .collect();
+        let clap_buffers = channel_pointers
+            .iter_mut()
+            .map(|pointers| clap_audio_buffer {
+                data32: pointers.as_mut_ptr(),
+                data64: std::ptr::null_mut(),
+                channel_count: pointers.len() as u32,
+                latency: 0,
+                constant_mask: 0,
+            })
+            .collect();
+
+        Self {
+            _samples: samples,
+            channel_pointers,
+            clap_buffers,
+        }
+    }
+
+    /// Whether all output samples are finite.
+    ///
+    /// # Safety
+    ///
+    /// Must not be called while the plugin is processing.
+    unsafe fn is_finite(&self) -> bool {
+        self.channel_pointers.iter().flatten().all(|&channel| {
+            std::slice::from_raw_parts(channel, SMOKE_TEST_BLOCK_SIZE as usize)
+                .iter()
+                .all(|sample| sample.is_finite())
+        })
+    }
+}
+
+/// Turn a missing function pointer into an error.
+fn required<T>(function: Option<T>, name: &str) -> Result<T> {
+    function.with_context(|| format!("The plugin does not implement '{name}'"))
+}
+
+unsafe extern "C" fn host_get_extension(
+    _host: *const clap_host,
+    _extension_id: *const c_char,
+) -> *const c_void {
+    std::ptr::null()
+}
+
+unsafe extern "C" fn host_request(_host: *const clap_host) {}
+
+unsafe extern "C" fn input_events_size(_list: *const clap_input_events) -> u32 {
+    0
+}
+
+unsafe extern "C" fn input_events_get(
+    _list: *const clap_input_events,
+    _index: u32,
+) -> *const clap_event_header {
+    std::ptr::null()
+}
+
+unsafe extern "C" fn output_events_try_push(
+    _list: *const clap_output_events,
+    _event: *const clap_event_header,
+) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skipped_validations_do_not_pass() {
+        let bundle_path = Path::new("Plugin.clap");
+        assert!(report(bundle_path, Outcome::Passed));
+        assert!(!report(bundle_path, Outcome::Failed(String::from("error"))));
+        assert!(!report(
+            bundle_path,
+            Outcome::Skipped(String::from("reason"))
+        ));
+    }
+
+    #[test]
+    fn missing_validators_fall_back_to_smoke_tests() {
+        // The smoke tests fail here because the libraries don't exist, instead of the validation
+        // being skipped
+        let validator = "nih-plug-nonexistent-validator";
+        let missing_library = Path::new("nonexistent-plugin-library");
+        assert!(matches!(
+            validate_clap(validator, Path::new("Plugin.clap"), missing_library),
+            Outcome::Failed(reason) if reason.contains("Could not load")
+        ));
+        assert!(matches!(
+            validate_vst3(validator, Path::new("Plugin.vst3"), missing_library),
+            Outcome::Failed(reason) if reason.contains("Could not load")
+        ));
+    }
+}
@@ -0,0 +1,485 @@
+//! A VST3 smoke test for when the VST3 SDK's validator is not installed. The xtask does not depend
+//! on the VST3 bindings, so this defines the small subset of the VST3 interfaces needed to
+//! instantiate a plugin, query its parameters, and process a single block of audio.
+
+// The interface definitions also contain the functions and fields the smoke test doesn't use so
+// their layouts match the VST3 SDK's
+#![allow(dead_code)]
+
+use anyhow::{Context, Result};
+use libloading::Library;
+use std::ffi::{c_char, c_void, CStr};
+use std::marker::PhantomData;
+use std::path::Path;
+
+use super::{AudioBuffers, SMOKE_TEST_BLOCK_SIZE, SMOKE_TEST_SAMPLE_RATE};
+
+type TResult = i32;
+type Tuid = [u8; 16];
+type TBool = u8;
+
+const K_RESULT_OK: TResult = 0;
+
+const K_AUDIO: i32 = 0;
+const K_INPUT: i32 = 0;
+const K_OUTPUT: i32 = 1;
+const K_REALTIME: i32 = 0;
+const K_SAMPLE_32: i32 = 0;
+
+/// The category of the factory classes that implement `IComponent`.
+const AUDIO_MODULE_CLASS: &str = "Audio Module Class";
+
+const ICOMPONENT_IID: Tuid = tuid(0xE831FF31, 0xF2D54301, 0x928EBBEE, 0x25697802);
+const IAUDIO_PROCESSOR_IID: Tuid = tuid(0x42043F99, 0xB7DA453C, 0xA569E79D, 0x9AAEC33D);
+const IEDIT_CONTROLLER_IID: Tuid = tuid(0xDCD7BBE3, 0x7742448D, 0xA874AACC, 0x979C759E);
+
+/// The functions every VST3 module exports for initializing and deinitializing the module. These
+/// are named differently on every platform.
+#[cfg(windows)]
+const MODULE_ENTRY_EXIT: (&[u8], &[u8]) = (b"InitDll\0", b"ExitDll\0");
+#[cfg(target_os = "macos")]
+const MODULE_ENTRY_EXIT: (&[u8], &[u8]) = (b"bundleEntry\0", b"bundleExit\0");
+#[cfg(not(any(windows, target_os = "macos")))]
+const MODULE_ENTRY_EXIT: (&[u8], &[u8]) = (b"ModuleEntry\0", b"ModuleExit\0");
+
+#[cfg(windows)]
+type ModuleEntry = unsafe extern "system" fn() -> bool;
+#[cfg(not(windows))]
+type ModuleEntry = unsafe extern "C" fn(*mut c_void) -> bool;
+#[cfg(windows)]
+type ModuleExit = unsafe extern "system" fn() -> bool;
+#[cfg(not(windows))]
+type ModuleExit = unsafe extern "C" fn() -> bool;
+
+/// Build an interface ID from the four integers used in the VST3 SDK's interface declarations.
+/// Like in the SDK, these use COM's byte order on Windows and are big-endian everywhere else.
+const fn tuid(l1: u32, l2: u32, l3: u32, l4: u32) -> Tuid {
+    let [a0, a1, a2, a3] = l1.to_be_bytes();
+    let [b0, b1, b2, b3] = l2.to_be_bytes();
+    let [c0, c1, c2, c3] = l3.to_be_bytes();
+    let [d0, d1, d2, d3] = l4.to_be_bytes();
+    if cfg!(windows) {
+        [
+            a3, a2, a1, a0, b1, b0, b3, b2, c0, c1, c2, c3, d0, d1, d2, d3,
+        ]
+    } else {
+        [
+            a0, a1, a2, a3, b0, b1, b2, b3, c0, c1, c2, c3, d0, d1, d2, d3,
+        ]
+    }
+}
+
+#[repr(C)]
+struct FUnknownVtbl {
+    query_interface:
+        unsafe extern "system" fn(*mut c_void, *const Tuid, *mut *mut c_void) -> TResult,
+    add_ref: unsafe extern "system" fn(*mut c_void) -> u32,
+    release: unsafe extern "system" fn(*mut c_void) -> u32,
+}
+
+#[repr(C)]
+struct IPluginFactoryVtbl {
+    unknown: FUnknownVtbl,
+    get_factory_info: unsafe extern "system" fn(*mut c_void, *mut c_void) -> TResult,
+    count_classes: unsafe extern "system" fn(*mut c_void) -> i32,
+    get_class_info: unsafe extern "system" fn(*mut c_void, i32, *mut PClassInfo) -> TResult,
+    create_instance: unsafe extern "system" fn(
+        *mut c_void,
+        *const c_char,
+        *const c_char,
+        *mut *mut c_void,
+    ) -> TResult,
+}
+
+#[repr(C)]
+struct IComponentVtbl {
+    unknown: FUnknownVtbl,
+    initialize: unsafe extern "system" fn(*mut c_void, *mut c_void) -> TResult,
+    terminate: unsafe extern "system" fn(*mut c_void) -> TResult,
+    get_controller_class_id: unsafe extern "system" fn(*mut c_void, *mut Tuid) -> TResult,
+    set_io_mode: unsafe extern "system" fn(*mut c_void, i32) -> TResult,
+    get_bus_count: unsafe extern "system" fn(*mut c_void, i32, i32) -> i32,
+    get_bus_info: unsafe extern "system" fn(*mut c_void, i32, i32, i32, *mut BusInfo) -> TResult,
+    get_routing_info: unsafe extern "system" fn(*mut c_void, *mut c_void, *mut c_void) -> TResult,
+    activate_bus: unsafe extern "system" fn(*mut c_void, i32, i32, i32, TBool) -> TResult,
+    set_active: unsafe extern "system" fn(*mut c_void, TBool) -> TResult,
+}
+
+#[repr(C)]
+struct IAudioProcessorVtbl {
+    unknown: FUnknownVtbl,
+    set_bus_arrangements:
+        unsafe extern "system" fn(*mut c_void, *mut u64, i32, *mut u64, i32) -> TResult,
+    get_bus_arrangement: unsafe extern "system" fn(*mut c_void, i32, i32, *mut u64) -> TResult,
+    can_process_sample_size: unsafe extern "system" fn(*mut c_void, i32) -> TResult,
+    get_latency_samples: unsafe extern "system" fn(*mut c_void) -> u32,
+    setup_processing: unsafe extern "system" fn(*mut c_void, *mut ProcessSetup) -> TResult,
+    set_processing: unsafe extern "system" fn(*mut c_void, TBool) -> TResult,
+    process: unsafe extern "system" fn(*mut c_void, *mut ProcessData) -> TResult,
+}
+
+#[repr(C)]
+struct IEditControllerVtbl {
+    unknown: FUnknownVtbl,
+    initialize: unsafe extern "system" fn(*mut c_void, *mut c_void) -> TResult,
+    terminate: unsafe extern "system" fn(*mut c_void) -> TResult,
+    set_component_state: unsafe extern "system" fn(*mut c_void, *mut c_void) -> TResult,
+    set_state: unsafe extern "system" fn(*mut c_void, *mut c_void) -> TResult,
+    get_state: unsafe extern "system" fn(*mut c_void, *mut c_void) -> TResult,
+    get_parameter_count: unsafe extern "system" fn(*mut c_void) -> i32,
+    get_parameter_info: unsafe extern "system" fn(*mut c_void, i32, *mut ParameterInfo) -> TResult,
+    get_param_string_by_value:
+        unsafe extern "system" fn(*mut c_void, u32, f64, *mut u16) -> TResult,
+    get_param_value_by_string:
+        unsafe extern "system" fn(*mut c_void, u32, *const u16, *mut f64) -> TResult,
+    normalized_param_to_plain: unsafe extern "system" fn(*mut c_void, u32, f64) -> f64,
+    plain_param_to_normalized: unsafe extern "system" fn(*mut c_void, u32, f64) -> f64,
+    get_param_normalized: unsafe extern "system" fn(*mut c_void, u32) -> f64,
+}
+
+#[repr(C)]
+struct PClassInfo {
+    cid: Tuid,
+    cardinality: i32,
+    category: [c_char; 32],
+    name: [c_char; 64],
+}
+
+#[repr(C)]
+struct BusInfo {
+    media_type: i32,
+    direction: i32,
+    channel_count: i32,
+    name: [u16; 128],
+    bus_type: i32,
+    flags: u32,
+}
+
+#[repr(C)]
+struct ParameterInfo {
+    id: u32,
+    title: [u16; 128],
+    short_title: [u16; 128],
+    units: [u16; 128],
+    step_count: i32,
+    default_normalized_value: f64,
+    unit_id: i32,
+    flags: i32,
+}
+
+#[repr(C)]
+struct ProcessSetup {
+    process_mode: i32,
+    symbolic_sample_size: i32,
+    max_samples_per_block: i32,
+    sample_rate: f64,
+}
+
+#[repr(C)]
+struct AudioBusBuffers {
+    num_channels: i32,
+    silence_flags: u64,
+    channel_buffers_32: *mut *mut f32,
+}
+
+#[repr(C)]
+struct ProcessData {
+    process_mode: i32,
+    symbolic_sample_size: i32,
+    num_samples: i32,
+    num_inputs: i32,
+    num_outputs: i32,
+    inputs: *mut AudioBusBuffers,
+    outputs: *mut AudioBusBuffers,
+    input_parameter_changes: *mut c_void,
+    output_parameter_changes: *mut c_void,
+    input_events: *mut c_void,
+    output_events: *mut c_void,
+    process_context: *mut c_void,
+}
+
+/// An owned reference to a VST3 object, using `V` as the object's vtable. The reference is
+/// released when this object is dropped.
+struct ComPtr<V> {
+    ptr: *mut c_void,
+    _vtbl: PhantomData<V>,
+}
+
+impl<V> ComPtr<V> {
+    /// Take ownership of a reference returned by the plugin.
+    unsafe fn from_raw(ptr: *mut c_void) -> Option<Self> {
+        if ptr.is_null() {
+            None
+        } else {
+            Some(Self {
+                ptr,
+                _vtbl: PhantomData,
+            })
+        }
+    }
+
+    unsafe fn vtbl(&self) -> &V {
+        &**(self.ptr as *const *const V)
+    }
+
+    /// Query the object for another interface. Returns `None` if the object doesn't implement it.
+    unsafe fn query<W>(&self, iid: &Tuid) -> Option<ComPtr<W>> {
+        let unknown = &**(self.ptr as *const *const FUnknownVtbl);
+        let mut obj = std::ptr::null_mut();
+        if (unknown.query_interface)(self.ptr, iid, &mut obj) == K_RESULT_OK {
+            ComPtr::from_raw(obj)
+        } else {
+            None
+        }
+    }
+}
+
+impl<V> Drop for ComPtr<V> {
+    fn drop(&mut self) {
+        // Every vtable starts with `FUnknown`'s functions
+        unsafe {
+            let unknown = &**(self.ptr as *const *const FUnknownVtbl);
+            (unknown.release)(self.ptr);
+        }
+    }
+}
+
+/// Load a VST3 plugin library, and instantiate, activate, and process a single block of silence
+/// with every audio module class it contains. This requires the plugin's component to also
+/// implement the edit controller, like NIH-plug's plugins do.
+///
+/// # Safety
+///
+/// This runs arbitrary code from the library.
+pub(super) unsafe fn vst3_smoke_test(library_path: &Path) -> Result<()> {
+    let library = Library::new(library_path)
+        .with_context(|| format!("Could not load '{}'", library_path.display()))?;
+    let (entry_name, exit_name) = MODULE_ENTRY_EXIT;
+    let module_entry = *library
+        .get::<ModuleEntry>(entry_name)
+        .context("The library does not export the VST3 module entry point")?;
+    let module_exit = *library
+        .get::<ModuleExit>(exit_name)
+        .context("The library does not export the VST3 module exit point")?;
+    let get_plugin_factory = *library
+        .get::<unsafe extern "system" fn() -> *mut c_void>(b"GetPluginFactory\0")
+        .context("The library does not export 'GetPluginFactory'")?;
+    // Just like with the CLAP smoke test, the plugin may still have threads running after the
+    // module has been deinitialized
+    std::mem::forget(library);
+
+    #[cfg(windows)]
+    let initialized = module_entry();
+    #[cfg(not(windows))]
+    let initialized = module_entry(std::ptr::null_mut());
+    if !initialized {
+        anyhow::bail!("The VST3 module entry point returned false");
+    }
+
+    let result = match ComPtr::<IPluginFactoryVtbl>::from_raw(get_plugin_factory()) {
+        Some(factory) => vst3_smoke_test_factory(&factory),
+        None => Err(anyhow::anyhow!(
+            "'GetPluginFactory()' returned a null pointer"
+        )),
+    };
+    module_exit();
+
+    result
+}
+
+unsafe fn vst3_smoke_test_factory(factory: &ComPtr<IPluginFactoryVtbl>) -> Result<()> {
+    let num_classes = (factory.vtbl().count_classes)(factory.ptr);
+    let mut num_tested = 0;
+    for class_idx in 0..num_classes {
+        let mut info: PClassInfo = std::mem::zeroed();
+        if (factory.vtbl().get_class_info)(factory.ptr, class_idx, &mut info) != K_RESULT_OK {
+            anyhow::bail!("Could not query factory class {class_idx}");
+        }
+        if c_string(&info.category).as_deref() != Some(AUDIO_MODULE_CLASS) {
+            continue;
+        }
+
+        let name = c_string(&info.name).unwrap_or_default();
+        vst3_smoke_test_component(factory, &info.cid)
+            .with_context(|| format!("Smoke test failed for '{name}'"))?;
+        num_tested += 1;
+    }
+
+    if num_tested == 0 {
+        anyhow::bail!("The plugin factory does not contain any audio module classes");
+    }
+
+    Ok(())
+}
+
+unsafe fn vst3_smoke_test_component(
+    factory: &ComPtr<IPluginFactoryVtbl>,
+    cid: &Tuid,
+) -> Result<()> {
+    let mut obj = std::ptr::null_mut();
+    let result = (factory.vtbl().create_instance)(
+        factory.ptr,
+        cid.as_ptr() as *const c_char,
+        ICOMPONENT_IID.as_ptr() as *const c_char,
+        &mut obj,
+    );
+    let component = match ComPtr::<IComponentVtbl>::from_raw(obj) {
+        Some(component) if result == K_RESULT_OK => component,
+        _ => anyhow::bail!("'IPluginFactory::createInstance()' did not return an 'IComponent'"),
+    };
+
+    if (component.vtbl().initialize)(component.ptr, std::ptr::null_mut()) != K_RESULT_OK {
+        anyhow::bail!("'IComponent::initialize()' failed");
+    }
+    let result = vst3_smoke_test_initialized_component(&component);
+    (component.vtbl().terminate)(component.ptr);
+
+    result
+}
+
+unsafe fn vst3_smoke_test_initialized_component(component: &ComPtr<IComponentVtbl>) -> Result<()> {
+    let controller = component
+        .query::<IEditControllerVtbl>(&IEDIT_CONTROLLER_IID)
+        .context("The component does not implement 'IEditController'")?;
+    let num_params = (controller.vtbl().get_parameter_count)(controller.ptr);
+    for param_idx in 0..num_params {
+        let mut info: ParameterInfo = std::mem::zeroed();
+        if (controller.vtbl().get_parameter_info)(controller.ptr, param_idx, &mut info)
+            != K_RESULT_OK
+        {
+            anyhow::bail!("Could not query parameter {param_idx}");
+        }
+
+        let value = (controller.vtbl().get_param_normalized)(controller.ptr, info.id);
+        if !(0.0..=1.0).contains(&value) {
+            anyhow::bail!(
+                "Parameter {} has normalized value {value}, which is outside of [0, 1]",
+                info.id
+            );
+        }
+    }
+
+    // The component's audio buses determine the buffers passed to the process function
+    let num_channels = |direction: i32| -> Result<Vec<u32>> {
+        let num_buses = (component.vtbl().get_bus_count)(component.ptr, K_AUDIO, direction);
+        (0..num_buses)
+            .map(|bus_idx| {
+                let mut info: BusInfo = std::mem::zeroed();
+                if (component.vtbl().get_bus_info)(
+                    component.ptr,
+                    K_AUDIO,
+                    direction,
+                    bus_idx,
+                    &mut info,
+                ) != K_RESULT_OK
+                {
+                    anyhow::bail!("Could not query audio bus {bus_idx}");
+                }
+                (component.vtbl().activate_bus)(component.ptr, K_AUDIO, direction, bus_idx, 1);
+
+                Ok(info.channel_count.max(0) as u32)
+            })
+            .collect()
+    };
+    let mut inputs = AudioBuffers::new(&num_channels(K_INPUT)?);
+    let mut outputs = AudioBuffers::new(&num_channels(K_OUTPUT)?);
+
+    let processor = component
+        .query::<IAudioProcessorVtbl>(&IAUDIO_PROCESSOR_IID)
+        .context("The component does not implement 'IAudioProcessor'")?;
+    let mut setup = ProcessSetup {
+        process_mode: K_REALTIME,
+        symbolic_sample_size: K_SAMPLE_32,
+        max_samples_per_block: SMOKE_TEST_BLOCK_SIZE as i32,
+        sample_rate: SMOKE_TEST_SAMPLE_RATE,
+    };
+    if (processor.vtbl().setup_processing)(processor.ptr, &mut setup) != K_RESULT_OK {
+        anyhow::bail!("'IAudioProcessor::setupProcessing()' failed");
+    }
+    if (component.vtbl().set_active)(component.ptr, 1) != K_RESULT_OK {
+        anyhow::bail!("'IComponent::setActive()' failed");
+    }
+    // Plugins are allowed to not implement this, so the result is ignored
+    (processor.vtbl().set_processing)(processor.ptr, 1);
+
+    let bus_buffers = |buffers: &mut AudioBuffers| -> Vec<AudioBusBuffers> {
+        buffers
+            .channel_pointers
+            .iter_mut()
+            .map(|pointers| AudioBusBuffers {
+                num_channels: pointers.len() as i32,
+                silence_flags: 0,
+                channel_buffers_32: pointers.as_mut_ptr(),
+            })
+            .collect()
+    };
+    let mut input_buses = bus_buffers(&mut inputs);
+    let mut output_buses = bus_buffers(&mut outputs);
+    let mut data = ProcessData {
+        process_mode: K_REALTIME,
+        symbolic_sample_size: K_SAMPLE_32,
+        num_samples: SMOKE_TEST_BLOCK_SIZE as i32,
+        num_inputs: input_buses.len() as i32,
+        num_outputs: output_buses.len() as i32,
+        inputs: input_buses.as_mut_ptr(),
+        outputs: output_buses.as_mut_ptr(),
+        input_parameter_changes: std::ptr::null_mut(),
+        output_parameter_changes: std::ptr::null_mut(),
+        input_events: std::ptr::null_mut(),
+        output_events: std::ptr::null_mut(),
+        process_context: std::ptr::null_mut(),
+    };
+    let result = (processor.vtbl().process)(processor.ptr, &mut data);
+
+    (processor.vtbl().set_processing)(processor.ptr, 0);
+    (component.vtbl().set_active)(component.ptr, 0);
+
+    if result != K_RESULT_OK {
+        anyhow::bail!("'IAudioProcessor::process()' returned an error");
+    }
+    if !outputs.is_finite() {
+        anyhow::bail!("The plugin produced NaN or infinite output samples from silence");
+    }
+
+    Ok(())
+}
+
+/// Read a null terminated string from a fixed size buffer. Returns `None` if the string is not
+/// null terminated or not valid UTF-8.
+fn c_string(chars: &[c_char]) -> Option<String> {
+    // SAFETY: `c_char` is either `i8` or `u8`, so this reinterprets the buffer's bytes
+    let bytes = unsafe { std::slice::from_raw_parts(chars.as_ptr() as *const u8, chars.len()) };
+    CStr::from_bytes_until_nul(bytes)
+        .ok()?
+        .to_str()
+        .ok()
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interface_ids() {
+        let iid = tuid(0x01020304, 0x05060708, 0x090A0B0C, 0x0D0E0F10);
+        if cfg!(windows) {
+            assert_eq!(iid, [4, 3, 2, 1, 6, 5, 8, 7, 9, 10, 11, 12, 13, 14, 15, 16]);
+        } else {
+            assert_eq!(iid, [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16]);
+        }
+    }
+
+    #[test]
+    fn fixed_size_strings() {
+        let mut chars = [0 as c_char; 8];
+        for (c, byte) in chars.iter_mut().zip(b"Plugin") {
+            *c = *byte as c_char;
+        }
+        assert_eq!(c_string(&chars).as_deref(), Some("Plugin"));
+
+        // Without a null terminator the string is rejected
+        let chars = [b'a' as c_char; 4];
+        assert_eq!(c_string(&chars), None);
+    }
+}
@@ -174,6 +174,9 @@ impl Plugin for Crossover {
         // Two to five of these ports will be used at a time
         aux_output_ports: &[new_nonzero_u32(NUM_CHANNELS); 5],
 
+        main_input_channel_layout: None,
+        main_output_channel_layout: None,
+
         names: PortNames {
             layout: Some("Up to five bands"),
 
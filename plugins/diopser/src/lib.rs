@@ -22,6 +22,7 @@ compile_error!("Compiling without SIMD support is currently not supported");
 use atomic_float::AtomicF32;
 use editor::SafeModeClamper;
 use nih_plug::prelude::*;
+use nih_plug::util::filter;
 use std::simd::f32x2;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
@@ -30,7 +31,6 @@ use crate::params::{DiopserParams, SpreadStyle};
 use crate::spectrum::{SpectrumInput, SpectrumOutput};
 
 mod editor;
-mod filter;
 mod params;
 mod spectrum;
 
@@ -70,6 +70,11 @@ pub struct Diopser {
     spectrum_input: SpectrumInput,
     /// This can be cloned and moved into the editor.
     spectrum_output: Arc<Mutex<SpectrumOutput>>,
+    /// Set by the editor's open and close hooks. The spectrum is only computed while this is set.
+    spectrum_enabled: Arc<AtomicBool>,
+    /// Whether `spectrum_enabled` was set during the last process call. When the editor is opened
+    /// again, the spectrum from when it was last open is cleared first.
+    spectrum_was_enabled: bool,
 }
 
 impl Default for Diopser {
@@ -98,6 +103,8 @@ impl Default for Diopser {
 
             spectrum_input,
             spectrum_output: Arc::new(Mutex::new(spectrum_output)),
+            spectrum_enabled: Arc::new(AtomicBool::new(false)),
+            spectrum_was_enabled: false,
         }
     }
 }
@@ -127,6 +134,9 @@ impl Plugin for Diopser {
     }
 
     fn editor(&mut self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
+        let spectrum_enabled_on_open = self.spectrum_enabled.clone();
+        let spectrum_enabled_on_close = self.spectrum_enabled.clone();
+
         editor::create(
             editor::Data {
                 params: self.params.clone(),
@@ -137,6 +147,14 @@ impl Plugin for Diopser {
             },
             self.params.editor_state.clone(),
         )
+        .map(|editor| {
+            // The spectrum is only needed while the editor is open
+            nih_plug::editor::with_lifecycle_hooks(
+                editor,
+                move || spectrum_enabled_on_open.store(true, Ordering::Relaxed),
+                move || spectrum_enabled_on_close.store(false, Ordering::Relaxed),
+            )
+        })
     }
 
     fn filter_state(state: &mut PluginState) {
@@ -158,12 +176,16 @@ impl Plugin for Diopser {
         buffer_config: &BufferConfig,
         _context: &mut impl InitContext<Self>,
     ) -> bool {
-        self.sample_rate
-            .store(buffer_config.sample_rate, Ordering::Relaxed);
+        let old_sample_rate = self
+            .sample_rate
+            .swap(buffer_config.sample_rate, Ordering::Relaxed);
 
         // The spectrum is smoothed so it decays gradually
         self.spectrum_input
             .update_sample_rate(buffer_config.sample_rate);
+        if buffer_config.sample_rate != old_sample_rate {
+            self.spectrum_input.clear();
+        }
 
         true
     }
@@ -246,9 +268,15 @@ impl Plugin for Diopser {
         }
 
         // Compute a spectrum for the GUI if needed
-        if self.params.editor_state.is_open() {
+        let spectrum_enabled = self.spectrum_enabled.load(Ordering::Relaxed);
+        if spectrum_enabled {
+            if !self.spectrum_was_enabled {
+                self.spectrum_input.clear();
+            }
+
             self.spectrum_input.compute(buffer);
         }
+        self.spectrum_was_enabled = spectrum_enabled;
 
         ProcessStatus::Normal
     }
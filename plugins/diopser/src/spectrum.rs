@@ -104,6 +104,15 @@ impl SpectrumInput {
         self.smoothing_decay_weight = 0.25f64.powf(decay_samples.recip()) as f32
     }
 
+    /// Clear the spectrum and send the empty spectrum to the editor. Called in `initialize()` when
+    /// the sample rate changes, since the old spectrum's bins would otherwise be drawn at the wrong
+    /// frequencies until the next spectrum has been computed. This is also called when the editor
+    /// is opened again so it doesn't start with the spectrum from when it was last closed.
+    pub fn clear(&mut self) {
+        self.spectrum_result_buffer.fill(0.0);
+        self.triple_buffer_input.write(self.spectrum_result_buffer);
+    }
+
     /// Compute the spectrum for a buffer and send it to the corresponding output pair.
     pub fn compute(&mut self, buffer: &Buffer) {
         self.stft.process_analyze_only(
@@ -145,3 +154,23 @@ impl SpectrumInput {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clear_after_sample_rate_change() {
+        let (mut input, mut output) = SpectrumInput::new(2);
+        input.update_sample_rate(44100.0);
+        input.spectrum_result_buffer[10] = 1.0;
+        input
+            .triple_buffer_input
+            .write(input.spectrum_result_buffer);
+        assert_eq!(output.read()[10], 1.0);
+
+        input.update_sample_rate(96000.0);
+        input.clear();
+        assert!(output.read().iter().all(|&magnitude| magnitude == 0.0));
+    }
+}
@@ -130,6 +130,12 @@ impl Plugin for Gain {
             aux_input_ports: &[],
             aux_output_ports: &[],
 
+            // Surround plugins can declare their ports' speaker arrangements here, for instance
+            // `Some(ChannelLayout::Surround51)` for a 5.1 port. Ports with one or two channels are
+            // treated as mono and stereo ports if this is not set.
+            main_input_channel_layout: None,
+            main_output_channel_layout: None,
+
             // Individual ports and the layout as a whole can be named here. By default these names
             // are generated as needed. This layout will be called 'Stereo', while the other one is
             // given the name 'Mono' based no the number of input and output channels.
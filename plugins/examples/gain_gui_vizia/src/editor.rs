@@ -1,39 +1,109 @@
 use atomic_float::AtomicF32;
-use nih_plug::prelude::{util, Editor};
+use nih_plug::prelude::{util, Editor, GuiContext, ParamSetter, PluginState, SessionState};
 use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::vizia::vg;
 use nih_plug_vizia::widgets::*;
 use nih_plug_vizia::{assets, create_vizia_editor, ViziaState, ViziaTheming};
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Duration;
 
-use crate::GainParams;
+use crate::{Gain, GainParams};
+
+/// The input level in decibels at the left edge of the gain curve, and the lowest output level
+/// shown at the bottom of the gain curve.
+const CURVE_MIN_DB: f32 = -60.0;
+/// The highest output level shown at the top of the gain curve.
+const CURVE_MAX_DB: f32 = 30.0;
+/// The number of points the gain curve is made out of.
+const CURVE_NUM_POINTS: usize = 64;
+/// The key for the active tab in the [`SessionState`].
+const ACTIVE_TAB_KEY: &str = "active-tab";
 
 #[derive(Lens)]
 struct Data {
     params: Arc<GainParams>,
     peak_meter: Arc<AtomicF32>,
+    gui_context: Arc<dyn GuiContext>,
+    session_state: Arc<SessionState>,
+    /// The index of the tab that's currently shown. This is stored in `session_state` so the same
+    /// tab is shown when the editor is reopened, but it's not saved with the patch.
+    active_tab: usize,
+    /// Feedback for the last clipboard action.
+    status: String,
+}
+
+enum AppEvent {
+    /// Show the tab with this index.
+    SelectTab(usize),
+    /// Reset all parameters to their default values.
+    ResetParameters,
+    /// Copy the plugin's entire state to the clipboard.
+    CopyState,
+    /// Load a state previously copied with `CopyState`, possibly from another instance.
+    PasteState,
 }
 
-impl Model for Data {}
+impl Model for Data {
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|app_event, _| match app_event {
+            AppEvent::SelectTab(tab) => {
+                self.active_tab = *tab;
+                self.session_state.set(ACTIVE_TAB_KEY, *tab);
+            }
+            AppEvent::ResetParameters => ParamSetter::new(self.gui_context.as_ref())
+                .reset_parameters_to_default(self.params.as_ref()),
+            AppEvent::CopyState => {
+                let data = self.gui_context.get_state().to_clipboard_string::<Gain>();
+                self.status = match cx.set_clipboard(data) {
+                    Ok(()) => String::from("Copied the patch"),
+                    Err(err) => format!("Could not copy the patch: {err}"),
+                };
+            }
+            AppEvent::PasteState => {
+                let state = cx
+                    .get_clipboard()
+                    .map_err(|err| err.to_string())
+                    .and_then(|data| {
+                        PluginState::from_clipboard_string::<Gain>(&data)
+                            .map_err(|err| err.to_string())
+                    });
+
+                // The state is only loaded once it has been fully validated
+                self.status = match state {
+                    Ok(state) => {
+                        self.gui_context.set_state(state);
+                        String::from("Pasted the patch")
+                    }
+                    Err(err) => err,
+                };
+            }
+        });
+    }
+}
 
 // Makes sense to also define this here, makes it a bit easier to keep track of
 pub(crate) fn default_state() -> Arc<ViziaState> {
-    ViziaState::new(|| (200, 150))
+    ViziaState::new(|| (200, 300))
 }
 
 pub(crate) fn create(
     params: Arc<GainParams>,
     peak_meter: Arc<AtomicF32>,
+    session_state: Arc<SessionState>,
     editor_state: Arc<ViziaState>,
 ) -> Option<Box<dyn Editor>> {
-    create_vizia_editor(editor_state, ViziaTheming::Custom, move |cx, _| {
+    create_vizia_editor(editor_state, ViziaTheming::Custom, move |cx, context| {
         assets::register_noto_sans_light(cx);
         assets::register_noto_sans_thin(cx);
 
         Data {
             params: params.clone(),
             peak_meter: peak_meter.clone(),
+            gui_context: context,
+            active_tab: session_state.get_or_default(ACTIVE_TAB_KEY),
+            session_state: session_state.clone(),
+            status: String::new(),
         }
         .build(cx);
 
@@ -46,17 +116,27 @@ pub(crate) fn create(
                 .child_top(Stretch(1.0))
                 .child_bottom(Pixels(0.0));
 
-            Label::new(cx, "Gain");
-            ParamSlider::new(cx, Data::params, |params| &params.gain);
-
-            PeakMeter::new(
-                cx,
-                Data::peak_meter
-                    .map(|peak_meter| util::gain_to_db(peak_meter.load(Ordering::Relaxed))),
-                Some(Duration::from_millis(600)),
-            )
-            // This is how adding padding works in vizia
-            .top(Pixels(10.0));
+            HStack::new(cx, |cx| {
+                for (tab, name) in ["Gain", "Patch"].into_iter().enumerate() {
+                    Button::new(cx, |cx| Label::new(cx, name))
+                        .checked(Data::active_tab.map(move |active_tab| *active_tab == tab))
+                        .on_press(move |cx| cx.emit(AppEvent::SelectTab(tab)));
+                }
+            })
+            .col_between(Pixels(5.0))
+            .height(Auto)
+            .width(Auto)
+            .bottom(Pixels(10.0));
+
+            // The active tab is restored from the session state when the editor is reopened
+            let params = params.clone();
+            Binding::new(cx, Data::active_tab, move |cx, active_tab| {
+                if active_tab.get(cx) == 0 {
+                    build_gain_tab(cx, params.clone());
+                } else {
+                    build_patch_tab(cx);
+                }
+            });
         })
         .row_between(Pixels(0.0))
         .child_left(Stretch(1.0))
@@ -65,3 +145,140 @@ pub(crate) fn create(
         ResizeHandle::new(cx);
     })
 }
+
+fn build_gain_tab(cx: &mut Context, params: Arc<GainParams>) {
+    VStack::new(cx, |cx| {
+        Label::new(cx, "Gain");
+        ParamSlider::new(cx, Data::params, |params| &params.gain)
+            .with_decibel_ticks(&[-24.0, -18.0, -12.0, -6.0, 0.0, 6.0], &[-12.0, 0.0]);
+
+        PeakMeter::new(
+            cx,
+            Data::peak_meter.map(|peak_meter| util::gain_to_db(peak_meter.load(Ordering::Relaxed))),
+            Some(Duration::from_millis(600)),
+        )
+        // This is how adding padding works in vizia
+        .top(Pixels(10.0));
+
+        GainCurve::new(cx, params)
+            .top(Pixels(10.0))
+            .width(Pixels(180.0))
+            .height(Pixels(70.0));
+    })
+    .height(Auto)
+    .child_left(Stretch(1.0))
+    .child_right(Stretch(1.0));
+}
+
+fn build_patch_tab(cx: &mut Context) {
+    VStack::new(cx, |cx| {
+        HStack::new(cx, |cx| {
+            Button::new(cx, |cx| Label::new(cx, "Copy"))
+                .on_press(|cx| cx.emit(AppEvent::CopyState));
+            Button::new(cx, |cx| Label::new(cx, "Paste"))
+                .on_press(|cx| cx.emit(AppEvent::PasteState));
+            Button::new(cx, |cx| Label::new(cx, "Reset"))
+                .on_press(|cx| cx.emit(AppEvent::ResetParameters));
+        })
+        .col_between(Pixels(5.0))
+        .height(Auto)
+        .width(Auto);
+
+        Label::new(cx, Data::status)
+            .font_size(11.0)
+            .width(Pixels(180.0))
+            .height(Auto)
+            .top(Pixels(5.0));
+    })
+    .height(Auto)
+    .child_left(Stretch(1.0))
+    .child_right(Stretch(1.0));
+}
+
+/// Draws the output level as a function of the input level for the current gain. The curve's points
+/// stand in for data that's more expensive to compute, like an EQ's frequency response. Instead of
+/// recomputing them on every frame, they're only recomputed when the gain parameter changes, either
+/// from the GUI or from the host.
+struct GainCurve {
+    params: Arc<GainParams>,
+
+    /// The output levels in decibels for `CURVE_NUM_POINTS` evenly spaced input levels between
+    /// `CURVE_MIN_DB` and 0 dBFS.
+    points: Vec<f32>,
+    /// Keeps this view subscribed to changes to the gain parameter until it's removed.
+    _param_subscription: ParamSubscription,
+}
+
+impl GainCurve {
+    fn new(cx: &mut Context, params: Arc<GainParams>) -> Handle<'_, Self> {
+        let param_subscription = ParamSubscription::new(["gain"]);
+
+        Self {
+            params,
+            points: Vec::with_capacity(CURVE_NUM_POINTS),
+            _param_subscription: param_subscription.clone(),
+        }
+        .build(cx, |cx| {
+            // This will send a `ParamChangeEvent::Changed` to this view right away, and then again
+            // once per frame when the gain parameter has changed
+            cx.emit(ParamChangeEvent::Subscribe(param_subscription));
+        })
+    }
+
+    fn recompute(&mut self) {
+        let gain_db = util::gain_to_db(self.params.gain.value());
+
+        self.points.clear();
+        self.points.extend((0..CURVE_NUM_POINTS).map(|idx| {
+            let input_db = CURVE_MIN_DB * (1.0 - (idx as f32 / (CURVE_NUM_POINTS - 1) as f32));
+            (input_db + gain_db).clamp(CURVE_MIN_DB, CURVE_MAX_DB)
+        }));
+    }
+}
+
+impl View for GainCurve {
+    fn element(&self) -> Option<&'static str> {
+        Some("gain-curve")
+    }
+
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|param_change_event, _| {
+            if let ParamChangeEvent::Changed(_) = param_change_event {
+                self.recompute();
+                cx.needs_redraw();
+            }
+        });
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &Canvas) {
+        let bounds = cx.bounds();
+        if bounds.w == 0.0 || bounds.h == 0.0 || self.points.is_empty() {
+            return;
+        }
+
+        let mut path = vg::Path::new();
+        for (idx, output_db) in self.points.iter().enumerate() {
+            let x = bounds.x + (bounds.w * (idx as f32 / (CURVE_NUM_POINTS - 1) as f32));
+            let y = bounds.y
+                + (bounds.h * (1.0 - ((output_db - CURVE_MIN_DB) / (CURVE_MAX_DB - CURVE_MIN_DB))));
+            if idx == 0 {
+                path.move_to((x, y));
+            } else {
+                path.line_to((x, y));
+            }
+        }
+
+        let font_rgba = cx.font_color().get_rgba();
+        let mut paint = vg::Paint::default();
+        paint.set_color(vg::Color::from_argb(
+            font_rgba.alpha,
+            font_rgba.red,
+            font_rgba.green,
+            font_rgba.blue,
+        ));
+        paint.set_stroke_width(cx.logical_to_physical(1.5));
+        paint.set_style(vg::PaintStyle::Stroke);
+        paint.set_anti_alias(true);
+        canvas.draw_path(&path, &paint);
+    }
+}
@@ -20,6 +20,9 @@ pub struct Gain {
     ///
     /// This is stored as voltage gain.
     peak_meter: Arc<AtomicF32>,
+    /// Editor state that's remembered while the plugin is loaded but that's not saved with the
+    /// patch, like the editor's active tab.
+    session_state: Arc<SessionState>,
 }
 
 #[derive(Params)]
@@ -40,6 +43,7 @@ impl Default for Gain {
 
             peak_meter_decay_weight: 1.0,
             peak_meter: Arc::new(AtomicF32::new(util::MINUS_INFINITY_DB)),
+            session_state: SessionState::new(),
         }
     }
 }
@@ -100,6 +104,7 @@ impl Plugin for Gain {
         editor::create(
             self.params.clone(),
             self.peak_meter.clone(),
+            self.session_state.clone(),
             self.params.editor_state.clone(),
         )
     }
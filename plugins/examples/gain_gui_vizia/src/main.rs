@@ -1,7 +1,13 @@
+use nih_plug::plugin::capabilities::PluginFormat;
 use nih_plug::prelude::*;
 
 use gain_gui_vizia::Gain;
 
 fn main() {
-    nih_export_standalone::<Gain>();
+    // The plugin is also exported as a CLAP and a VST3 plugin, which is listed in the summary
+    // printed by `--info`
+    nih_export_standalone_with_config::<Gain>(StandaloneConfig {
+        formats: vec![PluginFormat::clap::<Gain>(), PluginFormat::vst3::<Gain>()],
+        ..Default::default()
+    });
 }
@@ -87,6 +87,18 @@ impl Plugin for MidiInverter {
                     channel: 15 - channel,
                     note: 127 - note,
                 }),
+                NoteEvent::AllNotesOff { timing, channel } => {
+                    context.send_event(NoteEvent::AllNotesOff {
+                        timing,
+                        channel: channel.map(|channel| 15 - channel),
+                    })
+                }
+                NoteEvent::AllSoundOff { timing, channel } => {
+                    context.send_event(NoteEvent::AllSoundOff {
+                        timing,
+                        channel: channel.map(|channel| 15 - channel),
+                    })
+                }
                 NoteEvent::PolyPressure {
                     timing,
                     voice_id,
@@ -0,0 +1,7 @@
+use nih_plug::prelude::*;
+
+use param_visibility_egui::ParamVisibilityFilter;
+
+fn main() {
+    nih_export_standalone::<ParamVisibilityFilter>();
+}
@@ -0,0 +1,56 @@
+use nih_plug::prelude::Editor;
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::widgets::*;
+use nih_plug_vizia::{assets, create_vizia_editor, ViziaState, ViziaTheming};
+use std::sync::Arc;
+
+use crate::FilterParams;
+
+#[derive(Lens)]
+struct Data {
+    params: Arc<FilterParams>,
+}
+
+impl Model for Data {}
+
+// Makes sense to also define this here, makes it a bit easier to keep track of
+pub(crate) fn default_state() -> Arc<ViziaState> {
+    ViziaState::new(|| (300, 240))
+}
+
+pub(crate) fn create(
+    params: Arc<FilterParams>,
+    editor_state: Arc<ViziaState>,
+) -> Option<Box<dyn Editor>> {
+    create_vizia_editor(editor_state, ViziaTheming::Custom, move |cx, _| {
+        assets::register_noto_sans_light(cx);
+        assets::register_noto_sans_thin(cx);
+
+        Data {
+            params: params.clone(),
+        }
+        .build(cx);
+
+        VStack::new(cx, |cx| {
+            Label::new(cx, "Param Visibility")
+                .font_family(vec![FamilyOwned::Name(String::from(assets::NOTO_SANS))])
+                .font_weight(FontWeightKeyword::Thin)
+                .font_size(30.0)
+                .height(Pixels(50.0))
+                .child_top(Stretch(1.0))
+                .child_bottom(Pixels(1.0));
+
+            // The predicate is evaluated again whenever a parameter changes, so changing the filter
+            // type or disabling the filter immediately updates the other rows
+            GenericUi::new_with_visibility(cx, Data::params, |params: &FilterParams, param_id| {
+                params.visibility(param_id)
+            })
+            .child_top(Pixels(0.0));
+        })
+        .row_between(Pixels(0.0))
+        .child_left(Stretch(1.0))
+        .child_right(Stretch(1.0));
+
+        ResizeHandle::new(cx);
+    })
+}
@@ -0,0 +1,249 @@
+use nih_plug::prelude::*;
+use nih_plug::util::filter::{Biquad, BiquadCoefficients};
+use nih_plug_vizia::ViziaState;
+use std::sync::Arc;
+
+mod editor;
+
+/// The maximum number of channels supported by the plugin's audio IO layouts.
+const MAX_NUM_CHANNELS: usize = 2;
+
+/// A simple filter plugin whose generic UI only shows the parameters that currently do something.
+/// The filter's frequency, Q, and gain are greyed out while the filter is disabled, and the gain
+/// parameter is hidden unless the filter is a peaking filter. This only affects the editor, all
+/// parameters can still be automated at any time.
+pub struct ParamVisibilityFilter {
+    params: Arc<FilterParams>,
+
+    /// Needed for computing the filter coefficients.
+    sample_rate: f32,
+    /// One filter per channel.
+    filters: [Biquad; MAX_NUM_CHANNELS],
+    /// The filter type, frequency, Q, and gain the filter coefficients were last computed for.
+    /// Computing new coefficients is relatively expensive, so this is only done when one of these
+    /// values has changed.
+    coefficients_computed_for: Option<(FilterType, f32, f32, f32)>,
+}
+
+#[derive(Params)]
+pub struct FilterParams {
+    /// The editor state, saved together with the parameter state so the custom scaling can be
+    /// restored.
+    #[persist = "editor-state"]
+    editor_state: Arc<ViziaState>,
+
+    #[id = "enabled"]
+    pub enabled: BoolParam,
+    #[id = "type"]
+    pub filter_type: EnumParam<FilterType>,
+    #[id = "freq"]
+    pub frequency: FloatParam,
+    #[id = "q"]
+    pub q: FloatParam,
+    /// Only used for [`FilterType::Peaking`].
+    #[id = "gain"]
+    pub gain: FloatParam,
+}
+
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterType {
+    #[name = "Low-pass"]
+    Lowpass,
+    #[name = "High-pass"]
+    Highpass,
+    Peaking,
+}
+
+impl Default for ParamVisibilityFilter {
+    fn default() -> Self {
+        Self {
+            params: Arc::new(FilterParams::default()),
+
+            sample_rate: 1.0,
+            filters: [Biquad::default(); MAX_NUM_CHANNELS],
+            coefficients_computed_for: None,
+        }
+    }
+}
+
+impl Default for FilterParams {
+    fn default() -> Self {
+        Self {
+            editor_state: editor::default_state(),
+
+            enabled: BoolParam::new("Enabled", true),
+            filter_type: EnumParam::new("Type", FilterType::Lowpass),
+            frequency: FloatParam::new(
+                "Frequency",
+                1_000.0,
+                FloatRange::Skewed {
+                    min: 20.0,
+                    max: 20_000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+            q: FloatParam::new(
+                "Q",
+                std::f32::consts::FRAC_1_SQRT_2,
+                FloatRange::Skewed {
+                    min: 0.1,
+                    max: 10.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
+            gain: FloatParam::new(
+                "Gain",
+                0.0,
+                FloatRange::Linear {
+                    min: -24.0,
+                    max: 24.0,
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_step_size(0.1)
+            .with_unit(" dB"),
+        }
+    }
+}
+
+impl FilterParams {
+    /// Decides how the editor's generic UI shows a parameter based on the current parameter
+    /// values. This is called on the GUI thread.
+    pub fn visibility(&self, param_id: &str) -> ParamVisibility {
+        match param_id {
+            "gain" if self.filter_type.value() != FilterType::Peaking => ParamVisibility::Hidden,
+            "freq" | "q" | "gain" if !self.enabled.value() => ParamVisibility::Disabled,
+            _ => ParamVisibility::Visible,
+        }
+    }
+}
+
+impl Plugin for ParamVisibilityFilter {
+    const NAME: &'static str = "Param Visibility (VIZIA)";
+    const VENDOR: &'static str = "Moist Plugins GmbH";
+    const URL: &'static str = "https://youtu.be/dQw4w9WgXcQ";
+    const EMAIL: &'static str = "info@example.com";
+
+    const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(2),
+            main_output_channels: NonZeroU32::new(2),
+            ..AudioIOLayout::const_default()
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(1),
+            main_output_channels: NonZeroU32::new(1),
+            ..AudioIOLayout::const_default()
+        },
+    ];
+
+    const SAMPLE_ACCURATE_AUTOMATION: bool = true;
+
+    type SysExMessage = ();
+    type BackgroundTask = ();
+
+    fn params(&self) -> Arc<dyn Params> {
+        self.params.clone()
+    }
+
+    fn editor(&mut self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
+        editor::create(self.params.clone(), self.params.editor_state.clone())
+    }
+
+    fn initialize(
+        &mut self,
+        _audio_io_layout: &AudioIOLayout,
+        buffer_config: &BufferConfig,
+        _context: &mut impl InitContext<Self>,
+    ) -> bool {
+        self.sample_rate = buffer_config.sample_rate;
+        self.coefficients_computed_for = None;
+
+        true
+    }
+
+    fn reset(&mut self) {
+        for filter in &mut self.filters {
+            filter.reset();
+        }
+    }
+
+    fn process(
+        &mut self,
+        buffer: &mut Buffer,
+        _aux: &mut AuxiliaryBuffers,
+        _context: &mut impl ProcessContext<Self>,
+    ) -> ProcessStatus {
+        for channel_samples in buffer.iter_samples() {
+            let filter_type = self.params.filter_type.value();
+            let frequency = self.params.frequency.smoothed.next();
+            let q = self.params.q.smoothed.next();
+            let gain = self.params.gain.smoothed.next();
+            if !self.params.enabled.value() {
+                continue;
+            }
+
+            self.update_filters(filter_type, frequency, q, gain);
+            for (sample, filter) in channel_samples.into_iter().zip(&mut self.filters) {
+                *sample = filter.process(*sample);
+            }
+        }
+
+        ProcessStatus::Normal
+    }
+}
+
+impl ParamVisibilityFilter {
+    /// Recompute the filter coefficients if any of the filter's parameters have changed.
+    fn update_filters(&mut self, filter_type: FilterType, frequency: f32, q: f32, gain: f32) {
+        let filter_settings = Some((filter_type, frequency, q, gain));
+        if self.coefficients_computed_for == filter_settings {
+            return;
+        }
+
+        // The frequency is clamped in case the plugin runs at a low sample rate
+        let frequency = frequency.min(self.sample_rate * 0.49);
+        let coefficients = match filter_type {
+            FilterType::Lowpass => BiquadCoefficients::lowpass(self.sample_rate, frequency, q),
+            FilterType::Highpass => BiquadCoefficients::highpass(self.sample_rate, frequency, q),
+            FilterType::Peaking => {
+                BiquadCoefficients::peaking(self.sample_rate, frequency, q, gain)
+            }
+        };
+        for filter in &mut self.filters {
+            filter.coefficients = coefficients;
+        }
+
+        self.coefficients_computed_for = filter_settings;
+    }
+}
+
+impl ClapPlugin for ParamVisibilityFilter {
+    const CLAP_ID: &'static str = "com.moist-plugins-gmbh.param-visibility-vizia";
+    const CLAP_DESCRIPTION: Option<&'static str> =
+        Some("A filter example plugin that hides parameters that have no effect");
+    const CLAP_MANUAL_URL: Option<&'static str> = Some(Self::URL);
+    const CLAP_SUPPORT_URL: Option<&'static str> = None;
+    const CLAP_FEATURES: &'static [ClapFeature] = &[
+        ClapFeature::AudioEffect,
+        ClapFeature::Stereo,
+        ClapFeature::Mono,
+        ClapFeature::Filter,
+    ];
+}
+
+impl Vst3Plugin for ParamVisibilityFilter {
+    const VST3_CLASS_ID: [u8; 16] = *b"ParamVisibViziaA";
+    const VST3_SUBCATEGORIES: &'static [Vst3SubCategory] =
+        &[Vst3SubCategory::Fx, Vst3SubCategory::Filter];
+}
+
+nih_export_clap!(ParamVisibilityFilter);
+nih_export_vst3!(ParamVisibilityFilter);
@@ -0,0 +1,201 @@
+use nih_plug::params::persist::ArcSwap;
+use nih_plug::prelude::*;
+use nih_plug_egui::{create_egui_editor, egui, EguiState};
+use std::fs;
+use std::sync::Arc;
+
+/// A pass-through plugin with a file path that can be edited from the GUI. The path is stored in a
+/// persistent field so it's saved and restored together with the rest of the plugin's state. The
+/// audio thread checks the path at the start of every process call without locking, and it asks a
+/// background thread to look up the file whenever the path changes. A real plugin would load the
+/// file there instead.
+pub struct PersistentPath {
+    params: Arc<PersistentPathParams>,
+
+    /// The path the audio thread last saw. This is used to detect changes to the path, and it's
+    /// never dereferenced on the audio thread.
+    current_sample_path: Arc<String>,
+    /// A description of the file at the current path, written to by the background thread and
+    /// shown in the GUI.
+    file_status: Arc<ArcSwap<String>>,
+}
+
+#[derive(Params)]
+pub struct PersistentPathParams {
+    /// The editor state, saved together with the parameter state so the custom scaling can be
+    /// restored.
+    #[persist = "editor-state"]
+    editor_state: Arc<EguiState>,
+
+    /// The path to a sample. This is written to by the editor and read from by the audio thread.
+    /// An `ArcSwap` can be read from without locking, and storing a new path replaces the entire
+    /// string at once. Restoring the plugin's state also stores a new path here.
+    #[persist = "sample-path"]
+    pub sample_path: Arc<ArcSwap<String>>,
+}
+
+/// The plugin's background tasks.
+pub enum Task {
+    /// The sample path has changed. The old path is passed along so it gets deallocated on the
+    /// background thread instead of on the audio thread.
+    SamplePathChanged {
+        new_path: Arc<String>,
+        old_path: Arc<String>,
+    },
+}
+
+/// The editor's own state.
+struct EditorState {
+    /// The contents of the path text field.
+    path_text: String,
+    /// Whether the path text field is currently being edited. While this is the case, the text
+    /// field is not overwritten with the stored path.
+    editing: bool,
+}
+
+impl Default for PersistentPath {
+    fn default() -> Self {
+        Self {
+            params: Arc::new(PersistentPathParams::default()),
+
+            current_sample_path: Arc::new(String::new()),
+            file_status: Arc::new(ArcSwap::from_pointee(String::new())),
+        }
+    }
+}
+
+impl Default for PersistentPathParams {
+    fn default() -> Self {
+        Self {
+            editor_state: EguiState::from_size(400, 120),
+
+            sample_path: Arc::new(ArcSwap::from_pointee(String::new())),
+        }
+    }
+}
+
+impl Plugin for PersistentPath {
+    const NAME: &'static str = "Persistent Path (egui)";
+    const VENDOR: &'static str = "Moist Plugins GmbH";
+    const URL: &'static str = "https://youtu.be/dQw4w9WgXcQ";
+    const EMAIL: &'static str = "info@example.com";
+
+    const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(2),
+            main_output_channels: NonZeroU32::new(2),
+            ..AudioIOLayout::const_default()
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(1),
+            main_output_channels: NonZeroU32::new(1),
+            ..AudioIOLayout::const_default()
+        },
+    ];
+
+    type SysExMessage = ();
+    type BackgroundTask = Task;
+
+    fn task_executor(&mut self) -> TaskExecutor<Self> {
+        let file_status = self.file_status.clone();
+        Box::new(move |task| match task {
+            Task::SamplePathChanged { new_path, old_path } => {
+                drop(old_path);
+
+                let status = if new_path.is_empty() {
+                    String::from("No file selected")
+                } else {
+                    match fs::metadata(new_path.as_str()) {
+                        Ok(metadata) if metadata.is_file() => {
+                            format!("Found a {} byte file", metadata.len())
+                        }
+                        Ok(_) => String::from("Not a file"),
+                        Err(err) => format!("Could not read the file: {err}"),
+                    }
+                };
+                file_status.store(Arc::new(status));
+            }
+        })
+    }
+
+    fn params(&self) -> Arc<dyn Params> {
+        self.params.clone()
+    }
+
+    fn editor(&mut self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
+        let sample_path = self.params.sample_path.clone();
+        let file_status = self.file_status.clone();
+        create_egui_editor(
+            self.params.editor_state.clone(),
+            EditorState {
+                path_text: String::new(),
+                editing: false,
+            },
+            |_, _| {},
+            move |egui_ctx, _setter, state| {
+                egui::CentralPanel::default().show(egui_ctx, |ui| {
+                    // The path may also have been changed by the host restoring the plugin's state
+                    if !state.editing {
+                        let current_path = sample_path.load();
+                        if state.path_text != **current_path {
+                            state.path_text.clone_from(&current_path);
+                        }
+                    }
+
+                    ui.label("Sample path");
+                    let response = ui.text_edit_singleline(&mut state.path_text);
+                    state.editing = response.has_focus();
+                    if response.lost_focus() {
+                        // The audio thread picks up the new path during its next process call
+                        sample_path.store(Arc::new(state.path_text.clone()));
+                    }
+
+                    ui.label(file_status.load().as_str());
+                });
+            },
+        )
+    }
+
+    fn process(
+        &mut self,
+        _buffer: &mut Buffer,
+        _aux: &mut AuxiliaryBuffers,
+        context: &mut impl ProcessContext<Self>,
+    ) -> ProcessStatus {
+        // Loading the path neither locks nor allocates. Holding on to the `Arc` only increments its
+        // reference count.
+        let sample_path = self.params.sample_path.load();
+        if !Arc::ptr_eq(&sample_path, &self.current_sample_path) {
+            let new_path = Arc::clone(&sample_path);
+            let old_path = std::mem::replace(&mut self.current_sample_path, new_path.clone());
+            context.execute_background(Task::SamplePathChanged { new_path, old_path });
+        }
+
+        ProcessStatus::Normal
+    }
+}
+
+impl ClapPlugin for PersistentPath {
+    const CLAP_ID: &'static str = "com.moist-plugins-gmbh.persistent-path";
+    const CLAP_DESCRIPTION: Option<&'static str> =
+        Some("An example plugin with a persistent file path");
+    const CLAP_MANUAL_URL: Option<&'static str> = Some(Self::URL);
+    const CLAP_SUPPORT_URL: Option<&'static str> = None;
+    const CLAP_FEATURES: &'static [ClapFeature] = &[
+        ClapFeature::AudioEffect,
+        ClapFeature::Stereo,
+        ClapFeature::Mono,
+        ClapFeature::Utility,
+    ];
+}
+
+impl Vst3Plugin for PersistentPath {
+    const VST3_CLASS_ID: [u8; 16] = *b"PersistentPathEx";
+    const VST3_SUBCATEGORIES: &'static [Vst3SubCategory] =
+        &[Vst3SubCategory::Fx, Vst3SubCategory::Tools];
+}
+
+nih_export_clap!(PersistentPath);
+nih_export_vst3!(PersistentPath);
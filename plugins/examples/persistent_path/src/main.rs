@@ -0,0 +1,7 @@
+use nih_plug::prelude::*;
+
+use persistent_path::PersistentPath;
+
+fn main() {
+    nih_export_standalone::<PersistentPath>();
+}
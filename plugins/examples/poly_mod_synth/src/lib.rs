@@ -1,9 +1,11 @@
 use nih_plug::prelude::*;
+use nih_plug::util::{AllocatedVoice, VoiceAllocator};
 use rand::Rng;
 use rand_pcg::Pcg32;
 use std::sync::Arc;
 
-/// The number of simultaneous voices for this synth.
+/// The maximum number of simultaneous voices for this synth. The number of voices that can actually
+/// play at the same time can be lowered with the `voices` parameter.
 const NUM_VOICES: u32 = 16;
 /// The maximum size of an audio block. We'll split up the audio in blocks and render smoothed
 /// values to buffers since these values may need to be reused for multiple voices.
@@ -22,10 +24,14 @@ struct PolyModSynth {
     /// A pseudo-random number generator. This will always be reseeded with the same seed when the
     /// synth is reset. That way the output is deterministic when rendering multiple times.
     prng: Pcg32,
-    /// The synth's voices. Inactive voices will be set to `None` values.
+    /// The synth's voices. Inactive voices will be set to `None` values. The voice allocator
+    /// decides which of these slots a new note is played in.
     voices: [Option<Voice>; NUM_VOICES as usize],
-    /// The next internal voice ID, used only to figure out the oldest voice for voice stealing.
-    /// This is incremented by one each time a voice is created.
+    /// Assigns notes to the slots in `voices`, and decides which voices get stolen when the
+    /// polyphony limit has been reached or when it is lowered.
+    voice_allocator: VoiceAllocator,
+    /// The next internal voice ID, used to detect polyphonic modulation for voices that started in
+    /// the current block. This is incremented by one each time a voice is created.
     next_internal_voice_id: u64,
 }
 
@@ -40,6 +46,10 @@ struct PolyModSynthParams {
     /// The amplitude envelope release time. This is the same for every voice.
     #[id = "amp_rel"]
     amp_release_ms: FloatParam,
+    /// The maximum number of voices that can play at the same time. Lowering this steals the
+    /// excess voices. With a single voice the synth is monophonic with legato.
+    #[id = "voices"]
+    voices: IntParam,
 }
 
 /// Data for a single synth voice. In a real synth where performance matter, you may want to use a
@@ -47,16 +57,16 @@ struct PolyModSynthParams {
 #[derive(Debug, Clone)]
 struct Voice {
     /// The identifier for this voice. Polyphonic modulation events are linked to a voice based on
-    /// these IDs. If the host doesn't provide these IDs, then this is computed through
-    /// `compute_fallback_voice_id()`. In that case polyphonic modulation will not work, but the
-    /// basic note events will still have an effect.
+    /// these IDs. If the host doesn't provide these IDs, then this is computed by the voice
+    /// allocator. In that case polyphonic modulation will not work, but the basic note events will
+    /// still have an effect.
     voice_id: i32,
     /// The note's channel, in `0..16`. Only used for the voice terminated event.
     channel: u8,
     /// The note's key/note, in `0..128`. Only used for the voice terminated event.
     note: u8,
     /// The voices internal ID. Each voice has an internal voice ID one higher than the previous
-    /// voice. This is used to steal the last voice in case all 16 voices are in use.
+    /// voice.
     internal_voice_id: u64,
     /// The square root of the note's velocity. This is used as a gain multiplier.
     velocity_sqrt: f32,
@@ -86,6 +96,7 @@ impl Default for PolyModSynth {
             prng: Pcg32::new(420, 1337),
             // `[None; N]` requires the `Some(T)` to be `Copy`able
             voices: [0; NUM_VOICES as usize].map(|_| None),
+            voice_allocator: VoiceAllocator::new(NUM_VOICES as usize),
             next_internal_voice_id: 0,
         }
     }
@@ -136,6 +147,14 @@ impl Default for PolyModSynthParams {
             )
             .with_step_size(0.1)
             .with_unit(" ms"),
+            voices: IntParam::new(
+                "Voices",
+                NUM_VOICES as i32,
+                IntRange::Linear {
+                    min: 1,
+                    max: NUM_VOICES as i32,
+                },
+            ),
         }
     }
 }
@@ -165,14 +184,29 @@ impl Plugin for PolyModSynth {
         self.params.clone()
     }
 
-    // If the synth as a variable number of voices, you will need to call
-    // `context.set_current_voice_capacity()` in `initialize()` and in `process()` (when the
+    // Because the number of voices can be changed with the `voices` parameter, the synth needs to
+    // call `context.set_current_voice_capacity()` in `initialize()` and in `process()` (when the
     // capacity changes) to inform the host about this.
+    fn initialize(
+        &mut self,
+        _audio_io_layout: &AudioIOLayout,
+        _buffer_config: &BufferConfig,
+        context: &mut impl InitContext<Self>,
+    ) -> bool {
+        // There are no active voices yet, so nothing can be stolen here
+        self.voice_allocator
+            .set_polyphony(self.params.voices.value() as usize, |_, _| ());
+        context.set_current_voice_capacity(self.voice_allocator.polyphony() as u32);
+
+        true
+    }
+
     fn reset(&mut self) {
         // This ensures the output is at least somewhat deterministic when rendering to audio
         self.prng = Pcg32::new(420, 1337);
 
         self.voices.fill(None);
+        self.voice_allocator.reset();
         self.next_internal_voice_id = 0;
     }
 
@@ -191,6 +225,20 @@ impl Plugin for PolyModSynth {
         let sample_rate = context.transport().sample_rate;
         let output = buffer.as_slice();
 
+        // The polyphony limit is only updated at the start of the buffer. When it gets lowered, the
+        // allocator steals releasing voices first and then the oldest held voices. The other voices
+        // keep playing without any interruptions.
+        let polyphony = self.params.voices.value() as usize;
+        if polyphony != self.voice_allocator.polyphony() {
+            let voices = &mut self.voices;
+            self.voice_allocator
+                .set_polyphony(polyphony, |voice_idx, stolen_voice| {
+                    voices[voice_idx] = None;
+                    context.send_event(voice_terminated_event(0, stolen_voice));
+                });
+            context.set_current_voice_capacity(self.voice_allocator.polyphony() as u32);
+        }
+
         let mut next_event = context.next_event();
         let mut block_start: usize = 0;
         let mut block_end: usize = MAX_BLOCK_SIZE.min(num_samples);
@@ -216,20 +264,16 @@ impl Plugin for PolyModSynth {
                                 note,
                                 velocity,
                             } => {
-                                let initial_phase: f32 = self.prng.gen();
-                                // This starts with the attack portion of the amplitude envelope
-                                let amp_envelope = Smoother::new(SmoothingStyle::Exponential(
-                                    self.params.amp_attack_ms.value(),
-                                ));
-                                amp_envelope.reset(0.0);
-                                amp_envelope.set_target(sample_rate, 1.0);
-
-                                let voice =
-                                    self.start_voice(context, timing, voice_id, channel, note);
+                                let voice = self.start_voice(
+                                    context,
+                                    sample_rate,
+                                    timing,
+                                    voice_id,
+                                    channel,
+                                    note,
+                                );
                                 voice.velocity_sqrt = velocity.sqrt();
-                                voice.phase = initial_phase;
                                 voice.phase_delta = util::midi_note_to_freq(note) / sample_rate;
-                                voice.amp_envelope = amp_envelope;
                             }
                             NoteEvent::NoteOff {
                                 timing: _,
@@ -248,6 +292,12 @@ impl Plugin for PolyModSynth {
                             } => {
                                 self.choke_voices(context, timing, voice_id, channel, note);
                             }
+                            NoteEvent::AllNotesOff { timing: _, channel } => {
+                                self.start_release_for_all_voices(sample_rate, channel)
+                            }
+                            NoteEvent::AllSoundOff { timing, channel } => {
+                                self.choke_all_voices(context, timing, channel);
+                            }
                             NoteEvent::PolyModulation {
                                 timing: _,
                                 voice_id,
@@ -407,7 +457,7 @@ impl Plugin for PolyModSynth {
 
             // Terminate voices whose release period has fully ended. This could be done as part of
             // the previous loop but this is simpler.
-            for voice in self.voices.iter_mut() {
+            for (voice_idx, voice) in self.voices.iter_mut().enumerate() {
                 match voice {
                     Some(v) if v.releasing && v.amp_envelope.previous_value() == 0.0 => {
                         // This event is very important, as it allows the host to manage its own modulation
@@ -419,6 +469,7 @@ impl Plugin for PolyModSynth {
                             note: v.note,
                         });
                         *voice = None;
+                        self.voice_allocator.terminate(voice_idx);
                     }
                     _ => (),
                 }
@@ -442,70 +493,71 @@ impl PolyModSynth {
             .position(|voice| matches!(voice, Some(voice) if voice.voice_id == voice_id))
     }
 
-    /// Start a new voice with the given voice ID. If all voices are currently in use, the oldest
-    /// voice will be stolen. Returns a reference to the new voice.
+    /// Start a new voice with the given voice ID. If the polyphony limit has been reached, then the
+    /// voice allocator steals a voice to make room for it. If the synth is monophonic and another
+    /// note is being held, then the new note takes over that voice with legato instead. Returns a
+    /// reference to the new voice.
     fn start_voice(
         &mut self,
         context: &mut impl ProcessContext<Self>,
+        sample_rate: f32,
         sample_offset: u32,
         voice_id: Option<i32>,
         channel: u8,
         note: u8,
     ) -> &mut Voice {
-        let new_voice = Voice {
-            voice_id: voice_id.unwrap_or_else(|| compute_fallback_voice_id(note, channel)),
+        // The stolen voice needs to be terminated so the host can reuse its modulation resources
+        let voices = &mut self.voices;
+        let assignment =
+            self.voice_allocator
+                .note_on(voice_id, channel, note, |voice_idx, stolen_voice| {
+                    voices[voice_idx] = None;
+                    context.send_event(voice_terminated_event(sample_offset, stolen_voice));
+                });
+
+        // This starts with the attack portion of the amplitude envelope
+        let amp_envelope = Smoother::new(SmoothingStyle::Exponential(
+            self.params.amp_attack_ms.value(),
+        ));
+        amp_envelope.reset(0.0);
+        amp_envelope.set_target(sample_rate, 1.0);
+
+        let mut new_voice = Voice {
+            // The allocator computes a fallback voice ID if the host didn't provide one
+            voice_id: self
+                .voice_allocator
+                .voice(assignment.index)
+                .unwrap()
+                .voice_id,
             internal_voice_id: self.next_internal_voice_id,
             channel,
             note,
             velocity_sqrt: 1.0,
 
-            phase: 0.0,
+            phase: self.prng.gen(),
             phase_delta: 0.0,
             releasing: false,
-            amp_envelope: Smoother::none(),
+            amp_envelope,
 
             voice_gain: None,
         };
         self.next_internal_voice_id = self.next_internal_voice_id.wrapping_add(1);
 
-        // Can't use `.iter_mut().find()` here because nonlexical lifetimes don't apply to return
-        // values
-        match self.voices.iter().position(|voice| voice.is_none()) {
-            Some(free_voice_idx) => {
-                self.voices[free_voice_idx] = Some(new_voice);
-                return self.voices[free_voice_idx].as_mut().unwrap();
-            }
-            None => {
-                // If there is no free voice, find and steal the oldest one
-                // SAFETY: We can skip a lot of checked unwraps here since we already know all voices are in
-                //         use
-                let oldest_voice = unsafe {
-                    self.voices
-                        .iter_mut()
-                        .min_by_key(|voice| voice.as_ref().unwrap_unchecked().internal_voice_id)
-                        .unwrap_unchecked()
-                };
-
-                // The stolen voice needs to be terminated so the host can reuse its modulation
-                // resources
-                {
-                    let oldest_voice = oldest_voice.as_ref().unwrap();
-                    context.send_event(NoteEvent::VoiceTerminated {
-                        timing: sample_offset,
-                        voice_id: Some(oldest_voice.voice_id),
-                        channel: oldest_voice.channel,
-                        note: oldest_voice.note,
-                    });
-                }
-
-                *oldest_voice = Some(new_voice);
-                return oldest_voice.as_mut().unwrap();
+        // With legato the previous note's voice has still ended from the host's point of view, but
+        // the new voice continues where the old one left off instead of restarting its envelope
+        if let Some(previous_voice) = assignment.legato {
+            context.send_event(voice_terminated_event(sample_offset, previous_voice));
+            if let Some(previous_voice) = self.voices[assignment.index].take() {
+                new_voice.phase = previous_voice.phase;
+                new_voice.amp_envelope = previous_voice.amp_envelope;
             }
         }
+
+        self.voices[assignment.index].insert(new_voice)
     }
 
     /// Start the release process for one or more voice by changing their amplitude envelope. If
-    /// `voice_id` is not provided, then this will terminate all matching voices.
+    /// `voice_id` is not provided, then this will release all matching voices.
     fn start_release_for_voices(
         &mut self,
         sample_rate: f32,
@@ -513,31 +565,16 @@ impl PolyModSynth {
         channel: u8,
         note: u8,
     ) {
-        for voice in self.voices.iter_mut() {
-            match voice {
-                Some(Voice {
-                    voice_id: candidate_voice_id,
-                    channel: candidate_channel,
-                    note: candidate_note,
-                    releasing,
-                    amp_envelope,
-                    ..
-                }) if voice_id == Some(*candidate_voice_id)
-                    || (channel == *candidate_channel && note == *candidate_note) =>
-                {
-                    *releasing = true;
-                    amp_envelope.style =
-                        SmoothingStyle::Exponential(self.params.amp_release_ms.value());
-                    amp_envelope.set_target(sample_rate, 0.0);
-
-                    // If this targetted a single voice ID, we're done here. Otherwise there may be
-                    // multiple overlapping voices as we enabled support for that in the
-                    // `PolyModulationConfig`.
-                    if voice_id.is_some() {
-                        return;
-                    }
-                }
-                _ => (),
+        // If this targetted a single voice ID, then only that voice is released. Otherwise there
+        // may be multiple overlapping voices as we enabled support for that in the
+        // `PolyModulationConfig`. The voice allocator also needs to know which voices are
+        // releasing, since those are stolen first.
+        while let Some(voice_idx) = self.voice_allocator.note_off(voice_id, channel, note) {
+            if let Some(voice) = self.voices[voice_idx].as_mut() {
+                voice.releasing = true;
+                voice.amp_envelope.style =
+                    SmoothingStyle::Exponential(self.params.amp_release_ms.value());
+                voice.amp_envelope.set_target(sample_rate, 0.0);
             }
         }
     }
@@ -553,7 +590,7 @@ impl PolyModSynth {
         channel: u8,
         note: u8,
     ) {
-        for voice in self.voices.iter_mut() {
+        for (voice_idx, voice) in self.voices.iter_mut().enumerate() {
             match voice {
                 Some(Voice {
                     voice_id: candidate_voice_id,
@@ -571,6 +608,7 @@ impl PolyModSynth {
                         note,
                     });
                     *voice = None;
+                    self.voice_allocator.terminate(voice_idx);
 
                     if voice_id.is_some() {
                         return;
@@ -580,12 +618,47 @@ impl PolyModSynth {
             }
         }
     }
+
+    /// Start the release process for all voices on a channel, or for all voices if `channel` is
+    /// `None`. This is used for all notes off events, which the host also sends when the transport
+    /// stops playing.
+    fn start_release_for_all_voices(&mut self, sample_rate: f32, channel: Option<u8>) {
+        let voices = &mut self.voices;
+        let amp_release_ms = self.params.amp_release_ms.value();
+        self.voice_allocator.release_all(channel, |voice_idx, _| {
+            if let Some(voice) = voices[voice_idx].as_mut() {
+                voice.releasing = true;
+                voice.amp_envelope.style = SmoothingStyle::Exponential(amp_release_ms);
+                voice.amp_envelope.set_target(sample_rate, 0.0);
+            }
+        });
+    }
+
+    /// Immediately terminate all voices on a channel, or all voices if `channel` is `None`. This is
+    /// used for all sound off events.
+    fn choke_all_voices(
+        &mut self,
+        context: &mut impl ProcessContext<Self>,
+        sample_offset: u32,
+        channel: Option<u8>,
+    ) {
+        let voices = &mut self.voices;
+        self.voice_allocator
+            .choke_all(channel, |voice_idx, choked_voice| {
+                voices[voice_idx] = None;
+                context.send_event(voice_terminated_event(sample_offset, choked_voice));
+            });
+    }
 }
 
-/// Compute a voice ID in case the host doesn't provide them. Polyphonic modulation will not work in
-/// this case, but playing notes will.
-const fn compute_fallback_voice_id(note: u8, channel: u8) -> i32 {
-    note as i32 | ((channel as i32) << 16)
+/// The event that tells the host that a voice stolen or replaced by the voice allocator has ended.
+fn voice_terminated_event(timing: u32, voice: AllocatedVoice) -> NoteEvent<()> {
+    NoteEvent::VoiceTerminated {
+        timing,
+        voice_id: Some(voice.voice_id),
+        channel: voice.channel,
+        note: voice.note,
+    }
 }
 
 impl ClapPlugin for PolyModSynth {
@@ -601,10 +674,9 @@ impl ClapPlugin for PolyModSynth {
     ];
 
     const CLAP_POLY_MODULATION_CONFIG: Option<PolyModulationConfig> = Some(PolyModulationConfig {
-        // If the plugin's voice capacity changes at runtime (for instance, when switching to a
-        // monophonic mode), then the plugin should inform the host in the `initialize()` function
-        // as well as in the `process()` function if it changes at runtime using
-        // `context.set_current_voice_capacity()`
+        // The plugin's voice capacity changes at runtime when the `voices` parameter is changed,
+        // so the plugin informs the host about the current capacity in the `initialize()` function
+        // as well as in the `process()` function using `context.set_current_voice_capacity()`
         max_voice_capacity: NUM_VOICES,
         // This enables voice stacking in Bitwig.
         supports_overlapping_voices: true,
@@ -624,3 +696,315 @@ impl Vst3Plugin for PolyModSynth {
 
 nih_export_clap!(PolyModSynth);
 nih_export_vst3!(PolyModSynth);
+
+#[cfg(test)]
+mod tests {
+    use clap_sys::audio_buffer::clap_audio_buffer;
+    use clap_sys::events::{
+        clap_event_header, clap_event_note, clap_event_param_value, clap_input_events,
+        clap_output_events, CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_NOTE_END, CLAP_EVENT_NOTE_OFF,
+        CLAP_EVENT_NOTE_ON, CLAP_EVENT_PARAM_VALUE,
+    };
+    use clap_sys::ext::params::{clap_param_info, clap_plugin_params, CLAP_EXT_PARAMS};
+    use clap_sys::ext::voice_info::{
+        clap_host_voice_info, clap_plugin_voice_info, clap_voice_info, CLAP_EXT_VOICE_INFO,
+    };
+    use clap_sys::factory::plugin_factory::{clap_plugin_factory, CLAP_PLUGIN_FACTORY_ID};
+    use clap_sys::host::clap_host;
+    use clap_sys::id::clap_id;
+    use clap_sys::plugin::clap_plugin;
+    use clap_sys::process::clap_process;
+    use clap_sys::version::CLAP_VERSION;
+    use std::ffi::{c_char, c_void, CStr};
+    use std::mem;
+
+    use super::*;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+    const BLOCK_SIZE: u32 = 512;
+
+    /// The host only supports the voice info extension, since the plugin informs the host when its
+    /// voice capacity changes.
+    static HOST_VOICE_INFO: clap_host_voice_info = clap_host_voice_info {
+        changed: Some(host_voice_info_changed),
+    };
+
+    /// The synth, created through its CLAP factory and activated like a host would.
+    struct TestInstance {
+        plugin: *const clap_plugin,
+        params: *const clap_plugin_params,
+        voice_info: *const clap_plugin_voice_info,
+        /// The plugin holds on to a pointer to this, so it needs to outlive the plugin instance.
+        _host: Box<clap_host>,
+    }
+
+    impl TestInstance {
+        fn new() -> Self {
+            let host = Box::new(clap_host {
+                clap_version: CLAP_VERSION,
+                host_data: std::ptr::null_mut(),
+                name: c"Simulated Host".as_ptr(),
+                vendor: c"NIH-plug".as_ptr(),
+                url: c"".as_ptr(),
+                version: c"1.0.0".as_ptr(),
+                get_extension: Some(host_get_extension),
+                request_restart: Some(host_request),
+                request_process: Some(host_request),
+                request_callback: Some(host_request),
+            });
+
+            unsafe {
+                assert!((clap_entry.init.unwrap())(c"".as_ptr()));
+                let factory = (clap_entry.get_factory.unwrap())(CLAP_PLUGIN_FACTORY_ID.as_ptr())
+                    as *const clap_plugin_factory;
+                assert!(!factory.is_null());
+                let plugin = ((*factory).create_plugin.unwrap())(
+                    factory,
+                    &*host,
+                    c"com.moist-plugins-gmbh.poly-mod-synth".as_ptr(),
+                );
+                assert!(!plugin.is_null());
+
+                assert!(((*plugin).init.unwrap())(plugin));
+                assert!(((*plugin).activate.unwrap())(
+                    plugin,
+                    SAMPLE_RATE,
+                    1,
+                    BLOCK_SIZE
+                ));
+                assert!(((*plugin).start_processing.unwrap())(plugin));
+
+                let params = ((*plugin).get_extension.unwrap())(plugin, CLAP_EXT_PARAMS.as_ptr())
+                    as *const clap_plugin_params;
+                let voice_info =
+                    ((*plugin).get_extension.unwrap())(plugin, CLAP_EXT_VOICE_INFO.as_ptr())
+                        as *const clap_plugin_voice_info;
+                assert!(!params.is_null() && !voice_info.is_null());
+
+                Self {
+                    plugin,
+                    params,
+                    voice_info,
+                    _host: host,
+                }
+            }
+        }
+
+        /// The CLAP ID of the parameter with this name.
+        fn param_id(&self, name: &str) -> clap_id {
+            unsafe {
+                let count = ((*self.params).count.unwrap())(self.plugin);
+                let mut info: clap_param_info = mem::zeroed();
+                for index in 0..count {
+                    assert!(((*self.params).get_info.unwrap())(
+                        self.plugin,
+                        index,
+                        &mut info
+                    ));
+                    if CStr::from_ptr(info.name.as_ptr()).to_str() == Ok(name) {
+                        return info.id;
+                    }
+                }
+            }
+
+            panic!("There is no '{name}' parameter")
+        }
+
+        /// A parameter value event at the start of the block that sets the parameter to the value
+        /// represented by `text`.
+        fn param_value_event(&self, param_id: clap_id, text: &CStr) -> clap_event_param_value {
+            let mut value = 0.0;
+            assert!(unsafe {
+                ((*self.params).text_to_value.unwrap())(
+                    self.plugin,
+                    param_id,
+                    text.as_ptr(),
+                    &mut value,
+                )
+            });
+
+            clap_event_param_value {
+                header: clap_event_header {
+                    size: mem::size_of::<clap_event_param_value>() as u32,
+                    time: 0,
+                    space_id: CLAP_CORE_EVENT_SPACE_ID,
+                    type_: CLAP_EVENT_PARAM_VALUE,
+                    flags: 0,
+                },
+                param_id,
+                cookie: std::ptr::null_mut(),
+                note_id: -1,
+                port_index: -1,
+                channel: -1,
+                key: -1,
+                value,
+            }
+        }
+
+        /// The plugin's current voice capacity.
+        fn voice_capacity(&self) -> u32 {
+            let mut info: clap_voice_info = unsafe { mem::zeroed() };
+            assert!(unsafe { ((*self.voice_info).get.unwrap())(self.plugin, &mut info) });
+
+            info.voice_count
+        }
+
+        /// Process a block with `events` at the start of the block. Returns the `(note_id, key)`
+        /// pairs from the note end events the plugin sent, and whether the output was silent.
+        fn process(&self, events: &[*const clap_event_header]) -> (Vec<(i32, i16)>, bool) {
+            let mut input = vec![vec![0.0f32; BLOCK_SIZE as usize]; 2];
+            let mut output = vec![vec![0.0f32; BLOCK_SIZE as usize]; 2];
+            let mut input_ptrs: Vec<*mut f32> = input.iter_mut().map(|c| c.as_mut_ptr()).collect();
+            let mut output_ptrs: Vec<*mut f32> =
+                output.iter_mut().map(|c| c.as_mut_ptr()).collect();
+            let audio_input = clap_audio_buffer {
+                data32: input_ptrs.as_mut_ptr(),
+                data64: std::ptr::null_mut(),
+                channel_count: 2,
+                latency: 0,
+                constant_mask: 0,
+            };
+            let mut audio_output = clap_audio_buffer {
+                data32: output_ptrs.as_mut_ptr(),
+                data64: std::ptr::null_mut(),
+                channel_count: 2,
+                latency: 0,
+                constant_mask: 0,
+            };
+
+            let in_events = clap_input_events {
+                ctx: &events as *const &[*const clap_event_header] as *mut c_void,
+                size: Some(input_events_size),
+                get: Some(input_events_get),
+            };
+            let mut ended_notes: Vec<(i32, i16)> = Vec::new();
+            let out_events = clap_output_events {
+                ctx: &mut ended_notes as *mut Vec<(i32, i16)> as *mut c_void,
+                try_push: Some(record_note_end),
+            };
+
+            let process = clap_process {
+                steady_time: -1,
+                frames_count: BLOCK_SIZE,
+                transport: std::ptr::null(),
+                audio_inputs: &audio_input,
+                audio_outputs: &mut audio_output,
+                audio_inputs_count: 1,
+                audio_outputs_count: 1,
+                in_events: &in_events,
+                out_events: &out_events,
+            };
+            unsafe { ((*self.plugin).process.unwrap())(self.plugin, &process) };
+
+            let silent = output.iter().flatten().all(|&sample| sample == 0.0);
+
+            (ended_notes, silent)
+        }
+    }
+
+    impl Drop for TestInstance {
+        fn drop(&mut self) {
+            unsafe {
+                ((*self.plugin).stop_processing.unwrap())(self.plugin);
+                ((*self.plugin).deactivate.unwrap())(self.plugin);
+                ((*self.plugin).destroy.unwrap())(self.plugin);
+            }
+        }
+    }
+
+    unsafe extern "C" fn host_get_extension(
+        _host: *const clap_host,
+        extension_id: *const c_char,
+    ) -> *const c_void {
+        if CStr::from_ptr(extension_id) == CLAP_EXT_VOICE_INFO {
+            &HOST_VOICE_INFO as *const _ as *const c_void
+        } else {
+            std::ptr::null()
+        }
+    }
+
+    unsafe extern "C" fn host_request(_host: *const clap_host) {}
+
+    unsafe extern "C" fn host_voice_info_changed(_host: *const clap_host) {}
+
+    unsafe extern "C" fn input_events_size(list: *const clap_input_events) -> u32 {
+        let events = &*((*list).ctx as *const &[*const clap_event_header]);
+
+        events.len() as u32
+    }
+
+    unsafe extern "C" fn input_events_get(
+        list: *const clap_input_events,
+        index: u32,
+    ) -> *const clap_event_header {
+        let events = &*((*list).ctx as *const &[*const clap_event_header]);
+
+        events[index as usize]
+    }
+
+    unsafe extern "C" fn record_note_end(
+        list: *const clap_output_events,
+        event: *const clap_event_header,
+    ) -> bool {
+        if (*event).space_id == CLAP_CORE_EVENT_SPACE_ID && (*event).type_ == CLAP_EVENT_NOTE_END {
+            let ended_notes = &mut *((*list).ctx as *mut Vec<(i32, i16)>);
+            let event = &*(event as *const clap_event_note);
+            ended_notes.push((event.note_id, event.key));
+        }
+
+        true
+    }
+
+    /// A note event at the start of the block.
+    fn note_event(type_: u16, note_id: i32, key: i16) -> clap_event_note {
+        clap_event_note {
+            header: clap_event_header {
+                size: mem::size_of::<clap_event_note>() as u32,
+                time: 0,
+                space_id: CLAP_CORE_EVENT_SPACE_ID,
+                type_,
+                flags: 0,
+            },
+            note_id,
+            port_index: 0,
+            channel: 0,
+            key,
+            velocity: 1.0,
+        }
+    }
+
+    #[test]
+    fn reduce_polyphony_mid_note() {
+        let instance = TestInstance::new();
+        assert_eq!(instance.voice_capacity(), NUM_VOICES);
+
+        let note_ons: Vec<clap_event_note> = [60, 62, 64, 67]
+            .into_iter()
+            .enumerate()
+            .map(|(note_id, key)| note_event(CLAP_EVENT_NOTE_ON, note_id as i32, key))
+            .collect();
+        let headers: Vec<*const clap_event_header> = note_ons
+            .iter()
+            .map(|event| &event.header as *const _)
+            .collect();
+        assert_eq!(instance.process(&headers), (vec![], false));
+
+        // The second note is still releasing after this block
+        let note_off = note_event(CLAP_EVENT_NOTE_OFF, 1, 62);
+        assert_eq!(
+            instance.process(&[&note_off.header as *const _]),
+            (vec![], false)
+        );
+
+        // Lowering the polyphony steals the releasing voice first, and then the oldest held voice
+        let voices = instance.param_value_event(instance.param_id("Voices"), c"2");
+        assert_eq!(
+            instance.process(&[&voices.header as *const _]),
+            (vec![(1, 62), (0, 60)], false)
+        );
+        assert_eq!(instance.voice_capacity(), 2);
+
+        // The remaining voices keep playing
+        assert_eq!(instance.process(&[]), (vec![], false));
+    }
+}
@@ -575,6 +575,14 @@ impl CompressorBank {
         // Sidechain data doesn't need to be reset as it will be overwritten immediately before use
     }
 
+    /// Send empty analyzer data to the editor. Called when the sample rate changes, since the old
+    /// data's bins would otherwise be drawn at the wrong frequencies until the next window has been
+    /// processed.
+    pub fn clear_analyzer_data(&mut self) {
+        self.analyzer_input_data.input_buffer().num_bins = 0;
+        self.analyzer_input_data.publish();
+    }
+
     /// Apply the magnitude compression to a buffer of FFT bins. The compressors are first updated
     /// if needed. The overlap amount is needed to compute the effective sample rate. The
     /// `first_non_dc_bin` argument is used to avoid upwards compression on the DC bins, or the
@@ -340,9 +340,14 @@ impl Plugin for SpectralCompressor {
         // Needed to update the compressors later
         self.buffer_config = *buffer_config;
 
-        // And this is used in the editor to draw the analyzer
-        self.sample_rate
-            .store(buffer_config.sample_rate, Ordering::Relaxed);
+        // And this is used in the editor to draw the analyzer. Data computed at the old sample rate
+        // would be drawn at the wrong frequencies, so that is cleared.
+        let old_sample_rate = self
+            .sample_rate
+            .swap(buffer_config.sample_rate, Ordering::Relaxed);
+        if buffer_config.sample_rate != old_sample_rate {
+            self.compressor_bank.clear_analyzer_data();
+        }
 
         // This plugin can accept a variable number of audio channels, so we need to resize
         // channel-dependent data structures accordingly
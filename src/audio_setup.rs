@@ -27,11 +27,81 @@ pub struct AudioIOLayout {
     /// (<https://github.com/rust-lang/rust/issues/67441>).
     pub aux_output_ports: &'static [NonZeroU32],
 
+    /// The speaker arrangement for the main input port. Set this to declare a surround layout like
+    /// [`ChannelLayout::Surround51`] so hosts can instantiate the plugin on surround tracks. The
+    /// layout's channel count must match `main_input_channels`. When this is `None`, one and two
+    /// channel ports are treated as mono and stereo, and ports with more channels are reported as
+    /// discrete channels without a speaker arrangement.
+    pub main_input_channel_layout: Option<ChannelLayout>,
+    /// The speaker arrangement for the main output port. See
+    /// [`main_input_channel_layout`][Self::main_input_channel_layout].
+    pub main_output_channel_layout: Option<ChannelLayout>,
+
     /// Optional names for the audio ports. Defining these can be useful for plugins with multiple
     /// output and input ports.
     pub names: PortNames,
 }
 
+/// A speaker arrangement for a multichannel audio port. These are the common ITU-R BS.775 and
+/// BS.2051 layouts. Channels are always stored in the order returned by
+/// [`speakers()`][Self::speakers()], which is the same order used by CLAP, VST3, and
+/// `WAVE_FORMAT_EXTENSIBLE`. Use [`Buffer::speaker_channel()`] to access a buffer's channels by
+/// speaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChannelLayout {
+    /// A single channel. The channel is considered to be a center speaker.
+    Mono,
+    /// L, R.
+    Stereo,
+    /// 5.0 surround: L, R, C, Ls, Rs.
+    Surround50,
+    /// 5.1 surround: L, R, C, LFE, Ls, Rs.
+    Surround51,
+    /// 7.0 surround: L, R, C, Lrs, Rrs, Lss, Rss.
+    Surround70,
+    /// 7.1 surround: L, R, C, LFE, Lrs, Rrs, Lss, Rss.
+    Surround71,
+    /// 5.1.4 surround, a common Dolby Atmos bed: 5.1 followed by the top front and top rear
+    /// left/right pairs.
+    Surround514,
+    /// 7.1.4 surround, a common Dolby Atmos bed: 7.1 followed by the top front and top rear
+    /// left/right pairs.
+    Surround714,
+}
+
+/// A speaker in a [`ChannelLayout`]. These are named after the speaker positions used by CLAP and
+/// `WAVE_FORMAT_EXTENSIBLE`. In 5.x layouts the surround channels (Ls, Rs) are the back left and
+/// right speakers. 7.x layouts add the side speakers, with the back speakers acting as the rear
+/// surround channels (Lrs, Rrs) and the side speakers acting as the side surround channels (Lss,
+/// Rss).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Speaker {
+    /// L.
+    FrontLeft,
+    /// R.
+    FrontRight,
+    /// C.
+    FrontCenter,
+    /// LFE.
+    Lfe,
+    /// Ls in 5.x layouts, Lrs in 7.x layouts.
+    BackLeft,
+    /// Rs in 5.x layouts, Rrs in 7.x layouts.
+    BackRight,
+    /// Lss in 7.x layouts.
+    SideLeft,
+    /// Rss in 7.x layouts.
+    SideRight,
+    /// Ltf.
+    TopFrontLeft,
+    /// Rtf.
+    TopFrontRight,
+    /// Ltr.
+    TopBackLeft,
+    /// Rtr.
+    TopBackRight,
+}
+
 /// Construct a `NonZeroU32` value at compile time. Equivalent to `NonZeroU32::new(n).unwrap()`.
 pub const fn new_nonzero_u32(n: u32) -> NonZeroU32 {
     match NonZeroU32::new(n) {
@@ -84,6 +154,12 @@ pub struct BufferConfig {
     /// The maximum buffer size the host will use. The plugin should be able to accept variable
     /// sized buffers up to this size, or between the minimum and the maximum buffer size if both
     /// are set.
+    ///
+    /// The buffers passed to [`Plugin::process()`][crate::prelude::Plugin::process()] are
+    /// guaranteed to never exceed this size, so scratch buffers allocated with this size in
+    /// [`Plugin::initialize()`][crate::prelude::Plugin::initialize()] are always large enough. If
+    /// the host sends larger buffers than it promised, then the wrapper splits those buffers into
+    /// multiple smaller blocks.
     pub max_buffer_size: u32,
     /// The current processing mode. The host will reinitialize the plugin any time this changes.
     pub process_mode: ProcessMode,
@@ -114,6 +190,8 @@ impl AudioIOLayout {
             main_output_channels: None,
             aux_input_ports: &[],
             aux_output_ports: &[],
+            main_input_channel_layout: None,
+            main_output_channel_layout: None,
             names: PortNames::const_default(),
         }
     }
@@ -125,6 +203,19 @@ impl AudioIOLayout {
             return name.to_owned();
         }
 
+        // Surround layouts are named after the main port's speaker arrangement
+        let surround_layout = self
+            .main_output_layout()
+            .or_else(|| self.main_input_layout())
+            .filter(|layout| layout.is_surround());
+        if let Some(layout) = surround_layout {
+            return if self.aux_input_ports.is_empty() {
+                layout.name().to_owned()
+            } else {
+                format!("{} with sidechain", layout.name())
+            };
+        }
+
         // If the name is not set then we'll try to come up with something descriptive
         match (
             self.main_input_channels
@@ -150,6 +241,20 @@ impl AudioIOLayout {
         }
     }
 
+    /// The main input port's speaker arrangement. This is either taken from
+    /// [`main_input_channel_layout`][Self::main_input_channel_layout], or it is mono or stereo
+    /// depending on the number of channels. Returns `None` if the plugin doesn't have a main input
+    /// port, or if the port's channels are discrete channels without a speaker arrangement.
+    pub fn main_input_layout(&self) -> Option<ChannelLayout> {
+        port_layout(self.main_input_channels, self.main_input_channel_layout)
+    }
+
+    /// The main output port's speaker arrangement. See
+    /// [`main_input_layout()`][Self::main_input_layout()].
+    pub fn main_output_layout(&self) -> Option<ChannelLayout> {
+        port_layout(self.main_output_channels, self.main_output_channel_layout)
+    }
+
     /// The name for the main input port. Either generated or taken from the `names` field.
     pub fn main_input_name(&self) -> String {
         self.names.main_input.unwrap_or("Input").to_owned()
@@ -189,6 +294,136 @@ impl AudioIOLayout {
     }
 }
 
+impl ChannelLayout {
+    /// All supported layouts, ordered by their channel counts.
+    pub const ALL: [ChannelLayout; 8] = [
+        ChannelLayout::Mono,
+        ChannelLayout::Stereo,
+        ChannelLayout::Surround50,
+        ChannelLayout::Surround51,
+        ChannelLayout::Surround70,
+        ChannelLayout::Surround71,
+        ChannelLayout::Surround514,
+        ChannelLayout::Surround714,
+    ];
+
+    /// The speakers in this layout, in the order their channels are stored in.
+    pub const fn speakers(self) -> &'static [Speaker] {
+        use Speaker::*;
+
+        match self {
+            ChannelLayout::Mono => &[FrontCenter],
+            ChannelLayout::Stereo => &[FrontLeft, FrontRight],
+            ChannelLayout::Surround50 => &[FrontLeft, FrontRight, FrontCenter, BackLeft, BackRight],
+            ChannelLayout::Surround51 => {
+                &[FrontLeft, FrontRight, FrontCenter, Lfe, BackLeft, BackRight]
+            }
+            ChannelLayout::Surround70 => &[
+                FrontLeft,
+                FrontRight,
+                FrontCenter,
+                BackLeft,
+                BackRight,
+                SideLeft,
+                SideRight,
+            ],
+            ChannelLayout::Surround71 => &[
+                FrontLeft,
+                FrontRight,
+                FrontCenter,
+                Lfe,
+                BackLeft,
+                BackRight,
+                SideLeft,
+                SideRight,
+            ],
+            ChannelLayout::Surround514 => &[
+                FrontLeft,
+                FrontRight,
+                FrontCenter,
+                Lfe,
+                BackLeft,
+                BackRight,
+                TopFrontLeft,
+                TopFrontRight,
+                TopBackLeft,
+                TopBackRight,
+            ],
+            ChannelLayout::Surround714 => &[
+                FrontLeft,
+                FrontRight,
+                FrontCenter,
+                Lfe,
+                BackLeft,
+                BackRight,
+                SideLeft,
+                SideRight,
+                TopFrontLeft,
+                TopFrontRight,
+                TopBackLeft,
+                TopBackRight,
+            ],
+        }
+    }
+
+    /// The number of channels in this layout.
+    pub const fn num_channels(self) -> u32 {
+        self.speakers().len() as u32
+    }
+
+    /// The index of `speaker`'s channel, or `None` if the layout doesn't contain that speaker.
+    pub fn channel_index(self, speaker: Speaker) -> Option<usize> {
+        self.speakers()
+            .iter()
+            .position(|candidate| *candidate == speaker)
+    }
+
+    /// Whether this is a surround layout, i.e. anything other than mono or stereo.
+    pub fn is_surround(self) -> bool {
+        !matches!(self, ChannelLayout::Mono | ChannelLayout::Stereo)
+    }
+
+    /// The layout's conventional name, like `5.1`.
+    pub fn name(self) -> &'static str {
+        match self {
+            ChannelLayout::Mono => "Mono",
+            ChannelLayout::Stereo => "Stereo",
+            ChannelLayout::Surround50 => "5.0",
+            ChannelLayout::Surround51 => "5.1",
+            ChannelLayout::Surround70 => "7.0",
+            ChannelLayout::Surround71 => "7.1",
+            ChannelLayout::Surround514 => "5.1.4",
+            ChannelLayout::Surround714 => "7.1.4",
+        }
+    }
+}
+
+/// The speaker arrangement for a port with `num_channels` channels and an optional explicitly
+/// declared layout. See [`AudioIOLayout::main_input_layout()`].
+fn port_layout(
+    num_channels: Option<NonZeroU32>,
+    declared_layout: Option<ChannelLayout>,
+) -> Option<ChannelLayout> {
+    let num_channels = num_channels?.get();
+    match declared_layout {
+        Some(layout) if layout.num_channels() == num_channels => Some(layout),
+        Some(layout) => {
+            nih_debug_assert_failure!(
+                "The {} channel layout has {} channels, but the port has {} channels",
+                layout.name(),
+                layout.num_channels(),
+                num_channels
+            );
+            None
+        }
+        None => match num_channels {
+            1 => Some(ChannelLayout::Mono),
+            2 => Some(ChannelLayout::Stereo),
+            _ => None,
+        },
+    }
+}
+
 impl PortNames {
     /// [`PortNames::default()`], but as a const function. Used when initializing
     /// `Plugin::AUDIO_IO_LAYOUTS`. (<https://github.com/rust-lang/rust/issues/67792>)
@@ -202,3 +437,52 @@ impl PortNames {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channel_layouts() {
+        for layout in ChannelLayout::ALL {
+            let speakers = layout.speakers();
+            for (channel_idx, speaker) in speakers.iter().enumerate() {
+                assert_eq!(layout.channel_index(*speaker), Some(channel_idx));
+            }
+        }
+
+        assert_eq!(ChannelLayout::Surround51.num_channels(), 6);
+        assert_eq!(ChannelLayout::Surround714.num_channels(), 12);
+        assert_eq!(
+            ChannelLayout::Surround51.channel_index(Speaker::Lfe),
+            Some(3)
+        );
+        assert_eq!(
+            ChannelLayout::Surround71.channel_index(Speaker::SideRight),
+            Some(7)
+        );
+        assert_eq!(ChannelLayout::Surround50.channel_index(Speaker::Lfe), None);
+    }
+
+    #[test]
+    fn port_layouts() {
+        let layout = AudioIOLayout {
+            main_input_channels: NonZeroU32::new(2),
+            main_output_channels: NonZeroU32::new(6),
+            main_output_channel_layout: Some(ChannelLayout::Surround51),
+            ..AudioIOLayout::const_default()
+        };
+        assert_eq!(layout.main_input_layout(), Some(ChannelLayout::Stereo));
+        assert_eq!(layout.main_output_layout(), Some(ChannelLayout::Surround51));
+        assert_eq!(layout.name(), "5.1");
+
+        // Without a declared layout, multichannel ports are discrete channels
+        let layout = AudioIOLayout {
+            main_input_channels: NonZeroU32::new(6),
+            main_output_channels: NonZeroU32::new(6),
+            ..AudioIOLayout::const_default()
+        };
+        assert_eq!(layout.main_output_layout(), None);
+        assert_eq!(layout.name(), "6 inputs, 6 outputs");
+    }
+}
@@ -1,11 +1,18 @@
 //! Adapters and utilities for working with audio buffers.
 
 use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::audio_setup::{ChannelLayout, Speaker};
+use crate::params::smoothing::Smoother;
+use crate::util;
 
 mod blocks;
+mod channels;
 mod samples;
 
 pub use blocks::{Block, BlockChannelsIter, BlocksIter};
+pub use channels::ChannelsIter;
 pub use samples::{ChannelSamples, ChannelSamplesIter, SamplesIter};
 
 /// The audio buffers used during processing. This contains the output audio output buffers with the
@@ -63,6 +70,169 @@ impl<'a> Buffer<'a> {
         &self.output_slices
     }
 
+    /// Iterate over the channels, returning each channel's index along with its samples. Unlike
+    /// iterating over [`as_slice()`][Self::as_slice()], this makes it easy to write per-channel
+    /// processing code that works for any number of channels, for instance by indexing into a
+    /// per-channel filter state array.
+    #[inline]
+    pub fn iter_channels<'slice>(&'slice mut self) -> ChannelsIter<'slice, 'a> {
+        ChannelsIter {
+            channels: self.output_slices.iter_mut().enumerate(),
+        }
+    }
+
+    /// Get the left and right channels if this is a stereo buffer. Returns `None` if the buffer
+    /// does not contain exactly two channels, so mono and surround layouts can be handled
+    /// separately instead of silently processing only the first two channels:
+    ///
+    /// ```ignore
+    /// match buffer.stereo_pair() {
+    ///     Some((left, right)) => self.process_stereo(left, right),
+    ///     None => {
+    ///         for (channel_idx, channel) in buffer.iter_channels() {
+    ///             self.process_mono(channel_idx, channel);
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    #[inline]
+    pub fn stereo_pair(&mut self) -> Option<(&mut [f32], &mut [f32])> {
+        match self.output_slices.as_mut_slice() {
+            [left, right] => Some((&mut **left, &mut **right)),
+            _ => None,
+        }
+    }
+
+    /// Get the channel for a speaker in a buffer with the given channel layout, for instance the
+    /// LFE channel of a 5.1 buffer. The main ports' layouts can be obtained from the
+    /// [`AudioIOLayout`][crate::prelude::AudioIOLayout] passed to
+    /// [`Plugin::initialize()`][crate::prelude::Plugin::initialize()] using
+    /// [`main_output_layout()`][crate::prelude::AudioIOLayout::main_output_layout()]. Returns
+    /// `None` if the layout doesn't contain the speaker, or if the buffer's channel count doesn't
+    /// match the layout.
+    #[inline]
+    pub fn speaker_channel(
+        &mut self,
+        layout: ChannelLayout,
+        speaker: Speaker,
+    ) -> Option<&mut [f32]> {
+        if self.output_slices.len() != layout.num_channels() as usize {
+            return None;
+        }
+
+        let channel_idx = layout.channel_index(speaker)?;
+        Some(&mut *self.output_slices[channel_idx])
+    }
+
+    /// Convert a stereo buffer from left/right to mid/side in place using
+    /// [`util::stereo_to_mid_side()`]. The first channel then contains the mid signal and the second
+    /// channel contains the side signal. Returns `false` and leaves the buffer untouched if it does
+    /// not contain exactly two channels. A warning is logged the first time that happens.
+    pub fn stereo_to_mid_side(&mut self) -> bool {
+        match self.stereo_pair() {
+            Some((left, right)) => {
+                util::stereo_to_mid_side(left, right);
+                true
+            }
+            None => {
+                warn_not_stereo(self.channels());
+                false
+            }
+        }
+    }
+
+    /// The inverse of [`stereo_to_mid_side()`][Self::stereo_to_mid_side()]. Converts a buffer
+    /// containing mid and side channels back to left/right using
+    /// [`util::mid_side_to_stereo()`]. Returns `false` and leaves the buffer untouched if it does
+    /// not contain exactly two channels. A warning is logged the first time that happens.
+    pub fn mid_side_to_stereo(&mut self) -> bool {
+        match self.stereo_pair() {
+            Some((mid, side)) => {
+                util::mid_side_to_stereo(mid, side);
+                true
+            }
+            None => {
+                warn_not_stereo(self.channels());
+                false
+            }
+        }
+    }
+
+    /// Write the average of all channels to `output`, which must have the same length as this
+    /// buffer. Averaging instead of summing keeps the level of signals that are identical on every
+    /// channel intact. A mono buffer is copied as is, and `output` is filled with silence if the
+    /// buffer does not have any channels. This can for instance be used to compute a single
+    /// envelope for linked stereo or surround processing.
+    pub fn sum_to_mono(&self, output: &mut [f32]) {
+        nih_debug_assert_eq!(output.len(), self.num_samples);
+
+        output.fill(0.0);
+        for channel in &self.output_slices {
+            for (output_sample, sample) in output.iter_mut().zip(channel.iter()) {
+                *output_sample += *sample;
+            }
+        }
+
+        if self.channels() > 1 {
+            let gain = (self.channels() as f32).recip();
+            for output_sample in output {
+                *output_sample *= gain;
+            }
+        }
+    }
+
+    /// Copy the first channel to all other channels. This is useful for plugins that process a
+    /// single channel of audio and that should output that signal on every channel regardless of
+    /// the layout. Does nothing for mono buffers and buffers without any channels.
+    pub fn duplicate_mono(&mut self) {
+        if let Some((first_channel, other_channels)) = self.output_slices.split_first_mut() {
+            for channel in other_channels {
+                channel.copy_from_slice(first_channel);
+            }
+        }
+    }
+
+    /// Multiply every sample by `gain`'s value for that sample, using the same gain for every
+    /// channel. This advances the smoother by one step per sample, just like calling
+    /// [`Smoother::next()`] for every sample would. When the smoother is not currently smoothing,
+    /// the entire buffer is multiplied by the smoother's current value instead. The smoother is
+    /// still advanced for buffers without any channels.
+    pub fn apply_smoothed_gain(&mut self, gain: &Smoother<f32>) {
+        if !gain.is_smoothing() {
+            let gain = gain.next();
+            for channel in self.output_slices.iter_mut() {
+                apply_gain(channel, gain);
+            }
+
+            return;
+        }
+
+        for sample_idx in 0..self.num_samples {
+            let gain = gain.next();
+            for channel in self.output_slices.iter_mut() {
+                channel[sample_idx] *= gain;
+            }
+        }
+    }
+
+    /// The same as [`apply_smoothed_gain()`][Self::apply_smoothed_gain()], but with a separate
+    /// smoother for every channel. `gains` must contain one smoother per channel. Extra smoothers
+    /// are ignored and channels without a smoother are left untouched, but this will trigger a
+    /// debug assertion failure.
+    pub fn apply_smoothed_gain_per_channel(&mut self, gains: &[&Smoother<f32>]) {
+        nih_debug_assert_eq!(gains.len(), self.channels());
+
+        for (channel, gain) in self.output_slices.iter_mut().zip(gains) {
+            if gain.is_smoothing() {
+                for sample in channel.iter_mut() {
+                    *sample *= gain.next();
+                }
+            } else {
+                apply_gain(channel, gain.next());
+            }
+        }
+    }
+
     /// Iterate over the samples, returning a channel iterator for each sample.
     #[inline]
     pub fn iter_samples<'slice>(&'slice mut self) -> SamplesIter<'slice, 'a> {
@@ -133,6 +303,29 @@ impl<'a> Buffer<'a> {
     }
 }
 
+/// Multiply every sample in `channel` by `gain`. Unity gain leaves the channel untouched.
+fn apply_gain(channel: &mut [f32], gain: f32) {
+    if gain != 1.0 {
+        for sample in channel {
+            *sample *= gain;
+        }
+    }
+}
+
+/// Log a warning when trying to use mid/side processing on a non-stereo buffer. This only happens
+/// once to avoid flooding the log from the audio thread.
+fn warn_not_stereo(num_channels: usize) {
+    static WARNED: AtomicBool = AtomicBool::new(false);
+    if !WARNED.swap(true, Ordering::Relaxed) {
+        util::permit_alloc(|| {
+            nih_warn!(
+                "Mid/side conversion requires a stereo buffer, but the buffer has {num_channels} \
+                 channels. The buffer is left unchanged."
+            )
+        });
+    }
+}
+
 #[cfg(any(miri, test))]
 mod miri {
     use super::*;
@@ -199,3 +392,193 @@ mod miri {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::smoothing::SmoothingStyle;
+
+    /// Create a buffer with `num_channels` channels where every sample in channel `n` is set to
+    /// `n + 1`.
+    fn test_channels(num_channels: usize) -> Vec<Vec<f32>> {
+        (0..num_channels)
+            .map(|channel_idx| vec![(channel_idx + 1) as f32; 16])
+            .collect()
+    }
+
+    fn make_buffer(real_buffers: &mut [Vec<f32>]) -> Buffer<'_> {
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.set_slices(16, |output_slices| {
+                *output_slices = real_buffers
+                    .iter_mut()
+                    .map(|channel| channel.as_mut_slice())
+                    .collect();
+            })
+        };
+
+        buffer
+    }
+
+    #[test]
+    fn mono() {
+        let mut real_buffers = test_channels(1);
+        let mut buffer = make_buffer(&mut real_buffers);
+
+        assert!(buffer.stereo_pair().is_none());
+        assert_eq!(buffer.iter_channels().count(), 1);
+
+        let mut mono = [0.0; 16];
+        buffer.sum_to_mono(&mut mono);
+        assert_eq!(mono, [1.0; 16]);
+
+        buffer.duplicate_mono();
+        assert_eq!(real_buffers[0], [1.0; 16]);
+    }
+
+    #[test]
+    fn stereo() {
+        let mut real_buffers = test_channels(2);
+        let mut buffer = make_buffer(&mut real_buffers);
+
+        let (left, right) = buffer.stereo_pair().unwrap();
+        assert_eq!(left[0], 1.0);
+        assert_eq!(right[0], 2.0);
+
+        let mut mono = [0.0; 16];
+        buffer.sum_to_mono(&mut mono);
+        assert_eq!(mono, [1.5; 16]);
+
+        buffer.duplicate_mono();
+        assert_eq!(real_buffers, [[1.0; 16], [1.0; 16]]);
+    }
+
+    #[test]
+    fn four_channels() {
+        let mut real_buffers = test_channels(4);
+        let mut buffer = make_buffer(&mut real_buffers);
+
+        assert!(buffer.stereo_pair().is_none());
+        for (channel_idx, channel) in buffer.iter_channels() {
+            assert!(channel
+                .iter()
+                .all(|sample| *sample == (channel_idx + 1) as f32));
+            channel.fill(channel_idx as f32);
+        }
+
+        let mut mono = [0.0; 16];
+        buffer.sum_to_mono(&mut mono);
+        assert_eq!(mono, [1.5; 16]);
+
+        buffer.duplicate_mono();
+        assert!(real_buffers.iter().all(|channel| channel == &[0.0; 16]));
+    }
+
+    #[test]
+    fn surround_speakers() {
+        let mut real_buffers = test_channels(6);
+        let mut buffer = make_buffer(&mut real_buffers);
+
+        assert!(buffer.stereo_pair().is_none());
+        assert_eq!(
+            buffer.speaker_channel(ChannelLayout::Surround51, Speaker::Lfe),
+            Some(&mut [4.0; 16][..])
+        );
+        buffer
+            .speaker_channel(ChannelLayout::Surround51, Speaker::BackRight)
+            .unwrap()
+            .fill(0.0);
+        assert_eq!(real_buffers[5], [0.0; 16]);
+
+        let mut buffer = make_buffer(&mut real_buffers);
+        assert!(buffer
+            .speaker_channel(ChannelLayout::Surround51, Speaker::SideLeft)
+            .is_none());
+        // The layout needs to match the buffer's channel count
+        assert!(buffer
+            .speaker_channel(ChannelLayout::Surround71, Speaker::Lfe)
+            .is_none());
+    }
+
+    #[test]
+    fn mid_side() {
+        let mut real_buffers = test_channels(2);
+        let mut buffer = make_buffer(&mut real_buffers);
+
+        assert!(buffer.stereo_to_mid_side());
+        let (mid, side) = buffer.stereo_pair().unwrap();
+        assert_eq!(mid, [1.5; 16]);
+        assert_eq!(side, [-0.5; 16]);
+
+        assert!(buffer.mid_side_to_stereo());
+        assert_eq!(real_buffers, test_channels(2));
+    }
+
+    #[test]
+    fn mid_side_not_stereo() {
+        for num_channels in [1, 4] {
+            let mut real_buffers = test_channels(num_channels);
+            let mut buffer = make_buffer(&mut real_buffers);
+
+            assert!(!buffer.stereo_to_mid_side());
+            assert!(!buffer.mid_side_to_stereo());
+            assert_eq!(real_buffers, test_channels(num_channels));
+        }
+    }
+
+    /// Create a smoother that's in the middle of smoothing from 1 to 0.25 over 10 samples when
+    /// using a 1 kHz sample rate.
+    fn smoothing_gain() -> Smoother<f32> {
+        let smoother = Smoother::new(SmoothingStyle::Linear(10.0));
+        smoother.reset(1.0);
+        smoother.set_target(1000.0, 0.25);
+
+        smoother
+    }
+
+    #[test]
+    fn smoothed_gain() {
+        let mut real_buffers = test_channels(2);
+        let mut expected_buffers = test_channels(2);
+        let mut buffer = make_buffer(&mut real_buffers);
+
+        // Smoothing ends in the middle of the first buffer, and the second buffer takes the fast
+        // path
+        let gain = smoothing_gain();
+        let manual_gain = smoothing_gain();
+        for _ in 0..2 {
+            buffer.apply_smoothed_gain(&gain);
+            for sample_idx in 0..16 {
+                let gain = manual_gain.next();
+                for channel in expected_buffers.iter_mut() {
+                    channel[sample_idx] *= gain;
+                }
+            }
+        }
+
+        assert_eq!(real_buffers, expected_buffers);
+        assert_eq!(gain.steps_left(), manual_gain.steps_left());
+    }
+
+    #[test]
+    fn smoothed_gain_per_channel() {
+        let mut real_buffers = test_channels(2);
+        let mut expected_buffers = test_channels(2);
+        let mut buffer = make_buffer(&mut real_buffers);
+
+        let left_gain = smoothing_gain();
+        let right_gain = Smoother::new(SmoothingStyle::None);
+        right_gain.reset(0.5);
+        buffer.apply_smoothed_gain_per_channel(&[&left_gain, &right_gain]);
+
+        let manual_gain = smoothing_gain();
+        for sample in expected_buffers[0].iter_mut() {
+            *sample *= manual_gain.next();
+        }
+        for sample in expected_buffers[1].iter_mut() {
+            *sample *= 0.5;
+        }
+
+        assert_eq!(real_buffers, expected_buffers);
+    }
+}
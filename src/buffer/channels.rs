@@ -0,0 +1,28 @@
+//! Per-channel iterators.
+
+use std::iter::Enumerate;
+use std::slice::IterMut;
+
+/// An iterator over all channels in a buffer, yielding each channel's index along with its
+/// samples. Created using [`Buffer::iter_channels()`][super::Buffer::iter_channels()].
+pub struct ChannelsIter<'slice, 'sample: 'slice> {
+    pub(super) channels: Enumerate<IterMut<'slice, &'sample mut [f32]>>,
+}
+
+impl<'slice, 'sample> Iterator for ChannelsIter<'slice, 'sample> {
+    type Item = (usize, &'slice mut [f32]);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.channels
+            .next()
+            .map(|(channel_idx, channel)| (channel_idx, &mut **channel))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.channels.size_hint()
+    }
+}
+
+impl ExactSizeIterator for ChannelsIter<'_, '_> {}
@@ -7,6 +7,8 @@ pub mod init;
 pub mod process;
 
 // Contexts for more plugin-API specific features
+pub mod context_menu;
+pub mod param_indication;
 pub mod remote_controls;
 
 /// The currently active plugin API. This may be useful to display in an about screen in the
@@ -18,6 +20,18 @@ pub enum PluginApi {
     Vst3,
 }
 
+/// Information about the host application the plugin is running in, if the plugin API provides
+/// it. This can be used to add workarounds for bugs in specific hosts. See
+/// [`InitContext::host_info()`][init::InitContext::host_info()].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostInfo {
+    /// The host's name, like `Bitwig Studio`.
+    pub name: String,
+    /// The host's version, if the plugin API reports it. The format of this string is entirely up
+    /// to the host. VST3 does not expose the host's version, so this is always `None` there.
+    pub version: Option<String>,
+}
+
 impl Display for PluginApi {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
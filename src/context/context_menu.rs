@@ -0,0 +1,43 @@
+//! A context for adding entries to the host's [context
+//! menus](https://github.com/free-audio/clap/blob/main/include/clap/ext/context-menu.h) for CLAP
+//! plugins.
+
+use crate::prelude::Param;
+
+/// A context for adding entries to the host's [context
+/// menus](https://github.com/free-audio/clap/blob/main/include/clap/ext/context-menu.h) for CLAP
+/// plugins.
+///
+/// Hosts like Bitwig Studio show these entries when right clicking on a parameter, or when opening
+/// the plugin's own context menu. The entries are defined once when the plugin gets created, and
+/// the entries' actions are run on the main thread when the user selects them.
+pub trait ContextMenuContext {
+    type Menu: ContextMenu;
+
+    /// Add entries to the context menu for one of the plugin's parameters. Calling this multiple
+    /// times for the same parameter appends the new entries to the existing ones.
+    fn add_param_entries(&mut self, param: &impl Param, f: impl FnOnce(&mut Self::Menu));
+
+    /// Add entries to the plugin's global context menu. This menu is not tied to a specific
+    /// parameter.
+    fn add_global_entries(&mut self, f: impl FnOnce(&mut Self::Menu));
+}
+
+/// A (sub)menu that can contain entries, titles, separators, and nested submenus.
+pub trait ContextMenu {
+    /// Add an entry that runs `action` on the main thread when it gets selected. The action should
+    /// not block for long periods of time, and it must not try to lock the plugin.
+    fn add_entry(&mut self, label: impl Into<String>, action: impl Fn() + Send + Sync + 'static);
+
+    /// Add a greyed out entry that cannot be selected.
+    fn add_disabled_entry(&mut self, label: impl Into<String>);
+
+    /// Add a title. Titles cannot be selected.
+    fn add_title(&mut self, title: impl Into<String>);
+
+    /// Add a separator line.
+    fn add_separator(&mut self);
+
+    /// Add a submenu with the entries added in `f`. Empty submenus are not shown.
+    fn add_submenu(&mut self, label: impl Into<String>, f: impl FnOnce(&mut Self));
+}
@@ -2,8 +2,10 @@
 
 use std::sync::Arc;
 
+use super::param_indication::ParamIndication;
 use super::PluginApi;
-use crate::prelude::{Param, ParamPtr, Plugin, PluginState};
+use crate::prelude::{Param, ParamFlags, ParamPtr, Params, Plugin, PluginState};
+use crate::util::{ProcessLoad, Rng};
 
 /// Callbacks the plugin can make when the user interacts with its GUI such as updating parameter
 /// values. This is passed to the plugin during [`Editor::spawn()`][crate::prelude::Editor::spawn()]. All of
@@ -54,6 +56,29 @@ pub trait GuiContext: Send + Sync + 'static {
     /// mostly marked as unsafe for API reasons.
     unsafe fn raw_end_set_parameter(&self, param: ParamPtr);
 
+    /// Set multiple parameters to already normalized values in one go. Create a [`ParamSetter`]
+    /// and use [`ParamSetter::set_parameters_normalized()`] instead for a safe, user friendly API.
+    ///
+    /// The default implementation sets every parameter in its own gesture using the functions
+    /// above. The wrappers override this to coalesce the resulting host notifications.
+    ///
+    /// # Safety
+    ///
+    /// The implementing function still needs to check if the parameters actually exist. This
+    /// function is mostly marked as unsafe for API reasons.
+    unsafe fn raw_set_parameters_normalized(
+        &self,
+        values: &[(ParamPtr, f32)],
+        reset_smoothers: bool,
+    ) {
+        let _ = reset_smoothers;
+        for &(param, normalized) in values {
+            self.raw_begin_set_parameter(param);
+            self.raw_set_parameter_normalized(param, normalized);
+            self.raw_end_set_parameter(param);
+        }
+    }
+
     /// Serialize the plugin's current state to a serde-serializable object. Useful for implementing
     /// preset handling within a plugin's GUI.
     fn get_state(&self) -> PluginState;
@@ -61,8 +86,27 @@ pub trait GuiContext: Send + Sync + 'static {
     /// Restore the state from a previously serialized state object. This will block the GUI thread
     /// until the state has been restored and a parameter value rescan has been requested from the
     /// host. If the plugin is currently processing audio, then the parameter values will be
-    /// restored at the end of the current processing cycle.
+    /// restored at the end of the current processing cycle. The parameters' smoothers are reset to
+    /// the restored values, so the new state takes effect immediately instead of gliding towards it.
     fn set_state(&self, state: PluginState);
+
+    /// Measurements of how long the plugin's process function takes, for instance to show a CPU
+    /// meter in the editor. Returns `None` if NIH-plug was compiled without the
+    /// `process_profiling` feature.
+    fn process_load(&self) -> Option<Arc<ProcessLoad>> {
+        None
+    }
+
+    /// How the host is currently indicating a parameter's automation and controller mappings.
+    /// Returns [`ParamIndication::default()`] if the host or the plugin API doesn't support this.
+    /// Create a [`ParamSetter`] and use [`ParamSetter::indication()`] instead for a user friendly
+    /// API. The editor is notified through
+    /// [`Editor::param_values_changed()`][crate::prelude::Editor::param_values_changed()] when
+    /// this changes.
+    fn param_indication(&self, param: ParamPtr) -> ParamIndication {
+        let _ = param;
+        ParamIndication::default()
+    }
 }
 
 /// An way to run background tasks from the plugin's GUI, equivalent to the
@@ -180,4 +224,335 @@ impl<'a> ParamSetter<'a> {
     pub fn end_set_parameter<P: Param>(&self, param: &P) {
         unsafe { self.raw_context.raw_end_set_parameter(param.as_ptr()) };
     }
+
+    /// How the host is currently indicating the parameter's automation and controller mappings.
+    /// See [`GuiContext::param_indication()`].
+    pub fn indication<P: Param>(&self, param: &P) -> ParamIndication {
+        self.raw_context.param_indication(param.as_ptr())
+    }
+
+    /// Set multiple parameters to already normalized values at once, for instance when loading a
+    /// preset from the plugin's GUI. Parameter pointers can be obtained from
+    /// [`Params::param_map()`][crate::params::Params::param_map()] or [`Param::as_ptr()`]. Every
+    /// parameter is set in its own automation gesture since neither CLAP nor VST3 have a way to
+    /// group gestures, so this must not be called while one of these parameters is already being
+    /// changed between [`begin_set_parameter()`][Self::begin_set_parameter()] and
+    /// [`end_set_parameter()`][Self::end_set_parameter()]. Unlike calling those functions for
+    /// every parameter, the CLAP wrapper sends all changes to the host in a single parameter flush
+    /// and the VST3 wrapper wraps them in a single group edit if the host supports that.
+    ///
+    /// If `reset_smoothers` is set, then the parameters' smoothers immediately jump to the new
+    /// values instead of gradually moving towards them. In the VST3 wrapper this only works while
+    /// the plugin is not processing audio since the host applies the values during the next
+    /// process call otherwise.
+    pub fn set_parameters_normalized(&self, values: &[(ParamPtr, f32)], reset_smoothers: bool) {
+        unsafe {
+            self.raw_context
+                .raw_set_parameters_normalized(values, reset_smoothers)
+        };
+    }
+
+    /// Get the current unmodulated normalized value of the parameter in `params` with the string ID
+    /// `id`. Returns `None` for unknown IDs. See [`Params::param_by_id()`] for how the parameter is
+    /// looked up.
+    pub fn normalized_value_by_id<P: Params + ?Sized>(&self, params: &P, id: &str) -> Option<f32> {
+        let param = params.param_by_id(id)?;
+
+        Some(unsafe { param.unmodulated_normalized_value() })
+    }
+
+    /// Set the parameter in `params` with the string ID `id` to a normalized value. This is wrapped
+    /// in its own automation gesture, so it must not be called while the parameter is already being
+    /// changed between [`begin_set_parameter()`][Self::begin_set_parameter()] and
+    /// [`end_set_parameter()`][Self::end_set_parameter()]. Stepped parameters snap to the nearest
+    /// step, just like with [`set_parameter()`][Self::set_parameter()]. Returns `false` and does
+    /// nothing if there is no parameter with that ID. See [`Params::param_by_id()`] for how the
+    /// parameter is looked up.
+    pub fn set_parameter_normalized_by_id<P: Params + ?Sized>(
+        &self,
+        params: &P,
+        id: &str,
+        normalized: f32,
+    ) -> bool {
+        let Some(param) = params.param_by_id(id) else {
+            return false;
+        };
+
+        unsafe {
+            let normalized = param.preview_normalized(param.preview_plain(normalized));
+
+            self.raw_context.raw_begin_set_parameter(param);
+            self.raw_context
+                .raw_set_parameter_normalized(param, normalized);
+            self.raw_context.raw_end_set_parameter(param);
+        }
+
+        true
+    }
+
+    /// Reset every parameter in `params` to its default value, for instance for an `Init` button
+    /// in the plugin's GUI. This uses [`set_parameters_normalized()`][Self::set_parameters_normalized()],
+    /// so every parameter is set in its own automation gesture and the same rules apply. The
+    /// parameters' smoothers snap to the default values instead of gliding towards them.
+    ///
+    /// The bypass and gain reduction parameters are not reset, and persistent fields marked with
+    /// `#[persist = "key"]` are left untouched since those usually contain GUI state or other data
+    /// that isn't part of a preset. Neither CLAP nor VST3 has a concept of an init preset, so this
+    /// can only be triggered from the plugin's GUI.
+    pub fn reset_parameters_to_default<P: Params + ?Sized>(&self, params: &P) {
+        let excluded_flags = ParamFlags::BYPASS | ParamFlags::GAIN_REDUCTION;
+        let defaults: Vec<(ParamPtr, f32)> = params
+            .param_map()
+            .into_iter()
+            .filter(|(_, param, _)| !unsafe { param.flags() }.intersects(excluded_flags))
+            .map(|(_, param, _)| (param, unsafe { param.default_normalized_value() }))
+            .collect();
+
+        self.set_parameters_normalized(&defaults, true);
+    }
+
+    /// Set the parameters in `params` to random values, for instance for a `Randomize` button in
+    /// the plugin's GUI. Parameters are skipped when they have the
+    /// [`NON_RANDOMIZABLE`][ParamFlags::NON_RANDOMIZABLE] flag, when they cannot be automated, and
+    /// when they are the bypass or gain reduction parameter.
+    ///
+    /// `amount` constrains how far the new values can be from the current values, in normalized
+    /// units. With an amount of `1.0` every parameter gets a uniformly distributed random value in
+    /// its entire range. With an amount of `0.2` the new normalized values are picked uniformly
+    /// from the current normalized value ±0.2, clamped to the parameter's range. Stepped
+    /// parameters, including integer, boolean, and enum parameters, pick uniformly between the
+    /// steps in that range so every choice is equally likely.
+    ///
+    /// The values are applied using
+    /// [`set_parameters_normalized()`][Self::set_parameters_normalized()], so the same rules apply
+    /// and the parameters' smoothers snap to the new values.
+    pub fn randomize_parameters<P: Params + ?Sized>(&self, params: &P, rng: &mut Rng, amount: f32) {
+        let excluded_flags = ParamFlags::NON_RANDOMIZABLE
+            | ParamFlags::NON_AUTOMATABLE
+            | ParamFlags::HIDDEN
+            | ParamFlags::BYPASS
+            | ParamFlags::GAIN_REDUCTION;
+        let values: Vec<(ParamPtr, f32)> = params
+            .param_map()
+            .into_iter()
+            .filter(|(_, param, _)| !unsafe { param.flags() }.intersects(excluded_flags))
+            .map(|(_, param, _)| {
+                let (current, step_count) =
+                    unsafe { (param.unmodulated_normalized_value(), param.step_count()) };
+
+                (param, random_normalized(current, step_count, amount, rng))
+            })
+            .collect();
+
+        self.set_parameters_normalized(&values, true);
+    }
+}
+
+/// Pick a random normalized value within `amount` of `current`. See
+/// [`ParamSetter::randomize_parameters()`].
+fn random_normalized(current: f32, step_count: Option<usize>, amount: f32, rng: &mut Rng) -> f32 {
+    let amount = amount.clamp(0.0, 1.0);
+    let min = (current - amount).max(0.0);
+    let max = (current + amount).min(1.0);
+
+    match step_count {
+        Some(step_count) if step_count > 0 => {
+            let num_steps = step_count as f32;
+            let max_step = (max * num_steps).floor() as u32;
+            let min_step = ((min * num_steps).ceil() as u32).min(max_step);
+            let step = min_step + (rng.next_u32() % (max_step - min_step + 1));
+
+            step as f32 / num_steps
+        }
+        _ => min + (rng.next_f32() * (max - min)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::ParamMut;
+    use crate::prelude::{BoolParam, FloatParam, FloatRange, IntParam, IntRange};
+    use crate::testing::{GuiContextCall, ManyParams, TestGuiContext};
+
+    /// The number of automation gestures that were started and ended on `context`.
+    fn gesture_counts(context: &TestGuiContext) -> (usize, usize) {
+        let calls = context.calls();
+        let num_begins = calls
+            .iter()
+            .filter(|call| matches!(call, GuiContextCall::BeginSetParameter(_)))
+            .count();
+        let num_ends = calls
+            .iter()
+            .filter(|call| matches!(call, GuiContextCall::EndSetParameter(_)))
+            .count();
+
+        (num_begins, num_ends)
+    }
+
+    #[test]
+    fn set_parameters_normalized() {
+        let params = Arc::new(ManyParams::default());
+        let preset: Vec<(ParamPtr, f32)> = params
+            .params
+            .iter()
+            .map(|param| (param.as_ptr(), 0.5))
+            .collect();
+
+        let context = TestGuiContext::new(params.clone());
+        ParamSetter::new(&context).set_parameters_normalized(&preset, true);
+
+        // When using the default batch implementation, every parameter gets its own gesture
+        let expected_calls: Vec<GuiContextCall> = preset
+            .iter()
+            .flat_map(|&(param, normalized)| {
+                [
+                    GuiContextCall::BeginSetParameter(param),
+                    GuiContextCall::SetParameterNormalized(param, normalized),
+                    GuiContextCall::EndSetParameter(param),
+                ]
+            })
+            .collect();
+        assert_eq!(context.calls(), expected_calls);
+    }
+
+    struct ResetParams {
+        gain: FloatParam,
+        voices: IntParam,
+        bypass: BoolParam,
+    }
+
+    unsafe impl Params for ResetParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            vec![
+                (String::from("gain"), self.gain.as_ptr(), String::new()),
+                (String::from("voices"), self.voices.as_ptr(), String::new()),
+                (String::from("bypass"), self.bypass.as_ptr(), String::new()),
+            ]
+        }
+    }
+
+    #[test]
+    fn reset_parameters_to_default() {
+        let params = Arc::new(ResetParams {
+            gain: FloatParam::new("Gain", 0.25, FloatRange::Linear { min: 0.0, max: 1.0 }),
+            voices: IntParam::new("Voices", 4, IntRange::Linear { min: 1, max: 16 }),
+            bypass: BoolParam::new("Bypass", false).make_bypass(),
+        });
+        params.gain.set_plain_value(0.75);
+        params.voices.set_plain_value(12);
+        params.bypass.set_plain_value(true);
+
+        let context = TestGuiContext::new(params.clone());
+        ParamSetter::new(&context).reset_parameters_to_default(&params);
+
+        // The bypass parameter is left alone
+        let gain = params.gain.as_ptr();
+        let voices = params.voices.as_ptr();
+        assert_eq!(
+            context.calls(),
+            [
+                GuiContextCall::BeginSetParameter(gain),
+                GuiContextCall::SetParameterNormalized(gain, 0.25),
+                GuiContextCall::EndSetParameter(gain),
+                GuiContextCall::BeginSetParameter(voices),
+                GuiContextCall::SetParameterNormalized(voices, 3.0 / 15.0),
+                GuiContextCall::EndSetParameter(voices),
+            ]
+        );
+        assert!(params.bypass.value());
+    }
+
+    #[test]
+    fn get_and_set_by_id() {
+        let params = Arc::new(ResetParams {
+            gain: FloatParam::new("Gain", 0.25, FloatRange::Linear { min: 0.0, max: 1.0 }),
+            voices: IntParam::new("Voices", 4, IntRange::Linear { min: 1, max: 16 }),
+            bypass: BoolParam::new("Bypass", false),
+        });
+
+        assert_eq!(params.param_by_id("gain"), Some(params.gain.as_ptr()));
+        assert_eq!(params.param_by_id("voices"), Some(params.voices.as_ptr()));
+        // IDs are case sensitive
+        assert_eq!(params.param_by_id("Gain"), None);
+        assert_eq!(params.param_by_id("unknown"), None);
+
+        let context = TestGuiContext::new(params.clone());
+        let setter = ParamSetter::new(&context);
+        assert!(setter.set_parameter_normalized_by_id(&params, "gain", 0.75));
+        assert!(setter.set_parameter_normalized_by_id(&params, "voices", 0.5));
+        assert!(!setter.set_parameter_normalized_by_id(&params, "unknown", 0.5));
+
+        assert_eq!(params.gain.value(), 0.75);
+        assert_eq!(setter.normalized_value_by_id(&params, "gain"), Some(0.75));
+        // Stepped parameters are snapped to the nearest step
+        assert_eq!(params.voices.value(), 9);
+        assert_eq!(
+            setter.normalized_value_by_id(&params, "voices"),
+            Some(8.0 / 15.0)
+        );
+        assert_eq!(setter.normalized_value_by_id(&params, "unknown"), None);
+        assert_eq!(gesture_counts(&context), (2, 2));
+    }
+
+    #[test]
+    fn randomize_skips_excluded_parameters() {
+        let params = Arc::new(ResetParams {
+            gain: FloatParam::new("Gain", 0.25, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .non_randomizable(),
+            voices: IntParam::new("Voices", 4, IntRange::Linear { min: 1, max: 16 }),
+            bypass: BoolParam::new("Bypass", false).make_bypass(),
+        });
+
+        let context = TestGuiContext::new(params.clone());
+        let setter = ParamSetter::new(&context);
+        let mut rng = Rng::new(1234);
+        let mut voices_changed = false;
+        for _ in 0..32 {
+            setter.randomize_parameters(&params, &mut rng, 1.0);
+            voices_changed |= params.voices.value() != 4;
+
+            assert_eq!(params.gain.value(), 0.25);
+            assert!(!params.bypass.value());
+        }
+        assert!(voices_changed);
+        // Only the voices parameter is ever set
+        let voices = params.voices.as_ptr();
+        let set_params: Vec<ParamPtr> = context
+            .calls()
+            .into_iter()
+            .filter_map(|call| match call {
+                GuiContextCall::SetParameterNormalized(param, _) => Some(param),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(set_params, [voices; 32]);
+    }
+
+    #[test]
+    fn random_normalized_amount() {
+        let mut rng = Rng::new(1234);
+        for _ in 0..1000 {
+            let value = random_normalized(0.5, None, 0.2, &mut rng);
+            assert!((0.3..=0.7).contains(&value), "{value} is out of range");
+
+            let value = random_normalized(0.95, None, 0.2, &mut rng);
+            assert!((0.75..=1.0).contains(&value), "{value} is out of range");
+
+            // Stepped parameters are always snapped to one of the steps within range
+            let value = random_normalized(0.5, Some(10), 0.2, &mut rng);
+            let step = value * 10.0;
+            assert_eq!(step, step.round());
+            assert!((3.0..=7.0).contains(&step), "{step} is out of range");
+        }
+
+        // Every step, including both ends, can be picked
+        let mut seen = [false; 5];
+        for _ in 0..1000 {
+            let value = random_normalized(0.0, Some(4), 1.0, &mut rng);
+            seen[(value * 4.0).round() as usize] = true;
+        }
+        assert_eq!(seen, [true; 5]);
+        assert_eq!(random_normalized(0.5, Some(10), 0.0, &mut rng), 0.5);
+    }
 }
@@ -1,6 +1,6 @@
 //! A context passed during plugin initialization.
 
-use super::PluginApi;
+use super::{HostInfo, PluginApi};
 use crate::prelude::Plugin;
 
 /// Callbacks the plugin can make while it is being initialized. This is passed to the plugin during
@@ -14,6 +14,11 @@ pub trait InitContext<P: Plugin> {
     /// Get the current plugin API.
     fn plugin_api(&self) -> PluginApi;
 
+    /// Get the name and version of the host application, if the plugin API provides this
+    /// information. This is always `None` for the standalone target. Only use this to work around
+    /// issues in specific hosts, since the host and its behavior should otherwise not matter.
+    fn host_info(&self) -> Option<HostInfo>;
+
     /// Run a task directly on this thread. This ensures that the task has finished executing before
     /// the plugin finishes initializing.
     ///
@@ -27,6 +32,15 @@ pub trait InitContext<P: Plugin> {
     /// this may cause audio playback to be restarted.
     fn set_latency_samples(&self, samples: u32);
 
+    /// Update the plugin's current tail length in samples. This is the number of samples the plugin
+    /// continues to produce sound for after its input has gone silent, so hosts know how long to
+    /// keep processing during offline renders. Use [`INFINITE_TAIL`][crate::prelude::INFINITE_TAIL] if
+    /// the tail never ends. The tail length may depend on the plugin's parameters, in which case
+    /// this should be called again whenever it changes. Once this has been called, the value set
+    /// here takes precedence over the tail length from
+    /// [`ProcessStatus::Tail`][crate::prelude::ProcessStatus::Tail].
+    fn set_tail_samples(&self, samples: u32);
+
     /// Set the current voice **capacity** for this plugin (so not the number of currently active
     /// voices). This may only be called if
     /// [`ClapPlugin::CLAP_POLY_MODULATION_CONFIG`][crate::prelude::ClapPlugin::CLAP_POLY_MODULATION_CONFIG]
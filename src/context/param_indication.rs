@@ -0,0 +1,52 @@
+//! Host indications about how a parameter is automated or mapped to a controller. This is
+//! currently only supported for CLAP plugins through the `param-indication` extension.
+
+/// The state the host reported for a parameter. Query this from the editor using
+/// [`GuiContext::param_indication()`][crate::prelude::GuiContext::param_indication()] or
+/// [`ParamSetter::indication()`][crate::prelude::ParamSetter::indication()] to highlight
+/// automated or mapped parameters in the same way the host does. This defaults to no indication,
+/// which is also what's returned for hosts and plugin APIs that don't support this.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ParamIndication {
+    /// The parameter's automation state in the host.
+    pub automation: AutomationIndication,
+    /// The color the host uses to indicate the parameter's automation, if it specified one.
+    pub automation_color: Option<IndicationColor>,
+    /// Whether the parameter is mapped to a physical controller.
+    pub mapped: bool,
+    /// The color the host uses to indicate the controller mapping, if it specified one.
+    pub mapping_color: Option<IndicationColor>,
+}
+
+/// The automation state of a parameter as reported by the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AutomationIndication {
+    /// The host does not have any automation for this parameter.
+    #[default]
+    None,
+    /// The host has automation for this parameter, but it isn't playing it back.
+    Present,
+    /// The host is playing back the parameter's automation.
+    Playing,
+    /// The host is recording automation for this parameter.
+    Recording,
+    /// The host's automation for this parameter is being overridden, for instance because the
+    /// user is changing the parameter's value while the automation is playing.
+    Overriding,
+}
+
+/// A color specified by the host, in 8-bit sRGB with an alpha channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndicationColor {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+    pub alpha: u8,
+}
+
+impl ParamIndication {
+    /// Whether the host is indicating anything at all for this parameter.
+    pub fn is_none(&self) -> bool {
+        self.automation == AutomationIndication::None && !self.mapped
+    }
+}
@@ -1,7 +1,7 @@
 //! A context passed during the process function.
 
 use super::PluginApi;
-use crate::prelude::{Plugin, PluginNoteEvent};
+use crate::prelude::{Plugin, PluginNoteEvent, ProcessMode};
 
 /// Contains both context data and callbacks the plugin can use during processing. Most notably this
 /// is how a plugin sends and receives note events, gets transport information, and accesses
@@ -40,6 +40,22 @@ pub trait ProcessContext<P: Plugin> {
     /// Get information about the current transport position and status.
     fn transport(&self) -> &Transport;
 
+    /// Get the current processing mode. This is [`ProcessMode::Offline`] when the host is rendering
+    /// or bouncing audio faster than realtime, in which case the plugin can choose to use more
+    /// expensive higher quality processing like more oversampling. Unlike
+    /// [`BufferConfig::process_mode`][crate::prelude::BufferConfig::process_mode], this reflects
+    /// the mode for the current process call even if the host switches modes without
+    /// reinitializing the plugin.
+    fn process_mode(&self) -> ProcessMode;
+
+    /// Returns `true` if the plugin's editor is currently open. This can be used to skip work that
+    /// only matters for the GUI, like computing spectra for an analyzer, while the editor is
+    /// closed. This is purely advisory: the editor may be opened or closed right after this
+    /// function returns, so the plugin should not rely on it for anything other than saving CPU.
+    /// The flag is cleared whenever the wrapper drops the editor, even if the host destroys the
+    /// editor or the plugin instance without closing it first.
+    fn is_editor_open(&self) -> bool;
+
     /// Returns the next note event, if there is one. Use
     /// [`NoteEvent::timing()`][crate::prelude::NoteEvent::timing()] to get the event's timing
     /// within the buffer. Only available when
@@ -84,6 +100,28 @@ pub trait ProcessContext<P: Plugin> {
     /// this may cause audio playback to be restarted.
     fn set_latency_samples(&self, samples: u32);
 
+    /// Update the plugin's current tail length in samples. This is the number of samples the plugin
+    /// continues to produce sound for after its input has gone silent, so hosts know how long to
+    /// keep processing during offline renders. Use [`INFINITE_TAIL`][crate::prelude::INFINITE_TAIL] if
+    /// the tail never ends. The tail length may depend on the plugin's parameters, in which case
+    /// this should be called again whenever it changes. Once this has been called, the value set
+    /// here takes precedence over the tail length from
+    /// [`ProcessStatus::Tail`][crate::prelude::ProcessStatus::Tail].
+    fn set_tail_samples(&self, samples: u32);
+
+    /// Report the plugin's current gain reduction in decibels, so hosts can show it on their
+    /// channel strip meters. Gain reduction is a positive value, so a compressor that is currently
+    /// attenuating its input by 6 dB should report `6.0`. Negative values are treated as no gain
+    /// reduction. Dynamics plugins should call this once per process call.
+    ///
+    /// This only does something if the plugin has a parameter created with
+    /// [`FloatParam::make_gain_reduction_meter()`][crate::prelude::FloatParam::make_gain_reduction_meter()].
+    /// That parameter is exposed to the host as a read-only output parameter. To avoid flooding the
+    /// host with parameter changes, it is updated at most 30 times per second using the highest
+    /// gain reduction reported since the last update, and only when its value changes by at least
+    /// 0.1 dB.
+    fn set_gain_reduction(&self, gain_reduction_db: f32);
+
     /// Set the current voice **capacity** for this plugin (so not the number of currently active
     /// voices). This may only be called if
     /// [`ClapPlugin::CLAP_POLY_MODULATION_CONFIG`][crate::prelude::ClapPlugin::CLAP_POLY_MODULATION_CONFIG]
@@ -92,15 +130,72 @@ pub trait ProcessContext<P: Plugin> {
     /// monophonic modulation when dropping the capacity down to 1.
     fn set_current_voice_capacity(&self, capacity: u32);
 
+    /// Run `task` `num_tasks` times in parallel using the host's thread pool, passing the task's
+    /// index in `0..num_tasks` to each invocation. This function only returns after all tasks have
+    /// finished running, so the closure can freely borrow data from the process function. This can
+    /// be used to spread expensive per-voice or per-band processing over multiple cores.
+    ///
+    /// This uses CLAP's `thread-pool` extension. If the plugin is not running as a CLAP plugin, if
+    /// the host does not support the extension, or if the host rejects the request, then the tasks
+    /// are simply run sequentially on the audio thread instead. Calling this function from within
+    /// one of the tasks will also cause the nested tasks to be run sequentially.
+    ///
+    /// # Realtime safety
+    ///
+    /// The tasks run on the host's audio worker threads while the audio thread is blocked waiting
+    /// for them to finish. Just like in the process function, the task must not allocate, must not
+    /// wait on locks, and must not perform any other blocking operations. If the
+    /// `assert_process_allocs` feature is enabled, then allocations in these tasks will also be
+    /// caught.
+    fn execute_parallel(&self, num_tasks: u32, task: &(dyn Fn(u32) + Sync));
+
     // TODO: Add this, this works similar to [GuiContext::set_parameter] but it adds the parameter
     //       change to a queue (or directly to the VST3 plugin's parameter output queues) instead of
     //       using main thread host automation (and all the locks involved there).
     // fn set_parameter<P: Param>(&self, param: &P, value: P::Plain);
 }
 
+/// A discontinuity in the transport's playback position. See [`Transport::jump`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportJump {
+    /// The playback position wrapped around from the end of the loop region back to its start.
+    Looped,
+    /// The playback position changed in any other way, for instance because the user moved the
+    /// playhead while the transport was running.
+    Jumped,
+}
+
+/// A position in bars, beats, and ticks, as shown in most hosts' transport bars. See
+/// [`Transport::bar_beat_tick()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BarBeatTick {
+    /// The bar number, starting at 0 for the first bar of the song. Hosts usually display this
+    /// starting from 1.
+    pub bar: i32,
+    /// The beat within the bar in the time signature's denominator, starting at 0. Hosts usually
+    /// display this starting from 1.
+    pub beat: u32,
+    /// The position within the beat, in `0..Transport::TICKS_PER_BEAT`.
+    pub tick: u32,
+}
+
 /// Information about the plugin's transport. Depending on the plugin API and the host not all
 /// fields may be available.
-#[derive(Debug)]
+///
+/// All positions describe the start of the current block. Use
+/// [`samples_to_beats()`][Self::samples_to_beats()] to get the musical position at a sample offset
+/// within the block. The positions are reported by the host in different units depending on the
+/// plugin API, and the missing ones are computed from the reported ones using the current tempo:
+///
+/// - CLAP hosts report the position in seconds and in beats, along with the current bar's start
+///   position and number.
+/// - VST3 hosts report the position in samples and in beats, along with the current bar's start
+///   position.
+/// - The standalone JACK backend reports the position in samples. If JACK's transport has
+///   bar/beat/tick information, then it also reports the position in beats along with the current
+///   bar's start position and number. The other standalone backends only report the position in
+///   samples.
+#[derive(Debug, Clone)]
 pub struct Transport {
     /// Whether the transport is currently running.
     pub playing: bool,
@@ -119,6 +214,14 @@ pub struct Transport {
     pub time_sig_numerator: Option<i32>,
     /// The time signature's denominator.
     pub time_sig_denominator: Option<i32>,
+    /// Set when the playback position did not continue from where the previous process call left
+    /// off, for instance because the host's loop region wrapped around or because the user moved
+    /// the playhead. Time-based effects can use this to clear or realign their delay lines. This is
+    /// detected by comparing the host's reported position to the previous block's position, so
+    /// it's always `None` when the transport is not playing, when playback has just started, or
+    /// when the host does not report a playback position. Jumps back to the start of the loop
+    /// are reported as [`TransportJump::Jumped`] if the host doesn't report the loop region.
+    pub jump: Option<TransportJump>,
 
     // XXX: VST3 also has a continuous time in samples that ignores loops, but we can't reconstruct
     //      something similar in CLAP so it may be best to just ignore that so you can't rely on it
@@ -153,6 +256,10 @@ pub struct Transport {
 }
 
 impl Transport {
+    /// The number of ticks in a beat used for [`bar_beat_tick()`][Self::bar_beat_tick()]. This is
+    /// the same resolution most hosts use.
+    pub const TICKS_PER_BEAT: u32 = 960;
+
     /// Initialize the transport struct without any information.
     pub(crate) fn new(sample_rate: f32) -> Self {
         Self {
@@ -164,6 +271,7 @@ impl Transport {
             tempo: None,
             time_sig_numerator: None,
             time_sig_denominator: None,
+            jump: None,
 
             pos_samples: None,
             pos_seconds: None,
@@ -270,6 +378,55 @@ impl Transport {
         }
     }
 
+    /// The position within the current bar, in beats of the time signature's denominator. For
+    /// instance, this is in `[0, 6)` for a 6/8 time signature. Computed from the position in beats
+    /// and the start of the current bar.
+    pub fn beat_in_bar(&self) -> Option<f64> {
+        match (
+            self.time_sig_denominator,
+            self.pos_beats(),
+            self.bar_start_pos_beats(),
+        ) {
+            (Some(time_sig_denominator), Some(pos_beats), Some(bar_start_pos_beats)) => {
+                let quarter_notes_in_bar = pos_beats - bar_start_pos_beats;
+
+                // Floating point errors could result in tiny negative values at the start of a bar
+                Some((quarter_notes_in_bar * time_sig_denominator as f64 / 4.0).max(0.0))
+            }
+            (_, _, _) => None,
+        }
+    }
+
+    /// The position as bars, beats, and ticks. See [`BarBeatTick`].
+    pub fn bar_beat_tick(&self) -> Option<BarBeatTick> {
+        let bar = self.bar_number()?;
+        let beat_in_bar = self.beat_in_bar()?;
+
+        // The small offset prevents positions that should be exactly on a beat from ending up on
+        // the last tick of the previous beat because of rounding errors
+        let ticks = (beat_in_bar * Self::TICKS_PER_BEAT as f64 + 1e-6).floor() as u64;
+        Some(BarBeatTick {
+            bar,
+            beat: (ticks / Self::TICKS_PER_BEAT as u64) as u32,
+            tick: (ticks % Self::TICKS_PER_BEAT as u64) as u32,
+        })
+    }
+
+    /// Convert a duration in samples to a duration in quarter notes at the current tempo. Adding
+    /// this to [`pos_beats()`][Self::pos_beats()] gives the musical position at a sample offset
+    /// within the current block.
+    pub fn samples_to_beats(&self, samples: f64) -> Option<f64> {
+        self.tempo
+            .map(|tempo| samples / self.sample_rate as f64 / 60.0 * tempo)
+    }
+
+    /// Convert a duration in quarter notes to a duration in samples at the current tempo. This
+    /// can be used to compute tempo synced delay times or the sample offset of the next beat.
+    pub fn beats_to_samples(&self, beats: f64) -> Option<f64> {
+        self.tempo
+            .map(|tempo| beats / tempo * 60.0 * self.sample_rate as f64)
+    }
+
     /// The loop range in samples, if the loop is active and this information is available. None of
     /// the plugin API docs mention whether this is exclusive or inclusive, but just assume that the
     /// end is exclusive. Will be calculated from other information if needed.
@@ -337,3 +494,92 @@ impl Transport {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+
+    /// A playing transport at 120 BPM with the specified time signature, positioned at `pos_beats`
+    /// quarter notes. The position is reported in samples, like the VST3 and standalone wrappers
+    /// do.
+    fn simulated_transport(pos_beats: f64, numerator: i32, denominator: i32) -> Transport {
+        let mut transport = Transport::new(SAMPLE_RATE);
+        transport.playing = true;
+        transport.tempo = Some(120.0);
+        transport.time_sig_numerator = Some(numerator);
+        transport.time_sig_denominator = Some(denominator);
+        transport.pos_samples = Some((pos_beats * SAMPLE_RATE as f64 / 2.0) as i64);
+
+        transport
+    }
+
+    #[test]
+    fn conversions() {
+        let transport = simulated_transport(0.0, 4, 4);
+        assert_eq!(transport.beats_to_samples(1.0), Some(24_000.0));
+        assert_eq!(transport.samples_to_beats(24_000.0), Some(1.0));
+        assert_eq!(Transport::new(SAMPLE_RATE).samples_to_beats(24_000.0), None);
+
+        let transport = simulated_transport(10.0, 4, 4);
+        assert_eq!(transport.pos_samples(), Some(240_000));
+        assert_eq!(transport.pos_seconds(), Some(5.0));
+        assert_eq!(transport.pos_beats(), Some(10.0));
+    }
+
+    #[test]
+    fn bar_beat_tick() {
+        // Bar 2 (zero-indexed), beat 3, and halfway through that beat
+        let transport = simulated_transport(8.0 + 2.5, 4, 4);
+        assert_eq!(transport.bar_number(), Some(2));
+        assert_eq!(transport.bar_start_pos_beats(), Some(8.0));
+        assert_eq!(transport.beat_in_bar(), Some(2.5));
+        assert_eq!(
+            transport.bar_beat_tick(),
+            Some(BarBeatTick {
+                bar: 2,
+                beat: 2,
+                tick: Transport::TICKS_PER_BEAT / 2,
+            })
+        );
+
+        let transport = simulated_transport(0.0, 4, 4);
+        assert_eq!(
+            transport.bar_beat_tick(),
+            Some(BarBeatTick {
+                bar: 0,
+                beat: 0,
+                tick: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn compound_time_signature() {
+        // A bar of 6/8 lasts three quarter notes, and its beats are eighth notes
+        let transport = simulated_transport(3.0 + 2.0, 6, 8);
+        assert_eq!(transport.bar_number(), Some(1));
+        assert_eq!(transport.bar_start_pos_beats(), Some(3.0));
+        assert_eq!(transport.beat_in_bar(), Some(4.0));
+        assert_eq!(
+            transport.bar_beat_tick(),
+            Some(BarBeatTick {
+                bar: 1,
+                beat: 4,
+                tick: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn host_bar_position() {
+        // Hosts can report the bar's start position directly, for instance after a time signature
+        // change. That takes precedence over the computed position.
+        let mut transport = simulated_transport(13.0, 3, 4);
+        transport.bar_start_pos_beats = Some(12.0);
+        transport.bar_number = Some(7);
+        assert_eq!(transport.beat_in_bar(), Some(1.0));
+        assert_eq!(transport.bar_beat_tick().map(|pos| pos.bar), Some(7));
+    }
+}
@@ -2,14 +2,16 @@
 //! pages](https://github.com/free-audio/clap/blob/main/include/clap/ext/draft/remote-controls.h)
 //! for CLAP plugins.
 
-use crate::prelude::Param;
+use crate::prelude::{Param, Params};
 
 /// A context for defining plugin-specific [remote
 /// pages](https://github.com/free-audio/clap/blob/main/include/clap/ext/draft/remote-controls.h)
 /// for CLAP plugins.
 ///
 /// These pages can contain references to up to eight parameters, but if the plugin defines more
-/// parameters for a page then the pages are automatically split.
+/// parameters for a page then the pages are automatically split. If the plugin does not define any
+/// pages, then pages are created automatically for all of its automatable parameters in declaration
+/// order, with a separate section for each parameter group.
 pub trait RemoteControlsContext {
     type Section: RemoteControlsSection;
 
@@ -37,6 +39,12 @@ pub trait RemoteControlsPage {
     // Add a reference to one of the plugin's parameters to the page.
     fn add_param(&mut self, param: &impl Param);
 
+    // Add references to all automatable parameters from a `Params` object to the page, in
+    // declaration order. This includes parameters from nested parameter objects. Hidden,
+    // non-automatable and bypass parameters are skipped. Like with the other functions, the page
+    // is split automatically if this results in more than eight parameters.
+    fn add_params(&mut self, params: &impl Params);
+
     // Add an empty space on the page. Can be useful for grouping and aligning parameters within a
     // page.
     fn add_spacer(&mut self);
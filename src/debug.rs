@@ -10,7 +10,9 @@
 //       `#[macro_use] extern crate nih_plug;`. That's why the macros are also re-exported from this
 //       module.
 
-/// Write something to the logger. This defaults to STDERR unless the user is running Windows and a
+/// Write something to the logger at the info level. This is the same as [`nih_info!()`]. When the
+/// host provides a logging facility, like CLAP's `clap.log` extension, then messages are written to
+/// the host's log. Otherwise this defaults to STDERR unless the user is running Windows and a
 /// debugger has been attached, in which case `OutputDebugString()` will be used instead.
 ///
 /// The logger's behavior can be controlled by setting the `NIH_LOG` environment variable to:
@@ -20,20 +22,34 @@
 ///   `OutputDebugString()`.
 /// - A file path, in which case the output gets appended to the end of that file which will be
 ///   created if necessary.
+///
+/// Messages logged from the audio thread are copied to fixed-size buffers and written from a
+/// background thread, so logging neither blocks nor allocates on the audio thread. Long messages
+/// are truncated, and the oldest messages are dropped if the queue fills up.
 #[macro_export]
 macro_rules! nih_log {
     ($($args:tt)*) => (
-        $crate::log::info!($($args)*)
+        $crate::nih_info!($($args)*)
     );
 }
 #[doc(inline)]
 pub use nih_log;
 
+/// Write something to the logger at the info level. See [`nih_log!()`] for more information.
+#[macro_export]
+macro_rules! nih_info {
+    ($($args:tt)*) => (
+        $crate::__nih_log_at_level!(Info, $($args)*)
+    );
+}
+#[doc(inline)]
+pub use nih_info;
+
 /// Similar to `nih_log!()`, but less subtle. Used for printing warnings.
 #[macro_export]
 macro_rules! nih_warn {
     ($($args:tt)*) => (
-        $crate::log::warn!($($args)*)
+        $crate::__nih_log_at_level!(Warn, $($args)*)
     );
 }
 #[doc(inline)]
@@ -43,18 +59,29 @@ pub use nih_warn;
 #[macro_export]
 macro_rules! nih_error {
     ($($args:tt)*) => (
-        $crate::log::error!($($args)*)
+        $crate::__nih_log_at_level!(Error, $($args)*)
     );
 }
 #[doc(inline)]
 pub use nih_error;
 
+/// The same as `nih_log!()`, but at the debug level. Like the `nih_debug_assert*!()` macros, this
+/// is only shown when compiling in debug mode.
+#[macro_export]
+macro_rules! nih_debug {
+    ($($args:tt)*) => (
+        $crate::util::permit_alloc(|| $crate::__nih_log_at_level!(Debug, $($args)*))
+    );
+}
+#[doc(inline)]
+pub use nih_debug;
+
 /// The same as `nih_log!()`, but with source and thread information. Like the
 /// `nih_debug_assert*!()` macros, this is only shown when compiling in debug mode.
 #[macro_export]
 macro_rules! nih_trace {
     ($($args:tt)*) => (
-        $crate::util::permit_alloc(|| $crate::log::trace!($($args)*))
+        $crate::util::permit_alloc(|| $crate::__nih_log_at_level!(Trace, $($args)*))
     );
 }
 #[doc(inline)]
@@ -67,13 +94,13 @@ pub use nih_trace;
 #[macro_export]
 macro_rules! nih_dbg {
     () => {
-        $crate::util::permit_alloc(|| $crate::log::debug!(""));
+        $crate::nih_debug!("");
     };
     ($val:expr $(,)?) => {
         // Match here acts as a let-binding: https://stackoverflow.com/questions/48732263/why-is-rusts-assert-eq-implemented-using-a-match/48732525#48732525
         match $val {
             tmp => {
-                $crate::util::permit_alloc(|| $crate::log::debug!("{} = {:#?}", stringify!($val), &tmp));
+                $crate::nih_debug!("{} = {:#?}", stringify!($val), &tmp);
                 tmp
             }
         }
@@ -83,6 +110,29 @@ macro_rules! nih_dbg {
 #[doc(inline)]
 pub use nih_dbg;
 
+/// The implementation of the logging macros. This checks the log level so the arguments are not
+/// formatted when the message would not be shown, and then hands the message to the logger.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __nih_log_at_level {
+    ($level:ident, target: $target:expr, $($args:tt)+) => {{
+        let level = $crate::log::Level::$level;
+        if level <= $crate::log::STATIC_MAX_LEVEL && level <= $crate::log::max_level() {
+            $crate::debug::__private_log(
+                level,
+                $target,
+                module_path!(),
+                file!(),
+                line!(),
+                format_args!($($args)+),
+            );
+        }
+    }};
+    ($level:ident, $($args:tt)+) => (
+        $crate::__nih_log_at_level!($level, target: module_path!(), $($args)+)
+    );
+}
+
 /// A `debug_assert!()` analogue that prints the error with line number information instead of
 /// panicking. During tests this is upgraded to a regular panicking `debug_assert!()`.
 ///
@@ -94,7 +144,7 @@ macro_rules! nih_debug_assert {
         if cfg!(test) {
            debug_assert!($cond);
         } else if cfg!(debug_assertions) && !$cond {
-            $crate::util::permit_alloc(|| $crate::log::warn!(concat!("Debug assertion failed: ", stringify!($cond))));
+            $crate::util::permit_alloc(|| $crate::nih_warn!(concat!("Debug assertion failed: ", stringify!($cond))));
         }
     );
     ($cond:expr, $format:expr $(, $($args:tt)*)?) => (
@@ -102,7 +152,7 @@ macro_rules! nih_debug_assert {
         if cfg!(test) {
            debug_assert!($cond, $format, $($($args)*)?);
         } else if cfg!(debug_assertions) && !$cond {
-            $crate::util::permit_alloc(|| $crate::log::warn!(concat!("Debug assertion failed: ", stringify!($cond), ", ", $format), $($($args)*)?));
+            $crate::util::permit_alloc(|| $crate::nih_warn!(concat!("Debug assertion failed: ", stringify!($cond), ", ", $format), $($($args)*)?));
         }
     );
 }
@@ -117,14 +167,14 @@ macro_rules! nih_debug_assert_failure {
         if cfg!(test) {
            debug_assert!(false, "Debug assertion failed");
         } else if cfg!(debug_assertions) {
-            $crate::util::permit_alloc(|| $crate::log::warn!("Debug assertion failed"));
+            $crate::util::permit_alloc(|| $crate::nih_warn!("Debug assertion failed"));
         }
     );
     ($format:expr $(, $($args:tt)*)?) => (
         if cfg!(test) {
            debug_assert!(false, concat!("Debug assertion failed: ", $format), $($($args)*)?);
         } else if cfg!(debug_assertions) {
-            $crate::util::permit_alloc(|| $crate::log::warn!(concat!("Debug assertion failed: ", $format), $($($args)*)?));
+            $crate::util::permit_alloc(|| $crate::nih_warn!(concat!("Debug assertion failed: ", $format), $($($args)*)?));
         }
     );
 }
@@ -140,7 +190,7 @@ macro_rules! nih_debug_assert_eq {
         if cfg!(test) {
            debug_assert_eq!($left, $right);
         } else if cfg!(debug_assertions) && $left != $right {
-            $crate::util::permit_alloc(|| $crate::log::warn!(concat!("Debug assertion failed: ", stringify!($left), " != ", stringify!($right))));
+            $crate::util::permit_alloc(|| $crate::nih_warn!(concat!("Debug assertion failed: ", stringify!($left), " != ", stringify!($right))));
         }
     );
     ($left:expr, $right:expr, $format:expr $(, $($args:tt)*)?) => (
@@ -148,7 +198,7 @@ macro_rules! nih_debug_assert_eq {
         if cfg!(test) {
            debug_assert_eq!($left, $right, $format, $($($args)*)?);
         } else if cfg!(debug_assertions) && $left != $right {
-            $crate::util::permit_alloc(|| $crate::log::warn!(concat!("Debug assertion failed: ", stringify!($left), " != ", stringify!($right), ", ", $format), $($($args)*)?));
+            $crate::util::permit_alloc(|| $crate::nih_warn!(concat!("Debug assertion failed: ", stringify!($left), " != ", stringify!($right), ", ", $format), $($($args)*)?));
         }
     );
 }
@@ -164,7 +214,7 @@ macro_rules! nih_debug_assert_ne {
         if cfg!(test) {
            debug_assert_ne!($left, $right);
         } else if cfg!(debug_assertions) && $left == $right {
-            $crate::util::permit_alloc(|| $crate::log::warn!(concat!("Debug assertion failed: ", stringify!($left), " == ", stringify!($right))));
+            $crate::util::permit_alloc(|| $crate::nih_warn!(concat!("Debug assertion failed: ", stringify!($left), " == ", stringify!($right))));
         }
     );
     ($left:expr, $right:expr, $format:expr $(, $($args:tt)*)?) => (
@@ -172,9 +222,22 @@ macro_rules! nih_debug_assert_ne {
         if cfg!(test) {
            debug_assert_ne!($left, $right, $format, $($($args)*)?);
         } else if cfg!(debug_assertions) && $left == $right  {
-            $crate::util::permit_alloc(|| $crate::log::warn!(concat!("Debug assertion failed: ", stringify!($left), " == ", stringify!($right), ", ", $format), $($($args)*)?));
+            $crate::util::permit_alloc(|| $crate::nih_warn!(concat!("Debug assertion failed: ", stringify!($left), " == ", stringify!($right), ", ", $format), $($($args)*)?));
         }
     );
 }
 #[doc(inline)]
 pub use nih_debug_assert_ne;
+
+/// Used by the logging macros. See [`nih_log!()`].
+#[doc(hidden)]
+pub fn __private_log(
+    level: log::Level,
+    target: &str,
+    module_path: &'static str,
+    file: &'static str,
+    line: u32,
+    args: std::fmt::Arguments,
+) {
+    crate::wrapper::util::logger::log(level, target, module_path, file, line, args);
+}
@@ -5,7 +5,10 @@ use std::any::Any;
 use std::ffi::c_void;
 use std::sync::Arc;
 
-use crate::prelude::GuiContext;
+use crate::prelude::{BufferConfig, GuiContext};
+
+pub mod param_changes;
+pub mod session_state;
 
 /// An editor for a [`Plugin`][crate::prelude::Plugin].
 pub trait Editor: Send {
@@ -54,6 +57,24 @@ pub trait Editor: Send {
     /// there.
     fn set_scale_factor(&self, factor: f32) -> bool;
 
+    /// Called after the editor has been opened with [`spawn()`][Self::spawn()]. This can be used to
+    /// allocate resources that are only needed while the editor is visible, or to start collecting
+    /// data that's only displayed in the editor like a spectrum analyzer's spectrum. This is always
+    /// called on the main thread right after `spawn()` has returned. When the host closes the
+    /// editor, this is followed by exactly one call to [`on_close()`][Self::on_close()] before the
+    /// editor is opened again.
+    ///
+    /// Use [`with_lifecycle_hooks()`] to add these hooks to an editor created by one of the GUI
+    /// integrations. The default implementation does nothing.
+    fn on_open(&self) {}
+
+    /// Called on the main thread after the editor has been closed, i.e. after the handle returned by
+    /// [`spawn()`][Self::spawn()] has been dropped. Use this to undo whatever
+    /// [`on_open()`][Self::on_open()] did.
+    ///
+    /// The default implementation does nothing.
+    fn on_close(&self) {}
+
     /// Called whenever a specific parameter's value has changed while the editor is open. You don't
     /// need to do anything with this, but this can be used to force a redraw when the host sends a
     /// new value for a parameter or when a parameter change sent to the host gets processed.
@@ -69,6 +90,16 @@ pub trait Editor: Send {
     /// loaded.
     fn param_values_changed(&self);
 
+    /// Called when the plugin has been initialized with a different [`BufferConfig`] while the
+    /// editor is open, for instance because the host changed the sample rate. Editors that display
+    /// data computed on the audio thread, like spectrum analyzers and meters, can use this to
+    /// rescale or clear that data. This is called on the main thread after
+    /// [`Plugin::initialize()`][crate::prelude::Plugin::initialize()] has returned, so the plugin
+    /// can already reset its own editor data there.
+    ///
+    /// The default implementation does nothing.
+    fn buffer_config_changed(&self, _buffer_config: &BufferConfig) {}
+
     /// Returns whether this editor supports resizing. If this returns `true`, the host will allow
     /// the user to resize the plugin window, and [`set_size()`][Self::set_size()] will be called
     /// with the new dimensions.
@@ -96,6 +127,96 @@ pub trait Editor: Send {
     //       itself. This would also need an associated `PREFERRED_FRAME_RATE` constant.
 }
 
+/// Run `on_open` and `on_close` when `editor` is opened and closed. This is useful for editors
+/// created by the GUI integrations, since those implement [`Editor`] for you. See
+/// [`Editor::on_open()`] and [`Editor::on_close()`] for the thread and timing guarantees.
+///
+/// ```ignore
+/// fn editor(&mut self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
+///     let analyzer_enabled_open = self.analyzer_enabled.clone();
+///     let analyzer_enabled_close = self.analyzer_enabled.clone();
+///
+///     create_vizia_editor(/* ... */).map(|editor| {
+///         nih_plug::editor::with_lifecycle_hooks(
+///             editor,
+///             move || analyzer_enabled_open.store(true, Ordering::Relaxed),
+///             move || analyzer_enabled_close.store(false, Ordering::Relaxed),
+///         )
+///     })
+/// }
+/// ```
+pub fn with_lifecycle_hooks(
+    editor: Box<dyn Editor>,
+    on_open: impl Fn() + Send + 'static,
+    on_close: impl Fn() + Send + 'static,
+) -> Box<dyn Editor> {
+    Box::new(LifecycleHooks {
+        editor,
+        on_open: Box::new(on_open),
+        on_close: Box::new(on_close),
+    })
+}
+
+/// The editor returned by [`with_lifecycle_hooks()`]. Everything except for the hooks is forwarded
+/// to the wrapped editor.
+struct LifecycleHooks {
+    editor: Box<dyn Editor>,
+    on_open: Box<dyn Fn() + Send>,
+    on_close: Box<dyn Fn() + Send>,
+}
+
+impl Editor for LifecycleHooks {
+    fn spawn(
+        &self,
+        parent: ParentWindowHandle,
+        context: Arc<dyn GuiContext>,
+    ) -> Box<dyn Any + Send> {
+        self.editor.spawn(parent, context)
+    }
+
+    fn size(&self) -> (u32, u32) {
+        self.editor.size()
+    }
+
+    fn set_scale_factor(&self, factor: f32) -> bool {
+        self.editor.set_scale_factor(factor)
+    }
+
+    fn on_open(&self) {
+        self.editor.on_open();
+        (self.on_open)();
+    }
+
+    fn on_close(&self) {
+        (self.on_close)();
+        self.editor.on_close();
+    }
+
+    fn param_value_changed(&self, id: &str, normalized_value: f32) {
+        self.editor.param_value_changed(id, normalized_value)
+    }
+
+    fn param_modulation_changed(&self, id: &str, modulation_offset: f32) {
+        self.editor.param_modulation_changed(id, modulation_offset)
+    }
+
+    fn param_values_changed(&self) {
+        self.editor.param_values_changed()
+    }
+
+    fn buffer_config_changed(&self, buffer_config: &BufferConfig) {
+        self.editor.buffer_config_changed(buffer_config)
+    }
+
+    fn can_resize(&self) -> bool {
+        self.editor.can_resize()
+    }
+
+    fn set_size(&self, width: u32, height: u32) -> bool {
+        self.editor.set_size(width, height)
+    }
+}
+
 /// A raw window handle for platform and GUI framework agnostic editors. This implements
 /// [`HasRawWindowHandle`] so it can be used directly with GUI libraries that use the same
 /// [`raw_window_handle`] version. If the library links against a different version of
@@ -131,3 +252,81 @@ unsafe impl HasRawWindowHandle for ParentWindowHandle {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Counts how often the hooks have been called.
+    #[derive(Default)]
+    struct CountingEditor {
+        opened: Arc<AtomicUsize>,
+        closed: Arc<AtomicUsize>,
+    }
+
+    impl Editor for CountingEditor {
+        fn spawn(
+            &self,
+            _parent: ParentWindowHandle,
+            _context: Arc<dyn GuiContext>,
+        ) -> Box<dyn Any + Send> {
+            Box::new(())
+        }
+
+        fn size(&self) -> (u32, u32) {
+            (100, 50)
+        }
+
+        fn set_scale_factor(&self, _factor: f32) -> bool {
+            false
+        }
+
+        fn on_open(&self) {
+            self.opened.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn on_close(&self) {
+            self.closed.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn param_value_changed(&self, _id: &str, _normalized_value: f32) {}
+
+        fn param_modulation_changed(&self, _id: &str, _modulation_offset: f32) {}
+
+        fn param_values_changed(&self) {}
+    }
+
+    #[test]
+    fn lifecycle_hooks() {
+        let inner = CountingEditor::default();
+        let inner_opened = inner.opened.clone();
+        let inner_closed = inner.closed.clone();
+
+        let open = Arc::new(AtomicUsize::new(0));
+        let editor = with_lifecycle_hooks(
+            Box::new(inner),
+            {
+                let open = open.clone();
+                move || {
+                    open.fetch_add(1, Ordering::SeqCst);
+                }
+            },
+            {
+                let open = open.clone();
+                move || {
+                    open.fetch_sub(1, Ordering::SeqCst);
+                }
+            },
+        );
+        assert_eq!(editor.size(), (100, 50));
+
+        editor.on_open();
+        assert_eq!(open.load(Ordering::SeqCst), 1);
+        assert_eq!(inner_opened.load(Ordering::SeqCst), 1);
+
+        editor.on_close();
+        assert_eq!(open.load(Ordering::SeqCst), 0);
+        assert_eq!(inner_closed.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,134 @@
+//! Coalesced parameter change notifications for editors that cache data computed from parameter
+//! values.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// A subscription to changes to a fixed set of parameters. Editors that cache data derived from
+/// parameter values, like an EQ's computed frequency response curve, can use this to only
+/// recompute that data after one of the parameters it depends on has changed instead of polling
+/// every parameter on every frame.
+///
+/// The editor forwards its [`Editor::param_value_changed()`][crate::prelude::Editor::param_value_changed()]
+/// calls to [`notify()`][Self::notify()], and its
+/// [`Editor::param_values_changed()`][crate::prelude::Editor::param_values_changed()] calls to
+/// [`notify_all()`][Self::notify_all()]. The wrapper calls those functions on the GUI thread for
+/// every parameter change, including automation and state changes coming from the host. Once per
+/// frame, the GUI then calls [`take_changed()`][Self::take_changed()] to get the subscribed
+/// parameters that have changed since the previous frame.
+///
+/// Changes are coalesced. A parameter that changes any number of times between two
+/// `take_changed()` calls is only reported once, so every subscribed parameter results in at most
+/// one notification per frame. Marking a parameter as changed is lock-free and does not allocate.
+#[derive(Debug)]
+pub struct ParamChanges {
+    /// Whether the parameter has changed since the last `take_changed()` call, indexed by the
+    /// subscribed parameters' IDs.
+    changed: HashMap<String, AtomicBool>,
+}
+
+impl ParamChanges {
+    /// Subscribe to changes to the parameters with these IDs. These are the same IDs passed to
+    /// [`Editor::param_value_changed()`][crate::prelude::Editor::param_value_changed()]. All
+    /// parameters start out as changed so any cached data gets computed on the first frame.
+    pub fn new<I, S>(param_ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            changed: param_ids
+                .into_iter()
+                .map(|param_id| (param_id.into(), AtomicBool::new(true)))
+                .collect(),
+        }
+    }
+
+    /// Whether changes to the parameter with this ID are tracked by this subscription.
+    pub fn is_subscribed(&self, param_id: &str) -> bool {
+        self.changed.contains_key(param_id)
+    }
+
+    /// Mark a parameter as changed. This is a no-op for parameters that are not part of this
+    /// subscription. Returns whether the parameter was part of this subscription.
+    pub fn notify(&self, param_id: &str) -> bool {
+        match self.changed.get(param_id) {
+            Some(changed) => {
+                changed.store(true, Ordering::Release);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Mark all subscribed parameters as changed. Used when the host changes many parameters at
+    /// once, like when loading a preset.
+    pub fn notify_all(&self) {
+        for changed in self.changed.values() {
+            changed.store(true, Ordering::Release);
+        }
+    }
+
+    /// Whether any of the subscribed parameters have changed since the last
+    /// [`take_changed()`][Self::take_changed()] call.
+    pub fn has_changes(&self) -> bool {
+        self.changed
+            .values()
+            .any(|changed| changed.load(Ordering::Acquire))
+    }
+
+    /// Get the IDs of the subscribed parameters that have changed since the last call, in sorted
+    /// order, and reset them to unchanged. This should be called once per frame on the GUI thread.
+    pub fn take_changed(&self) -> Vec<&str> {
+        let mut param_ids: Vec<&str> = self
+            .changed
+            .iter()
+            .filter(|(_, changed)| changed.swap(false, Ordering::AcqRel))
+            .map(|(param_id, _)| param_id.as_str())
+            .collect();
+        param_ids.sort_unstable();
+
+        param_ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn initially_changed() {
+        let changes = ParamChanges::new(["freq", "gain"]);
+        assert!(changes.has_changes());
+        assert_eq!(changes.take_changed(), ["freq", "gain"]);
+        assert!(!changes.has_changes());
+        assert!(changes.take_changed().is_empty());
+    }
+
+    #[test]
+    fn coalesced() {
+        let changes = ParamChanges::new(["freq", "gain", "q"]);
+        changes.take_changed();
+
+        assert!(changes.notify("gain"));
+        assert!(changes.notify("freq"));
+        assert!(changes.notify("gain"));
+        assert!(!changes.notify("mix"));
+        assert_eq!(changes.take_changed(), ["freq", "gain"]);
+        assert!(changes.take_changed().is_empty());
+
+        changes.notify_all();
+        assert_eq!(changes.take_changed(), ["freq", "gain", "q"]);
+    }
+
+    #[test]
+    fn unsubscribed() {
+        let changes = ParamChanges::new(["gain"]);
+        changes.take_changed();
+
+        assert!(changes.is_subscribed("gain"));
+        assert!(!changes.is_subscribed("freq"));
+        changes.notify("freq");
+        assert!(!changes.has_changes());
+    }
+}
@@ -0,0 +1,109 @@
+//! Editor state that survives closing and reopening the editor without being saved as part of the
+//! plugin's state.
+
+use parking_lot::Mutex;
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A typed key-value store for editor state that should survive closing and reopening the editor,
+/// but that should not be saved together with the plugin's state. Think of scroll positions, the
+/// currently selected tab, or the last folder opened in a file dialog. This is different from
+/// `#[persist]` fields, which are stored in the plugin's state and are thus restored when the
+/// patch is loaded again.
+///
+/// Create this in your plugin's [`Default`] implementation and pass a clone of the `Arc` to the
+/// editor. Since the plugin owns the store, its values are dropped together with the plugin
+/// instance. Closing the editor does not clear the store.
+///
+/// ```
+/// # use nih_plug::prelude::SessionState;
+/// let session_state = SessionState::new();
+/// assert_eq!(session_state.get::<usize>("active-tab"), None);
+///
+/// session_state.set("active-tab", 2usize);
+/// assert_eq!(session_state.get::<usize>("active-tab"), Some(2));
+/// // Values are only returned when they're read back as the same type
+/// assert_eq!(session_state.get::<u32>("active-tab"), None);
+/// ```
+#[derive(Default)]
+pub struct SessionState {
+    /// The stored values, indexed by their keys.
+    values: Mutex<HashMap<String, Box<dyn Any + Send>>>,
+}
+
+impl std::fmt::Debug for SessionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionState")
+            .field("keys", &self.values.lock().keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl SessionState {
+    /// Create an empty store.
+    pub fn new() -> Arc<Self> {
+        Arc::default()
+    }
+
+    /// Get a copy of the value stored for `key`. Returns `None` if there is no value for that key,
+    /// or if the value has a different type.
+    pub fn get<T: Clone + 'static>(&self, key: &str) -> Option<T> {
+        self.values.lock().get(key)?.downcast_ref::<T>().cloned()
+    }
+
+    /// The same as [`get()`][Self::get()], but returns `T`'s default value if there is no value of
+    /// that type for `key`.
+    pub fn get_or_default<T: Clone + Default + 'static>(&self, key: &str) -> T {
+        self.get(key).unwrap_or_default()
+    }
+
+    /// Store a value for `key`, replacing any existing value regardless of its type.
+    pub fn set<T: Send + 'static>(&self, key: impl Into<String>, value: T) {
+        self.values.lock().insert(key.into(), Box::new(value));
+    }
+
+    /// Remove the value stored for `key`. Returns whether there was a value for that key.
+    pub fn remove(&self, key: &str) -> bool {
+        self.values.lock().remove(key).is_some()
+    }
+
+    /// Remove all stored values.
+    pub fn clear(&self) {
+        self.values.lock().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_values() {
+        let session_state = SessionState::new();
+        session_state.set("folder", String::from("/home/user/samples"));
+        session_state.set("scroll", 0.5f32);
+
+        assert_eq!(
+            session_state.get::<String>("folder").as_deref(),
+            Some("/home/user/samples")
+        );
+        assert_eq!(session_state.get::<f32>("scroll"), Some(0.5));
+        assert_eq!(session_state.get::<f64>("scroll"), None);
+        assert_eq!(session_state.get_or_default::<f64>("scroll"), 0.0);
+    }
+
+    #[test]
+    fn remove_and_clear() {
+        let session_state = SessionState::new();
+        session_state.set("tab", 1usize);
+        session_state.set("scroll", 0.5f32);
+
+        assert!(session_state.remove("tab"));
+        assert!(!session_state.remove("tab"));
+        assert_eq!(session_state.get::<usize>("tab"), None);
+
+        session_state.clear();
+        assert_eq!(session_state.get::<f32>("scroll"), None);
+    }
+}
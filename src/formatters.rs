@@ -255,6 +255,38 @@ pub fn s2v_f32_hz_then_khz() -> Arc<dyn Fn(&str) -> Option<f32> + Send + Sync> {
     })
 }
 
+/// Format a `f32` millisecond value as a rounded `ms` value below one second, and as a rounded
+/// `s` value above one second. This already includes the unit. Useful for free running delay and
+/// LFO times, with [`NoteDivision`][crate::util::NoteDivision] parameters for their tempo synced
+/// counterparts.
+pub fn v2s_f32_ms_then_s(digits: usize) -> Arc<dyn Fn(f32) -> String + Send + Sync> {
+    Arc::new(move |value| {
+        if value < 1000.0 {
+            format!("{value:.digits$} ms")
+        } else {
+            format!("{:.digits$} s", value / 1000.0, digits = digits.max(1))
+        }
+    })
+}
+
+/// Convert an input in the same format as that of [`v2s_f32_ms_then_s()`] to a millisecond value.
+/// Values without a unit are parsed as milliseconds.
+pub fn s2v_f32_ms_then_s() -> Arc<dyn Fn(&str) -> Option<f32> + Send + Sync> {
+    Arc::new(|string| {
+        let string = string.trim();
+        let cleaned_string: Option<f32> = string
+            .trim_end_matches([' ', 'm', 'M', 's', 'S'])
+            .parse()
+            .ok();
+        let lowercase = string.to_ascii_lowercase();
+        if lowercase.ends_with('s') && !lowercase.ends_with("ms") {
+            cleaned_string.map(|x| x * 1000.0)
+        } else {
+            cleaned_string
+        }
+    })
+}
+
 /// Format an order/power of two. Useful in conjunction with [`s2v_i32_power_of_two()`] to limit
 /// integer parameter ranges to be only powers of two.
 pub fn v2s_i32_power_of_two() -> Arc<dyn Fn(i32) -> String + Send + Sync> {
@@ -352,6 +384,20 @@ mod tests {
         assert_eq!("0.01", v2s(0.009));
     }
 
+    #[test]
+    fn f32_ms_then_s_roundtrip() {
+        let v2s = v2s_f32_ms_then_s(1);
+        let s2v = s2v_f32_ms_then_s();
+
+        assert_eq!(v2s(250.0), "250.0 ms");
+        assert_eq!(v2s(1500.0), "1.5 s");
+        assert_eq!(s2v("250.0 ms"), Some(250.0));
+        assert_eq!(s2v("1.5 s"), Some(1500.0));
+        assert_eq!(s2v("2S"), Some(2000.0));
+        assert_eq!(s2v("40"), Some(40.0));
+        assert_eq!(s2v("fast"), None);
+    }
+
     // More of these validators could use tests, but this one in particular is tricky and I noticed
     // an issue where it didn't roundtrip correctly
     #[test]
@@ -113,6 +113,9 @@ pub mod params;
 pub mod plugin;
 pub mod wrapper;
 
+#[cfg(test)]
+mod testing;
+
 // This is also re-exported from the prelude but since the other export entry points are macros and
 // macros are always accessible from the crate's root, it seems like a good idea to keep the
 // symmetry and also export this function in the same places
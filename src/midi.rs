@@ -1,6 +1,7 @@
 //! Constants and definitions surrounding MIDI support.
 
 use midi_consts::channel_event as midi;
+use serde::{Deserialize, Serialize};
 
 use self::sysex::SysExMessage;
 use crate::prelude::Plugin;
@@ -9,6 +10,11 @@ pub mod sysex;
 
 pub use midi_consts::channel_event::control_change;
 
+/// The MIDI CC number for all sound off messages, parsed as [`NoteEvent::AllSoundOff`].
+pub(crate) const ALL_SOUND_OFF_CC: u8 = 120;
+/// The MIDI CC number for all notes off messages, parsed as [`NoteEvent::AllNotesOff`].
+pub(crate) const ALL_NOTES_OFF_CC: u8 = 123;
+
 /// A plugin-specific note event type.
 ///
 /// The reason why this is defined like this instead of parameterizing `NoteEvent` with `P` is
@@ -19,7 +25,7 @@ pub use midi_consts::channel_event::control_change;
 pub type PluginNoteEvent<P> = NoteEvent<<P as Plugin>::SysExMessage>;
 
 /// Determines which note events a plugin can send and receive.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum MidiConfig {
     /// The plugin will not have a note input or output port and will thus not receive any not
     /// events.
@@ -36,6 +42,27 @@ pub enum MidiConfig {
     MidiCCs,
 }
 
+/// Determines whether the plugin also receives MIDI messages as raw bytes through
+/// [`NoteEvent::MidiRaw`] events. This is useful for plugins that implement their own MIDI
+/// handling, like custom CC schemes. Raw messages are only available when the host or MIDI device
+/// sends MIDI, which is the case for CLAP hosts that use the MIDI note dialect and the standalone
+/// target. VST3 does not expose raw MIDI, so VST3 plugins never receive these events. SysEx
+/// messages are always sent as [`NoteEvent::MidiSysEx`] events instead. Use
+/// [`RawSysEx`][sysex::RawSysEx] as the plugin's [`SysExMessage`] type to receive every SysEx
+/// message as raw bytes in the same order as the other raw events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawMidiConfig {
+    /// Only send parsed note events. This is the default.
+    None,
+    /// Send every MIDI message as a [`NoteEvent::MidiRaw`] event, directly followed by the parsed
+    /// event for that message with the same timing. Messages NIH-plug cannot parse are only sent as
+    /// raw events.
+    Alongside,
+    /// Only send MIDI messages as [`NoteEvent::MidiRaw`] events without parsing them. Note events
+    /// the host sends using the plugin API's own note event types are still sent as parsed events.
+    Only,
+}
+
 // FIXME: Like the voice ID, channel and note number can also be omitted in CLAP. And instead of an
 //        Option, maybe this should use a dedicated type to more clearly indicate that missing
 //        values should be treated as wildcards.
@@ -95,6 +122,37 @@ pub enum NoteEvent<S> {
         /// The note's MIDI key number, in `0..128`.
         note: u8,
     },
+    /// An all notes off event, available on [`MidiConfig::Basic`] and up. This is the equivalent of
+    /// receiving a note off event for every note that's currently held down. Voices should be
+    /// released and they are allowed to finish their release stages. Use
+    /// [`AllSoundOff`][Self::AllSoundOff] to silence voices immediately instead.
+    ///
+    /// This is sent for MIDI CC 123 messages, and for CLAP note off events that target all keys.
+    /// VST3 plugins only receive these MIDI CCs on [`MidiConfig::MidiCCs`] and up.
+    /// NIH-plug also sends this event with `channel: None` at the start of the block where the
+    /// host's transport stops playing, so synthesizers don't end up with hanging notes when the
+    /// host doesn't send the note off events itself.
+    AllNotesOff {
+        timing: u32,
+        /// The channel whose notes should be released, in `0..16`. `None` means that the notes on
+        /// all channels should be released. VST3 plugins cannot send events without a channel to
+        /// the host.
+        channel: Option<u8>,
+    },
+    /// An all sound off event, available on [`MidiConfig::Basic`] and up. Unlike
+    /// [`AllNotesOff`][Self::AllNotesOff], this indicates that all voices should stop playing
+    /// immediately without going through their release stages, like a
+    /// [`Choke`][Self::Choke] event for every voice.
+    ///
+    /// This is sent for MIDI CC 120 messages, and for CLAP note choke events that target all keys.
+    /// VST3 plugins only receive these MIDI CCs on [`MidiConfig::MidiCCs`] and up.
+    AllSoundOff {
+        timing: u32,
+        /// The channel whose voices should be silenced, in `0..16`. `None` means that the voices on
+        /// all channels should be silenced. VST3 plugins cannot send events without a channel to
+        /// the host.
+        channel: Option<u8>,
+    },
 
     /// Sent by the plugin to the host to indicate that a voice has ended. This **needs** to be sent
     /// when a voice terminates when using polyphonic modulation. Otherwise you can ignore this
@@ -301,7 +359,9 @@ pub enum NoteEvent<S> {
     ///
     /// The wrapper does not perform any special handling for two message 14-bit CCs (where the CC
     /// number is in `0..32`, and the next CC is that number plus 32) or for four message RPN
-    /// messages. For now you will need to handle these CCs yourself.
+    /// messages. For now you will need to handle these CCs yourself. All sound off (CC 120) and all
+    /// notes off (CC 123) messages are sent as [`AllSoundOff`][Self::AllSoundOff] and
+    /// [`AllNotesOff`][Self::AllNotesOff] events instead.
     MidiCC {
         timing: u32,
         /// The affected channel, in `0..16`.
@@ -325,6 +385,17 @@ pub enum NoteEvent<S> {
     /// plugin doesn't support this kind of message), then this will be logged during debug builds
     /// of the plugin, and no event is emitted.
     MidiSysEx { timing: u32, message: S },
+    /// A raw MIDI message as it was received from the host, only sent when
+    /// [`Plugin::MIDI_INPUT_RAW`][crate::prelude::Plugin::MIDI_INPUT_RAW] is enabled. Messages
+    /// shorter than three bytes are padded with zeroes. Plugins can also output these events when
+    /// [`MidiConfig::MidiCCs`] is enabled for the plugin's MIDI output. VST3 does not support raw
+    /// MIDI, so the VST3 wrapper converts these events to the equivalent VST3 events and drops
+    /// messages that VST3 cannot represent.
+    MidiRaw {
+        timing: u32,
+        /// The MIDI message, starting with the status byte.
+        data: [u8; 3],
+    },
 }
 
 /// The result of converting a `NoteEvent<S>` to MIDI. This is a bit weirder than it would have to
@@ -346,6 +417,8 @@ impl<S> NoteEvent<S> {
             NoteEvent::NoteOn { timing, .. } => *timing,
             NoteEvent::NoteOff { timing, .. } => *timing,
             NoteEvent::Choke { timing, .. } => *timing,
+            NoteEvent::AllNotesOff { timing, .. } => *timing,
+            NoteEvent::AllSoundOff { timing, .. } => *timing,
             NoteEvent::VoiceTerminated { timing, .. } => *timing,
             NoteEvent::PolyModulation { timing, .. } => *timing,
             NoteEvent::MonoAutomation { timing, .. } => *timing,
@@ -361,6 +434,7 @@ impl<S> NoteEvent<S> {
             NoteEvent::MidiCC { timing, .. } => *timing,
             NoteEvent::MidiProgramChange { timing, .. } => *timing,
             NoteEvent::MidiSysEx { timing, .. } => *timing,
+            NoteEvent::MidiRaw { timing, .. } => *timing,
         }
     }
 
@@ -370,6 +444,8 @@ impl<S> NoteEvent<S> {
             NoteEvent::NoteOn { voice_id, .. } => *voice_id,
             NoteEvent::NoteOff { voice_id, .. } => *voice_id,
             NoteEvent::Choke { voice_id, .. } => *voice_id,
+            NoteEvent::AllNotesOff { .. } => None,
+            NoteEvent::AllSoundOff { .. } => None,
             NoteEvent::VoiceTerminated { voice_id, .. } => *voice_id,
             NoteEvent::PolyModulation { voice_id, .. } => Some(*voice_id),
             NoteEvent::MonoAutomation { .. } => None,
@@ -385,6 +461,7 @@ impl<S> NoteEvent<S> {
             NoteEvent::MidiCC { .. } => None,
             NoteEvent::MidiProgramChange { .. } => None,
             NoteEvent::MidiSysEx { .. } => None,
+            NoteEvent::MidiRaw { .. } => None,
         }
     }
 
@@ -394,6 +471,8 @@ impl<S> NoteEvent<S> {
             NoteEvent::NoteOn { channel, .. } => Some(*channel),
             NoteEvent::NoteOff { channel, .. } => Some(*channel),
             NoteEvent::Choke { channel, .. } => Some(*channel),
+            NoteEvent::AllNotesOff { channel, .. } => *channel,
+            NoteEvent::AllSoundOff { channel, .. } => *channel,
             NoteEvent::VoiceTerminated { channel, .. } => Some(*channel),
             NoteEvent::PolyModulation { .. } => None,
             NoteEvent::MonoAutomation { .. } => None,
@@ -409,6 +488,30 @@ impl<S> NoteEvent<S> {
             NoteEvent::MidiCC { channel, .. } => Some(*channel),
             NoteEvent::MidiProgramChange { channel, .. } => Some(*channel),
             NoteEvent::MidiSysEx { .. } => None,
+            NoteEvent::MidiRaw { data, .. } if (0x80..0xf0).contains(&data[0]) => {
+                Some(data[0] & midi::MIDI_CHANNEL_MASK)
+            }
+            NoteEvent::MidiRaw { .. } => None,
+        }
+    }
+
+    /// Create a [`NoteEvent::MidiRaw`] event for a MIDI message. Returns `None` if the message is
+    /// empty, if it's longer than three bytes, if it doesn't start with a status byte, or if it's a
+    /// SysEx message. SysEx messages are handled by the plugin's [`SysExMessage`] type instead, see
+    /// [`RawSysEx`][sysex::RawSysEx] for receiving them as raw bytes.
+    pub fn from_raw_midi(timing: u32, midi_data: &[u8]) -> Option<Self> {
+        match midi_data {
+            [status_byte, ..] if midi_data.len() <= 3 && *status_byte >= 0x80 => {
+                if *status_byte == 0xf0 {
+                    return None;
+                }
+
+                let mut data = [0; 3];
+                data[..midi_data.len()].copy_from_slice(midi_data);
+
+                Some(NoteEvent::MidiRaw { timing, data })
+            }
+            _ => None,
         }
     }
 }
@@ -472,6 +575,18 @@ impl<S: SysExMessage> NoteEvent<S> {
                             / ((1 << 14) - 1) as f32,
                     });
                 }
+                midi::CONTROL_CHANGE if midi_data[1] == ALL_SOUND_OFF_CC => {
+                    return Ok(NoteEvent::AllSoundOff {
+                        timing,
+                        channel: Some(channel),
+                    });
+                }
+                midi::CONTROL_CHANGE if midi_data[1] == ALL_NOTES_OFF_CC => {
+                    return Ok(NoteEvent::AllNotesOff {
+                        timing,
+                        channel: Some(channel),
+                    });
+                }
                 midi::CONTROL_CHANGE => {
                     return Ok(NoteEvent::MidiCC {
                         timing,
@@ -598,6 +713,22 @@ impl<S: SysExMessage> NoteEvent<S> {
                 cc,
                 (value * 127.0).round().clamp(0.0, 127.0) as u8,
             ])),
+            NoteEvent::AllNotesOff {
+                timing: _,
+                channel: Some(channel),
+            } => Some(MidiResult::Basic([
+                midi::CONTROL_CHANGE | channel,
+                ALL_NOTES_OFF_CC,
+                0,
+            ])),
+            NoteEvent::AllSoundOff {
+                timing: _,
+                channel: Some(channel),
+            } => Some(MidiResult::Basic([
+                midi::CONTROL_CHANGE | channel,
+                ALL_SOUND_OFF_CC,
+                0,
+            ])),
             NoteEvent::MidiProgramChange {
                 timing: _,
                 channel,
@@ -613,7 +744,10 @@ impl<S: SysExMessage> NoteEvent<S> {
                 let (padded_sysex_buffer, length) = message.to_buffer();
                 Some(MidiResult::SysEx(padded_sysex_buffer, length))
             }
+            NoteEvent::MidiRaw { timing: _, data } => Some(MidiResult::Basic(data)),
             NoteEvent::Choke { .. }
+            | NoteEvent::AllNotesOff { channel: None, .. }
+            | NoteEvent::AllSoundOff { channel: None, .. }
             | NoteEvent::VoiceTerminated { .. }
             | NoteEvent::PolyModulation { .. }
             | NoteEvent::MonoAutomation { .. }
@@ -626,14 +760,44 @@ impl<S: SysExMessage> NoteEvent<S> {
         }
     }
 
+    /// Add a sample offset to this event's timing, needed to move events into the wrapper's
+    /// buffered blocks when the plugin uses a fixed block size.
+    pub(crate) fn add_timing(&mut self, samples: u32) {
+        match self {
+            NoteEvent::NoteOn { timing, .. } => *timing += samples,
+            NoteEvent::NoteOff { timing, .. } => *timing += samples,
+            NoteEvent::Choke { timing, .. } => *timing += samples,
+            NoteEvent::AllNotesOff { timing, .. } => *timing += samples,
+            NoteEvent::AllSoundOff { timing, .. } => *timing += samples,
+            NoteEvent::VoiceTerminated { timing, .. } => *timing += samples,
+            NoteEvent::PolyModulation { timing, .. } => *timing += samples,
+            NoteEvent::MonoAutomation { timing, .. } => *timing += samples,
+            NoteEvent::PolyPressure { timing, .. } => *timing += samples,
+            NoteEvent::PolyVolume { timing, .. } => *timing += samples,
+            NoteEvent::PolyPan { timing, .. } => *timing += samples,
+            NoteEvent::PolyTuning { timing, .. } => *timing += samples,
+            NoteEvent::PolyVibrato { timing, .. } => *timing += samples,
+            NoteEvent::PolyExpression { timing, .. } => *timing += samples,
+            NoteEvent::PolyBrightness { timing, .. } => *timing += samples,
+            NoteEvent::MidiChannelPressure { timing, .. } => *timing += samples,
+            NoteEvent::MidiPitchBend { timing, .. } => *timing += samples,
+            NoteEvent::MidiCC { timing, .. } => *timing += samples,
+            NoteEvent::MidiProgramChange { timing, .. } => *timing += samples,
+            NoteEvent::MidiSysEx { timing, .. } => *timing += samples,
+            NoteEvent::MidiRaw { timing, .. } => *timing += samples,
+        }
+    }
+
     /// Subtract a sample offset from this event's timing, needed to compensate for the block
     /// splitting in the VST3 wrapper implementation because all events have to be read upfront.
-    #[cfg_attr(not(feature = "vst3"), allow(dead_code))]
+    #[cfg_attr(not(any(feature = "vst3", feature = "standalone")), allow(dead_code))]
     pub(crate) fn subtract_timing(&mut self, samples: u32) {
         match self {
             NoteEvent::NoteOn { timing, .. } => *timing -= samples,
             NoteEvent::NoteOff { timing, .. } => *timing -= samples,
             NoteEvent::Choke { timing, .. } => *timing -= samples,
+            NoteEvent::AllNotesOff { timing, .. } => *timing -= samples,
+            NoteEvent::AllSoundOff { timing, .. } => *timing -= samples,
             NoteEvent::VoiceTerminated { timing, .. } => *timing -= samples,
             NoteEvent::PolyModulation { timing, .. } => *timing -= samples,
             NoteEvent::MonoAutomation { timing, .. } => *timing -= samples,
@@ -649,6 +813,7 @@ impl<S: SysExMessage> NoteEvent<S> {
             NoteEvent::MidiCC { timing, .. } => *timing -= samples,
             NoteEvent::MidiProgramChange { timing, .. } => *timing -= samples,
             NoteEvent::MidiSysEx { timing, .. } => *timing -= samples,
+            NoteEvent::MidiRaw { timing, .. } => *timing -= samples,
         }
     }
 }
@@ -743,6 +908,41 @@ mod tests {
         assert_eq!(roundtrip_basic_event(event), event);
     }
 
+    #[test]
+    fn test_all_notes_off_midi_conversion() {
+        let event = NoteEvent::<()>::AllNotesOff {
+            timing: TIMING,
+            channel: Some(3),
+        };
+
+        assert_eq!(roundtrip_basic_event(event), event);
+        assert_eq!(
+            NoteEvent::<()>::from_midi(TIMING, &[midi::CONTROL_CHANGE | 3, 123, 0]),
+            Ok(event)
+        );
+    }
+
+    #[test]
+    fn test_all_sound_off_midi_conversion() {
+        let event = NoteEvent::<()>::AllSoundOff {
+            timing: TIMING,
+            channel: Some(3),
+        };
+
+        assert_eq!(roundtrip_basic_event(event), event);
+        assert_eq!(
+            NoteEvent::<()>::from_midi(TIMING, &[midi::CONTROL_CHANGE | 3, 120, 0]),
+            Ok(event)
+        );
+        // There's no single MIDI message that targets all channels
+        assert!(NoteEvent::<()>::AllSoundOff {
+            timing: TIMING,
+            channel: None,
+        }
+        .as_midi()
+        .is_none());
+    }
+
     #[test]
     fn test_program_change_midi_conversion() {
         let event = NoteEvent::<()>::MidiProgramChange {
@@ -754,8 +954,41 @@ mod tests {
         assert_eq!(roundtrip_basic_event(event), event);
     }
 
+    #[test]
+    fn test_raw_midi_conversion() {
+        let event = NoteEvent::<()>::from_raw_midi(TIMING, &[0xd3, 0x40]).unwrap();
+        assert_eq!(
+            event,
+            NoteEvent::MidiRaw {
+                timing: TIMING,
+                data: [0xd3, 0x40, 0x00],
+            }
+        );
+        assert_eq!(event.channel(), Some(3));
+
+        match event.as_midi().unwrap() {
+            MidiResult::Basic(midi_data) => assert_eq!(midi_data, [0xd3, 0x40, 0x00]),
+            MidiResult::SysEx(_, _) => panic!("Unexpected SysEx result"),
+        }
+    }
+
+    #[test]
+    fn test_invalid_raw_midi() {
+        assert_eq!(NoteEvent::<()>::from_raw_midi(TIMING, &[]), None);
+        assert_eq!(NoteEvent::<()>::from_raw_midi(TIMING, &[0x40, 0x40]), None);
+        assert_eq!(
+            NoteEvent::<()>::from_raw_midi(TIMING, &[0xf0, 0x7e, 0xf7]),
+            None
+        );
+        assert_eq!(
+            NoteEvent::<()>::from_raw_midi(TIMING, &[0x90, 0x40, 0x40, 0x40]),
+            None
+        );
+    }
+
     mod sysex {
         use super::*;
+        use crate::midi::sysex::RawSysEx;
 
         #[derive(Clone, Debug, PartialEq)]
         enum MessageType {
@@ -816,5 +1049,29 @@ mod tests {
 
             assert!(parsed.is_err());
         }
+
+        #[test]
+        fn test_raw_sysex_roundtrip() {
+            let midi_data = [0xf0, 0x41, 0x10, 0x42, 0x12, 0xf7];
+            let event = NoteEvent::<RawSysEx<8>>::from_midi(TIMING, &midi_data).unwrap();
+            match event {
+                NoteEvent::MidiSysEx { timing, message } => {
+                    assert_eq!(timing, TIMING);
+                    assert_eq!(message.as_bytes(), midi_data);
+                }
+                event => panic!("Unexpected event: {event:?}"),
+            }
+
+            match event.as_midi() {
+                Some(MidiResult::SysEx(padded_sysex_buffer, length)) => {
+                    assert_eq!(padded_sysex_buffer[..length], midi_data)
+                }
+                result => panic!("Unexpected result: {result:?}"),
+            }
+
+            // Messages that are too long or that are not SysEx messages are rejected
+            assert!(NoteEvent::<RawSysEx<4>>::from_midi(TIMING, &midi_data).is_err());
+            assert!(NoteEvent::<RawSysEx<8>>::from_midi(TIMING, &[0xf8]).is_err());
+        }
     }
 }
@@ -49,3 +49,52 @@ impl SysExMessage for () {
         ([], 0)
     }
 }
+
+/// A SysEx message type that accepts every SysEx message of up to `N` bytes as raw bytes. Use this
+/// as a plugin's [`SysExMessage`][crate::prelude::Plugin::SysExMessage] type to implement your own
+/// SysEx handling. This is the SysEx counterpart to
+/// [`RawMidiConfig`][crate::prelude::RawMidiConfig]. Received messages are sent to the plugin as
+/// [`NoteEvent::MidiSysEx`][crate::prelude::NoteEvent::MidiSysEx] events in the same order as the
+/// other MIDI messages, and messages created with [`RawSysEx::new()`] can also be output by the
+/// plugin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawSysEx<const N: usize> {
+    buffer: [u8; N],
+    length: usize,
+}
+
+impl<const N: usize> RawSysEx<N> {
+    /// Create a message from its raw bytes, including the `0xf0` start byte and the `0xf7` end
+    /// byte. Returns `None` if the message does not start with `0xf0` or if it's longer than `N`
+    /// bytes.
+    pub fn new(message: &[u8]) -> Option<Self> {
+        if message.first() != Some(&0xf0) || message.len() > N {
+            return None;
+        }
+
+        let mut buffer = [0; N];
+        buffer[..message.len()].copy_from_slice(message);
+
+        Some(Self {
+            buffer,
+            length: message.len(),
+        })
+    }
+
+    /// The message's raw bytes, including the start and end bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer[..self.length]
+    }
+}
+
+impl<const N: usize> SysExMessage for RawSysEx<N> {
+    type Buffer = [u8; N];
+
+    fn from_buffer(buffer: &[u8]) -> Option<Self> {
+        Self::new(buffer)
+    }
+
+    fn to_buffer(self) -> (Self::Buffer, usize) {
+        (self.buffer, self.length)
+    }
+}
@@ -15,6 +15,7 @@ pub use nih_plug_derive::Params;
 
 // Parameter types
 mod boolean;
+mod changed;
 pub mod enums;
 mod float;
 mod integer;
@@ -25,6 +26,7 @@ pub mod range;
 pub mod smoothing;
 
 pub use boolean::BoolParam;
+pub use changed::ChangedParams;
 pub use enums::EnumParam;
 pub use float::FloatParam;
 pub use integer::IntParam;
@@ -41,18 +43,52 @@ bitflags::bitflags! {
         const BYPASS = 1 << 0;
         /// The parameter cannot be changed from an automation lane. The parameter can however still
         /// be manually changed by the user from either the plugin's own GUI or from the host's
-        /// generic UI.
+        /// generic UI. Hosts will not offer the parameter in their automation lists, but it is
+        /// still a regular host parameter that's saved as part of the plugin's state. This is
+        /// different from `#[persist]` fields, which are saved in the plugin's state but which are
+        /// not exposed to the host at all.
         const NON_AUTOMATABLE = 1 << 1;
         /// Hides the parameter in the host's generic UI for this plugin. This also implies
         /// `NON_AUTOMATABLE`. Setting this does not prevent you from changing the parameter in the
-        /// plugin's editor GUI.
+        /// plugin's editor GUI, and the parameter's value is still saved as part of the plugin's
+        /// state.
         const HIDDEN = 1 << 2;
         /// Don't show this parameter when generating a generic UI for the plugin using one of
         /// NIH-plug's generic UI widgets.
         const HIDE_IN_GENERIC_UI = 1 << 3;
+        /// When applied to a [`FloatParam`], this turns the parameter into a read-only output
+        /// parameter that shows the plugin's gain reduction in decibels. The plugin reports its
+        /// gain reduction using
+        /// [`ProcessContext::set_gain_reduction()`][crate::prelude::ProcessContext::set_gain_reduction()],
+        /// and the wrapper then updates this parameter so hosts can show it on their meters. Only
+        /// a single parameter can be a gain reduction parameter. This also implies
+        /// `NON_AUTOMATABLE`.
+        const GAIN_REDUCTION = 1 << 4;
+        /// Leave this parameter untouched when randomizing the plugin's parameters using
+        /// [`ParamSetter::randomize_parameters()`][crate::prelude::ParamSetter::randomize_parameters()].
+        /// Useful for parameters like output gain or oversampling where a random value would be
+        /// more annoying than inspiring.
+        const NON_RANDOMIZABLE = 1 << 5;
     }
 }
 
+/// How a parameter should be shown in one of NIH-plug's generic UI widgets. Generic UIs can take a
+/// predicate that computes this for every parameter from the current parameter values, for
+/// instance to hide a sidechain filter's frequency while the sidechain is disabled. This is purely
+/// cosmetic. The parameter still exists, it can still be automated, and the host's own generic UI
+/// still shows it. Use [`ParamFlags::HIDE_IN_GENERIC_UI`] for parameters that should never be
+/// shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParamVisibility {
+    /// The parameter is shown and it can be edited. This is the default.
+    #[default]
+    Visible,
+    /// The parameter is shown, but it is greyed out and cannot be edited from the generic UI.
+    Disabled,
+    /// The parameter is not shown.
+    Hidden,
+}
+
 // See https://rust-lang.github.io/api-guidelines/future-proofing.html for more information
 mod sealed {
     /// Dummy trait to prevent [`Param`] from being implemented outside of NIH-plug. This is not
@@ -242,14 +278,38 @@ pub(crate) trait ParamMut: Param {
 /// collisions) that will be used to identify the parameter internally so you can safely move it
 /// around and rename the field without breaking compatibility with old presets.
 ///
+/// ## `#[display_order = 2]`, `#[group = "group name"]`
+///
+/// By default parameters are shown in the host's generic UI in the order they are declared in. The
+/// `display_order` attribute can be added to a field with an `id` or a `nested` attribute to move
+/// it to a different position without reordering the struct. Fields without this attribute use
+/// their index in the list of parameter fields as their display order. If that index is the same
+/// as another field's explicit display order, then the field with the explicit display order comes
+/// first, so `#[display_order = 0]` moves a parameter to the front. Other ties keep their
+/// declaration order. A nested object's parameters are always moved together. This only changes
+/// the order the wrappers report the parameters in, the parameter IDs and the hashes the wrappers
+/// derive from them stay the same. The `group` attribute puts a single parameter defined with `id`
+/// in a group, just like `#[nested(group = "...")]` does for nested parameter objects.
+///
+/// ```ignore
+/// #[derive(Params)]
+/// struct MyParams {
+///     #[id = "gain"]
+///     #[display_order = 0]
+///     #[group = "Output"]
+///     pub gain: FloatParam,
+/// }
+/// ```
+///
 /// ## `#[persist = "key"]`
 ///
 /// The struct can also contain other fields that should be persisted along with the rest of the
 /// preset data. These fields should be [`PersistentField`][persist::PersistentField]s annotated
 /// with the `#[persist = "key"]` attribute containing types that can be serialized and deserialized
-/// with [Serde](https://serde.rs/).
+/// with [Serde](https://serde.rs/). See the [`persist`] module for how to share these fields between
+/// the editor and the audio thread without locking.
 ///
-/// ## `#[nested]`, `#[nested(group_name = "group name")]`
+/// ## `#[nested]`, `#[nested(group = "group name")]`
 ///
 /// Finally, the `Params` object may include parameters from other objects. Setting a group name is
 /// optional, but some hosts can use this information to display the parameters in a tree structure.
@@ -258,23 +318,37 @@ pub(crate) trait ParamMut: Param {
 ///
 /// Take a look at the example gain example plugin to see how this is used.
 ///
-/// ## `#[nested(id_prefix = "foo", group_name = "Foo")]`
+/// ## `#[nested(id_prefix = "foo", group = "Foo")]`
 ///
 /// Adding this attribute to a `Params` sub-object works similarly to the regular `#[nested]`
 /// attribute, but it also adds an ID to all parameters from the nested object. If a parameter in
 /// the nested nested object normally has parameter ID `bar`, the parameter's ID will now be renamed
-/// to `foo_bar`. The same thing happens with persistent field keys to support multiple copies of
-/// the field. _This makes it possible to reuse the same parameter struct with different names and
-/// parameter indices._
+/// to `foo_bar`. The prefix and the original ID are always joined with an underscore, so the prefix
+/// itself should not end with one. The same thing happens with persistent field keys to support
+/// multiple copies of the field. _This makes it possible to reuse the same parameter struct with
+/// different names and parameter indices._ The group name is prefixed to the groups of the nested
+/// object's parameters, so two copies of an oscillator's parameters could be shown as
+/// `Oscillator 1` and `Oscillator 2` in the host.
 ///
-/// ## `#[nested(array, group_name = "Foo")]`
+/// ## `#[nested(array, group = "Foo")]`
 ///
 /// This can be applied to an array-like data structure and it works similar to a `nested` attribute
-/// with an `id_name`, except that it will iterate over the array and create unique indices for all
+/// with an `id_prefix`, except that it will iterate over the array and create unique indices for all
 /// nested parameters. If the nested parameters object has a parameter called `bar`, then that
 /// parameter will belong to the group `Foo {array_index + 1}`, and it will have the renamed
 /// parameter ID `bar_{array_index + 1}`. The same thing applies to persistent field keys.
 ///
+/// ## `#[nested(array, id_format = "band{index}_{id}", group = "Band")]`
+///
+/// The `id_format` attribute changes how the IDs for the nested parameters in an array are formed.
+/// `{id}` is replaced by the nested parameter's original ID, and `{index}` is replaced by the array
+/// index plus one. With the format above, the `gain` parameter for the first element in the array
+/// will get the ID `band1_gain`. The format must contain exactly one `{id}` placeholder and at
+/// least one `{index}` placeholder so every element's IDs are unique. Make sure the resulting IDs
+/// stay unambiguous when the array grows past nine elements, for instance by putting a separator
+/// after the index. Both arrays and `Vec`s of `Params` objects can be used here. Persistent field
+/// keys are formatted the same way.
+///
 /// # Safety
 ///
 /// This implementation is safe when using from the wrapper because the plugin's returned `Params`
@@ -297,6 +371,20 @@ pub unsafe trait Params: 'static + Send + Sync {
     /// fine to be able to support custom reusable Params implementations.
     fn param_map(&self) -> Vec<(String, ParamPtr, String)>;
 
+    /// Look up a parameter by its string ID, like the `stable` in `#[id = "stable"]`. IDs from
+    /// nested parameter objects include their prefixes, so this uses the same IDs as
+    /// [`param_map()`][Self::param_map()]. The comparison is case sensitive, and `None` is returned
+    /// for unknown IDs. This is meant for scripting, OSC control, and generic editors. It builds the
+    /// parameter map on every call, so look up the parameter once and store the pointer when it's
+    /// needed more often. See [`ParamSetter`][crate::prelude::ParamSetter] for helpers that get and
+    /// set parameters by ID.
+    fn param_by_id(&self, id: &str) -> Option<ParamPtr> {
+        self.param_map()
+            .into_iter()
+            .find(|(param_id, _, _)| param_id == id)
+            .map(|(_, param_ptr, _)| param_ptr)
+    }
+
     /// Serialize all fields marked with `#[persist = "stable_name"]` into a hash map containing
     /// JSON-representations of those fields so they can be written to the plugin's state and
     /// recalled later. This uses [`persist::serialize_field()`] under the hood.
@@ -0,0 +1,175 @@
+//! Tracking which parameters have changed between process calls.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::internals::ParamPtr;
+use super::{Param, Params};
+
+/// Keeps track of which of a plugin's parameters have changed since the previous block. This lets
+/// [`Plugin::process()`][crate::prelude::Plugin::process()] only recompute expensive things like
+/// filter coefficients when the parameters they depend on have actually changed, instead of doing
+/// that for every block or for every sample.
+///
+/// Call [`update()`][Self::update()] once at the start of every process call. That call is the
+/// reset point. Afterwards [`is_changed()`][Self::is_changed()] and
+/// [`changed_ids()`][Self::changed_ids()] report the parameters whose values are different from
+/// their values at the previous `update()` call, and they keep reporting the same parameters until
+/// the next `update()` call. Checking a parameter does not reset anything, so the same parameter can
+/// be checked from multiple places within a block. Every `ChangedParams` object keeps its own
+/// state, so multiple objects can track the same parameters independently.
+///
+/// Changes from automation, monophonic modulation, the GUI, and state restores are all detected. A
+/// parameter that changes and then changes back to its old value before the next `update()` call
+/// is not reported. All parameters are reported as changed after the first `update()` call, so
+/// everything gets computed in the first block.
+///
+/// ```ignore
+/// fn process(&mut self, buffer: &mut Buffer, ...) -> ProcessStatus {
+///     self.changed_params.update();
+///     if self.changed_params.is_changed(&self.params.cutoff) {
+///         self.filter.set_cutoff(self.params.cutoff.value());
+///     }
+///
+///     ...
+/// }
+/// ```
+pub struct ChangedParams {
+    /// Keeps the parameters alive for as long as this object exists, since `tracked` contains
+    /// pointers to them.
+    _params: Arc<dyn Params>,
+    /// The tracked parameters, in the same order as in the parameters object's param map.
+    tracked: Vec<TrackedParam>,
+    /// Indices into `tracked`, used to look up parameters in
+    /// [`is_changed()`][Self::is_changed()].
+    indices: HashMap<ParamPtr, usize>,
+}
+
+/// A parameter tracked by [`ChangedParams`].
+struct TrackedParam {
+    id: String,
+    ptr: ParamPtr,
+    /// The parameter's modulated normalized value at the last `update()` call, or `None` before the
+    /// first call.
+    last_value: Option<f32>,
+    /// Whether the value changed between the last two `update()` calls.
+    changed: bool,
+}
+
+impl ChangedParams {
+    /// Track changes to all of the parameters in `params`. This allocates, so it should be called
+    /// from the plugin's `Default` implementation or from
+    /// [`Plugin::initialize()`][crate::prelude::Plugin::initialize()].
+    pub fn new(params: Arc<dyn Params>) -> Self {
+        let param_map = params.param_map();
+        let mut tracked = Vec::with_capacity(param_map.len());
+        let mut indices = HashMap::with_capacity(param_map.len());
+        for (idx, (id, ptr, _)) in param_map.into_iter().enumerate() {
+            indices.insert(ptr, idx);
+            tracked.push(TrackedParam {
+                id,
+                ptr,
+                last_value: None,
+                changed: false,
+            });
+        }
+
+        Self {
+            _params: params,
+            tracked,
+            indices,
+        }
+    }
+
+    /// Determine which parameters have changed since the last time this function was called. This
+    /// should be called once at the start of every process call. This does not allocate.
+    pub fn update(&mut self) {
+        for param in &mut self.tracked {
+            // SAFETY: `self._params` keeps the parameters alive
+            let value = unsafe { param.ptr.modulated_normalized_value() };
+            param.changed = param.last_value != Some(value);
+            param.last_value = Some(value);
+        }
+    }
+
+    /// Whether the parameter's value changed between the last two [`update()`][Self::update()]
+    /// calls. Always returns `false` for parameters that are not part of the tracked parameters
+    /// object.
+    pub fn is_changed(&self, param: &impl Param) -> bool {
+        self.indices
+            .get(&param.as_ptr())
+            .is_some_and(|&idx| self.tracked[idx].changed)
+    }
+
+    /// Whether any of the parameters changed between the last two [`update()`][Self::update()]
+    /// calls.
+    pub fn any_changed(&self) -> bool {
+        self.tracked.iter().any(|param| param.changed)
+    }
+
+    /// The IDs of the parameters that changed between the last two [`update()`][Self::update()]
+    /// calls, in the same order as in the parameters object's param map.
+    pub fn changed_ids(&self) -> impl Iterator<Item = &str> + '_ {
+        self.tracked
+            .iter()
+            .filter(|param| param.changed)
+            .map(|param| param.id.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::ParamMut;
+    use crate::testing::TestParams;
+
+    #[test]
+    fn changes_are_reported_until_the_next_update() {
+        let params = Arc::new(TestParams::default());
+        let mut changed_params = ChangedParams::new(params.clone());
+
+        // Everything is reported as changed in the first block
+        changed_params.update();
+        assert_eq!(
+            changed_params.changed_ids().collect::<Vec<_>>(),
+            ["gain", "gr", "pitch"]
+        );
+        changed_params.update();
+        assert!(!changed_params.any_changed());
+
+        // Setting a parameter to its current value is not a change
+        params.gain.set_normalized_value(0.75);
+        params.pitch.set_normalized_value(0.0);
+        changed_params.update();
+        assert_eq!(changed_params.changed_ids().collect::<Vec<_>>(), ["gain"]);
+        // Checking a parameter doesn't reset it
+        assert!(changed_params.is_changed(&params.gain));
+        assert!(changed_params.is_changed(&params.gain));
+        assert!(!changed_params.is_changed(&params.pitch));
+
+        // Changes that were undone before the next block are not reported
+        params.gain_reduction.set_normalized_value(0.5);
+        params.gain_reduction.set_normalized_value(0.0);
+        changed_params.update();
+        assert!(!changed_params.any_changed());
+    }
+
+    #[test]
+    fn independent_trackers() {
+        let params = Arc::new(TestParams::default());
+        let mut first = ChangedParams::new(params.clone());
+        let mut second = ChangedParams::new(params.clone());
+        first.update();
+        second.update();
+
+        params.gain.set_normalized_value(0.25);
+        first.update();
+        assert!(first.is_changed(&params.gain));
+
+        // The second tracker still sees the change after the first one has moved on
+        first.update();
+        second.update();
+        assert!(!first.is_changed(&params.gain));
+        assert!(second.is_changed(&params.gain));
+    }
+}
@@ -393,7 +393,9 @@ impl<T: Enum + PartialEq + 'static> EnumParam<T> {
 
     /// Mark the parameter as non-automatable. This means that the parameter cannot be changed from
     /// an automation lane. The parameter can however still be manually changed by the user from
-    /// either the plugin's own GUI or from the host's generic UI.
+    /// either the plugin's own GUI or from the host's generic UI, and it's still saved as part of
+    /// the plugin's state. See [`ParamFlags::NON_AUTOMATABLE`] for how this differs from
+    /// `#[persist]` fields.
     pub fn non_automatable(mut self) -> Self {
         self.inner.inner = self.inner.inner.non_automatable();
         self
@@ -407,12 +409,27 @@ impl<T: Enum + PartialEq + 'static> EnumParam<T> {
         self
     }
 
+    /// Same as [`hide()`][Self::hide()], but only when NIH-plug is compiled without debug
+    /// assertions. This can be used for debug parameters that should only be visible to the host
+    /// while developing the plugin.
+    pub fn hide_in_release(mut self) -> Self {
+        self.inner.inner = self.inner.inner.hide_in_release();
+        self
+    }
+
     /// Don't show this parameter when generating a generic UI for the plugin using one of
     /// NIH-plug's generic UI widgets.
     pub fn hide_in_generic_ui(mut self) -> Self {
         self.inner.inner = self.inner.inner.hide_in_generic_ui();
         self
     }
+
+    /// Leave this parameter untouched when randomizing the plugin's parameters. See
+    /// [`ParamFlags::NON_RANDOMIZABLE`].
+    pub fn non_randomizable(mut self) -> Self {
+        self.inner.inner = self.inner.inner.non_randomizable();
+        self
+    }
 }
 
 impl EnumParamInner {
@@ -435,10 +452,7 @@ impl EnumParamInner {
     /// Set the parameter based on a serialized stable string identifier. Return whether the ID was
     /// known and the parameter was set.
     pub fn set_from_id(&self, id: &str) -> bool {
-        match self
-            .ids
-            .and_then(|ids| ids.iter().position(|candidate| *candidate == id))
-        {
+        match self.index_from_id(id) {
             Some(index) => {
                 self.set_plain_value(index as i32);
                 true
@@ -446,4 +460,10 @@ impl EnumParamInner {
             None => false,
         }
     }
+
+    /// Get the variant index for a stable string identifier, if this enum parameter has stable IDs
+    /// and the ID is known.
+    pub(crate) fn index_from_id(&self, id: &str) -> Option<usize> {
+        self.ids?.iter().position(|candidate| *candidate == id)
+    }
 }
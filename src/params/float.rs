@@ -51,6 +51,9 @@ pub struct FloatParam {
     /// input. If this is set and if [`value_to_string`][Self::value_to_string] is not set, then
     /// this is also used when formatting the parameter. This must be a positive, nonzero number.
     step_size: Option<f32>,
+    /// The number of steps reported to the host, if set. Unlike `step_size`, this does not affect
+    /// the values the parameter can take when it's changed from the plugin's GUI.
+    step_count: Option<usize>,
     /// The parameter's human readable display name.
     name: String,
     /// The parameter value's unit, added after [`value_to_string`][Self::value_to_string] if that
@@ -141,7 +144,7 @@ impl Param for FloatParam {
     }
 
     fn step_count(&self) -> Option<usize> {
-        None
+        self.step_count
     }
 
     fn previous_step(&self, from: Self::Plain, finer: bool) -> Self::Plain {
@@ -285,6 +288,7 @@ impl FloatParam {
 
             range,
             step_size: None,
+            step_count: None,
             name: name.into(),
             unit: "",
             poly_modulation_id: None,
@@ -333,7 +337,9 @@ impl FloatParam {
                 SmoothingStyle::Logarithmic(_),
                 FloatRange::Linear { min, max }
                 | FloatRange::Skewed { min, max, .. }
-                | FloatRange::SymmetricalSkewed { min, max, .. },
+                | FloatRange::SymmetricalSkewed { min, max, .. }
+                | FloatRange::Logarithmic { min, max }
+                | FloatRange::Custom { min, max, .. },
             ) => *min == 0.0 || *max == 0.0 || min.signum() != max.signum(),
             _ => false,
         };
@@ -372,6 +378,22 @@ impl FloatParam {
         self
     }
 
+    /// Report `step_count` steps to the host, for instance so the host's automation lanes show
+    /// notches. This results in `step_count + 1` distinct values, similar to
+    /// [`Param::step_count()`]. The parameter itself stays continuous, so the plugin's GUI can still
+    /// set any value in the parameter's range. Values coming from the host's automation are
+    /// quantized to these steps in the CLAP wrapper before they're applied, so any smoothing is
+    /// applied after quantization. VST3 plugins rely on the host to only send stepped values
+    /// since VST3 routes changes made from the GUI through the host as well. Use
+    /// [`with_step_size()`][Self::with_step_size()] instead if the parameter's values should
+    /// always be discrete. This must be a positive, nonzero number.
+    pub fn with_step_count(mut self, step_count: usize) -> Self {
+        nih_debug_assert_ne!(step_count, 0, "The step count must be nonzero");
+
+        self.step_count = Some(step_count);
+        self
+    }
+
     /// Use a custom conversion function to convert the plain, unnormalized value to a
     /// string.
     pub fn with_value_to_string(
@@ -398,7 +420,9 @@ impl FloatParam {
 
     /// Mark the parameter as non-automatable. This means that the parameter cannot be changed from
     /// an automation lane. The parameter can however still be manually changed by the user from
-    /// either the plugin's own GUI or from the host's generic UI.
+    /// either the plugin's own GUI or from the host's generic UI, and it's still saved as part of
+    /// the plugin's state. See [`ParamFlags::NON_AUTOMATABLE`] for how this differs from
+    /// `#[persist]` fields.
     pub fn non_automatable(mut self) -> Self {
         self.flags.insert(ParamFlags::NON_AUTOMATABLE);
         self
@@ -412,12 +436,43 @@ impl FloatParam {
         self
     }
 
+    /// Same as [`hide()`][Self::hide()], but only when NIH-plug is compiled without debug
+    /// assertions. This can be used for debug parameters that should only be visible to the host
+    /// while developing the plugin.
+    pub fn hide_in_release(mut self) -> Self {
+        if !cfg!(debug_assertions) {
+            self.flags.insert(ParamFlags::HIDDEN);
+        }
+        self
+    }
+
     /// Don't show this parameter when generating a generic UI for the plugin using one of
     /// NIH-plug's generic UI widgets.
     pub fn hide_in_generic_ui(mut self) -> Self {
         self.flags.insert(ParamFlags::HIDE_IN_GENERIC_UI);
         self
     }
+
+    /// Leave this parameter untouched when randomizing the plugin's parameters. See
+    /// [`ParamFlags::NON_RANDOMIZABLE`].
+    pub fn non_randomizable(mut self) -> Self {
+        self.flags.insert(ParamFlags::NON_RANDOMIZABLE);
+        self
+    }
+
+    /// Use this parameter to show the plugin's gain reduction on the host's meters. The
+    /// parameter's plain value is the gain reduction in decibels, so the range should start at
+    /// zero and the default value should be zero, for instance using
+    /// `FloatRange::Linear { min: 0.0, max: 24.0 }` together with `.with_unit(" dB")`. The
+    /// parameter becomes read-only for the host and it is updated from the gain reduction reported
+    /// through
+    /// [`ProcessContext::set_gain_reduction()`][crate::prelude::ProcessContext::set_gain_reduction()].
+    /// Only a single parameter can be a gain reduction parameter. See
+    /// [`ParamFlags::GAIN_REDUCTION`].
+    pub fn make_gain_reduction_meter(mut self) -> Self {
+        self.flags.insert(ParamFlags::GAIN_REDUCTION);
+        self
+    }
 }
 
 /// Calculate how many decimals to round to when displaying a floating point value with a specific
@@ -437,3 +492,27 @@ fn decimals_from_step_size(step_size: f32) -> usize {
 
     num_digits as usize
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn step_count() {
+        let param = FloatParam::new(
+            "Pitch",
+            0.0,
+            FloatRange::Linear {
+                min: 0.0,
+                max: 12.0,
+            },
+        )
+        .with_step_count(12);
+        assert_eq!(param.step_count(), Some(12));
+
+        // Changing the parameter from the GUI should not quantize the value
+        assert!(param.set_normalized_value(0.125));
+        assert_eq!(param.modulated_normalized_value(), 0.125);
+        assert_eq!(param.value(), 1.5);
+    }
+}
@@ -367,7 +367,9 @@ impl IntParam {
 
     /// Mark the parameter as non-automatable. This means that the parameter cannot be changed from
     /// an automation lane. The parameter can however still be manually changed by the user from
-    /// either the plugin's own GUI or from the host's generic UI.
+    /// either the plugin's own GUI or from the host's generic UI, and it's still saved as part of
+    /// the plugin's state. See [`ParamFlags::NON_AUTOMATABLE`] for how this differs from
+    /// `#[persist]` fields.
     pub fn non_automatable(mut self) -> Self {
         self.flags.insert(ParamFlags::NON_AUTOMATABLE);
         self
@@ -381,10 +383,27 @@ impl IntParam {
         self
     }
 
+    /// Same as [`hide()`][Self::hide()], but only when NIH-plug is compiled without debug
+    /// assertions. This can be used for debug parameters that should only be visible to the host
+    /// while developing the plugin.
+    pub fn hide_in_release(mut self) -> Self {
+        if !cfg!(debug_assertions) {
+            self.flags.insert(ParamFlags::HIDDEN);
+        }
+        self
+    }
+
     /// Don't show this parameter when generating a generic UI for the plugin using one of
     /// NIH-plug's generic UI widgets.
     pub fn hide_in_generic_ui(mut self) -> Self {
         self.flags.insert(ParamFlags::HIDE_IN_GENERIC_UI);
         self
     }
+
+    /// Leave this parameter untouched when randomizing the plugin's parameters. See
+    /// [`ParamFlags::NON_RANDOMIZABLE`].
+    pub fn non_randomizable(mut self) -> Self {
+        self.flags.insert(ParamFlags::NON_RANDOMIZABLE);
+        self
+    }
 }
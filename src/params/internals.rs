@@ -131,7 +131,7 @@ impl ParamPtr {
         match self {
             ParamPtr::FloatParam(p) => (**p).default_plain_value(),
             ParamPtr::IntParam(p) => (**p).default_plain_value() as f32,
-            ParamPtr::BoolParam(p) => (**p).modulated_normalized_value(),
+            ParamPtr::BoolParam(p) => (**p).default_normalized_value(),
             ParamPtr::EnumParam(p) => (**p).default_plain_value() as f32,
         }
     }
@@ -146,9 +146,9 @@ impl ParamPtr {
     pub unsafe fn preview_normalized(&self, plain: f32) -> f32 {
         match self {
             ParamPtr::FloatParam(p) => (**p).preview_normalized(plain),
-            ParamPtr::IntParam(p) => (**p).preview_normalized(plain as i32),
-            ParamPtr::BoolParam(_) => plain,
-            ParamPtr::EnumParam(p) => (**p).preview_normalized(plain as i32),
+            ParamPtr::IntParam(p) => (**p).preview_normalized(plain.round() as i32),
+            ParamPtr::BoolParam(p) => (**p).preview_normalized(plain >= 0.5),
+            ParamPtr::EnumParam(p) => (**p).preview_normalized(plain.round() as i32),
         }
     }
 
@@ -163,8 +163,116 @@ impl ParamPtr {
         match self {
             ParamPtr::FloatParam(p) => (**p).preview_plain(normalized),
             ParamPtr::IntParam(p) => (**p).preview_plain(normalized) as f32,
-            ParamPtr::BoolParam(_) => normalized,
+            ParamPtr::BoolParam(p) => {
+                if (**p).preview_plain(normalized) {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
             ParamPtr::EnumParam(p) => (**p).preview_plain(normalized) as f32,
         }
     }
+
+    /// Format a plain, unnormalized value as a string, as a float. This is the same as calling
+    /// [`normalized_value_to_string()`][Self::normalized_value_to_string()] with the value's
+    /// normalized equivalent. Boolean parameters use `0.0` and `1.0` as their plain values.
+    ///
+    /// # Safety
+    ///
+    /// Calling this function is only safe as long as the object this `ParamPtr` was created for is
+    /// still alive.
+    pub unsafe fn plain_value_to_string(&self, plain: f32, include_unit: bool) -> String {
+        self.normalized_value_to_string(self.preview_normalized(plain), include_unit)
+    }
+
+    /// Parse a string to a plain, unnormalized value, as a float. Returns `None` if the string
+    /// could not be parsed. This is the plain value equivalent of
+    /// [`string_to_normalized_value()`][Self::string_to_normalized_value()].
+    ///
+    /// # Safety
+    ///
+    /// Calling this function is only safe as long as the object this `ParamPtr` was created for is
+    /// still alive.
+    pub unsafe fn string_to_plain_value(&self, string: &str) -> Option<f32> {
+        self.string_to_normalized_value(string)
+            .map(|normalized| self.preview_plain(normalized))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::{BoolParam, Enum, EnumParam, FloatParam, FloatRange, IntParam, IntRange};
+
+    #[derive(Enum, Debug, PartialEq)]
+    enum TestEnum {
+        A,
+        B,
+        C,
+    }
+
+    #[test]
+    fn float_conversions() {
+        let param = FloatParam::new(
+            "Float",
+            5.0,
+            FloatRange::Linear {
+                min: 0.0,
+                max: 10.0,
+            },
+        )
+        .with_unit(" Hz");
+        let ptr = param.as_ptr();
+        unsafe {
+            assert_eq!(ptr.default_plain_value(), 5.0);
+            assert_eq!(ptr.preview_normalized(2.5), 0.25);
+            assert_eq!(ptr.preview_plain(0.25), 2.5);
+            assert_eq!(ptr.plain_value_to_string(2.5, true), "2.5 Hz");
+            assert_eq!(ptr.string_to_plain_value("2.5 Hz"), Some(2.5));
+        }
+    }
+
+    #[test]
+    fn int_conversions() {
+        let param = IntParam::new("Int", 0, IntRange::Linear { min: -2, max: 2 });
+        let ptr = param.as_ptr();
+        unsafe {
+            assert_eq!(ptr.default_plain_value(), 0.0);
+            assert_eq!(ptr.preview_normalized(1.0), 0.75);
+            // Plain values should be rounded rather than truncated
+            assert_eq!(ptr.preview_normalized(0.999), 0.75);
+            assert_eq!(ptr.preview_plain(0.75), 1.0);
+            assert_eq!(ptr.plain_value_to_string(1.0, false), "1");
+            assert_eq!(ptr.string_to_plain_value("-2"), Some(-2.0));
+        }
+    }
+
+    #[test]
+    fn bool_conversions() {
+        let param = BoolParam::new("Bool", true);
+        let ptr = param.as_ptr();
+        unsafe {
+            assert_eq!(ptr.default_plain_value(), 1.0);
+            assert_eq!(ptr.preview_normalized(0.0), 0.0);
+            assert_eq!(ptr.preview_normalized(1.0), 1.0);
+            assert_eq!(ptr.preview_plain(0.25), 0.0);
+            assert_eq!(ptr.preview_plain(0.75), 1.0);
+            assert_eq!(ptr.plain_value_to_string(1.0, false), "On");
+            assert_eq!(ptr.string_to_plain_value("Off"), Some(0.0));
+        }
+    }
+
+    #[test]
+    fn enum_conversions() {
+        let param = EnumParam::new("Enum", TestEnum::B);
+        let ptr = param.as_ptr();
+        unsafe {
+            assert_eq!(ptr.default_plain_value(), 1.0);
+            assert_eq!(ptr.preview_normalized(2.0), 1.0);
+            assert_eq!(ptr.preview_plain(0.5), 1.0);
+            assert_eq!(ptr.plain_value_to_string(2.0, false), "C");
+            assert_eq!(ptr.string_to_plain_value("A"), Some(0.0));
+        }
+    }
 }
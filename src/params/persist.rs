@@ -1,8 +1,52 @@
 //! Traits and helpers for persistent fields. See the [`Params`][super::Params] trait for more
 //! information.
+//!
+//! # Sharing persistent fields with the audio thread
+//!
+//! Persistent fields are often written to by the editor while the audio thread reads them, like the
+//! path to a file the plugin should play back. Locking a `Mutex` or an `RwLock` from the audio
+//! thread can block it while the editor or the host is holding the lock, so those types should not
+//! be used for this. Simple `Copy` values can be stored in an atomic or an
+//! [`AtomicCell`][crossbeam::atomic::AtomicCell]. For anything else, like strings and vectors,
+//! [`ArcSwap`] can be used. Reading an `ArcSwap` is lock-free, and writing a new value replaces the
+//! entire value at once so the audio thread never sees a half-updated value.
+//!
+//! ```
+//! use nih_plug::params::persist::ArcSwap;
+//! use nih_plug::prelude::*;
+//! use std::sync::Arc;
+//!
+//! #[derive(Params, Default)]
+//! struct SamplerParams {
+//!     /// The path to the sample that should be played back. Written to by the editor, and read
+//!     /// from by the audio thread.
+//!     #[persist = "sample-path"]
+//!     sample_path: Arc<ArcSwap<String>>,
+//! }
+//!
+//! let params = Arc::new(SamplerParams::default());
+//!
+//! // The editor gets a copy of the `Arc`, and it can store a new path at any time
+//! let editor_sample_path = params.sample_path.clone();
+//! editor_sample_path.store(Arc::new(String::from("/path/to/sample.wav")));
+//!
+//! // The audio thread can access the current value without locking or allocating
+//! let sample_path = params.sample_path.load();
+//! assert_eq!(sample_path.as_str(), "/path/to/sample.wav");
+//! ```
+//!
+//! The guard returned by `load()` keeps the old value alive until it is dropped, so the audio
+//! thread should not hold on to it for longer than a single process call. If the editor replaces
+//! the value in the meantime, then the old value is deallocated on the audio thread when the guard
+//! is dropped. `ArcSwap` may also allocate a small amount of bookkeeping data the first time a
+//! thread accesses it. The `persistent_path` example plugin shows how to edit a persistent path
+//! from an editor, and how to avoid deallocating old values on the audio thread.
 
 use std::sync::Arc;
 
+/// Re-export for storing persistent fields that are shared between the editor and the audio thread.
+/// See the [module level documentation][self] for more information.
+pub use arc_swap::ArcSwap;
 /// Re-export for use in the [`Params`][super::Params] proc-macro.
 pub use serde_json::from_str as deserialize_field;
 /// Re-export for use in the [`Params`][super::Params] proc-macro.
@@ -217,6 +261,22 @@ where
 impl_persistent_arc!(crossbeam::atomic::AtomicCell<T>,
                      T: serde::Serialize + serde::Deserialize<'a> + Copy + Send);
 
+impl<'a, T> PersistentField<'a, T> for ArcSwap<T>
+where
+    T: serde::Serialize + serde::Deserialize<'a> + Send + Sync,
+{
+    fn set(&self, new_value: T) {
+        self.store(Arc::new(new_value));
+    }
+    fn map<F, R>(&self, f: F) -> R
+    where
+        F: Fn(&T) -> R,
+    {
+        f(&self.load())
+    }
+}
+impl_persistent_arc!(ArcSwap<T>, T);
+
 /// Can be used with the `#[serde(with = "nih_plug::params::internals::serialize_atomic_cell")]`
 /// attribute to serialize `AtomicCell<T>`s.
 pub mod serialize_atomic_cell {
@@ -22,6 +22,46 @@ pub enum FloatRange {
         factor: f32,
         center: f32,
     },
+    /// The values are distributed logarithmically between `min` and `max`, so every doubling of the
+    /// value takes up the same amount of the normalized range. This is the natural mapping for
+    /// frequency parameters. `min` needs to be greater than zero.
+    Logarithmic { min: f32, max: f32 },
+    /// A fully custom mapping between plain values in `[min, max]` and normalized values in
+    /// `[0, 1]`, for parameters that don't fit any of the other ranges. `normalize` should map
+    /// `min` to 0 and `max` to 1, both functions need to be monotonically increasing, and
+    /// `unnormalize` needs to be `normalize`'s inverse. This is checked at a couple points when the
+    /// parameter is created in debug builds. Closures can be used here as long as they don't
+    /// capture anything.
+    ///
+    /// ```
+    /// # use nih_plug::prelude::*;
+    /// // The first half of the range covers 0-100 ms, and the second half covers 100-2000 ms
+    /// let range = FloatRange::Custom {
+    ///     min: 0.0,
+    ///     max: 2000.0,
+    ///     normalize: |plain| {
+    ///         if plain <= 100.0 {
+    ///             plain / 200.0
+    ///         } else {
+    ///             0.5 + (plain - 100.0) / 3800.0
+    ///         }
+    ///     },
+    ///     unnormalize: |normalized| {
+    ///         if normalized <= 0.5 {
+    ///             normalized * 200.0
+    ///         } else {
+    ///             100.0 + (normalized - 0.5) * 3800.0
+    ///         }
+    ///     },
+    /// };
+    /// assert_eq!(range.unnormalize(range.normalize(500.0)), 500.0);
+    /// ```
+    Custom {
+        min: f32,
+        max: f32,
+        normalize: fn(f32) -> f32,
+        unnormalize: fn(f32) -> f32,
+    },
     /// A reversed range that goes from high to low instead of from low to high.
     Reversed(&'static FloatRange),
 }
@@ -94,6 +134,15 @@ impl FloatRange {
                     (1.0 - inverted_scaled_proportion.powf(*factor)) * 0.5
                 }
             }
+            FloatRange::Logarithmic { min, max } => {
+                (plain.clamp(*min, *max) / min).ln() / (max / min).ln()
+            }
+            FloatRange::Custom {
+                min,
+                max,
+                normalize,
+                ..
+            } => normalize(plain.clamp(*min, *max)).clamp(0.0, 1.0),
             FloatRange::Reversed(range) => 1.0 - range.normalize(plain),
         }
     }
@@ -126,6 +175,15 @@ impl FloatRange {
 
                 (skewed_proportion * (max - min)) + min
             }
+            FloatRange::Logarithmic { min, max } => {
+                (min * (max / min).powf(normalized)).clamp(*min, *max)
+            }
+            FloatRange::Custom {
+                min,
+                max,
+                unnormalize,
+                ..
+            } => unnormalize(normalized).clamp(*min, *max),
             FloatRange::Reversed(range) => range.unnormalize(1.0 - normalized),
         }
     }
@@ -140,7 +198,9 @@ impl FloatRange {
         match self {
             FloatRange::Linear { min, max }
             | FloatRange::Skewed { min, max, .. }
-            | FloatRange::SymmetricalSkewed { min, max, .. } => {
+            | FloatRange::SymmetricalSkewed { min, max, .. }
+            | FloatRange::Logarithmic { min, max }
+            | FloatRange::Custom { min, max, .. } => {
                 let normalized_naive_step_size = if finer { 0.005 } else { 0.02 };
                 let naive_step =
                     self.unnormalize(self.normalize(from) - normalized_naive_step_size);
@@ -166,7 +226,9 @@ impl FloatRange {
         match self {
             FloatRange::Linear { min, max }
             | FloatRange::Skewed { min, max, .. }
-            | FloatRange::SymmetricalSkewed { min, max, .. } => {
+            | FloatRange::SymmetricalSkewed { min, max, .. }
+            | FloatRange::Logarithmic { min, max }
+            | FloatRange::Custom { min, max, .. } => {
                 let normalized_naive_step_size = if finer { 0.005 } else { 0.02 };
                 let naive_step =
                     self.unnormalize(self.normalize(from) + normalized_naive_step_size);
@@ -189,7 +251,9 @@ impl FloatRange {
         match self {
             FloatRange::Linear { min, max }
             | FloatRange::Skewed { min, max, .. }
-            | FloatRange::SymmetricalSkewed { min, max, .. } => {
+            | FloatRange::SymmetricalSkewed { min, max, .. }
+            | FloatRange::Logarithmic { min, max }
+            | FloatRange::Custom { min, max, .. } => {
                 ((value / step_size).round() * step_size).clamp(*min, *max)
             }
             FloatRange::Reversed(range) => range.snap_to_step(value, step_size),
@@ -202,7 +266,9 @@ impl FloatRange {
         match self {
             FloatRange::Linear { min, max }
             | FloatRange::Skewed { min, max, .. }
-            | FloatRange::SymmetricalSkewed { min, max, .. } => {
+            | FloatRange::SymmetricalSkewed { min, max, .. }
+            | FloatRange::Logarithmic { min, max }
+            | FloatRange::Custom { min, max, .. } => {
                 nih_debug_assert!(
                     min < max,
                     "The range minimum ({}) needs to be less than the range maximum ({}) and they \
@@ -210,6 +276,34 @@ impl FloatRange {
                     min,
                     max
                 );
+
+                if let FloatRange::Logarithmic { min, .. } = self {
+                    nih_debug_assert!(
+                        *min > 0.0,
+                        "The minimum of a logarithmic range ({}) needs to be greater than zero",
+                        min
+                    );
+                }
+                if let FloatRange::Custom { min, max, .. } = self {
+                    // Custom mappings need to round trip, or the parameter's value would drift
+                    // every time the host or the editor sets it
+                    for i in 0..=10 {
+                        let plain = min + (max - min) * (i as f32 / 10.0);
+                        let round_tripped = self.unnormalize(self.normalize(plain));
+                        nih_debug_assert!(
+                            (round_tripped - plain).abs() <= (max - min) * 1e-4,
+                            "The custom range's unnormalize function is not the inverse of its \
+                             normalize function: {} round trips to {}",
+                            plain,
+                            round_tripped
+                        );
+                    }
+                    nih_debug_assert!(
+                        self.normalize(*min) < self.normalize(*max),
+                        "The custom range's normalize function needs to be monotonically \
+                         increasing"
+                    );
+                }
             }
             FloatRange::Reversed(range) => range.assert_validity(),
         }
@@ -417,6 +511,121 @@ mod tests {
         }
     }
 
+    mod logarithmic {
+        use super::*;
+
+        const fn make_logarithmic_float_range() -> FloatRange {
+            FloatRange::Logarithmic {
+                min: 20.0,
+                max: 20_000.0,
+            }
+        }
+
+        #[test]
+        fn range_normalize_float() {
+            let range = make_logarithmic_float_range();
+            assert_eq!(range.normalize(20.0), 0.0);
+            assert_eq!(range.normalize(20_000.0), 1.0);
+            assert!((range.normalize(200.0) - (1.0 / 3.0)).abs() < 1e-6);
+            assert!((range.normalize(2_000.0) - (2.0 / 3.0)).abs() < 1e-6);
+        }
+
+        #[test]
+        fn range_unnormalize_float() {
+            let range = make_logarithmic_float_range();
+            assert_eq!(range.unnormalize(0.0), 20.0);
+            assert_eq!(range.unnormalize(1.0), 20_000.0);
+            assert!((range.unnormalize(0.5) - 632.4555).abs() < 1e-2);
+        }
+
+        #[test]
+        fn range_round_trip() {
+            let range = make_logarithmic_float_range();
+            for plain in [20.0, 55.0, 440.0, 1_000.0, 12_345.0, 20_000.0] {
+                let round_tripped = range.unnormalize(range.normalize(plain));
+                assert!(
+                    (round_tripped - plain).abs() <= plain * 1e-5,
+                    "{plain} round trips to {round_tripped}"
+                );
+            }
+        }
+
+        #[test]
+        #[should_panic]
+        fn validity_zero_minimum() {
+            FloatRange::Logarithmic {
+                min: 0.0,
+                max: 20_000.0,
+            }
+            .assert_validity();
+        }
+    }
+
+    mod custom {
+        use super::*;
+
+        /// A piecewise linear range where the first half of the normalized range covers 10-12.
+        fn make_custom_float_range() -> FloatRange {
+            FloatRange::Custom {
+                min: 10.0,
+                max: 20.0,
+                normalize: |plain| {
+                    if plain <= 12.0 {
+                        (plain - 10.0) / 4.0
+                    } else {
+                        0.5 + (plain - 12.0) / 16.0
+                    }
+                },
+                unnormalize: |normalized| {
+                    if normalized <= 0.5 {
+                        10.0 + normalized * 4.0
+                    } else {
+                        12.0 + (normalized - 0.5) * 16.0
+                    }
+                },
+            }
+        }
+
+        #[test]
+        fn range_normalize_float() {
+            let range = make_custom_float_range();
+            assert_eq!(range.normalize(11.0), 0.25);
+            assert_eq!(range.normalize(16.0), 0.75);
+        }
+
+        #[test]
+        fn range_unnormalize_float() {
+            let range = make_custom_float_range();
+            assert_eq!(range.unnormalize(0.25), 11.0);
+            assert_eq!(range.unnormalize(0.75), 16.0);
+        }
+
+        #[test]
+        fn range_clamping() {
+            let range = make_custom_float_range();
+            assert_eq!(range.normalize(5.0), 0.0);
+            assert_eq!(range.normalize(25.0), 1.0);
+            assert_eq!(range.unnormalize(1.5), 20.0);
+        }
+
+        #[test]
+        fn validity() {
+            make_custom_float_range().assert_validity();
+        }
+
+        #[test]
+        #[should_panic]
+        fn validity_not_inverse() {
+            FloatRange::Custom {
+                min: 10.0,
+                max: 20.0,
+                normalize: |plain| (plain - 10.0) / 10.0,
+                unnormalize: |normalized| 10.0 + normalized * 5.0,
+            }
+            .assert_validity();
+        }
+    }
+
     mod reversed_linear {
         use super::*;
 
@@ -1,11 +1,17 @@
 //! Utilities to handle smoothing parameter changes over time.
 
-use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
 use std::sync::Arc;
 
 // Re-exported here because it's sued in `SmoothingStyle`.
 pub use atomic_float::AtomicF32;
 
+/// Target values with an absolute value below this threshold are replaced with zero when
+/// [`Smoother::set_flush_to_zero()`] is enabled. This corresponds to -300 dB when used as a gain
+/// value, and it's well above the point where multiplying audio by the value would start producing
+/// denormals.
+pub const FLUSH_TO_ZERO_THRESHOLD: f32 = 1e-15;
+
 /// Controls if and how parameters gets smoothed.
 #[derive(Debug, Clone)]
 pub enum SmoothingStyle {
@@ -63,6 +69,12 @@ pub struct Smoother<T: Smoothable> {
     current: AtomicF32,
     /// The value we're smoothing towards
     target: T::Atomic,
+    /// When set, new target values are reached on the next sample regardless of `style`. See
+    /// [`Smoother::set_bypassed()`].
+    bypassed: AtomicBool,
+    /// When set, target values close to zero are replaced by exactly zero. See
+    /// [`Smoother::set_flush_to_zero()`].
+    flush_to_zero: AtomicBool,
 }
 
 /// An iterator that continuously produces smoothed values. Can be used as an alternative to the
@@ -93,6 +105,16 @@ impl SmoothingStyle {
         }
     }
 
+    /// Whether this is [`SmoothingStyle::Logarithmic`], possibly wrapped in
+    /// [`SmoothingStyle::OversamplingAware`].
+    fn is_logarithmic(&self) -> bool {
+        match self {
+            Self::OversamplingAware(_, style) => style.is_logarithmic(),
+            Self::Logarithmic(_) => true,
+            _ => false,
+        }
+    }
+
     /// Compute the step size for this smoother. `num_steps` can be obtained using
     /// [`SmoothingStyle::num_steps()`]. Check the source code of the [`SmoothingStyle::next()`] and
     /// [`SmoothingStyle::next_step()`] functions for details on how these values should be used.
@@ -186,6 +208,8 @@ impl<T: Smoothable> Default for Smoother<T> {
             step_size: Default::default(),
             current: AtomicF32::new(0.0),
             target: Default::default(),
+            bypassed: AtomicBool::new(false),
+            flush_to_zero: AtomicBool::new(false),
         }
     }
 }
@@ -209,6 +233,8 @@ impl<T: Smoothable> Clone for Smoother<T> {
             step_size: AtomicF32::new(self.step_size.load(Ordering::Relaxed)),
             current: AtomicF32::new(self.current.load(Ordering::Relaxed)),
             target: T::atomic_new(T::atomic_load(&self.target)),
+            bypassed: AtomicBool::new(self.bypassed.load(Ordering::Relaxed)),
+            flush_to_zero: AtomicBool::new(self.flush_to_zero.load(Ordering::Relaxed)),
         }
     }
 }
@@ -249,8 +275,50 @@ impl<T: Smoothable> Smoother<T> {
         SmootherIter { smoother: self }
     }
 
+    /// Temporarily disable or re-enable smoothing without changing the smoother's style. This can
+    /// be used to avoid double smoothing in hosts that already smooth parameter changes, or for a
+    /// snappier sound design mode. This can safely be called from any thread, including from the
+    /// editor through a parameter's `smoothed` field.
+    ///
+    /// While the smoother is bypassed it behaves as if its style was [`SmoothingStyle::None`]: a
+    /// new target value is reached after a single step, so the next call to
+    /// [`next()`][Self::next()] or [`next_block()`][Self::next_block()] will yield the target
+    /// value. Bypassing a smoother that is currently smoothing makes it jump to its target value
+    /// on the next step.
+    pub fn set_bypassed(&self, bypassed: bool) {
+        self.bypassed.store(bypassed, Ordering::Relaxed);
+        if bypassed && self.steps_left.load(Ordering::Relaxed) > 0 {
+            self.steps_left.store(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Whether smoothing has been disabled using [`set_bypassed()`][Self::set_bypassed()].
+    #[inline]
+    pub fn is_bypassed(&self) -> bool {
+        self.bypassed.load(Ordering::Relaxed)
+    }
+
+    /// Replace target values with an absolute value below [`FLUSH_TO_ZERO_THRESHOLD`] with exactly
+    /// zero. This is disabled by default. Enabling it prevents a gain smoother that fades to
+    /// silence from settling on a tiny value that causes denormals further down the signal chain.
+    /// This only affects targets set after calling this function.
+    ///
+    /// [`SmoothingStyle::Logarithmic`] cannot smooth to or from zero, so with that style these
+    /// targets are clamped to `±FLUSH_TO_ZERO_THRESHOLD` instead.
+    pub fn set_flush_to_zero(&self, flush_to_zero: bool) {
+        self.flush_to_zero.store(flush_to_zero, Ordering::Relaxed);
+    }
+
+    /// Whether near-zero targets are flushed to zero. See
+    /// [`set_flush_to_zero()`][Self::set_flush_to_zero()].
+    #[inline]
+    pub fn is_flushing_to_zero(&self) -> bool {
+        self.flush_to_zero.load(Ordering::Relaxed)
+    }
+
     /// Reset the smoother the specified value.
     pub fn reset(&self, value: T) {
+        let value = self.flush_target(value);
         T::atomic_store(&self.target, value);
         self.current.store(value.to_f32(), Ordering::Relaxed);
         self.steps_left.store(0, Ordering::Relaxed);
@@ -258,9 +326,14 @@ impl<T: Smoothable> Smoother<T> {
 
     /// Set the target value.
     pub fn set_target(&self, sample_rate: f32, target: T) {
+        let target = self.flush_target(target);
         T::atomic_store(&self.target, target);
 
-        let steps_left = self.style.num_steps(sample_rate) as i32;
+        let steps_left = if self.bypassed.load(Ordering::Relaxed) {
+            1
+        } else {
+            self.style.num_steps(sample_rate) as i32
+        };
         self.steps_left.store(steps_left, Ordering::Relaxed);
 
         let current = self.current.load(Ordering::Relaxed);
@@ -275,6 +348,23 @@ impl<T: Smoothable> Smoother<T> {
         );
     }
 
+    /// Replace `value` with zero if flushing to zero is enabled and the value is close to zero.
+    /// Logarithmic smoothing would get stuck at zero, so for that style the value is clamped to
+    /// the threshold instead.
+    #[inline]
+    fn flush_target(&self, value: T) -> T {
+        let value_f32 = value.to_f32();
+        if self.flush_to_zero.load(Ordering::Relaxed) && value_f32.abs() < FLUSH_TO_ZERO_THRESHOLD {
+            if self.style.is_logarithmic() {
+                T::from_f32(FLUSH_TO_ZERO_THRESHOLD.copysign(value_f32))
+            } else {
+                T::from_f32(0.0)
+            }
+        } else {
+            value
+        }
+    }
+
     /// Get the next value from this smoother. The value will be equal to the previous value once
     /// the smoothing period is over. This should be called exactly once per sample.
     // Yes, Clippy, like I said, this was intentional
@@ -713,5 +803,97 @@ mod tests {
         assert_eq!(smoother.next(), 20);
     }
 
+    #[test]
+    fn bypassed_smoothing() {
+        let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::Linear(100.0));
+        smoother.reset(10.0);
+        smoother.set_bypassed(true);
+        assert!(smoother.is_bypassed());
+
+        smoother.set_target(100.0, 20.0);
+        assert_eq!(smoother.steps_left(), 1);
+        assert_eq!(smoother.next(), 20.0);
+        assert_eq!(smoother.previous_value(), 20.0);
+
+        let mut block = [0.0; 4];
+        smoother.set_target(100.0, 30.0);
+        smoother.next_block(&mut block, 4);
+        assert_eq!(block, [30.0; 4]);
+
+        // Re-enabling smoothing only affects new target values
+        smoother.set_bypassed(false);
+        smoother.set_target(100.0, 40.0);
+        assert_eq!(smoother.steps_left(), 10);
+    }
+
+    #[test]
+    fn bypassing_while_smoothing() {
+        let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::Linear(100.0));
+        smoother.reset(10.0);
+        smoother.set_target(100.0, 20.0);
+        smoother.next();
+        assert!(smoother.is_smoothing());
+
+        smoother.set_bypassed(true);
+        assert_eq!(smoother.next(), 20.0);
+        assert!(!smoother.is_smoothing());
+    }
+
+    #[test]
+    fn flush_to_zero() {
+        let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::Linear(100.0));
+        smoother.reset(1.0);
+        smoother.set_target(100.0, 1e-30);
+        for _ in 0..10 {
+            smoother.next();
+        }
+        assert_eq!(smoother.next(), 1e-30);
+
+        smoother.reset(1.0);
+        smoother.set_flush_to_zero(true);
+        smoother.set_target(100.0, 1e-30);
+        for _ in 0..(10 - 1) {
+            assert_ne!(smoother.next(), 0.0);
+        }
+        assert_eq!(smoother.next(), 0.0);
+        assert_eq!(smoother.next(), 0.0);
+
+        // Values above the threshold are left alone
+        smoother.set_target(100.0, 1e-3);
+        for _ in 0..10 {
+            smoother.next();
+        }
+        assert_eq!(smoother.next(), 1e-3);
+    }
+
+    #[test]
+    fn logarithmic_flush_to_zero() {
+        let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::Logarithmic(100.0));
+        smoother.set_flush_to_zero(true);
+        smoother.reset(1.0);
+        smoother.set_target(100.0, 1e-30);
+        for _ in 0..10 {
+            smoother.next();
+        }
+        assert_eq!(smoother.next(), FLUSH_TO_ZERO_THRESHOLD);
+
+        // Smoothing away from the clamped value still works
+        smoother.set_target(100.0, 1.0);
+        for _ in 0..(10 - 1) {
+            let value = smoother.next();
+            assert!(value.is_finite() && value > FLUSH_TO_ZERO_THRESHOLD);
+        }
+        assert_eq!(smoother.next(), 1.0);
+
+        // The same applies when resetting the smoother
+        smoother.reset(-1e-30);
+        assert_eq!(smoother.next(), -FLUSH_TO_ZERO_THRESHOLD);
+        smoother.set_target(100.0, -1.0);
+        for _ in 0..(10 - 1) {
+            assert!(smoother.next().is_finite());
+        }
+        assert_eq!(smoother.next(), -1.0);
+    }
+
     // TODO: Tests for the exponential smoothing
 }
@@ -1,13 +1,15 @@
 //! Traits and structs describing plugins and editors. This includes extension structs for features
 //! that are specific to one or more plugin-APIs.
 
+use std::num::NonZeroU32;
 use std::sync::Arc;
 
 use crate::prelude::{
     AsyncExecutor, AudioIOLayout, AuxiliaryBuffers, Buffer, BufferConfig, Editor, InitContext,
-    MidiConfig, Params, PluginState, ProcessContext, SysExMessage,
+    MidiConfig, Params, PluginState, ProcessContext, RawMidiConfig, SysExMessage,
 };
 
+pub mod capabilities;
 pub mod clap;
 #[cfg(feature = "vst3")]
 pub mod vst3;
@@ -101,6 +103,11 @@ pub trait Plugin: Default + Send + 'static {
     /// Whether the plugin accepts note events, and what which events it wants to receive. If this
     /// is set to [`MidiConfig::None`], then the plugin won't receive any note events.
     const MIDI_INPUT: MidiConfig = MidiConfig::None;
+    /// Whether the plugin also receives the MIDI messages sent by the host as raw bytes through
+    /// [`NoteEvent::MidiRaw`][crate::prelude::NoteEvent::MidiRaw] events. This only has an effect
+    /// when [`MIDI_INPUT`][Self::MIDI_INPUT] is not set to [`MidiConfig::None`]. See
+    /// [`RawMidiConfig`] for more information.
+    const MIDI_INPUT_RAW: RawMidiConfig = RawMidiConfig::None;
     /// Whether the plugin can output note events. If this is set to [`MidiConfig::None`], then the
     /// plugin won't have a note output port. When this is set to another value, then in most hosts
     /// the plugin will consume all note and MIDI CC input. If you don't want that, then you will
@@ -111,6 +118,21 @@ pub trait Plugin: Default + Send + 'static {
     /// blocks may be as small as a single sample. Bitwig Studio sends at most one parameter change
     /// every 64 samples.
     const SAMPLE_ACCURATE_AUTOMATION: bool = false;
+    /// If set, then [`process()`][Self::process()] is always called with exactly this many
+    /// samples, regardless of the host's buffer size. This is useful for algorithms that work on
+    /// fixed size frames. The wrapper buffers the host's audio until a full block is available,
+    /// which adds this many samples of latency on top of the latency set through
+    /// [`InitContext::set_latency_samples()`][crate::prelude::InitContext::set_latency_samples()].
+    /// The wrapper reports the combined latency to the host. The [`BufferConfig`] passed to
+    /// [`initialize()`][Self::initialize()] has both its minimum and maximum buffer size set to
+    /// this value.
+    ///
+    /// Parameter changes that happen while a block is being buffered are applied before the next
+    /// block is processed, and note events are delivered with the [`process()`][Self::process()]
+    /// call for the block they fall into. Note events sent by the plugin are delayed along with
+    /// the audio. The transport information describes the host's buffer in which the block was
+    /// completed.
+    const FIXED_BLOCK_SIZE: Option<NonZeroU32> = None;
 
     /// If this is set to true, then the plugin will report itself as having a hard realtime
     /// processing requirement when the host asks for it. Supported hosts will never ask the plugin
@@ -185,6 +207,13 @@ pub trait Plugin: Default + Send + 'static {
     /// Initialize the plugin for the given audio IO configuration. From this point onwards the
     /// audio IO layouts and the buffer sizes are fixed until this function is called again.
     ///
+    /// Whenever the host changes the audio IO layout, the sample rate, the maximum buffer size, or
+    /// the processing mode, this function is called again with the new values before the next
+    /// call to [`process()`][Self::process()]. This is the place to (re)allocate any buffers whose
+    /// sizes depend on these values. The host may or may not call
+    /// [`deactivate()`][Self::deactivate()] before such a change, so this function should not
+    /// rely on it.
+    ///
     /// Before this point, the plugin should not have done any expensive initialization. Please
     /// don't be that plugin that takes twenty seconds to scan.
     ///
@@ -212,7 +241,8 @@ pub trait Plugin: Default + Send + 'static {
 
     /// Clear internal state such as filters and envelopes. This is always called after
     /// [`initialize()`][Self::initialize()], and it may also be called at any other time from the
-    /// audio thread. You should thus not do any allocations in this function.
+    /// audio thread. You should thus not do any allocations in this function. Allocations here are
+    /// also caught by the `assert_process_allocs` feature.
     fn reset(&mut self) {}
 
     /// Process audio. The host's input buffers have already been copied to the output buffers if
@@ -220,7 +250,8 @@ pub trait Plugin: Default + Send + 'static {
     /// guaranteed to contain the same number of samples. Lastly, denormals have already been taken
     /// case of by NIH-plug, and you can optionally enable the `assert_process_allocs` feature to
     /// abort the program when any allocation occurs in the process function while running in debug
-    /// mode.
+    /// mode. The `assert_process_allocs_warn` feature logs those allocations instead. Code that
+    /// knowingly allocates can be wrapped in [`permit_alloc()`][crate::util::permit_alloc()].
     ///
     /// The framework provides convenient iterators on the [`Buffer`] object to process audio either
     /// either per-sample per-channel, or per-block per-channel per-sample. The first approach is
@@ -250,22 +281,43 @@ pub trait Plugin: Default + Send + 'static {
     ///
     /// There is no one-to-one relationship between calls to `initialize()` and `deactivate()`.
     /// `initialize()` may be called more than once before `deactivate()` is called, for instance
-    /// when restoring state while the plugin is still activate.
+    /// when restoring state while the plugin is still activate or when the host changes the audio
+    /// configuration without deactivating the plugin first.
     fn deactivate(&mut self) {}
 }
 
+/// A tail length that can be passed to
+/// [`InitContext::set_tail_samples()`][crate::prelude::InitContext::set_tail_samples()] and
+/// [`ProcessContext::set_tail_samples()`][crate::prelude::ProcessContext::set_tail_samples()] to
+/// indicate that the plugin's tail never ends. This is useful for self-oscillating filters and
+/// delays with feedback.
+pub const INFINITE_TAIL: u32 = u32::MAX;
+
 /// Indicates the current situation after the plugin has processed audio.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProcessStatus {
     /// Something went wrong while processing audio.
     Error(&'static str),
     /// The plugin has finished processing audio. When the input is silent, the host may suspend the
-    /// plugin to save resources as it sees fit.
+    /// plugin to save resources as it sees fit. This corresponds to CLAP's
+    /// `CLAP_PROCESS_CONTINUE_IF_NOT_QUIET`.
     Normal,
-    /// The plugin has a (reverb) tail with a specific length in samples.
+    /// The plugin has a (reverb) tail, and it will only output silence after this many samples
+    /// once the input becomes silent. The host can use this to stop calling
+    /// [`Plugin::process()`] when the tail has ended. Returning `Tail(0)` indicates that the tail
+    /// has ended now and that the host can put the plugin to sleep until it receives new input or
+    /// events.
+    ///
+    /// For CLAP this results in `CLAP_PROCESS_TAIL` with the tail length reported through the
+    /// `tail` extension, or `CLAP_PROCESS_SLEEP` for `Tail(0)`. For VST3 the tail length is
+    /// reported through `IAudioProcessor::getTailSamples()`. If the tail length depends on the
+    /// plugin's parameters, then consider using
+    /// [`ProcessContext::set_tail_samples()`][crate::prelude::ProcessContext::set_tail_samples()]
+    /// instead so the host also knows about the tail length when the plugin is not processing.
     Tail(u32),
     /// This plugin will continue to produce sound regardless of whether or not the input is silent,
     /// and should thus not be deactivated by the host. This is essentially the same as having an
-    /// infinite tail.
+    /// infinite tail, and it corresponds to CLAP's `CLAP_PROCESS_CONTINUE` and VST3's
+    /// `kInfiniteTail`.
     KeepAlive,
 }
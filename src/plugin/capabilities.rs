@@ -0,0 +1,460 @@
+//! A machine-readable summary of a plugin's capabilities for tooling like validators and
+//! documentation generators.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[cfg(feature = "vst3")]
+use crate::prelude::Vst3Plugin;
+use crate::prelude::{
+    AsyncExecutor, AudioIOLayout, ClapPlugin, MidiConfig, ParamFlags, ParamPtr, Params, Plugin,
+    RawMidiConfig,
+};
+use crate::wrapper::util::hash_param_id;
+
+/// The version of the [`PluginCapabilities`] format. This is increased whenever fields are removed
+/// or when their meaning changes, so tools can check whether they understand the summary.
+pub const CAPABILITIES_FORMAT_VERSION: u32 = 2;
+
+/// The names used for [`ParamFlags`] in [`ParamCapabilities::flags`].
+const PARAM_FLAG_NAMES: [(ParamFlags, &str); 6] = [
+    (ParamFlags::BYPASS, "bypass"),
+    (ParamFlags::NON_AUTOMATABLE, "non_automatable"),
+    (ParamFlags::HIDDEN, "hidden"),
+    (ParamFlags::HIDE_IN_GENERIC_UI, "hide_in_generic_ui"),
+    (ParamFlags::GAIN_REDUCTION, "gain_reduction"),
+    (ParamFlags::NON_RANDOMIZABLE, "non_randomizable"),
+];
+
+/// A summary of a plugin's capabilities derived from the [`Plugin`] trait's associated constants
+/// and the plugin's [`Params`] object. This can be serialized to JSON with
+/// [`to_json()`][Self::to_json()] so CI tooling can consume it. The standalone target prints this
+/// summary when it's started with the `--info` option, and `cargo xtask validate` checks the
+/// summary of standalone bundles.
+///
+/// Plugin formats cannot be detected from the `Plugin` trait alone. The standalone target always
+/// lists itself in [`formats`][Self::formats], and the CLAP and VST3 formats are added with
+/// [`with_formats()`][Self::with_formats()] or through the standalone's `StandaloneConfig::formats`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginCapabilities {
+    /// The version of this format, always [`CAPABILITIES_FORMAT_VERSION`] when created by this
+    /// version of NIH-plug.
+    pub format_version: u32,
+
+    /// [`Plugin::NAME`].
+    pub name: String,
+    /// [`Plugin::VENDOR`].
+    pub vendor: String,
+    /// [`Plugin::VERSION`].
+    pub version: String,
+    /// The plugin formats the plugin is exported as.
+    pub formats: Vec<PluginFormat>,
+
+    /// The plugin's supported audio IO layouts, in the same order as
+    /// [`Plugin::AUDIO_IO_LAYOUTS`].
+    pub audio_io_layouts: Vec<AudioIOLayoutCapabilities>,
+    /// [`Plugin::MIDI_INPUT`].
+    pub midi_input: MidiConfig,
+    /// [`Plugin::MIDI_OUTPUT`].
+    pub midi_output: MidiConfig,
+    /// Whether the plugin receives raw MIDI messages through [`Plugin::MIDI_INPUT_RAW`].
+    pub raw_midi_input: bool,
+    /// [`Plugin::SAMPLE_ACCURATE_AUTOMATION`].
+    pub sample_accurate_automation: bool,
+
+    /// The total number of parameters, including parameters hidden from the host.
+    pub param_count: usize,
+    /// The number of parameters the host can automate.
+    pub automatable_param_count: usize,
+    /// Whether the plugin has an editor.
+    pub has_editor: bool,
+    /// The plugin's latency in samples after it has been initialized, if that's known.
+    pub latency_samples: Option<u32>,
+    /// All of the plugin's parameters, in the same order as [`Params::param_map()`].
+    #[serde(default)]
+    pub params: Vec<ParamCapabilities>,
+}
+
+/// A single parameter's metadata. Values are plain, unnormalized values converted to floats, with
+/// boolean parameters using `0.0` and `1.0`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParamCapabilities {
+    /// The parameter's stable ID.
+    pub id: String,
+    /// The hash of the parameter's ID the CLAP and VST3 wrappers use to identify the parameter.
+    pub hash: u32,
+    /// The parameter's display name.
+    pub name: String,
+    /// The path of the parameter's group, or an empty string if the parameter is not part of a
+    /// group.
+    pub group: String,
+    /// The kind of parameter.
+    #[serde(rename = "type")]
+    pub param_type: ParamType,
+    /// The parameter's minimum value.
+    pub min: f32,
+    /// The parameter's maximum value.
+    pub max: f32,
+    /// The parameter's default value.
+    pub default: f32,
+    /// The parameter's default value formatted as a string, including the unit.
+    pub default_display: String,
+    /// The parameter's unit, if it has one.
+    pub unit: String,
+    /// The number of steps for discrete parameters, or `None` for continuous parameters.
+    pub step_count: Option<usize>,
+    /// The names of the parameter's [`ParamFlags`] in snake case, for instance `non_automatable`.
+    pub flags: Vec<String>,
+}
+
+/// The kind of a [`ParamCapabilities`] parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParamType {
+    Float,
+    Int,
+    Bool,
+    Enum,
+}
+
+/// A single audio IO layout. See [`AudioIOLayout`] for the meaning of these fields.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AudioIOLayoutCapabilities {
+    pub main_input_channels: Option<u32>,
+    pub main_output_channels: Option<u32>,
+    pub aux_input_ports: Vec<u32>,
+    pub aux_output_ports: Vec<u32>,
+}
+
+/// A plugin format along with its format-specific metadata.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "format", rename_all = "lowercase")]
+pub enum PluginFormat {
+    /// The plugin is exported as a CLAP plugin.
+    Clap {
+        /// [`ClapPlugin::CLAP_ID`].
+        id: String,
+        /// [`ClapPlugin::CLAP_FEATURES`] as strings.
+        features: Vec<String>,
+    },
+    /// The plugin is exported as a VST3 plugin.
+    Vst3 {
+        /// The plugin's `Vst3Plugin::VST3_CLASS_ID` as a hexadecimal string.
+        class_id: String,
+        /// The VST3 subcategories string as reported to the host.
+        subcategories: String,
+    },
+    /// The plugin is exported as a standalone application.
+    Standalone,
+}
+
+impl PluginCapabilities {
+    /// Create a summary of `plugin`'s capabilities. This calls [`Plugin::params()`] and
+    /// [`Plugin::editor()`], and since the plugin has not been initialized the latency is not
+    /// known. The editor is dropped again right away without ever being opened.
+    pub fn new<P: Plugin>(plugin: &mut P) -> Self {
+        let params = plugin.params();
+        let has_editor = plugin
+            .editor(AsyncExecutor {
+                execute_background: Arc::new(|_| ()),
+                execute_gui: Arc::new(|_| ()),
+            })
+            .is_some();
+
+        Self::from_parts::<P>(params.as_ref(), has_editor, None)
+    }
+
+    /// Create the summary from information the wrappers already have.
+    pub(crate) fn from_parts<P: Plugin>(
+        params: &dyn Params,
+        has_editor: bool,
+        latency_samples: Option<u32>,
+    ) -> Self {
+        let param_map = params.param_map();
+        let automatable_param_count = param_map
+            .iter()
+            .filter(|(_, param_ptr, _)| {
+                !unsafe { param_ptr.flags() }.intersects(
+                    ParamFlags::NON_AUTOMATABLE | ParamFlags::HIDDEN | ParamFlags::GAIN_REDUCTION,
+                )
+            })
+            .count();
+
+        Self {
+            format_version: CAPABILITIES_FORMAT_VERSION,
+
+            name: P::NAME.to_owned(),
+            vendor: P::VENDOR.to_owned(),
+            version: P::VERSION.to_owned(),
+            formats: Vec::new(),
+
+            audio_io_layouts: P::AUDIO_IO_LAYOUTS
+                .iter()
+                .map(AudioIOLayoutCapabilities::from)
+                .collect(),
+            midi_input: P::MIDI_INPUT,
+            midi_output: P::MIDI_OUTPUT,
+            raw_midi_input: P::MIDI_INPUT >= MidiConfig::Basic
+                && P::MIDI_INPUT_RAW != RawMidiConfig::None,
+            sample_accurate_automation: P::SAMPLE_ACCURATE_AUTOMATION,
+
+            param_count: param_map.len(),
+            automatable_param_count,
+            has_editor,
+            latency_samples,
+            params: param_map
+                .iter()
+                .map(|(id, param_ptr, group)| unsafe {
+                    ParamCapabilities::new(id, *param_ptr, group)
+                })
+                .collect(),
+        }
+    }
+
+    /// Add plugin formats to [`formats`][Self::formats]. Use [`PluginFormat::clap()`] and
+    /// [`PluginFormat::vst3()`] to create these.
+    pub fn with_formats(mut self, formats: impl IntoIterator<Item = PluginFormat>) -> Self {
+        self.formats.extend(formats);
+        self
+    }
+
+    /// Serialize the summary to pretty printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Could not serialize the plugin's capabilities")
+    }
+
+    /// Format the summary as human readable text, with the parameters listed in a table.
+    pub fn to_table(&self) -> String {
+        let mut table = format!("{} {} by {}\n", self.name, self.version, self.vendor);
+        for (idx, layout) in self.audio_io_layouts.iter().enumerate() {
+            table.push_str(&format!(
+                "Audio IO layout {}: {} in, {} out, aux inputs {:?}, aux outputs {:?}\n",
+                idx + 1,
+                layout.main_input_channels.unwrap_or(0),
+                layout.main_output_channels.unwrap_or(0),
+                layout.aux_input_ports,
+                layout.aux_output_ports
+            ));
+        }
+        table.push_str(&format!(
+            "MIDI input: {:?}, MIDI output: {:?}, editor: {}, latency: {}\n",
+            self.midi_input,
+            self.midi_output,
+            if self.has_editor { "yes" } else { "no" },
+            match self.latency_samples {
+                Some(latency) => format!("{latency} samples"),
+                None => String::from("unknown"),
+            }
+        ));
+        table.push_str(&format!(
+            "{} parameters, {} automatable\n\n",
+            self.param_count, self.automatable_param_count
+        ));
+
+        let header = ["ID", "Hash", "Name", "Type", "Range", "Default", "Flags"].map(String::from);
+        let rows: Vec<[String; 7]> = self
+            .params
+            .iter()
+            .map(|param| {
+                [
+                    param.id.clone(),
+                    format!("{:08x}", param.hash),
+                    param.name.clone(),
+                    format!("{:?}", param.param_type).to_lowercase(),
+                    format!("{} to {}{}", param.min, param.max, param.unit),
+                    param.default_display.clone(),
+                    param.flags.join(", "),
+                ]
+            })
+            .collect();
+
+        let mut widths = header.clone().map(|column| column.chars().count());
+        for row in &rows {
+            for (width, column) in widths.iter_mut().zip(row) {
+                *width = (*width).max(column.chars().count());
+            }
+        }
+        for row in std::iter::once(&header).chain(&rows) {
+            let line = row
+                .iter()
+                .zip(widths)
+                .map(|(column, width)| format!("{column:width$}"))
+                .collect::<Vec<_>>()
+                .join("  ");
+            table.push_str(line.trim_end());
+            table.push('\n');
+        }
+
+        table
+    }
+}
+
+impl ParamCapabilities {
+    /// Describe the parameter `param_ptr` points to.
+    ///
+    /// # Safety
+    ///
+    /// The object `param_ptr` points to must still be alive.
+    unsafe fn new(id: &str, param_ptr: ParamPtr, group: &str) -> Self {
+        let flags = param_ptr.flags();
+
+        Self {
+            id: id.to_owned(),
+            hash: hash_param_id(id),
+            name: param_ptr.name().to_owned(),
+            group: group.to_owned(),
+            param_type: match param_ptr {
+                ParamPtr::FloatParam(_) => ParamType::Float,
+                ParamPtr::IntParam(_) => ParamType::Int,
+                ParamPtr::BoolParam(_) => ParamType::Bool,
+                ParamPtr::EnumParam(_) => ParamType::Enum,
+            },
+            min: param_ptr.preview_plain(0.0),
+            max: param_ptr.preview_plain(1.0),
+            default: param_ptr.default_plain_value(),
+            default_display: param_ptr
+                .normalized_value_to_string(param_ptr.default_normalized_value(), true),
+            unit: param_ptr.unit().to_owned(),
+            step_count: param_ptr.step_count(),
+            flags: PARAM_FLAG_NAMES
+                .iter()
+                .filter(|(flag, _)| flags.contains(*flag))
+                .map(|(_, name)| String::from(*name))
+                .collect(),
+        }
+    }
+}
+
+impl From<&AudioIOLayout> for AudioIOLayoutCapabilities {
+    fn from(layout: &AudioIOLayout) -> Self {
+        Self {
+            main_input_channels: layout.main_input_channels.map(|channels| channels.get()),
+            main_output_channels: layout.main_output_channels.map(|channels| channels.get()),
+            aux_input_ports: layout
+                .aux_input_ports
+                .iter()
+                .map(|channels| channels.get())
+                .collect(),
+            aux_output_ports: layout
+                .aux_output_ports
+                .iter()
+                .map(|channels| channels.get())
+                .collect(),
+        }
+    }
+}
+
+impl PluginFormat {
+    /// The CLAP format for plugin `P`.
+    pub fn clap<P: ClapPlugin>() -> Self {
+        PluginFormat::Clap {
+            id: P::CLAP_ID.to_owned(),
+            features: P::CLAP_FEATURES
+                .iter()
+                .map(|feature| feature.as_str().to_owned())
+                .collect(),
+        }
+    }
+
+    /// The VST3 format for plugin `P`.
+    #[cfg(feature = "vst3")]
+    pub fn vst3<P: Vst3Plugin>() -> Self {
+        PluginFormat::Vst3 {
+            class_id: P::VST3_CLASS_ID
+                .iter()
+                .map(|byte| format!("{byte:02X}"))
+                .collect(),
+            subcategories: P::VST3_SUBCATEGORIES
+                .iter()
+                .map(|subcategory| subcategory.as_str())
+                .collect::<Vec<_>>()
+                .join("|"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use crate::testing::TestPlugin;
+
+    #[test]
+    fn summary() {
+        let capabilities = PluginCapabilities::new(&mut TestPlugin::default())
+            .with_formats([PluginFormat::clap::<TestPlugin>()]);
+
+        assert_eq!(capabilities.name, "Test Plugin");
+        assert_eq!(
+            capabilities.formats,
+            [PluginFormat::Clap {
+                id: String::from("com.nih-plug.test"),
+                features: vec![String::from("audio-effect")],
+            }]
+        );
+        assert_eq!(
+            capabilities.audio_io_layouts,
+            [
+                AudioIOLayoutCapabilities {
+                    main_input_channels: Some(2),
+                    main_output_channels: Some(2),
+                    aux_input_ports: Vec::new(),
+                    aux_output_ports: Vec::new(),
+                },
+                AudioIOLayoutCapabilities {
+                    main_input_channels: Some(1),
+                    main_output_channels: Some(1),
+                    aux_input_ports: Vec::new(),
+                    aux_output_ports: Vec::new(),
+                },
+                AudioIOLayoutCapabilities {
+                    main_input_channels: Some(2),
+                    main_output_channels: Some(2),
+                    aux_input_ports: vec![2],
+                    aux_output_ports: Vec::new(),
+                },
+            ]
+        );
+        assert_eq!(capabilities.midi_input, MidiConfig::Basic);
+        assert!(!capabilities.raw_midi_input);
+        assert_eq!(capabilities.param_count, 3);
+        assert_eq!(capabilities.automatable_param_count, 2);
+        assert!(capabilities.has_editor);
+        assert_eq!(capabilities.latency_samples, None);
+    }
+
+    #[test]
+    fn params() {
+        let capabilities = PluginCapabilities::new(&mut TestPlugin::default());
+
+        assert_eq!(capabilities.params.len(), 3);
+        let gain = &capabilities.params[0];
+        assert_eq!(gain.id, "gain");
+        assert_eq!(gain.hash, hash_param_id("gain"));
+        assert_eq!(gain.param_type, ParamType::Float);
+        assert_eq!((gain.min, gain.max, gain.default), (0.0, 1.0, 0.5));
+        assert_eq!(gain.step_count, None);
+        assert!(gain.flags.is_empty());
+        assert_eq!(capabilities.params[1].flags, ["gain_reduction"]);
+        assert_eq!(capabilities.params[2].step_count, Some(12));
+
+        let table = capabilities.to_table();
+        assert!(table.starts_with("Test Plugin 0.1.0 by NIH-plug\n"));
+        assert!(table.contains(&format!("{:08x}", hash_param_id("gr"))));
+    }
+
+    #[test]
+    fn json_roundtrip() {
+        let capabilities = PluginCapabilities::new(&mut TestPlugin::default())
+            .with_formats([PluginFormat::clap::<TestPlugin>(), PluginFormat::Standalone]);
+        let json = capabilities.to_json();
+
+        assert!(json.contains("\"name\": \"Test Plugin\""));
+        assert!(json.contains("\"format\": \"clap\""));
+        assert!(json.contains("\"format\": \"standalone\""));
+        assert_eq!(
+            serde_json::from_str::<PluginCapabilities>(&json).unwrap(),
+            capabilities
+        );
+    }
+}
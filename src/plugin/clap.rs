@@ -1,5 +1,5 @@
 use super::Plugin;
-use crate::prelude::{ClapFeature, RemoteControlsContext};
+use crate::prelude::{ClapFeature, ContextMenuContext, RemoteControlsContext};
 
 /// Provides auxiliary metadata needed for a CLAP plugin.
 #[allow(unused_variables)]
@@ -20,11 +20,48 @@ pub trait ClapPlugin: Plugin {
     /// If set, this informs the host about the plugin's capabilities for polyphonic modulation.
     const CLAP_POLY_MODULATION_CONFIG: Option<PolyModulationConfig> = None;
 
+    /// The note dialects the plugin's note input port accepts. This only has an effect if
+    /// [`MIDI_INPUT`][Plugin::MIDI_INPUT] is set to at least
+    /// [`MidiConfig::Basic`][crate::prelude::MidiConfig::Basic]. Regardless of the dialect the
+    /// host uses, the plugin receives the same [`NoteEvent`][crate::prelude::NoteEvent]s.
+    const CLAP_NOTE_INPUT_DIALECTS: ClapNoteDialects = ClapNoteDialects::ClapPreferred;
+    /// The note dialects the plugin's note output port produces. This only has an effect if
+    /// [`MIDI_OUTPUT`][Plugin::MIDI_OUTPUT] is set to at least
+    /// [`MidiConfig::Basic`][crate::prelude::MidiConfig::Basic]. With
+    /// [`ClapNoteDialects::MidiOnly`], note events are sent to the host as MIDI messages, and events
+    /// that don't have a MIDI equivalent are dropped. With [`ClapNoteDialects::ClapOnly`], MIDI CCs,
+    /// pitch bend, and SysEx messages cannot be sent.
+    const CLAP_NOTE_OUTPUT_DIALECTS: ClapNoteDialects = ClapNoteDialects::ClapPreferred;
+
     /// This function can be implemented to define plugin-specific [remote control
     /// pages](https://github.com/free-audio/clap/blob/main/include/clap/ext/draft/remote-controls.h)
     /// that the host can use to provide better hardware mapping for a plugin. See the linked
     /// extension for more information.
     fn remote_controls(&self, context: &mut impl RemoteControlsContext) {}
+
+    /// This function can be implemented to add entries to the host's [context
+    /// menus](https://github.com/free-audio/clap/blob/main/include/clap/ext/context-menu.h) for the
+    /// plugin's parameters or for the plugin as a whole. The entries' actions are run on the main
+    /// thread when the user selects them. Since this is called only once when the plugin gets
+    /// created, any state the actions need should be captured in the closures, for instance by
+    /// cloning an `Arc` to the plugin's parameters. No extra entries are added by default.
+    fn context_menu(&self, context: &mut impl ContextMenuContext) {}
+}
+
+/// The note dialects a CLAP plugin's note port supports. NIH-plug translates both CLAP note events
+/// and MIDI messages to [`NoteEvent`][crate::prelude::NoteEvent]s, so this only changes what's
+/// advertised to the host and how output events are sent. MIDI 2.0 is not supported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClapNoteDialects {
+    /// Support both CLAP note events and MIDI, preferring CLAP note events. This is the default.
+    /// Some hosts, like REAPER, only send SysEx to note ports that support MIDI.
+    ClapPreferred,
+    /// Support both CLAP note events and MIDI, preferring MIDI.
+    MidiPreferred,
+    /// Only support CLAP note events.
+    ClapOnly,
+    /// Only support MIDI.
+    MidiOnly,
 }
 
 /// Configuration for the plugin's polyphonic modulation options, if it supports .
@@ -14,11 +14,35 @@ pub trait Vst3Plugin: Plugin {
     /// truncated.
     const VST3_SUBCATEGORIES: &'static [Vst3SubCategory];
 
+    /// Fixed MIDI CC to parameter assignments that are reported to the host through VST3's
+    /// `IMidiMapping` interface. Hosts like Cubase use these to let the user control the plugin's
+    /// parameters with a MIDI controller. The host then converts those CCs to regular parameter
+    /// changes, so the plugin won't receive them as MIDI events. CCs without an assignment are
+    /// still sent to the plugin as [`NoteEvent::MidiCC`][crate::prelude::NoteEvent::MidiCC] events
+    /// if [`MIDI_INPUT`][Plugin::MIDI_INPUT] is set to
+    /// [`MidiConfig::MidiCCs`][crate::prelude::MidiConfig::MidiCCs]. There are no assignments by
+    /// default.
+    const VST3_MIDI_CC_ASSIGNMENTS: &'static [Vst3MidiCcAssignment] = &[];
+
     /// [`VST3_CLASS_ID`][Self::VST3_CLASS_ID`] in the correct order for the current platform so
     /// projects and presets can be shared between platforms. This should not be overridden.
     const PLATFORM_VST3_CLASS_ID: [u8; 16] = swap_vst3_uid_byte_order(Self::VST3_CLASS_ID);
 }
 
+/// Maps a MIDI CC to one of the plugin's parameters. See
+/// [`Vst3Plugin::VST3_MIDI_CC_ASSIGNMENTS`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Vst3MidiCcAssignment {
+    /// The zero-indexed MIDI channel this assignment applies to, or `None` to apply it to all 16
+    /// channels.
+    pub channel: Option<u8>,
+    /// The MIDI CC number, in `0..128`.
+    pub cc: u8,
+    /// The ID of the parameter the CC controls. This is the same ID used in the `#[id = "..."]`
+    /// attribute on the parameter.
+    pub param_id: &'static str,
+}
+
 #[cfg(not(target_os = "windows"))]
 const fn swap_vst3_uid_byte_order(uid: [u8; 16]) -> [u8; 16] {
     uid
@@ -8,36 +8,48 @@ pub use crate::nih_export_clap;
 #[cfg(feature = "vst3")]
 pub use crate::nih_export_vst3;
 #[cfg(feature = "standalone")]
-pub use crate::wrapper::standalone::{nih_export_standalone, nih_export_standalone_with_args};
+pub use crate::wrapper::standalone::{
+    nih_export_standalone, nih_export_standalone_with_args, nih_export_standalone_with_config,
+    StandaloneConfig,
+};
 
 pub use crate::formatters;
 pub use crate::util;
 
 pub use crate::audio_setup::{
-    new_nonzero_u32, AudioIOLayout, AuxiliaryBuffers, BufferConfig, PortNames, ProcessMode,
+    new_nonzero_u32, AudioIOLayout, AuxiliaryBuffers, BufferConfig, ChannelLayout, PortNames,
+    ProcessMode, Speaker,
 };
 pub use crate::buffer::Buffer;
+pub use crate::context::context_menu::{ContextMenu, ContextMenuContext};
 pub use crate::context::gui::{AsyncExecutor, GuiContext, ParamSetter};
 pub use crate::context::init::InitContext;
-pub use crate::context::process::{ProcessContext, Transport};
+pub use crate::context::param_indication::{
+    AutomationIndication, IndicationColor, ParamIndication,
+};
+pub use crate::context::process::{BarBeatTick, ProcessContext, Transport, TransportJump};
 pub use crate::context::remote_controls::{
     RemoteControlsContext, RemoteControlsPage, RemoteControlsSection,
 };
-pub use crate::context::PluginApi;
+pub use crate::context::{HostInfo, PluginApi};
 // This also includes the derive macro
+pub use crate::editor::param_changes::ParamChanges;
+pub use crate::editor::session_state::SessionState;
 pub use crate::editor::{Editor, ParentWindowHandle};
-pub use crate::midi::sysex::SysExMessage;
-pub use crate::midi::{control_change, MidiConfig, NoteEvent, PluginNoteEvent};
+pub use crate::midi::sysex::{RawSysEx, SysExMessage};
+pub use crate::midi::{control_change, MidiConfig, NoteEvent, PluginNoteEvent, RawMidiConfig};
 pub use crate::params::enums::{Enum, EnumParam};
 pub use crate::params::internals::ParamPtr;
 pub use crate::params::range::{FloatRange, IntRange};
 pub use crate::params::smoothing::{AtomicF32, Smoothable, Smoother, SmoothingStyle};
 pub use crate::params::Params;
-pub use crate::params::{BoolParam, FloatParam, IntParam, Param, ParamFlags};
-pub use crate::plugin::clap::{ClapPlugin, PolyModulationConfig};
+pub use crate::params::{
+    BoolParam, ChangedParams, FloatParam, IntParam, Param, ParamFlags, ParamVisibility,
+};
+pub use crate::plugin::clap::{ClapNoteDialects, ClapPlugin, PolyModulationConfig};
 #[cfg(feature = "vst3")]
-pub use crate::plugin::vst3::Vst3Plugin;
-pub use crate::plugin::{Plugin, ProcessStatus, TaskExecutor};
+pub use crate::plugin::vst3::{Vst3MidiCcAssignment, Vst3Plugin};
+pub use crate::plugin::{Plugin, ProcessStatus, TaskExecutor, INFINITE_TAIL};
 pub use crate::wrapper::clap::features::ClapFeature;
 pub use crate::wrapper::state::PluginState;
 #[cfg(feature = "vst3")]
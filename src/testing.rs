@@ -0,0 +1,400 @@
+//! Test fixtures shared between the unit tests for the wrappers, the plugin state, the plugin
+//! capabilities, and the GUI utilities.
+
+use parking_lot::Mutex;
+use std::any::Any;
+use std::num::NonZeroU32;
+use std::sync::Arc;
+
+use crate::prelude::*;
+use crate::wrapper::state::{self, PluginState};
+
+/// The parameters for [`TestPlugin`].
+pub(crate) struct TestParams {
+    pub gain: FloatParam,
+    pub gain_reduction: FloatParam,
+    /// A continuous parameter that reports 12 steps to the host.
+    pub pitch: FloatParam,
+}
+
+unsafe impl Params for TestParams {
+    fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+        vec![
+            (String::from("gain"), self.gain.as_ptr(), String::new()),
+            (
+                String::from("gr"),
+                self.gain_reduction.as_ptr(),
+                String::new(),
+            ),
+            (String::from("pitch"), self.pitch.as_ptr(), String::new()),
+        ]
+    }
+}
+
+impl Default for TestParams {
+    fn default() -> Self {
+        Self {
+            gain: FloatParam::new("Gain", 0.5, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_smoother(SmoothingStyle::Linear(100.0)),
+            gain_reduction: FloatParam::new(
+                "Gain Reduction",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_flags(ParamFlags::GAIN_REDUCTION),
+            pitch: FloatParam::new(
+                "Pitch",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 12.0,
+                },
+            )
+            .with_step_count(12),
+        }
+    }
+}
+
+/// A plugin that doesn't do anything, but that records the calls the wrappers make to it.
+#[derive(Default)]
+pub(crate) struct TestPlugin {
+    pub params: Arc<TestParams>,
+
+    /// The main output channel count and the buffer config from every `initialize()` call.
+    pub initialize_calls: Vec<(Option<NonZeroU32>, BufferConfig)>,
+    /// The channel and sample counts from every `process()` call.
+    pub process_calls: Vec<(usize, usize)>,
+    /// The transport information from every `process()` call.
+    pub process_transports: Vec<Transport>,
+    /// The process mode from every `process()` call.
+    pub process_modes: Vec<ProcessMode>,
+    /// Tracks parameter changes between `process()` calls. Created in `initialize()`.
+    pub changed_params: Option<ChangedParams>,
+    /// The IDs of the parameters that changed before every `process()` call.
+    pub process_changed_params: Vec<Vec<String>>,
+    /// The calls made to the plugin's [`TestEditor`].
+    pub editor_calls: Arc<Mutex<Vec<EditorCall>>>,
+    /// If set, `process()` reports a tail length of `gain * 1000` samples, like a reverb whose
+    /// decay time depends on a parameter.
+    pub report_tail: bool,
+}
+
+impl Plugin for TestPlugin {
+    const NAME: &'static str = "Test Plugin";
+    const VENDOR: &'static str = "NIH-plug";
+    const URL: &'static str = "";
+    const EMAIL: &'static str = "";
+    const VERSION: &'static str = "0.1.0";
+
+    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(2),
+            main_output_channels: NonZeroU32::new(2),
+            ..AudioIOLayout::const_default()
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(1),
+            main_output_channels: NonZeroU32::new(1),
+            ..AudioIOLayout::const_default()
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(2),
+            main_output_channels: NonZeroU32::new(2),
+            aux_input_ports: &[new_nonzero_u32(2)],
+            ..AudioIOLayout::const_default()
+        },
+    ];
+    const MIDI_INPUT: MidiConfig = MidiConfig::Basic;
+
+    type SysExMessage = ();
+    type BackgroundTask = ();
+
+    fn params(&self) -> Arc<dyn Params> {
+        self.params.clone()
+    }
+
+    fn editor(&mut self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
+        Some(Box::new(TestEditor {
+            calls: self.editor_calls.clone(),
+        }))
+    }
+
+    fn initialize(
+        &mut self,
+        audio_io_layout: &AudioIOLayout,
+        buffer_config: &BufferConfig,
+        _context: &mut impl InitContext<Self>,
+    ) -> bool {
+        self.initialize_calls
+            .push((audio_io_layout.main_output_channels, *buffer_config));
+        self.changed_params = Some(ChangedParams::new(self.params.clone()));
+        true
+    }
+
+    fn process(
+        &mut self,
+        buffer: &mut Buffer,
+        _aux: &mut AuxiliaryBuffers,
+        context: &mut impl ProcessContext<Self>,
+    ) -> ProcessStatus {
+        self.process_calls
+            .push((buffer.channels(), buffer.samples()));
+        self.process_transports.push(context.transport().clone());
+        self.process_modes.push(context.process_mode());
+        if let Some(changed_params) = &mut self.changed_params {
+            changed_params.update();
+            self.process_changed_params
+                .push(changed_params.changed_ids().map(String::from).collect());
+        }
+        if self.report_tail {
+            context.set_tail_samples((self.params.gain.value() * 1000.0) as u32);
+        }
+        ProcessStatus::Normal
+    }
+}
+
+impl ClapPlugin for TestPlugin {
+    const CLAP_ID: &'static str = "com.nih-plug.test";
+    const CLAP_DESCRIPTION: Option<&'static str> = None;
+    const CLAP_MANUAL_URL: Option<&'static str> = None;
+    const CLAP_SUPPORT_URL: Option<&'static str> = None;
+    const CLAP_FEATURES: &'static [ClapFeature] = &[ClapFeature::AudioEffect];
+}
+
+#[cfg(feature = "vst3")]
+impl Vst3Plugin for TestPlugin {
+    const VST3_CLASS_ID: [u8; 16] = *b"NihPlugVst3Tests";
+    const VST3_SUBCATEGORIES: &'static [Vst3SubCategory] = &[Vst3SubCategory::Fx];
+    const VST3_MIDI_CC_ASSIGNMENTS: &'static [Vst3MidiCcAssignment] = &[
+        Vst3MidiCcAssignment {
+            channel: None,
+            cc: 7,
+            param_id: "gain",
+        },
+        Vst3MidiCcAssignment {
+            channel: Some(1),
+            cc: 8,
+            param_id: "gain",
+        },
+    ];
+}
+
+/// A call made to a [`TestEditor`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum EditorCall {
+    Spawn,
+    BufferConfigChanged(BufferConfig),
+}
+
+/// An editor that doesn't open any windows, but that records when it's opened and when the buffer
+/// config changes. Parameter change notifications are ignored.
+pub(crate) struct TestEditor {
+    calls: Arc<Mutex<Vec<EditorCall>>>,
+}
+
+impl Editor for TestEditor {
+    fn spawn(
+        &self,
+        _parent: ParentWindowHandle,
+        _context: Arc<dyn GuiContext>,
+    ) -> Box<dyn Any + Send> {
+        self.calls.lock().push(EditorCall::Spawn);
+        Box::new(())
+    }
+
+    fn size(&self) -> (u32, u32) {
+        (200, 100)
+    }
+
+    fn set_scale_factor(&self, _factor: f32) -> bool {
+        false
+    }
+
+    fn param_value_changed(&self, _id: &str, _normalized_value: f32) {}
+
+    fn param_modulation_changed(&self, _id: &str, _modulation_offset: f32) {}
+
+    fn param_values_changed(&self) {}
+
+    fn buffer_config_changed(&self, buffer_config: &BufferConfig) {
+        self.calls
+            .lock()
+            .push(EditorCall::BufferConfigChanged(*buffer_config));
+    }
+}
+
+/// A bank of 50 identical parameters, named `param_0` through `param_49`.
+pub(crate) struct ManyParams {
+    pub params: Vec<FloatParam>,
+}
+
+unsafe impl Params for ManyParams {
+    fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+        self.params
+            .iter()
+            .enumerate()
+            .map(|(i, param)| (format!("param_{i}"), param.as_ptr(), String::new()))
+            .collect()
+    }
+}
+
+impl Default for ManyParams {
+    fn default() -> Self {
+        Self {
+            params: (0..50)
+                .map(|i| {
+                    FloatParam::new(
+                        format!("Param {i}"),
+                        0.0,
+                        FloatRange::Linear { min: 0.0, max: 1.0 },
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A plugin with [`ManyParams`] that doesn't do anything, for testing how a large number of
+/// parameters is communicated to the host.
+#[derive(Default)]
+pub(crate) struct ManyParamsPlugin {
+    pub params: Arc<ManyParams>,
+}
+
+impl Plugin for ManyParamsPlugin {
+    const NAME: &'static str = "Many Params Test Plugin";
+    const VENDOR: &'static str = "NIH-plug";
+    const URL: &'static str = "";
+    const EMAIL: &'static str = "";
+    const VERSION: &'static str = "0.1.0";
+
+    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[AudioIOLayout {
+        main_input_channels: NonZeroU32::new(2),
+        main_output_channels: NonZeroU32::new(2),
+        ..AudioIOLayout::const_default()
+    }];
+
+    type SysExMessage = ();
+    type BackgroundTask = ();
+
+    fn params(&self) -> Arc<dyn Params> {
+        self.params.clone()
+    }
+
+    fn process(
+        &mut self,
+        _buffer: &mut Buffer,
+        _aux: &mut AuxiliaryBuffers,
+        _context: &mut impl ProcessContext<Self>,
+    ) -> ProcessStatus {
+        ProcessStatus::Normal
+    }
+}
+
+impl ClapPlugin for ManyParamsPlugin {
+    const CLAP_ID: &'static str = "com.nih-plug.test-many-params";
+    const CLAP_DESCRIPTION: Option<&'static str> = None;
+    const CLAP_MANUAL_URL: Option<&'static str> = None;
+    const CLAP_SUPPORT_URL: Option<&'static str> = None;
+    const CLAP_FEATURES: &'static [ClapFeature] = &[ClapFeature::AudioEffect];
+}
+
+/// A call made to a [`TestGuiContext`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum GuiContextCall {
+    BeginSetParameter(ParamPtr),
+    SetParameterNormalized(ParamPtr, f32),
+    EndSetParameter(ParamPtr),
+    GetState,
+    SetState,
+}
+
+/// A [`GuiContext`] that records the calls made to it. Like a wrapper that's not processing audio,
+/// parameter changes and states are applied to the parameters immediately. The batch parameter
+/// setting function uses the trait's default implementation.
+pub(crate) struct TestGuiContext {
+    params: Arc<dyn Params>,
+    calls: Mutex<Vec<GuiContextCall>>,
+}
+
+impl TestGuiContext {
+    pub fn new(params: Arc<dyn Params>) -> Self {
+        Self {
+            params,
+            calls: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The calls made to this context so far.
+    pub fn calls(&self) -> Vec<GuiContextCall> {
+        self.calls.lock().clone()
+    }
+
+    /// Forget about the calls made to this context so far.
+    pub fn clear_calls(&self) {
+        self.calls.lock().clear();
+    }
+}
+
+impl GuiContext for TestGuiContext {
+    fn plugin_api(&self) -> PluginApi {
+        PluginApi::Standalone
+    }
+
+    fn request_resize(&self) -> bool {
+        false
+    }
+
+    unsafe fn raw_begin_set_parameter(&self, param: ParamPtr) {
+        self.calls
+            .lock()
+            .push(GuiContextCall::BeginSetParameter(param));
+    }
+
+    unsafe fn raw_set_parameter_normalized(&self, param: ParamPtr, normalized: f32) {
+        self.calls
+            .lock()
+            .push(GuiContextCall::SetParameterNormalized(param, normalized));
+        param.set_normalized_value(normalized);
+    }
+
+    unsafe fn raw_end_set_parameter(&self, param: ParamPtr) {
+        self.calls
+            .lock()
+            .push(GuiContextCall::EndSetParameter(param));
+    }
+
+    fn get_state(&self) -> PluginState {
+        self.calls.lock().push(GuiContextCall::GetState);
+
+        let param_map = self.params.param_map();
+        unsafe {
+            state::serialize_object::<TestPlugin>(
+                self.params.clone(),
+                param_map
+                    .iter()
+                    .map(|(param_id, param_ptr, _)| (param_id, *param_ptr)),
+            )
+        }
+    }
+
+    fn set_state(&self, mut state: PluginState) {
+        self.calls.lock().push(GuiContextCall::SetState);
+
+        let param_map = self.params.param_map();
+        let success = unsafe {
+            state::deserialize_object::<TestPlugin>(
+                &mut state,
+                self.params.clone(),
+                |param_id| {
+                    param_map
+                        .iter()
+                        .find(|(id, _, _)| id == param_id)
+                        .map(|(_, param_ptr, _)| *param_ptr)
+                },
+                None,
+            )
+        };
+        assert!(success);
+    }
+}
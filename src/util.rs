@@ -1,9 +1,59 @@
 //! General conversion functions and utilities.
 
-mod stft;
+mod ab_comparison;
+mod adsr;
+mod delay_line;
+pub mod filter;
+mod gain_stage;
+mod lookahead;
+mod lookup_table;
+mod loudness;
+mod mod_matrix;
+mod mono_voice;
+#[cfg(feature = "mts_esp")]
+pub mod mts_esp;
+mod noise;
+mod oscillator;
+mod oversampling;
+mod pan;
+mod preset_morph;
+mod process_load;
+mod rng;
+mod setting_crossfade;
+pub mod shaper;
+pub mod stft;
+mod tempo_sync;
+mod true_peak;
+pub mod tuning;
+mod unison;
+mod voice_allocator;
 pub mod window;
 
+pub use ab_comparison::{AbComparison, AbSlot, AbSlots};
+pub use adsr::{Adsr, AdsrCurve, AdsrRetrigger, AdsrStage};
+pub use delay_line::{DelayInterpolation, DelayLine};
+pub use filter::DcBlocker;
+pub use gain_stage::{GainStage, GainStageSmoothers};
+pub use lookahead::LookaheadLimiter;
+pub use lookup_table::{LookupTable, TableInterpolation};
+pub use loudness::{LoudnessMeter, LoudnessReadings};
+pub use mod_matrix::{ModConnection, ModMatrix};
+pub use mono_voice::{GlideMode, MonoEvent, MonoVoice};
+pub use noise::{BrownNoise, PinkNoise};
+pub use oscillator::{Oscillator, Waveform};
+pub use oversampling::{Oversampler, OversamplingFilter};
+pub use pan::{
+    equal_power_crossfade, equal_power_pan, linear_crossfade, linear_pan, EqualPowerTable,
+};
+pub use preset_morph::PresetMorph;
+pub use process_load::ProcessLoad;
+pub use rng::Rng;
+pub use setting_crossfade::{SettingCrossfade, DEFAULT_SETTING_CROSSFADE_MS};
 pub use stft::StftHelper;
+pub use tempo_sync::{NoteDivision, TempoSync, DEFAULT_TEMPO};
+pub use true_peak::TruePeakMeter;
+pub use unison::{unison_spread, Unison, MAX_UNISON_VOICES};
+pub use voice_allocator::{AllocatedVoice, VoiceAllocator, VoiceAssignment};
 
 pub const MINUS_INFINITY_DB: f32 = -100.0;
 pub const MINUS_INFINITY_GAIN: f32 = 1e-5; // 10f32.powf(MINUS_INFINITY_DB / 20)
@@ -12,14 +62,26 @@ pub const NOTES: [&str; 12] = [
 ];
 
 /// Temporarily allow allocations within `func` if NIH-plug was configured with the
-/// `assert_process_allocs` feature.
+/// `assert_process_allocs` feature. With that feature enabled, allocating in
+/// [`Plugin::process()`][crate::prelude::Plugin::process()],
+/// [`Plugin::reset()`][crate::prelude::Plugin::reset()], or in a task passed to
+/// [`ProcessContext::execute_parallel()`][crate::prelude::ProcessContext::execute_parallel()]
+/// terminates the plugin in debug builds. This can be used to whitelist code that knowingly
+/// allocates there, like the rare reset that only happens after the plugin has been reinitialized.
+/// Both the check and this function compile to nothing in release builds.
+///
+/// ```
+/// # use nih_plug::util::permit_alloc;
+/// let buffer: Vec<f32> = permit_alloc(|| vec![0.0; 512]);
+/// ```
 #[cfg(all(debug_assertions, feature = "assert_process_allocs"))]
 pub fn permit_alloc<T, F: FnOnce() -> T>(func: F) -> T {
     assert_no_alloc::permit_alloc(func)
 }
 
 /// Temporarily allow allocations within `func` if NIH-plug was configured with the
-/// `assert_process_allocs` feature.
+/// `assert_process_allocs` feature. See the other definition of this function for more
+/// information.
 #[cfg(not(all(debug_assertions, feature = "assert_process_allocs")))]
 pub fn permit_alloc<T, F: FnOnce() -> T>(func: F) -> T {
     func()
@@ -102,6 +164,61 @@ pub fn freq_to_midi_note(freq: f32) -> f32 {
     ((freq / 440.0).log2() * 12.0) + 69.0
 }
 
+/// Snap a value to the closest detent if it's within `snap_distance` of one, and return the value
+/// unchanged otherwise. A value exactly halfway between two detents that are both in range snaps to
+/// the lower detent. The detents don't need to be sorted. This is used by the GUI integrations'
+/// sliders and 2D widgets, where the values and distances are usually normalized `[0, 1]` values.
+pub fn snap_to_detent(value: f32, detents: &[f32], snap_distance: f32) -> f32 {
+    let mut closest: Option<(f32, f32)> = None;
+    for &detent in detents {
+        let distance = (value - detent).abs();
+        let is_closer = match closest {
+            Some((closest_detent, closest_distance)) => {
+                distance < closest_distance
+                    || (distance == closest_distance && detent < closest_detent)
+            }
+            None => true,
+        };
+        if distance <= snap_distance && is_closer {
+            closest = Some((detent, distance));
+        }
+    }
+
+    closest.map_or(value, |(detent, _)| detent)
+}
+
+/// Convert a stereo signal to mid/side in place. The left channel is replaced with the mid signal
+/// `(L + R) / 2`, and the right channel is replaced with the side signal `(L - R) / 2`. With this
+/// scaling a signal that's identical on both channels keeps its level in the mid channel, and
+/// [`mid_side_to_stereo()`] converts the signal back without any gain changes. Both slices must
+/// have the same length.
+#[inline]
+pub fn stereo_to_mid_side(left: &mut [f32], right: &mut [f32]) {
+    nih_debug_assert_eq!(left.len(), right.len());
+
+    for (left, right) in left.iter_mut().zip(right.iter_mut()) {
+        let mid = (*left + *right) * 0.5;
+        let side = (*left - *right) * 0.5;
+        *left = mid;
+        *right = side;
+    }
+}
+
+/// The inverse of [`stereo_to_mid_side()`]. The mid channel is replaced with the left signal
+/// `M + S`, and the side channel is replaced with the right signal `M - S`. Both slices must have
+/// the same length.
+#[inline]
+pub fn mid_side_to_stereo(mid: &mut [f32], side: &mut [f32]) {
+    nih_debug_assert_eq!(mid.len(), side.len());
+
+    for (mid, side) in mid.iter_mut().zip(side.iter_mut()) {
+        let left = *mid + *side;
+        let right = *mid - *side;
+        *mid = left;
+        *side = right;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     mod db_gain_conversion {
@@ -193,4 +310,52 @@ mod tests {
             approx::assert_relative_eq!(gain_to_db(-2.0), gain_to_db_fast(-2.0), epsilon = 1e-7);
         }
     }
+
+    mod detents {
+        use super::super::*;
+
+        #[test]
+        fn test_snap_within_distance() {
+            let detents = [0.0, 0.5, 1.0];
+            assert_eq!(snap_to_detent(0.47, &detents, 0.05), 0.5);
+            assert_eq!(snap_to_detent(0.4, &detents, 0.05), 0.4);
+            assert_eq!(snap_to_detent(0.97, &[], 0.05), 0.97);
+        }
+
+        #[test]
+        fn test_snap_ties_to_lower_detent() {
+            assert_eq!(snap_to_detent(0.5, &[0.75, 0.25], 0.25), 0.25);
+        }
+    }
+
+    mod mid_side_conversion {
+        use super::super::*;
+
+        #[test]
+        fn test_known_values() {
+            let mut left = [1.0, 1.0, 1.0, 0.0];
+            let mut right = [1.0, -1.0, 0.0, 0.5];
+            stereo_to_mid_side(&mut left, &mut right);
+
+            assert_eq!(left, [1.0, 0.0, 0.5, 0.25]);
+            assert_eq!(right, [0.0, 1.0, 0.5, -0.25]);
+        }
+
+        #[test]
+        fn test_round_trip() {
+            let original_left = [0.3, -0.7, 0.125, 1.0, 0.0];
+            let original_right = [-0.2, -0.7, 0.9, 0.0, 0.4];
+            let mut left = original_left;
+            let mut right = original_right;
+            stereo_to_mid_side(&mut left, &mut right);
+            mid_side_to_stereo(&mut left, &mut right);
+
+            for (converted, original) in left.iter().zip(original_left) {
+                approx::assert_relative_eq!(*converted, original, epsilon = 1e-7);
+            }
+            for (converted, original) in right.iter().zip(original_right) {
+                approx::assert_relative_eq!(*converted, original, epsilon = 1e-7);
+            }
+        }
+    }
 }
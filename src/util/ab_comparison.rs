@@ -0,0 +1,420 @@
+//! A/B comparisons between two plugin states.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::params::persist::PersistentField;
+use crate::prelude::{ParamSetter, PluginState};
+
+/// One of the two slots in an [`AbComparison`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AbSlot {
+    #[default]
+    A,
+    B,
+}
+
+/// The stored contents of an [`AbComparison`]. This is what gets written to the plugin's state when
+/// the comparison is stored in a `#[persist = "key"]` field.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AbSlots {
+    /// The slot the plugin's current state belongs to.
+    pub active: AbSlot,
+    /// The plugin state stored in the A slot, or `None` if nothing has been stored in this slot
+    /// yet.
+    pub a: Option<PluginState>,
+    /// The plugin state stored in the B slot.
+    pub b: Option<PluginState>,
+}
+
+/// Lets the user compare two versions of the plugin's state by switching between an A and a B slot
+/// from the plugin's GUI. The plugin's current state always belongs to the
+/// [active slot][Self::active()]. Switching to the other slot stores the current state in the
+/// active slot before restoring the other slot's state.
+///
+/// The slots contain the plugin's entire state as returned by [`GuiContext::get_state()`],
+/// including persistent fields like the editor's state. The comparison's own persistent field is
+/// left out so the slots don't end up containing each other. States are restored using
+/// [`GuiContext::set_state()`], so the smoothers snap to the new values instead of gliding towards
+/// them, and the host is informed about all of the new parameter values at once. Like any other
+/// state, the parameter values are stored as plain values indexed by parameter ID, so they survive
+/// changes to a parameter's range.
+///
+/// Both slots can be saved together with the plugin's state by storing this in a
+/// `#[persist = "ab-comparison"]` field on the plugin's `Params` struct. If the field uses another
+/// key, then the comparison needs to be created using
+/// [`with_persist_key()`][Self::with_persist_key()] instead so it can leave that field out of the
+/// slots. All functions must be called from the GUI thread.
+///
+/// [`GuiContext::get_state()`]: crate::prelude::GuiContext::get_state()
+/// [`GuiContext::set_state()`]: crate::prelude::GuiContext::set_state()
+#[derive(Debug)]
+pub struct AbComparison {
+    slots: Mutex<AbSlots>,
+    /// The key of the persistent field this comparison is stored in.
+    persist_key: String,
+}
+
+impl Default for AbComparison {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AbComparison {
+    /// The persistent field key used by [`new()`][Self::new()].
+    pub const DEFAULT_PERSIST_KEY: &'static str = "ab-comparison";
+
+    /// Create a comparison that is stored in a `#[persist = "ab-comparison"]` field.
+    pub fn new() -> Self {
+        Self::with_persist_key(Self::DEFAULT_PERSIST_KEY)
+    }
+
+    /// Create a comparison that is stored in a persistent field with another key. This is the key
+    /// as it appears in the plugin's state, so it includes the prefix added by a `#[nested]` field
+    /// with an `id_prefix`.
+    pub fn with_persist_key(persist_key: impl Into<String>) -> Self {
+        Self {
+            slots: Mutex::new(AbSlots::default()),
+            persist_key: persist_key.into(),
+        }
+    }
+
+    /// The slot the plugin's current state belongs to.
+    pub fn active(&self) -> AbSlot {
+        self.slots.lock().active
+    }
+
+    /// Whether a slot contains a state. The active slot always counts as being filled since it
+    /// contains the plugin's current state.
+    pub fn is_filled(&self, slot: AbSlot) -> bool {
+        let slots = self.slots.lock();
+        slots.active == slot || slot_state(&slots, slot).is_some()
+    }
+
+    /// Store the plugin's current state in a slot. If this is the inactive slot, then the state will
+    /// be restored when switching to it.
+    pub fn store(&self, slot: AbSlot, setter: &ParamSetter) {
+        let state = self.snapshot(setter);
+        *slot_state_mut(&mut self.slots.lock(), slot) = Some(state);
+    }
+
+    /// Switch to another slot. The plugin's current state is stored in the active slot, and the
+    /// other slot's state is restored. If the other slot doesn't contain a state yet, then it starts
+    /// out as a copy of the current slot. Does nothing if `slot` is already active.
+    pub fn switch_to(&self, slot: AbSlot, setter: &ParamSetter) {
+        if self.active() == slot {
+            return;
+        }
+
+        // The lock can't be held while getting or setting the state since that also reads or
+        // writes this object's persistent field
+        let state = self.snapshot(setter);
+        let target = {
+            let mut slots = self.slots.lock();
+            let active = slots.active;
+            *slot_state_mut(&mut slots, active) = Some(state);
+            slots.active = slot;
+
+            slot_state(&slots, slot).cloned()
+        };
+        if let Some(target) = target {
+            setter.raw_context.set_state(target);
+        }
+    }
+
+    /// Switch to whichever slot is currently inactive. See [`switch_to()`][Self::switch_to()].
+    pub fn toggle(&self, setter: &ParamSetter) {
+        let other = match self.active() {
+            AbSlot::A => AbSlot::B,
+            AbSlot::B => AbSlot::A,
+        };
+
+        self.switch_to(other, setter);
+    }
+
+    /// Copy the state from one slot to another. If `to` is the active slot, then the copied state is
+    /// restored. Does nothing if `from` has never been filled.
+    pub fn copy(&self, from: AbSlot, to: AbSlot, setter: &ParamSetter) {
+        if from == to {
+            return;
+        }
+
+        let active = self.active();
+        let state = if active == from {
+            Some(self.snapshot(setter))
+        } else {
+            slot_state(&self.slots.lock(), from).cloned()
+        };
+        let Some(state) = state else {
+            return;
+        };
+
+        *slot_state_mut(&mut self.slots.lock(), to) = Some(state.clone());
+        if active == to {
+            setter.raw_context.set_state(state);
+        }
+    }
+
+    /// Copy the A slot's state to the B slot. See [`copy()`][Self::copy()].
+    pub fn copy_a_to_b(&self, setter: &ParamSetter) {
+        self.copy(AbSlot::A, AbSlot::B, setter);
+    }
+
+    /// Copy the B slot's state to the A slot. See [`copy()`][Self::copy()].
+    pub fn copy_b_to_a(&self, setter: &ParamSetter) {
+        self.copy(AbSlot::B, AbSlot::A, setter);
+    }
+
+    /// Get the plugin's current state without this object's own persistent field. Otherwise every
+    /// stored state would contain all previously stored states.
+    fn snapshot(&self, setter: &ParamSetter) -> PluginState {
+        let mut state = setter.raw_context.get_state();
+        state.fields.remove(&self.persist_key);
+
+        state
+    }
+}
+
+impl PersistentField<'_, AbSlots> for AbComparison {
+    fn set(&self, new_value: AbSlots) {
+        *self.slots.lock() = new_value;
+    }
+
+    fn map<F, R>(&self, f: F) -> R
+    where
+        F: Fn(&AbSlots) -> R,
+    {
+        f(&self.slots.lock())
+    }
+}
+
+fn slot_state(slots: &AbSlots, slot: AbSlot) -> Option<&PluginState> {
+    match slot {
+        AbSlot::A => slots.a.as_ref(),
+        AbSlot::B => slots.b.as_ref(),
+    }
+}
+
+fn slot_state_mut(slots: &mut AbSlots, slot: AbSlot) -> &mut Option<PluginState> {
+    match slot {
+        AbSlot::A => &mut slots.a,
+        AbSlot::B => &mut slots.b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::params::persist;
+    use crate::prelude::{
+        FloatParam, FloatRange, GuiContext, IntParam, IntRange, Param, ParamPtr, Params,
+    };
+    use crate::testing::{GuiContextCall, TestGuiContext};
+
+    struct TestParams {
+        cutoff: FloatParam,
+        voices: IntParam,
+        /// Some persistent editor state that should be compared along with the parameters.
+        editor_note: Mutex<String>,
+        comparison: AbComparison,
+        /// A second comparison stored under another key. This has the same contents as
+        /// `comparison` until one of them is used.
+        other_comparison: AbComparison,
+    }
+
+    unsafe impl Params for TestParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            vec![
+                (String::from("cutoff"), self.cutoff.as_ptr(), String::new()),
+                (String::from("voices"), self.voices.as_ptr(), String::new()),
+            ]
+        }
+
+        fn serialize_fields(&self) -> BTreeMap<String, String> {
+            BTreeMap::from([
+                (
+                    String::from("editor-note"),
+                    self.editor_note.map(persist::serialize_field).unwrap(),
+                ),
+                (
+                    String::from("ab-comparison"),
+                    self.comparison.map(persist::serialize_field).unwrap(),
+                ),
+                (
+                    String::from("other-ab-comparison"),
+                    self.other_comparison.map(persist::serialize_field).unwrap(),
+                ),
+            ])
+        }
+
+        fn deserialize_fields(&self, serialized: &BTreeMap<String, String>) {
+            if let Some(data) = serialized.get("editor-note") {
+                self.editor_note
+                    .set(persist::deserialize_field(data).unwrap());
+            }
+            if let Some(data) = serialized.get("ab-comparison") {
+                self.comparison
+                    .set(persist::deserialize_field(data).unwrap());
+            }
+            if let Some(data) = serialized.get("other-ab-comparison") {
+                self.other_comparison
+                    .set(persist::deserialize_field(data).unwrap());
+            }
+        }
+    }
+
+    impl TestParams {
+        fn new() -> Self {
+            Self {
+                cutoff: FloatParam::new(
+                    "Cutoff",
+                    100.0,
+                    FloatRange::Linear {
+                        min: 0.0,
+                        max: 1000.0,
+                    },
+                ),
+                voices: IntParam::new("Voices", 4, IntRange::Linear { min: 1, max: 16 }),
+                editor_note: Mutex::new(String::new()),
+                comparison: AbComparison::new(),
+                other_comparison: AbComparison::with_persist_key("other-ab-comparison"),
+            }
+        }
+
+        fn set(&self, cutoff: f32, voices: i32, note: &str, setter: &ParamSetter) {
+            setter.set_parameters_normalized(
+                &[
+                    (self.cutoff.as_ptr(), self.cutoff.preview_normalized(cutoff)),
+                    (self.voices.as_ptr(), self.voices.preview_normalized(voices)),
+                ],
+                false,
+            );
+            *self.editor_note.lock() = String::from(note);
+        }
+
+        fn values(&self) -> (f32, i32, String) {
+            (
+                self.cutoff.value(),
+                self.voices.value(),
+                self.editor_note.lock().clone(),
+            )
+        }
+    }
+
+    #[test]
+    fn switch_between_slots() {
+        let params = Arc::new(TestParams::new());
+        let context = TestGuiContext::new(params.clone());
+        let setter = ParamSetter::new(&context);
+        let comparison = &params.comparison;
+        assert_eq!(comparison.active(), AbSlot::A);
+        assert!(!comparison.is_filled(AbSlot::B));
+
+        // B starts out as a copy of A
+        params.set(500.0, 2, "a", &setter);
+        context.clear_calls();
+        comparison.switch_to(AbSlot::B, &setter);
+        assert_eq!(comparison.active(), AbSlot::B);
+        assert_eq!(params.values(), (500.0, 2, String::from("a")));
+        assert_eq!(context.calls(), [GuiContextCall::GetState]);
+
+        // Switching restores the entire state, including persistent fields
+        params.set(750.0, 12, "b", &setter);
+        context.clear_calls();
+        comparison.toggle(&setter);
+        assert_eq!(comparison.active(), AbSlot::A);
+        assert_eq!(params.values(), (500.0, 2, String::from("a")));
+        assert_eq!(
+            context.calls(),
+            [GuiContextCall::GetState, GuiContextCall::SetState]
+        );
+
+        comparison.toggle(&setter);
+        assert_eq!(comparison.active(), AbSlot::B);
+        assert_eq!(params.values(), (750.0, 12, String::from("b")));
+    }
+
+    #[test]
+    fn slots_do_not_contain_themselves() {
+        let params = Arc::new(TestParams::new());
+        let context = TestGuiContext::new(params.clone());
+        let setter = ParamSetter::new(&context);
+        let comparison = &params.comparison;
+
+        params.set(500.0, 2, "a", &setter);
+        comparison.switch_to(AbSlot::B, &setter);
+        comparison.toggle(&setter);
+        comparison.toggle(&setter);
+
+        // The other comparison's field is kept even though it had the exact same contents
+        let slots = comparison.map(Clone::clone);
+        for state in [slots.a.unwrap(), slots.b.unwrap()] {
+            assert_eq!(
+                state.fields.keys().collect::<Vec<_>>(),
+                ["editor-note", "other-ab-comparison"]
+            );
+        }
+
+        params.other_comparison.toggle(&setter);
+        let slots = params.other_comparison.map(Clone::clone);
+        assert_eq!(
+            slots.a.unwrap().fields.keys().collect::<Vec<_>>(),
+            ["ab-comparison", "editor-note"]
+        );
+    }
+
+    #[test]
+    fn copy_a_to_b() {
+        let params = Arc::new(TestParams::new());
+        let context = TestGuiContext::new(params.clone());
+        let setter = ParamSetter::new(&context);
+        let comparison = &params.comparison;
+
+        params.set(500.0, 2, "a", &setter);
+        comparison.switch_to(AbSlot::B, &setter);
+        params.set(750.0, 12, "b", &setter);
+
+        // B is active, so copying to it changes the current state
+        comparison.copy_a_to_b(&setter);
+        assert_eq!(params.values(), (500.0, 2, String::from("a")));
+
+        params.set(250.0, 6, "c", &setter);
+        comparison.copy_b_to_a(&setter);
+        comparison.toggle(&setter);
+        assert_eq!(comparison.active(), AbSlot::A);
+        assert_eq!(params.values(), (250.0, 6, String::from("c")));
+    }
+
+    #[test]
+    fn persist_roundtrip() {
+        let params = Arc::new(TestParams::new());
+        let context = TestGuiContext::new(params.clone());
+        let setter = ParamSetter::new(&context);
+        let comparison = &params.comparison;
+
+        params.set(500.0, 2, "a", &setter);
+        comparison.switch_to(AbSlot::B, &setter);
+        params.set(750.0, 12, "b", &setter);
+        comparison.store(AbSlot::B, &setter);
+
+        // Restoring the plugin's state also restores both slots
+        let state = context.get_state();
+        let restored_params = Arc::new(TestParams::new());
+        let restored_context = TestGuiContext::new(restored_params.clone());
+        let restored_setter = ParamSetter::new(&restored_context);
+        restored_context.set_state(state);
+        let restored = &restored_params.comparison;
+        assert_eq!(restored.active(), AbSlot::B);
+        assert!(restored.is_filled(AbSlot::A));
+        assert_eq!(restored_params.values(), (750.0, 12, String::from("b")));
+
+        restored.toggle(&restored_setter);
+        assert_eq!(restored_params.values(), (500.0, 2, String::from("a")));
+        restored.toggle(&restored_setter);
+        assert_eq!(restored_params.values(), (750.0, 12, String::from("b")));
+    }
+}
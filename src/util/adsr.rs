@@ -0,0 +1,475 @@
+//! An ADSR envelope generator for amplitude and filter envelopes.
+
+/// The exponential curves approach a target slightly past the stage's end value so they reach that
+/// value in a finite amount of time. These ratios define how far past the end value that target
+/// is. Smaller values result in more pronounced curves.
+const EXPONENTIAL_ATTACK_TARGET_RATIO: f32 = 0.3;
+const EXPONENTIAL_DECAY_RELEASE_TARGET_RATIO: f32 = 0.0001;
+
+/// A per-voice ADSR envelope generator. Unlike the [`Smoother`][crate::prelude::Smoother], this is
+/// not thread safe and it is meant to be owned by a single voice. [`note_on()`][Self::note_on()]
+/// starts the envelope, [`note_off()`][Self::note_off()] moves it to the release stage from any
+/// other stage, and [`is_finished()`][Self::is_finished()] can be used to tell when the voice can
+/// be reclaimed.
+///
+/// The envelope's output is in the `[0, 1]` range. Stages with a time of zero milliseconds take a
+/// single sample.
+#[derive(Debug, Clone)]
+pub struct Adsr {
+    sample_rate: f32,
+    attack_ms: f32,
+    decay_ms: f32,
+    sustain_level: f32,
+    release_ms: f32,
+    curve: AdsrCurve,
+    retrigger: AdsrRetrigger,
+
+    stage: AdsrStage,
+    /// The envelope's current value.
+    value: f32,
+
+    /// For linear curves, the amount added to or subtracted from [`value`][Self::value] for every
+    /// sample in a stage. For exponential curves this is the one-pole filter's coefficient. The
+    /// release step is computed at [`note_off()`][Self::note_off()] for linear curves since it
+    /// depends on the value the release starts from.
+    attack_step: f32,
+    decay_step: f32,
+    release_step: f32,
+    /// The offsets added in every step of the exponential curves. Unused for linear curves.
+    attack_base: f32,
+    decay_base: f32,
+    release_base: f32,
+}
+
+/// The shape of the envelope's attack, decay, and release stages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AdsrCurve {
+    /// Every stage changes the value at a constant rate. The release stage always takes the
+    /// configured release time, regardless of the value it starts from.
+    Linear,
+    /// The stages follow exponential curves like an analog envelope generator. The attack stage
+    /// is a concave curve, and the decay and release stages are convex curves. The release time is
+    /// the time it takes to release from full scale, so releasing from a lower value takes less
+    /// time.
+    #[default]
+    Exponential,
+}
+
+/// What happens when [`Adsr::note_on()`] is called while the envelope is still active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AdsrRetrigger {
+    /// Continue the attack stage from the envelope's current value. This avoids clicks when a
+    /// voice gets retriggered.
+    #[default]
+    Legato,
+    /// Reset the envelope to zero and start the attack stage from scratch.
+    Reset,
+}
+
+/// The envelope's current stage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdsrStage {
+    /// The envelope is not active and it outputs zeroes.
+    Idle,
+    Attack,
+    Decay,
+    /// The envelope stays at the sustain level until [`Adsr::note_off()`] is called.
+    Sustain,
+    Release,
+}
+
+impl Adsr {
+    /// Create a new envelope generator. The times are in milliseconds, and the sustain level should
+    /// be in the `[0, 1]` range. The envelope is [`Exponential`][AdsrCurve::Exponential] and uses
+    /// [`Legato`][AdsrRetrigger::Legato] retriggering by default.
+    pub fn new(
+        sample_rate: f32,
+        attack_ms: f32,
+        decay_ms: f32,
+        sustain_level: f32,
+        release_ms: f32,
+    ) -> Self {
+        let mut adsr = Self {
+            sample_rate,
+            attack_ms,
+            decay_ms,
+            sustain_level: sustain_level.clamp(0.0, 1.0),
+            release_ms,
+            curve: AdsrCurve::default(),
+            retrigger: AdsrRetrigger::default(),
+
+            stage: AdsrStage::Idle,
+            value: 0.0,
+
+            attack_step: 0.0,
+            decay_step: 0.0,
+            release_step: 0.0,
+            attack_base: 0.0,
+            decay_base: 0.0,
+            release_base: 0.0,
+        };
+        adsr.update_coefficients();
+
+        adsr
+    }
+
+    /// Use a different curve shape for the envelope's stages.
+    pub fn with_curve(mut self, curve: AdsrCurve) -> Self {
+        self.curve = curve;
+        self.update_coefficients();
+        self
+    }
+
+    /// Change what happens when the envelope gets retriggered while it's still active.
+    pub fn with_retrigger(mut self, retrigger: AdsrRetrigger) -> Self {
+        self.retrigger = retrigger;
+        self
+    }
+
+    /// Change the sample rate. This should be called from the plugin's `initialize()` function.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.update_coefficients();
+    }
+
+    /// Change the envelope's times and sustain level. This can be called while the envelope is
+    /// active, for instance at the start of every block, and the changes take effect immediately.
+    pub fn set_parameters(
+        &mut self,
+        attack_ms: f32,
+        decay_ms: f32,
+        sustain_level: f32,
+        release_ms: f32,
+    ) {
+        self.attack_ms = attack_ms;
+        self.decay_ms = decay_ms;
+        self.sustain_level = sustain_level.clamp(0.0, 1.0);
+        self.release_ms = release_ms;
+        self.update_coefficients();
+    }
+
+    /// Start the envelope's attack stage. See [`AdsrRetrigger`] for what happens if the envelope
+    /// is still active.
+    pub fn note_on(&mut self) {
+        if self.retrigger == AdsrRetrigger::Reset {
+            self.value = 0.0;
+        }
+
+        self.stage = AdsrStage::Attack;
+    }
+
+    /// Move the envelope to the release stage, regardless of the stage it is currently in. The
+    /// release starts from the envelope's current value. See [`AdsrCurve`] for how long the release
+    /// takes.
+    pub fn note_off(&mut self) {
+        if self.stage == AdsrStage::Idle {
+            return;
+        }
+
+        self.stage = AdsrStage::Release;
+        if self.curve == AdsrCurve::Linear {
+            self.release_step = self.value / self.stage_samples(self.release_ms);
+        }
+    }
+
+    /// Immediately stop the envelope and reset its value to zero.
+    pub fn reset(&mut self) {
+        self.stage = AdsrStage::Idle;
+        self.value = 0.0;
+    }
+
+    /// The envelope's current stage.
+    pub fn stage(&self) -> AdsrStage {
+        self.stage
+    }
+
+    /// The envelope's current value, without advancing it.
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    /// Whether the envelope has finished its release stage or has never been started. The voice
+    /// using this envelope can be reclaimed at this point.
+    pub fn is_finished(&self) -> bool {
+        self.stage == AdsrStage::Idle
+    }
+
+    /// Advance the envelope by one sample and return the new value.
+    #[allow(clippy::should_implement_trait)]
+    #[inline]
+    pub fn next(&mut self) -> f32 {
+        match (self.stage, self.curve) {
+            (AdsrStage::Idle, _) => (),
+            (AdsrStage::Attack, curve) => {
+                self.value = match curve {
+                    AdsrCurve::Linear => self.value + self.attack_step,
+                    AdsrCurve::Exponential => self.attack_base + (self.value * self.attack_step),
+                };
+
+                if self.value >= 1.0 {
+                    self.value = 1.0;
+                    self.stage = AdsrStage::Decay;
+                }
+            }
+            (AdsrStage::Decay, curve) => {
+                self.value = match curve {
+                    AdsrCurve::Linear => self.value - self.decay_step,
+                    AdsrCurve::Exponential => self.decay_base + (self.value * self.decay_step),
+                };
+
+                if self.value <= self.sustain_level {
+                    self.value = self.sustain_level;
+                    self.stage = AdsrStage::Sustain;
+                }
+            }
+            (AdsrStage::Sustain, _) => self.value = self.sustain_level,
+            (AdsrStage::Release, curve) => {
+                self.value = match curve {
+                    AdsrCurve::Linear => self.value - self.release_step,
+                    AdsrCurve::Exponential => self.release_base + (self.value * self.release_step),
+                };
+
+                if self.value <= 0.0 {
+                    self.value = 0.0;
+                    self.stage = AdsrStage::Idle;
+                }
+            }
+        }
+
+        self.value
+    }
+
+    /// Fill the first `block_len` values of `block_values` with the envelope's next values.
+    #[inline]
+    pub fn next_block(&mut self, block_values: &mut [f32], block_len: usize) {
+        self.next_block_exact(&mut block_values[..block_len])
+    }
+
+    /// The same as [`next_block()`][Self::next_block()], but filling the entire slice.
+    pub fn next_block_exact(&mut self, block_values: &mut [f32]) {
+        if self.stage == AdsrStage::Idle {
+            block_values.fill(0.0);
+            return;
+        }
+
+        for value in block_values {
+            *value = self.next();
+        }
+    }
+
+    /// The length of a stage in samples. This is at least one sample.
+    fn stage_samples(&self, time_ms: f32) -> f32 {
+        (time_ms / 1000.0 * self.sample_rate).max(1.0)
+    }
+
+    /// Recompute the step sizes and coefficients after changing the envelope's settings.
+    fn update_coefficients(&mut self) {
+        let attack_samples = self.stage_samples(self.attack_ms);
+        let decay_samples = self.stage_samples(self.decay_ms);
+        let release_samples = self.stage_samples(self.release_ms);
+
+        match self.curve {
+            AdsrCurve::Linear => {
+                self.attack_step = attack_samples.recip();
+                self.decay_step = (1.0 - self.sustain_level) / decay_samples;
+                // The release step depends on the value the release starts from, so it's computed
+                // in `note_off()` instead
+            }
+            AdsrCurve::Exponential => {
+                let attack_ratio = EXPONENTIAL_ATTACK_TARGET_RATIO;
+                let decay_ratio = EXPONENTIAL_DECAY_RELEASE_TARGET_RATIO;
+
+                self.attack_step = exponential_coefficient(attack_samples, attack_ratio);
+                self.attack_base = (1.0 + attack_ratio) * (1.0 - self.attack_step);
+                self.decay_step = exponential_coefficient(decay_samples, decay_ratio);
+                self.decay_base = (self.sustain_level - decay_ratio) * (1.0 - self.decay_step);
+                self.release_step = exponential_coefficient(release_samples, decay_ratio);
+                self.release_base = -decay_ratio * (1.0 - self.release_step);
+            }
+        }
+    }
+}
+
+/// The one-pole filter coefficient for an exponential curve that overshoots its end value by
+/// `target_ratio` and that reaches the end value in `num_samples` samples.
+fn exponential_coefficient(num_samples: f32, target_ratio: f32) -> f32 {
+    (-((1.0 + target_ratio) / target_ratio).ln() / num_samples).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 1000.0;
+
+    /// Advance the envelope until it leaves `stage`, returning the number of samples that took.
+    /// Panics if the envelope doesn't leave the stage within `max_samples` samples.
+    fn samples_in_stage(adsr: &mut Adsr, stage: AdsrStage, max_samples: usize) -> usize {
+        assert_eq!(adsr.stage(), stage);
+        for num_samples in 1..=max_samples {
+            adsr.next();
+            if adsr.stage() != stage {
+                return num_samples;
+            }
+        }
+
+        panic!("The envelope did not leave the {stage:?} stage within {max_samples} samples");
+    }
+
+    #[test]
+    fn idle_until_note_on() {
+        let mut adsr = Adsr::new(SAMPLE_RATE, 10.0, 10.0, 0.5, 10.0);
+        assert!(adsr.is_finished());
+        assert_eq!(adsr.next(), 0.0);
+
+        // Releasing an idle envelope shouldn't do anything
+        adsr.note_off();
+        assert!(adsr.is_finished());
+        assert_eq!(adsr.next(), 0.0);
+    }
+
+    #[test]
+    fn linear_stage_transitions() {
+        let mut adsr = Adsr::new(SAMPLE_RATE, 10.0, 20.0, 0.5, 40.0).with_curve(AdsrCurve::Linear);
+
+        adsr.note_on();
+        let attack_samples = samples_in_stage(&mut adsr, AdsrStage::Attack, 100);
+        assert!((10..=11).contains(&attack_samples), "{attack_samples}");
+        assert_eq!(adsr.value(), 1.0);
+
+        let decay_samples = samples_in_stage(&mut adsr, AdsrStage::Decay, 100);
+        assert!((20..=21).contains(&decay_samples), "{decay_samples}");
+        assert_eq!(adsr.value(), 0.5);
+
+        for _ in 0..1000 {
+            assert_eq!(adsr.next(), 0.5);
+        }
+        assert_eq!(adsr.stage(), AdsrStage::Sustain);
+
+        adsr.note_off();
+        let release_samples = samples_in_stage(&mut adsr, AdsrStage::Release, 100);
+        assert!((40..=41).contains(&release_samples), "{release_samples}");
+        assert_eq!(adsr.value(), 0.0);
+        assert!(adsr.is_finished());
+    }
+
+    #[test]
+    fn exponential_stage_transitions() {
+        let mut adsr = Adsr::new(SAMPLE_RATE, 10.0, 20.0, 0.25, 40.0);
+
+        adsr.note_on();
+        let attack_samples = samples_in_stage(&mut adsr, AdsrStage::Attack, 100);
+        assert!((10..=11).contains(&attack_samples), "{attack_samples}");
+        assert_eq!(adsr.value(), 1.0);
+
+        // The decay should be monotonic
+        let mut previous_value = adsr.value();
+        for _ in 0..5 {
+            assert!(adsr.next() < previous_value);
+            previous_value = adsr.value();
+        }
+        let decay_samples = samples_in_stage(&mut adsr, AdsrStage::Decay, 100) + 5;
+        assert!((19..=21).contains(&decay_samples), "{decay_samples}");
+        assert_eq!(adsr.value(), 0.25);
+
+        // The exponential release time is the time it takes to go from full scale to zero, so
+        // releasing from the sustain level takes less time than that
+        adsr.note_off();
+        let release_samples = samples_in_stage(&mut adsr, AdsrStage::Release, 100);
+        assert!((30..=41).contains(&release_samples), "{release_samples}");
+        assert!(adsr.is_finished());
+    }
+
+    #[test]
+    fn release_from_any_stage() {
+        for curve in [AdsrCurve::Linear, AdsrCurve::Exponential] {
+            for (stage, samples_before_release) in [
+                (AdsrStage::Attack, 5),
+                (AdsrStage::Decay, 15),
+                (AdsrStage::Sustain, 100),
+            ] {
+                let mut adsr = Adsr::new(SAMPLE_RATE, 10.0, 20.0, 0.5, 40.0).with_curve(curve);
+                adsr.note_on();
+                for _ in 0..samples_before_release {
+                    adsr.next();
+                }
+                assert_eq!(adsr.stage(), stage, "{curve:?}");
+
+                let release_start = adsr.value();
+                assert!(release_start > 0.0);
+                adsr.note_off();
+                assert_eq!(adsr.stage(), AdsrStage::Release);
+
+                // The release should start from the current value without jumping
+                let first_release_value = adsr.next();
+                assert!(first_release_value < release_start, "{curve:?} {stage:?}");
+                assert!(
+                    release_start - first_release_value < release_start * 0.25,
+                    "{curve:?} {stage:?}"
+                );
+
+                let release_samples = samples_in_stage(&mut adsr, AdsrStage::Release, 100) + 1;
+                let expected_release_samples = match curve {
+                    AdsrCurve::Linear => 39..=42,
+                    AdsrCurve::Exponential => 30..=42,
+                };
+                assert!(
+                    expected_release_samples.contains(&release_samples),
+                    "{curve:?} {stage:?} {release_samples}"
+                );
+                assert!(adsr.is_finished());
+            }
+        }
+    }
+
+    #[test]
+    fn retrigger() {
+        let mut legato = Adsr::new(SAMPLE_RATE, 10.0, 20.0, 0.5, 40.0);
+        let mut reset =
+            Adsr::new(SAMPLE_RATE, 10.0, 20.0, 0.5, 40.0).with_retrigger(AdsrRetrigger::Reset);
+        for adsr in [&mut legato, &mut reset] {
+            adsr.note_on();
+            for _ in 0..50 {
+                adsr.next();
+            }
+            adsr.note_off();
+            for _ in 0..5 {
+                adsr.next();
+            }
+            adsr.note_on();
+            assert_eq!(adsr.stage(), AdsrStage::Attack);
+        }
+
+        assert!(legato.value() > 0.0);
+        assert_eq!(reset.value(), 0.0);
+        assert!(legato.next() > reset.next());
+    }
+
+    #[test]
+    fn zero_times() {
+        let mut adsr = Adsr::new(SAMPLE_RATE, 0.0, 0.0, 0.5, 0.0).with_curve(AdsrCurve::Linear);
+
+        adsr.note_on();
+        assert_eq!(adsr.next(), 1.0);
+        assert_eq!(adsr.next(), 0.5);
+        assert_eq!(adsr.stage(), AdsrStage::Sustain);
+        adsr.note_off();
+        assert_eq!(adsr.next(), 0.0);
+        assert!(adsr.is_finished());
+    }
+
+    #[test]
+    fn next_block() {
+        let mut adsr = Adsr::new(SAMPLE_RATE, 10.0, 20.0, 0.5, 40.0);
+        let mut reference = adsr.clone();
+        adsr.note_on();
+        reference.note_on();
+
+        let mut block = [0.0; 64];
+        adsr.next_block(&mut block, 48);
+        for value in &block[..48] {
+            assert_eq!(*value, reference.next());
+        }
+        assert_eq!(block[48..], [0.0; 16]);
+    }
+}
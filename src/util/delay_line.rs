@@ -0,0 +1,269 @@
+//! A delay line with fractional delay reads.
+
+/// A single channel delay line with interpolated reads for delays, choruses, flangers, and other
+/// modulated delay effects. The buffer is allocated in [`new()`][Self::new()] and
+/// [`resize()`][Self::resize()], so reading from and writing to the delay line never allocates.
+///
+/// Delays are measured relative to the next sample that will be written. To delay a signal by `d`
+/// samples, read from the delay line _before_ writing the current input sample:
+///
+/// ```
+/// # use nih_plug::util::DelayLine;
+/// # let (input, feedback, delay_samples) = (0.5, 0.3, 100.5);
+/// # let mut delay_line = DelayLine::new(1000);
+/// let delayed = delay_line.read(delay_samples);
+/// delay_line.write(input + delayed * feedback);
+/// let output = delayed;
+/// ```
+///
+/// This order also makes feedback safe, since the value being read never depends on the sample
+/// that's about to be written. The shortest possible delay is thus one sample.
+#[derive(Debug, Clone)]
+pub struct DelayLine {
+    /// The ring buffer. The length is always a power of two so the indices can be wrapped using
+    /// `mask`.
+    buffer: Vec<f32>,
+    mask: usize,
+    /// The index the next sample will be written to.
+    write_pos: usize,
+    max_delay_samples: usize,
+    interpolation: DelayInterpolation,
+
+    /// The previous output of [`read_allpass()`][Self::read_allpass()].
+    allpass_state: f32,
+}
+
+/// The interpolation used by [`DelayLine::read()`] for fractional delays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DelayInterpolation {
+    /// Round the delay down to the nearest integer number of samples.
+    None,
+    /// Linearly interpolate between the two nearest samples. This is cheap, but it attenuates
+    /// high frequencies when the delay is close to halfway between two samples.
+    #[default]
+    Linear,
+    /// Four point, third order Hermite interpolation. This is more expensive than linear
+    /// interpolation, but it's much more accurate for higher frequencies.
+    Hermite,
+}
+
+impl DelayLine {
+    /// Create a delay line that can delay signals by up to `max_delay_samples` samples. The delay
+    /// line uses linear interpolation by default.
+    pub fn new(max_delay_samples: usize) -> Self {
+        let mut delay_line = Self {
+            buffer: Vec::new(),
+            mask: 0,
+            write_pos: 0,
+            max_delay_samples: 0,
+            interpolation: DelayInterpolation::default(),
+
+            allpass_state: 0.0,
+        };
+        delay_line.resize(max_delay_samples);
+
+        delay_line
+    }
+
+    /// Use a different interpolation method for [`read()`][Self::read()].
+    pub fn with_interpolation(mut self, interpolation: DelayInterpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// Change the interpolation method used for [`read()`][Self::read()].
+    pub fn set_interpolation(&mut self, interpolation: DelayInterpolation) {
+        self.interpolation = interpolation;
+    }
+
+    /// Change the maximum delay. This clears the delay line and it may allocate, so it should be
+    /// called from the plugin's `initialize()` function, for instance when the sample rate
+    /// changes.
+    pub fn resize(&mut self, max_delay_samples: usize) {
+        // Hermite interpolation needs two extra samples past the maximum delay
+        let len = (max_delay_samples + 3).next_power_of_two();
+        self.buffer.clear();
+        self.buffer.resize(len, 0.0);
+        self.mask = len - 1;
+        self.write_pos = 0;
+        self.max_delay_samples = max_delay_samples;
+        self.allpass_state = 0.0;
+    }
+
+    /// The maximum delay in samples. Longer delays are clamped to this value.
+    pub fn max_delay_samples(&self) -> usize {
+        self.max_delay_samples
+    }
+
+    /// Clear the delay line without changing its size. This should be called from the plugin's
+    /// `reset()` function.
+    pub fn reset(&mut self) {
+        self.buffer.fill(0.0);
+        self.write_pos = 0;
+        self.allpass_state = 0.0;
+    }
+
+    /// Write a sample to the delay line. See the struct's documentation for the order in which
+    /// [`read()`][Self::read()] and `write()` should be called.
+    #[inline]
+    pub fn write(&mut self, sample: f32) {
+        self.buffer[self.write_pos] = sample;
+        self.write_pos = (self.write_pos + 1) & self.mask;
+    }
+
+    /// Read the sample from `delay_samples` samples ago using the delay line's interpolation
+    /// method. The delay is clamped to `[1, max_delay_samples]`.
+    #[inline]
+    pub fn read(&self, delay_samples: f32) -> f32 {
+        let (delay_int, delay_frac) = self.split_delay(delay_samples);
+
+        match self.interpolation {
+            DelayInterpolation::None => self.tap(delay_int),
+            DelayInterpolation::Linear => {
+                let y0 = self.tap(delay_int);
+                let y1 = self.tap(delay_int + 1);
+
+                y0 + (y1 - y0) * delay_frac
+            }
+            DelayInterpolation::Hermite => {
+                // The newer sample is not available yet at delays below two samples, so the
+                // current sample is used in its place
+                let y_newer = self.tap((delay_int - 1).max(1));
+                let y0 = self.tap(delay_int);
+                let y1 = self.tap(delay_int + 1);
+                let y2 = self.tap(delay_int + 2);
+
+                let c1 = 0.5 * (y1 - y_newer);
+                let c2 = y_newer - 2.5 * y0 + 2.0 * y1 - 0.5 * y2;
+                let c3 = 0.5 * (y2 - y_newer) + 1.5 * (y0 - y1);
+
+                ((c3 * delay_frac + c2) * delay_frac + c1) * delay_frac + y0
+            }
+        }
+    }
+
+    /// Read the sample from `delay_samples` samples ago using first order all-pass interpolation.
+    /// All-pass interpolation has a flat magnitude response, which makes it a good fit for
+    /// feedback loops like those in physical models where linear interpolation's high frequency
+    /// loss would accumulate. The delay is clamped to `[1, max_delay_samples]`.
+    ///
+    /// All-pass interpolation is stateful, so this should be called exactly once per sample and
+    /// only a single tap can be read from a delay line this way. Quickly modulating the delay
+    /// time results in audible transients.
+    #[inline]
+    pub fn read_allpass(&mut self, delay_samples: f32) -> f32 {
+        let (delay_int, delay_frac) = self.split_delay(delay_samples);
+        let y0 = self.tap(delay_int);
+        let y1 = self.tap(delay_int + 1);
+
+        let coefficient = (1.0 - delay_frac) / (1.0 + delay_frac);
+        self.allpass_state = coefficient * y0 + y1 - coefficient * self.allpass_state;
+
+        self.allpass_state
+    }
+
+    /// Split a delay into an integer part in `[1, max_delay_samples]` and a fractional part.
+    #[inline]
+    fn split_delay(&self, delay_samples: f32) -> (usize, f32) {
+        let delay_samples = delay_samples.clamp(1.0, self.max_delay_samples.max(1) as f32);
+        let delay_int = delay_samples as usize;
+
+        (delay_int, delay_samples - delay_int as f32)
+    }
+
+    /// The sample written `delay` samples ago, where a delay of one is the most recently written
+    /// sample.
+    #[inline]
+    fn tap(&self, delay: usize) -> f32 {
+        self.buffer[self.write_pos.wrapping_sub(delay) & self.mask]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 1 kHz sine wave at 48 kHz.
+    fn sine(position: f32) -> f32 {
+        (std::f32::consts::TAU * 1_000.0 * position / 48_000.0).sin()
+    }
+
+    /// Run a 1 kHz sine wave through a delay line using `read_fn`, and return the largest error
+    /// compared to the ideal delayed sine wave after the delay line has filled up.
+    fn max_error(
+        delay_line: &mut DelayLine,
+        delay_samples: f32,
+        mut read_fn: impl FnMut(&mut DelayLine, f32) -> f32,
+    ) -> f32 {
+        let mut max_error: f32 = 0.0;
+        for i in 0..4_000 {
+            let delayed = read_fn(delay_line, delay_samples);
+            delay_line.write(sine(i as f32));
+
+            if i >= 2_000 {
+                max_error = max_error.max((delayed - sine(i as f32 - delay_samples)).abs());
+            }
+        }
+
+        max_error
+    }
+
+    #[test]
+    fn integer_delay() {
+        let mut delay_line = DelayLine::new(16).with_interpolation(DelayInterpolation::None);
+        for i in 0..100 {
+            let delayed = delay_line.read(5.0);
+            delay_line.write(i as f32);
+
+            assert_eq!(delayed, (i as f32 - 5.0).max(0.0));
+        }
+    }
+
+    #[test]
+    fn delay_is_clamped() {
+        let mut delay_line = DelayLine::new(10);
+        assert_eq!(delay_line.max_delay_samples(), 10);
+        for i in 1..=20 {
+            delay_line.write(i as f32);
+        }
+
+        assert_eq!(delay_line.read(0.0), 20.0);
+        assert_eq!(delay_line.read(10.0), 11.0);
+        assert_eq!(delay_line.read(100.0), 11.0);
+
+        delay_line.reset();
+        assert_eq!(delay_line.read(1.0), 0.0);
+    }
+
+    #[test]
+    fn linear_interpolation_ramp() {
+        // Linear interpolation on a linear ramp should be exact
+        let mut delay_line = DelayLine::new(32);
+        for i in 0..64 {
+            delay_line.write(i as f32);
+        }
+
+        assert_eq!(delay_line.read(1.0), 63.0);
+        assert_eq!(delay_line.read(2.5), 61.5);
+        assert_eq!(delay_line.read(10.25), 53.75);
+    }
+
+    #[test]
+    fn fractional_read_accuracy() {
+        for delay_samples in [3.25, 7.9, 10.5] {
+            let mut delay_line = DelayLine::new(32);
+            let linear_error = max_error(&mut delay_line, delay_samples, |d, delay| d.read(delay));
+            assert!(linear_error < 3e-3, "{delay_samples}: {linear_error}");
+
+            let mut delay_line = DelayLine::new(32).with_interpolation(DelayInterpolation::Hermite);
+            let hermite_error = max_error(&mut delay_line, delay_samples, |d, delay| d.read(delay));
+            assert!(hermite_error < 1e-4, "{delay_samples}: {hermite_error}");
+
+            let mut delay_line = DelayLine::new(32);
+            let allpass_error = max_error(&mut delay_line, delay_samples, |d, delay| {
+                d.read_allpass(delay)
+            });
+            assert!(allpass_error < 2e-4, "{delay_samples}: {allpass_error}");
+        }
+    }
+}
@@ -0,0 +1,659 @@
+//! Basic filter building blocks. These filters process a single channel of `f32` samples, don't
+//! allocate, and can safely be used on the audio thread. Use one filter instance per channel. With
+//! the `simd` feature enabled, a [`Biquad`] can also process multiple channels at once using
+//! `std::simd` vectors.
+
+use std::f32::consts;
+use std::ops::{Add, Mul, Sub};
+#[cfg(feature = "simd")]
+use std::simd::{LaneCount, Simd, SupportedLaneCount};
+
+/// A biquad filter using the transposed direct form II structure. Use
+/// [`BiquadCoefficients`] to compute the coefficients for common filter types.
+///
+/// Based on <https://en.wikipedia.org/wiki/Digital_biquad_filter#Transposed_direct_forms>.
+///
+/// The type parameter `T` should be either an `f32` or a SIMD type.
+#[derive(Debug, Clone, Copy)]
+pub struct Biquad<T = f32> {
+    pub coefficients: BiquadCoefficients<T>,
+    s1: T,
+    s2: T,
+}
+
+/// The coefficients `[b0, b1, b2, a1, a2]` for a [`Biquad`]. These coefficients are all
+/// prenormalized, i.e. they have been divided by `a0`.
+///
+/// The filter designs are based on the [Audio EQ
+/// Cookbook](https://webaudio.github.io/Audio-EQ-Cookbook/audio-eq-cookbook.html). `frequency` is
+/// the cutoff or center frequency in Hertz, and it must be below the Nyquist frequency. A `q` of
+/// `std::f32::consts::FRAC_1_SQRT_2` results in a Butterworth response for the low-pass and
+/// high-pass filters.
+///
+/// The type parameter `T` should be either an `f32` or a SIMD type.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BiquadCoefficients<T = f32> {
+    b0: T,
+    b1: T,
+    b2: T,
+    a1: T,
+    a2: T,
+}
+
+/// Either an `f32` or some SIMD vector type of `f32`s that can be used with our biquads.
+pub trait SimdType:
+    Mul<Output = Self> + Sub<Output = Self> + Add<Output = Self> + Copy + Sized
+{
+    fn from_f32(value: f32) -> Self;
+}
+
+/// A state variable filter using the trapezoidal integration approach from Andrew Simper's
+/// [Linear Trap Integrated SVF](https://cytomic.com/files/dsp/SvfLinearTrapOptimised2.pdf) paper.
+/// Unlike a [`Biquad`], this filter behaves well when its frequency gets modulated at audio rates.
+#[derive(Debug, Clone, Copy)]
+pub struct StateVariableFilter {
+    filter_type: SvfType,
+
+    /// The `k` coefficient, which is just `1 / q`.
+    k: f32,
+    a1: f32,
+    a2: f32,
+    a3: f32,
+
+    ic1eq: f32,
+    ic2eq: f32,
+}
+
+/// A DC blocker. This is a one-pole high-pass filter with a very low cutoff frequency that removes
+/// the DC offset introduced by nonlinear processing like asymmetric waveshaping, while passing
+/// audible low frequencies through mostly unaffected. The cutoff frequency defaults to
+/// [`DcBlocker::DEFAULT_CUTOFF`].
+///
+/// Based on the classic `y[n] = x[n] - x[n - 1] + R * y[n - 1]` difference equation.
+#[derive(Debug, Clone, Copy)]
+pub struct DcBlocker {
+    /// The pole's position `R`, computed from the cutoff frequency.
+    coefficient: f32,
+
+    x1: f32,
+    y1: f32,
+}
+
+/// The output of a [`StateVariableFilter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SvfType {
+    Lowpass,
+    Highpass,
+    /// A band-pass filter with a peak gain of 0 dB.
+    Bandpass,
+    Notch,
+    Allpass,
+}
+
+impl<T: SimdType> Default for Biquad<T> {
+    /// Before setting constants the filter should just act as an identity function.
+    fn default() -> Self {
+        Self::new(BiquadCoefficients::identity())
+    }
+}
+
+impl<T: SimdType> Biquad<T> {
+    /// Create a new biquad filter with the specified coefficients.
+    pub fn new(coefficients: BiquadCoefficients<T>) -> Self {
+        Self {
+            coefficients,
+            s1: T::from_f32(0.0),
+            s2: T::from_f32(0.0),
+        }
+    }
+
+    /// Process a single sample.
+    #[inline]
+    pub fn process(&mut self, sample: T) -> T {
+        let result = self.coefficients.b0 * sample + self.s1;
+
+        self.s1 = self.coefficients.b1 * sample - self.coefficients.a1 * result + self.s2;
+        self.s2 = self.coefficients.b2 * sample - self.coefficients.a2 * result;
+
+        result
+    }
+
+    /// Reset the state to zero, useful after making large, non-interpolatable changes to the filter
+    /// coefficients.
+    pub fn reset(&mut self) {
+        self.s1 = T::from_f32(0.0);
+        self.s2 = T::from_f32(0.0);
+    }
+}
+
+impl<T: SimdType> BiquadCoefficients<T> {
+    /// Convert scalar coefficients into the correct vector type.
+    pub fn from_f32s(scalar: BiquadCoefficients<f32>) -> Self {
+        Self {
+            b0: T::from_f32(scalar.b0),
+            b1: T::from_f32(scalar.b1),
+            b2: T::from_f32(scalar.b2),
+            a1: T::from_f32(scalar.a1),
+            a2: T::from_f32(scalar.a2),
+        }
+    }
+
+    /// Filter coefficients that would cause the sound to be passed through as is.
+    pub fn identity() -> Self {
+        Self::from_f32s(BiquadCoefficients {
+            b0: 1.0,
+            b1: 0.0,
+            b2: 0.0,
+            a1: 0.0,
+            a2: 0.0,
+        })
+    }
+
+    /// Compute the coefficients for a low-pass filter.
+    pub fn lowpass(sample_rate: f32, frequency: f32, q: f32) -> Self {
+        let (cos_omega0, alpha) = omega0_alpha(sample_rate, frequency, q);
+
+        let b0 = (1.0 - cos_omega0) / 2.0;
+        let b1 = 1.0 - cos_omega0;
+        let b2 = (1.0 - cos_omega0) / 2.0;
+        let a0 = 1.0 + alpha;
+        let a1 = -2.0 * cos_omega0;
+        let a2 = 1.0 - alpha;
+
+        Self::normalized(b0, b1, b2, a0, a1, a2)
+    }
+
+    /// Compute the coefficients for a high-pass filter.
+    pub fn highpass(sample_rate: f32, frequency: f32, q: f32) -> Self {
+        let (cos_omega0, alpha) = omega0_alpha(sample_rate, frequency, q);
+
+        let b0 = (1.0 + cos_omega0) / 2.0;
+        let b1 = -(1.0 + cos_omega0);
+        let b2 = (1.0 + cos_omega0) / 2.0;
+        let a0 = 1.0 + alpha;
+        let a1 = -2.0 * cos_omega0;
+        let a2 = 1.0 - alpha;
+
+        Self::normalized(b0, b1, b2, a0, a1, a2)
+    }
+
+    /// Compute the coefficients for a band-pass filter with a peak gain of 0 dB.
+    pub fn bandpass(sample_rate: f32, frequency: f32, q: f32) -> Self {
+        let (cos_omega0, alpha) = omega0_alpha(sample_rate, frequency, q);
+
+        let b0 = alpha;
+        let b1 = 0.0;
+        let b2 = -alpha;
+        let a0 = 1.0 + alpha;
+        let a1 = -2.0 * cos_omega0;
+        let a2 = 1.0 - alpha;
+
+        Self::normalized(b0, b1, b2, a0, a1, a2)
+    }
+
+    /// Compute the coefficients for a notch filter.
+    pub fn notch(sample_rate: f32, frequency: f32, q: f32) -> Self {
+        let (cos_omega0, alpha) = omega0_alpha(sample_rate, frequency, q);
+
+        let b0 = 1.0;
+        let b1 = -2.0 * cos_omega0;
+        let b2 = 1.0;
+        let a0 = 1.0 + alpha;
+        let a1 = -2.0 * cos_omega0;
+        let a2 = 1.0 - alpha;
+
+        Self::normalized(b0, b1, b2, a0, a1, a2)
+    }
+
+    /// Compute the coefficients for an all-pass filter.
+    pub fn allpass(sample_rate: f32, frequency: f32, q: f32) -> Self {
+        let (cos_omega0, alpha) = omega0_alpha(sample_rate, frequency, q);
+
+        let b0 = 1.0 - alpha;
+        let b1 = -2.0 * cos_omega0;
+        let b2 = 1.0 + alpha;
+        let a0 = 1.0 + alpha;
+        let a1 = -2.0 * cos_omega0;
+        let a2 = 1.0 - alpha;
+
+        Self::normalized(b0, b1, b2, a0, a1, a2)
+    }
+
+    /// Compute the coefficients for a peaking (bell) filter that boosts or cuts the signal by
+    /// `gain_db` decibels around `frequency`.
+    pub fn peaking(sample_rate: f32, frequency: f32, q: f32, gain_db: f32) -> Self {
+        let (cos_omega0, alpha) = omega0_alpha(sample_rate, frequency, q);
+        let a = shelf_gain(gain_db);
+
+        let b0 = 1.0 + alpha * a;
+        let b1 = -2.0 * cos_omega0;
+        let b2 = 1.0 - alpha * a;
+        let a0 = 1.0 + alpha / a;
+        let a1 = -2.0 * cos_omega0;
+        let a2 = 1.0 - alpha / a;
+
+        Self::normalized(b0, b1, b2, a0, a1, a2)
+    }
+
+    /// Compute the coefficients for a low shelf filter that boosts or cuts the signal below
+    /// `frequency` by `gain_db` decibels.
+    pub fn low_shelf(sample_rate: f32, frequency: f32, q: f32, gain_db: f32) -> Self {
+        let (cos_omega0, alpha) = omega0_alpha(sample_rate, frequency, q);
+        let a = shelf_gain(gain_db);
+        let two_sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+        let b0 = a * ((a + 1.0) - (a - 1.0) * cos_omega0 + two_sqrt_a_alpha);
+        let b1 = 2.0 * a * ((a - 1.0) - (a + 1.0) * cos_omega0);
+        let b2 = a * ((a + 1.0) - (a - 1.0) * cos_omega0 - two_sqrt_a_alpha);
+        let a0 = (a + 1.0) + (a - 1.0) * cos_omega0 + two_sqrt_a_alpha;
+        let a1 = -2.0 * ((a - 1.0) + (a + 1.0) * cos_omega0);
+        let a2 = (a + 1.0) + (a - 1.0) * cos_omega0 - two_sqrt_a_alpha;
+
+        Self::normalized(b0, b1, b2, a0, a1, a2)
+    }
+
+    /// Compute the coefficients for a high shelf filter that boosts or cuts the signal above
+    /// `frequency` by `gain_db` decibels.
+    pub fn high_shelf(sample_rate: f32, frequency: f32, q: f32, gain_db: f32) -> Self {
+        let (cos_omega0, alpha) = omega0_alpha(sample_rate, frequency, q);
+        let a = shelf_gain(gain_db);
+        let two_sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+        let b0 = a * ((a + 1.0) + (a - 1.0) * cos_omega0 + two_sqrt_a_alpha);
+        let b1 = -2.0 * a * ((a - 1.0) + (a + 1.0) * cos_omega0);
+        let b2 = a * ((a + 1.0) + (a - 1.0) * cos_omega0 - two_sqrt_a_alpha);
+        let a0 = (a + 1.0) - (a - 1.0) * cos_omega0 + two_sqrt_a_alpha;
+        let a1 = 2.0 * ((a - 1.0) - (a + 1.0) * cos_omega0);
+        let a2 = (a + 1.0) - (a - 1.0) * cos_omega0 - two_sqrt_a_alpha;
+
+        Self::normalized(b0, b1, b2, a0, a1, a2)
+    }
+
+    /// Prenormalize the coefficients by dividing everything by `a0`.
+    #[inline]
+    fn normalized(b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) -> Self {
+        Self::from_f32s(BiquadCoefficients {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+        })
+    }
+}
+
+impl SimdType for f32 {
+    #[inline(always)]
+    fn from_f32(value: f32) -> Self {
+        value
+    }
+}
+
+#[cfg(feature = "simd")]
+impl<const LANES: usize> SimdType for Simd<f32, LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    #[inline(always)]
+    fn from_f32(value: f32) -> Self {
+        Simd::splat(value)
+    }
+}
+
+impl StateVariableFilter {
+    /// Create a new state variable filter. See [`BiquadCoefficients`] for the meaning of the
+    /// parameters.
+    pub fn new(filter_type: SvfType, sample_rate: f32, frequency: f32, q: f32) -> Self {
+        let mut filter = Self {
+            filter_type,
+
+            k: 0.0,
+            a1: 0.0,
+            a2: 0.0,
+            a3: 0.0,
+
+            ic1eq: 0.0,
+            ic2eq: 0.0,
+        };
+        filter.set_parameters(sample_rate, frequency, q);
+
+        filter
+    }
+
+    /// Change the filter's output. This does not reset the filter's state.
+    pub fn set_filter_type(&mut self, filter_type: SvfType) {
+        self.filter_type = filter_type;
+    }
+
+    /// Change the filter's frequency and resonance. Unlike with the [`Biquad`], this can safely be
+    /// done for every sample.
+    pub fn set_parameters(&mut self, sample_rate: f32, frequency: f32, q: f32) {
+        nih_debug_assert!(sample_rate > 0.0);
+        nih_debug_assert!(frequency > 0.0);
+        nih_debug_assert!(frequency < sample_rate / 2.0);
+        nih_debug_assert!(q > 0.0);
+
+        let g = (consts::PI * (frequency / sample_rate)).tan();
+        self.k = q.recip();
+        self.a1 = (1.0 + g * (g + self.k)).recip();
+        self.a2 = g * self.a1;
+        self.a3 = g * self.a2;
+    }
+
+    /// Process a single sample.
+    #[inline]
+    pub fn process(&mut self, sample: f32) -> f32 {
+        let v3 = sample - self.ic2eq;
+        let v1 = self.a1 * self.ic1eq + self.a2 * v3;
+        let v2 = self.ic2eq + self.a2 * self.ic1eq + self.a3 * v3;
+        self.ic1eq = 2.0 * v1 - self.ic1eq;
+        self.ic2eq = 2.0 * v2 - self.ic2eq;
+
+        match self.filter_type {
+            SvfType::Lowpass => v2,
+            SvfType::Highpass => sample - self.k * v1 - v2,
+            SvfType::Bandpass => self.k * v1,
+            SvfType::Notch => sample - self.k * v1,
+            SvfType::Allpass => sample - 2.0 * self.k * v1,
+        }
+    }
+
+    /// Reset the state to zero.
+    pub fn reset(&mut self) {
+        self.ic1eq = 0.0;
+        self.ic2eq = 0.0;
+    }
+}
+
+impl DcBlocker {
+    /// The default cutoff frequency in Hertz.
+    pub const DEFAULT_CUTOFF: f32 = 5.0;
+
+    /// Create a new DC blocker with the [default cutoff frequency][Self::DEFAULT_CUTOFF].
+    pub fn new(sample_rate: f32) -> Self {
+        Self::with_cutoff(sample_rate, Self::DEFAULT_CUTOFF)
+    }
+
+    /// Create a new DC blocker with a cutoff frequency in Hertz. This should only be a couple of
+    /// Hertz.
+    pub fn with_cutoff(sample_rate: f32, cutoff: f32) -> Self {
+        let mut filter = Self {
+            coefficient: 0.0,
+
+            x1: 0.0,
+            y1: 0.0,
+        };
+        filter.set_cutoff(sample_rate, cutoff);
+
+        filter
+    }
+
+    /// Change the cutoff frequency in Hertz. This does not reset the filter's state.
+    pub fn set_cutoff(&mut self, sample_rate: f32, cutoff: f32) {
+        nih_debug_assert!(sample_rate > 0.0);
+        nih_debug_assert!(cutoff > 0.0);
+        nih_debug_assert!(cutoff < sample_rate / 2.0);
+
+        self.coefficient = (-consts::TAU * (cutoff / sample_rate)).exp();
+    }
+
+    /// Process a single sample.
+    #[inline]
+    pub fn process(&mut self, sample: f32) -> f32 {
+        let result = sample - self.x1 + self.coefficient * self.y1;
+        self.x1 = sample;
+        self.y1 = result;
+
+        result
+    }
+
+    /// Process a block of samples in place.
+    pub fn process_block(&mut self, block: &mut [f32]) {
+        for sample in block {
+            *sample = self.process(*sample);
+        }
+    }
+
+    /// Reset the state to zero.
+    pub fn reset(&mut self) {
+        self.x1 = 0.0;
+        self.y1 = 0.0;
+    }
+}
+
+/// Compute `cos(omega0)` and `alpha` for the Audio EQ Cookbook filter designs.
+#[inline]
+fn omega0_alpha(sample_rate: f32, frequency: f32, q: f32) -> (f32, f32) {
+    nih_debug_assert!(sample_rate > 0.0);
+    nih_debug_assert!(frequency > 0.0);
+    nih_debug_assert!(frequency < sample_rate / 2.0);
+    nih_debug_assert!(q > 0.0);
+
+    let omega0 = consts::TAU * (frequency / sample_rate);
+    let alpha = omega0.sin() / (2.0 * q);
+
+    (omega0.cos(), alpha)
+}
+
+/// The `A` value used for the Audio EQ Cookbook's peaking and shelving filters.
+#[inline]
+fn shelf_gain(gain_db: f32) -> f32 {
+    10.0f32.powf(gain_db / 40.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+    const FREQUENCY: f32 = 1_000.0;
+    const Q: f32 = consts::FRAC_1_SQRT_2;
+    const GAIN_DB: f32 = 6.0;
+    /// The test frequencies. A second of audio contains an integer number of periods for all of
+    /// these frequencies, which keeps the RMS measurement exact.
+    const TEST_FREQUENCIES: [f32; 4] = [100.0, 1_000.0, 2_500.0, 5_000.0];
+
+    /// The numerator and denominator coefficients `[c0, c1, c2]` of an analog prototype filter with
+    /// a cutoff frequency of 1 rad/s, i.e. `H(s) = (n2 s^2 + n1 s + n0) / (d2 s^2 + d1 s + d0)`.
+    type AnalogPrototype = ([f64; 3], [f64; 3]);
+
+    /// The filters are designed using the bilinear transform with frequency prewarping, so their
+    /// magnitude response at `frequency` equals the analog prototype's magnitude response at the
+    /// prewarped frequency.
+    fn analytic_magnitude((numerator, denominator): AnalogPrototype, frequency: f32) -> f64 {
+        let omega = (std::f64::consts::PI * (frequency as f64 / SAMPLE_RATE as f64)).tan()
+            / (std::f64::consts::PI * (FREQUENCY as f64 / SAMPLE_RATE as f64)).tan();
+        let magnitude = |[c0, c1, c2]: [f64; 3]| (c0 - c2 * omega * omega).hypot(c1 * omega);
+
+        magnitude(numerator) / magnitude(denominator)
+    }
+
+    fn analog_prototype(name: &str) -> AnalogPrototype {
+        let q = Q as f64;
+        let a = 10.0f64.powf(GAIN_DB as f64 / 40.0);
+        let sqrt_a_q = a.sqrt() / q;
+        match name {
+            "lowpass" => ([1.0, 0.0, 0.0], [1.0, 1.0 / q, 1.0]),
+            "highpass" => ([0.0, 0.0, 1.0], [1.0, 1.0 / q, 1.0]),
+            "bandpass" => ([0.0, 1.0 / q, 0.0], [1.0, 1.0 / q, 1.0]),
+            "notch" => ([1.0, 0.0, 1.0], [1.0, 1.0 / q, 1.0]),
+            "allpass" => ([1.0, -1.0 / q, 1.0], [1.0, 1.0 / q, 1.0]),
+            "peaking" => ([1.0, a / q, 1.0], [1.0, 1.0 / (a * q), 1.0]),
+            "low_shelf" => ([a * a, a * sqrt_a_q, a], [1.0, sqrt_a_q, a]),
+            "high_shelf" => ([a, a * sqrt_a_q, a * a], [a, sqrt_a_q, 1.0]),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Measure the filter's gain at `frequency` by filtering a sine wave and comparing the output's
+    /// RMS level after the filter has settled.
+    fn measured_magnitude(mut process: impl FnMut(f32) -> f32, frequency: f32) -> f64 {
+        let num_samples = SAMPLE_RATE as usize;
+        let mut sum_of_squares = 0.0f64;
+        for i in 0..num_samples * 2 {
+            let phase = (i % num_samples) as f64 * frequency as f64 / SAMPLE_RATE as f64;
+            let sample = (std::f64::consts::TAU * phase).sin() as f32;
+            let output = process(sample) as f64;
+            if i >= num_samples {
+                sum_of_squares += output * output;
+            }
+        }
+
+        (sum_of_squares / num_samples as f64 * 2.0).sqrt()
+    }
+
+    fn assert_magnitude_response(
+        name: &str,
+        mut process: impl FnMut(f32) -> f32,
+        mut reset: impl FnMut(),
+    ) {
+        let prototype = analog_prototype(name);
+        for frequency in TEST_FREQUENCIES {
+            reset();
+
+            let expected = analytic_magnitude(prototype, frequency);
+            let measured = measured_magnitude(&mut process, frequency);
+            if expected < 1e-6 {
+                assert!(
+                    measured < 0.01,
+                    "{name} at {frequency} Hz: expected silence, measured {measured}"
+                );
+            } else {
+                let error_db = 20.0 * (measured / expected).log10();
+                assert!(
+                    error_db.abs() < 0.05,
+                    "{name} at {frequency} Hz: expected {expected}, measured {measured}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn biquad_identity() {
+        let mut biquad = Biquad::<f32>::default();
+        for sample in [0.0, 1.0, -0.5, 0.25] {
+            assert_eq!(biquad.process(sample), sample);
+        }
+    }
+
+    #[test]
+    fn known_magnitudes() {
+        // A Butterworth low-pass filter is -3 dB at the cutoff frequency, and the peaking and
+        // shelving filters reach their full or half gain at the center frequency
+        let magnitude_db = |coefficients: BiquadCoefficients| {
+            let mut biquad = Biquad::new(coefficients);
+            20.0 * measured_magnitude(|sample| biquad.process(sample), FREQUENCY).log10()
+        };
+
+        let lowpass = magnitude_db(BiquadCoefficients::lowpass(SAMPLE_RATE, FREQUENCY, Q));
+        assert!((lowpass + 3.0103).abs() < 0.01, "{lowpass}");
+        let peaking = magnitude_db(BiquadCoefficients::peaking(
+            SAMPLE_RATE,
+            FREQUENCY,
+            Q,
+            GAIN_DB,
+        ));
+        assert!((peaking - GAIN_DB as f64).abs() < 0.01, "{peaking}");
+        let low_shelf = magnitude_db(BiquadCoefficients::low_shelf(
+            SAMPLE_RATE,
+            FREQUENCY,
+            Q,
+            GAIN_DB,
+        ));
+        assert!(
+            (low_shelf - GAIN_DB as f64 / 2.0).abs() < 0.01,
+            "{low_shelf}"
+        );
+    }
+
+    #[test]
+    fn biquad_magnitude_response() {
+        let filters = [
+            (
+                "lowpass",
+                BiquadCoefficients::lowpass(SAMPLE_RATE, FREQUENCY, Q),
+            ),
+            (
+                "highpass",
+                BiquadCoefficients::highpass(SAMPLE_RATE, FREQUENCY, Q),
+            ),
+            (
+                "bandpass",
+                BiquadCoefficients::bandpass(SAMPLE_RATE, FREQUENCY, Q),
+            ),
+            (
+                "notch",
+                BiquadCoefficients::notch(SAMPLE_RATE, FREQUENCY, Q),
+            ),
+            (
+                "allpass",
+                BiquadCoefficients::allpass(SAMPLE_RATE, FREQUENCY, Q),
+            ),
+            (
+                "peaking",
+                BiquadCoefficients::peaking(SAMPLE_RATE, FREQUENCY, Q, GAIN_DB),
+            ),
+            (
+                "low_shelf",
+                BiquadCoefficients::low_shelf(SAMPLE_RATE, FREQUENCY, Q, GAIN_DB),
+            ),
+            (
+                "high_shelf",
+                BiquadCoefficients::high_shelf(SAMPLE_RATE, FREQUENCY, Q, GAIN_DB),
+            ),
+        ];
+
+        for (name, coefficients) in filters {
+            let biquad = std::cell::RefCell::new(Biquad::new(coefficients));
+            assert_magnitude_response(
+                name,
+                |sample| biquad.borrow_mut().process(sample),
+                || biquad.borrow_mut().reset(),
+            );
+        }
+    }
+
+    #[test]
+    fn dc_blocker() {
+        // A constant offset is removed completely
+        let mut dc_blocker = DcBlocker::new(SAMPLE_RATE);
+        let mut block = vec![0.5; SAMPLE_RATE as usize];
+        dc_blocker.process_block(&mut block);
+        assert!(block[0] > 0.49);
+        assert!(block.last().unwrap().abs() < 1e-4);
+
+        // While low frequencies pass through, also when they have a DC offset
+        dc_blocker.reset();
+        let gain = measured_magnitude(|sample| dc_blocker.process(sample), 40.0);
+        assert!(gain > 0.99, "Measured gain at 40 Hz: {gain}");
+
+        dc_blocker.reset();
+        let gain = measured_magnitude(|sample| dc_blocker.process(sample + 0.5), 100.0);
+        assert!((gain - 1.0).abs() < 0.01, "Measured gain at 100 Hz: {gain}");
+    }
+
+    #[test]
+    fn svf_magnitude_response() {
+        let filters = [
+            ("lowpass", SvfType::Lowpass),
+            ("highpass", SvfType::Highpass),
+            ("bandpass", SvfType::Bandpass),
+            ("notch", SvfType::Notch),
+            ("allpass", SvfType::Allpass),
+        ];
+
+        for (name, filter_type) in filters {
+            let svf = std::cell::RefCell::new(StateVariableFilter::new(
+                filter_type,
+                SAMPLE_RATE,
+                FREQUENCY,
+                Q,
+            ));
+            assert_magnitude_response(
+                name,
+                |sample| svf.borrow_mut().process(sample),
+                || svf.borrow_mut().reset(),
+            );
+        }
+    }
+}
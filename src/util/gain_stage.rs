@@ -0,0 +1,204 @@
+//! Input gain, output gain, and dry/wet mixing for effects.
+
+use crate::buffer::Buffer;
+use crate::params::smoothing::Smoother;
+use crate::util::equal_power_crossfade;
+
+/// The smoothers used by a [`GainStage`]. These are usually the `smoothed` fields of the plugin's
+/// input gain, output gain, and dry/wet parameters. The gains are linear gains, and the mix goes
+/// from `0.0` for a fully dry signal to `1.0` for a fully wet signal.
+#[derive(Debug, Clone, Copy)]
+pub struct GainStageSmoothers<'a> {
+    pub input_gain: &'a Smoother<f32>,
+    pub output_gain: &'a Smoother<f32>,
+    pub mix: &'a Smoother<f32>,
+}
+
+/// Applies an effect's input gain, output gain, and dry/wet mix in the right order:
+///
+/// 1. A copy of the unprocessed input is stored as the dry signal.
+/// 2. The input gain is applied.
+/// 3. The effect is applied using the closure passed to
+///    [`process_block()`][Self::process_block()] or [`process_frame()`][Self::process_frame()].
+/// 4. The output gain is applied to the wet signal.
+/// 5. The dry and wet signals are mixed using an [equal-power crossfade][equal_power_crossfade()].
+///
+/// Since neither gain is applied to the dry signal, a mix of `0.0` outputs the exact input. The
+/// smoothers are advanced once per sample, so they stay in sync with the audio regardless of which
+/// processing function is used.
+///
+/// The dry signal buffers are allocated in [`new()`][Self::new()] and [`resize()`][Self::resize()],
+/// which should be called from the plugin's `initialize()` function. Processing audio never
+/// allocates.
+#[derive(Debug, Clone, Default)]
+pub struct GainStage {
+    /// The unprocessed input for every channel, used by
+    /// [`process_block()`][Self::process_block()].
+    dry: Vec<Vec<f32>>,
+    /// The unprocessed input for a single sample, used by
+    /// [`process_frame()`][Self::process_frame()].
+    dry_frame: Vec<f32>,
+}
+
+impl GainStage {
+    /// Create a gain stage for `num_channels` channels that can process blocks of up to
+    /// `max_block_size` samples.
+    pub fn new(num_channels: usize, max_block_size: usize) -> Self {
+        let mut gain_stage = Self::default();
+        gain_stage.resize(num_channels, max_block_size);
+
+        gain_stage
+    }
+
+    /// Change the number of channels and the maximum block size. This allocates and should be
+    /// called from the plugin's `initialize()` function.
+    pub fn resize(&mut self, num_channels: usize, max_block_size: usize) {
+        self.dry
+            .resize_with(num_channels, || Vec::with_capacity(max_block_size));
+        for dry in &mut self.dry {
+            dry.resize(max_block_size, 0.0);
+        }
+        self.dry_frame.resize(num_channels, 0.0);
+    }
+
+    /// Process a single sample's channel values in place. `process` is called with the input
+    /// gain applied, and it should apply the effect to the frame in place.
+    pub fn process_frame(
+        &mut self,
+        frame: &mut [f32],
+        smoothers: GainStageSmoothers,
+        process: impl FnOnce(&mut [f32]),
+    ) {
+        nih_debug_assert_eq!(frame.len(), self.dry_frame.len());
+
+        let input_gain = smoothers.input_gain.next();
+        for (sample, dry) in frame.iter_mut().zip(self.dry_frame.iter_mut()) {
+            *dry = *sample;
+            *sample *= input_gain;
+        }
+
+        process(frame);
+
+        let output_gain = smoothers.output_gain.next();
+        let (dry_gain, wet_gain) = equal_power_crossfade(smoothers.mix.next());
+        for (sample, dry) in frame.iter_mut().zip(self.dry_frame.iter()) {
+            *sample = (*dry * dry_gain) + (*sample * output_gain * wet_gain);
+        }
+    }
+
+    /// Process an entire buffer in place. `process` is called with the input gain applied, and it
+    /// should apply the effect to the buffer in place.
+    ///
+    /// The buffer should not contain more samples than the maximum block size passed to
+    /// [`new()`][Self::new()] or [`resize()`][Self::resize()]. The dry signal is not available for
+    /// any samples past that point, so those samples will be output fully wet.
+    pub fn process_block(
+        &mut self,
+        buffer: &mut Buffer,
+        smoothers: GainStageSmoothers,
+        process: impl FnOnce(&mut Buffer),
+    ) {
+        nih_debug_assert_eq!(buffer.channels(), self.dry.len());
+        nih_debug_assert!(self.dry.iter().all(|dry| buffer.samples() <= dry.len()));
+
+        for (sample_idx, mut channel_samples) in buffer.iter_samples().enumerate() {
+            let input_gain = smoothers.input_gain.next();
+            for (sample, dry) in channel_samples.iter_mut().zip(self.dry.iter_mut()) {
+                if let Some(dry) = dry.get_mut(sample_idx) {
+                    *dry = *sample;
+                }
+                *sample *= input_gain;
+            }
+        }
+
+        process(buffer);
+
+        for (sample_idx, mut channel_samples) in buffer.iter_samples().enumerate() {
+            let output_gain = smoothers.output_gain.next();
+            let (dry_gain, wet_gain) = equal_power_crossfade(smoothers.mix.next());
+            for (sample, dry) in channel_samples.iter_mut().zip(self.dry.iter()) {
+                *sample = match dry.get(sample_idx) {
+                    Some(dry) => (*dry * dry_gain) + (*sample * output_gain * wet_gain),
+                    None => *sample * output_gain,
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::smoothing::SmoothingStyle;
+
+    const NUM_SAMPLES: usize = 64;
+
+    fn smoother(value: f32) -> Smoother<f32> {
+        let smoother = Smoother::new(SmoothingStyle::Linear(10.0));
+        smoother.reset(value);
+
+        smoother
+    }
+
+    fn input(channel_idx: usize) -> Vec<f32> {
+        (0..NUM_SAMPLES)
+            .map(|sample_idx| ((sample_idx + channel_idx) as f32 * 0.37).sin() * 0.8)
+            .collect()
+    }
+
+    #[test]
+    fn fully_dry_is_exact() {
+        let input_gain = smoother(4.0);
+        let output_gain = smoother(0.25);
+        let mix = smoother(0.0);
+        let smoothers = GainStageSmoothers {
+            input_gain: &input_gain,
+            output_gain: &output_gain,
+            mix: &mix,
+        };
+
+        let mut gain_stage = GainStage::new(2, NUM_SAMPLES);
+        let mut channels = vec![input(0), input(1)];
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.set_slices(NUM_SAMPLES, |output_slices| {
+                *output_slices = channels
+                    .iter_mut()
+                    .map(|channel| channel.as_mut_slice())
+                    .collect();
+            })
+        };
+        gain_stage.process_block(&mut buffer, smoothers, |buffer| {
+            for channel in buffer.as_slice() {
+                for sample in channel.iter_mut() {
+                    *sample = sample.tanh() + 1.0;
+                }
+            }
+        });
+        assert_eq!(channels, vec![input(0), input(1)]);
+
+        let mut frame = [0.5, -0.25];
+        gain_stage.process_frame(&mut frame, smoothers, |frame| frame.fill(1.0));
+        assert_eq!(frame, [0.5, -0.25]);
+    }
+
+    #[test]
+    fn fully_wet() {
+        let input_gain = smoother(2.0);
+        let output_gain = smoother(0.5);
+        let mix = smoother(1.0);
+        let smoothers = GainStageSmoothers {
+            input_gain: &input_gain,
+            output_gain: &output_gain,
+            mix: &mix,
+        };
+
+        let mut gain_stage = GainStage::new(1, NUM_SAMPLES);
+        let mut frame = [0.25];
+        gain_stage.process_frame(&mut frame, smoothers, |frame| {
+            assert_eq!(frame, [0.5]);
+            frame[0] += 1.0;
+        });
+        approx::assert_relative_eq!(frame[0], 0.75, epsilon = 1e-6);
+    }
+}
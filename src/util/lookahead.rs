@@ -0,0 +1,358 @@
+//! A lookahead peak limiter building block.
+
+use std::collections::VecDeque;
+
+use crate::buffer::Buffer;
+use crate::util::gain_to_db;
+
+/// Delays audio by a fixed lookahead time and computes a gain reduction envelope from the peaks in
+/// that lookahead window, so the gain is already fully reduced by the time a transient leaves the
+/// delay line. This is the core of a lookahead limiter, and it can also be used as the detector
+/// stage of other lookahead dynamics processors. All channels share a single envelope, so the
+/// stereo image is preserved.
+///
+/// The gain reduction envelope is computed in three stages:
+///
+/// 1. For every input sample, the gain needed to keep the loudest channel at the threshold is
+///    computed and held for the duration of the lookahead window.
+/// 2. When that gain rises again, it's smoothed using an exponential release.
+/// 3. The result is smoothed using a moving average over the lookahead window. This is the attack
+///    stage, so the attack time is always equal to the lookahead time.
+///
+/// Because the moving average only ever averages gains that are at or below the gain needed for the
+/// sample that's currently leaving the delay line, the output never exceeds the threshold.
+///
+/// The delayed signal is [`latency_samples()`][Self::latency_samples()] samples late, which
+/// should be reported to the host using
+/// [`InitContext::set_latency_samples()`][crate::prelude::InitContext::set_latency_samples()]. The
+/// buffers are allocated in [`new()`][Self::new()] and [`resize()`][Self::resize()], which should
+/// be called from the plugin's `initialize()` function. Processing audio never allocates.
+#[derive(Debug, Clone)]
+pub struct LookaheadLimiter {
+    /// The delay line for every channel. Only the first `lookahead_samples` samples are used.
+    delay_lines: Vec<Vec<f32>>,
+    /// The index in the delay lines to read the delayed sample from and to write the next sample
+    /// to.
+    delay_pos: usize,
+    max_lookahead_samples: usize,
+    lookahead_samples: usize,
+
+    /// The threshold as a linear gain.
+    threshold: f32,
+    /// The one pole coefficient for the release stage.
+    release_coefficient: f32,
+
+    /// The number of samples processed since the last reset, used to expire entries in
+    /// `hold_window`.
+    sample_idx: u64,
+    /// A monotonic queue containing `(sample_idx, gain)` pairs for computing the minimum gain over
+    /// the lookahead window. The gains are in increasing order. The capacity is reserved up front,
+    /// and the queue never contains more than `lookahead_samples + 1` entries.
+    hold_window: VecDeque<(u64, f32)>,
+    /// The output of the release stage.
+    release_envelope: f32,
+    /// The last `lookahead_samples + 1` outputs of the release stage for the moving average.
+    average_buffer: Vec<f32>,
+    average_pos: usize,
+    /// The sum of the values in `average_buffer`. This uses double precision to avoid accumulating
+    /// rounding errors.
+    average_sum: f64,
+    /// The gain applied to the most recently processed sample.
+    current_gain: f32,
+
+    /// Scratch space for copying a sample's channel values in [`process()`][Self::process()].
+    frame: Vec<f32>,
+}
+
+impl LookaheadLimiter {
+    /// Create a limiter for `num_channels` channels that supports lookahead times of up to
+    /// `max_lookahead_samples` samples. The lookahead is initially set to the maximum, and the
+    /// threshold is initially set to 0 dBFS with a 100 ms release at 44.1 kHz.
+    pub fn new(num_channels: usize, max_lookahead_samples: usize) -> Self {
+        let mut limiter = Self {
+            delay_lines: Vec::new(),
+            delay_pos: 0,
+            max_lookahead_samples: 0,
+            lookahead_samples: 0,
+
+            threshold: 1.0,
+            release_coefficient: 0.0,
+
+            sample_idx: 0,
+            hold_window: VecDeque::new(),
+            release_envelope: 1.0,
+            average_buffer: Vec::new(),
+            average_pos: 0,
+            average_sum: 0.0,
+            current_gain: 1.0,
+
+            frame: Vec::new(),
+        };
+        limiter.resize(num_channels, max_lookahead_samples);
+        limiter.set_release(44_100.0, 100.0);
+
+        limiter
+    }
+
+    /// Change the number of channels and the maximum lookahead time. This sets the lookahead to the
+    /// new maximum and it clears the limiter's state. This allocates, so it should be called from
+    /// the plugin's `initialize()` function, for instance when the sample rate changes.
+    pub fn resize(&mut self, num_channels: usize, max_lookahead_samples: usize) {
+        self.delay_lines
+            .resize_with(num_channels, || Vec::with_capacity(max_lookahead_samples));
+        for delay_line in &mut self.delay_lines {
+            delay_line.clear();
+            delay_line.resize(max_lookahead_samples, 0.0);
+        }
+        self.max_lookahead_samples = max_lookahead_samples;
+
+        self.hold_window.clear();
+        self.hold_window.reserve(max_lookahead_samples + 1);
+        self.average_buffer.clear();
+        self.average_buffer.resize(max_lookahead_samples + 1, 1.0);
+        self.frame.clear();
+        self.frame.resize(num_channels, 0.0);
+
+        self.lookahead_samples = max_lookahead_samples;
+        self.reset();
+    }
+
+    /// Change the lookahead time. This is clamped to the maximum lookahead time. Changing the
+    /// lookahead also changes the latency, so the plugin should report the new latency to the host
+    /// afterwards. This clears the limiter's state if the lookahead time changes.
+    pub fn set_lookahead(&mut self, lookahead_samples: usize) {
+        let lookahead_samples = lookahead_samples.min(self.max_lookahead_samples);
+        if lookahead_samples != self.lookahead_samples {
+            self.lookahead_samples = lookahead_samples;
+            self.reset();
+        }
+    }
+
+    /// The limiter's latency in samples. This is always equal to the lookahead time.
+    pub fn latency_samples(&self) -> u32 {
+        self.lookahead_samples as u32
+    }
+
+    /// Set the threshold as a linear gain. Use [`db_to_gain()`][crate::util::db_to_gain()] to set
+    /// the threshold in decibels.
+    pub fn set_threshold(&mut self, threshold: f32) {
+        nih_debug_assert!(threshold > 0.0);
+
+        self.threshold = threshold;
+    }
+
+    /// Set the release time, which is the time it takes for the gain to recover by about 63%
+    /// after a peak has passed.
+    pub fn set_release(&mut self, sample_rate: f32, release_ms: f32) {
+        self.release_coefficient = if release_ms > 0.0 {
+            (-1.0 / (release_ms / 1000.0 * sample_rate)).exp()
+        } else {
+            0.0
+        };
+    }
+
+    /// The gain that was applied to the most recently processed sample.
+    pub fn current_gain(&self) -> f32 {
+        self.current_gain
+    }
+
+    /// The current gain reduction in decibels, as a positive value. This can be passed directly to
+    /// [`ProcessContext::set_gain_reduction()`][crate::prelude::ProcessContext::set_gain_reduction()].
+    pub fn gain_reduction_db(&self) -> f32 {
+        -gain_to_db(self.current_gain)
+    }
+
+    /// Clear the delay lines and the gain reduction envelope. This should be called from the
+    /// plugin's `reset()` function.
+    pub fn reset(&mut self) {
+        for delay_line in &mut self.delay_lines {
+            delay_line.fill(0.0);
+        }
+        self.delay_pos = 0;
+
+        self.sample_idx = 0;
+        self.hold_window.clear();
+        self.release_envelope = 1.0;
+        self.average_buffer.fill(1.0);
+        self.average_pos = 0;
+        self.average_sum = (self.lookahead_samples + 1) as f64;
+        self.current_gain = 1.0;
+    }
+
+    /// Process a single sample for every channel in place. `frame` contains one value per channel,
+    /// and it must have the same number of channels the limiter was created with. The frame is
+    /// replaced with the delayed and limited samples. Returns the gain that was applied.
+    pub fn process_frame(&mut self, frame: &mut [f32]) -> f32 {
+        nih_debug_assert_eq!(frame.len(), self.delay_lines.len());
+
+        let peak = frame
+            .iter()
+            .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        let gain = self.next_gain(peak);
+
+        if self.lookahead_samples == 0 {
+            for sample in frame.iter_mut() {
+                *sample *= gain;
+            }
+        } else {
+            for (sample, delay_line) in frame.iter_mut().zip(&mut self.delay_lines) {
+                let delayed = delay_line[self.delay_pos];
+                delay_line[self.delay_pos] = *sample;
+                *sample = delayed * gain;
+            }
+
+            self.delay_pos += 1;
+            if self.delay_pos == self.lookahead_samples {
+                self.delay_pos = 0;
+            }
+        }
+
+        gain
+    }
+
+    /// Process an entire buffer in place. The buffer must have the same number of channels the
+    /// limiter was created with.
+    pub fn process(&mut self, buffer: &mut Buffer) {
+        nih_debug_assert_eq!(buffer.channels(), self.delay_lines.len());
+
+        let mut frame = std::mem::take(&mut self.frame);
+        for mut channel_samples in buffer.iter_samples() {
+            for (value, sample) in frame.iter_mut().zip(channel_samples.iter_mut()) {
+                *value = *sample;
+            }
+
+            self.process_frame(&mut frame);
+
+            for (value, sample) in frame.iter().zip(channel_samples.iter_mut()) {
+                *sample = *value;
+            }
+        }
+        self.frame = frame;
+    }
+
+    /// Compute the gain for the sample leaving the delay line after `peak` has been added to the
+    /// lookahead window.
+    fn next_gain(&mut self, peak: f32) -> f32 {
+        let window_len = self.lookahead_samples as u64 + 1;
+
+        // The hold stage computes the minimum gain over the lookahead window
+        let required_gain = if peak > self.threshold {
+            self.threshold / peak
+        } else {
+            1.0
+        };
+        while matches!(self.hold_window.back(), Some((_, gain)) if *gain >= required_gain) {
+            self.hold_window.pop_back();
+        }
+        self.hold_window.push_back((self.sample_idx, required_gain));
+        while matches!(self.hold_window.front(), Some((idx, _)) if idx + window_len <= self.sample_idx)
+        {
+            self.hold_window.pop_front();
+        }
+        let held_gain = self.hold_window.front().map_or(1.0, |(_, gain)| *gain);
+        self.sample_idx += 1;
+
+        // The release stage is instant when the gain needs to go down
+        self.release_envelope = if held_gain < self.release_envelope {
+            held_gain
+        } else {
+            held_gain + (self.release_envelope - held_gain) * self.release_coefficient
+        };
+
+        // The attack stage is a moving average over the lookahead window
+        let average_len = self.lookahead_samples + 1;
+        self.average_sum +=
+            self.release_envelope as f64 - self.average_buffer[self.average_pos] as f64;
+        self.average_buffer[self.average_pos] = self.release_envelope;
+        self.average_pos += 1;
+        if self.average_pos == average_len {
+            self.average_pos = 0;
+        }
+
+        self.current_gain = (self.average_sum / average_len as f64).min(1.0) as f32;
+        self.current_gain
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOOKAHEAD: usize = 64;
+
+    #[test]
+    fn latency() {
+        let mut limiter = LookaheadLimiter::new(2, LOOKAHEAD);
+        assert_eq!(limiter.latency_samples(), LOOKAHEAD as u32);
+
+        // Signals below the threshold are only delayed
+        for i in 0..256usize {
+            let mut frame = [i as f32 / 1000.0, -(i as f32) / 1000.0];
+            let gain = limiter.process_frame(&mut frame);
+
+            let expected = i.saturating_sub(LOOKAHEAD) as f32 / 1000.0;
+            assert_eq!(gain, 1.0);
+            assert_eq!(frame, [expected, -expected]);
+        }
+    }
+
+    #[test]
+    fn transient_alignment() {
+        let mut limiter = LookaheadLimiter::new(1, LOOKAHEAD);
+        limiter.set_threshold(0.5);
+        limiter.set_release(48_000.0, 1.0);
+
+        let transient_idx = 100;
+        for i in 0..1000 {
+            let mut frame = [if i == transient_idx { 1.0 } else { 0.0 }];
+            let gain = limiter.process_frame(&mut frame);
+
+            // The gain should be fully reduced exactly when the transient leaves the delay line
+            if i == transient_idx + LOOKAHEAD {
+                assert_eq!(frame[0], 0.5);
+                assert_eq!(gain, 0.5);
+            } else {
+                assert_eq!(frame[0], 0.0);
+            }
+
+            // And the gain reduction should start when the transient enters the delay line
+            if i < transient_idx {
+                assert_eq!(gain, 1.0);
+            } else if i < transient_idx + LOOKAHEAD {
+                assert!(gain < 1.0 && gain > 0.5, "{i}: {gain}");
+            }
+        }
+
+        // After the release the gain should have recovered
+        assert!(limiter.current_gain() > 0.99);
+    }
+
+    #[test]
+    fn threshold_is_never_exceeded() {
+        let mut limiter = LookaheadLimiter::new(2, LOOKAHEAD);
+        limiter.set_threshold(0.25);
+        limiter.set_release(48_000.0, 10.0);
+
+        for i in 0..48_000 {
+            let sample = (i as f32 * 0.05).sin() * (1.0 + (i as f32 * 0.001).sin());
+            let mut frame = [sample, sample * 0.5];
+            limiter.process_frame(&mut frame);
+
+            assert!(frame[0].abs() <= 0.25 + 1e-6, "{i}: {}", frame[0]);
+        }
+
+        assert!(limiter.gain_reduction_db() > 0.0);
+    }
+
+    #[test]
+    fn no_lookahead() {
+        let mut limiter = LookaheadLimiter::new(1, LOOKAHEAD);
+        limiter.set_lookahead(0);
+        limiter.set_threshold(0.5);
+        assert_eq!(limiter.latency_samples(), 0);
+
+        let mut frame = [2.0];
+        assert_eq!(limiter.process_frame(&mut frame), 0.25);
+        assert_eq!(frame, [0.5]);
+    }
+}
@@ -0,0 +1,181 @@
+//! Lookup tables for expensive functions.
+
+/// A table containing precomputed values of a function, like `tanh()` or `exp()`, over a fixed
+/// domain. [`get()`][Self::get()] interpolates between the precomputed values, which is usually
+/// much cheaper than calling the function itself. Inputs outside of the domain are clamped to the
+/// domain.
+///
+/// The table is filled in [`new()`][Self::new()] and [`initialize()`][Self::initialize()], which
+/// should be called from the plugin's `initialize()` function. Reading from the table never
+/// allocates.
+///
+/// ```
+/// # use nih_plug::util::{LookupTable, TableInterpolation};
+/// let table = LookupTable::new(f32::tanh, -5.0, 5.0, 1024)
+///     .with_interpolation(TableInterpolation::Cubic);
+/// assert!((table.get(0.5) - 0.5f32.tanh()).abs() < 1e-5);
+/// assert_eq!(table.get(10.0), table.get(5.0));
+/// ```
+#[derive(Debug, Clone)]
+pub struct LookupTable {
+    /// The function's values at `size` evenly spaced points spanning the domain. These are
+    /// surrounded by two linearly extrapolated values so the cubic interpolation also works at the
+    /// edges of the domain without evaluating the function outside of it.
+    values: Vec<f32>,
+    min: f32,
+    max: f32,
+    /// The number of table entries per unit of input.
+    scale: f32,
+    interpolation: TableInterpolation,
+}
+
+/// The interpolation used by [`LookupTable::get()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TableInterpolation {
+    /// Linearly interpolate between the two nearest values. This is the cheapest option, and it
+    /// is accurate enough for most functions if the table is large enough.
+    #[default]
+    Linear,
+    /// Four point, third order Hermite interpolation. This is more expensive than linear
+    /// interpolation, but it's much more accurate for smooth functions, so the table can be a lot
+    /// smaller.
+    Cubic,
+}
+
+impl LookupTable {
+    /// Create a table containing `size` values of `function` evenly spaced over `[min, max]`. The
+    /// table uses linear interpolation by default. `size` must be at least two, and `max` must be
+    /// greater than `min`.
+    pub fn new(function: impl Fn(f32) -> f32, min: f32, max: f32, size: usize) -> Self {
+        let mut table = Self {
+            values: Vec::new(),
+            min: 0.0,
+            max: 0.0,
+            scale: 0.0,
+            interpolation: TableInterpolation::default(),
+        };
+        table.initialize(function, min, max, size);
+
+        table
+    }
+
+    /// Use a different interpolation method for [`get()`][Self::get()].
+    pub fn with_interpolation(mut self, interpolation: TableInterpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// Change the interpolation method used for [`get()`][Self::get()].
+    pub fn set_interpolation(&mut self, interpolation: TableInterpolation) {
+        self.interpolation = interpolation;
+    }
+
+    /// Fill the table with a new function, domain, or size. This may allocate, so it should be
+    /// called from the plugin's `initialize()` function. See [`new()`][Self::new()].
+    pub fn initialize(&mut self, function: impl Fn(f32) -> f32, min: f32, max: f32, size: usize) {
+        nih_debug_assert!(size >= 2);
+        nih_debug_assert!(max > min);
+        let size = size.max(2);
+
+        self.values.clear();
+        self.values.reserve(size + 2);
+        self.values.push(0.0);
+        self.values.extend((0..size).map(|i| {
+            let x = min as f64 + (max as f64 - min as f64) * (i as f64 / (size - 1) as f64);
+            function(x as f32)
+        }));
+        self.values[0] = 2.0 * self.values[1] - self.values[2];
+        self.values
+            .push(2.0 * self.values[size] - self.values[size - 1]);
+
+        self.min = min;
+        self.max = max;
+        self.scale = (size - 1) as f32 / (max - min);
+    }
+
+    /// The domain the function was evaluated over, as a `(min, max)` pair.
+    pub fn domain(&self) -> (f32, f32) {
+        (self.min, self.max)
+    }
+
+    /// The number of precomputed function values.
+    pub fn size(&self) -> usize {
+        self.values.len() - 2
+    }
+
+    /// Look up the function's value at `x` using the table's interpolation method. `x` is clamped
+    /// to the table's domain.
+    #[inline]
+    pub fn get(&self, x: f32) -> f32 {
+        let last_idx = self.values.len() - 3;
+        let position = ((x - self.min) * self.scale).clamp(0.0, last_idx as f32);
+        // The last value is interpolated from the second to last value with a fraction of one
+        let idx = (position as usize).min(last_idx - 1);
+        let frac = position - idx as f32;
+
+        // `self.values[idx + 1]` is the value at `idx` because of the extrapolated first value
+        let y0 = self.values[idx + 1];
+        let y1 = self.values[idx + 2];
+        match self.interpolation {
+            TableInterpolation::Linear => y0 + (y1 - y0) * frac,
+            TableInterpolation::Cubic => {
+                let y_prev = self.values[idx];
+                let y2 = self.values[idx + 3];
+
+                let c1 = 0.5 * (y1 - y_prev);
+                let c2 = y_prev - 2.5 * y0 + 2.0 * y1 - 0.5 * y2;
+                let c3 = 0.5 * (y2 - y_prev) + 1.5 * (y0 - y1);
+
+                ((c3 * frac + c2) * frac + c1) * frac + y0
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The largest error between the table and `function` over the table's domain.
+    fn max_error(table: &LookupTable, function: impl Fn(f32) -> f32) -> f32 {
+        let (min, max) = table.domain();
+        (0..=10_000)
+            .map(|i| {
+                let x = min + (max - min) * (i as f32 / 10_000.0);
+                (table.get(x) - function(x)).abs()
+            })
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn interpolation_accuracy() {
+        let linear = LookupTable::new(f32::tanh, -5.0, 5.0, 512);
+        let cubic = linear.clone().with_interpolation(TableInterpolation::Cubic);
+        let linear_error = max_error(&linear, f32::tanh);
+        let cubic_error = max_error(&cubic, f32::tanh);
+        assert!(linear_error < 1e-4, "Linear error was {linear_error}");
+        assert!(cubic_error < 1e-5, "Cubic error was {cubic_error}");
+
+        let sine = LookupTable::new(f32::sin, 0.0, std::f32::consts::TAU, 256)
+            .with_interpolation(TableInterpolation::Cubic);
+        let sine_error = max_error(&sine, f32::sin);
+        assert!(sine_error < 1e-5, "Sine error was {sine_error}");
+    }
+
+    #[test]
+    fn domain_is_clamped() {
+        let table = LookupTable::new(f32::exp, -1.0, 1.0, 64);
+        assert_eq!(table.size(), 64);
+        approx::assert_relative_eq!(table.get(-1.0), (-1.0f32).exp());
+        approx::assert_relative_eq!(table.get(1.0), 1.0f32.exp());
+        assert_eq!(table.get(-100.0), table.get(-1.0));
+        assert_eq!(table.get(100.0), table.get(1.0));
+
+        // The table values are hit exactly at the sample points
+        let table =
+            LookupTable::new(|x| x * x, 0.0, 4.0, 5).with_interpolation(TableInterpolation::Cubic);
+        for x in [0.0, 1.0, 2.0, 3.0, 4.0] {
+            assert_eq!(table.get(x), x * x);
+        }
+    }
+}
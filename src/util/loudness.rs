@@ -0,0 +1,508 @@
+//! Loudness measurements as described in ITU-R BS.1770 and EBU R 128.
+
+use atomic_float::AtomicF32;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::audio_setup::{ChannelLayout, Speaker};
+use crate::buffer::Buffer;
+use crate::util::MINUS_INFINITY_DB;
+
+/// The number of 100 ms steps in the momentary loudness window.
+const MOMENTARY_STEPS: usize = 4;
+/// The number of 100 ms steps in the short-term loudness window.
+const SHORT_TERM_STEPS: usize = 30;
+
+/// Gating blocks at or below this loudness are ignored for the integrated loudness.
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+/// Gating blocks more than this many LU below the ungated loudness are ignored for the integrated
+/// loudness.
+const RELATIVE_GATE_LU: f64 = -10.0;
+
+/// The resolution of the histogram used to compute the integrated loudness, in LU.
+const HISTOGRAM_RESOLUTION_LU: f64 = 0.01;
+/// The number of bins in the histogram. This covers the range from [`ABSOLUTE_GATE_LUFS`] up to
+/// +10 LUFS. Louder gating blocks are put in the last bin.
+const HISTOGRAM_BINS: usize = 8000;
+
+/// Measures the momentary, short-term, and integrated loudness of a signal in LUFS as described in
+/// ITU-R BS.1770-4 and EBU R 128. The signal is K-weighted, and the mean square powers of the
+/// channels are weighted according to their speaker positions and summed:
+///
+/// - The momentary loudness uses a 400 ms window.
+/// - The short-term loudness uses a 3 second window.
+/// - The integrated loudness covers everything since the last reset. It's computed from
+///   overlapping 400 ms gating blocks, ignoring blocks below -70 LUFS and blocks that are more
+///   than 10 LU below the loudness of the remaining blocks.
+///
+/// All three values are updated every 100 ms and are published to a [`LoudnessReadings`] object
+/// that can be shared with the editor. Until enough audio has been processed to fill a window, the
+/// missing part of the window is treated as silence. The gating blocks are stored in a histogram
+/// with a resolution of 0.01 LU, so the integrated loudness can be measured over any length of
+/// time in constant memory.
+///
+/// All buffers are allocated in [`new()`][Self::new()] and [`resize()`][Self::resize()], which
+/// should be called from the plugin's `initialize()` function. Processing audio never allocates.
+///
+/// ```ignore
+/// // In `initialize()`
+/// let layout = audio_io_layout.main_input_layout().unwrap_or(ChannelLayout::Stereo);
+/// self.loudness_meter.resize(buffer_config.sample_rate, layout);
+///
+/// // In `process()`
+/// self.loudness_meter.process(buffer);
+///
+/// // In the editor
+/// let short_term_lufs = loudness_readings.short_term();
+/// ```
+#[derive(Debug)]
+pub struct LoudnessMeter {
+    /// The K-weighting filter for every channel.
+    filters: Vec<KWeighting>,
+    /// The weight applied to every channel's mean square power.
+    channel_weights: Vec<f64>,
+
+    /// The length of a 100 ms step in samples.
+    step_len: usize,
+    /// The number of samples that have been added to `step_sum`.
+    step_pos: usize,
+    /// The sum of the channels' weighted squared K-weighted samples in the current step.
+    step_sum: f64,
+    /// The weighted mean square power of the most recent steps. This is a ring buffer with
+    /// `step_powers_pos` pointing to the oldest step.
+    step_powers: [f64; SHORT_TERM_STEPS],
+    step_powers_pos: usize,
+    /// The number of steps since the integrated loudness was last reset. Gating blocks are only
+    /// added to the histogram once this covers an entire block.
+    num_steps: usize,
+
+    /// The number of gating blocks in every bin, and the sum of those blocks' powers.
+    histogram: Vec<(u64, f64)>,
+
+    readings: Arc<LoudnessReadings>,
+}
+
+/// The most recent [`LoudnessMeter`] readings in LUFS, shared with the editor. Use
+/// [`LoudnessMeter::readings()`] to get this object. The readings are [`MINUS_INFINITY_DB`] for
+/// silence.
+#[derive(Debug)]
+pub struct LoudnessReadings {
+    momentary: AtomicF32,
+    short_term: AtomicF32,
+    integrated: AtomicF32,
+    /// Set by [`request_reset()`][Self::request_reset()] and handled by the meter the next time it
+    /// processes audio.
+    reset_requested: AtomicBool,
+}
+
+/// The two K-weighting stages for a single channel. This uses double precision because the
+/// high-pass stage's poles are very close to the unit circle.
+#[derive(Debug, Clone)]
+struct KWeighting {
+    /// The `[b0, b1, b2, a1, a2]` coefficients of the high shelf and high-pass stages.
+    coefficients: [[f64; 5]; 2],
+    /// The transposed direct form II state for both stages.
+    state: [[f64; 2]; 2],
+}
+
+impl Default for LoudnessReadings {
+    fn default() -> Self {
+        Self {
+            momentary: AtomicF32::new(MINUS_INFINITY_DB),
+            short_term: AtomicF32::new(MINUS_INFINITY_DB),
+            integrated: AtomicF32::new(MINUS_INFINITY_DB),
+            reset_requested: AtomicBool::new(false),
+        }
+    }
+}
+
+impl LoudnessReadings {
+    /// The momentary loudness over the last 400 ms.
+    pub fn momentary(&self) -> f32 {
+        self.momentary.load(Ordering::Relaxed)
+    }
+
+    /// The short-term loudness over the last 3 seconds.
+    pub fn short_term(&self) -> f32 {
+        self.short_term.load(Ordering::Relaxed)
+    }
+
+    /// The gated integrated loudness since the last reset.
+    pub fn integrated(&self) -> f32 {
+        self.integrated.load(Ordering::Relaxed)
+    }
+
+    /// Ask the meter to restart the integrated loudness measurement. This is handled the next time
+    /// the meter processes audio, so it can be called from the editor.
+    pub fn request_reset(&self) {
+        self.reset_requested.store(true, Ordering::Relaxed);
+    }
+}
+
+impl LoudnessMeter {
+    /// Create a loudness meter for a signal with the specified channel layout.
+    pub fn new(sample_rate: f32, layout: ChannelLayout) -> Self {
+        let mut meter = Self {
+            filters: Vec::new(),
+            channel_weights: Vec::new(),
+
+            step_len: 1,
+            step_pos: 0,
+            step_sum: 0.0,
+            step_powers: [0.0; SHORT_TERM_STEPS],
+            step_powers_pos: 0,
+            num_steps: 0,
+
+            histogram: vec![(0, 0.0); HISTOGRAM_BINS],
+
+            readings: Arc::new(LoudnessReadings::default()),
+        };
+        meter.resize(sample_rate, layout);
+
+        meter
+    }
+
+    /// Change the sample rate and the channel layout. This clears the meter's state. This
+    /// allocates, so it should be called from the plugin's `initialize()` function.
+    pub fn resize(&mut self, sample_rate: f32, layout: ChannelLayout) {
+        self.filters.clear();
+        self.filters
+            .resize(layout.speakers().len(), KWeighting::new(sample_rate));
+        self.channel_weights.clear();
+        self.channel_weights.extend(
+            layout
+                .speakers()
+                .iter()
+                .map(|speaker| channel_weight(layout, *speaker)),
+        );
+
+        self.step_len = ((sample_rate / 10.0).round() as usize).max(1);
+        self.reset();
+    }
+
+    /// The object containing the meter's readings. This can be shared with the editor.
+    pub fn readings(&self) -> Arc<LoudnessReadings> {
+        self.readings.clone()
+    }
+
+    /// Clear all measurements, including the integrated loudness. This should be called from the
+    /// plugin's `reset()` function.
+    pub fn reset(&mut self) {
+        for filter in &mut self.filters {
+            filter.reset();
+        }
+
+        self.step_pos = 0;
+        self.step_sum = 0.0;
+        self.step_powers.fill(0.0);
+        self.step_powers_pos = 0;
+        self.reset_integrated();
+
+        self.readings
+            .momentary
+            .store(MINUS_INFINITY_DB, Ordering::Relaxed);
+        self.readings
+            .short_term
+            .store(MINUS_INFINITY_DB, Ordering::Relaxed);
+    }
+
+    /// Restart the integrated loudness measurement without affecting the momentary and short-term
+    /// loudness. See also [`LoudnessReadings::request_reset()`].
+    pub fn reset_integrated(&mut self) {
+        self.num_steps = 0;
+        self.histogram.fill((0, 0.0));
+
+        self.readings
+            .integrated
+            .store(MINUS_INFINITY_DB, Ordering::Relaxed);
+        self.readings
+            .reset_requested
+            .store(false, Ordering::Relaxed);
+    }
+
+    /// Process an entire buffer. The buffer must have the same number of channels as the meter's
+    /// channel layout.
+    pub fn process(&mut self, buffer: &Buffer) {
+        nih_debug_assert_eq!(buffer.channels(), self.filters.len());
+
+        if self.readings.reset_requested.load(Ordering::Relaxed) {
+            self.reset_integrated();
+        }
+
+        let channels = buffer.as_slice_immutable();
+        for sample_idx in 0..buffer.samples() {
+            for ((channel, filter), weight) in channels
+                .iter()
+                .zip(&mut self.filters)
+                .zip(&self.channel_weights)
+            {
+                let filtered = filter.process(channel[sample_idx] as f64);
+                self.step_sum += filtered * filtered * weight;
+            }
+
+            self.step_pos += 1;
+            if self.step_pos == self.step_len {
+                self.finish_step();
+            }
+        }
+    }
+
+    /// Store the current step's power, update the readings, and add the new gating block to the
+    /// histogram.
+    fn finish_step(&mut self) {
+        self.step_powers[self.step_powers_pos] = self.step_sum / self.step_len as f64;
+        self.step_powers_pos = (self.step_powers_pos + 1) % SHORT_TERM_STEPS;
+        self.step_pos = 0;
+        self.step_sum = 0.0;
+
+        let momentary_power = self.recent_power(MOMENTARY_STEPS);
+        let short_term_power = self.recent_power(SHORT_TERM_STEPS);
+        self.readings
+            .momentary
+            .store(power_to_lufs(momentary_power) as f32, Ordering::Relaxed);
+        self.readings
+            .short_term
+            .store(power_to_lufs(short_term_power) as f32, Ordering::Relaxed);
+
+        // The gating blocks are the momentary loudness windows, which overlap by 75%
+        self.num_steps += 1;
+        if self.num_steps >= MOMENTARY_STEPS {
+            let block_loudness = power_to_lufs(momentary_power);
+            if block_loudness > ABSOLUTE_GATE_LUFS {
+                let bin = &mut self.histogram[histogram_bin(block_loudness)];
+                bin.0 += 1;
+                bin.1 += momentary_power;
+
+                self.readings
+                    .integrated
+                    .store(self.integrated_loudness() as f32, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// The mean power of the last `num_steps` steps.
+    fn recent_power(&self, num_steps: usize) -> f64 {
+        let sum: f64 = (1..=num_steps)
+            .map(|offset| {
+                self.step_powers
+                    [(self.step_powers_pos + SHORT_TERM_STEPS - offset) % SHORT_TERM_STEPS]
+            })
+            .sum();
+
+        sum / num_steps as f64
+    }
+
+    /// Compute the integrated loudness from the gating blocks in the histogram.
+    fn integrated_loudness(&self) -> f64 {
+        let (count, power) = sum_bins(&self.histogram);
+        if count == 0 {
+            return MINUS_INFINITY_DB as f64;
+        }
+
+        let relative_gate = power_to_lufs(power / count as f64) + RELATIVE_GATE_LU;
+        let first_bin = if relative_gate > ABSOLUTE_GATE_LUFS {
+            histogram_bin(relative_gate)
+        } else {
+            0
+        };
+        let (count, power) = sum_bins(&self.histogram[first_bin..]);
+        if count == 0 {
+            return MINUS_INFINITY_DB as f64;
+        }
+
+        power_to_lufs(power / count as f64)
+    }
+}
+
+impl KWeighting {
+    /// Compute the filter coefficients for the sample rate. The analog prototypes for the two stages
+    /// are taken from libebur128 and match the coefficients from ITU-R BS.1770 at 48 kHz.
+    fn new(sample_rate: f32) -> Self {
+        let sample_rate = sample_rate as f64;
+
+        // The high shelf models the acoustic effect of the listener's head
+        let shelf_frequency = 1_681.974_450_955_533;
+        let shelf_gain_db = 3.999_843_853_973_347;
+        let shelf_q = 0.707_175_236_955_419_6;
+        let k = (std::f64::consts::PI * shelf_frequency / sample_rate).tan();
+        let vh = 10.0f64.powf(shelf_gain_db / 20.0);
+        let vb = vh.powf(0.499_666_774_154_541_6);
+        let a0 = 1.0 + k / shelf_q + k * k;
+        let shelf = [
+            (vh + vb * k / shelf_q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / shelf_q + k * k) / a0,
+            2.0 * (k * k - 1.0) / a0,
+            (1.0 - k / shelf_q + k * k) / a0,
+        ];
+
+        // The revised low-frequency B-curve high-pass filter
+        let highpass_frequency = 38.135_470_876_024_44;
+        let highpass_q = 0.500_327_037_323_877_3;
+        let k = (std::f64::consts::PI * highpass_frequency / sample_rate).tan();
+        let a0 = 1.0 + k / highpass_q + k * k;
+        let highpass = [
+            1.0,
+            -2.0,
+            1.0,
+            2.0 * (k * k - 1.0) / a0,
+            (1.0 - k / highpass_q + k * k) / a0,
+        ];
+
+        Self {
+            coefficients: [shelf, highpass],
+            state: [[0.0; 2]; 2],
+        }
+    }
+
+    fn reset(&mut self) {
+        self.state = [[0.0; 2]; 2];
+    }
+
+    #[inline]
+    fn process(&mut self, sample: f64) -> f64 {
+        self.coefficients.iter().zip(&mut self.state).fold(
+            sample,
+            |input, ([b0, b1, b2, a1, a2], [s1, s2])| {
+                let output = b0 * input + *s1;
+                *s1 = b1 * input - a1 * output + *s2;
+                *s2 = b2 * input - a2 * output;
+
+                output
+            },
+        )
+    }
+}
+
+/// The weight for a speaker's mean square power. Following ITU-R BS.1770-4, the LFE channel is
+/// ignored and speakers at ear height between 60 and 120 degrees from the front get a weight of
+/// 1.41. That includes the surround channels in 5.x layouts and the side surround channels in 7.x
+/// layouts, but not the 7.x rear surround channels.
+fn channel_weight(layout: ChannelLayout, speaker: Speaker) -> f64 {
+    let has_side_speakers = layout.speakers().contains(&Speaker::SideLeft);
+    match speaker {
+        Speaker::Lfe => 0.0,
+        Speaker::BackLeft | Speaker::BackRight if !has_side_speakers => 1.41,
+        Speaker::SideLeft | Speaker::SideRight => 1.41,
+        _ => 1.0,
+    }
+}
+
+/// Convert a weighted mean square power to LUFS.
+fn power_to_lufs(power: f64) -> f64 {
+    if power > 0.0 {
+        (-0.691 + 10.0 * power.log10()).max(MINUS_INFINITY_DB as f64)
+    } else {
+        MINUS_INFINITY_DB as f64
+    }
+}
+
+/// The histogram bin for a gating block that's louder than the absolute gate.
+fn histogram_bin(loudness: f64) -> usize {
+    (((loudness - ABSOLUTE_GATE_LUFS) / HISTOGRAM_RESOLUTION_LU) as usize).min(HISTOGRAM_BINS - 1)
+}
+
+/// The total number of gating blocks in a part of the histogram and the sum of their powers.
+fn sum_bins(bins: &[(u64, f64)]) -> (u64, f64) {
+    bins.iter()
+        .fold((0, 0.0), |(count, power), (bin_count, bin_power)| {
+            (count + bin_count, power + bin_power)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::db_to_gain;
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+
+    /// Feed the meter one second blocks of a 1 kHz sine wave with the specified peak level in
+    /// dBFS on every channel in `channels`. Every other channel is silent.
+    fn process_sine(meter: &mut LoudnessMeter, level_db: f32, seconds: usize, channels: &[usize]) {
+        let block_len = SAMPLE_RATE as usize;
+        let num_channels = meter.filters.len();
+        let gain = db_to_gain(level_db);
+        let mut data = vec![vec![0.0f32; block_len]; num_channels];
+        for _ in 0..seconds {
+            for (i, sample) in data[channels[0]].iter_mut().enumerate() {
+                *sample = (i as f32 * std::f32::consts::TAU * 1000.0 / SAMPLE_RATE).sin() * gain;
+            }
+            for channel in &channels[1..] {
+                data[*channel] = data[channels[0]].clone();
+            }
+
+            let mut buffer = Buffer::default();
+            unsafe {
+                buffer.set_slices(block_len, |output_slices| {
+                    *output_slices = data
+                        .iter_mut()
+                        .map(|channel| channel.as_mut_slice())
+                        .collect();
+                })
+            };
+            meter.process(&buffer);
+        }
+    }
+
+    #[test]
+    fn stereo_sine() {
+        // EBU Tech 3341 test case 1: a stereo 1 kHz sine wave at -23 dBFS reads -23 LUFS
+        let mut meter = LoudnessMeter::new(SAMPLE_RATE, ChannelLayout::Stereo);
+        let readings = meter.readings();
+        process_sine(&mut meter, -23.0, 20, &[0, 1]);
+
+        approx::assert_relative_eq!(readings.momentary(), -23.0, epsilon = 0.1);
+        approx::assert_relative_eq!(readings.short_term(), -23.0, epsilon = 0.1);
+        approx::assert_relative_eq!(readings.integrated(), -23.0, epsilon = 0.1);
+
+        readings.request_reset();
+        process_sine(&mut meter, -33.0, 1, &[0, 1]);
+        approx::assert_relative_eq!(readings.integrated(), -33.0, epsilon = 0.1);
+
+        meter.reset();
+        assert_eq!(readings.momentary(), MINUS_INFINITY_DB);
+        assert_eq!(readings.integrated(), MINUS_INFINITY_DB);
+    }
+
+    #[test]
+    fn gating() {
+        // EBU Tech 3341 test case 3: the quiet parts fall below the relative gate
+        let mut meter = LoudnessMeter::new(SAMPLE_RATE, ChannelLayout::Stereo);
+        let readings = meter.readings();
+        process_sine(&mut meter, -36.0, 10, &[0, 1]);
+        process_sine(&mut meter, -23.0, 60, &[0, 1]);
+        process_sine(&mut meter, -36.0, 10, &[0, 1]);
+        approx::assert_relative_eq!(readings.integrated(), -23.0, epsilon = 0.1);
+
+        // And silence falls below the absolute gate
+        process_sine(&mut meter, MINUS_INFINITY_DB, 5, &[0, 1]);
+        approx::assert_relative_eq!(readings.integrated(), -23.0, epsilon = 0.1);
+        assert_eq!(readings.short_term(), MINUS_INFINITY_DB);
+    }
+
+    #[test]
+    fn surround_weighting() {
+        // A sine wave at -20 dBFS in a single channel reads -23 LUFS. The surround channels are 1.5
+        // dB louder, and the LFE channel is ignored.
+        let lfe = 3;
+        for (speaker, expected) in [(Speaker::FrontCenter, -23.0), (Speaker::BackLeft, -21.5)] {
+            let mut meter = LoudnessMeter::new(SAMPLE_RATE, ChannelLayout::Surround51);
+            let readings = meter.readings();
+            let channel = ChannelLayout::Surround51.channel_index(speaker).unwrap();
+            process_sine(&mut meter, -20.0, 5, &[channel, lfe]);
+
+            approx::assert_relative_eq!(readings.integrated(), expected, epsilon = 0.1);
+        }
+
+        // The rear surround channels in 7.x layouts are not weighted
+        assert_eq!(
+            channel_weight(ChannelLayout::Surround71, Speaker::BackLeft),
+            1.0
+        );
+        assert_eq!(
+            channel_weight(ChannelLayout::Surround71, Speaker::SideLeft),
+            1.41
+        );
+    }
+}
@@ -0,0 +1,231 @@
+//! A modulation matrix for routing modulation sources to parameters.
+
+use serde::{Deserialize, Serialize};
+
+/// Routes modulation sources like LFOs and envelopes to parameters, with a depth for every
+/// connection. Sources are identified by a plugin-defined type `S`, usually a fieldless enum, and
+/// destinations are identified by the parameters' poly modulation IDs as set with
+/// `.with_poly_modulation_id()`. This way the matrix's output can be treated exactly like the
+/// host's polyphonic modulation: both are offsets to the parameter's normalized value.
+///
+/// Every block, and for every voice, [`compute_offsets()`][Self::compute_offsets()] sums the
+/// contributions of all connections into a normalized offset per destination. These offsets can
+/// then be added to any polyphonic modulation offsets sent by the host, and passed to
+/// [`Param::preview_modulated()`][crate::prelude::Param::preview_modulated()] to get the voice's
+/// modulated value. That value can in turn be used as the target for the voice's
+/// [`Smoother`][crate::prelude::Smoother] to avoid zipper noise from block-rate modulation:
+///
+/// ```
+/// # use nih_plug::prelude::*;
+/// # use nih_plug::util::ModMatrix;
+/// # use serde::{Deserialize, Serialize};
+/// #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// enum ModSource {
+///     Lfo,
+///     Envelope,
+/// }
+///
+/// const CUTOFF_POLY_MOD_ID: u32 = 0;
+/// const NUM_POLY_MOD_IDS: usize = 1;
+///
+/// # let cutoff = FloatParam::new("Cutoff", 1000.0, FloatRange::Linear { min: 20.0, max: 20000.0 })
+/// #     .with_poly_modulation_id(CUTOFF_POLY_MOD_ID);
+/// # let voice_cutoff = cutoff.smoothed.clone();
+/// # let (lfo_value, envelope_value, host_poly_mod_offset) = (0.5, 1.0, 0.0);
+/// let mut matrix = ModMatrix::new();
+/// matrix.connect(ModSource::Lfo, CUTOFF_POLY_MOD_ID, 0.1);
+/// matrix.connect(ModSource::Envelope, CUTOFF_POLY_MOD_ID, 0.4);
+///
+/// // In the plugin's process function, for every voice
+/// let mut offsets = [0.0; NUM_POLY_MOD_IDS];
+/// matrix.compute_offsets(
+///     |source| match source {
+///         ModSource::Lfo => lfo_value,
+///         ModSource::Envelope => envelope_value,
+///     },
+///     &mut offsets,
+/// );
+///
+/// let target = cutoff
+///     .preview_modulated(host_poly_mod_offset + offsets[CUTOFF_POLY_MOD_ID as usize]);
+/// voice_cutoff.set_target(44100.0, target);
+/// ```
+///
+/// The matrix can be serialized, so it can be stored in the plugin's state by putting it in a
+/// `#[persist = "key"]` field, for instance as an `Arc<RwLock<ModMatrix<S>>>` that is shared with
+/// the editor. The audio thread should use `try_read()` on that lock and keep using the previous
+/// block's offsets when the editor is changing the matrix.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModMatrix<S> {
+    connections: Vec<ModConnection<S>>,
+}
+
+/// A single connection in a [`ModMatrix`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModConnection<S> {
+    /// The modulation source.
+    pub source: S,
+    /// The destination parameter's poly modulation ID.
+    pub destination: u32,
+    /// The source's value is multiplied by this amount and then added to the destination
+    /// parameter's normalized value. Negative depths invert the modulation.
+    pub depth: f32,
+}
+
+impl<S> Default for ModMatrix<S> {
+    fn default() -> Self {
+        Self {
+            connections: Vec::new(),
+        }
+    }
+}
+
+impl<S: Copy + PartialEq> ModMatrix<S> {
+    /// Create an empty modulation matrix.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Connect `source` to the parameter with poly modulation ID `destination`. If the source and
+    /// the destination are already connected, then only the connection's depth is changed.
+    pub fn connect(&mut self, source: S, destination: u32, depth: f32) {
+        match self.connection_mut(source, destination) {
+            Some(connection) => connection.depth = depth,
+            None => self.connections.push(ModConnection {
+                source,
+                destination,
+                depth,
+            }),
+        }
+    }
+
+    /// Remove the connection between `source` and `destination`. Returns `false` if they were not
+    /// connected.
+    pub fn disconnect(&mut self, source: S, destination: u32) -> bool {
+        let num_connections = self.connections.len();
+        self.connections.retain(|connection| {
+            !(connection.source == source && connection.destination == destination)
+        });
+
+        self.connections.len() != num_connections
+    }
+
+    /// Remove all connections to the parameter with poly modulation ID `destination`.
+    pub fn disconnect_destination(&mut self, destination: u32) {
+        self.connections
+            .retain(|connection| connection.destination != destination);
+    }
+
+    /// Remove all connections.
+    pub fn clear(&mut self) {
+        self.connections.clear();
+    }
+
+    /// The depth of the connection between `source` and `destination`, if they are connected.
+    pub fn depth(&self, source: S, destination: u32) -> Option<f32> {
+        self.connections
+            .iter()
+            .find(|connection| connection.source == source && connection.destination == destination)
+            .map(|connection| connection.depth)
+    }
+
+    /// All of the matrix's connections, in the order they were made.
+    pub fn connections(&self) -> &[ModConnection<S>] {
+        &self.connections
+    }
+
+    /// Whether the matrix does not contain any connections.
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty()
+    }
+
+    /// Sum the modulation for every destination. `source_value` is called with every connection's
+    /// source to get that source's current value, which is usually in either the `[0, 1]` or the
+    /// `[-1, 1]` range. `offsets` is indexed by poly modulation ID and it receives the normalized
+    /// offset for every destination. Destinations without connections are set to zero.
+    /// Connections to poly modulation IDs that don't fit in `offsets` are ignored. This does not
+    /// allocate, so it can be called from the audio thread.
+    pub fn compute_offsets(&self, mut source_value: impl FnMut(S) -> f32, offsets: &mut [f32]) {
+        offsets.fill(0.0);
+        for connection in &self.connections {
+            if let Some(offset) = offsets.get_mut(connection.destination as usize) {
+                *offset += source_value(connection.source) * connection.depth;
+            } else {
+                nih_debug_assert_failure!(
+                    "Poly modulation ID {} does not fit in the offsets slice",
+                    connection.destination
+                );
+            }
+        }
+    }
+
+    /// The same as [`compute_offsets()`][Self::compute_offsets()], but for a single destination.
+    pub fn compute_offset(&self, mut source_value: impl FnMut(S) -> f32, destination: u32) -> f32 {
+        self.connections
+            .iter()
+            .filter(|connection| connection.destination == destination)
+            .map(|connection| source_value(connection.source) * connection.depth)
+            .sum()
+    }
+
+    fn connection_mut(&mut self, source: S, destination: u32) -> Option<&mut ModConnection<S>> {
+        self.connections
+            .iter_mut()
+            .find(|connection| connection.source == source && connection.destination == destination)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    enum Source {
+        Lfo,
+        Envelope,
+    }
+
+    fn source_value(source: Source) -> f32 {
+        match source {
+            Source::Lfo => -0.5,
+            Source::Envelope => 1.0,
+        }
+    }
+
+    #[test]
+    fn sum_connections() {
+        let mut matrix = ModMatrix::new();
+        matrix.connect(Source::Lfo, 0, 0.25);
+        matrix.connect(Source::Envelope, 0, 0.5);
+        matrix.connect(Source::Envelope, 2, -0.5);
+
+        let mut offsets = [1.0; 3];
+        matrix.compute_offsets(source_value, &mut offsets);
+        assert_eq!(offsets, [0.375, 0.0, -0.5]);
+        assert_eq!(matrix.compute_offset(source_value, 2), -0.5);
+    }
+
+    #[test]
+    fn change_connections() {
+        let mut matrix = ModMatrix::new();
+        matrix.connect(Source::Lfo, 0, 0.2);
+        matrix.connect(Source::Lfo, 0, 0.4);
+        assert_eq!(matrix.connections().len(), 1);
+        assert_eq!(matrix.depth(Source::Lfo, 0), Some(0.4));
+
+        assert!(matrix.disconnect(Source::Lfo, 0));
+        assert!(!matrix.disconnect(Source::Lfo, 0));
+        assert!(matrix.is_empty());
+    }
+
+    #[test]
+    fn serialize() {
+        let mut matrix = ModMatrix::new();
+        matrix.connect(Source::Lfo, 1, 0.25);
+        matrix.connect(Source::Envelope, 3, -1.0);
+
+        let serialized = serde_json::to_string(&matrix).unwrap();
+        let deserialized: ModMatrix<Source> = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, matrix);
+    }
+}
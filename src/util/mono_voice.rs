@@ -0,0 +1,500 @@
+//! Note stacking, legato, and portamento for monophonic synthesizers.
+
+use crate::midi::NoteEvent;
+
+/// The number of held notes that are remembered. When more notes are held, the oldest held note is
+/// forgotten.
+const MAX_HELD_NOTES: usize = 128;
+
+/// Turns a stream of note events into the pitch and envelope triggers for a monophonic voice. The
+/// most recently pressed key always plays. Keys that are still held are remembered, so releasing
+/// the sounding key returns to the most recent key that is still held instead of releasing the
+/// voice. The voice is only released once all keys have been released.
+///
+/// With legato enabled, a new note or a return to a held note while the voice is already playing
+/// does not retrigger the envelopes. The voice's pitch glides to each new note over the glide
+/// time, which can be taken from a [`FloatParam`] and updated with
+/// [`set_glide_time()`][Self::set_glide_time()] for every block. Pitches are expressed as
+/// fractional MIDI note numbers, and the glide is linear in that domain so it takes equally long
+/// for every interval.
+///
+/// ```ignore
+/// self.mono_voice.set_glide_time(self.params.glide_ms.value());
+/// while let Some(event) = context.next_event() {
+///     match self.mono_voice.handle_event(&event) {
+///         Some(MonoEvent::Trigger { .. }) => self.amp_envelope.note_on(),
+///         Some(MonoEvent::Release | MonoEvent::Choke) => self.amp_envelope.note_off(),
+///         Some(MonoEvent::Legato { .. }) | None => (),
+///     }
+/// }
+///
+/// // And then for every sample
+/// self.oscillator.set_frequency(util::f32_midi_note_to_freq(self.mono_voice.next()));
+/// ```
+///
+/// [`FloatParam`]: crate::prelude::FloatParam
+#[derive(Debug, Clone)]
+pub struct MonoVoice {
+    sample_rate: f32,
+    glide_time_ms: f32,
+    glide_mode: GlideMode,
+    legato: bool,
+
+    /// The keys that are currently held as `(channel, note, velocity)` tuples, in the order they
+    /// were pressed. The last note is the one that is playing.
+    held_notes: Vec<(u8, u8, f32)>,
+    /// The current pitch as a fractional MIDI note number. `None` until the first note has been
+    /// played.
+    pitch: Option<f32>,
+    /// The note the pitch is gliding towards.
+    target_pitch: f32,
+    /// The amount added to the pitch every sample while gliding.
+    glide_step: f32,
+    /// The number of samples left in the current glide.
+    glide_steps_left: u32,
+}
+
+/// When [`MonoVoice`] glides between notes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GlideMode {
+    /// Only glide when a note is played while another note is held, or when returning to a held
+    /// note. A note played after all keys have been released starts at its own pitch.
+    #[default]
+    LegatoOnly,
+    /// Always glide from the previous note's pitch, even if the previous note has already been
+    /// released.
+    Always,
+}
+
+/// What a monophonic voice should do in response to a note event. Returned from
+/// [`MonoVoice::note_on()`], [`MonoVoice::note_off()`], and [`MonoVoice::handle_event()`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MonoEvent {
+    /// A new note starts and the envelopes should be (re)triggered. This is also used when
+    /// returning to a held note with legato disabled.
+    Trigger { note: u8, velocity: f32 },
+    /// The voice switches to a different note without retriggering its envelopes.
+    Legato { note: u8, velocity: f32 },
+    /// All keys have been released, so the envelopes should start their release stage.
+    Release,
+    /// All keys have been released because of a choke event, so the voice should be silenced
+    /// immediately.
+    Choke,
+}
+
+impl MonoVoice {
+    /// Create a new monophonic voice. Legato is enabled by default, the glide time is zero, and the
+    /// glide mode is [`LegatoOnly`][GlideMode::LegatoOnly].
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            glide_time_ms: 0.0,
+            glide_mode: GlideMode::default(),
+            legato: true,
+
+            held_notes: Vec::with_capacity(MAX_HELD_NOTES),
+            pitch: None,
+            target_pitch: 0.0,
+            glide_step: 0.0,
+            glide_steps_left: 0,
+        }
+    }
+
+    /// Use a different glide mode.
+    pub fn with_glide_mode(mut self, glide_mode: GlideMode) -> Self {
+        self.glide_mode = glide_mode;
+        self
+    }
+
+    /// Enable or disable legato. When legato is disabled, every note change retriggers the
+    /// envelopes.
+    pub fn with_legato(mut self, legato: bool) -> Self {
+        self.legato = legato;
+        self
+    }
+
+    /// Change the sample rate. This only affects glides that start after this call.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+    }
+
+    /// Change the glide time in milliseconds. This only affects glides that start after this call.
+    /// A glide time of zero disables portamento.
+    pub fn set_glide_time(&mut self, glide_time_ms: f32) {
+        self.glide_time_ms = glide_time_ms.max(0.0);
+    }
+
+    /// Change the glide mode.
+    pub fn set_glide_mode(&mut self, glide_mode: GlideMode) {
+        self.glide_mode = glide_mode;
+    }
+
+    /// Enable or disable legato.
+    pub fn set_legato(&mut self, legato: bool) {
+        self.legato = legato;
+    }
+
+    /// Forget all held notes and jump to the current glide's target pitch.
+    pub fn reset(&mut self) {
+        self.held_notes.clear();
+        if self.pitch.is_some() {
+            self.pitch = Some(self.target_pitch);
+        }
+        self.glide_steps_left = 0;
+    }
+
+    /// The note that is currently playing, if any key is held.
+    pub fn current_note(&self) -> Option<u8> {
+        self.held_notes.last().map(|&(_, note, _)| note)
+    }
+
+    /// The current pitch as a fractional MIDI note number, without advancing the glide. This is
+    /// zero until the first note has been played.
+    pub fn pitch(&self) -> f32 {
+        self.pitch.unwrap_or_default()
+    }
+
+    /// Whether the pitch is currently gliding towards a note.
+    pub fn is_gliding(&self) -> bool {
+        self.glide_steps_left > 0
+    }
+
+    /// Handle a key being pressed.
+    pub fn note_on(&mut self, channel: u8, note: u8, velocity: f32) -> MonoEvent {
+        let was_playing = !self.held_notes.is_empty();
+
+        self.remove_held_note(channel, note);
+        if self.held_notes.len() >= MAX_HELD_NOTES {
+            self.held_notes.remove(0);
+        }
+        self.held_notes.push((channel, note, velocity));
+
+        self.play_note(note, velocity, was_playing)
+    }
+
+    /// Handle a key being released. Returns `None` if the released key was not the sounding note,
+    /// in which case it's only removed from the held notes.
+    pub fn note_off(&mut self, channel: u8, note: u8) -> Option<MonoEvent> {
+        let was_sounding =
+            matches!(self.held_notes.last(), Some(&(c, n, _)) if c == channel && n == note);
+        if !self.remove_held_note(channel, note) || !was_sounding {
+            return None;
+        }
+
+        match self.held_notes.last() {
+            Some(&(_, note, velocity)) => Some(self.play_note(note, velocity, true)),
+            None => Some(MonoEvent::Release),
+        }
+    }
+
+    /// Handle all keys on `channel` being released, or the keys on all channels if `channel` is
+    /// `None`. If the sounding note was released, then this either returns to the most recent key
+    /// that is still held on another channel or releases the voice.
+    pub fn all_notes_off(&mut self, channel: Option<u8>) -> Option<MonoEvent> {
+        let sounding_note = self.held_notes.last().copied();
+        self.held_notes
+            .retain(|&(c, _, _)| channel.is_some_and(|channel| c != channel));
+        if self.held_notes.last().copied() == sounding_note {
+            return None;
+        }
+
+        match self.held_notes.last() {
+            Some(&(_, note, velocity)) => Some(self.play_note(note, velocity, true)),
+            None => Some(MonoEvent::Release),
+        }
+    }
+
+    /// Handle all sound on `channel` being stopped, or on all channels if `channel` is `None`.
+    /// Like [`all_notes_off()`][Self::all_notes_off()], but if no other keys are held then this
+    /// always returns a [`MonoEvent::Choke`] so a voice that's still in its release stage is
+    /// silenced as well.
+    pub fn all_sound_off(&mut self, channel: Option<u8>) -> Option<MonoEvent> {
+        let result = self.all_notes_off(channel);
+        if self.held_notes.is_empty() {
+            Some(MonoEvent::Choke)
+        } else {
+            result
+        }
+    }
+
+    /// Handle a note event. Note on, note off, choke, all notes off, and all sound off events are
+    /// handled. All other events are ignored. A choke event for the sounding note when no other
+    /// keys are held results in a [`MonoEvent::Choke`].
+    pub fn handle_event<S>(&mut self, event: &NoteEvent<S>) -> Option<MonoEvent> {
+        match *event {
+            NoteEvent::NoteOn {
+                channel,
+                note,
+                velocity,
+                ..
+            } => Some(self.note_on(channel, note, velocity)),
+            NoteEvent::NoteOff { channel, note, .. } => self.note_off(channel, note),
+            NoteEvent::Choke { channel, note, .. } => match self.note_off(channel, note) {
+                Some(MonoEvent::Release) => Some(MonoEvent::Choke),
+                result => result,
+            },
+            NoteEvent::AllNotesOff { channel, .. } => self.all_notes_off(channel),
+            NoteEvent::AllSoundOff { channel, .. } => self.all_sound_off(channel),
+            _ => None,
+        }
+    }
+
+    /// Advance the glide by one sample and return the new pitch as a fractional MIDI note number.
+    #[allow(clippy::should_implement_trait)]
+    #[inline]
+    pub fn next(&mut self) -> f32 {
+        if self.glide_steps_left > 0 {
+            self.glide_steps_left -= 1;
+            self.pitch = if self.glide_steps_left == 0 {
+                Some(self.target_pitch)
+            } else {
+                self.pitch.map(|pitch| pitch + self.glide_step)
+            };
+        }
+
+        self.pitch()
+    }
+
+    /// Fill a block with pitches by calling [`next()`][Self::next()] for every sample.
+    pub fn next_block(&mut self, block_values: &mut [f32]) {
+        for value in block_values {
+            *value = self.next();
+        }
+    }
+
+    /// Start playing `note`, gliding to it if needed. `overlapping` indicates whether another note
+    /// was held when this note started.
+    fn play_note(&mut self, note: u8, velocity: f32, overlapping: bool) -> MonoEvent {
+        let should_glide = match self.glide_mode {
+            GlideMode::LegatoOnly => overlapping,
+            GlideMode::Always => true,
+        };
+        self.start_glide(note as f32, should_glide);
+
+        if overlapping && self.legato {
+            MonoEvent::Legato { note, velocity }
+        } else {
+            MonoEvent::Trigger { note, velocity }
+        }
+    }
+
+    fn start_glide(&mut self, target_pitch: f32, should_glide: bool) {
+        self.target_pitch = target_pitch;
+
+        let num_steps = (self.glide_time_ms / 1000.0 * self.sample_rate).round() as u32;
+        match self.pitch {
+            Some(pitch) if should_glide && num_steps > 0 => {
+                self.glide_step = (target_pitch - pitch) / num_steps as f32;
+                self.glide_steps_left = num_steps;
+            }
+            _ => {
+                self.pitch = Some(target_pitch);
+                self.glide_steps_left = 0;
+            }
+        }
+    }
+
+    /// Remove a key from the held notes. Returns whether the key was held.
+    fn remove_held_note(&mut self, channel: u8, note: u8) -> bool {
+        match self
+            .held_notes
+            .iter()
+            .position(|&(c, n, _)| c == channel && n == note)
+        {
+            Some(idx) => {
+                self.held_notes.remove(idx);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A glide time of 10 samples at 1 kHz.
+    const SAMPLE_RATE: f32 = 1000.0;
+    const GLIDE_TIME_MS: f32 = 10.0;
+
+    #[test]
+    fn note_stacking() {
+        let mut voice = MonoVoice::new(SAMPLE_RATE);
+        assert_eq!(
+            voice.note_on(0, 60, 0.5),
+            MonoEvent::Trigger {
+                note: 60,
+                velocity: 0.5
+            }
+        );
+        assert_eq!(
+            voice.note_on(0, 64, 0.75),
+            MonoEvent::Legato {
+                note: 64,
+                velocity: 0.75
+            }
+        );
+        assert_eq!(
+            voice.note_on(0, 67, 1.0),
+            MonoEvent::Legato {
+                note: 67,
+                velocity: 1.0
+            }
+        );
+        assert_eq!(voice.next(), 67.0);
+
+        // Releasing a key that isn't sounding doesn't change anything
+        assert_eq!(voice.note_off(0, 64), None);
+        assert_eq!(voice.current_note(), Some(67));
+
+        // Releasing the top note returns to the held lower note
+        assert_eq!(
+            voice.note_off(0, 67),
+            Some(MonoEvent::Legato {
+                note: 60,
+                velocity: 0.5
+            })
+        );
+        assert_eq!(voice.next(), 60.0);
+        assert_eq!(voice.note_off(0, 60), Some(MonoEvent::Release));
+        assert_eq!(voice.current_note(), None);
+        assert_eq!(voice.note_off(0, 60), None);
+
+        // Without legato every note change retriggers the envelopes
+        let mut voice = MonoVoice::new(SAMPLE_RATE).with_legato(false);
+        voice.note_on(0, 60, 1.0);
+        assert_eq!(
+            voice.note_on(0, 64, 1.0),
+            MonoEvent::Trigger {
+                note: 64,
+                velocity: 1.0
+            }
+        );
+        assert_eq!(
+            voice.note_off(0, 64),
+            Some(MonoEvent::Trigger {
+                note: 60,
+                velocity: 1.0
+            })
+        );
+    }
+
+    #[test]
+    fn glide() {
+        let mut voice = MonoVoice::new(SAMPLE_RATE);
+        voice.set_glide_time(GLIDE_TIME_MS);
+
+        // The first note starts at its own pitch
+        voice.note_on(0, 60, 1.0);
+        assert!(!voice.is_gliding());
+        assert_eq!(voice.next(), 60.0);
+
+        // Overlapping notes glide linearly over the glide time
+        voice.note_on(0, 70, 1.0);
+        assert!(voice.is_gliding());
+        let mut pitches = [0.0; 10];
+        voice.next_block(&mut pitches);
+        assert_eq!(pitches[0], 61.0);
+        assert_eq!(pitches[4], 65.0);
+        assert_eq!(pitches[9], 70.0);
+        assert!(!voice.is_gliding());
+
+        // Returning to a held note also glides
+        voice.note_off(0, 70);
+        assert_eq!(voice.next(), 69.0);
+
+        // In the default mode, a note after all keys are released doesn't glide
+        voice.note_off(0, 60);
+        voice.note_on(0, 72, 1.0);
+        assert_eq!(voice.next(), 72.0);
+        voice.note_off(0, 72);
+
+        // But it does when always gliding
+        voice.set_glide_mode(GlideMode::Always);
+        voice.note_on(0, 62, 1.0);
+        assert_eq!(voice.next(), 71.0);
+    }
+
+    #[test]
+    fn choke() {
+        let mut voice = MonoVoice::new(SAMPLE_RATE);
+        voice.handle_event(&NoteEvent::<()>::NoteOn {
+            timing: 0,
+            voice_id: None,
+            channel: 0,
+            note: 60,
+            velocity: 1.0,
+        });
+        assert_eq!(
+            voice.handle_event(&NoteEvent::<()>::Choke {
+                timing: 0,
+                voice_id: None,
+                channel: 0,
+                note: 60,
+            }),
+            Some(MonoEvent::Choke)
+        );
+    }
+
+    #[test]
+    fn all_notes_off() {
+        let mut voice = MonoVoice::new(SAMPLE_RATE);
+        voice.note_on(0, 60, 0.5);
+        voice.note_on(1, 64, 1.0);
+
+        // Notes on other channels are not affected, and releasing the sounding note's channel
+        // returns to the note that's still held
+        assert_eq!(
+            voice.handle_event(&NoteEvent::<()>::AllNotesOff {
+                timing: 0,
+                channel: Some(0),
+            }),
+            None
+        );
+        assert_eq!(voice.current_note(), Some(64));
+        voice.note_on(0, 60, 0.5);
+        assert_eq!(
+            voice.handle_event(&NoteEvent::<()>::AllNotesOff {
+                timing: 0,
+                channel: Some(0),
+            }),
+            Some(MonoEvent::Legato {
+                note: 64,
+                velocity: 1.0
+            })
+        );
+
+        assert_eq!(
+            voice.handle_event(&NoteEvent::<()>::AllNotesOff {
+                timing: 0,
+                channel: None,
+            }),
+            Some(MonoEvent::Release)
+        );
+        assert_eq!(voice.current_note(), None);
+    }
+
+    #[test]
+    fn all_sound_off() {
+        let mut voice = MonoVoice::new(SAMPLE_RATE);
+        voice.note_on(0, 60, 1.0);
+        assert_eq!(
+            voice.handle_event(&NoteEvent::<()>::AllSoundOff {
+                timing: 0,
+                channel: None,
+            }),
+            Some(MonoEvent::Choke)
+        );
+        assert_eq!(voice.current_note(), None);
+
+        // A voice in its release stage is also silenced
+        assert_eq!(
+            voice.handle_event(&NoteEvent::<()>::AllSoundOff {
+                timing: 0,
+                channel: Some(0),
+            }),
+            Some(MonoEvent::Choke)
+        );
+    }
+}
@@ -0,0 +1,238 @@
+//! An MTS-ESP client for following the session's global microtuning.
+
+use libloading::Library;
+use std::ffi::{c_char, CStr};
+use std::path::PathBuf;
+
+/// The number of MIDI channels supported by MTS-ESP's multi-channel tuning tables.
+const NUM_CHANNELS: u8 = 16;
+
+/// An MTS-ESP client. When an MTS-ESP master plugin is present in the session, this retunes MIDI
+/// notes according to the master's tuning. Otherwise notes use 12-TET with A4 tuned to 440 Hz.
+///
+/// The MTS-ESP library is loaded at runtime, so the client also works when the library is not
+/// installed. Creating the client loads the library and registers the client with the master, so
+/// this should be done outside of the process function, for instance in the plugin's `Default`
+/// implementation. The other functions are realtime-safe and are intended to be called for every
+/// `NoteEvent::NoteOn` event. Since the master can change its tuning at any time, frequencies
+/// should not be cached for longer than a single note.
+pub struct MtsClient {
+    /// The loaded MTS-ESP library, or `None` if the library could not be found.
+    library: Option<MtsLibrary>,
+}
+
+/// Function pointers into the MTS-ESP library. The `Library` is kept alive for as long as these
+/// functions can be called.
+struct MtsLibrary {
+    register_client: unsafe extern "C" fn(),
+    deregister_client: unsafe extern "C" fn(),
+    has_master: unsafe extern "C" fn() -> bool,
+    should_filter_note: unsafe extern "C" fn(c_char, c_char) -> bool,
+    get_tuning_table: unsafe extern "C" fn() -> *const f64,
+    get_scale_name: unsafe extern "C" fn() -> *const c_char,
+    /// These functions were added in later versions of the library.
+    multi_channel: Option<MtsMultiChannel>,
+
+    _library: Library,
+}
+
+struct MtsMultiChannel {
+    use_multi_channel_tuning: unsafe extern "C" fn(c_char) -> bool,
+    should_filter_note_multi_channel: unsafe extern "C" fn(c_char, c_char) -> bool,
+    get_multi_channel_tuning_table: unsafe extern "C" fn(c_char) -> *const f64,
+}
+
+impl Default for MtsClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for MtsClient {
+    fn drop(&mut self) {
+        if let Some(library) = &self.library {
+            unsafe { (library.deregister_client)() };
+        }
+    }
+}
+
+impl MtsClient {
+    /// Load the MTS-ESP library and register this client with the MTS-ESP master. If the library
+    /// is not installed, then the client always falls back to 12-TET.
+    pub fn new() -> Self {
+        let library = MtsLibrary::load();
+        if let Some(library) = &library {
+            unsafe { (library.register_client)() };
+        }
+
+        Self { library }
+    }
+
+    /// Whether the MTS-ESP library was found. Even if it was, notes are only retuned when
+    /// [`has_master()`][Self::has_master()] also returns `true`.
+    pub fn is_library_loaded(&self) -> bool {
+        self.library.is_some()
+    }
+
+    /// Whether an MTS-ESP master is currently present in the session.
+    pub fn has_master(&self) -> bool {
+        match &self.library {
+            Some(library) => unsafe { (library.has_master)() },
+            None => false,
+        }
+    }
+
+    /// Get the frequency in Hz for a MIDI note on a MIDI channel, as found in `NoteEvent`s. This
+    /// uses the master's tuning when an MTS-ESP master is present, and 12-TET with A4 tuned to
+    /// 440 Hz otherwise.
+    pub fn note_to_freq(&self, note: u8, channel: u8) -> f32 {
+        let note = note.min(127);
+        if let Some(library) = &self.library {
+            if unsafe { (library.has_master)() } {
+                if let Some(frequency) = unsafe { library.frequency(note, channel) } {
+                    return frequency as f32;
+                }
+            }
+        }
+
+        super::midi_note_to_freq(note)
+    }
+
+    /// Whether the master wants a note to be ignored. Notes that should be filtered should not be
+    /// played at all. Always returns `false` when there is no MTS-ESP master.
+    pub fn should_filter_note(&self, note: u8, channel: u8) -> bool {
+        let note = note.min(127);
+        match &self.library {
+            Some(library) if unsafe { (library.has_master)() } => unsafe {
+                match &library.multi_channel {
+                    Some(multi_channel)
+                        if channel < NUM_CHANNELS
+                            && (multi_channel.use_multi_channel_tuning)(channel as c_char) =>
+                    {
+                        (multi_channel.should_filter_note_multi_channel)(
+                            note as c_char,
+                            channel as c_char,
+                        )
+                    }
+                    _ => (library.should_filter_note)(note as c_char, channel as c_char),
+                }
+            },
+            _ => false,
+        }
+    }
+
+    /// The name of the master's current scale, if there is an MTS-ESP master. This allocates.
+    pub fn scale_name(&self) -> Option<String> {
+        let library = self.library.as_ref()?;
+        if !unsafe { (library.has_master)() } {
+            return None;
+        }
+
+        let name = unsafe { (library.get_scale_name)() };
+        if name.is_null() {
+            None
+        } else {
+            Some(
+                unsafe { CStr::from_ptr(name) }
+                    .to_string_lossy()
+                    .into_owned(),
+            )
+        }
+    }
+}
+
+impl MtsLibrary {
+    /// Try to load the MTS-ESP library from its standard installation location. Returns `None` if
+    /// the library is not installed or if it does not export the required functions.
+    fn load() -> Option<Self> {
+        let library = unsafe { Library::new(library_path()?) }.ok()?;
+        unsafe {
+            Some(Self {
+                register_client: *library.get(b"MTS_RegisterClient\0").ok()?,
+                deregister_client: *library.get(b"MTS_DeregisterClient\0").ok()?,
+                has_master: *library.get(b"MTS_HasMaster\0").ok()?,
+                should_filter_note: *library.get(b"MTS_ShouldFilterNote\0").ok()?,
+                get_tuning_table: *library.get(b"MTS_GetTuningTable\0").ok()?,
+                get_scale_name: *library.get(b"MTS_GetScaleName\0").ok()?,
+                multi_channel: MtsMultiChannel::load(&library),
+
+                _library: library,
+            })
+        }
+    }
+
+    /// The master's frequency for a note, preferring the channel's tuning table when the master
+    /// uses multi-channel tuning.
+    ///
+    /// # Safety
+    ///
+    /// `note` must be a valid MIDI note number.
+    unsafe fn frequency(&self, note: u8, channel: u8) -> Option<f64> {
+        let mut table = std::ptr::null();
+        if let Some(multi_channel) = &self.multi_channel {
+            if channel < NUM_CHANNELS && (multi_channel.use_multi_channel_tuning)(channel as c_char)
+            {
+                table = (multi_channel.get_multi_channel_tuning_table)(channel as c_char);
+            }
+        }
+        if table.is_null() {
+            table = (self.get_tuning_table)();
+        }
+
+        if table.is_null() {
+            None
+        } else {
+            Some(*table.add(note as usize))
+        }
+    }
+}
+
+impl MtsMultiChannel {
+    /// Look up the multi-channel tuning functions. Returns `None` for older versions of the library
+    /// that don't have these functions.
+    unsafe fn load(library: &Library) -> Option<Self> {
+        Some(Self {
+            use_multi_channel_tuning: *library.get(b"MTS_UseMultiChannelTuning\0").ok()?,
+            should_filter_note_multi_channel: *library
+                .get(b"MTS_ShouldFilterNoteMultiChannel\0")
+                .ok()?,
+            get_multi_channel_tuning_table: *library
+                .get(b"MTS_GetMultiChannelTuningTable\0")
+                .ok()?,
+        })
+    }
+}
+
+/// The location the MTS-ESP installer puts the library in.
+fn library_path() -> Option<PathBuf> {
+    if cfg!(target_os = "windows") {
+        let common_files = std::env::var_os("CommonProgramFiles")
+            .unwrap_or_else(|| r"C:\Program Files\Common Files".into());
+
+        Some(PathBuf::from(common_files).join(r"MTS-ESP\LIBMTS.dll"))
+    } else if cfg!(target_os = "macos") {
+        Some(PathBuf::from(
+            "/Library/Application Support/MTS-ESP/libMTS.dylib",
+        ))
+    } else if cfg!(target_os = "linux") {
+        Some(PathBuf::from("/usr/local/lib/libMTS.so"))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_back_to_12_tet() {
+        let client = MtsClient { library: None };
+
+        assert!(!client.has_master());
+        assert_eq!(client.note_to_freq(69, 0), 440.0);
+        assert_eq!(client.note_to_freq(81, 15), 880.0);
+        assert!(!client.should_filter_note(69, 0));
+        assert_eq!(client.scale_name(), None);
+    }
+}
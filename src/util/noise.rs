@@ -0,0 +1,167 @@
+//! Spectrally shaped noise generators.
+
+use super::Rng;
+
+/// The leak coefficient for the brown noise integrator. This puts the corner frequency, below which
+/// the spectrum is flat, at around 140 Hz at a 44.1 kHz sample rate. Without the leak the output
+/// would drift off to infinity.
+const BROWN_NOISE_LEAK: f32 = 1.0 / 1.02;
+/// The brown noise integrator's input gain.
+const BROWN_NOISE_INPUT_GAIN: f32 = 0.02 / 1.02;
+/// Keeps the brown noise's output roughly within `[-1, 1]`.
+const BROWN_NOISE_OUTPUT_GAIN: f32 = 3.5;
+
+/// Pink (1/f) noise with a -3 dB per octave slope, generated by filtering white noise from an
+/// [`Rng`]. This uses Paul Kellet's refined filter, which is accurate to within 0.05 dB above 9.2 Hz
+/// at a 44.1 kHz sample rate. The output is roughly in the `[-1, 1]` range.
+#[derive(Debug, Clone)]
+pub struct PinkNoise {
+    rng: Rng,
+    /// The states of the filter's one-pole sections.
+    filter_states: [f32; 7],
+}
+
+/// Brown (1/f²) noise with a -6 dB per octave slope, generated by integrating white noise from an
+/// [`Rng`] with a leaky integrator. The spectrum is flat below roughly 140 Hz at a 44.1 kHz sample
+/// rate. The output is roughly in the `[-1, 1]` range.
+#[derive(Debug, Clone)]
+pub struct BrownNoise {
+    rng: Rng,
+    /// The integrator's state.
+    state: f32,
+}
+
+impl PinkNoise {
+    /// Create a pink noise generator using white noise from `rng`.
+    pub fn new(rng: Rng) -> Self {
+        Self {
+            rng,
+            filter_states: [0.0; 7],
+        }
+    }
+
+    /// Generate the next pink noise sample.
+    #[inline]
+    pub fn next_sample(&mut self) -> f32 {
+        let white = self.rng.next_bipolar_f32();
+        let [b0, b1, b2, b3, b4, b5, b6] = &mut self.filter_states;
+
+        *b0 = (0.99886 * *b0) + (white * 0.0555179);
+        *b1 = (0.99332 * *b1) + (white * 0.0750759);
+        *b2 = (0.96900 * *b2) + (white * 0.153852);
+        *b3 = (0.86650 * *b3) + (white * 0.3104856);
+        *b4 = (0.55000 * *b4) + (white * 0.5329522);
+        *b5 = (-0.7616 * *b5) - (white * 0.0168980);
+        let pink = *b0 + *b1 + *b2 + *b3 + *b4 + *b5 + *b6 + (white * 0.5362);
+        *b6 = white * 0.115926;
+
+        // The filter's output has peaks of around 9, this keeps the output roughly within `[-1, 1]`
+        pink * 0.11
+    }
+
+    /// Fill `block` with pink noise.
+    pub fn next_block(&mut self, block: &mut [f32]) {
+        for sample in block {
+            *sample = self.next_sample();
+        }
+    }
+
+    /// Clear the filter's state. This does not reset the random number generator.
+    pub fn reset(&mut self) {
+        self.filter_states = [0.0; 7];
+    }
+}
+
+impl BrownNoise {
+    /// Create a brown noise generator using white noise from `rng`.
+    pub fn new(rng: Rng) -> Self {
+        Self { rng, state: 0.0 }
+    }
+
+    /// Generate the next brown noise sample.
+    #[inline]
+    pub fn next_sample(&mut self) -> f32 {
+        let white = self.rng.next_bipolar_f32();
+        self.state = (self.state * BROWN_NOISE_LEAK) + (white * BROWN_NOISE_INPUT_GAIN);
+
+        self.state * BROWN_NOISE_OUTPUT_GAIN
+    }
+
+    /// Fill `block` with brown noise.
+    pub fn next_block(&mut self, block: &mut [f32]) {
+        for sample in block {
+            *sample = self.next_sample();
+        }
+    }
+
+    /// Clear the integrator's state. This does not reset the random number generator.
+    pub fn reset(&mut self) {
+        self.state = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::f64::consts::TAU;
+
+    const SAMPLE_RATE: f64 = 44100.0;
+    const SEGMENT_LEN: usize = 4096;
+    const NUM_SEGMENTS: usize = 64;
+
+    /// Estimate the power of `signal` at `frequency` by averaging the DFT bin's power over a number
+    /// of Hann windowed segments.
+    fn power_at(mut signal: impl FnMut(&mut [f32]), frequency: f64) -> f64 {
+        let mut segment = [0.0; SEGMENT_LEN];
+        let mut power = 0.0;
+        for _ in 0..NUM_SEGMENTS {
+            signal(&mut segment);
+
+            let (mut re, mut im) = (0.0, 0.0);
+            for (i, sample) in segment.iter().enumerate() {
+                let window = 0.5 - (0.5 * (TAU * i as f64 / SEGMENT_LEN as f64).cos());
+                let phase = TAU * frequency * i as f64 / SAMPLE_RATE;
+                re += *sample as f64 * window * phase.cos();
+                im -= *sample as f64 * window * phase.sin();
+            }
+            power += (re * re) + (im * im);
+        }
+
+        power / NUM_SEGMENTS as f64
+    }
+
+    /// The slope between 1 kHz and 4 kHz in decibels per octave.
+    fn slope_db_per_octave(mut signal: impl FnMut(&mut [f32])) -> f64 {
+        let low = power_at(&mut signal, 1000.0);
+        let high = power_at(&mut signal, 4000.0);
+
+        10.0 * (high / low).log10() / 2.0
+    }
+
+    #[test]
+    fn pink_noise_slope() {
+        let mut pink_noise = PinkNoise::new(Rng::new(1234));
+        let slope = slope_db_per_octave(|block| pink_noise.next_block(block));
+
+        assert!((slope + 3.0).abs() < 1.0, "slope = {slope} dB/octave");
+    }
+
+    #[test]
+    fn brown_noise_slope() {
+        let mut brown_noise = BrownNoise::new(Rng::new(1234));
+        let slope = slope_db_per_octave(|block| brown_noise.next_block(block));
+
+        assert!((slope + 6.0).abs() < 1.0, "slope = {slope} dB/octave");
+    }
+
+    #[test]
+    fn noise_range() {
+        let mut pink_noise = PinkNoise::new(Rng::new(1234));
+        let mut brown_noise = BrownNoise::new(Rng::new(1234));
+        for _ in 0..100_000 {
+            assert!(pink_noise.next_sample().abs() < 1.5);
+            assert!(brown_noise.next_sample().abs() < 1.5);
+        }
+    }
+}
@@ -0,0 +1,390 @@
+//! Band-limited oscillators for subtractive synthesis.
+
+use std::f32::consts::TAU;
+
+/// The highest phase increment per sample. This keeps the frequency just below the Nyquist
+/// frequency, where the corrections for the discontinuities would overlap.
+const MAX_PHASE_DELTA: f32 = 0.49;
+
+/// A per-voice band-limited oscillator. The saw, square, and triangle waveforms use PolyBLEP and
+/// PolyBLAMP corrections to greatly reduce the aliasing caused by the discontinuities in the naive
+/// waveforms, and the corrections are also applied when the oscillator gets hard synced to another
+/// oscillator. Like the [`Adsr`][super::Adsr], this is meant to be owned by a single voice.
+///
+/// The output is in the `[-1, 1]` range, give or take some overshoot from the corrections. All
+/// waveforms start at the beginning of their cycle: the saw wave rises from -1, the square wave
+/// starts at 1, and the sine and triangle waves start at 0 and -1 respectively.
+///
+/// Hard sync works by passing the master oscillator's [`sync_offset()`][Self::sync_offset()] to
+/// the slave oscillator's [`sync()`][Self::sync()]:
+///
+/// ```
+/// # use nih_plug::util::{Oscillator, Waveform};
+/// let mut master = Oscillator::new(48000.0, Waveform::Sine);
+/// let mut slave = Oscillator::new(48000.0, Waveform::Saw);
+/// master.set_frequency(110.0);
+/// slave.set_frequency(290.0);
+///
+/// for _ in 0..512 {
+///     master.next();
+///     if let Some(offset) = master.sync_offset() {
+///         slave.sync(offset);
+///     }
+///
+///     let _sample = slave.next();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Oscillator {
+    sample_rate: f32,
+    waveform: Waveform,
+    /// The phase in `[0, 1)` for the next sample.
+    phase: f32,
+    /// How much the phase increases every sample.
+    phase_delta: f32,
+
+    /// Corrections for discontinuities that happened between the last sample and the next sample
+    /// also affect the next sample. These are accumulated here.
+    next_correction: f32,
+    /// Set by [`sync()`][Self::sync()]. The phase gets reset to zero this many samples before the
+    /// sample after the next one.
+    pending_sync: Option<f32>,
+    /// The value returned by [`sync_offset()`][Self::sync_offset()].
+    sync_offset: Option<f32>,
+}
+
+/// The waveform produced by an [`Oscillator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Waveform {
+    Sine,
+    /// A rising sawtooth wave.
+    #[default]
+    Saw,
+    /// A square wave with a 50% duty cycle.
+    Square,
+    Triangle,
+}
+
+impl Oscillator {
+    /// Create a new oscillator. The frequency is zero until
+    /// [`set_frequency()`][Self::set_frequency()] is called.
+    pub fn new(sample_rate: f32, waveform: Waveform) -> Self {
+        Self {
+            sample_rate,
+            waveform,
+            phase: 0.0,
+            phase_delta: 0.0,
+
+            next_correction: 0.0,
+            pending_sync: None,
+            sync_offset: None,
+        }
+    }
+
+    /// Change the sample rate. This should be called from the plugin's `initialize()` function.
+    /// The oscillator's frequency stays the same.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        let frequency = self.frequency();
+        self.sample_rate = sample_rate;
+        self.set_frequency(frequency);
+    }
+
+    /// Change the oscillator's waveform. This takes effect immediately, so changing the waveform
+    /// while the oscillator is running may cause a click.
+    pub fn set_waveform(&mut self, waveform: Waveform) {
+        self.waveform = waveform;
+    }
+
+    /// Change the oscillator's frequency in Hz. This can be changed every sample for modulation.
+    /// The frequency is limited to just below the Nyquist frequency.
+    pub fn set_frequency(&mut self, frequency: f32) {
+        self.phase_delta = (frequency / self.sample_rate).clamp(0.0, MAX_PHASE_DELTA);
+    }
+
+    /// The oscillator's current frequency in Hz.
+    pub fn frequency(&self) -> f32 {
+        self.phase_delta * self.sample_rate
+    }
+
+    /// The oscillator's current waveform.
+    pub fn waveform(&self) -> Waveform {
+        self.waveform
+    }
+
+    /// Reset the oscillator's phase to the start of the cycle and discard any pending corrections
+    /// or syncs. Call this when a voice gets (re)triggered.
+    pub fn reset(&mut self) {
+        self.reset_to_phase(0.0);
+    }
+
+    /// Like [`reset()`][Self::reset()], but start at `phase` instead of at the start of the cycle.
+    /// The phase is a fraction of the cycle in `[0, 1)`. This can be used to randomize the phases
+    /// of unison voices.
+    pub fn reset_to_phase(&mut self, phase: f32) {
+        // `rem_euclid()` can round up to 1.0 for tiny negative phases
+        let phase = phase.rem_euclid(1.0);
+        self.phase = if phase < 1.0 { phase } else { 0.0 };
+        self.next_correction = 0.0;
+        self.pending_sync = None;
+        self.sync_offset = None;
+    }
+
+    /// Hard sync this oscillator to a master oscillator. The next call to [`next()`][Self::next()]
+    /// resets the phase `offset` samples before the sample after the one it returns. Pass the
+    /// master oscillator's [`sync_offset()`][Self::sync_offset()] here after generating the master
+    /// oscillator's sample.
+    pub fn sync(&mut self, offset: f32) {
+        self.pending_sync = Some(offset.clamp(0.0, 1.0));
+    }
+
+    /// If the oscillator's cycle started over between the sample returned by the last call to
+    /// [`next()`][Self::next()] and the next sample, then this returns how long before the next
+    /// sample that happened, in samples. This can be passed to another oscillator's
+    /// [`sync()`][Self::sync()] to hard sync it to this oscillator.
+    pub fn sync_offset(&self) -> Option<f32> {
+        self.sync_offset
+    }
+
+    /// Generate the next sample and advance the oscillator's phase.
+    #[allow(clippy::should_implement_trait)]
+    #[inline]
+    pub fn next(&mut self) -> f32 {
+        let mut sample = naive_value(self.waveform, self.phase) + self.next_correction;
+        self.next_correction = 0.0;
+        self.sync_offset = None;
+
+        let phase_delta = self.phase_delta;
+        match self.pending_sync.take() {
+            // The phase advances normally until the sync happens, jumps back to zero, and then
+            // advances for the remainder of the sample
+            Some(offset) => {
+                let mut sync_phase = self.phase + (phase_delta * (1.0 - offset));
+                self.add_edge_corrections(&mut sample, self.phase, sync_phase, offset);
+                if sync_phase >= 1.0 {
+                    sync_phase -= 1.0;
+                }
+
+                let value_jump =
+                    naive_value(self.waveform, 0.0) - naive_value(self.waveform, sync_phase);
+                let slope_jump = naive_slope(self.waveform, 0.0, phase_delta)
+                    - naive_slope(self.waveform, sync_phase, phase_delta);
+                self.add_correction(&mut sample, offset, value_jump, slope_jump);
+
+                self.phase = phase_delta * offset;
+                self.sync_offset = Some(offset);
+            }
+            None => {
+                let next_phase = self.phase + phase_delta;
+                self.add_edge_corrections(&mut sample, self.phase, next_phase, 0.0);
+
+                self.phase = if next_phase >= 1.0 {
+                    self.sync_offset = Some((next_phase - 1.0) / phase_delta);
+                    next_phase - 1.0
+                } else {
+                    next_phase
+                };
+            }
+        }
+
+        sample
+    }
+
+    /// Fill `output` with the oscillator's next samples.
+    pub fn next_block(&mut self, output: &mut [f32]) {
+        for sample in output {
+            *sample = self.next();
+        }
+    }
+
+    /// Add the corrections for the waveform's discontinuities when the phase advances from
+    /// `from_phase` to `to_phase`, where `to_phase` is reached `end_offset` samples before the
+    /// next sample. `to_phase` may be larger than one if the cycle starts over in between.
+    fn add_edge_corrections(
+        &mut self,
+        sample: &mut f32,
+        from_phase: f32,
+        to_phase: f32,
+        end_offset: f32,
+    ) {
+        if to_phase <= from_phase {
+            return;
+        }
+
+        // The phases at which the waveform has a discontinuity, and the jumps in value and slope
+        // at those points
+        let edges: &[(f32, f32, f32)] = match self.waveform {
+            Waveform::Sine => &[],
+            Waveform::Saw => &[(1.0, -2.0, 0.0)],
+            Waveform::Square => &[(0.5, -2.0, 0.0), (1.0, 2.0, 0.0)],
+            Waveform::Triangle => &[
+                (0.5, 0.0, -8.0 * self.phase_delta),
+                (1.0, 0.0, 8.0 * self.phase_delta),
+            ],
+        };
+        for &(edge_phase, value_jump, slope_jump) in edges {
+            if from_phase < edge_phase && to_phase >= edge_phase {
+                let offset = end_offset + ((to_phase - edge_phase) / self.phase_delta);
+                self.add_correction(sample, offset, value_jump, slope_jump);
+            }
+        }
+    }
+
+    /// Add the PolyBLEP and PolyBLAMP corrections for a discontinuity that happens `offset`
+    /// samples before the next sample to `sample` and to the next sample.
+    fn add_correction(&mut self, sample: &mut f32, offset: f32, value_jump: f32, slope_jump: f32) {
+        let offset = offset.clamp(0.0, 1.0);
+        let remainder = 1.0 - offset;
+
+        *sample += (value_jump * offset * offset / 2.0) + (slope_jump * offset.powi(3) / 6.0);
+        self.next_correction +=
+            (-value_jump * remainder * remainder / 2.0) + (slope_jump * remainder.powi(3) / 6.0);
+    }
+}
+
+/// The naive, aliasing waveform's value at `phase`.
+#[inline]
+fn naive_value(waveform: Waveform, phase: f32) -> f32 {
+    match waveform {
+        Waveform::Sine => (phase * TAU).sin(),
+        Waveform::Saw => (2.0 * phase) - 1.0,
+        Waveform::Square => {
+            if phase < 0.5 {
+                1.0
+            } else {
+                -1.0
+            }
+        }
+        Waveform::Triangle => 1.0 - (4.0 * (phase - 0.5).abs()),
+    }
+}
+
+/// The naive waveform's slope at `phase`, per sample.
+#[inline]
+fn naive_slope(waveform: Waveform, phase: f32, phase_delta: f32) -> f32 {
+    match waveform {
+        Waveform::Sine => TAU * phase_delta * (phase * TAU).cos(),
+        Waveform::Saw => 2.0 * phase_delta,
+        Waveform::Square => 0.0,
+        Waveform::Triangle => {
+            if phase < 0.5 {
+                4.0 * phase_delta
+            } else {
+                -4.0 * phase_delta
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+    /// The number of samples used for the spectra. This results in 10 Hz bins.
+    const NUM_SAMPLES: usize = 4800;
+
+    /// The ratio between the energy in the bins that don't contain any of `frequency`'s harmonics
+    /// and the energy in the bins that do, in decibels. `frequency` must be a multiple of the
+    /// 10 Hz bin size.
+    fn aliasing_db(signal: &[f32], frequency: f32) -> f32 {
+        let harmonic_spacing = (frequency / (SAMPLE_RATE / NUM_SAMPLES as f32)).round() as usize;
+        let (mut harmonic_energy, mut alias_energy) = (0.0f64, 0.0f64);
+        for bin in 1..NUM_SAMPLES / 2 {
+            let (mut re, mut im) = (0.0f64, 0.0f64);
+            for (n, sample) in signal.iter().enumerate() {
+                let angle =
+                    std::f64::consts::TAU * ((bin * n) % NUM_SAMPLES) as f64 / NUM_SAMPLES as f64;
+                re += *sample as f64 * angle.cos();
+                im -= *sample as f64 * angle.sin();
+            }
+
+            let energy = (re * re) + (im * im);
+            if bin % harmonic_spacing == 0 {
+                harmonic_energy += energy;
+            } else {
+                alias_energy += energy;
+            }
+        }
+
+        10.0 * (alias_energy / harmonic_energy).log10() as f32
+    }
+
+    /// Compare the aliasing of the band-limited and the naive versions of `waveform` at a high
+    /// frequency.
+    fn assert_reduced_aliasing(waveform: Waveform, min_improvement_db: f32) {
+        const FREQUENCY: f32 = 4410.0;
+
+        let mut oscillator = Oscillator::new(SAMPLE_RATE, waveform);
+        oscillator.set_frequency(FREQUENCY);
+        let mut band_limited = vec![0.0; NUM_SAMPLES];
+        oscillator.next_block(&mut band_limited);
+
+        let naive: Vec<f32> = (0..NUM_SAMPLES)
+            .map(|n| naive_value(waveform, (n as f32 * FREQUENCY / SAMPLE_RATE).fract()))
+            .collect();
+
+        let band_limited_db = aliasing_db(&band_limited, FREQUENCY);
+        let naive_db = aliasing_db(&naive, FREQUENCY);
+        assert!(
+            band_limited_db < naive_db - min_improvement_db,
+            "{waveform:?}: {band_limited_db} dB aliasing, naive: {naive_db} dB"
+        );
+    }
+
+    #[test]
+    fn saw_aliasing() {
+        assert_reduced_aliasing(Waveform::Saw, 10.0);
+    }
+
+    #[test]
+    fn square_aliasing() {
+        assert_reduced_aliasing(Waveform::Square, 10.0);
+    }
+
+    #[test]
+    fn triangle_aliasing() {
+        assert_reduced_aliasing(Waveform::Triangle, 10.0);
+    }
+
+    #[test]
+    fn hard_sync() {
+        // The master's period is exactly 128 samples, so the synced output should repeat every
+        // 128 samples even though the slave's frequency is not a multiple of the master's
+        let mut master = Oscillator::new(SAMPLE_RATE, Waveform::Sine);
+        let mut slave = Oscillator::new(SAMPLE_RATE, Waveform::Saw);
+        master.set_frequency(375.0);
+        slave.set_frequency(1234.5);
+
+        let output: Vec<f32> = (0..1000)
+            .map(|_| {
+                master.next();
+                if let Some(offset) = master.sync_offset() {
+                    slave.sync(offset);
+                }
+
+                slave.next()
+            })
+            .collect();
+
+        for n in 200..850 {
+            assert!(
+                (output[n] - output[n + 128]).abs() < 1e-3,
+                "{} != {} at sample {n}",
+                output[n],
+                output[n + 128]
+            );
+        }
+        assert!(output.iter().all(|sample| sample.abs() <= 1.1));
+    }
+
+    #[test]
+    fn reset() {
+        let mut oscillator = Oscillator::new(SAMPLE_RATE, Waveform::Square);
+        oscillator.set_frequency(1000.0);
+        let first: Vec<f32> = (0..100).map(|_| oscillator.next()).collect();
+        oscillator.reset();
+        let second: Vec<f32> = (0..100).map(|_| oscillator.next()).collect();
+
+        assert_eq!(first, second);
+    }
+}
@@ -0,0 +1,484 @@
+//! Polyphase FIR oversampling for nonlinear processing like saturation and distortion.
+
+use std::f64::consts;
+
+/// The number of taps in each 2x stage's anti-aliasing filter. `NUM_TAPS - 1` is divisible by 8 so
+/// the linear-phase filters' latency is an integer number of samples for every supported
+/// oversampling factor.
+const NUM_TAPS: usize = 97;
+/// The anti-aliasing filter's cutoff frequency relative to the oversampled sample rate. A value of
+/// 0.25 would be the original Nyquist frequency.
+const CUTOFF: f64 = 0.23;
+/// The Kaiser window's beta parameter. This results in roughly 85 dB of stopband attenuation.
+const KAISER_BETA: f64 = 8.0;
+/// The DFT size used to compute the minimum-phase filters.
+const MINIMUM_PHASE_DFT_SIZE: usize = 1024;
+
+/// Upsamples a single channel of audio, runs a function on the upsampled signal, and then
+/// downsamples the results again. The resampling is done with a cascade of 2x polyphase FIR
+/// stages. Use one oversampler per channel.
+///
+/// All buffers are allocated when the oversampler gets created, so [`process()`][Self::process()]
+/// does not allocate. Oversampling adds latency, which should be reported to the host using
+/// [`InitContext::set_latency_samples()`][crate::prelude::InitContext::set_latency_samples()] and
+/// [`Oversampler::latency_samples()`]:
+///
+/// ```ignore
+/// context.set_latency_samples(self.oversamplers[0].latency_samples());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Oversampler {
+    /// The 2x stages, from the lowest to the highest sample rate.
+    stages: Vec<OversamplingStage>,
+    /// The buffers containing the upsampled signal for each stage. `stage_buffers[i]` has room for
+    /// `max_block_size * 2^(i + 1)` samples.
+    stage_buffers: Vec<Vec<f32>>,
+    max_block_size: usize,
+    latency: u32,
+}
+
+/// The anti-aliasing filter design used by the [`Oversampler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OversamplingFilter {
+    /// Linear-phase filters preserve the signal's phase relationships, at the cost of higher
+    /// latency.
+    LinearPhase,
+    /// Minimum-phase filters have the same magnitude response as the linear-phase filters but much
+    /// lower latency. The phase response is not linear, and the reported latency is the filters'
+    /// group delay at DC.
+    MinimumPhase,
+}
+
+/// A single 2x upsampling and downsampling stage.
+#[derive(Debug, Clone)]
+struct OversamplingStage {
+    /// The anti-aliasing filter split into its even and odd phases. These have already been
+    /// multiplied by two to compensate for the zero stuffing.
+    upsampling_phases: [Vec<f32>; 2],
+    /// The full anti-aliasing filter used for downsampling.
+    downsampling_filter: Vec<f32>,
+    upsampling_history: History,
+    downsampling_history: History,
+}
+
+/// A history of the last `n` samples that can be read as a contiguous slice, with the most recent
+/// sample first. This uses a doubled ring buffer so reading the samples doesn't require any
+/// copying.
+#[derive(Debug, Clone)]
+struct History {
+    buffer: Vec<f32>,
+    len: usize,
+    pos: usize,
+}
+
+impl Oversampler {
+    /// Create an oversampler that upsamples the signal by `factor`, which must be 1, 2, 4, or 8.
+    /// With a factor of 1 the signal is passed to the processing function as is. Blocks larger than
+    /// `max_block_size` are processed in smaller chunks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `factor` is not one of the supported factors, or if `max_block_size == 0`.
+    pub fn new(factor: usize, max_block_size: usize, filter: OversamplingFilter) -> Self {
+        assert!(
+            matches!(factor, 1 | 2 | 4 | 8),
+            "Unsupported oversampling factor {factor}"
+        );
+        assert_ne!(max_block_size, 0);
+
+        let num_stages = factor.trailing_zeros() as usize;
+        let coefficients = match filter {
+            OversamplingFilter::LinearPhase => design_lowpass(),
+            OversamplingFilter::MinimumPhase => to_minimum_phase(&design_lowpass()),
+        };
+
+        // Each stage delays the signal by the filter's group delay twice, once when upsampling and
+        // once when downsampling. This is measured in the stage's oversampled sample rate.
+        let group_delay = coefficients
+            .iter()
+            .enumerate()
+            .map(|(i, coefficient)| i as f64 * coefficient)
+            .sum::<f64>()
+            / coefficients.iter().sum::<f64>();
+        let latency = (1..=num_stages)
+            .map(|stage_idx| 2.0 * group_delay / (1 << stage_idx) as f64)
+            .sum::<f64>()
+            .round() as u32;
+
+        let coefficients: Vec<f32> = coefficients.into_iter().map(|c| c as f32).collect();
+        let stages = (0..num_stages)
+            .map(|_| OversamplingStage::new(&coefficients))
+            .collect();
+        let stage_buffers = (1..=num_stages)
+            .map(|stage_idx| vec![0.0; max_block_size << stage_idx])
+            .collect();
+
+        Self {
+            stages,
+            stage_buffers,
+            max_block_size,
+            latency,
+        }
+    }
+
+    /// The oversampling factor.
+    pub fn factor(&self) -> usize {
+        1 << self.stages.len()
+    }
+
+    /// The latency introduced by the oversampling filters, in samples at the original sample rate.
+    pub fn latency_samples(&self) -> u32 {
+        self.latency
+    }
+
+    /// Clear the filters' histories. This should be called from the plugin's `reset()` function.
+    pub fn reset(&mut self) {
+        for stage in &mut self.stages {
+            stage.upsampling_history.reset();
+            stage.downsampling_history.reset();
+        }
+    }
+
+    /// Upsample `block`, run `process_cb` on the upsampled signal, and write the downsampled
+    /// results back to `block`. `process_cb` gets a slice containing `block.len() * factor`
+    /// samples, or fewer if the block is larger than the maximum block size.
+    pub fn process(&mut self, block: &mut [f32], mut process_cb: impl FnMut(&mut [f32])) {
+        if self.stages.is_empty() {
+            process_cb(block);
+            return;
+        }
+
+        for chunk in block.chunks_mut(self.max_block_size) {
+            let num_stages = self.stages.len();
+
+            // Upsample from the lowest to the highest sample rate. `split_at_mut()` is needed to
+            // be able to read from the previous stage's buffer while writing to the next one.
+            let (first_stage_buffer, _) = self.stage_buffers.split_first_mut().unwrap();
+            self.stages[0].upsample(chunk, &mut first_stage_buffer[..chunk.len() * 2]);
+            for stage_idx in 1..num_stages {
+                let (lower, higher) = self.stage_buffers.split_at_mut(stage_idx);
+                let input_len = chunk.len() << stage_idx;
+                self.stages[stage_idx].upsample(
+                    &lower[stage_idx - 1][..input_len],
+                    &mut higher[0][..input_len * 2],
+                );
+            }
+
+            let oversampled_len = chunk.len() << num_stages;
+            process_cb(&mut self.stage_buffers[num_stages - 1][..oversampled_len]);
+
+            // And then back down again
+            for stage_idx in (1..num_stages).rev() {
+                let (lower, higher) = self.stage_buffers.split_at_mut(stage_idx);
+                let output_len = chunk.len() << stage_idx;
+                self.stages[stage_idx].downsample(
+                    &higher[0][..output_len * 2],
+                    &mut lower[stage_idx - 1][..output_len],
+                );
+            }
+            self.stages[0].downsample(&self.stage_buffers[0][..chunk.len() * 2], chunk);
+        }
+    }
+}
+
+impl OversamplingStage {
+    fn new(coefficients: &[f32]) -> Self {
+        let upsampling_phases = [0, 1].map(|phase| {
+            coefficients
+                .iter()
+                .skip(phase)
+                .step_by(2)
+                .map(|c| c * 2.0)
+                .collect::<Vec<_>>()
+        });
+
+        Self {
+            upsampling_history: History::new(upsampling_phases[0].len()),
+            downsampling_history: History::new(coefficients.len()),
+            upsampling_phases,
+            downsampling_filter: coefficients.to_vec(),
+        }
+    }
+
+    /// Upsample `input` by a factor two into `output`, which must be twice the size of `input`.
+    fn upsample(&mut self, input: &[f32], output: &mut [f32]) {
+        nih_debug_assert_eq!(input.len() * 2, output.len());
+
+        for (sample, output) in input.iter().zip(output.chunks_exact_mut(2)) {
+            self.upsampling_history.push(*sample);
+            let history = self.upsampling_history.samples();
+
+            output[0] = dot_product(&self.upsampling_phases[0], history);
+            output[1] = dot_product(&self.upsampling_phases[1], history);
+        }
+    }
+
+    /// Downsample `input` by a factor two into `output`, which must be half the size of `input`.
+    fn downsample(&mut self, input: &[f32], output: &mut [f32]) {
+        nih_debug_assert_eq!(input.len(), output.len() * 2);
+
+        for (samples, output) in input.chunks_exact(2).zip(output.iter_mut()) {
+            // Only every other output sample needs to be computed
+            self.downsampling_history.push(samples[0]);
+            *output = dot_product(
+                &self.downsampling_filter,
+                self.downsampling_history.samples(),
+            );
+            self.downsampling_history.push(samples[1]);
+        }
+    }
+}
+
+impl History {
+    fn new(len: usize) -> Self {
+        Self {
+            buffer: vec![0.0; len * 2],
+            len,
+            pos: 0,
+        }
+    }
+
+    #[inline]
+    fn push(&mut self, sample: f32) {
+        self.pos = if self.pos == 0 {
+            self.len - 1
+        } else {
+            self.pos - 1
+        };
+
+        self.buffer[self.pos] = sample;
+        self.buffer[self.pos + self.len] = sample;
+    }
+
+    /// The last `len` samples, with the most recent sample first.
+    #[inline]
+    fn samples(&self) -> &[f32] {
+        &self.buffer[self.pos..self.pos + self.len]
+    }
+
+    fn reset(&mut self) {
+        self.buffer.fill(0.0);
+    }
+}
+
+#[inline]
+fn dot_product(coefficients: &[f32], samples: &[f32]) -> f32 {
+    coefficients
+        .iter()
+        .zip(samples)
+        .map(|(coefficient, sample)| coefficient * sample)
+        .sum()
+}
+
+/// Design the linear-phase anti-aliasing filter using a Kaiser windowed sinc function. The filter
+/// is normalized to have unity gain at DC.
+fn design_lowpass() -> Vec<f64> {
+    let center = (NUM_TAPS - 1) as f64 / 2.0;
+    let mut coefficients: Vec<f64> = (0..NUM_TAPS)
+        .map(|i| {
+            let x = i as f64 - center;
+            let sinc = if x == 0.0 {
+                2.0 * CUTOFF
+            } else {
+                (consts::TAU * CUTOFF * x).sin() / (consts::PI * x)
+            };
+            let window = bessel_i0(KAISER_BETA * (1.0 - (x / center).powi(2)).sqrt())
+                / bessel_i0(KAISER_BETA);
+
+            sinc * window
+        })
+        .collect();
+
+    let sum: f64 = coefficients.iter().sum();
+    for coefficient in &mut coefficients {
+        *coefficient /= sum;
+    }
+
+    coefficients
+}
+
+/// Convert a linear-phase filter to a minimum-phase filter with the same magnitude response using
+/// the real cepstrum. This only happens when creating the oversampler, so a naive DFT is fine here.
+fn to_minimum_phase(coefficients: &[f64]) -> Vec<f64> {
+    let n = MINIMUM_PHASE_DFT_SIZE;
+
+    let mut spectrum = vec![(0.0, 0.0); n];
+    for (bin, coefficient) in spectrum.iter_mut().zip(coefficients) {
+        *bin = (*coefficient, 0.0);
+    }
+    let spectrum = dft(&spectrum, false);
+
+    // The real cepstrum is the inverse DFT of the log magnitude spectrum. Folding the
+    // anti-causal part onto the causal part results in a minimum-phase signal.
+    let log_magnitudes: Vec<(f64, f64)> = spectrum
+        .iter()
+        .map(|&(re, im)| (re.hypot(im).max(1e-9).ln(), 0.0))
+        .collect();
+    let mut cepstrum = dft(&log_magnitudes, true);
+    for (i, bin) in cepstrum.iter_mut().enumerate() {
+        let scale = match i {
+            0 => 1.0,
+            i if i < n / 2 => 2.0,
+            i if i == n / 2 => 1.0,
+            _ => 0.0,
+        };
+        bin.0 *= scale;
+        bin.1 *= scale;
+    }
+
+    let exp_spectrum: Vec<(f64, f64)> = dft(&cepstrum, false)
+        .into_iter()
+        .map(|(re, im)| {
+            let magnitude = re.exp();
+            (magnitude * im.cos(), magnitude * im.sin())
+        })
+        .collect();
+
+    dft(&exp_spectrum, true)
+        .into_iter()
+        .take(coefficients.len())
+        .map(|(re, _)| re)
+        .collect()
+}
+
+/// A naive `O(n^2)` complex DFT using `(re, im)` tuples. The inverse DFT is normalized.
+fn dft(input: &[(f64, f64)], inverse: bool) -> Vec<(f64, f64)> {
+    let n = input.len();
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let twiddles: Vec<(f64, f64)> = (0..n)
+        .map(|k| {
+            let angle = sign * consts::TAU * k as f64 / n as f64;
+            (angle.cos(), angle.sin())
+        })
+        .collect();
+
+    (0..n)
+        .map(|k| {
+            let (mut re, mut im) = (0.0, 0.0);
+            for (j, &(x_re, x_im)) in input.iter().enumerate() {
+                let (t_re, t_im) = twiddles[(j * k) % n];
+                re += x_re * t_re - x_im * t_im;
+                im += x_re * t_im + x_im * t_re;
+            }
+
+            if inverse {
+                (re / n as f64, im / n as f64)
+            } else {
+                (re, im)
+            }
+        })
+        .collect()
+}
+
+/// The zeroth order modified Bessel function of the first kind, used for the Kaiser window.
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let mut k = 1.0;
+    while term > sum * 1e-12 {
+        term *= (x / (2.0 * k)).powi(2);
+        sum += term;
+        k += 1.0;
+    }
+
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+
+    fn sine(frequency: f32, num_samples: usize) -> Vec<f32> {
+        (0..num_samples)
+            .map(|i| (std::f32::consts::TAU * frequency * i as f32 / SAMPLE_RATE).sin())
+            .collect()
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|x| x * x).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn latency() {
+        for (factor, expected_latency) in [(1, 0), (2, 48), (4, 72), (8, 84)] {
+            let linear_phase = Oversampler::new(factor, 64, OversamplingFilter::LinearPhase);
+            assert_eq!(linear_phase.factor(), factor);
+            assert_eq!(linear_phase.latency_samples(), expected_latency);
+
+            let minimum_phase = Oversampler::new(factor, 64, OversamplingFilter::MinimumPhase);
+            assert!(minimum_phase.latency_samples() <= expected_latency / 4);
+        }
+    }
+
+    #[test]
+    fn linear_phase_delayed_identity() {
+        for factor in [2, 4, 8] {
+            let mut oversampler = Oversampler::new(factor, 100, OversamplingFilter::LinearPhase);
+            let latency = oversampler.latency_samples() as usize;
+
+            let input = sine(1_000.0, 4_800);
+            let mut output = input.clone();
+            let mut num_oversampled_samples = 0;
+            for block in output.chunks_mut(64) {
+                oversampler.process(block, |oversampled| {
+                    num_oversampled_samples += oversampled.len()
+                });
+            }
+            assert_eq!(num_oversampled_samples, input.len() * factor);
+
+            for i in (latency + 1_000)..input.len() {
+                assert!(
+                    (output[i] - input[i - latency]).abs() < 1e-3,
+                    "{factor}x at sample {i}: expected {}, got {}",
+                    input[i - latency],
+                    output[i]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn minimum_phase_passband() {
+        let mut oversampler = Oversampler::new(4, 512, OversamplingFilter::MinimumPhase);
+
+        for frequency in [100.0, 1_000.0, 10_000.0] {
+            oversampler.reset();
+            let input = sine(frequency, 9_600);
+            let mut output = input.clone();
+            oversampler.process(&mut output, |_| ());
+
+            let gain_db = 20.0 * (rms(&output[4_800..]) / rms(&input[4_800..])).log10();
+            assert!(gain_db.abs() < 0.05, "{frequency} Hz: {gain_db} dB");
+        }
+    }
+
+    #[test]
+    fn removes_aliasing() {
+        for filter in [
+            OversamplingFilter::LinearPhase,
+            OversamplingFilter::MinimumPhase,
+        ] {
+            // The processing function replaces the signal with a tone above the original Nyquist
+            // frequency, which would alias back to 12 kHz without the downsampling filter
+            let mut oversampler = Oversampler::new(2, 512, filter);
+            let aliasing_tone = (0..9_600)
+                .map(|i| (std::f32::consts::TAU * 36_000.0 * i as f32 / (SAMPLE_RATE * 2.0)).sin())
+                .collect::<Vec<_>>();
+
+            let mut output = vec![0.0; 4_800];
+            let mut position = 0;
+            for block in output.chunks_mut(480) {
+                oversampler.process(block, |oversampled| {
+                    let len = oversampled.len();
+                    oversampled.copy_from_slice(&aliasing_tone[position..position + len]);
+                    position += len;
+                });
+            }
+
+            let level_db = 20.0 * rms(&output[1_000..]).log10();
+            assert!(level_db < -70.0, "{filter:?}: {level_db} dB");
+        }
+    }
+}
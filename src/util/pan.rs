@@ -0,0 +1,158 @@
+//! Panning and crossfading gain curves.
+
+use std::f32::consts::FRAC_PI_2;
+
+/// The number of segments in an [`EqualPowerTable`]. The table contains one more entry than this so
+/// both ends of the curve are stored exactly.
+const TABLE_SIZE: usize = 1024;
+
+/// Compute the left and right channel gains for panning a signal to `position`, where `-1.0` is
+/// hard left, `0.0` is the center, and `1.0` is hard right. This uses a sine/cosine curve so the
+/// signal's total power stays the same regardless of the position. Both channels are at -3 dB when
+/// the signal is panned to the center. Positions outside of `[-1, 1]` are clamped.
+#[inline]
+pub fn equal_power_pan(position: f32) -> (f32, f32) {
+    equal_power_crossfade((position.clamp(-1.0, 1.0) + 1.0) * 0.5)
+}
+
+/// Compute the left and right channel gains for panning a signal to `position`, where `-1.0` is
+/// hard left and `1.0` is hard right. The gains always add up to one, so both channels are at
+/// -6 dB when the signal is panned to the center. Positions outside of `[-1, 1]` are clamped.
+#[inline]
+pub fn linear_pan(position: f32) -> (f32, f32) {
+    linear_crossfade((position.clamp(-1.0, 1.0) + 1.0) * 0.5)
+}
+
+/// Compute the dry and wet gains for a dry/wet mix, where `0.0` is fully dry and `1.0` is fully
+/// wet. This uses a sine/cosine curve so the total power stays the same when mixing two
+/// uncorrelated signals, which avoids a dip in loudness in the middle of the crossfade. Mixes
+/// outside of `[0, 1]` are clamped.
+#[inline]
+pub fn equal_power_crossfade(mix: f32) -> (f32, f32) {
+    let (wet, dry) = (mix.clamp(0.0, 1.0) * FRAC_PI_2).sin_cos();
+
+    (dry, wet)
+}
+
+/// Compute the dry and wet gains for a dry/wet mix, where `0.0` is fully dry and `1.0` is fully
+/// wet. The gains always add up to one, which is the correct choice when mixing correlated
+/// signals, like a dry signal with a filtered version of itself. Mixes outside of `[0, 1]` are
+/// clamped.
+#[inline]
+pub fn linear_crossfade(mix: f32) -> (f32, f32) {
+    let mix = mix.clamp(0.0, 1.0);
+
+    (1.0 - mix, mix)
+}
+
+/// A lookup table based version of [`equal_power_pan()`] and [`equal_power_crossfade()`] for when
+/// the gains need to be computed for every sample. The gains are linearly interpolated from a
+/// table of the curve and they are within `1e-6` of the exact values. The table is stored inline,
+/// so this object should be created once and stored on the plugin instead of being recreated
+/// in the process function.
+#[derive(Debug, Clone)]
+pub struct EqualPowerTable {
+    /// `sin(x * pi / 2)` for `x` in `[0, 1]`.
+    table: [f32; TABLE_SIZE + 1],
+}
+
+impl Default for EqualPowerTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EqualPowerTable {
+    /// Compute the lookup table.
+    pub fn new() -> Self {
+        let mut table = [0.0; TABLE_SIZE + 1];
+        for (idx, value) in table.iter_mut().enumerate() {
+            *value = ((idx as f64 / TABLE_SIZE as f64) * std::f64::consts::FRAC_PI_2).sin() as f32;
+        }
+
+        Self { table }
+    }
+
+    /// The same as [`equal_power_pan()`], but using the lookup table.
+    #[inline]
+    pub fn pan(&self, position: f32) -> (f32, f32) {
+        self.crossfade((position.clamp(-1.0, 1.0) + 1.0) * 0.5)
+    }
+
+    /// The same as [`equal_power_crossfade()`], but using the lookup table.
+    #[inline]
+    pub fn crossfade(&self, mix: f32) -> (f32, f32) {
+        let mix = mix.clamp(0.0, 1.0);
+
+        (self.lookup(1.0 - mix), self.lookup(mix))
+    }
+
+    /// Look up `sin(x * pi / 2)` for `x` in `[0, 1]`.
+    #[inline]
+    fn lookup(&self, x: f32) -> f32 {
+        let pos = x * TABLE_SIZE as f32;
+        let idx = (pos as usize).min(TABLE_SIZE - 1);
+        let t = pos - idx as f32;
+
+        self.table[idx] + ((self.table[idx + 1] - self.table[idx]) * t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::gain_to_db;
+
+    /// Positions spread evenly over `[-1, 1]`.
+    fn positions() -> impl Iterator<Item = f32> {
+        (0..=200).map(|idx| (idx as f32 / 100.0) - 1.0)
+    }
+
+    #[test]
+    fn equal_power_center() {
+        let (left, right) = equal_power_pan(0.0);
+        assert_eq!(left, right);
+        approx::assert_relative_eq!(gain_to_db(left), -3.0103, epsilon = 1e-4);
+
+        let (dry, wet) = equal_power_crossfade(0.5);
+        approx::assert_relative_eq!(gain_to_db(dry), -3.0103, epsilon = 1e-4);
+        approx::assert_relative_eq!(gain_to_db(wet), -3.0103, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn equal_power_sum() {
+        for position in positions() {
+            let (left, right) = equal_power_pan(position);
+            approx::assert_relative_eq!(left * left + right * right, 1.0, epsilon = 1e-6);
+        }
+
+        assert_eq!(equal_power_pan(-1.0), (1.0, 0.0));
+        assert_eq!(equal_power_crossfade(2.0), equal_power_crossfade(1.0));
+    }
+
+    #[test]
+    fn linear_sum() {
+        for position in positions() {
+            let (left, right) = linear_pan(position);
+            approx::assert_relative_eq!(left + right, 1.0, epsilon = 1e-6);
+        }
+
+        assert_eq!(linear_pan(0.0), (0.5, 0.5));
+        assert_eq!(linear_crossfade(-1.0), (1.0, 0.0));
+        assert_eq!(linear_crossfade(0.25), (0.75, 0.25));
+    }
+
+    #[test]
+    fn lookup_table() {
+        let table = EqualPowerTable::new();
+        for position in positions() {
+            let (left, right) = table.pan(position);
+            let (expected_left, expected_right) = equal_power_pan(position);
+            approx::assert_relative_eq!(left, expected_left, epsilon = 1e-6);
+            approx::assert_relative_eq!(right, expected_right, epsilon = 1e-6);
+        }
+
+        assert_eq!(table.crossfade(0.0), (1.0, 0.0));
+        assert_eq!(table.crossfade(1.0), (0.0, 1.0));
+    }
+}
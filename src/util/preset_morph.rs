@@ -0,0 +1,442 @@
+//! Gradually morphing the current parameter values into a preset's values.
+
+use std::time::Duration;
+
+use crate::prelude::{ParamFlags, ParamPtr, ParamSetter, Params, PluginState};
+use crate::wrapper::state::ParamValue;
+
+/// Smoothly morphs the plugin's parameters from their current values to the values stored in a
+/// [`PluginState`] over a fixed amount of time, like the preset morphing found in many pad and
+/// texture synthesizers. This is driven from the plugin's GUI by calling
+/// [`advance()`][Self::advance()] regularly, for instance every frame.
+///
+/// Every morphed parameter is changed in a single automation gesture that is started in
+/// [`start()`][Self::start()] and that ends when the morph finishes or when it is
+/// [cancelled][Self::cancel()], so the morph shows up as one change in the host's automation and
+/// undo history. Every step only sends the values that changed since the previous step. The
+/// smoothers are not reset between steps, so the parameters glide from one step to the next. How a
+/// parameter is morphed depends on its type:
+///
+/// - Float and integer parameters are interpolated linearly in the normalized domain, so a
+///   frequency parameter with a logarithmic range also morphs logarithmically. Stepped parameters
+///   are snapped to the nearest step.
+/// - Boolean and enum parameters can't be interpolated. They switch to the target value halfway
+///   through the morph.
+///
+/// Only parameters that are part of the target state and that have a different value are morphed.
+/// The bypass and gain reduction parameters are never morphed, and persistent fields are left
+/// untouched. Use [`GuiContext::set_state()`][crate::prelude::GuiContext::set_state()] to load
+/// an entire state at once instead. Since the morphed parameters' gestures stay open during the
+/// morph, the morph should be cancelled before the user changes one of those parameters in another
+/// way, and before the editor is closed.
+///
+/// ```ignore
+/// // When the user picks a preset
+/// morph.start(&setter, params.as_ref(), &preset_state, Duration::from_secs(2));
+///
+/// // And then in the GUI's frame or idle callback
+/// morph.advance(&setter, frame_time);
+/// ```
+#[derive(Debug, Default)]
+pub struct PresetMorph {
+    /// The parameters that are being morphed.
+    params: Vec<MorphedParam>,
+    /// The morph's total duration.
+    duration: Duration,
+    /// How much time has passed since the morph started.
+    elapsed: Duration,
+}
+
+/// A parameter that is being morphed and its start and end points.
+#[derive(Debug, Clone, Copy)]
+struct MorphedParam {
+    param: ParamPtr,
+    /// The parameter's normalized value when the morph started.
+    start: f32,
+    /// The normalized value the parameter should have at the end of the morph.
+    target: f32,
+    /// Whether this parameter switches to its target value halfway through the morph instead of
+    /// being interpolated. Used for boolean and enum parameters.
+    switch_at_midpoint: bool,
+    /// The normalized value that was last sent to the host.
+    last_value: f32,
+}
+
+impl PresetMorph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start morphing the parameters in `params` towards the values in `target` over `duration`.
+    /// This begins an automation gesture for every parameter that will be morphed. The morph
+    /// starts from the parameters' current values. Calling this while another morph is active
+    /// retargets that morph: the previous morph's gestures are ended, the new morph picks up from
+    /// wherever the previous morph left the parameters, and the timer starts over.
+    pub fn start<P: Params + ?Sized>(
+        &mut self,
+        setter: &ParamSetter,
+        params: &P,
+        target: &PluginState,
+        duration: Duration,
+    ) {
+        self.cancel(setter);
+
+        let excluded_flags = ParamFlags::BYPASS | ParamFlags::GAIN_REDUCTION;
+
+        self.params = params
+            .param_map()
+            .into_iter()
+            .filter(|(_, param, _)| !unsafe { param.flags() }.intersects(excluded_flags))
+            .filter_map(|(param_id, param, _)| {
+                let value = target.params.get(&param_id)?;
+                let target = unsafe { target_normalized_value(param, value) };
+                if target.is_none() {
+                    nih_debug_assert_failure!(
+                        "Invalid morph target {:?} for parameter \"{}\"",
+                        value,
+                        param_id
+                    );
+                }
+
+                let start = unsafe { param.unmodulated_normalized_value() };
+                Some(MorphedParam {
+                    param,
+                    start,
+                    target: target?,
+                    switch_at_midpoint: matches!(
+                        param,
+                        ParamPtr::BoolParam(_) | ParamPtr::EnumParam(_)
+                    ),
+                    last_value: start,
+                })
+            })
+            .filter(|morphed_param| morphed_param.start != morphed_param.target)
+            .collect();
+        self.duration = duration;
+        self.elapsed = Duration::ZERO;
+
+        for morphed_param in &self.params {
+            unsafe {
+                setter
+                    .raw_context
+                    .raw_begin_set_parameter(morphed_param.param)
+            };
+        }
+    }
+
+    /// Stop the active morph and end the morphed parameters' automation gestures. The parameters
+    /// keep the values they currently have. Does nothing if there is no active morph.
+    pub fn cancel(&mut self, setter: &ParamSetter) {
+        for morphed_param in self.params.drain(..) {
+            unsafe {
+                setter
+                    .raw_context
+                    .raw_end_set_parameter(morphed_param.param)
+            };
+        }
+    }
+
+    /// Whether a morph is in progress.
+    pub fn is_active(&self) -> bool {
+        !self.params.is_empty()
+    }
+
+    /// How far along the active morph is, between 0 and 1. Returns 1 if there is no active morph.
+    pub fn progress(&self) -> f32 {
+        if !self.is_active() || self.duration.is_zero() {
+            1.0
+        } else {
+            (self.elapsed.as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+        }
+    }
+
+    /// Advance the active morph by `delta` and set the parameters whose values changed to their new
+    /// values. Once the morph's duration has passed the parameters are set to the exact target
+    /// values, their gestures are ended, and the morph stops. Returns whether the morph is still
+    /// active afterwards. Does nothing if there is no active morph.
+    pub fn advance(&mut self, setter: &ParamSetter, delta: Duration) -> bool {
+        if !self.is_active() {
+            return false;
+        }
+
+        self.elapsed += delta;
+        let progress = self.progress();
+        for morphed_param in &mut self.params {
+            let value = morphed_param.value_at(progress);
+            if value != morphed_param.last_value {
+                unsafe {
+                    setter
+                        .raw_context
+                        .raw_set_parameter_normalized(morphed_param.param, value)
+                };
+                morphed_param.last_value = value;
+            }
+        }
+
+        if progress >= 1.0 {
+            self.cancel(setter);
+        }
+
+        self.is_active()
+    }
+}
+
+impl MorphedParam {
+    /// The parameter's normalized value when the morph is at `progress`.
+    fn value_at(&self, progress: f32) -> f32 {
+        if progress >= 1.0 {
+            self.target
+        } else if self.switch_at_midpoint {
+            if progress >= 0.5 {
+                self.target
+            } else {
+                self.start
+            }
+        } else {
+            let normalized = self.start + ((self.target - self.start) * progress);
+
+            // Stepped parameters are snapped to the nearest step
+            unsafe {
+                self.param
+                    .preview_normalized(self.param.preview_plain(normalized))
+            }
+        }
+    }
+}
+
+/// Convert a parameter value from a [`PluginState`] to a normalized value for `param`. Returns
+/// `None` if the value's type doesn't match the parameter, or if the enum variant ID is unknown.
+unsafe fn target_normalized_value(param: ParamPtr, value: &ParamValue) -> Option<f32> {
+    match (param, value) {
+        (ParamPtr::FloatParam(_), ParamValue::F32(plain)) => Some(param.preview_normalized(*plain)),
+        (ParamPtr::IntParam(_) | ParamPtr::EnumParam(_), ParamValue::I32(plain)) => {
+            Some(param.preview_normalized(*plain as f32))
+        }
+        (ParamPtr::BoolParam(_), ParamValue::Bool(plain)) => {
+            Some(param.preview_normalized(*plain as u8 as f32))
+        }
+        (ParamPtr::EnumParam(p), ParamValue::String(id)) => {
+            let index = (*p).index_from_id(id)?;
+            Some(param.preview_normalized(index as f32))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::prelude::{
+        BoolParam, Enum, EnumParam, FloatParam, FloatRange, IntParam, IntRange, Param,
+    };
+    use crate::testing::{GuiContextCall, TestGuiContext};
+
+    #[derive(Enum, Debug, PartialEq)]
+    enum Waveform {
+        #[id = "sine"]
+        Sine,
+        #[id = "saw"]
+        Saw,
+        #[id = "square"]
+        Square,
+    }
+
+    struct TestParams {
+        cutoff: FloatParam,
+        voices: IntParam,
+        waveform: EnumParam<Waveform>,
+        unison: BoolParam,
+        bypass: BoolParam,
+    }
+
+    unsafe impl Params for TestParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            vec![
+                (String::from("cutoff"), self.cutoff.as_ptr(), String::new()),
+                (String::from("voices"), self.voices.as_ptr(), String::new()),
+                (
+                    String::from("waveform"),
+                    self.waveform.as_ptr(),
+                    String::new(),
+                ),
+                (String::from("unison"), self.unison.as_ptr(), String::new()),
+                (String::from("bypass"), self.bypass.as_ptr(), String::new()),
+            ]
+        }
+    }
+
+    impl TestParams {
+        fn new() -> Self {
+            Self {
+                cutoff: FloatParam::new(
+                    "Cutoff",
+                    128.0,
+                    FloatRange::Linear {
+                        min: 0.0,
+                        max: 1024.0,
+                    },
+                ),
+                voices: IntParam::new("Voices", 1, IntRange::Linear { min: 1, max: 9 }),
+                waveform: EnumParam::new("Waveform", Waveform::Sine),
+                unison: BoolParam::new("Unison", false),
+                bypass: BoolParam::new("Bypass", false).make_bypass(),
+            }
+        }
+    }
+
+    fn target_state() -> PluginState {
+        PluginState {
+            version: String::new(),
+            params: BTreeMap::from([
+                (String::from("cutoff"), ParamValue::F32(896.0)),
+                (String::from("voices"), ParamValue::I32(9)),
+                (
+                    String::from("waveform"),
+                    ParamValue::String(String::from("square")),
+                ),
+                (String::from("unison"), ParamValue::Bool(true)),
+                (String::from("bypass"), ParamValue::Bool(true)),
+            ]),
+            fields: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn reaches_target_after_duration() {
+        let params = Arc::new(TestParams::new());
+        let context = TestGuiContext::new(params.clone());
+        let setter = ParamSetter::new(&context);
+        let mut morph = PresetMorph::new();
+        let cutoff = params.cutoff.as_ptr();
+        let voices = params.voices.as_ptr();
+        let waveform = params.waveform.as_ptr();
+        let unison = params.unison.as_ptr();
+
+        // Every morphed parameter gets a single gesture for the entire morph. The bypass parameter
+        // is never morphed.
+        morph.start(&setter, &params, &target_state(), Duration::from_secs(1));
+        assert!(morph.is_active());
+        assert_eq!(morph.progress(), 0.0);
+        assert_eq!(
+            context.calls(),
+            [cutoff, voices, waveform, unison].map(GuiContextCall::BeginSetParameter)
+        );
+        context.clear_calls();
+
+        // Bools and enums switch halfway through, everything else is interpolated. Only values
+        // that changed are sent.
+        assert!(morph.advance(&setter, Duration::from_millis(250)));
+        assert_eq!(morph.progress(), 0.25);
+        assert_eq!(params.cutoff.value(), 320.0);
+        assert_eq!(params.voices.value(), 3);
+        assert_eq!(params.waveform.value(), Waveform::Sine);
+        assert!(!params.unison.value());
+        assert_eq!(
+            context.calls(),
+            [
+                GuiContextCall::SetParameterNormalized(cutoff, 0.3125),
+                GuiContextCall::SetParameterNormalized(voices, 0.25),
+            ]
+        );
+        context.clear_calls();
+
+        assert!(morph.advance(&setter, Duration::from_millis(250)));
+        assert_eq!(params.waveform.value(), Waveform::Square);
+        assert!(params.unison.value());
+        assert_eq!(
+            context.calls(),
+            [
+                GuiContextCall::SetParameterNormalized(cutoff, 0.5),
+                GuiContextCall::SetParameterNormalized(voices, 0.5),
+                GuiContextCall::SetParameterNormalized(waveform, 1.0),
+                GuiContextCall::SetParameterNormalized(unison, 1.0),
+            ]
+        );
+        context.clear_calls();
+
+        // The booleans and enums are not sent again, and the gestures end with the morph
+        assert!(!morph.advance(&setter, Duration::from_millis(500)));
+        assert!(!morph.is_active());
+        assert_eq!(params.cutoff.value(), 896.0);
+        assert_eq!(params.voices.value(), 9);
+        assert_eq!(params.waveform.value(), Waveform::Square);
+        assert!(!params.bypass.value());
+        assert_eq!(
+            context.calls(),
+            [
+                GuiContextCall::SetParameterNormalized(cutoff, 0.875),
+                GuiContextCall::SetParameterNormalized(voices, 1.0),
+                GuiContextCall::EndSetParameter(cutoff),
+                GuiContextCall::EndSetParameter(voices),
+                GuiContextCall::EndSetParameter(waveform),
+                GuiContextCall::EndSetParameter(unison),
+            ]
+        );
+    }
+
+    #[test]
+    fn cancel_and_retarget() {
+        let params = Arc::new(TestParams::new());
+        let context = TestGuiContext::new(params.clone());
+        let setter = ParamSetter::new(&context);
+        let mut morph = PresetMorph::new();
+        let cutoff = params.cutoff.as_ptr();
+        let voices = params.voices.as_ptr();
+        let waveform = params.waveform.as_ptr();
+        let unison = params.unison.as_ptr();
+
+        // Cancelling ends the gestures
+        morph.start(&setter, &params, &target_state(), Duration::from_secs(1));
+        morph.advance(&setter, Duration::from_millis(500));
+        context.clear_calls();
+        morph.cancel(&setter);
+        assert!(!morph.is_active());
+        assert_eq!(
+            context.calls(),
+            [cutoff, voices, waveform, unison].map(GuiContextCall::EndSetParameter)
+        );
+        assert!(!morph.advance(&setter, Duration::from_millis(500)));
+        assert_eq!(params.cutoff.value(), 512.0);
+
+        // Retargeting picks up from the current values, and it ends the previous morph's gestures
+        // before starting new ones
+        let mut state = target_state();
+        state
+            .params
+            .insert(String::from("cutoff"), ParamValue::F32(0.0));
+        morph.start(&setter, &params, &state, Duration::from_secs(1));
+        context.clear_calls();
+        morph.start(&setter, &params, &state, Duration::from_secs(1));
+        assert_eq!(
+            context.calls(),
+            [
+                GuiContextCall::EndSetParameter(cutoff),
+                GuiContextCall::EndSetParameter(voices),
+                GuiContextCall::BeginSetParameter(cutoff),
+                GuiContextCall::BeginSetParameter(voices),
+            ]
+        );
+        morph.advance(&setter, Duration::from_millis(500));
+        assert_eq!(params.cutoff.value(), 256.0);
+
+        // A zero duration jumps straight to the target
+        morph.start(&setter, &params, &target_state(), Duration::ZERO);
+        assert!(!morph.advance(&setter, Duration::ZERO));
+        assert_eq!(params.cutoff.value(), 896.0);
+        assert_eq!(
+            context
+                .calls()
+                .iter()
+                .filter(|call| matches!(call, GuiContextCall::BeginSetParameter(_)))
+                .count(),
+            context
+                .calls()
+                .iter()
+                .filter(|call| matches!(call, GuiContextCall::EndSetParameter(_)))
+                .count(),
+        );
+    }
+}
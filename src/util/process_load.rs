@@ -0,0 +1,154 @@
+use atomic_float::AtomicF32;
+use std::sync::atomic::Ordering;
+#[cfg(feature = "process_profiling")]
+use std::time::{Duration, Instant};
+
+/// The time constant for [`ProcessLoad::average()`], in seconds.
+#[cfg(feature = "process_profiling")]
+const AVERAGE_TIME_CONSTANT: f32 = 0.5;
+
+/// Measurements of how long the plugin's process function takes, relative to the duration of the
+/// audio that was processed. A load of 1.0 means that processing a block took just as long as
+/// playing it back, at which point the plugin can no longer run in realtime. These measurements
+/// include the time spent in NIH-plug's wrappers.
+///
+/// The measurements are only taken when the `process_profiling` feature is enabled, so this
+/// instrumentation is free otherwise. Use
+/// [`GuiContext::process_load()`][crate::prelude::GuiContext::process_load()] to get this object
+/// from a plugin's editor, for instance to show a CPU meter.
+pub struct ProcessLoad {
+    /// The load of the most recent process call.
+    last: AtomicF32,
+    /// An exponential moving average of the load.
+    average: AtomicF32,
+    /// The highest load since the last call to [`reset_max()`][Self::reset_max()].
+    max: AtomicF32,
+}
+
+/// Records the duration of a process call when dropped. Created using
+/// [`ProcessLoad::start_measurement()`].
+#[must_use]
+pub(crate) struct ProcessLoadMeasurement<'a> {
+    #[cfg(feature = "process_profiling")]
+    process_load: &'a ProcessLoad,
+    #[cfg(feature = "process_profiling")]
+    start: Instant,
+    /// The duration of the audio being processed, in seconds.
+    #[cfg(feature = "process_profiling")]
+    block_duration: f32,
+    #[cfg(not(feature = "process_profiling"))]
+    _process_load: std::marker::PhantomData<&'a ProcessLoad>,
+}
+
+impl Default for ProcessLoad {
+    fn default() -> Self {
+        Self {
+            last: AtomicF32::new(0.0),
+            average: AtomicF32::new(0.0),
+            max: AtomicF32::new(0.0),
+        }
+    }
+}
+
+impl ProcessLoad {
+    /// Whether NIH-plug was compiled with the `process_profiling` feature. If it wasn't, then all
+    /// measurements stay at zero.
+    pub const ENABLED: bool = cfg!(feature = "process_profiling");
+
+    /// The load of the most recent process call.
+    pub fn last(&self) -> f32 {
+        self.last.load(Ordering::Relaxed)
+    }
+
+    /// The load averaged over roughly the last half second.
+    pub fn average(&self) -> f32 {
+        self.average.load(Ordering::Relaxed)
+    }
+
+    /// The highest load since the plugin was created or since [`reset_max()`][Self::reset_max()]
+    /// was last called.
+    pub fn max(&self) -> f32 {
+        self.max.load(Ordering::Relaxed)
+    }
+
+    /// Reset the value returned by [`max()`][Self::max()].
+    pub fn reset_max(&self) {
+        self.max.store(0.0, Ordering::Relaxed);
+    }
+
+    /// Start timing a process call for `num_samples` samples. The measurement is recorded when the
+    /// returned object is dropped. Does nothing if the `process_profiling` feature is disabled.
+    #[allow(unused_variables)]
+    pub(crate) fn start_measurement(
+        &self,
+        num_samples: usize,
+        sample_rate: f32,
+    ) -> ProcessLoadMeasurement<'_> {
+        ProcessLoadMeasurement {
+            #[cfg(feature = "process_profiling")]
+            process_load: self,
+            #[cfg(feature = "process_profiling")]
+            start: Instant::now(),
+            #[cfg(feature = "process_profiling")]
+            block_duration: num_samples as f32 / sample_rate,
+            #[cfg(not(feature = "process_profiling"))]
+            _process_load: std::marker::PhantomData,
+        }
+    }
+
+    /// Record the time it took to process `block_duration` seconds of audio.
+    #[cfg(feature = "process_profiling")]
+    fn record(&self, elapsed: Duration, block_duration: f32) {
+        // Empty blocks, or process calls before the sample rate is known
+        if block_duration <= 0.0 || !block_duration.is_finite() {
+            return;
+        }
+
+        let load = elapsed.as_secs_f32() / block_duration;
+        self.last.store(load, Ordering::Relaxed);
+        // The average uses a time constant so it behaves the same regardless of the block size
+        let alpha = 1.0 - (-block_duration / AVERAGE_TIME_CONSTANT).exp();
+        let average = self.average.load(Ordering::Relaxed);
+        self.average
+            .store(average + ((load - average) * alpha), Ordering::Relaxed);
+        if load > self.max() {
+            self.max.store(load, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(feature = "process_profiling")]
+impl Drop for ProcessLoadMeasurement<'_> {
+    fn drop(&mut self) {
+        self.process_load
+            .record(self.start.elapsed(), self.block_duration);
+    }
+}
+
+#[cfg(all(test, feature = "process_profiling"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measure_load() {
+        let process_load = ProcessLoad::default();
+        // Processing a 125 ms block in 62.5 ms is a 50% load
+        process_load.record(Duration::from_micros(31_250), 0.125);
+        process_load.record(Duration::from_micros(62_500), 0.125);
+
+        assert_eq!(process_load.last(), 0.5);
+        assert_eq!(process_load.max(), 0.5);
+        assert!(process_load.average() > 0.0 && process_load.average() < 0.5);
+
+        process_load.reset_max();
+        assert_eq!(process_load.max(), 0.0);
+    }
+
+    #[test]
+    fn ignore_empty_blocks() {
+        let process_load = ProcessLoad::default();
+        process_load.record(Duration::from_millis(1), 0.0);
+
+        assert_eq!(process_load.last(), 0.0);
+    }
+}
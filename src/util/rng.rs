@@ -0,0 +1,161 @@
+//! A small, fast random number generator for noise and dither.
+
+use std::f32::consts::TAU;
+
+/// The multiplier for PCG's underlying linear congruential generator.
+const PCG_MULTIPLIER: u64 = 6364136223846793005;
+/// The stream used by [`Rng::new()`].
+const DEFAULT_STREAM: u64 = 0xda3e39cb94b95bdb;
+
+/// A PCG32 random number generator (see <https://www.pcg-random.org>). This is fast, small, and it
+/// never allocates, so it can be used freely from the audio thread. The generator is fully
+/// deterministic: two generators created with the same seed and stream produce the same sequence,
+/// which makes offline renders reproducible. Use [`for_voice()`][Self::for_voice()] to give every
+/// voice its own independent sequence derived from a single seed.
+/// [`next_bipolar_f32()`][Self::next_bipolar_f32()] produces white noise, and
+/// [`PinkNoise`][super::PinkNoise] and [`BrownNoise`][super::BrownNoise] produce spectrally shaped
+/// noise.
+///
+/// This is **not** a cryptographically secure random number generator.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+    /// Selects one of the 2^63 possible sequences. This is always odd.
+    increment: u64,
+    /// Gaussian samples are generated in pairs. This contains the second sample of the last pair,
+    /// if it has not been used yet.
+    spare_gaussian: Option<f32>,
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Rng {
+    /// Create a generator from a seed.
+    pub fn new(seed: u64) -> Self {
+        Self::with_stream(seed, DEFAULT_STREAM)
+    }
+
+    /// Create a generator for a voice. Generators for different voices produce independent
+    /// sequences even when they share the same seed. Using the same `seed` and `voice_idx` again
+    /// results in the same sequence.
+    pub fn for_voice(seed: u64, voice_idx: usize) -> Self {
+        Self::with_stream(seed, voice_idx as u64)
+    }
+
+    /// Create a generator from a seed and a stream. Each stream is an independent sequence.
+    pub fn with_stream(seed: u64, stream: u64) -> Self {
+        let mut rng = Self {
+            state: 0,
+            increment: (stream << 1) | 1,
+            spare_gaussian: None,
+        };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+
+        rng
+    }
+
+    /// Generate a uniformly distributed 32-bit integer.
+    #[inline]
+    pub fn next_u32(&mut self) -> u32 {
+        let old_state = self.state;
+        self.state = old_state
+            .wrapping_mul(PCG_MULTIPLIER)
+            .wrapping_add(self.increment);
+
+        let xorshifted = (((old_state >> 18) ^ old_state) >> 27) as u32;
+        let rotation = (old_state >> 59) as u32;
+        xorshifted.rotate_right(rotation)
+    }
+
+    /// Generate a uniformly distributed float in the `[0, 1)` range.
+    #[inline]
+    pub fn next_f32(&mut self) -> f32 {
+        // An `f32` has 24 bits of precision, so the remaining bits are discarded
+        (self.next_u32() >> 8) as f32 * (1.0 / (1u32 << 24) as f32)
+    }
+
+    /// Generate a uniformly distributed float in the `[-1, 1)` range. This is white noise.
+    #[inline]
+    pub fn next_bipolar_f32(&mut self) -> f32 {
+        (self.next_f32() * 2.0) - 1.0
+    }
+
+    /// Generate a uniformly distributed float in the `[min, max)` range.
+    #[inline]
+    pub fn next_f32_in_range(&mut self, min: f32, max: f32) -> f32 {
+        min + (self.next_f32() * (max - min))
+    }
+
+    /// Generate a normally distributed float with a mean of 0 and a standard deviation of 1. This
+    /// uses the Box-Muller transform.
+    pub fn next_gaussian_f32(&mut self) -> f32 {
+        if let Some(sample) = self.spare_gaussian.take() {
+            return sample;
+        }
+
+        // The first value must not be zero since its logarithm is taken
+        let u1 = 1.0 - self.next_f32();
+        let u2 = self.next_f32();
+        let radius = (-2.0 * u1.ln()).sqrt();
+        let (sin, cos) = (TAU * u2).sin_cos();
+        self.spare_gaussian = Some(radius * sin);
+
+        radius * cos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deterministic() {
+        let mut rng1 = Rng::new(1234);
+        let mut rng2 = Rng::new(1234);
+        for _ in 0..1000 {
+            assert_eq!(rng1.next_u32(), rng2.next_u32());
+        }
+    }
+
+    #[test]
+    fn independent_voices() {
+        let mut voice1 = Rng::for_voice(1234, 0);
+        let mut voice2 = Rng::for_voice(1234, 1);
+        let matching = (0..1000)
+            .filter(|_| voice1.next_u32() == voice2.next_u32())
+            .count();
+
+        assert!(matching < 10);
+    }
+
+    #[test]
+    fn uniform_range() {
+        let mut rng = Rng::new(1234);
+        for _ in 0..10_000 {
+            let value = rng.next_f32();
+            assert!((0.0..1.0).contains(&value));
+
+            let value = rng.next_bipolar_f32();
+            assert!((-1.0..1.0).contains(&value));
+        }
+    }
+
+    #[test]
+    fn gaussian_distribution() {
+        const NUM_SAMPLES: usize = 100_000;
+
+        let mut rng = Rng::new(1234);
+        let samples: Vec<f32> = (0..NUM_SAMPLES).map(|_| rng.next_gaussian_f32()).collect();
+        let mean = samples.iter().sum::<f32>() / NUM_SAMPLES as f32;
+        let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f32>() / NUM_SAMPLES as f32;
+
+        assert!(mean.abs() < 0.02, "mean = {mean}");
+        assert!((variance - 1.0).abs() < 0.02, "variance = {variance}");
+    }
+}
@@ -0,0 +1,270 @@
+//! Click-free switching between settings that can't be smoothed.
+
+use super::linear_crossfade;
+
+/// The default crossfade time for [`SettingCrossfade`], in milliseconds.
+pub const DEFAULT_SETTING_CROSSFADE_MS: f32 = 5.0;
+
+/// Switches between settings that cannot be smoothed, like a filter type or a distortion
+/// algorithm, without clicks. When the setting changes, the signal is processed both with the old
+/// setting and with the new setting for a few milliseconds, and the two outputs are crossfaded
+/// linearly. The crossfade takes [`DEFAULT_SETTING_CROSSFADE_MS`] by default.
+///
+/// The helper owns two copies of the processor `P` that the setting `S` applies to, for instance a
+/// filter and its filter type. When a crossfade starts, the current processor's state is copied to
+/// the second processor using [`Clone::clone_from()`], and that copy plays the old setting. That
+/// keeps the new output close to the old output at the start of the crossfade. The derived
+/// `clone_from()` implementation clones the whole processor, so processors that own heap
+/// allocations should implement `clone_from()` themselves to reuse those allocations on the audio
+/// thread. Outside of a crossfade
+/// only a single processor runs, but during a crossfade the processing cost doubles. If the setting
+/// changes again during a crossfade, then the new crossfade starts once the current one is finished.
+///
+/// This handles a single channel. Multichannel plugins can use one instance per channel.
+///
+/// ```ignore
+/// let output = self.filter_crossfade.next(
+///     self.params.filter_type.value(),
+///     input,
+///     |filter, filter_type, input| filter.process(filter_type, input),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct SettingCrossfade<S, P> {
+    sample_rate: f32,
+    fade_time_ms: f32,
+
+    /// The setting that's currently playing, or that's being faded to.
+    setting: S,
+    /// The processor for [`setting`][Self::setting].
+    processor: P,
+    /// The processor for the setting that's being faded out. This is created up front so starting
+    /// a crossfade doesn't need to allocate a new processor.
+    old_processor: P,
+    /// During a crossfade, the setting that is being faded out.
+    old_setting: Option<S>,
+    /// The crossfade's length in samples.
+    fade_len: u32,
+    /// The number of samples processed in the current crossfade.
+    fade_pos: u32,
+}
+
+impl<S: Copy + PartialEq, P: Clone> SettingCrossfade<S, P> {
+    /// Create a crossfade helper for a processor. `setting` is the setting that's initially
+    /// active.
+    pub fn new(sample_rate: f32, setting: S, processor: P) -> Self {
+        Self {
+            sample_rate,
+            fade_time_ms: DEFAULT_SETTING_CROSSFADE_MS,
+
+            setting,
+            old_processor: processor.clone(),
+            processor,
+            old_setting: None,
+            fade_len: 0,
+            fade_pos: 0,
+        }
+    }
+
+    /// Use a different crossfade time. This should only be a couple of milliseconds.
+    pub fn with_fade_time(mut self, fade_time_ms: f32) -> Self {
+        self.fade_time_ms = fade_time_ms.max(0.0);
+        self
+    }
+
+    /// Change the sample rate. This only affects crossfades that start after this call.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+    }
+
+    /// The setting that's currently playing, or that's being faded to.
+    pub fn setting(&self) -> S {
+        self.setting
+    }
+
+    /// Whether a crossfade is in progress. The processing cost is doubled while this is the case.
+    pub fn is_fading(&self) -> bool {
+        self.old_setting.is_some()
+    }
+
+    /// The processor for the current setting. Changes to this processor are not applied to the
+    /// processor for the old setting during a crossfade.
+    pub fn processor_mut(&mut self) -> &mut P {
+        &mut self.processor
+    }
+
+    /// Stop the crossfade, if one is in progress, and continue with only the current setting.
+    pub fn reset(&mut self) {
+        self.old_setting = None;
+    }
+
+    /// Process a single sample with `setting`. `process` is called with a processor, the setting
+    /// that processor should use, and `input`, and it should return the processor's output. If
+    /// the setting changed, then this starts a crossfade and `process` gets called twice until the
+    /// crossfade is finished.
+    pub fn next(
+        &mut self,
+        setting: S,
+        input: f32,
+        mut process: impl FnMut(&mut P, S, f32) -> f32,
+    ) -> f32 {
+        if setting != self.setting && self.old_setting.is_none() {
+            self.start_fade(setting);
+        }
+
+        let output = process(&mut self.processor, self.setting, input);
+        match self.old_setting {
+            Some(old_setting) => {
+                let old_output = process(&mut self.old_processor, old_setting, input);
+
+                self.fade_pos += 1;
+                let (old_gain, new_gain) =
+                    linear_crossfade(self.fade_pos as f32 / (self.fade_len + 1) as f32);
+                if self.fade_pos >= self.fade_len {
+                    self.old_setting = None;
+                }
+
+                (old_output * old_gain) + (output * new_gain)
+            }
+            None => output,
+        }
+    }
+
+    /// Process a block of samples in place with `setting`. See [`next()`][Self::next()].
+    pub fn process_block(
+        &mut self,
+        setting: S,
+        block: &mut [f32],
+        mut process: impl FnMut(&mut P, S, f32) -> f32,
+    ) {
+        for sample in block {
+            *sample = self.next(setting, *sample, &mut process);
+        }
+    }
+
+    fn start_fade(&mut self, setting: S) {
+        let fade_len = (self.fade_time_ms / 1000.0 * self.sample_rate).round() as u32;
+        if fade_len == 0 {
+            self.setting = setting;
+            return;
+        }
+
+        self.old_processor.clone_from(&self.processor);
+        self.old_setting = Some(self.setting);
+        self.setting = setting;
+        self.fade_len = fade_len;
+        self.fade_pos = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 1000.0;
+    /// 10 samples at 1 kHz.
+    const FADE_TIME_MS: f32 = 10.0;
+
+    /// A one-pole lowpass filter with the target gain as its setting. The output jumps when the
+    /// gain gets switched without a crossfade.
+    #[derive(Debug, Clone, Default)]
+    struct Lowpass {
+        state: f32,
+    }
+
+    impl Lowpass {
+        fn process(&mut self, gain: f32, input: f32) -> f32 {
+            self.state += (input - self.state) * 0.5;
+            self.state * gain
+        }
+    }
+
+    #[test]
+    fn no_discontinuity() {
+        let mut crossfade = SettingCrossfade::new(SAMPLE_RATE, 1.0, Lowpass::default())
+            .with_fade_time(FADE_TIME_MS);
+
+        let mut last_output = 0.0;
+        let mut max_step = 0.0f32;
+        for i in 0..100 {
+            let gain = if i < 50 { 1.0 } else { -1.0 };
+            let output = crossfade.next(gain, 1.0, Lowpass::process);
+            assert_eq!(crossfade.is_fading(), (50..59).contains(&i));
+            if i > 40 {
+                max_step = max_step.max((output - last_output).abs());
+            }
+            last_output = output;
+        }
+
+        // The output goes from 1 to -1 over ten samples, so it changes by roughly 0.2 per sample
+        // instead of jumping by 2
+        assert!(max_step < 0.25, "Maximum step was {max_step}");
+        assert!(!crossfade.is_fading());
+        approx::assert_relative_eq!(last_output, -1.0);
+    }
+
+    #[test]
+    fn queued_changes() {
+        let mut crossfade = SettingCrossfade::new(SAMPLE_RATE, 1.0, Lowpass::default())
+            .with_fade_time(FADE_TIME_MS);
+
+        crossfade.next(0.5, 1.0, Lowpass::process);
+        assert_eq!(crossfade.setting(), 0.5);
+
+        // Changing the setting again during the crossfade waits for it to finish
+        for _ in 0..9 {
+            crossfade.next(0.0, 1.0, Lowpass::process);
+            assert_eq!(crossfade.setting(), 0.5);
+        }
+        assert!(!crossfade.is_fading());
+        crossfade.next(0.0, 1.0, Lowpass::process);
+        assert_eq!(crossfade.setting(), 0.0);
+        assert!(crossfade.is_fading());
+
+        // Without a crossfade time the setting changes immediately
+        let mut crossfade =
+            SettingCrossfade::new(SAMPLE_RATE, 1.0, Lowpass::default()).with_fade_time(0.0);
+        crossfade.next(0.5, 1.0, Lowpass::process);
+        assert!(!crossfade.is_fading());
+    }
+
+    #[test]
+    fn reuse_old_processor() {
+        /// A processor that owns a heap allocation.
+        #[derive(Debug)]
+        struct Delay {
+            buffer: Vec<f32>,
+        }
+
+        impl Clone for Delay {
+            fn clone(&self) -> Self {
+                Self {
+                    buffer: self.buffer.clone(),
+                }
+            }
+
+            fn clone_from(&mut self, source: &Self) {
+                self.buffer.clone_from(&source.buffer);
+            }
+        }
+
+        let mut crossfade = SettingCrossfade::new(
+            SAMPLE_RATE,
+            1.0,
+            Delay {
+                buffer: vec![0.0; 16],
+            },
+        )
+        .with_fade_time(FADE_TIME_MS);
+        let old_buffer_ptr = crossfade.old_processor.buffer.as_ptr();
+
+        // Starting a crossfade copies the current processor's state without reallocating
+        crossfade.processor_mut().buffer[0] = 1.0;
+        crossfade.next(0.5, 1.0, |delay, gain, input| {
+            delay.buffer[0] * gain * input
+        });
+        assert!(crossfade.is_fading());
+        assert_eq!(crossfade.old_processor.buffer[0], 1.0);
+        assert_eq!(crossfade.old_processor.buffer.as_ptr(), old_buffer_ptr);
+    }
+}
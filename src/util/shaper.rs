@@ -0,0 +1,285 @@
+//! Waveshaping transfer functions for saturation, clipping, and wavefolding. The clipping
+//! functions are odd-symmetric, monotonically increasing, and they never exceed `[-1, 1]`.
+//!
+//! The single sample functions can be used directly, or [`Waveshaper`] can be used to combine a
+//! [`Shape`] with drive and output gain compensation.
+
+use std::f32::consts::{FRAC_2_PI, FRAC_PI_2};
+
+#[cfg(feature = "simd")]
+use std::simd::{num::SimdFloat, LaneCount, Simd, SupportedLaneCount};
+
+/// A waveshaping transfer function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Shape {
+    /// Hyperbolic tangent saturation. See [`tanh()`].
+    #[default]
+    Tanh,
+    /// Cubic soft clipping. See [`cubic()`].
+    Cubic,
+    /// Hard clipping. See [`hard_clip()`].
+    HardClip,
+    /// Arctangent saturation. See [`arctan()`].
+    Arctan,
+    /// Sine wavefolding. See [`sine_fold()`].
+    SineFold,
+}
+
+/// Hyperbolic tangent saturation. This is a smooth saturation curve with unity gain for small
+/// signals that approaches `-1` and `1` for large inputs.
+#[inline]
+pub fn tanh(x: f32) -> f32 {
+    x.tanh()
+}
+
+/// Cubic soft clipping using `1.5x - 0.5x^3`. The output reaches `-1` and `1` exactly at inputs of
+/// `-1` and `1`, and anything above that is clipped. This has a gain of 1.5 for small signals.
+#[inline]
+pub fn cubic(x: f32) -> f32 {
+    let x = x.clamp(-1.0, 1.0);
+
+    (1.5 * x) - (0.5 * x * x * x)
+}
+
+/// Hard clipping to `[-1, 1]`.
+#[inline]
+pub fn hard_clip(x: f32) -> f32 {
+    x.clamp(-1.0, 1.0)
+}
+
+/// Arctangent saturation, scaled so the output approaches `-1` and `1` for large inputs. This
+/// saturates more gently than [`tanh()`] and it has a gain of `2 / pi` for small signals.
+#[inline]
+pub fn arctan(x: f32) -> f32 {
+    x.atan() * FRAC_2_PI
+}
+
+/// Sine wavefolding using `sin(x * pi / 2)`. This behaves like a soft clipper for inputs in
+/// `[-1, 1]`, and signals that exceed that range are folded back instead of clipped. Unlike the
+/// other shapes this is not monotonic, but the output is still always in `[-1, 1]`.
+#[inline]
+pub fn sine_fold(x: f32) -> f32 {
+    (x * FRAC_PI_2).sin()
+}
+
+impl Shape {
+    /// Apply the transfer function to a single sample.
+    #[inline]
+    pub fn apply(self, x: f32) -> f32 {
+        match self {
+            Shape::Tanh => tanh(x),
+            Shape::Cubic => cubic(x),
+            Shape::HardClip => hard_clip(x),
+            Shape::Arctan => arctan(x),
+            Shape::SineFold => sine_fold(x),
+        }
+    }
+
+    /// Apply the transfer function to a block of samples in place. The shape is only matched on
+    /// once, which allows the compiler to vectorize the loops for the simpler shapes.
+    #[inline]
+    pub fn apply_block(self, block: &mut [f32]) {
+        fn apply_all(block: &mut [f32], f: impl Fn(f32) -> f32) {
+            for sample in block {
+                *sample = f(*sample);
+            }
+        }
+
+        match self {
+            Shape::Tanh => apply_all(block, tanh),
+            Shape::Cubic => apply_all(block, cubic),
+            Shape::HardClip => apply_all(block, hard_clip),
+            Shape::Arctan => apply_all(block, arctan),
+            Shape::SineFold => apply_all(block, sine_fold),
+        }
+    }
+
+    /// Apply the transfer function to every lane of a SIMD vector. Hard clipping and cubic soft
+    /// clipping use SIMD operations. `std::simd` does not provide the transcendental functions
+    /// needed for the other shapes, so those are computed per lane.
+    #[cfg(feature = "simd")]
+    #[inline]
+    pub fn apply_simd<const LANES: usize>(self, x: Simd<f32, LANES>) -> Simd<f32, LANES>
+    where
+        LaneCount<LANES>: SupportedLaneCount,
+    {
+        let one = Simd::splat(1.0);
+        match self {
+            Shape::Cubic => {
+                let x = x.simd_clamp(-one, one);
+                (Simd::splat(1.5) * x) - (Simd::splat(0.5) * x * x * x)
+            }
+            Shape::HardClip => x.simd_clamp(-one, one),
+            Shape::Tanh | Shape::Arctan | Shape::SineFold => {
+                Simd::from_array(x.to_array().map(|x| self.apply(x)))
+            }
+        }
+    }
+}
+
+/// Combines a [`Shape`] with a drive gain applied before the transfer function and optional output
+/// gain compensation. With gain compensation enabled, the output is scaled so a full scale input
+/// results in a full scale output regardless of the drive. That keeps the output level roughly
+/// constant when increasing the drive on peak-normalized material. Gain compensation is not used
+/// for [`Shape::SineFold`], since folding back a full scale input would result in extreme gains.
+#[derive(Debug, Clone, Copy)]
+pub struct Waveshaper {
+    shape: Shape,
+    /// The linear gain applied before the transfer function.
+    drive: f32,
+    compensate_gain: bool,
+    /// The linear gain applied after the transfer function. Recomputed whenever any of the other
+    /// settings change.
+    output_gain: f32,
+}
+
+impl Default for Waveshaper {
+    fn default() -> Self {
+        Self::new(Shape::default())
+    }
+}
+
+impl Waveshaper {
+    /// Create a waveshaper with unity drive and gain compensation enabled.
+    pub fn new(shape: Shape) -> Self {
+        let mut waveshaper = Self {
+            shape,
+            drive: 1.0,
+            compensate_gain: true,
+            output_gain: 1.0,
+        };
+        waveshaper.update_output_gain();
+
+        waveshaper
+    }
+
+    /// Change the transfer function.
+    pub fn set_shape(&mut self, shape: Shape) {
+        self.shape = shape;
+        self.update_output_gain();
+    }
+
+    /// Set the linear gain applied before the transfer function. Use
+    /// [`db_to_gain()`][crate::util::db_to_gain()] to set the drive in decibels.
+    pub fn set_drive(&mut self, drive: f32) {
+        nih_debug_assert!(drive > 0.0);
+
+        self.drive = drive;
+        self.update_output_gain();
+    }
+
+    /// Enable or disable the output gain compensation.
+    pub fn set_gain_compensation(&mut self, compensate_gain: bool) {
+        self.compensate_gain = compensate_gain;
+        self.update_output_gain();
+    }
+
+    /// The current transfer function.
+    pub fn shape(&self) -> Shape {
+        self.shape
+    }
+
+    /// The linear gain applied after the transfer function.
+    pub fn output_gain(&self) -> f32 {
+        self.output_gain
+    }
+
+    /// Process a single sample.
+    #[inline]
+    pub fn process(&self, x: f32) -> f32 {
+        self.shape.apply(x * self.drive) * self.output_gain
+    }
+
+    /// Process a block of samples in place.
+    pub fn process_block(&self, block: &mut [f32]) {
+        for sample in block.iter_mut() {
+            *sample *= self.drive;
+        }
+        self.shape.apply_block(block);
+        for sample in block.iter_mut() {
+            *sample *= self.output_gain;
+        }
+    }
+
+    /// Process every lane of a SIMD vector.
+    #[cfg(feature = "simd")]
+    #[inline]
+    pub fn process_simd<const LANES: usize>(&self, x: Simd<f32, LANES>) -> Simd<f32, LANES>
+    where
+        LaneCount<LANES>: SupportedLaneCount,
+    {
+        self.shape.apply_simd(x * Simd::splat(self.drive)) * Simd::splat(self.output_gain)
+    }
+
+    fn update_output_gain(&mut self) {
+        self.output_gain = if self.compensate_gain && self.shape != Shape::SineFold {
+            self.shape.apply(self.drive).recip()
+        } else {
+            1.0
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIPPING_SHAPES: [Shape; 4] = [Shape::Tanh, Shape::Cubic, Shape::HardClip, Shape::Arctan];
+
+    /// Inputs spread evenly over `[-10, 10]`.
+    fn inputs() -> impl Iterator<Item = f32> {
+        (0..=2000).map(|idx| (idx as f32 / 100.0) - 10.0)
+    }
+
+    #[test]
+    fn monotonic() {
+        for shape in CLIPPING_SHAPES {
+            let mut previous = f32::NEG_INFINITY;
+            for x in inputs() {
+                let y = shape.apply(x);
+                assert!(y >= previous, "{shape:?} is not monotonic at {x}");
+                assert_eq!(shape.apply(-x), -y, "{shape:?} is not odd-symmetric at {x}");
+                previous = y;
+            }
+        }
+    }
+
+    #[test]
+    fn clip_bounds() {
+        for shape in CLIPPING_SHAPES.into_iter().chain([Shape::SineFold]) {
+            for x in inputs().chain([1e6, -1e6]) {
+                assert!(shape.apply(x).abs() <= 1.0, "{shape:?} exceeds 1 at {x}");
+            }
+        }
+
+        assert_eq!(hard_clip(2.0), 1.0);
+        assert_eq!(cubic(1.0), 1.0);
+        assert_eq!(cubic(-5.0), -1.0);
+        assert_eq!(sine_fold(1.0), 1.0);
+    }
+
+    #[test]
+    fn block_matches_single_samples() {
+        for shape in CLIPPING_SHAPES.into_iter().chain([Shape::SineFold]) {
+            let mut block: Vec<f32> = inputs().collect();
+            shape.apply_block(&mut block);
+            for (x, y) in inputs().zip(block) {
+                assert_eq!(shape.apply(x), y);
+            }
+        }
+    }
+
+    #[test]
+    fn gain_compensation() {
+        let mut waveshaper = Waveshaper::new(Shape::Tanh);
+        for drive in [0.5, 1.0, 4.0, 20.0] {
+            waveshaper.set_drive(drive);
+            approx::assert_relative_eq!(waveshaper.process(1.0), 1.0, epsilon = 1e-6);
+            approx::assert_relative_eq!(waveshaper.process(-1.0), -1.0, epsilon = 1e-6);
+        }
+
+        waveshaper.set_gain_compensation(false);
+        assert_eq!(waveshaper.output_gain(), 1.0);
+        assert_eq!(waveshaper.process(100.0), 1.0);
+    }
+}
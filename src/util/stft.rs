@@ -4,6 +4,12 @@ use std::cmp;
 
 use crate::buffer::{Block, Buffer};
 
+#[cfg(feature = "fft")]
+mod spectral;
+
+#[cfg(feature = "fft")]
+pub use spectral::{Complex32, SpectralHelper, SpectralWindow};
+
 /// Some buffer that can be used with the [`StftHelper`].
 pub trait StftInput {
     /// The number of samples in this input.
@@ -291,7 +297,7 @@ impl<const NUM_SIDECHAIN_INPUTS: usize> StftHelper<NUM_SIDECHAIN_INPUTS> {
         overlap_times: usize,
         mut process_cb: F,
     ) where
-        M: StftInputMut,
+        M: StftInputMut + ?Sized,
         F: FnMut(usize, &mut [f32]),
     {
         self.process_overlap_add_sidechain(
@@ -318,7 +324,7 @@ impl<const NUM_SIDECHAIN_INPUTS: usize> StftHelper<NUM_SIDECHAIN_INPUTS> {
         overlap_times: usize,
         mut process_cb: F,
     ) where
-        M: StftInputMut,
+        M: StftInputMut + ?Sized,
         S: StftInput,
         F: FnMut(usize, Option<usize>, &mut [f32]),
     {
@@ -499,7 +505,7 @@ impl<const NUM_SIDECHAIN_INPUTS: usize> StftHelper<NUM_SIDECHAIN_INPUTS> {
         overlap_times: usize,
         mut analyze_cb: F,
     ) where
-        B: StftInput,
+        B: StftInput + ?Sized,
         F: FnMut(usize, &mut [f32]),
     {
         assert_eq!(buffer.num_channels(), self.main_input_ring_buffers.len());
@@ -0,0 +1,276 @@
+//! A spectral processing helper built on top of the [`StftHelper`] and `realfft`.
+
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use std::sync::Arc;
+
+use super::{StftHelper, StftInputMut};
+use crate::util::window;
+
+pub use realfft::num_complex::Complex32;
+
+/// Processes audio in the frequency domain using a windowed short-term Fourier transform with
+/// overlap-add reconstruction. This takes care of the buffering done by the [`StftHelper`], the
+/// windowing, the forward and inverse FFTs, and the gain compensation. The plugin only needs to
+/// modify the frequency bins of each frame.
+///
+/// The window function is applied both before the forward FFT and after the inverse FFT. If the
+/// frequency bins are not modified, then the output is identical to the input delayed by
+/// [`latency_samples()`][Self::latency_samples()] samples as long as the overlap amount is high
+/// enough for the window function. See [`SpectralWindow`] for the minimum overlap amounts.
+///
+/// All buffers and FFT plans are allocated up front, so [`process()`][Self::process()] does not
+/// allocate.
+pub struct SpectralHelper {
+    stft: StftHelper,
+    window_size: usize,
+    overlap_times: usize,
+
+    /// The window function applied before the forward FFT.
+    analysis_window: Vec<f32>,
+    /// The window function applied after the inverse FFT. This also includes the gain compensation
+    /// for the FFT round trip and for the overlapping windows.
+    synthesis_window: Vec<f32>,
+
+    r2c_plan: Arc<dyn RealToComplex<f32>>,
+    c2r_plan: Arc<dyn ComplexToReal<f32>>,
+    complex_fft_buffer: Vec<Complex32>,
+    r2c_scratch_buffer: Vec<Complex32>,
+    c2r_scratch_buffer: Vec<Complex32>,
+}
+
+/// The window function used by the [`SpectralHelper`]. These are periodic versions of the window
+/// functions from the [`window`] module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpectralWindow {
+    /// A Hann window. Needs an overlap amount of at least 4 for perfect reconstruction.
+    Hann,
+    /// A four term Blackman-Harris window. This has much lower side lobes than the Hann window,
+    /// at the cost of a wider main lobe. Needs an overlap amount of at least 8 for perfect
+    /// reconstruction.
+    BlackmanHarris,
+}
+
+impl SpectralHelper {
+    /// Create a spectral processor for `num_channels` channels that processes frames of
+    /// `window_size` samples. Every sample will be part of `overlap_times` different frames.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_channels == 0`, if `window_size == 0`, or if `window_size` is not divisible
+    /// by `overlap_times`.
+    pub fn new(
+        num_channels: usize,
+        window_size: usize,
+        overlap_times: usize,
+        window_function: SpectralWindow,
+    ) -> Self {
+        assert_ne!(overlap_times, 0);
+        assert_eq!(
+            window_size % overlap_times,
+            0,
+            "The window size must be divisible by the overlap amount"
+        );
+
+        // The periodic versions of these windows sum to a constant when overlapped, which the
+        // symmetric versions don't quite do
+        let mut analysis_window = vec![0.0; window_size + 1];
+        match window_function {
+            SpectralWindow::Hann => window::hann_in_place(&mut analysis_window),
+            SpectralWindow::BlackmanHarris => {
+                window::blackman_harris_in_place(&mut analysis_window)
+            }
+        }
+        analysis_window.truncate(window_size);
+
+        // The window gets applied twice, and `realfft` doesn't normalize the inverse FFT. The
+        // squared windows sum to `sum(w^2) / hop_size` when overlapped.
+        let hop_size = window_size / overlap_times;
+        let squared_window_sum: f32 = analysis_window.iter().map(|x| x * x).sum();
+        let gain_compensation = hop_size as f32 / (squared_window_sum * window_size as f32);
+        let synthesis_window = analysis_window
+            .iter()
+            .map(|x| x * gain_compensation)
+            .collect();
+
+        let mut planner = RealFftPlanner::new();
+        let r2c_plan = planner.plan_fft_forward(window_size);
+        let c2r_plan = planner.plan_fft_inverse(window_size);
+        let complex_fft_buffer = r2c_plan.make_output_vec();
+        let r2c_scratch_buffer = r2c_plan.make_scratch_vec();
+        let c2r_scratch_buffer = c2r_plan.make_scratch_vec();
+
+        Self {
+            stft: StftHelper::new(num_channels, window_size, 0),
+            window_size,
+            overlap_times,
+
+            analysis_window,
+            synthesis_window,
+
+            r2c_plan,
+            c2r_plan,
+            complex_fft_buffer,
+            r2c_scratch_buffer,
+            c2r_scratch_buffer,
+        }
+    }
+
+    /// The number of channels this `SpectralHelper` was configured for.
+    pub fn num_channels(&self) -> usize {
+        self.stft.num_channels()
+    }
+
+    /// The number of samples in each frame.
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    /// The number of frequency bins passed to the processing function. This is `window_size / 2 +
+    /// 1`.
+    pub fn num_bins(&self) -> usize {
+        self.complex_fft_buffer.len()
+    }
+
+    /// The amount of latency introduced when processing audio through this [`SpectralHelper`].
+    /// This should be reported to the host using
+    /// [`InitContext::set_latency_samples()`][crate::prelude::InitContext::set_latency_samples()].
+    pub fn latency_samples(&self) -> u32 {
+        self.stft.latency_samples()
+    }
+
+    /// Clear the internal buffers. This should be called from the plugin's `reset()` function.
+    pub fn reset(&mut self) {
+        self.stft.set_block_size(self.window_size);
+    }
+
+    /// Process the audio in `main_buffer` in the frequency domain. `process_cb` is called for every
+    /// frame and channel with the arguments `process_cb(channel_idx, frequency_bins)`, and it can
+    /// modify the frame's frequency bins in place. The bins are not normalized, and the DC and
+    /// Nyquist bins' imaginary parts are ignored when converting back to the time domain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `main_buffer` does not have the same number of channels as this
+    /// [`SpectralHelper`].
+    pub fn process<M, F>(&mut self, main_buffer: &mut M, mut process_cb: F)
+    where
+        M: StftInputMut + ?Sized,
+        F: FnMut(usize, &mut [Complex32]),
+    {
+        let Self {
+            stft,
+            overlap_times,
+            analysis_window,
+            synthesis_window,
+            r2c_plan,
+            c2r_plan,
+            complex_fft_buffer,
+            r2c_scratch_buffer,
+            c2r_scratch_buffer,
+            ..
+        } = self;
+
+        stft.process_overlap_add(
+            main_buffer,
+            *overlap_times,
+            |channel_idx, real_fft_buffer| {
+                window::multiply_with_window(real_fft_buffer, analysis_window);
+                let result = r2c_plan.process_with_scratch(
+                    real_fft_buffer,
+                    complex_fft_buffer,
+                    r2c_scratch_buffer,
+                );
+                nih_debug_assert!(result.is_ok(), "Forward FFT failed");
+
+                process_cb(channel_idx, &mut complex_fft_buffer[..]);
+
+                // The inverse real FFT requires these to be zero
+                complex_fft_buffer[0].im = 0.0;
+                if real_fft_buffer.len() % 2 == 0 {
+                    complex_fft_buffer.last_mut().unwrap().im = 0.0;
+                }
+
+                let result = c2r_plan.process_with_scratch(
+                    complex_fft_buffer,
+                    real_fft_buffer,
+                    c2r_scratch_buffer,
+                );
+                nih_debug_assert!(result.is_ok(), "Inverse FFT failed");
+                window::multiply_with_window(real_fft_buffer, synthesis_window);
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW_SIZE: usize = 256;
+
+    /// A deterministic noise-like test signal.
+    fn test_signal(num_samples: usize) -> Vec<f32> {
+        (0..num_samples)
+            .map(|i| {
+                let t = i as f32;
+                (t * 0.05).sin() * 0.5 + (t * 0.31).sin() * 0.3 + (t * 1.7).cos() * 0.2
+            })
+            .collect()
+    }
+
+    fn assert_delayed_identity(window_function: SpectralWindow, overlap_times: usize) {
+        let mut helper = SpectralHelper::new(2, WINDOW_SIZE, overlap_times, window_function);
+        let latency = helper.latency_samples() as usize;
+        assert_eq!(latency, WINDOW_SIZE);
+
+        let input = test_signal(WINDOW_SIZE * 16);
+        let mut left = input.clone();
+        let mut right = input.iter().map(|x| -x).collect::<Vec<_>>();
+
+        // Process the signal in oddly sized chunks to make sure that works too
+        let mut start = 0;
+        while start < input.len() {
+            let end = (start + 100).min(input.len());
+            let mut channels = [&mut left[start..end], &mut right[start..end]];
+            helper.process(&mut channels[..], |_, _| ());
+            start = end;
+        }
+
+        // The first full window won't be fully reconstructed yet since it starts with silence
+        for i in (latency + WINDOW_SIZE)..input.len() {
+            let expected = input[i - latency];
+            assert!(
+                (left[i] - expected).abs() < 1e-4,
+                "{window_function:?} with {overlap_times}x overlap at sample {i}: expected \
+                 {expected}, got {}",
+                left[i]
+            );
+            assert!((right[i] + expected).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn hann_reconstruction() {
+        assert_delayed_identity(SpectralWindow::Hann, 4);
+        assert_delayed_identity(SpectralWindow::Hann, 8);
+    }
+
+    #[test]
+    fn blackman_harris_reconstruction() {
+        assert_delayed_identity(SpectralWindow::BlackmanHarris, 8);
+    }
+
+    #[test]
+    fn zeroing_bins_silences_output() {
+        let mut helper = SpectralHelper::new(1, WINDOW_SIZE, 4, SpectralWindow::Hann);
+        assert_eq!(helper.num_bins(), WINDOW_SIZE / 2 + 1);
+
+        let mut samples = test_signal(WINDOW_SIZE * 4);
+        helper.process(&mut [&mut samples[..]][..], |channel_idx, bins| {
+            assert_eq!(channel_idx, 0);
+            bins.fill(Complex32::new(0.0, 0.0));
+        });
+
+        assert!(samples.iter().all(|sample| sample.abs() < 1e-6));
+    }
+}
@@ -0,0 +1,355 @@
+//! Tempo synced note divisions for delay times and LFO rates.
+
+use crate::params::smoothing::{Smoother, SmoothingStyle};
+use crate::prelude::{Enum, Transport};
+
+/// The tempo [`TempoSync`] uses when the host has never reported a valid tempo.
+pub const DEFAULT_TEMPO: f64 = 120.0;
+
+/// A note division for tempo synced time parameters. This can be used directly with an
+/// [`EnumParam`][crate::prelude::EnumParam], whose display names are the usual `1/4`, `1/4D`, and
+/// `1/4T` notation. Use [`TempoSync`] to convert a division to a time at the host's tempo. Every
+/// division has a stable ID, so divisions can be added in between existing divisions later
+/// without breaking old presets.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteDivision {
+    #[id = "1/1"]
+    #[name = "1/1"]
+    Whole,
+    #[id = "1/1d"]
+    #[name = "1/1D"]
+    WholeDotted,
+    #[id = "1/1t"]
+    #[name = "1/1T"]
+    WholeTriplet,
+    #[id = "1/2"]
+    #[name = "1/2"]
+    Half,
+    #[id = "1/2d"]
+    #[name = "1/2D"]
+    HalfDotted,
+    #[id = "1/2t"]
+    #[name = "1/2T"]
+    HalfTriplet,
+    #[id = "1/4"]
+    #[name = "1/4"]
+    Quarter,
+    #[id = "1/4d"]
+    #[name = "1/4D"]
+    QuarterDotted,
+    #[id = "1/4t"]
+    #[name = "1/4T"]
+    QuarterTriplet,
+    #[id = "1/8"]
+    #[name = "1/8"]
+    Eighth,
+    #[id = "1/8d"]
+    #[name = "1/8D"]
+    EighthDotted,
+    #[id = "1/8t"]
+    #[name = "1/8T"]
+    EighthTriplet,
+    #[id = "1/16"]
+    #[name = "1/16"]
+    Sixteenth,
+    #[id = "1/16d"]
+    #[name = "1/16D"]
+    SixteenthDotted,
+    #[id = "1/16t"]
+    #[name = "1/16T"]
+    SixteenthTriplet,
+    #[id = "1/32"]
+    #[name = "1/32"]
+    ThirtySecond,
+    #[id = "1/32d"]
+    #[name = "1/32D"]
+    ThirtySecondDotted,
+    #[id = "1/32t"]
+    #[name = "1/32T"]
+    ThirtySecondTriplet,
+}
+
+impl NoteDivision {
+    /// The division's length in quarter notes. Dotted divisions are one and a half times as long as
+    /// the plain division, and triplet divisions are two thirds as long.
+    pub fn beats(self) -> f64 {
+        let (plain_beats, modifier) = match self {
+            NoteDivision::Whole => (4.0, 1.0),
+            NoteDivision::WholeDotted => (4.0, 1.5),
+            NoteDivision::WholeTriplet => (4.0, 2.0 / 3.0),
+            NoteDivision::Half => (2.0, 1.0),
+            NoteDivision::HalfDotted => (2.0, 1.5),
+            NoteDivision::HalfTriplet => (2.0, 2.0 / 3.0),
+            NoteDivision::Quarter => (1.0, 1.0),
+            NoteDivision::QuarterDotted => (1.0, 1.5),
+            NoteDivision::QuarterTriplet => (1.0, 2.0 / 3.0),
+            NoteDivision::Eighth => (0.5, 1.0),
+            NoteDivision::EighthDotted => (0.5, 1.5),
+            NoteDivision::EighthTriplet => (0.5, 2.0 / 3.0),
+            NoteDivision::Sixteenth => (0.25, 1.0),
+            NoteDivision::SixteenthDotted => (0.25, 1.5),
+            NoteDivision::SixteenthTriplet => (0.25, 2.0 / 3.0),
+            NoteDivision::ThirtySecond => (0.125, 1.0),
+            NoteDivision::ThirtySecondDotted => (0.125, 1.5),
+            NoteDivision::ThirtySecondTriplet => (0.125, 2.0 / 3.0),
+        };
+
+        plain_beats * modifier
+    }
+}
+
+/// Converts [`NoteDivision`]s to times at the host's current tempo. Call
+/// [`update()`][Self::update()] at the start of every block, and then use
+/// [`division_samples()`][Self::division_samples()] for delay times or
+/// [`division_hz()`][Self::division_hz()] for LFO rates.
+///
+/// By default the results are not smoothed. They always correspond exactly to the division at the
+/// tempo from the start of the block, so a delay time jumps straight to the new value when the
+/// division or the tempo changes. Tempo changes can be smoothed using
+/// [`with_smoothing()`][Self::with_smoothing()], in which case
+/// [`advance()`][Self::advance()] needs to be called for every processed sample or block. Changes
+/// to the division are never smoothed. Delay lines should crossfade between the old and new delay
+/// times to avoid clicks, and LFOs should advance their phase by the rate every sample instead of
+/// computing the phase from the time so a tempo change doesn't cause the phase to jump.
+///
+/// The host's tempo is used regardless of whether the transport is playing, so tempo synced
+/// effects keep working while the transport is stopped. If the host doesn't report a tempo, or if
+/// it reports a tempo of zero or less, then the last valid tempo is used instead. Until the host
+/// has reported a valid tempo this is [`DEFAULT_TEMPO`].
+///
+/// ```ignore
+/// // At the start of every block
+/// self.tempo_sync.update(context.transport());
+/// for mut channel_samples in buffer.iter_samples() {
+///     let delay_samples = self
+///         .tempo_sync
+///         .division_samples(self.params.delay_division.value(), sample_rate);
+///     self.tempo_sync.advance(1);
+///
+///     ...
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TempoSync {
+    /// The last valid tempo in beats per minute. This is the smoother's target.
+    target_tempo: f64,
+    /// Whether the host has reported a valid tempo yet. The first tempo reported by the host is
+    /// applied immediately instead of being smoothed from the fallback tempo.
+    received_host_tempo: bool,
+    /// Smooths changes to the tempo reported by the host. This uses [`SmoothingStyle::None`]
+    /// unless [`with_smoothing()`][Self::with_smoothing()] was used.
+    tempo: Smoother<f32>,
+}
+
+impl Default for TempoSync {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TempoSync {
+    /// Create a converter that uses [`DEFAULT_TEMPO`] until the host reports a valid tempo. Tempo
+    /// changes are not smoothed.
+    pub fn new() -> Self {
+        let tempo = Smoother::new(SmoothingStyle::None);
+        tempo.reset(DEFAULT_TEMPO as f32);
+
+        Self {
+            target_tempo: DEFAULT_TEMPO,
+            received_host_tempo: false,
+            tempo,
+        }
+    }
+
+    /// Smooth changes to the host's tempo using this smoothing style. The smoothing only advances
+    /// when [`advance()`][Self::advance()] is called, so that must be called for every sample or
+    /// every block when smoothing is enabled.
+    pub fn with_smoothing(mut self, style: SmoothingStyle) -> Self {
+        let tempo = Smoother::new(style);
+        tempo.reset(self.target_tempo as f32);
+        self.tempo = tempo;
+        self
+    }
+
+    /// Use a different tempo until the host reports a valid tempo. Invalid tempos are ignored.
+    pub fn with_fallback_tempo(mut self, tempo: f64) -> Self {
+        self.set_tempo(tempo);
+        self
+    }
+
+    /// Update the tempo from the host's transport information. Call this at the start of every
+    /// block. Does nothing if the host did not report a valid tempo. If smoothing is enabled, then
+    /// tempo changes are smoothed, except for the first tempo reported by the host.
+    pub fn update(&mut self, transport: &Transport) {
+        match transport.tempo {
+            Some(tempo) if tempo.is_finite() && tempo > 0.0 => {
+                // `SmoothingStyle::None` would still take a single step to reach the new tempo
+                if !self.received_host_tempo || matches!(self.tempo.style, SmoothingStyle::None) {
+                    self.received_host_tempo = true;
+                    self.set_tempo(tempo);
+                } else if tempo != self.target_tempo {
+                    self.target_tempo = tempo;
+                    self.tempo.set_target(transport.sample_rate, tempo as f32);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Change the tempo directly, in beats per minute. This skips the smoothing. Tempos that are
+    /// not finite or that are zero or less are ignored.
+    pub fn set_tempo(&mut self, tempo: f64) {
+        if tempo.is_finite() && tempo > 0.0 {
+            self.target_tempo = tempo;
+            self.tempo.reset(tempo as f32);
+        }
+    }
+
+    /// Advance the tempo smoothing by `num_samples` samples. Call this after every processed
+    /// sample with a value of 1, or after every block with the block's length. Does nothing if
+    /// smoothing is disabled or if the tempo is not currently being smoothed.
+    pub fn advance(&mut self, num_samples: u32) {
+        if num_samples > 0 && self.tempo.is_smoothing() {
+            self.tempo.next_step(num_samples);
+        }
+    }
+
+    /// Whether the tempo is currently being smoothed towards a new tempo reported by the host.
+    pub fn is_smoothing(&self) -> bool {
+        self.tempo.is_smoothing()
+    }
+
+    /// The tempo in beats per minute that's used for the conversions. This is the smoothed tempo
+    /// if smoothing is enabled.
+    pub fn tempo(&self) -> f64 {
+        if self.tempo.is_smoothing() {
+            self.tempo.previous_value() as f64
+        } else {
+            self.target_tempo
+        }
+    }
+
+    /// The division's length in seconds at the current tempo.
+    pub fn division_seconds(&self, division: NoteDivision) -> f32 {
+        (division.beats() * 60.0 / self.tempo()) as f32
+    }
+
+    /// The division's length in samples at the current tempo. This is not rounded, so it can be
+    /// used with fractional delay lines.
+    pub fn division_samples(&self, division: NoteDivision, sample_rate: f32) -> f32 {
+        (division.beats() * 60.0 / self.tempo() * sample_rate as f64) as f32
+    }
+
+    /// The frequency in Hertz of an LFO that completes one cycle per division at the current
+    /// tempo.
+    pub fn division_hz(&self, division: NoteDivision) -> f32 {
+        (self.tempo() / 60.0 / division.beats()) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    #[test]
+    fn division_samples() {
+        // A quarter note is exactly half a second at 120 BPM
+        let mut tempo_sync = TempoSync::new();
+        tempo_sync.set_tempo(120.0);
+        for (division, expected_samples) in [
+            (NoteDivision::Whole, 96000.0),
+            (NoteDivision::WholeDotted, 144000.0),
+            (NoteDivision::Half, 48000.0),
+            (NoteDivision::Quarter, 24000.0),
+            (NoteDivision::QuarterDotted, 36000.0),
+            (NoteDivision::QuarterTriplet, 16000.0),
+            (NoteDivision::Eighth, 12000.0),
+            (NoteDivision::EighthTriplet, 8000.0),
+            (NoteDivision::Sixteenth, 6000.0),
+            (NoteDivision::ThirtySecondDotted, 4500.0),
+        ] {
+            approx::assert_relative_eq!(
+                tempo_sync.division_samples(division, SAMPLE_RATE),
+                expected_samples
+            );
+        }
+
+        approx::assert_relative_eq!(tempo_sync.division_seconds(NoteDivision::Quarter), 0.5);
+        approx::assert_relative_eq!(tempo_sync.division_hz(NoteDivision::Eighth), 4.0);
+
+        // Doubling the tempo halves the times
+        tempo_sync.set_tempo(240.0);
+        approx::assert_relative_eq!(
+            tempo_sync.division_samples(NoteDivision::Quarter, SAMPLE_RATE),
+            12000.0
+        );
+    }
+
+    #[test]
+    fn invalid_tempos() {
+        let mut tempo_sync = TempoSync::new().with_fallback_tempo(90.0);
+        assert_eq!(tempo_sync.tempo(), 90.0);
+
+        let mut transport = Transport::new(SAMPLE_RATE);
+        tempo_sync.update(&transport);
+        assert_eq!(tempo_sync.tempo(), 90.0);
+
+        transport.tempo = Some(140.0);
+        tempo_sync.update(&transport);
+        assert_eq!(tempo_sync.tempo(), 140.0);
+
+        // Without smoothing, tempo changes are applied immediately
+        transport.tempo = Some(150.0);
+        tempo_sync.update(&transport);
+        assert_eq!(tempo_sync.tempo(), 150.0);
+        transport.tempo = Some(140.0);
+        tempo_sync.update(&transport);
+        assert_eq!(tempo_sync.tempo(), 140.0);
+
+        // The last valid tempo is kept
+        for tempo in [Some(0.0), Some(-10.0), Some(f64::NAN), None] {
+            transport.tempo = tempo;
+            tempo_sync.update(&transport);
+            assert_eq!(tempo_sync.tempo(), 140.0);
+        }
+    }
+
+    #[test]
+    fn smoothed_tempo_changes() {
+        let mut tempo_sync = TempoSync::new().with_smoothing(SmoothingStyle::Linear(10.0));
+        let mut transport = Transport::new(SAMPLE_RATE);
+
+        // The host's first tempo is not smoothed from the fallback tempo
+        transport.tempo = Some(100.0);
+        tempo_sync.update(&transport);
+        assert!(!tempo_sync.is_smoothing());
+        assert_eq!(tempo_sync.tempo(), 100.0);
+
+        // 10 milliseconds at 48 kHz is 480 samples
+        transport.tempo = Some(200.0);
+        tempo_sync.update(&transport);
+        assert!(tempo_sync.is_smoothing());
+        assert_eq!(tempo_sync.tempo(), 100.0);
+        tempo_sync.advance(240);
+        approx::assert_relative_eq!(tempo_sync.tempo(), 150.0, epsilon = 1e-3);
+
+        // Reporting the same tempo again doesn't restart the smoothing
+        tempo_sync.update(&transport);
+        tempo_sync.advance(240);
+        assert!(!tempo_sync.is_smoothing());
+        assert_eq!(tempo_sync.tempo(), 200.0);
+        approx::assert_relative_eq!(
+            tempo_sync.division_samples(NoteDivision::Quarter, SAMPLE_RATE),
+            14400.0
+        );
+
+        // Setting the tempo directly skips the smoothing
+        transport.tempo = Some(50.0);
+        tempo_sync.update(&transport);
+        tempo_sync.set_tempo(80.0);
+        assert!(!tempo_sync.is_smoothing());
+        assert_eq!(tempo_sync.tempo(), 80.0);
+    }
+}
@@ -0,0 +1,285 @@
+//! True-peak metering as described in ITU-R BS.1770.
+
+use crate::buffer::Buffer;
+use crate::util::gain_to_db;
+
+/// The number of taps in each of the interpolation filter's four phases.
+const NUM_TAPS_PER_PHASE: usize = 12;
+
+/// The 48-tap 4x interpolation filter from ITU-R BS.1770-4, Annex 2, split into its four phases.
+/// Element `k` of a phase is applied to the input sample from `k` samples ago.
+const PHASES: [[f32; NUM_TAPS_PER_PHASE]; 4] = [
+    [
+        0.001_708_984_4,
+        0.010_986_328,
+        -0.019_653_32,
+        0.033_203_125,
+        -0.059_448_242,
+        0.137_329_1,
+        0.972_167_97,
+        -0.102_294_92,
+        0.047_607_42,
+        -0.026_611_328,
+        0.014_892_578,
+        -0.008_300_781,
+    ],
+    [
+        -0.029_174_805,
+        0.029_296_875,
+        -0.051_757_812,
+        0.089_111_33,
+        -0.166_503_9,
+        0.465_087_9,
+        0.779_785_16,
+        -0.200_317_38,
+        0.101_562_5,
+        -0.058_227_54,
+        0.033_081_055,
+        -0.018_920_898,
+    ],
+    [
+        -0.018_920_898,
+        0.033_081_055,
+        -0.058_227_54,
+        0.101_562_5,
+        -0.200_317_38,
+        0.779_785_16,
+        0.465_087_9,
+        -0.166_503_9,
+        0.089_111_33,
+        -0.051_757_812,
+        0.029_296_875,
+        -0.029_174_805,
+    ],
+    [
+        -0.008_300_781,
+        0.014_892_578,
+        -0.026_611_328,
+        0.047_607_42,
+        -0.102_294_92,
+        0.972_167_97,
+        0.137_329_1,
+        -0.059_448_242,
+        0.033_203_125,
+        -0.019_653_32,
+        0.010_986_328,
+        0.001_708_984_4,
+    ],
+];
+
+/// Measures the true-peak level of a signal, which also includes the peaks that occur between
+/// samples. These inter-sample peaks are what a DAC or a lossy encoder may end up producing, and
+/// they can be several decibels louder than the highest sample value. As per ITU-R BS.1770, the
+/// signal is upsampled by a factor of four using a polyphase FIR filter, and the true peak is the
+/// highest absolute value of the upsampled signal. The level is reported in dBTP.
+///
+/// The meter keeps track of two values:
+///
+/// - The instantaneous true peak is the highest true peak across all channels in the most recent
+///   buffer passed to [`process()`][Self::process()]. Limiters and peak meters can use this
+///   directly.
+/// - The held true peak is the highest true peak since the last call to
+///   [`reset_held_peak()`][Self::reset_held_peak()] or [`reset()`][Self::reset()]. This is the
+///   maximum true-peak value loudness meters display, and a GUI can reset it when the user clicks
+///   on the readout.
+///
+/// The interpolation filter delays the upsampled signal by about six samples. This doesn't affect
+/// the audio, but a limiter that uses the meter as its detector should take this into account.
+/// The per-channel state is allocated in [`new()`][Self::new()] and [`resize()`][Self::resize()],
+/// which should be called from the plugin's `initialize()` function. Processing audio never
+/// allocates.
+#[derive(Debug, Clone)]
+pub struct TruePeakMeter {
+    /// The interpolation filter state for every channel.
+    histories: Vec<History>,
+
+    /// The instantaneous true peak as a linear gain.
+    peak: f32,
+    /// The held true peak as a linear gain.
+    held_peak: f32,
+}
+
+/// The last [`NUM_TAPS_PER_PHASE`] input samples for a single channel. Every sample is stored twice
+/// so the most recent samples can always be read as a single contiguous slice.
+#[derive(Debug, Clone)]
+struct History {
+    samples: [f32; NUM_TAPS_PER_PHASE * 2],
+    /// The index the next sample will be written to.
+    pos: usize,
+}
+
+impl TruePeakMeter {
+    /// Create a true-peak meter for `num_channels` channels.
+    pub fn new(num_channels: usize) -> Self {
+        let mut meter = Self {
+            histories: Vec::new(),
+
+            peak: 0.0,
+            held_peak: 0.0,
+        };
+        meter.resize(num_channels);
+
+        meter
+    }
+
+    /// Change the number of channels. This clears the meter's state. This allocates, so it should
+    /// be called from the plugin's `initialize()` function.
+    pub fn resize(&mut self, num_channels: usize) {
+        self.histories.resize_with(num_channels, History::new);
+        self.reset();
+    }
+
+    /// Clear the interpolation filters and both true-peak values. This should be called from the
+    /// plugin's `reset()` function.
+    pub fn reset(&mut self) {
+        for history in &mut self.histories {
+            history.reset();
+        }
+
+        self.peak = 0.0;
+        self.held_peak = 0.0;
+    }
+
+    /// Reset only the held true peak.
+    pub fn reset_held_peak(&mut self) {
+        self.held_peak = 0.0;
+    }
+
+    /// The instantaneous true peak as a linear gain.
+    pub fn peak(&self) -> f32 {
+        self.peak
+    }
+
+    /// The instantaneous true peak in dBTP.
+    pub fn peak_dbtp(&self) -> f32 {
+        gain_to_db(self.peak)
+    }
+
+    /// The held true peak as a linear gain.
+    pub fn held_peak(&self) -> f32 {
+        self.held_peak
+    }
+
+    /// The held true peak in dBTP.
+    pub fn held_peak_dbtp(&self) -> f32 {
+        gain_to_db(self.held_peak)
+    }
+
+    /// Process a single sample for a channel. Returns the highest absolute value of the four
+    /// upsampled samples as a linear gain. This updates the held true peak, but not the
+    /// instantaneous true peak.
+    pub fn process_sample(&mut self, channel: usize, sample: f32) -> f32 {
+        let true_peak = self.histories[channel].next(sample);
+        self.held_peak = self.held_peak.max(true_peak);
+
+        true_peak
+    }
+
+    /// Process a block of samples for a channel. Returns the block's true peak as a linear gain.
+    /// Like [`process_sample()`][Self::process_sample()], this only updates the held true peak.
+    pub fn process_block(&mut self, channel: usize, samples: &[f32]) -> f32 {
+        let history = &mut self.histories[channel];
+        let true_peak = samples
+            .iter()
+            .fold(0.0f32, |peak, sample| peak.max(history.next(*sample)));
+        self.held_peak = self.held_peak.max(true_peak);
+
+        true_peak
+    }
+
+    /// Process an entire buffer and update both the instantaneous and the held true peak. The
+    /// buffer must have the same number of channels the meter was created with.
+    pub fn process(&mut self, buffer: &Buffer) {
+        nih_debug_assert_eq!(buffer.channels(), self.histories.len());
+
+        let mut peak = 0.0f32;
+        for (channel, samples) in buffer.as_slice_immutable().iter().enumerate() {
+            peak = peak.max(self.process_block(channel, samples));
+        }
+        self.peak = peak;
+    }
+}
+
+impl History {
+    fn new() -> Self {
+        Self {
+            samples: [0.0; NUM_TAPS_PER_PHASE * 2],
+            pos: 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.samples.fill(0.0);
+        self.pos = 0;
+    }
+
+    /// Add a sample to the history and return the highest absolute value of the four interpolated
+    /// samples.
+    #[allow(clippy::should_implement_trait)]
+    #[inline]
+    fn next(&mut self, sample: f32) -> f32 {
+        self.samples[self.pos] = sample;
+        self.samples[self.pos + NUM_TAPS_PER_PHASE] = sample;
+        self.pos += 1;
+        if self.pos == NUM_TAPS_PER_PHASE {
+            self.pos = 0;
+        }
+
+        // These are ordered from the oldest to the most recent sample
+        let samples = &self.samples[self.pos..self.pos + NUM_TAPS_PER_PHASE];
+        PHASES.iter().fold(0.0f32, |peak, phase| {
+            let interpolated: f32 = samples
+                .iter()
+                .zip(phase.iter().rev())
+                .map(|(sample, coefficient)| sample * coefficient)
+                .sum();
+
+            peak.max(interpolated.abs())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts;
+
+    #[test]
+    fn inter_sample_peaks() {
+        // A sine wave at a quarter of the sample rate with a 45 degree phase offset only ever hits
+        // +-0.707 at the sample positions, but its actual peak is 1.0
+        let signal: Vec<f32> = (0..256)
+            .map(|i| (i as f32 * consts::FRAC_PI_2 + consts::FRAC_PI_4).sin())
+            .collect();
+        let sample_peak = signal.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        approx::assert_relative_eq!(gain_to_db(sample_peak), -3.01, epsilon = 0.01);
+
+        let mut meter = TruePeakMeter::new(1);
+        meter.process_block(0, &signal);
+        let true_peak_db = meter.held_peak_dbtp();
+        assert!(
+            (-0.5..=0.5).contains(&true_peak_db),
+            "True peak was {true_peak_db} dBTP"
+        );
+    }
+
+    #[test]
+    fn low_frequencies() {
+        // Below a quarter of the sample rate the true peak should be very close to the actual peak
+        let mut meter = TruePeakMeter::new(1);
+        let true_peak = (0..4800)
+            .map(|i| (i as f32 * consts::TAU * 997.0 / 48_000.0).sin() * 0.5)
+            .fold(0.0f32, |peak, sample| {
+                peak.max(meter.process_sample(0, sample))
+            });
+        approx::assert_relative_eq!(true_peak, 0.5, epsilon = 0.01);
+
+        meter.reset_held_peak();
+        assert_eq!(meter.held_peak(), 0.0);
+        meter.process_sample(0, 0.25);
+        assert!(meter.held_peak() > 0.0);
+
+        meter.reset();
+        assert_eq!(meter.held_peak_dbtp(), gain_to_db(0.0));
+    }
+}
@@ -0,0 +1,508 @@
+//! Microtonal tuning using Scala scale (`.scl`) and keyboard mapping (`.kbm`) files.
+//!
+//! Parsing these files and computing a [`TuningTable`] allocates, so this should be done in a
+//! [`Plugin::BackgroundTask`][crate::prelude::Plugin::BackgroundTask]. The finished table can then
+//! be handed to the audio thread through a [`TuningTableQueue`]:
+//!
+//! ```
+//! # use nih_plug::util::tuning::{KeyboardMapping, Scale, TuningTable, TuningTableQueue};
+//! # let scl_contents = "! 5-TET\nFive tone equal temperament\n5\n240.0\n480.0\n720.0\n960.0\n2/1\n";
+//! let queue = TuningTableQueue::new();
+//!
+//! // In the background task
+//! let scale = Scale::parse(scl_contents).unwrap();
+//! let table = TuningTable::new(&scale, &KeyboardMapping::default()).unwrap();
+//! queue.push(table);
+//!
+//! // At the start of the process function
+//! let mut current_table = TuningTable::default();
+//! if let Some(new_table) = queue.pop() {
+//!     current_table = new_table;
+//! }
+//! let frequency = current_table.note_to_freq(69).unwrap();
+//! ```
+
+use anyhow::{Context, Result};
+use crossbeam::queue::ArrayQueue;
+use std::path::Path;
+
+/// The number of MIDI notes covered by a [`TuningTable`].
+pub const NUM_NOTES: usize = 128;
+
+/// A scale parsed from a Scala `.scl` file. The scale consists of one or more pitches relative to
+/// the implicit `1/1` unison. The last pitch is the scale's period, which is usually an octave.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scale {
+    description: String,
+    /// The scale's pitches in cents, not including the unison.
+    pitches: Vec<f64>,
+}
+
+/// A keyboard mapping parsed from a Scala `.kbm` file. This defines which MIDI notes map to which
+/// scale degrees, and which note is tuned to which reference frequency. The default mapping maps
+/// every MIDI note to consecutive scale degrees with the scale's unison on middle C and A4 tuned
+/// to 440 Hz, just like with regular 12-TET when used with a 12 tone equal tempered scale.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyboardMapping {
+    /// The first MIDI note that is mapped. Notes below this are unmapped.
+    pub first_note: u8,
+    /// The last MIDI note that is mapped. Notes above this are unmapped.
+    pub last_note: u8,
+    /// The MIDI note the first entry of the mapping, and thus the scale's unison, is mapped to.
+    pub middle_note: u8,
+    /// The MIDI note tuned to `reference_frequency`.
+    pub reference_note: u8,
+    /// The frequency in Hz `reference_note` is tuned to.
+    pub reference_frequency: f64,
+    /// The scale degree that's added every time the mapping repeats. `None` uses the scale's
+    /// period.
+    pub octave_degree: Option<usize>,
+    /// The scale degree for every key in the repeating mapping pattern, starting at
+    /// `middle_note`. Keys mapped to `None` are unmapped. An empty mapping maps every key to the
+    /// next scale degree.
+    pub mapping: Vec<Option<usize>>,
+}
+
+/// A table mapping MIDI note numbers to frequencies. This can be copied around without allocating,
+/// and it can be sent to the audio thread using a [`TuningTableQueue`]. The default table uses
+/// 12-TET with A4 tuned to 440 Hz.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TuningTable {
+    frequencies: [Option<f32>; NUM_NOTES],
+}
+
+/// Hands [`TuningTable`]s computed on a background thread to the audio thread without locking or
+/// allocating. Only the most recently pushed table is kept. Share this between the background task
+/// executor and the plugin using an `Arc`.
+#[derive(Debug)]
+pub struct TuningTableQueue {
+    queue: ArrayQueue<TuningTable>,
+}
+
+impl Scale {
+    /// Parse the contents of a Scala `.scl` file. Pitches containing a period are interpreted as
+    /// cents, and other pitches are interpreted as ratios or whole numbers.
+    pub fn parse(contents: &str) -> Result<Self> {
+        // Lines starting with an exclamation mark are comments. The description may be empty, so
+        // empty lines need to be kept.
+        let mut lines = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with('!'));
+
+        let description = lines
+            .next()
+            .context("The scale is missing a description line")?
+            .to_owned();
+        let num_pitches_line = lines
+            .next()
+            .context("The scale is missing the number of notes")?;
+        let num_pitches: usize = first_field(num_pitches_line)
+            .parse()
+            .with_context(|| format!("Invalid number of notes '{num_pitches_line}'"))?;
+
+        let pitches = lines
+            .filter(|line| !line.is_empty())
+            .take(num_pitches)
+            .map(parse_pitch)
+            .collect::<Result<Vec<_>>>()?;
+        if pitches.is_empty() {
+            anyhow::bail!("The scale does not contain any notes");
+        }
+        if pitches.len() != num_pitches {
+            anyhow::bail!(
+                "The scale should contain {num_pitches} notes, but only {} were found",
+                pitches.len()
+            );
+        }
+
+        Ok(Self {
+            description,
+            pitches,
+        })
+    }
+
+    /// Read and parse a Scala `.scl` file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read '{}'", path.display()))?;
+
+        Self::parse(&contents).with_context(|| format!("Could not parse '{}'", path.display()))
+    }
+
+    /// The scale's description.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// The scale's pitches in cents relative to the unison. The last pitch is the scale's period.
+    pub fn pitches(&self) -> &[f64] {
+        &self.pitches
+    }
+
+    /// The number of notes in the scale, including the period but not the unison.
+    pub fn len(&self) -> usize {
+        self.pitches.len()
+    }
+
+    /// Always returns `false` since a parsed scale contains at least one note.
+    pub fn is_empty(&self) -> bool {
+        self.pitches.is_empty()
+    }
+
+    /// The pitch of a scale degree in cents. Degrees outside of the scale wrap around the scale's
+    /// period.
+    fn degree_cents(&self, degree: i32) -> f64 {
+        let num_pitches = self.pitches.len() as i32;
+        let period = self.pitches[self.pitches.len() - 1];
+        let periods = degree.div_euclid(num_pitches);
+        let index = degree.rem_euclid(num_pitches);
+
+        let cents = if index == 0 {
+            0.0
+        } else {
+            self.pitches[index as usize - 1]
+        };
+
+        cents + periods as f64 * period
+    }
+}
+
+impl Default for KeyboardMapping {
+    fn default() -> Self {
+        Self {
+            first_note: 0,
+            last_note: 127,
+            middle_note: 60,
+            reference_note: 69,
+            reference_frequency: 440.0,
+            octave_degree: None,
+            mapping: Vec::new(),
+        }
+    }
+}
+
+impl KeyboardMapping {
+    /// Parse the contents of a Scala `.kbm` file. Unmapped keys are marked with an `x`.
+    pub fn parse(contents: &str) -> Result<Self> {
+        let mut lines = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('!'))
+            .map(first_field);
+        let mut next_field = |name: &str| {
+            lines
+                .next()
+                .with_context(|| format!("The keyboard mapping is missing the {name}"))
+        };
+
+        let map_size: usize = parse_field(next_field("map size")?, "map size")?;
+        let first_note = parse_note(next_field("first note")?, "first note")?;
+        let last_note = parse_note(next_field("last note")?, "last note")?;
+        let middle_note = parse_note(next_field("middle note")?, "middle note")?;
+        let reference_note = parse_note(next_field("reference note")?, "reference note")?;
+        let reference_frequency: f64 =
+            parse_field(next_field("reference frequency")?, "reference frequency")?;
+        let octave_degree: usize = parse_field(next_field("octave degree")?, "octave degree")?;
+        if reference_frequency.is_nan() || reference_frequency <= 0.0 {
+            anyhow::bail!("Invalid reference frequency '{reference_frequency}'");
+        }
+
+        // Missing entries at the end of the mapping are treated as unmapped keys
+        let mut mapping = Vec::with_capacity(map_size);
+        for _ in 0..map_size {
+            mapping.push(match lines.next() {
+                Some("x" | "X") | None => None,
+                Some(field) => Some(parse_field(field, "mapping entry")?),
+            });
+        }
+
+        Ok(Self {
+            first_note,
+            last_note,
+            middle_note,
+            reference_note,
+            reference_frequency,
+            octave_degree: if octave_degree == 0 {
+                None
+            } else {
+                Some(octave_degree)
+            },
+            mapping,
+        })
+    }
+
+    /// Read and parse a Scala `.kbm` file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read '{}'", path.display()))?;
+
+        Self::parse(&contents).with_context(|| format!("Could not parse '{}'", path.display()))
+    }
+
+    /// The pitch of a note in cents relative to `middle_note` in `scale`, or `None` if the note is
+    /// not mapped.
+    fn note_cents(&self, scale: &Scale, note: u8) -> Option<f64> {
+        if note < self.first_note || note > self.last_note {
+            return None;
+        }
+
+        let offset = note as i32 - self.middle_note as i32;
+        if self.mapping.is_empty() {
+            return Some(scale.degree_cents(offset));
+        }
+
+        let map_size = self.mapping.len() as i32;
+        let repetitions = offset.div_euclid(map_size);
+        let degree = self.mapping[offset.rem_euclid(map_size) as usize]?;
+        let octave_cents = match self.octave_degree {
+            Some(octave_degree) => scale.degree_cents(octave_degree as i32),
+            None => scale.degree_cents(scale.len() as i32),
+        };
+
+        Some(scale.degree_cents(degree as i32) + repetitions as f64 * octave_cents)
+    }
+}
+
+impl Default for TuningTable {
+    fn default() -> Self {
+        let mut frequencies = [None; NUM_NOTES];
+        for (note, frequency) in frequencies.iter_mut().enumerate() {
+            *frequency = Some(super::midi_note_to_freq(note as u8));
+        }
+
+        Self { frequencies }
+    }
+}
+
+impl TuningTable {
+    /// Compute the frequencies for all MIDI notes using a scale and a keyboard mapping. Returns an
+    /// error if the mapping's reference note is not mapped to a scale degree.
+    pub fn new(scale: &Scale, mapping: &KeyboardMapping) -> Result<Self> {
+        let reference_cents = mapping
+            .note_cents(scale, mapping.reference_note)
+            .with_context(|| {
+                format!(
+                    "The reference note {} is not mapped to a scale degree",
+                    mapping.reference_note
+                )
+            })?;
+
+        let mut frequencies = [None; NUM_NOTES];
+        for (note, frequency) in frequencies.iter_mut().enumerate() {
+            *frequency = mapping.note_cents(scale, note as u8).map(|cents| {
+                (mapping.reference_frequency * 2.0f64.powf((cents - reference_cents) / 1200.0))
+                    as f32
+            });
+        }
+
+        Ok(Self { frequencies })
+    }
+
+    /// Get the frequency for a MIDI note, or `None` if the note is not mapped and should not be
+    /// played.
+    #[inline]
+    pub fn note_to_freq(&self, note: u8) -> Option<f32> {
+        self.frequencies.get(note as usize).copied().flatten()
+    }
+}
+
+impl Default for TuningTableQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TuningTableQueue {
+    pub fn new() -> Self {
+        Self {
+            queue: ArrayQueue::new(1),
+        }
+    }
+
+    /// Send a new tuning table to the audio thread, replacing any table that has not yet been
+    /// received.
+    pub fn push(&self, table: TuningTable) {
+        self.queue.force_push(table);
+    }
+
+    /// Receive the most recently pushed tuning table, if there is one. This is realtime-safe.
+    pub fn pop(&self) -> Option<TuningTable> {
+        self.queue.pop()
+    }
+}
+
+/// Only the first whitespace separated field of a line is significant. Everything after it is a
+/// comment.
+fn first_field(line: &str) -> &str {
+    line.split_whitespace().next().unwrap_or("")
+}
+
+fn parse_field<T: std::str::FromStr>(field: &str, name: &str) -> Result<T> {
+    field
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid {name} '{field}'"))
+}
+
+fn parse_note(field: &str, name: &str) -> Result<u8> {
+    let note: u8 = parse_field(field, name)?;
+    if note as usize >= NUM_NOTES {
+        anyhow::bail!("Invalid {name} '{field}'");
+    }
+
+    Ok(note)
+}
+
+/// Parse a pitch line from a `.scl` file, returning the pitch in cents.
+fn parse_pitch(line: &str) -> Result<f64> {
+    let field = first_field(line);
+    let cents = if field.contains('.') {
+        parse_field(field, "pitch")?
+    } else {
+        let (numerator, denominator) = field.split_once('/').unwrap_or((field, "1"));
+        let numerator: u64 = parse_field(numerator, "pitch")?;
+        let denominator: u64 = parse_field(denominator, "pitch")?;
+        if numerator == 0 || denominator == 0 {
+            anyhow::bail!("Invalid ratio '{field}'");
+        }
+
+        (numerator as f64 / denominator as f64).log2() * 1200.0
+    };
+
+    Ok(cents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MEANTONE: &str = "! meanquar.scl
+!
+1/4-comma meantone scale. Pietro Aaron's temperament (1523)
+ 12
+!
+ 76.04900
+ 193.15686
+ 310.26471
+ 5/4
+ 503.42157
+ 579.47057
+ 696.57843
+ 25/16
+ 889.73529
+ 1006.84314
+ 1082.89214
+ 2/1
+";
+
+    #[test]
+    fn parse_scale() {
+        let scale = Scale::parse(MEANTONE).unwrap();
+        assert_eq!(
+            scale.description(),
+            "1/4-comma meantone scale. Pietro Aaron's temperament (1523)"
+        );
+        assert_eq!(scale.len(), 12);
+        assert_eq!(scale.pitches()[0], 76.049);
+        approx::assert_relative_eq!(scale.pitches()[3], 386.3137, epsilon = 1e-4);
+        approx::assert_relative_eq!(scale.pitches()[11], 1200.0);
+    }
+
+    #[test]
+    fn parse_invalid_scale() {
+        assert!(Scale::parse("").is_err());
+        assert!(Scale::parse("Too few notes\n3\n100.0\n2/1\n").is_err());
+        assert!(Scale::parse("Bad ratio\n1\n0/1\n").is_err());
+    }
+
+    #[test]
+    fn default_table_is_12_tet() {
+        let scale = Scale::parse(
+            "12-TET\n12\n100.\n200.\n300.\n400.\n500.\n600.\n700.\n800.\n900.\n1000.\n1100.\n2/1\n",
+        )
+        .unwrap();
+        let table = TuningTable::new(&scale, &KeyboardMapping::default()).unwrap();
+        let default_table = TuningTable::default();
+
+        for note in 0..NUM_NOTES as u8 {
+            approx::assert_relative_eq!(
+                table.note_to_freq(note).unwrap(),
+                default_table.note_to_freq(note).unwrap(),
+                max_relative = 1e-5
+            );
+        }
+    }
+
+    #[test]
+    fn keyboard_mapping() {
+        // Maps the white keys to a five note scale with a 3/2 period, leaving the black keys
+        // unmapped, with middle C tuned to 256 Hz
+        let mapping = KeyboardMapping::parse(
+            "! white keys
+12
+0
+127
+60
+60
+256.0
+5
+! Mapping
+0
+x
+1
+x
+2
+3
+x
+4
+x
+5
+x
+6
+",
+        )
+        .unwrap();
+        assert_eq!(mapping.mapping.len(), 12);
+        assert_eq!(mapping.octave_degree, Some(5));
+
+        let scale = Scale::parse("Equal fifths\n5\n140.\n280.\n420.\n560.\n3/2\n").unwrap();
+        let table = TuningTable::new(&scale, &mapping).unwrap();
+        assert_eq!(table.note_to_freq(60), Some(256.0));
+        assert_eq!(table.note_to_freq(61), None);
+        approx::assert_relative_eq!(
+            table.note_to_freq(62).unwrap(),
+            256.0 * 2.0f32.powf(140.0 / 1200.0)
+        );
+        approx::assert_relative_eq!(table.note_to_freq(72).unwrap(), 384.0, max_relative = 1e-6);
+        approx::assert_relative_eq!(
+            table.note_to_freq(48).unwrap(),
+            256.0 / 1.5,
+            max_relative = 1e-6
+        );
+    }
+
+    #[test]
+    fn unmapped_reference_note() {
+        let mapping = KeyboardMapping {
+            last_note: 60,
+            ..KeyboardMapping::default()
+        };
+
+        let scale = Scale::parse("Octave\n1\n2/1\n").unwrap();
+        assert!(TuningTable::new(&scale, &mapping).is_err());
+    }
+
+    #[test]
+    fn queue_keeps_latest_table() {
+        let scale = Scale::parse("Octave\n1\n2/1\n").unwrap();
+        let table = TuningTable::new(&scale, &KeyboardMapping::default()).unwrap();
+
+        let queue = TuningTableQueue::new();
+        queue.push(TuningTable::default());
+        queue.push(table);
+        assert_eq!(queue.pop(), Some(table));
+        assert_eq!(queue.pop(), None);
+    }
+}
@@ -0,0 +1,406 @@
+//! Detuned and panned unison copies of a band-limited oscillator.
+
+use super::{equal_power_pan, Oscillator, Rng, Waveform};
+
+/// The maximum number of unison copies a [`Unison`] oscillator can play.
+pub const MAX_UNISON_VOICES: usize = 16;
+
+/// How long it takes for copies to fade in or out and to move to their new detune and pan
+/// positions when the number of unison copies changes, in milliseconds.
+const VOICE_COUNT_FADE_MS: f32 = 10.0;
+
+/// Compute the frequency multiplier and the pan position for copy `index` out of `num_voices`
+/// unison copies. The copies are spread evenly over `[-detune_semitones, detune_semitones]` and
+/// `[-stereo_spread, stereo_spread]`, with the lowest copy panned furthest to the left. The
+/// distribution is symmetrical: the multipliers of copies mirrored around the center multiply to
+/// one, and their pan positions add up to zero. With an odd number of copies the middle copy is
+/// not detuned and panned to the center. The pan position can be passed to
+/// [`equal_power_pan()`][super::equal_power_pan()].
+pub fn unison_spread(
+    index: usize,
+    num_voices: usize,
+    detune_semitones: f32,
+    stereo_spread: f32,
+) -> (f32, f32) {
+    let offset = unison_offset(index, num_voices);
+
+    (
+        detune_multiplier(offset, detune_semitones),
+        offset * stereo_spread,
+    )
+}
+
+/// A supersaw-style unison oscillator. This plays up to [`MAX_UNISON_VOICES`] copies of a
+/// band-limited [`Oscillator`] that are detuned and panned according to [`unison_spread()`], and
+/// it mixes them down to stereo. Like the oscillator, this is meant to be owned by a single voice,
+/// so every voice assigned by a [`VoiceAllocator`][super::VoiceAllocator] has its own
+/// `Unison`.
+///
+/// The mix is normalized by the square root of the number of copies, so the loudness stays roughly
+/// the same when the number of copies changes. A single copy is panned to the center at -3 dB.
+///
+/// Changing the number of copies with [`set_num_voices()`][Self::set_num_voices()] is smooth:
+/// new copies fade in, removed copies fade out, and the remaining copies glide to their new detune
+/// and pan positions over 10 milliseconds. The CPU usage scales linearly with the number of copies.
+/// Copies that are fading out still count until they are silent, and while the copies are moving
+/// their multipliers and pan gains are recomputed every sample.
+///
+/// ```ignore
+/// // When the voice starts
+/// voice.unison.set_num_voices(self.params.unison_voices.value() as usize);
+/// voice.unison.reset();
+///
+/// // And then for every sample
+/// voice.unison.set_detune(self.params.detune.smoothed.next());
+/// let (left, right) = voice.unison.next(util::midi_note_to_freq(voice.note));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Unison {
+    sample_rate: f32,
+    num_voices: usize,
+    detune_semitones: f32,
+    stereo_spread: f32,
+    /// Used to randomize the copies' phases when they are reset, if set.
+    phase_rng: Option<Rng>,
+
+    /// Always contains [`MAX_UNISON_VOICES`] copies. Inactive copies have a gain of zero.
+    copies: Vec<UnisonCopy>,
+    /// The number of samples left until the copies reach their target gains and positions.
+    fade_steps_left: u32,
+}
+
+/// A single detuned copy in a [`Unison`] oscillator.
+#[derive(Debug, Clone)]
+struct UnisonCopy {
+    oscillator: Oscillator,
+    /// The copy's gain in `[0, 1]`. Used to fade copies in and out.
+    gain: f32,
+    gain_step: f32,
+    target_gain: f32,
+    /// The copy's position in the unison spread, in `[-1, 1]`.
+    offset: f32,
+    offset_step: f32,
+    target_offset: f32,
+
+    /// The frequency multiplier for the copy's current offset.
+    multiplier: f32,
+    /// The left and right channel gains for the copy's current offset.
+    pan_gains: (f32, f32),
+}
+
+impl Unison {
+    /// Create a unison oscillator with a single copy. The detune and stereo spread are zero until
+    /// they're changed.
+    pub fn new(sample_rate: f32, waveform: Waveform) -> Self {
+        let mut unison = Self {
+            sample_rate,
+            num_voices: 1,
+            detune_semitones: 0.0,
+            stereo_spread: 0.0,
+            phase_rng: None,
+
+            copies: vec![
+                UnisonCopy {
+                    oscillator: Oscillator::new(sample_rate, waveform),
+                    gain: 0.0,
+                    gain_step: 0.0,
+                    target_gain: 0.0,
+                    offset: 0.0,
+                    offset_step: 0.0,
+                    target_offset: 0.0,
+
+                    multiplier: 1.0,
+                    pan_gains: equal_power_pan(0.0),
+                };
+                MAX_UNISON_VOICES
+            ],
+            fade_steps_left: 0,
+        };
+        unison.copies[0].gain = 1.0;
+        unison.copies[0].target_gain = 1.0;
+
+        unison
+    }
+
+    /// Randomize the copies' phases whenever they are reset using this random number generator,
+    /// for instance [`Rng::for_voice()`]. Otherwise all copies start at the start of their cycle.
+    pub fn with_random_phases(mut self, rng: Rng) -> Self {
+        self.phase_rng = Some(rng);
+        self
+    }
+
+    /// Change the sample rate. This should be called from the plugin's `initialize()` function.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        for copy in &mut self.copies {
+            copy.oscillator.set_sample_rate(sample_rate);
+        }
+    }
+
+    /// Change the waveform of all copies.
+    pub fn set_waveform(&mut self, waveform: Waveform) {
+        for copy in &mut self.copies {
+            copy.oscillator.set_waveform(waveform);
+        }
+    }
+
+    /// Change the number of copies. This is clamped to `1..=MAX_UNISON_VOICES`. Copies that are
+    /// added fade in from a new phase, and the other copies smoothly move to their new positions.
+    pub fn set_num_voices(&mut self, num_voices: usize) {
+        let num_voices = num_voices.clamp(1, MAX_UNISON_VOICES);
+        if num_voices == self.num_voices {
+            return;
+        }
+        self.num_voices = num_voices;
+
+        let fade_steps = ((VOICE_COUNT_FADE_MS / 1000.0) * self.sample_rate)
+            .round()
+            .max(1.0);
+        for (index, copy) in self.copies.iter_mut().enumerate() {
+            if index < num_voices {
+                copy.target_gain = 1.0;
+                copy.target_offset = unison_offset(index, num_voices);
+
+                // New copies start at their final position
+                if copy.gain == 0.0 {
+                    copy.offset = copy.target_offset;
+                    reset_copy(copy, &mut self.phase_rng);
+                    copy.update_position(self.detune_semitones, self.stereo_spread);
+                }
+            } else {
+                copy.target_gain = 0.0;
+                copy.target_offset = copy.offset;
+            }
+
+            copy.gain_step = (copy.target_gain - copy.gain) / fade_steps;
+            copy.offset_step = (copy.target_offset - copy.offset) / fade_steps;
+        }
+        self.fade_steps_left = fade_steps as u32;
+    }
+
+    /// Change the detune amount. The outermost copies are detuned by this many semitones.
+    pub fn set_detune(&mut self, detune_semitones: f32) {
+        if detune_semitones != self.detune_semitones {
+            self.detune_semitones = detune_semitones;
+            self.update_positions();
+        }
+    }
+
+    /// Change the stereo spread, in `[0, 1]`. At 1 the outermost copies are panned hard left and
+    /// hard right.
+    pub fn set_stereo_spread(&mut self, stereo_spread: f32) {
+        let stereo_spread = stereo_spread.clamp(0.0, 1.0);
+        if stereo_spread != self.stereo_spread {
+            self.stereo_spread = stereo_spread;
+            self.update_positions();
+        }
+    }
+
+    /// The number of copies.
+    pub fn num_voices(&self) -> usize {
+        self.num_voices
+    }
+
+    /// The number of copies that are currently being rendered. This is higher than
+    /// [`num_voices()`][Self::num_voices()] while removed copies are fading out.
+    pub fn active_copies(&self) -> usize {
+        self.copies.iter().filter(|copy| copy.is_active()).count()
+    }
+
+    /// Reset all copies' phases. If [`with_random_phases()`][Self::with_random_phases()] was used,
+    /// then every copy starts at a random phase. Copies that are fading out are silenced
+    /// immediately. Call this when the voice gets (re)triggered.
+    pub fn reset(&mut self) {
+        for (index, copy) in self.copies.iter_mut().enumerate() {
+            if index < self.num_voices {
+                copy.gain = 1.0;
+                copy.offset = unison_offset(index, self.num_voices);
+            } else {
+                copy.gain = 0.0;
+            }
+            copy.target_gain = copy.gain;
+            copy.target_offset = copy.offset;
+            reset_copy(copy, &mut self.phase_rng);
+        }
+        self.fade_steps_left = 0;
+        self.update_positions();
+    }
+
+    /// Generate the next stereo sample for a note at `frequency` Hz. The frequency can be changed
+    /// every sample for modulation.
+    pub fn next(&mut self, frequency: f32) -> (f32, f32) {
+        let is_fading = self.fade_steps_left > 0;
+        if is_fading {
+            self.fade_steps_left -= 1;
+        }
+        let fade_finished = is_fading && self.fade_steps_left == 0;
+
+        let mut left = 0.0;
+        let mut right = 0.0;
+        let mut total_power = 0.0;
+        for copy in &mut self.copies {
+            if is_fading {
+                copy.advance_fade(fade_finished, self.detune_semitones, self.stereo_spread);
+            }
+            if !copy.is_active() {
+                continue;
+            }
+
+            copy.oscillator.set_frequency(frequency * copy.multiplier);
+            let sample = copy.oscillator.next() * copy.gain;
+            left += sample * copy.pan_gains.0;
+            right += sample * copy.pan_gains.1;
+            total_power += copy.gain * copy.gain;
+        }
+
+        if total_power > 0.0 {
+            let normalization = total_power.sqrt().recip();
+            (left * normalization, right * normalization)
+        } else {
+            (0.0, 0.0)
+        }
+    }
+
+    /// Fill a stereo block with the next samples. Both slices should have the same length.
+    pub fn next_block(&mut self, frequency: f32, left: &mut [f32], right: &mut [f32]) {
+        nih_debug_assert_eq!(left.len(), right.len());
+        for (left, right) in left.iter_mut().zip(right) {
+            (*left, *right) = self.next(frequency);
+        }
+    }
+
+    fn update_positions(&mut self) {
+        for copy in &mut self.copies {
+            copy.update_position(self.detune_semitones, self.stereo_spread);
+        }
+    }
+}
+
+impl UnisonCopy {
+    fn is_active(&self) -> bool {
+        self.gain > 0.0 || self.target_gain > 0.0
+    }
+
+    /// Move the gain and offset one step closer to their targets. On the last step they're set to
+    /// the exact targets.
+    fn advance_fade(&mut self, fade_finished: bool, detune_semitones: f32, stereo_spread: f32) {
+        if fade_finished {
+            self.gain = self.target_gain;
+            self.offset = self.target_offset;
+        } else {
+            self.gain += self.gain_step;
+            self.offset += self.offset_step;
+        }
+
+        if self.offset_step != 0.0 {
+            self.update_position(detune_semitones, stereo_spread);
+        }
+    }
+
+    fn update_position(&mut self, detune_semitones: f32, stereo_spread: f32) {
+        self.multiplier = detune_multiplier(self.offset, detune_semitones);
+        self.pan_gains = equal_power_pan(self.offset * stereo_spread);
+    }
+}
+
+/// Reset a copy's oscillator to a random phase if `phase_rng` is set, or to the start of its cycle
+/// otherwise.
+fn reset_copy(copy: &mut UnisonCopy, phase_rng: &mut Option<Rng>) {
+    match phase_rng {
+        Some(rng) => copy.oscillator.reset_to_phase(rng.next_f32()),
+        None => copy.oscillator.reset(),
+    }
+}
+
+/// The position of copy `index` out of `num_voices` in the unison spread, in `[-1, 1]`.
+fn unison_offset(index: usize, num_voices: usize) -> f32 {
+    if num_voices <= 1 {
+        0.0
+    } else {
+        ((index as f32 * 2.0) / (num_voices - 1) as f32) - 1.0
+    }
+}
+
+fn detune_multiplier(offset: f32, detune_semitones: f32) -> f32 {
+    (offset * detune_semitones / 12.0).exp2()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    #[test]
+    fn spread_symmetry() {
+        for num_voices in 1..=MAX_UNISON_VOICES {
+            let spread: Vec<(f32, f32)> = (0..num_voices)
+                .map(|index| unison_spread(index, num_voices, 0.5, 0.8))
+                .collect();
+
+            for (index, &(multiplier, pan)) in spread.iter().enumerate() {
+                let (mirrored_multiplier, mirrored_pan) = spread[num_voices - 1 - index];
+                approx::assert_relative_eq!(multiplier * mirrored_multiplier, 1.0, epsilon = 1e-6);
+                approx::assert_relative_eq!(pan, -mirrored_pan, epsilon = 1e-6);
+            }
+
+            // The copies are spread evenly from the lowest to the highest copy
+            let (lowest_multiplier, lowest_pan) = spread[0];
+            let (highest_multiplier, highest_pan) = spread[num_voices - 1];
+            if num_voices > 1 {
+                approx::assert_relative_eq!(lowest_multiplier, (-0.5f32 / 12.0).exp2());
+                approx::assert_relative_eq!(highest_multiplier, (0.5f32 / 12.0).exp2());
+                assert_eq!(lowest_pan, -0.8);
+                assert_eq!(highest_pan, 0.8);
+            }
+            if num_voices % 2 == 1 {
+                assert_eq!(spread[num_voices / 2], (1.0, 0.0));
+            }
+        }
+    }
+
+    #[test]
+    fn change_voice_count() {
+        let mut unison = Unison::new(SAMPLE_RATE, Waveform::Saw);
+        unison.set_detune(0.2);
+        unison.set_stereo_spread(1.0);
+        unison.set_num_voices(7);
+        assert_eq!(unison.num_voices(), 7);
+        assert_eq!(unison.active_copies(), 7);
+
+        let fade_samples = (VOICE_COUNT_FADE_MS / 1000.0 * SAMPLE_RATE) as usize;
+        for _ in 0..fade_samples {
+            let (left, right) = unison.next(220.0);
+            assert!(left.is_finite() && right.is_finite());
+        }
+
+        // Removed copies keep playing until they've faded out
+        unison.set_num_voices(3);
+        assert_eq!(unison.active_copies(), 7);
+        for _ in 0..fade_samples {
+            unison.next(220.0);
+        }
+        assert_eq!(unison.active_copies(), 3);
+        assert_eq!(unison.copies[0].offset, -1.0);
+        assert_eq!(unison.copies[2].offset, 1.0);
+
+        // The voice count is clamped
+        unison.set_num_voices(0);
+        assert_eq!(unison.num_voices(), 1);
+        unison.set_num_voices(1000);
+        assert_eq!(unison.num_voices(), MAX_UNISON_VOICES);
+    }
+
+    #[test]
+    fn random_phases() {
+        let mut unison = Unison::new(SAMPLE_RATE, Waveform::Saw).with_random_phases(Rng::new(1));
+        unison.set_num_voices(2);
+        unison.reset();
+
+        // Without detuning or spread, copies with the same phase would be identical
+        assert_ne!(
+            unison.copies[0].oscillator.clone().next(),
+            unison.copies[1].oscillator.clone().next()
+        );
+    }
+}
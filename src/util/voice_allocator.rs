@@ -0,0 +1,402 @@
+//! Assigning notes to voices with a polyphony limit that can change at runtime.
+
+/// Assigns notes to a fixed number of voice slots for polyphonic synthesizers. The allocator only
+/// keeps track of which note is playing in which slot. The plugin stores its own voice state in an
+/// array of the same size and uses the slot indices returned from this allocator to index it.
+///
+/// The number of voices that can play at the same time can be limited with
+/// [`set_polyphony()`][Self::set_polyphony()], for instance from an [`IntParam`] that ranges from
+/// 1 to the allocator's capacity. When the limit is lowered, only as many voices as needed to get
+/// under the new limit are stolen and the remaining voices keep playing in their slots. Releasing
+/// voices are stolen before held voices, and older voices are stolen before newer ones. The same
+/// rules apply when a note is started while the limit has been reached. Plugins that use polyphonic
+/// modulation should also report the new limit to the host using
+/// [`ProcessContext::set_current_voice_capacity()`][crate::prelude::ProcessContext::set_current_voice_capacity()].
+///
+/// With a polyphony of one the allocator is monophonic with legato. A new note started while
+/// another note is held takes over that voice instead of stealing it, so the voice can glide to
+/// the new pitch without restarting its envelopes. See [`VoiceAssignment::legato`].
+///
+/// The `poly_mod_synth` example plugin drives the polyphony limit with an [`IntParam`]:
+///
+/// ```ignore
+/// // At the start of every block
+/// self.voice_allocator
+///     .set_polyphony(self.params.voices.value() as usize, |index, stolen| {
+///         self.voices[index] = None;
+///         context.send_event(NoteEvent::VoiceTerminated {
+///             timing: 0,
+///             voice_id: Some(stolen.voice_id),
+///             channel: stolen.channel,
+///             note: stolen.note,
+///         });
+///     });
+///
+/// // The number of sounding voices can be shown in the GUI
+/// self.active_voices
+///     .store(self.voice_allocator.active_voices(), Ordering::Relaxed);
+/// ```
+///
+/// [`IntParam`]: crate::prelude::IntParam
+#[derive(Debug, Clone)]
+pub struct VoiceAllocator {
+    /// The voice slots. Free slots are `None`.
+    voices: Vec<Option<AllocatedVoice>>,
+    /// The maximum number of voices that can be active at the same time, in `1..=voices.len()`.
+    polyphony: usize,
+    /// The age assigned to the next note. This is incremented for every note so the oldest voice
+    /// can be found when stealing.
+    next_age: u64,
+}
+
+/// A note that has been assigned to one of a [`VoiceAllocator`]'s voice slots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocatedVoice {
+    /// The note's voice ID. If the host did not provide one, then this is computed from the note's
+    /// channel and note number.
+    pub voice_id: i32,
+    /// The note's channel, in `0..16`.
+    pub channel: u8,
+    /// The note's note number, in `0..128`.
+    pub note: u8,
+    /// Whether the note has been released. The voice stays allocated until it is
+    /// [terminated][VoiceAllocator::terminate()].
+    pub releasing: bool,
+    /// Used to find the oldest voice when stealing voices.
+    age: u64,
+}
+
+/// The result of [`VoiceAllocator::note_on()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoiceAssignment {
+    /// The index of the voice slot the note has been assigned to.
+    pub index: usize,
+    /// If the allocator is monophonic and the note took over a held voice, then this contains the
+    /// note that voice was playing before. The voice should glide to the new note instead of
+    /// restarting its envelopes. The previous note's voice has still ended, so CLAP plugins should
+    /// send a [`NoteEvent::VoiceTerminated`][crate::prelude::NoteEvent::VoiceTerminated] for it.
+    pub legato: Option<AllocatedVoice>,
+}
+
+impl VoiceAllocator {
+    /// Create an allocator with `capacity` voice slots. The polyphony limit is initially set to
+    /// the capacity. The capacity is at least one voice.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+
+        Self {
+            voices: vec![None; capacity],
+            polyphony: capacity,
+            next_age: 0,
+        }
+    }
+
+    /// The number of voice slots.
+    pub fn capacity(&self) -> usize {
+        self.voices.len()
+    }
+
+    /// The maximum number of voices that can be active at the same time.
+    pub fn polyphony(&self) -> usize {
+        self.polyphony
+    }
+
+    /// The number of voices that are currently allocated, including releasing voices.
+    pub fn active_voices(&self) -> usize {
+        self.voices.iter().filter(|voice| voice.is_some()).count()
+    }
+
+    /// The note playing in a voice slot, if the slot is in use.
+    pub fn voice(&self, index: usize) -> Option<&AllocatedVoice> {
+        self.voices.get(index)?.as_ref()
+    }
+
+    /// Change the polyphony limit. The limit is clamped to `1..=capacity`. If more voices are
+    /// active than the new limit allows, then the excess voices are stolen and `steal` is called
+    /// with each stolen voice's slot index and note. The plugin should immediately stop those
+    /// voices. This does nothing if the limit doesn't change, so it can be called for every block.
+    pub fn set_polyphony(
+        &mut self,
+        polyphony: usize,
+        mut steal: impl FnMut(usize, AllocatedVoice),
+    ) {
+        self.polyphony = polyphony.clamp(1, self.capacity());
+
+        while self.active_voices() > self.polyphony {
+            self.steal_voice(&mut steal);
+        }
+    }
+
+    /// Assign a new note to a voice slot. `voice_id` is the note event's voice ID. If the
+    /// polyphony limit has been reached, then a voice is stolen to make room for the note and
+    /// `steal` is called with that voice's slot index and note. When the allocator is monophonic
+    /// the note instead takes over the held voice with legato.
+    pub fn note_on(
+        &mut self,
+        voice_id: Option<i32>,
+        channel: u8,
+        note: u8,
+        mut steal: impl FnMut(usize, AllocatedVoice),
+    ) -> VoiceAssignment {
+        let new_voice = AllocatedVoice {
+            voice_id: voice_id.unwrap_or_else(|| compute_fallback_voice_id(note, channel)),
+            channel,
+            note,
+            releasing: false,
+            age: self.next_age,
+        };
+        self.next_age += 1;
+
+        if self.polyphony == 1 {
+            let held_voice = self
+                .voices
+                .iter_mut()
+                .enumerate()
+                .find(|(_, voice)| matches!(voice, Some(voice) if !voice.releasing));
+            if let Some((index, voice)) = held_voice {
+                let previous_voice = voice.replace(new_voice);
+
+                return VoiceAssignment {
+                    index,
+                    legato: previous_voice,
+                };
+            }
+        }
+
+        if self.active_voices() >= self.polyphony {
+            self.steal_voice(&mut steal);
+        }
+
+        let index = self
+            .voices
+            .iter()
+            .position(|voice| voice.is_none())
+            .expect("No free voice after stealing");
+        self.voices[index] = Some(new_voice);
+
+        VoiceAssignment {
+            index,
+            legato: None,
+        }
+    }
+
+    /// Mark a held note as released. The note is matched on its voice ID if the host provided one,
+    /// and on its channel and note number otherwise. Returns the voice's slot index, or `None` if
+    /// no held voice is playing this note. The voice stays allocated until
+    /// [`terminate()`][Self::terminate()] is called.
+    pub fn note_off(&mut self, voice_id: Option<i32>, channel: u8, note: u8) -> Option<usize> {
+        let (index, voice) = self
+            .voices
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, voice)| Some((index, voice.as_mut()?)))
+            .find(|(_, voice)| {
+                !voice.releasing
+                    && match voice_id {
+                        Some(voice_id) => voice.voice_id == voice_id,
+                        None => voice.channel == channel && voice.note == note,
+                    }
+            })?;
+        voice.releasing = true;
+
+        Some(index)
+    }
+
+    /// Mark all held notes on `channel` as released, or all held notes if `channel` is `None`. This
+    /// is meant for [`NoteEvent::AllNotesOff`] events. `release` is called with every released
+    /// voice's slot index and note so the plugin can start releasing the voice. The voices stay
+    /// allocated until [`terminate()`][Self::terminate()] is called.
+    ///
+    /// [`NoteEvent::AllNotesOff`]: crate::prelude::NoteEvent::AllNotesOff
+    pub fn release_all(
+        &mut self,
+        channel: Option<u8>,
+        mut release: impl FnMut(usize, AllocatedVoice),
+    ) {
+        for (index, voice) in self.voices.iter_mut().enumerate() {
+            if let Some(voice) = voice {
+                if !voice.releasing && (channel.is_none() || channel == Some(voice.channel)) {
+                    voice.releasing = true;
+                    release(index, *voice);
+                }
+            }
+        }
+    }
+
+    /// Free all voice slots on `channel`, or all voice slots if `channel` is `None`. This is meant
+    /// for [`NoteEvent::AllSoundOff`] events. `choke` is called with every freed voice's slot index
+    /// and note. The plugin should immediately stop those voices.
+    ///
+    /// [`NoteEvent::AllSoundOff`]: crate::prelude::NoteEvent::AllSoundOff
+    pub fn choke_all(&mut self, channel: Option<u8>, mut choke: impl FnMut(usize, AllocatedVoice)) {
+        for (index, voice) in self.voices.iter_mut().enumerate() {
+            if let Some(choked_voice) =
+                voice.take_if(|voice| channel.is_none() || channel == Some(voice.channel))
+            {
+                choke(index, choked_voice);
+            }
+        }
+    }
+
+    /// Free a voice slot. This should be called when a voice has finished playing, for instance
+    /// when its amplitude envelope has finished releasing. Returns the note the voice was playing.
+    pub fn terminate(&mut self, index: usize) -> Option<AllocatedVoice> {
+        self.voices.get_mut(index)?.take()
+    }
+
+    /// Free all voice slots without stealing them, for instance when the plugin gets reset.
+    pub fn reset(&mut self) {
+        self.voices.fill(None);
+        self.next_age = 0;
+    }
+
+    /// Steal a single voice. Releasing voices are stolen first, then the oldest held voice.
+    fn steal_voice(&mut self, steal: &mut impl FnMut(usize, AllocatedVoice)) {
+        let stolen_index = self
+            .voices
+            .iter()
+            .enumerate()
+            .filter_map(|(index, voice)| Some((index, voice.as_ref()?)))
+            .min_by_key(|(_, voice)| (!voice.releasing, voice.age))
+            .map(|(index, _)| index);
+
+        if let Some(index) = stolen_index {
+            let stolen_voice = self.voices[index].take().unwrap();
+            steal(index, stolen_voice);
+        }
+    }
+}
+
+/// Compute a voice ID for a note when the host did not provide one. This only depends on the
+/// note's channel and note number, so it is unique for every held note.
+const fn compute_fallback_voice_id(note: u8, channel: u8) -> i32 {
+    note as i32 | ((channel as i32) << 16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Start a note without voice ID and assert that no voices were stolen.
+    fn start_note(allocator: &mut VoiceAllocator, note: u8) -> usize {
+        let assignment = allocator.note_on(None, 0, note, |index, stolen| {
+            panic!("Unexpectedly stole voice {index}: {stolen:?}")
+        });
+        assert_eq!(assignment.legato, None);
+
+        assignment.index
+    }
+
+    /// Change the polyphony and return the notes that were playing in the stolen voices, in the
+    /// order they were stolen.
+    fn set_polyphony(allocator: &mut VoiceAllocator, polyphony: usize) -> Vec<u8> {
+        let mut stolen_notes = Vec::new();
+        allocator.set_polyphony(polyphony, |_, stolen| stolen_notes.push(stolen.note));
+
+        stolen_notes
+    }
+
+    #[test]
+    fn reduce_polyphony_mid_note() {
+        let mut allocator = VoiceAllocator::new(8);
+        let c = start_note(&mut allocator, 60);
+        let d = start_note(&mut allocator, 62);
+        let e = start_note(&mut allocator, 64);
+        let g = start_note(&mut allocator, 67);
+        assert_eq!(allocator.note_off(None, 0, 62), Some(d));
+        assert_eq!(allocator.active_voices(), 4);
+
+        // Releasing voices are stolen before held voices, and older voices before newer ones
+        assert_eq!(set_polyphony(&mut allocator, 2), [62, 60]);
+        assert_eq!(allocator.polyphony(), 2);
+        assert_eq!(allocator.active_voices(), 2);
+        assert_eq!(allocator.voice(c), None);
+        assert_eq!(allocator.voice(d), None);
+
+        // The remaining voices keep playing in their slots
+        assert_eq!(allocator.voice(e).map(|voice| voice.note), Some(64));
+        assert_eq!(allocator.voice(g).map(|voice| voice.note), Some(67));
+
+        // Raising the limit again doesn't change anything
+        assert_eq!(set_polyphony(&mut allocator, 8), []);
+        assert_eq!(allocator.active_voices(), 2);
+    }
+
+    #[test]
+    fn steal_when_limit_reached() {
+        let mut allocator = VoiceAllocator::new(8);
+        allocator.set_polyphony(2, |_, _| panic!("Nothing to steal"));
+        let c = start_note(&mut allocator, 60);
+        start_note(&mut allocator, 64);
+
+        let mut stolen_notes = Vec::new();
+        let assignment = allocator.note_on(Some(1234), 0, 67, |index, stolen| {
+            stolen_notes.push((index, stolen.note))
+        });
+        assert_eq!(stolen_notes, [(c, 60)]);
+        assert_eq!(assignment.index, c);
+        assert_eq!(allocator.voice(c).map(|voice| voice.voice_id), Some(1234));
+
+        // Note offs are matched on the voice ID if there is one
+        assert_eq!(allocator.note_off(Some(4321), 0, 67), None);
+        assert_eq!(allocator.note_off(Some(1234), 0, 67), Some(c));
+        assert_eq!(allocator.terminate(c).map(|voice| voice.note), Some(67));
+        assert_eq!(allocator.active_voices(), 1);
+    }
+
+    #[test]
+    fn monophonic_legato() {
+        let mut allocator = VoiceAllocator::new(8);
+        start_note(&mut allocator, 60);
+        let e = start_note(&mut allocator, 64);
+        assert_eq!(set_polyphony(&mut allocator, 0), [60]);
+        assert_eq!(allocator.polyphony(), 1);
+
+        // A new note takes over the held voice
+        let assignment = allocator.note_on(None, 0, 67, |_, _| panic!("Should be legato"));
+        assert_eq!(assignment.index, e);
+        assert_eq!(assignment.legato.map(|voice| voice.note), Some(64));
+        assert_eq!(allocator.voice(e).map(|voice| voice.note), Some(67));
+        assert_eq!(allocator.active_voices(), 1);
+
+        // Once the note is released, the next note steals the releasing voice
+        assert_eq!(allocator.note_off(None, 0, 67), Some(assignment.index));
+        let mut stolen_notes = Vec::new();
+        let assignment = allocator.note_on(None, 0, 72, |_, stolen| stolen_notes.push(stolen.note));
+        assert_eq!(stolen_notes, [67]);
+        assert_eq!(assignment.legato, None);
+        assert_eq!(allocator.active_voices(), 1);
+    }
+
+    #[test]
+    fn release_and_choke_all() {
+        let mut allocator = VoiceAllocator::new(8);
+        let c = start_note(&mut allocator, 60);
+        let e = allocator.note_on(None, 1, 64, |_, _| panic!()).index;
+        let g = start_note(&mut allocator, 67);
+        assert_eq!(allocator.note_off(None, 0, 67), Some(g));
+
+        // Only held notes on the channel are released
+        let mut released_notes = Vec::new();
+        allocator.release_all(Some(0), |index, voice| {
+            released_notes.push((index, voice.note))
+        });
+        assert_eq!(released_notes, [(c, 60)]);
+        assert_eq!(allocator.voice(c).map(|voice| voice.releasing), Some(true));
+        assert_eq!(allocator.voice(e).map(|voice| voice.releasing), Some(false));
+        assert_eq!(allocator.active_voices(), 3);
+
+        let mut choked_notes = Vec::new();
+        allocator.choke_all(Some(1), |index, voice| {
+            choked_notes.push((index, voice.note))
+        });
+        assert_eq!(choked_notes, [(e, 64)]);
+        assert_eq!(allocator.active_voices(), 2);
+
+        // Without a channel, all voices are affected, including releasing ones
+        choked_notes.clear();
+        allocator.choke_all(None, |index, voice| choked_notes.push((index, voice.note)));
+        assert_eq!(choked_notes, [(c, 60), (g, 67)]);
+        assert_eq!(allocator.active_voices(), 0);
+    }
+}
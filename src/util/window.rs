@@ -25,6 +25,32 @@ pub fn blackman_in_place(window: &mut [f32]) {
     }
 }
 
+/// A four term Blackman-Harris window function. This has a much lower side lobe level than the
+/// Blackman and Hann windows.
+///
+/// <https://en.wikipedia.org/wiki/Window_function#Blackman%E2%80%93Harris_window>
+pub fn blackman_harris(size: usize) -> Vec<f32> {
+    let mut window = vec![0.0; size];
+    blackman_harris_in_place(&mut window);
+
+    window
+}
+
+/// The same as [`blackman_harris()`], but filling an existing slice instead.
+pub fn blackman_harris_in_place(window: &mut [f32]) {
+    let size = window.len();
+
+    let scale_1 = (2.0 * f32::consts::PI) / (size - 1) as f32;
+    let scale_2 = scale_1 * 2.0;
+    let scale_3 = scale_1 * 3.0;
+    for (i, sample) in window.iter_mut().enumerate() {
+        let cos_1 = (scale_1 * i as f32).cos();
+        let cos_2 = (scale_2 * i as f32).cos();
+        let cos_3 = (scale_3 * i as f32).cos();
+        *sample = 0.35875 - (0.48829 * cos_1) + (0.14128 * cos_2) - (0.01168 * cos_3);
+    }
+}
+
 /// A Hann window function.
 ///
 /// <https://en.wikipedia.org/wiki/Hann_function>
@@ -1,19 +1,22 @@
 use atomic_refcell::AtomicRefMut;
-use clap_sys::ext::remote_controls::{
-    clap_remote_controls_page, CLAP_REMOTE_CONTROLS_COUNT,
-};
+use clap_sys::ext::remote_controls::{clap_remote_controls_page, CLAP_REMOTE_CONTROLS_COUNT};
 use clap_sys::id::{clap_id, CLAP_INVALID_ID};
 use clap_sys::string_sizes::CLAP_NAME_SIZE;
 use std::cell::Cell;
 use std::collections::{HashMap, VecDeque};
+use std::ffi::CString;
+use std::mem;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use super::wrapper::{OutputParamEvent, Task, Wrapper};
 use crate::event_loop::EventLoop;
 use crate::prelude::{
-    ClapPlugin, GuiContext, InitContext, ParamPtr, PluginApi, PluginNoteEvent, ProcessContext,
+    ClapPlugin, ContextMenu, ContextMenuContext, GuiContext, HostInfo, InitContext, ParamFlags,
+    ParamIndication, ParamPtr, Params, PluginApi, PluginNoteEvent, ProcessContext, ProcessMode,
     RemoteControlsContext, RemoteControlsPage, RemoteControlsSection, Transport,
 };
+use crate::util::ProcessLoad;
 use crate::wrapper::util::strlcpy;
 
 /// An [`InitContext`] implementation for the wrapper.
@@ -33,6 +36,8 @@ pub(crate) struct WrapperInitContext<'a, P: ClapPlugin> {
 pub(crate) struct PendingInitContextRequests {
     /// The value of the last `.set_latency_samples()` call.
     latency_changed: Cell<Option<u32>>,
+    /// The value of the last `.set_tail_samples()` call.
+    tail_changed: Cell<Option<u32>>,
 }
 
 /// A [`ProcessContext`] implementation for the wrapper. This is a separate object so it can hold on
@@ -65,11 +70,69 @@ pub(crate) struct RemoteControlPages<'a> {
     pages: &'a mut Vec<clap_remote_controls_page>,
 }
 
+/// A [`ContextMenuContext`] implementation for the wrapper. This is used when creating the plugin to
+/// allow the plugin to add entries to the host's context menus.
+pub(crate) struct ContextMenus<'a> {
+    param_ptr_to_hash: &'a HashMap<ParamPtr, u32>,
+    menus: &'a mut ContextMenuEntries,
+}
+
+/// The context menu entries defined by the plugin, in a format that can be passed to a
+/// `clap_context_menu_builder` directly.
+#[derive(Default)]
+pub(crate) struct ContextMenuEntries {
+    /// Entries for the plugin's global context menu.
+    pub global: Vec<ContextMenuItem>,
+    /// Entries for the parameters' context menus, indexed by parameter hash.
+    pub by_param_hash: HashMap<u32, Vec<ContextMenuItem>>,
+    /// The entries' actions. The action IDs passed to the host are indices into this vector.
+    pub actions: Vec<Box<dyn Fn() + Send + Sync>>,
+}
+
+/// A context menu item as it is passed to the host.
+pub(crate) enum ContextMenuItem {
+    Entry {
+        label: CString,
+        /// An index into [`ContextMenuEntries::actions`], or `None` if the entry is disabled.
+        action_id: Option<clap_id>,
+    },
+    Title(CString),
+    Separator,
+    Submenu {
+        label: CString,
+        items: Vec<ContextMenuItem>,
+    },
+}
+
+/// A (sub)menu the plugin can add entries to. These are converted to [`ContextMenuItem`]s after
+/// the plugin has added its entries.
+#[derive(Default)]
+pub(crate) struct Menu {
+    items: Vec<MenuItem>,
+}
+
+/// An item in a [`Menu`]. Unlike [`ContextMenuItem`], entries store their actions inline.
+enum MenuItem {
+    Entry {
+        label: String,
+        action: Option<Box<dyn Fn() + Send + Sync>>,
+    },
+    Title(String),
+    Separator,
+    Submenu {
+        label: String,
+        menu: Menu,
+    },
+}
+
 impl<P: ClapPlugin> Drop for WrapperInitContext<'_, P> {
     fn drop(&mut self) {
         if let Some(samples) = self.pending_requests.latency_changed.take() {
             self.wrapper.set_latency_samples(samples)
         }
+        if let Some(samples) = self.pending_requests.tail_changed.take() {
+            self.wrapper.set_tail_samples(samples)
+        }
     }
 }
 
@@ -78,6 +141,10 @@ impl<P: ClapPlugin> InitContext<P> for WrapperInitContext<'_, P> {
         PluginApi::Clap
     }
 
+    fn host_info(&self) -> Option<HostInfo> {
+        self.wrapper.host_info.clone()
+    }
+
     fn execute(&self, task: P::BackgroundTask) {
         (self.wrapper.task_executor.lock())(task);
     }
@@ -87,6 +154,11 @@ impl<P: ClapPlugin> InitContext<P> for WrapperInitContext<'_, P> {
         self.pending_requests.latency_changed.set(Some(samples));
     }
 
+    fn set_tail_samples(&self, samples: u32) {
+        // See this struct's docstring
+        self.pending_requests.tail_changed.set(Some(samples));
+    }
+
     fn set_current_voice_capacity(&self, capacity: u32) {
         self.wrapper.set_current_voice_capacity(capacity)
     }
@@ -112,6 +184,14 @@ impl<P: ClapPlugin> ProcessContext<P> for WrapperProcessContext<'_, P> {
         &self.transport
     }
 
+    fn process_mode(&self) -> ProcessMode {
+        self.wrapper.current_process_mode.load()
+    }
+
+    fn is_editor_open(&self) -> bool {
+        self.wrapper.editor_open.load(Ordering::SeqCst)
+    }
+
     fn next_event(&mut self) -> Option<PluginNoteEvent<P>> {
         self.input_events_guard.pop_front()
     }
@@ -124,9 +204,21 @@ impl<P: ClapPlugin> ProcessContext<P> for WrapperProcessContext<'_, P> {
         self.wrapper.set_latency_samples(samples)
     }
 
+    fn set_tail_samples(&self, samples: u32) {
+        self.wrapper.set_tail_samples(samples)
+    }
+
+    fn set_gain_reduction(&self, gain_reduction_db: f32) {
+        self.wrapper.gain_reduction_meter.report(gain_reduction_db)
+    }
+
     fn set_current_voice_capacity(&self, capacity: u32) {
         self.wrapper.set_current_voice_capacity(capacity)
     }
+
+    fn execute_parallel(&self, num_tasks: u32, task: &(dyn Fn(u32) + Sync)) {
+        self.wrapper.execute_parallel(num_tasks, task)
+    }
 }
 
 impl<P: ClapPlugin> GuiContext for WrapperGuiContext<P> {
@@ -142,7 +234,9 @@ impl<P: ClapPlugin> GuiContext for WrapperGuiContext<P> {
     // trust in the caller and assume that this is indeed the case
     unsafe fn raw_begin_set_parameter(&self, param: ParamPtr) {
         match self.wrapper.param_ptr_to_hash.get(&param) {
-            Some(hash) => {
+            // Duplicate begins can happen when a previous drag was interrupted. In that case the
+            // existing gesture is continued.
+            Some(hash) if self.wrapper.gesture_watchdog.begin(*hash) => {
                 let success = self
                     .wrapper
                     .queue_parameter_event(OutputParamEvent::BeginGesture { param_hash: *hash });
@@ -153,6 +247,7 @@ impl<P: ClapPlugin> GuiContext for WrapperGuiContext<P> {
                      the host"
                 );
             }
+            Some(_) => (),
             None => nih_debug_assert_failure!("Unknown parameter: {:?}", param),
         }
 
@@ -175,13 +270,32 @@ impl<P: ClapPlugin> GuiContext for WrapperGuiContext<P> {
                 // at the end of the current processing cycle or after requesting an explicit flush
                 // (when the plugin isn't processing audio). The parameter's actual value will only
                 // be changed when the output event is written to prevent changing parameter values
-                // in the middle of processing audio.
+                // in the middle of processing audio. This value is not rounded to whole steps so
+                // `FloatParam`s with a step count stay continuous. The value reported to the host
+                // is rounded when the event is written.
                 let clap_plain_value = normalized as f64 * param.step_count().unwrap_or(1) as f64;
+
+                // If the gesture was ended automatically because it didn't receive any changes for
+                // a while, then it needs to be restarted first
+                if self.wrapper.gesture_watchdog.set(*hash) {
+                    let success =
+                        self.wrapper
+                            .queue_parameter_event(OutputParamEvent::BeginGesture {
+                                param_hash: *hash,
+                            });
+                    nih_debug_assert!(
+                        success,
+                        "Parameter output event queue was full, parameter change will not be sent \
+                         to the host"
+                    );
+                }
+
                 let success = self
                     .wrapper
                     .queue_parameter_event(OutputParamEvent::SetValue {
                         param_hash: *hash,
                         clap_plain_value,
+                        reset_smoother: false,
                     });
 
                 nih_debug_assert!(
@@ -207,7 +321,8 @@ impl<P: ClapPlugin> GuiContext for WrapperGuiContext<P> {
 
     unsafe fn raw_end_set_parameter(&self, param: ParamPtr) {
         match self.wrapper.param_ptr_to_hash.get(&param) {
-            Some(hash) => {
+            // The gesture may have already been ended automatically by the wrapper
+            Some(hash) if self.wrapper.gesture_watchdog.end(*hash) => {
                 let success = self
                     .wrapper
                     .queue_parameter_event(OutputParamEvent::EndGesture { param_hash: *hash });
@@ -218,6 +333,7 @@ impl<P: ClapPlugin> GuiContext for WrapperGuiContext<P> {
                      the host"
                 );
             }
+            Some(_) => (),
             None => nih_debug_assert_failure!("Unknown parameter: {:?}", param),
         }
 
@@ -233,6 +349,42 @@ impl<P: ClapPlugin> GuiContext for WrapperGuiContext<P> {
         }
     }
 
+    unsafe fn raw_set_parameters_normalized(
+        &self,
+        values: &[(ParamPtr, f32)],
+        reset_smoothers: bool,
+    ) {
+        let mut events = Vec::with_capacity(values.len() * 3);
+        for &(param, normalized) in values {
+            match self.wrapper.param_ptr_to_hash.get(&param) {
+                Some(hash) => {
+                    // Every parameter gets its own gesture, but all of the events are sent to the
+                    // host in a single flush
+                    let gesture_started = self.wrapper.gesture_watchdog.begin(*hash);
+                    if gesture_started {
+                        events.push(OutputParamEvent::BeginGesture { param_hash: *hash });
+                    }
+                    events.push(OutputParamEvent::SetValue {
+                        param_hash: *hash,
+                        clap_plain_value: normalized as f64
+                            * param.step_count().unwrap_or(1) as f64,
+                        reset_smoother: reset_smoothers,
+                    });
+                    if gesture_started && self.wrapper.gesture_watchdog.end(*hash) {
+                        events.push(OutputParamEvent::EndGesture { param_hash: *hash });
+                    }
+                }
+                None => nih_debug_assert_failure!("Unknown parameter: {:?}", param),
+            }
+        }
+
+        let success = self.wrapper.queue_parameter_events(events);
+        nih_debug_assert!(
+            success,
+            "Parameter output event queue was full, parameter changes will not be sent to the host"
+        );
+    }
+
     fn get_state(&self) -> crate::wrapper::state::PluginState {
         self.wrapper.get_state_object()
     }
@@ -240,6 +392,19 @@ impl<P: ClapPlugin> GuiContext for WrapperGuiContext<P> {
     fn set_state(&self, state: crate::wrapper::state::PluginState) {
         self.wrapper.set_state_object_from_gui(state)
     }
+
+    fn process_load(&self) -> Option<Arc<ProcessLoad>> {
+        ProcessLoad::ENABLED.then(|| self.wrapper.process_load.clone())
+    }
+
+    fn param_indication(&self, param: ParamPtr) -> ParamIndication {
+        self.wrapper
+            .param_ptr_to_hash
+            .get(&param)
+            .and_then(|hash| self.wrapper.param_indications.get(hash))
+            .map(|indication| indication.load())
+            .unwrap_or_default()
+    }
 }
 
 /// A remote control section. The plugin can fill this with information for one or more pages.
@@ -256,17 +421,66 @@ pub(crate) struct Page {
 
 impl<'a> RemoteControlPages<'a> {
     /// Allow the plugin to define remote control pages and add them to `pages`. This does not clear
-    /// `pages` first.
+    /// `pages` first. If the plugin does not define any pages, then pages are created automatically
+    /// for the plugin's parameters in `params`.
     pub fn define_remote_control_pages<P: ClapPlugin>(
         plugin: &P,
         pages: &'a mut Vec<clap_remote_controls_page>,
         param_ptr_to_hash: &'a HashMap<ParamPtr, u32>,
+        params: &dyn Params,
     ) {
-        // The magic happens in the `add_section()` function defined below
-        plugin.remote_controls(&mut Self {
+        let mut context = Self {
             pages,
             param_ptr_to_hash,
-        });
+        };
+
+        // The magic happens in the `add_section()` function defined below
+        let num_pages_before = context.pages.len();
+        plugin.remote_controls(&mut context);
+        if context.pages.len() == num_pages_before {
+            context.define_default_pages(P::NAME, params);
+        }
+    }
+
+    /// Automatically create remote control pages for all of the plugin's automatable parameters in
+    /// declaration order. Each run of parameters belonging to the same parameter group gets its own
+    /// section, and the pages within those sections are split every eight parameters. Parameters
+    /// that are not part of any group are added to a section named after the plugin.
+    fn define_default_pages(&mut self, plugin_name: &str, params: &dyn Params) {
+        let mut current_group: Option<String> = None;
+        let mut current_params: Vec<Option<ParamPtr>> = Vec::new();
+        let add_group_section = |this: &mut Self, group: &str, params: Vec<Option<ParamPtr>>| {
+            if params.is_empty() {
+                return;
+            }
+
+            let name = if group.is_empty() { plugin_name } else { group };
+            this.add_section(name, |section| {
+                section.pages.push(Page {
+                    name: name.to_owned(),
+                    params,
+                })
+            });
+        };
+
+        for (_, param_ptr, group) in params.param_map() {
+            if !is_remote_controllable(param_ptr) {
+                continue;
+            }
+
+            if current_group.as_ref() != Some(&group) {
+                if let Some(current_group) = &current_group {
+                    add_group_section(self, current_group, mem::take(&mut current_params));
+                }
+                current_group = Some(group);
+            }
+
+            current_params.push(Some(param_ptr));
+        }
+
+        if let Some(current_group) = &current_group {
+            add_group_section(self, current_group, current_params);
+        }
     }
 
     /// Perform the boilerplate needed for creating and adding a new [`clap_remote_controls_page`].
@@ -372,7 +586,153 @@ impl RemoteControlsPage for Page {
         self.params.push(Some(param.as_ptr()));
     }
 
+    fn add_params(&mut self, params: &impl Params) {
+        self.params.extend(
+            params
+                .param_map()
+                .into_iter()
+                .map(|(_, param_ptr, _)| param_ptr)
+                .filter(|param_ptr| is_remote_controllable(*param_ptr))
+                .map(Some),
+        );
+    }
+
     fn add_spacer(&mut self) {
         self.params.push(None);
     }
 }
+
+impl<'a> ContextMenus<'a> {
+    /// Allow the plugin to add entries to the host's context menus and store them in `menus`. This
+    /// does not clear `menus` first.
+    pub fn define_context_menus<P: ClapPlugin>(
+        plugin: &P,
+        menus: &'a mut ContextMenuEntries,
+        param_ptr_to_hash: &'a HashMap<ParamPtr, u32>,
+    ) {
+        plugin.context_menu(&mut Self {
+            param_ptr_to_hash,
+            menus,
+        });
+    }
+
+    /// Convert the items from a [`Menu`] to [`ContextMenuItem`]s while moving the actions to
+    /// [`ContextMenuEntries::actions`]. Empty submenus are dropped.
+    fn convert_items(&mut self, menu: Menu) -> Vec<ContextMenuItem> {
+        let mut items = Vec::with_capacity(menu.items.len());
+        for item in menu.items {
+            match item {
+                MenuItem::Entry { label, action } => {
+                    let action_id = action.map(|action| {
+                        self.menus.actions.push(action);
+                        (self.menus.actions.len() - 1) as clap_id
+                    });
+
+                    items.push(ContextMenuItem::Entry {
+                        label: to_cstring(label),
+                        action_id,
+                    });
+                }
+                MenuItem::Title(title) => items.push(ContextMenuItem::Title(to_cstring(title))),
+                MenuItem::Separator => items.push(ContextMenuItem::Separator),
+                MenuItem::Submenu { label, menu } => {
+                    let submenu_items = self.convert_items(menu);
+                    if !submenu_items.is_empty() {
+                        items.push(ContextMenuItem::Submenu {
+                            label: to_cstring(label),
+                            items: submenu_items,
+                        });
+                    }
+                }
+            }
+        }
+
+        items
+    }
+}
+
+impl ContextMenuContext for ContextMenus<'_> {
+    type Menu = Menu;
+
+    fn add_param_entries(&mut self, param: &impl crate::prelude::Param, f: impl FnOnce(&mut Menu)) {
+        let param_hash = match self.param_ptr_to_hash.get(&param.as_ptr()) {
+            Some(hash) => *hash,
+            None => {
+                nih_debug_assert_failure!(
+                    "Context menu entries were added for an unknown parameter, ignoring..."
+                );
+                return;
+            }
+        };
+
+        let mut menu = Menu::default();
+        f(&mut menu);
+
+        let items = self.convert_items(menu);
+        self.menus
+            .by_param_hash
+            .entry(param_hash)
+            .or_default()
+            .extend(items);
+    }
+
+    fn add_global_entries(&mut self, f: impl FnOnce(&mut Menu)) {
+        let mut menu = Menu::default();
+        f(&mut menu);
+
+        let items = self.convert_items(menu);
+        self.menus.global.extend(items);
+    }
+}
+
+impl ContextMenu for Menu {
+    fn add_entry(&mut self, label: impl Into<String>, action: impl Fn() + Send + Sync + 'static) {
+        self.items.push(MenuItem::Entry {
+            label: label.into(),
+            action: Some(Box::new(action)),
+        });
+    }
+
+    fn add_disabled_entry(&mut self, label: impl Into<String>) {
+        self.items.push(MenuItem::Entry {
+            label: label.into(),
+            action: None,
+        });
+    }
+
+    fn add_title(&mut self, title: impl Into<String>) {
+        self.items.push(MenuItem::Title(title.into()));
+    }
+
+    fn add_separator(&mut self) {
+        self.items.push(MenuItem::Separator);
+    }
+
+    fn add_submenu(&mut self, label: impl Into<String>, f: impl FnOnce(&mut Self)) {
+        let mut menu = Menu::default();
+        f(&mut menu);
+
+        self.items.push(MenuItem::Submenu {
+            label: label.into(),
+            menu,
+        });
+    }
+}
+
+/// Whether a parameter should be included in automatically generated remote control pages. Hidden,
+/// non-automatable, bypass, and gain reduction parameters are skipped.
+fn is_remote_controllable(param_ptr: ParamPtr) -> bool {
+    let flags = unsafe { param_ptr.flags() };
+
+    !flags.intersects(
+        ParamFlags::HIDDEN
+            | ParamFlags::NON_AUTOMATABLE
+            | ParamFlags::BYPASS
+            | ParamFlags::GAIN_REDUCTION,
+    )
+}
+
+/// Convert a label to a [`CString`], stripping any null bytes.
+fn to_cstring(label: String) -> CString {
+    CString::new(label.replace('\0', "")).unwrap()
+}
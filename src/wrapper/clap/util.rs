@@ -1,7 +1,34 @@
+use clap_sys::color::clap_color;
+use clap_sys::ext::audio_ports::{CLAP_PORT_MONO, CLAP_PORT_STEREO};
+use clap_sys::ext::log::{
+    clap_host_log, CLAP_LOG_DEBUG, CLAP_LOG_ERROR, CLAP_LOG_INFO, CLAP_LOG_WARNING,
+};
+use clap_sys::ext::note_ports::{CLAP_NOTE_DIALECT_CLAP, CLAP_NOTE_DIALECT_MIDI};
+use clap_sys::ext::params::{
+    clap_param_info_flags, CLAP_PARAM_IS_AUTOMATABLE, CLAP_PARAM_IS_BYPASS, CLAP_PARAM_IS_HIDDEN,
+    CLAP_PARAM_IS_MODULATABLE, CLAP_PARAM_IS_MODULATABLE_PER_NOTE_ID, CLAP_PARAM_IS_READONLY,
+    CLAP_PARAM_IS_STEPPED,
+};
+use clap_sys::ext::surround::{
+    CLAP_PORT_SURROUND, CLAP_SURROUND_BL, CLAP_SURROUND_BR, CLAP_SURROUND_FC, CLAP_SURROUND_FL,
+    CLAP_SURROUND_FR, CLAP_SURROUND_LFE, CLAP_SURROUND_SL, CLAP_SURROUND_SR, CLAP_SURROUND_TBL,
+    CLAP_SURROUND_TBR, CLAP_SURROUND_TFL, CLAP_SURROUND_TFR,
+};
+use clap_sys::host::clap_host;
+use clap_sys::process::{
+    clap_process_status, CLAP_PROCESS_CONTINUE, CLAP_PROCESS_CONTINUE_IF_NOT_QUIET,
+    CLAP_PROCESS_ERROR, CLAP_PROCESS_SLEEP, CLAP_PROCESS_TAIL,
+};
 use clap_sys::stream::{clap_istream, clap_ostream};
+use std::ffi::{CStr, CString};
 use std::mem::MaybeUninit;
 use std::ops::Deref;
-use std::os::raw::c_void;
+use std::os::raw::{c_char, c_void};
+
+use crate::prelude::{
+    ChannelLayout, ClapNoteDialects, HostInfo, IndicationColor, ParamFlags, ProcessStatus, Speaker,
+};
+use crate::wrapper::util::logger::HostLogger;
 
 /// Early exit out of a function with the specified return value when one of the passed pointers is
 /// null.
@@ -84,6 +111,43 @@ impl<T> ClapPtr<T> {
     }
 }
 
+/// Writes the plugin's log messages to the host using the `clap.log` extension.
+pub struct ClapHostLogger {
+    host: ClapPtr<clap_host>,
+    host_log: ClapPtr<clap_host_log>,
+}
+
+impl ClapHostLogger {
+    /// # Safety
+    ///
+    /// Both pointers must remain valid for as long as this object exists.
+    pub unsafe fn new(host: *const clap_host, host_log: ClapPtr<clap_host_log>) -> Self {
+        Self {
+            host: ClapPtr::new(host),
+            host_log,
+        }
+    }
+}
+
+impl HostLogger for ClapHostLogger {
+    fn log(&self, level: log::Level, message: &str) {
+        let severity = match level {
+            log::Level::Error => CLAP_LOG_ERROR,
+            log::Level::Warn => CLAP_LOG_WARNING,
+            log::Level::Info => CLAP_LOG_INFO,
+            // CLAP does not have a separate trace level
+            log::Level::Debug | log::Level::Trace => CLAP_LOG_DEBUG,
+        };
+        let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+
+        // This can't use `clap_call!()` since panicking here would try to log the panic through
+        // this same logger
+        if let Some(log) = self.host_log.log {
+            unsafe { log(&*self.host, severity, message.as_ptr()) };
+        }
+    }
+}
+
 /// A buffer a stream can be read into. This is needed to allow reading into uninitialized vectors
 /// using slices without invoking UB.
 ///
@@ -164,3 +228,340 @@ pub fn write_stream(stream: &clap_ostream, slice: &[u8]) -> bool {
 
     true
 }
+
+/// Translate the plugin's [`ProcessStatus`] to the status returned from CLAP's process function.
+/// For [`ProcessStatus::Tail`] the host queries the actual tail length through the tail extension.
+pub fn to_clap_process_status(status: ProcessStatus) -> clap_process_status {
+    match status {
+        ProcessStatus::Error(_) => CLAP_PROCESS_ERROR,
+        ProcessStatus::Normal => CLAP_PROCESS_CONTINUE_IF_NOT_QUIET,
+        ProcessStatus::Tail(0) => CLAP_PROCESS_SLEEP,
+        ProcessStatus::Tail(_) => CLAP_PROCESS_TAIL,
+        ProcessStatus::KeepAlive => CLAP_PROCESS_CONTINUE,
+    }
+}
+
+/// Translate the plugin's [`ClapNoteDialects`] to the `supported_dialects` and `preferred_dialect`
+/// fields of a `clap_note_port_info`.
+pub fn to_clap_note_dialects(dialects: ClapNoteDialects) -> (u32, u32) {
+    match dialects {
+        ClapNoteDialects::ClapPreferred => (
+            CLAP_NOTE_DIALECT_CLAP | CLAP_NOTE_DIALECT_MIDI,
+            CLAP_NOTE_DIALECT_CLAP,
+        ),
+        ClapNoteDialects::MidiPreferred => (
+            CLAP_NOTE_DIALECT_CLAP | CLAP_NOTE_DIALECT_MIDI,
+            CLAP_NOTE_DIALECT_MIDI,
+        ),
+        ClapNoteDialects::ClapOnly => (CLAP_NOTE_DIALECT_CLAP, CLAP_NOTE_DIALECT_CLAP),
+        ClapNoteDialects::MidiOnly => (CLAP_NOTE_DIALECT_MIDI, CLAP_NOTE_DIALECT_MIDI),
+    }
+}
+
+/// The port type for an audio port with the given channel layout. Ports without a known layout
+/// don't have a port type.
+pub fn to_clap_port_type(channel_layout: Option<ChannelLayout>) -> *const c_char {
+    match channel_layout {
+        Some(ChannelLayout::Mono) => CLAP_PORT_MONO.as_ptr(),
+        Some(ChannelLayout::Stereo) => CLAP_PORT_STEREO.as_ptr(),
+        Some(_) => CLAP_PORT_SURROUND.as_ptr(),
+        None => std::ptr::null(),
+    }
+}
+
+/// The position of a [`Speaker`] in the surround extension's channel maps.
+pub fn to_clap_surround_position(speaker: Speaker) -> u8 {
+    (match speaker {
+        Speaker::FrontLeft => CLAP_SURROUND_FL,
+        Speaker::FrontRight => CLAP_SURROUND_FR,
+        Speaker::FrontCenter => CLAP_SURROUND_FC,
+        Speaker::Lfe => CLAP_SURROUND_LFE,
+        Speaker::BackLeft => CLAP_SURROUND_BL,
+        Speaker::BackRight => CLAP_SURROUND_BR,
+        Speaker::SideLeft => CLAP_SURROUND_SL,
+        Speaker::SideRight => CLAP_SURROUND_SR,
+        Speaker::TopFrontLeft => CLAP_SURROUND_TFL,
+        Speaker::TopFrontRight => CLAP_SURROUND_TFR,
+        Speaker::TopBackLeft => CLAP_SURROUND_TBL,
+        Speaker::TopBackRight => CLAP_SURROUND_TBR,
+    }) as u8
+}
+
+/// The surround extension's channel mask for a channel layout. This has a bit set for every
+/// speaker position in the layout.
+pub fn to_clap_surround_channel_mask(channel_layout: ChannelLayout) -> u64 {
+    channel_layout.speakers().iter().fold(0, |mask, &speaker| {
+        mask | (1 << to_clap_surround_position(speaker))
+    })
+}
+
+/// Translate a parameter's [`ParamFlags`] to the flags in its `clap_param_info`. `stepped` should
+/// be set for parameters with a step count, and `poly_modulatable` for parameters with a
+/// polyphonic modulation ID. Non-automatable, hidden, and gain reduction parameters are not
+/// modulatable either.
+pub fn to_clap_param_info_flags(
+    flags: ParamFlags,
+    stepped: bool,
+    poly_modulatable: bool,
+) -> clap_param_info_flags {
+    let automatable = !flags.contains(ParamFlags::NON_AUTOMATABLE);
+    let hidden = flags.contains(ParamFlags::HIDDEN);
+    let is_bypass = flags.contains(ParamFlags::BYPASS);
+    // Gain reduction parameters are output parameters that can only be changed by the plugin
+    let is_gain_reduction = flags.contains(ParamFlags::GAIN_REDUCTION);
+
+    // TODO: Somehow expose per note/channel/port modulation
+    let mut clap_flags = 0;
+    if automatable && !hidden && !is_gain_reduction {
+        clap_flags |= CLAP_PARAM_IS_AUTOMATABLE | CLAP_PARAM_IS_MODULATABLE;
+        if poly_modulatable {
+            clap_flags |= CLAP_PARAM_IS_MODULATABLE_PER_NOTE_ID;
+        }
+    }
+    if hidden {
+        clap_flags |= CLAP_PARAM_IS_HIDDEN | CLAP_PARAM_IS_READONLY;
+    }
+    if is_gain_reduction {
+        clap_flags |= CLAP_PARAM_IS_READONLY;
+    }
+    if is_bypass {
+        clap_flags |= CLAP_PARAM_IS_BYPASS
+    }
+    if stepped {
+        clap_flags |= CLAP_PARAM_IS_STEPPED
+    }
+
+    clap_flags
+}
+
+/// Convert a color passed to one of the `param-indication` extension's functions. The host may pass
+/// a null pointer if it doesn't want to specify a color, in which case this returns `None`.
+///
+/// # Safety
+///
+/// `color` needs to be either a null pointer or a valid pointer to a `clap_color`.
+pub unsafe fn clap_color_to_indication_color(color: *const clap_color) -> Option<IndicationColor> {
+    let color = unsafe { color.as_ref() }?;
+
+    Some(IndicationColor {
+        red: color.red,
+        green: color.green,
+        blue: color.blue,
+        alpha: color.alpha,
+    })
+}
+
+/// Read the host's name and version from the `clap_host` struct. Returns `None` if the host did
+/// not provide a name. Empty version strings are treated as missing.
+///
+/// # Safety
+///
+/// The `name` and `version` fields need to be either null pointers or valid null-terminated
+/// strings.
+pub unsafe fn host_info_from_clap_host(host: &clap_host) -> Option<HostInfo> {
+    unsafe fn read_string(ptr: *const c_char) -> Option<String> {
+        if ptr.is_null() {
+            return None;
+        }
+
+        let string = unsafe { CStr::from_ptr(ptr) }.to_string_lossy();
+        if string.is_empty() {
+            None
+        } else {
+            Some(string.into_owned())
+        }
+    }
+
+    Some(HostInfo {
+        name: unsafe { read_string(host.name) }?,
+        version: unsafe { read_string(host.version) },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use clap_sys::version::CLAP_VERSION;
+
+    use super::*;
+    use crate::prelude::{FloatParam, FloatRange, IntParam, IntRange, Param};
+
+    fn simulated_host(name: &CStr, version: &CStr) -> clap_host {
+        clap_host {
+            clap_version: CLAP_VERSION,
+            host_data: std::ptr::null_mut(),
+            name: name.as_ptr(),
+            vendor: std::ptr::null(),
+            url: std::ptr::null(),
+            version: version.as_ptr(),
+            get_extension: None,
+            request_restart: None,
+            request_process: None,
+            request_callback: None,
+        }
+    }
+
+    #[test]
+    fn indication_color() {
+        assert_eq!(
+            unsafe { clap_color_to_indication_color(std::ptr::null()) },
+            None
+        );
+
+        let color = clap_color {
+            alpha: 255,
+            red: 16,
+            green: 32,
+            blue: 64,
+        };
+        assert_eq!(
+            unsafe { clap_color_to_indication_color(&color) },
+            Some(IndicationColor {
+                red: 16,
+                green: 32,
+                blue: 64,
+                alpha: 255,
+            })
+        );
+    }
+
+    #[test]
+    fn note_dialects() {
+        assert_eq!(
+            to_clap_note_dialects(ClapNoteDialects::ClapPreferred),
+            (
+                CLAP_NOTE_DIALECT_CLAP | CLAP_NOTE_DIALECT_MIDI,
+                CLAP_NOTE_DIALECT_CLAP
+            )
+        );
+        assert_eq!(
+            to_clap_note_dialects(ClapNoteDialects::MidiPreferred),
+            (
+                CLAP_NOTE_DIALECT_CLAP | CLAP_NOTE_DIALECT_MIDI,
+                CLAP_NOTE_DIALECT_MIDI
+            )
+        );
+        assert_eq!(
+            to_clap_note_dialects(ClapNoteDialects::ClapOnly),
+            (CLAP_NOTE_DIALECT_CLAP, CLAP_NOTE_DIALECT_CLAP)
+        );
+        assert_eq!(
+            to_clap_note_dialects(ClapNoteDialects::MidiOnly),
+            (CLAP_NOTE_DIALECT_MIDI, CLAP_NOTE_DIALECT_MIDI)
+        );
+    }
+
+    #[test]
+    fn surround_channel_masks() {
+        assert_eq!(
+            to_clap_surround_channel_mask(ChannelLayout::Surround51),
+            0x3f
+        );
+        assert_eq!(
+            to_clap_surround_channel_mask(ChannelLayout::Surround71),
+            0x63f
+        );
+        assert_eq!(
+            to_clap_surround_channel_mask(ChannelLayout::Surround714),
+            0x2d63f
+        );
+    }
+
+    #[test]
+    fn param_info_flags() {
+        assert_eq!(
+            to_clap_param_info_flags(ParamFlags::empty(), false, true),
+            CLAP_PARAM_IS_AUTOMATABLE
+                | CLAP_PARAM_IS_MODULATABLE
+                | CLAP_PARAM_IS_MODULATABLE_PER_NOTE_ID
+        );
+        assert_eq!(
+            to_clap_param_info_flags(ParamFlags::NON_AUTOMATABLE, true, true),
+            CLAP_PARAM_IS_STEPPED
+        );
+        assert_eq!(
+            to_clap_param_info_flags(
+                ParamFlags::NON_AUTOMATABLE | ParamFlags::BYPASS,
+                true,
+                false
+            ),
+            CLAP_PARAM_IS_BYPASS | CLAP_PARAM_IS_STEPPED
+        );
+        assert_eq!(
+            to_clap_param_info_flags(ParamFlags::HIDDEN, false, false),
+            CLAP_PARAM_IS_HIDDEN | CLAP_PARAM_IS_READONLY
+        );
+        assert_eq!(
+            to_clap_param_info_flags(ParamFlags::GAIN_REDUCTION, false, true),
+            CLAP_PARAM_IS_READONLY
+        );
+    }
+
+    #[test]
+    fn hidden_param_info_flags() {
+        let param = FloatParam::new("Debug", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 }).hide();
+        assert_eq!(
+            to_clap_param_info_flags(param.flags(), false, false),
+            CLAP_PARAM_IS_HIDDEN | CLAP_PARAM_IS_READONLY
+        );
+
+        let param =
+            IntParam::new("Debug", 0, IntRange::Linear { min: 0, max: 10 }).hide_in_release();
+        let expected = if cfg!(debug_assertions) {
+            CLAP_PARAM_IS_AUTOMATABLE | CLAP_PARAM_IS_MODULATABLE | CLAP_PARAM_IS_STEPPED
+        } else {
+            CLAP_PARAM_IS_HIDDEN | CLAP_PARAM_IS_READONLY | CLAP_PARAM_IS_STEPPED
+        };
+        assert_eq!(
+            to_clap_param_info_flags(param.flags(), true, false),
+            expected
+        );
+    }
+
+    #[test]
+    fn host_info() {
+        let host = simulated_host(c"Simulated Host", c"1.2.3");
+        assert_eq!(
+            unsafe { host_info_from_clap_host(&host) },
+            Some(HostInfo {
+                name: String::from("Simulated Host"),
+                version: Some(String::from("1.2.3")),
+            })
+        );
+
+        let host = simulated_host(c"Simulated Host", c"");
+        assert_eq!(
+            unsafe { host_info_from_clap_host(&host) },
+            Some(HostInfo {
+                name: String::from("Simulated Host"),
+                version: None,
+            })
+        );
+
+        let host = simulated_host(c"", c"1.2.3");
+        assert_eq!(unsafe { host_info_from_clap_host(&host) }, None);
+    }
+
+    #[test]
+    fn process_status_translation() {
+        assert_eq!(
+            to_clap_process_status(ProcessStatus::Error("error")),
+            CLAP_PROCESS_ERROR
+        );
+        assert_eq!(
+            to_clap_process_status(ProcessStatus::Normal),
+            CLAP_PROCESS_CONTINUE_IF_NOT_QUIET
+        );
+        assert_eq!(
+            to_clap_process_status(ProcessStatus::Tail(512)),
+            CLAP_PROCESS_TAIL
+        );
+        assert_eq!(
+            to_clap_process_status(ProcessStatus::Tail(0)),
+            CLAP_PROCESS_SLEEP
+        );
+        assert_eq!(
+            to_clap_process_status(ProcessStatus::KeepAlive),
+            CLAP_PROCESS_CONTINUE
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use atomic_float::AtomicF32;
 use atomic_refcell::{AtomicRefCell, AtomicRefMut};
+use clap_sys::color::clap_color;
 use clap_sys::events::{
     clap_event_header, clap_event_midi, clap_event_midi_sysex, clap_event_note,
     clap_event_note_expression, clap_event_param_gesture, clap_event_param_mod,
@@ -17,36 +18,48 @@ use clap_sys::events::{
 };
 use clap_sys::ext::audio_ports::{
     clap_audio_port_info, clap_plugin_audio_ports, CLAP_AUDIO_PORT_IS_MAIN, CLAP_EXT_AUDIO_PORTS,
-    CLAP_PORT_MONO, CLAP_PORT_STEREO,
 };
 use clap_sys::ext::audio_ports_config::{
     clap_audio_ports_config, clap_plugin_audio_ports_config, CLAP_EXT_AUDIO_PORTS_CONFIG,
 };
-use clap_sys::ext::remote_controls::{
-    clap_plugin_remote_controls, clap_remote_controls_page, CLAP_EXT_REMOTE_CONTROLS,
+use clap_sys::ext::context_menu::{
+    clap_context_menu_builder, clap_context_menu_entry, clap_context_menu_item_title,
+    clap_context_menu_submenu, clap_context_menu_target, clap_plugin_context_menu,
+    CLAP_CONTEXT_MENU_ITEM_BEGIN_SUBMENU, CLAP_CONTEXT_MENU_ITEM_END_SUBMENU,
+    CLAP_CONTEXT_MENU_ITEM_ENTRY, CLAP_CONTEXT_MENU_ITEM_SEPARATOR, CLAP_CONTEXT_MENU_ITEM_TITLE,
+    CLAP_CONTEXT_MENU_TARGET_KIND_GLOBAL, CLAP_CONTEXT_MENU_TARGET_KIND_PARAM,
+    CLAP_EXT_CONTEXT_MENU,
 };
 use clap_sys::ext::gui::{
     clap_gui_resize_hints, clap_host_gui, clap_plugin_gui, clap_window, CLAP_EXT_GUI,
     CLAP_WINDOW_API_COCOA, CLAP_WINDOW_API_WIN32, CLAP_WINDOW_API_X11,
 };
 use clap_sys::ext::latency::{clap_host_latency, clap_plugin_latency, CLAP_EXT_LATENCY};
-use clap_sys::ext::note_ports::{
-    clap_note_port_info, clap_plugin_note_ports, CLAP_EXT_NOTE_PORTS, CLAP_NOTE_DIALECT_CLAP,
-    CLAP_NOTE_DIALECT_MIDI,
+use clap_sys::ext::log::{clap_host_log, CLAP_EXT_LOG};
+use clap_sys::ext::note_ports::{clap_note_port_info, clap_plugin_note_ports, CLAP_EXT_NOTE_PORTS};
+use clap_sys::ext::param_indication::{
+    clap_plugin_param_indication, CLAP_EXT_PARAM_INDICATION, CLAP_PARAM_INDICATION_AUTOMATION_NONE,
+    CLAP_PARAM_INDICATION_AUTOMATION_OVERRIDING, CLAP_PARAM_INDICATION_AUTOMATION_PLAYING,
+    CLAP_PARAM_INDICATION_AUTOMATION_PRESENT, CLAP_PARAM_INDICATION_AUTOMATION_RECORDING,
 };
 use clap_sys::ext::params::{
     clap_host_params, clap_param_info, clap_plugin_params, CLAP_EXT_PARAMS,
-    CLAP_PARAM_IS_AUTOMATABLE, CLAP_PARAM_IS_BYPASS, CLAP_PARAM_IS_HIDDEN,
-    CLAP_PARAM_IS_MODULATABLE, CLAP_PARAM_IS_MODULATABLE_PER_NOTE_ID, CLAP_PARAM_IS_READONLY,
-    CLAP_PARAM_IS_STEPPED, CLAP_PARAM_RESCAN_VALUES,
+    CLAP_PARAM_RESCAN_VALUES,
+};
+use clap_sys::ext::remote_controls::{
+    clap_plugin_remote_controls, clap_remote_controls_page, CLAP_EXT_REMOTE_CONTROLS,
 };
 use clap_sys::ext::render::{
     clap_plugin_render, clap_plugin_render_mode, CLAP_EXT_RENDER, CLAP_RENDER_OFFLINE,
     CLAP_RENDER_REALTIME,
 };
 use clap_sys::ext::state::{clap_plugin_state, CLAP_EXT_STATE};
-use clap_sys::ext::tail::{clap_plugin_tail, CLAP_EXT_TAIL};
+use clap_sys::ext::surround::{clap_plugin_surround, CLAP_EXT_SURROUND};
+use clap_sys::ext::tail::{clap_host_tail, clap_plugin_tail, CLAP_EXT_TAIL};
 use clap_sys::ext::thread_check::{clap_host_thread_check, CLAP_EXT_THREAD_CHECK};
+use clap_sys::ext::thread_pool::{
+    clap_host_thread_pool, clap_plugin_thread_pool, CLAP_EXT_THREAD_POOL,
+};
 use clap_sys::ext::voice_info::{
     clap_host_voice_info, clap_plugin_voice_info, clap_voice_info, CLAP_EXT_VOICE_INFO,
     CLAP_VOICE_INFO_SUPPORTS_OVERLAPPING_NOTES,
@@ -55,16 +68,12 @@ use clap_sys::fixedpoint::{CLAP_BEATTIME_FACTOR, CLAP_SECTIME_FACTOR};
 use clap_sys::host::clap_host;
 use clap_sys::id::{clap_id, CLAP_INVALID_ID};
 use clap_sys::plugin::clap_plugin;
-use clap_sys::process::{
-    clap_process, clap_process_status, CLAP_PROCESS_CONTINUE, CLAP_PROCESS_CONTINUE_IF_NOT_QUIET,
-    CLAP_PROCESS_ERROR,
-};
+use clap_sys::process::{clap_process, clap_process_status, CLAP_PROCESS_ERROR};
 use clap_sys::stream::{clap_istream, clap_ostream};
 use crossbeam::atomic::AtomicCell;
 use crossbeam::channel::{self, SendTimeoutError};
 use crossbeam::queue::ArrayQueue;
 use parking_lot::Mutex;
-use std::any::Any;
 use std::borrow::Borrow;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::ffi::{c_void, CStr};
@@ -83,17 +92,33 @@ use super::util::ClapPtr;
 use crate::event_loop::{BackgroundThread, EventLoop, MainThreadExecutor, TASK_QUEUE_CAPACITY};
 use crate::midi::MidiResult;
 use crate::prelude::{
-    AsyncExecutor, AudioIOLayout, AuxiliaryBuffers, BufferConfig, ClapPlugin, Editor, MidiConfig,
-    NoteEvent, ParamFlags, ParamPtr, Params, ParentWindowHandle, Plugin, PluginNoteEvent,
-    ProcessMode, ProcessStatus, SysExMessage, TaskExecutor, Transport,
+    AsyncExecutor, AudioIOLayout, AutomationIndication, AuxiliaryBuffers, BufferConfig,
+    ChannelLayout, ClapNoteDialects, ClapPlugin, Editor, HostInfo, MidiConfig, NoteEvent,
+    ParamFlags, ParamIndication, ParamPtr, Params, ParentWindowHandle, Plugin, PluginNoteEvent,
+    ProcessMode, ProcessStatus, RawMidiConfig, SysExMessage, TaskExecutor, Transport,
+};
+use crate::util::{permit_alloc, ProcessLoad};
+use crate::wrapper::clap::context::{
+    ContextMenuEntries, ContextMenuItem, ContextMenus, RemoteControlPages,
+};
+use crate::wrapper::clap::util::{
+    clap_color_to_indication_color, host_info_from_clap_host, read_stream, to_clap_note_dialects,
+    to_clap_param_info_flags, to_clap_port_type, to_clap_process_status,
+    to_clap_surround_channel_mask, to_clap_surround_position, write_stream, ClapHostLogger,
 };
-use crate::util::permit_alloc;
-use crate::wrapper::clap::context::RemoteControlPages;
-use crate::wrapper::clap::util::{read_stream, write_stream};
 use crate::wrapper::state::{self, PluginState};
+use crate::wrapper::util::automation::AutomationRecorder;
 use crate::wrapper::util::buffer_management::{BufferManager, ChannelPointers};
+use crate::wrapper::util::fixed_block::{
+    fixed_block_latency, plugin_buffer_config, FixedBlockBuffers,
+};
+use crate::wrapper::util::gain_reduction::GainReductionMeter;
+use crate::wrapper::util::gestures::GestureWatchdog;
+use crate::wrapper::util::logger::{self, HostLogger, InstanceGuard, LoggerRegistration};
+use crate::wrapper::util::transport_jumps::TransportJumpDetector;
 use crate::wrapper::util::{
-    clamp_input_event_timing, clamp_output_event_timing, hash_param_id, process_wrapper, strlcpy,
+    clamp_input_event_timing, clamp_output_event_timing, hash_param_id, max_block_end,
+    midi_input_events, process_wrapper, strlcpy, tail_length_samples, EditorHandle,
 };
 
 /// How many output parameter changes we can store in our output parameter change queue. Storing
@@ -118,7 +143,10 @@ pub struct Wrapper<P: ClapPlugin> {
     editor: AtomicRefCell<Option<Mutex<Box<dyn Editor>>>>,
     /// A handle for the currently active editor instance. The plugin should implement `Drop` on
     /// this handle for its closing behavior.
-    editor_handle: Mutex<Option<Box<dyn Any + Send>>>,
+    editor_handle: Mutex<Option<EditorHandle>>,
+    /// Whether the editor is currently open. This is managed by [`EditorHandle`] and it can be
+    /// queried through the [`ProcessContext`].
+    pub editor_open: Arc<AtomicBool>,
     /// The DPI scaling factor as passed to the [IPlugViewContentScaleSupport::set_scale_factor()]
     /// function. Defaults to 1.0, and will be kept there on macOS. When reporting and handling size
     /// the sizes communicated to and from the DAW should be scaled by this factor since NIH-plug's
@@ -148,9 +176,26 @@ pub struct Wrapper<P: ClapPlugin> {
     /// The current latency in samples, as set by the plugin through the [`ProcessContext`]. Uses
     /// the latency extension.
     pub current_latency: AtomicU32,
+    /// Measures how long the process function takes. Only updated when the `process_profiling`
+    /// feature is enabled.
+    pub process_load: Arc<ProcessLoad>,
+    /// Records the parameter changes sent by the host when the `automation_recording` feature is
+    /// enabled and the `NIH_RECORD_AUTOMATION` environment variable is set.
+    automation_recorder: AutomationRecorder,
+    /// The current tail length in samples, if the plugin has set one through the
+    /// [`InitContext`][crate::prelude::InitContext] or the [`ProcessContext`]. If this is `None`,
+    /// then the tail length is derived from the last process status instead. Uses the tail
+    /// extension.
+    current_tail: AtomicCell<Option<u32>>,
     /// A data structure that helps manage and create buffers for all of the plugin's inputs and
     /// outputs based on channel pointers provided by the host.
     buffer_manager: AtomicRefCell<BufferManager>,
+    /// Buffers the host's audio into blocks of [`Plugin::FIXED_BLOCK_SIZE`] samples. This is only
+    /// set if the plugin uses a fixed block size, and it's created in `clap_plugin::activate()`.
+    fixed_block_buffers: AtomicRefCell<Option<FixedBlockBuffers<P::SysExMessage>>>,
+    /// Fills in [`Transport::jump`] by comparing the host's playback position to the previous
+    /// block's position.
+    transport_jump_detector: AtomicRefCell<TransportJumpDetector>,
     /// The plugin is able to restore state through a method on the `GuiContext`. To avoid changing
     /// parameters mid-processing and running into garbled data if the host also tries to load state
     /// at the same time the restoring happens at the end of each processing call. If this zero
@@ -165,6 +210,8 @@ pub struct Wrapper<P: ClapPlugin> {
 
     // We'll query all of the host's extensions upfront
     host_callback: ClapPtr<clap_host>,
+    /// The host's name and version, read from `host_callback` when the plugin is created.
+    pub host_info: Option<HostInfo>,
 
     clap_plugin_audio_ports_config: clap_plugin_audio_ports_config,
 
@@ -222,8 +269,39 @@ pub struct Wrapper<P: ClapPlugin> {
     ///      even if it does then that should still not be a problem because the host also reads it
     ///      in the same order, right?
     output_parameter_events: ArrayQueue<OutputParamEvent>,
+    /// Keeps track of the gestures started by the editor so dangling gestures can be ended
+    /// automatically. See [`GestureWatchdog`] for more information.
+    pub gesture_watchdog: GestureWatchdog,
+    /// Sends the gain reduction reported by the plugin to the host through the plugin's gain
+    /// reduction parameter. See [`GainReductionMeter`] for more information.
+    pub gain_reduction_meter: GainReductionMeter,
 
     host_thread_check: AtomicRefCell<Option<ClapPtr<clap_host_thread_check>>>,
+    /// Keeps the log flushing thread alive, and routes log messages to the host if it supports the
+    /// `clap.log` extension. This is set in `init()` since host extensions cannot be queried before
+    /// that.
+    logger_registration: AtomicRefCell<Option<LoggerRegistration>>,
+
+    clap_plugin_thread_pool: clap_plugin_thread_pool,
+    host_thread_pool: AtomicRefCell<Option<ClapPtr<clap_host_thread_pool>>>,
+    /// The task passed to
+    /// [`ProcessContext::execute_parallel()`][crate::prelude::ProcessContext::execute_parallel()]
+    /// while the host's thread pool is executing it. This is only set for the duration of the
+    /// `clap_host_thread_pool::request_exec()` call, and the host's worker threads will only ever
+    /// immutably borrow this.
+    thread_pool_task: AtomicRefCell<Option<ThreadPoolTask>>,
+
+    clap_plugin_context_menu: clap_plugin_context_menu,
+    /// The context menu entries added by the plugin, if it defines any. Filled when initializing
+    /// the plugin.
+    context_menu_entries: ContextMenuEntries,
+
+    clap_plugin_param_indication: clap_plugin_param_indication,
+    /// The automation and mapping indications the host has set for each parameter, indexed by the
+    /// parameter's hash. This contains an entry for every parameter so it never needs to be
+    /// modified after the wrapper has been created. Exposed to the editor through
+    /// [`GuiContext::param_indication()`][crate::prelude::GuiContext::param_indication()].
+    pub param_indications: HashMap<u32, AtomicCell<ParamIndication>>,
 
     clap_plugin_remote_controls: clap_plugin_remote_controls,
     /// The plugin's remote control pages, if it defines any. Filled when initializing the plugin.
@@ -233,7 +311,10 @@ pub struct Wrapper<P: ClapPlugin> {
 
     clap_plugin_state: clap_plugin_state,
 
+    clap_plugin_surround: clap_plugin_surround,
+
     clap_plugin_tail: clap_plugin_tail,
+    host_tail: AtomicRefCell<Option<ClapPtr<clap_host_tail>>>,
 
     clap_plugin_voice_info: clap_plugin_voice_info,
     host_voice_info: AtomicRefCell<Option<ClapPtr<clap_host_voice_info>>>,
@@ -258,6 +339,16 @@ pub struct Wrapper<P: ClapPlugin> {
     background_thread: AtomicRefCell<Option<BackgroundThread<Task<P>, Self>>>,
 }
 
+/// A type erased pointer to the closure passed to
+/// [`ProcessContext::execute_parallel()`][crate::prelude::ProcessContext::execute_parallel()]. The
+/// closure is `Sync` and it is guaranteed to outlive the thread pool's
+/// `clap_host_thread_pool::request_exec()` call, so this can safely be shared with the host's
+/// worker threads.
+struct ThreadPoolTask(*const (dyn Fn(u32) + Sync + 'static));
+
+unsafe impl Send for ThreadPoolTask {}
+unsafe impl Sync for ThreadPoolTask {}
+
 /// Tasks that can be sent from the plugin to be executed on the main thread in a non-blocking
 /// realtime-safe way. Instead of using a random thread or the OS' event loop like in the Linux
 /// implementation, this uses [`clap_host::request_callback()`] instead.
@@ -279,6 +370,8 @@ pub enum Task<P: Plugin> {
     VoiceInfoChanged,
     /// Tell the host that it should rescan the current parameter values.
     RescanParamValues,
+    /// Inform the editor that the plugin has been initialized with a new buffer config.
+    BufferConfigChanged(BufferConfig),
 }
 
 /// The types of CLAP parameter updates for events.
@@ -306,6 +399,9 @@ pub enum OutputParamEvent {
         /// The 'plain' value as reported to CLAP. This is the normalized value multiplied by
         /// [`params::step_size()`][crate::params::step_size()].
         clap_plain_value: f64,
+        /// Whether the parameter's smoother should be reset to the new value instead of moving
+        /// towards it.
+        reset_smoother: bool,
     },
     /// Begin an automation gesture. This must always be sent after sending one or more [`SetValue`]
     /// events.
@@ -355,11 +451,21 @@ impl<P: ClapPlugin> EventLoop<Task<P>, Wrapper<P>> for Wrapper<P> {
             None => permit_alloc(|| thread::current().id() == self.main_thread_id),
         }
     }
+
+    /// Route the messages logged on the current thread to this instance's host logger until the
+    /// returned guard is dropped. This should be held whenever the wrapper calls into the plugin.
+    fn enter_logger(&self) -> Option<InstanceGuard> {
+        self.logger_registration
+            .borrow()
+            .as_ref()
+            .map(LoggerRegistration::enter)
+    }
 }
 
 impl<P: ClapPlugin> MainThreadExecutor<Task<P>> for Wrapper<P> {
     fn execute(&self, task: Task<P>, is_gui_thread: bool) {
         // This function is always called from the main thread, from [Self::on_main_thread].
+        let _logger_guard = self.enter_logger();
         match task {
             Task::PluginTask(task) => (self.task_executor.lock())(task),
             Task::ParameterValuesChanged => {
@@ -369,6 +475,13 @@ impl<P: ClapPlugin> MainThreadExecutor<Task<P>> for Wrapper<P> {
                     }
                 }
             }
+            Task::BufferConfigChanged(buffer_config) => {
+                if self.editor_handle.lock().is_some() {
+                    if let Some(editor) = self.editor.borrow().as_ref() {
+                        editor.lock().buffer_config_changed(&buffer_config);
+                    }
+                }
+            }
             Task::ParameterValueChanged(param_hash, normalized_value) => {
                 if self.editor_handle.lock().is_some() {
                     if let Some(editor) = self.editor.borrow().as_ref() {
@@ -442,6 +555,7 @@ impl<P: ClapPlugin> Wrapper<P> {
         // need a bunch of AtomicRefCells instead
         assert!(!host_callback.is_null());
         let host_callback = unsafe { ClapPtr::new(host_callback) };
+        let host_info = unsafe { host_info_from_clap_host(&host_callback) };
 
         // This is a mapping from the parameter IDs specified by the plugin to pointers to those
         // parameters. These pointers are assumed to be safe to dereference as long as
@@ -457,10 +571,16 @@ impl<P: ClapPlugin> Wrapper<P> {
                 (id, hash, ptr, group)
             })
             .collect();
-        let param_hashes = param_id_hashes_ptrs_groups
+        let param_hashes: Vec<u32> = param_id_hashes_ptrs_groups
             .iter()
             .map(|(_, hash, _, _)| *hash)
             .collect();
+        let gesture_watchdog = GestureWatchdog::new(param_hashes.iter().copied());
+        let gain_reduction_meter = GainReductionMeter::new(
+            param_id_hashes_ptrs_groups
+                .iter()
+                .map(|(_, hash, ptr, _)| (*hash, *ptr)),
+        );
         let param_by_hash = param_id_hashes_ptrs_groups
             .iter()
             .map(|(_, hash, ptr, _)| (*hash, *ptr))
@@ -501,6 +621,19 @@ impl<P: ClapPlugin> Wrapper<P> {
                  6 character parameter IDs to avoid collisions."
             );
 
+            // The plugin APIs only see the hashes, so two distinct IDs must also not hash to the
+            // same value. This includes the prefixed IDs from nested parameter structs.
+            let param_hashes: HashSet<u32> = param_id_hashes_ptrs_groups
+                .iter()
+                .map(|(_, hash, _, _)| *hash)
+                .collect();
+            nih_debug_assert_eq!(
+                param_ids.len(),
+                param_hashes.len(),
+                "Some of the plugin's parameter IDs have the same hash, weird things may happen. \
+                 Try renaming the parameters with colliding IDs."
+            );
+
             let poly_mod_ids: HashSet<u32> = poly_mod_ids_by_hash.values().copied().collect();
             nih_debug_assert_eq!(
                 poly_mod_ids_by_hash.len(),
@@ -530,8 +663,19 @@ impl<P: ClapPlugin> Wrapper<P> {
             &plugin,
             &mut remote_control_pages,
             &param_ptr_to_hash,
+            &*params,
         );
 
+        // Support for the param indication extension
+        let param_indications = param_hashes
+            .iter()
+            .map(|&hash| (hash, AtomicCell::new(ParamIndication::default())))
+            .collect();
+
+        // Support for the context menu extension
+        let mut context_menu_entries = ContextMenuEntries::default();
+        ContextMenus::define_context_menus(&plugin, &mut context_menu_entries, &param_ptr_to_hash);
+
         let wrapper = Self {
             this: AtomicRefCell::new(Weak::new()),
 
@@ -541,6 +685,7 @@ impl<P: ClapPlugin> Wrapper<P> {
             // Initialized later as it needs a reference to the wrapper for the async executor
             editor: AtomicRefCell::new(None),
             editor_handle: Mutex::new(None),
+            editor_open: Arc::new(AtomicBool::new(false)),
             editor_scaling_factor: AtomicF32::new(1.0),
 
             is_processing: AtomicBool::new(false),
@@ -553,16 +698,22 @@ impl<P: ClapPlugin> Wrapper<P> {
             output_events: AtomicRefCell::new(VecDeque::with_capacity(512)),
             last_process_status: AtomicCell::new(ProcessStatus::Normal),
             current_latency: AtomicU32::new(0),
+            process_load: Arc::new(ProcessLoad::default()),
+            automation_recorder: AutomationRecorder::from_env(),
+            current_tail: AtomicCell::new(None),
             // This is initialized just before calling `Plugin::initialize()` so that during the
             // process call buffers can be initialized without any allocations
             buffer_manager: AtomicRefCell::new(BufferManager::for_audio_io_layout(
                 0,
                 AudioIOLayout::default(),
             )),
+            fixed_block_buffers: AtomicRefCell::new(None),
+            transport_jump_detector: AtomicRefCell::new(TransportJumpDetector::default()),
             updated_state_sender,
             updated_state_receiver,
 
             host_callback,
+            host_info,
 
             clap_plugin: AtomicRefCell::new(clap_plugin {
                 // This needs to live on the heap because the plugin object contains a direct
@@ -642,8 +793,29 @@ impl<P: ClapPlugin> Wrapper<P> {
             param_ptr_to_hash,
             poly_mod_ids_by_hash,
             output_parameter_events: ArrayQueue::new(OUTPUT_EVENT_QUEUE_CAPACITY),
+            gesture_watchdog,
+            gain_reduction_meter,
 
             host_thread_check: AtomicRefCell::new(None),
+            logger_registration: AtomicRefCell::new(None),
+
+            clap_plugin_thread_pool: clap_plugin_thread_pool {
+                exec: Some(Self::ext_thread_pool_exec),
+            },
+            host_thread_pool: AtomicRefCell::new(None),
+            thread_pool_task: AtomicRefCell::new(None),
+
+            clap_plugin_context_menu: clap_plugin_context_menu {
+                populate: Some(Self::ext_context_menu_populate),
+                perform: Some(Self::ext_context_menu_perform),
+            },
+            context_menu_entries,
+
+            clap_plugin_param_indication: clap_plugin_param_indication {
+                set_mapping: Some(Self::ext_param_indication_set_mapping),
+                set_automation: Some(Self::ext_param_indication_set_automation),
+            },
+            param_indications,
 
             clap_plugin_remote_controls: clap_plugin_remote_controls {
                 count: Some(Self::ext_remote_controls_count),
@@ -661,9 +833,15 @@ impl<P: ClapPlugin> Wrapper<P> {
                 load: Some(Self::ext_state_load),
             },
 
+            clap_plugin_surround: clap_plugin_surround {
+                is_channel_mask_supported: Some(Self::ext_surround_is_channel_mask_supported),
+                get_channel_map: Some(Self::ext_surround_get_channel_map),
+            },
+
             clap_plugin_tail: clap_plugin_tail {
                 get: Some(Self::ext_tail_get),
             },
+            host_tail: AtomicRefCell::new(None),
 
             clap_plugin_voice_info: clap_plugin_voice_info {
                 get: Some(Self::ext_voice_info_get),
@@ -773,7 +951,30 @@ impl<P: ClapPlugin> Wrapper<P> {
     /// Returns `false` if the parameter value queue was full and the update will not be sent to the
     /// host (it will still be set on the plugin either way).
     pub fn queue_parameter_event(&self, event: OutputParamEvent) -> bool {
-        let result = self.output_parameter_events.push(event).is_ok();
+        self.queue_parameter_events([event])
+    }
+
+    /// The same as [`queue_parameter_event()`][Self::queue_parameter_event()], but for multiple
+    /// events at once. This only requests a single parameter flush from the host. The events are
+    /// either all queued or not queued at all, so a full queue can never cause a gesture to be
+    /// started without also being ended. Returns `false` if the events could not be queued.
+    pub fn queue_parameter_events<I>(&self, events: I) -> bool
+    where
+        I: IntoIterator<Item = OutputParamEvent>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        // The audio thread may still end timed out gestures in the meantime, but those events
+        // don't depend on anything else in the queue
+        let events = events.into_iter();
+        let free_capacity =
+            self.output_parameter_events.capacity() - self.output_parameter_events.len();
+        let result = events.len() <= free_capacity;
+        if result {
+            for event in events {
+                let pushed = self.output_parameter_events.push(event).is_ok();
+                nih_debug_assert!(pushed, "Parameter output event queue overflowed");
+            }
+        }
 
         // Requesting a flush is fine even during audio processing. This avoids a race condition.
         match &*self.host_params.borrow() {
@@ -1010,12 +1211,29 @@ impl<P: ClapPlugin> Wrapper<P> {
                 OutputParamEvent::SetValue {
                     param_hash,
                     clap_plain_value,
+                    reset_smoother,
                 } => {
                     self.update_plain_value_by_hash(
                         param_hash,
                         ClapParamUpdate::PlainValueSet(clap_plain_value),
                         sample_rate,
                     );
+                    if let (true, Some(sample_rate), Some(param_ptr)) = (
+                        reset_smoother,
+                        sample_rate,
+                        self.param_by_hash.get(&param_hash),
+                    ) {
+                        param_ptr.update_smoother(sample_rate, true);
+                    }
+
+                    // The host should only ever see whole steps for stepped parameters, even
+                    // though `FloatParam`s with a step count can be set to any value from the GUI
+                    let clap_plain_value = match self.param_by_hash.get(&param_hash) {
+                        Some(param_ptr) if param_ptr.step_count().is_some() => {
+                            clap_plain_value.round()
+                        }
+                        _ => clap_plain_value,
+                    };
 
                     let event = clap_event_param_value {
                         header: clap_event_header {
@@ -1065,6 +1283,41 @@ impl<P: ClapPlugin> Wrapper<P> {
             );
 
             let push_successful = match event {
+                // Plugins that only produce MIDI send all of their note events as MIDI messages.
+                // Events without a MIDI equivalent, like polyphonic modulation, are dropped.
+                event
+                    if P::CLAP_NOTE_OUTPUT_DIALECTS == ClapNoteDialects::MidiOnly
+                        && P::MIDI_OUTPUT >= MidiConfig::Basic
+                        && !matches!(event, NoteEvent::MidiSysEx { .. })
+                        && (P::MIDI_OUTPUT >= MidiConfig::MidiCCs
+                            || !matches!(
+                                event,
+                                NoteEvent::MidiChannelPressure { .. }
+                                    | NoteEvent::MidiPitchBend { .. }
+                                    | NoteEvent::MidiCC { .. }
+                                    | NoteEvent::MidiProgramChange { .. }
+                                    | NoteEvent::MidiRaw { .. }
+                            )) =>
+                {
+                    let midi_data = match event.as_midi() {
+                        Some(MidiResult::Basic(midi_data)) => midi_data,
+                        _ => continue,
+                    };
+
+                    let event = clap_event_midi {
+                        header: clap_event_header {
+                            size: mem::size_of::<clap_event_midi>() as u32,
+                            time,
+                            space_id: CLAP_CORE_EVENT_SPACE_ID,
+                            type_: CLAP_EVENT_MIDI,
+                            flags: 0,
+                        },
+                        port_index: 0,
+                        data: midi_data,
+                    };
+
+                    clap_call! { out=>try_push(out, &event.header) }
+                }
                 NoteEvent::NoteOn {
                     timing: _,
                     voice_id,
@@ -1114,6 +1367,33 @@ impl<P: ClapPlugin> Wrapper<P> {
 
                     clap_call! { out=>try_push(out, &event.header) }
                 }
+                // These are sent as note off and choke events that target all keys
+                NoteEvent::AllNotesOff { timing: _, channel }
+                | NoteEvent::AllSoundOff { timing: _, channel }
+                    if P::MIDI_OUTPUT >= MidiConfig::Basic =>
+                {
+                    let type_ = if matches!(event, NoteEvent::AllNotesOff { .. }) {
+                        CLAP_EVENT_NOTE_OFF
+                    } else {
+                        CLAP_EVENT_NOTE_CHOKE
+                    };
+                    let event = clap_event_note {
+                        header: clap_event_header {
+                            size: mem::size_of::<clap_event_note>() as u32,
+                            time,
+                            space_id: CLAP_CORE_EVENT_SPACE_ID,
+                            type_,
+                            flags: 0,
+                        },
+                        note_id: -1,
+                        port_index: 0,
+                        channel: channel.map(|channel| channel as i16).unwrap_or(-1),
+                        key: -1,
+                        velocity: 0.0,
+                    };
+
+                    clap_call! { out=>try_push(out, &event.header) }
+                }
                 // NOTE: This is gated behind `P::MIDI_INPUT`, because this is a merely a hint event
                 //       for the host. It is not output to any other plugin or device.
                 NoteEvent::VoiceTerminated {
@@ -1317,8 +1597,10 @@ impl<P: ClapPlugin> Wrapper<P> {
                 midi_event @ (NoteEvent::MidiChannelPressure { .. }
                 | NoteEvent::MidiPitchBend { .. }
                 | NoteEvent::MidiCC { .. }
-                | NoteEvent::MidiProgramChange { .. })
-                    if P::MIDI_OUTPUT >= MidiConfig::MidiCCs =>
+                | NoteEvent::MidiProgramChange { .. }
+                | NoteEvent::MidiRaw { .. })
+                    if P::MIDI_OUTPUT >= MidiConfig::MidiCCs
+                        && P::CLAP_NOTE_OUTPUT_DIALECTS != ClapNoteDialects::ClapOnly =>
                 {
                     // NIH-plug already includes MIDI conversion functions, so we'll reuse those for
                     // the MIDI events
@@ -1345,7 +1627,8 @@ impl<P: ClapPlugin> Wrapper<P> {
                     clap_call! { out=>try_push(out, &event.header) }
                 }
                 NoteEvent::MidiSysEx { timing: _, message }
-                    if P::MIDI_OUTPUT >= MidiConfig::Basic =>
+                    if P::MIDI_OUTPUT >= MidiConfig::Basic
+                        && P::CLAP_NOTE_OUTPUT_DIALECTS != ClapNoteDialects::ClapOnly =>
                 {
                     // SysEx is supported on the basic MIDI config so this is separate
                     let (padded_sysex_buffer, length) = message.to_buffer();
@@ -1371,7 +1654,8 @@ impl<P: ClapPlugin> Wrapper<P> {
                 }
                 _ => {
                     nih_debug_assert_failure!(
-                        "Invalid output event for the current MIDI_OUTPUT setting"
+                        "Invalid output event for the current MIDI_OUTPUT and \
+                         CLAP_NOTE_OUTPUT_DIALECTS settings"
                     );
                     continue;
                 }
@@ -1414,9 +1698,22 @@ impl<P: ClapPlugin> Wrapper<P> {
         match (raw_event.space_id, raw_event.type_) {
             (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_PARAM_VALUE) => {
                 let event = &*(event as *const clap_event_param_value);
+                // The host should only send whole steps for stepped parameters. This matters for
+                // `FloatParam`s with a step count, since those are still continuous internally.
+                let clap_plain_value = match self.param_by_hash.get(&event.param_id) {
+                    Some(param_ptr) if param_ptr.step_count().is_some() => event.value.round(),
+                    _ => event.value,
+                };
+                if let Some(param_ptr) = self.param_by_hash.get(&event.param_id) {
+                    self.automation_recorder.record(
+                        current_sample_idx as u32 + timing,
+                        event.param_id,
+                        clap_plain_value as f32 / param_ptr.step_count().unwrap_or(1) as f32,
+                    );
+                }
                 self.update_plain_value_by_hash(
                     event.param_id,
-                    ClapParamUpdate::PlainValueSet(event.value),
+                    ClapParamUpdate::PlainValueSet(clap_plain_value),
                     self.current_buffer_config.load().map(|c| c.sample_rate),
                 );
 
@@ -1429,7 +1726,7 @@ impl<P: ClapPlugin> Wrapper<P> {
                     // integer or enum parameters
                     let param_ptr = self.param_by_hash[&event.param_id];
                     let normalized_value =
-                        event.value as f32 / param_ptr.step_count().unwrap_or(1) as f32;
+                        clap_plain_value as f32 / param_ptr.step_count().unwrap_or(1) as f32;
 
                     input_events.push_back(NoteEvent::MonoAutomation {
                         timing,
@@ -1502,6 +1799,19 @@ impl<P: ClapPlugin> Wrapper<P> {
             (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_NOTE_OFF) => {
                 if P::MIDI_INPUT >= MidiConfig::Basic {
                     let event = &*(event as *const clap_event_note);
+                    // A note off event for all keys releases all notes
+                    if event.key == -1 && event.note_id == -1 {
+                        input_events.push_back(NoteEvent::AllNotesOff {
+                            timing,
+                            channel: if event.channel != -1 {
+                                Some(event.channel as u8)
+                            } else {
+                                None
+                            },
+                        });
+                        return;
+                    }
+
                     input_events.push_back(NoteEvent::NoteOff {
                         timing,
                         voice_id: if event.note_id != -1 {
@@ -1518,6 +1828,19 @@ impl<P: ClapPlugin> Wrapper<P> {
             (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_NOTE_CHOKE) => {
                 if P::MIDI_INPUT >= MidiConfig::Basic {
                     let event = &*(event as *const clap_event_note);
+                    // And a choke event for all keys silences all voices
+                    if event.key == -1 && event.note_id == -1 {
+                        input_events.push_back(NoteEvent::AllSoundOff {
+                            timing,
+                            channel: if event.channel != -1 {
+                                Some(event.channel as u8)
+                            } else {
+                                None
+                            },
+                        });
+                        return;
+                    }
+
                     input_events.push_back(NoteEvent::Choke {
                         timing,
                         voice_id: if event.note_id != -1 {
@@ -1633,25 +1956,33 @@ impl<P: ClapPlugin> Wrapper<P> {
                 }
             }
             (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_MIDI) => {
-                // In the Basic note port type, we'll still handle note on, note off, and polyphonic
-                // pressure events if the host sents us those. But we'll throw away any other MIDI
-                // messages to stay consistent with the VST3 wrapper.
+                // In the Basic note port type, we'll still handle note on, note off, all notes/sound
+                // off, and polyphonic pressure events if the host sents us those. But we'll throw
+                // away any other MIDI messages to stay consistent with the VST3 wrapper.
+                // Raw MIDI messages are sent regardless of the message type.
                 let event = &*(event as *const clap_event_midi);
 
-                match NoteEvent::from_midi(timing, &event.data) {
-                    Ok(
-                        note_event @ (NoteEvent::NoteOn { .. }
-                        | NoteEvent::NoteOff { .. }
-                        | NoteEvent::PolyPressure { .. }),
-                    ) if P::MIDI_INPUT >= MidiConfig::Basic => {
-                        input_events.push_back(note_event);
-                    }
-                    Ok(note_event) if P::MIDI_INPUT >= MidiConfig::MidiCCs => {
-                        input_events.push_back(note_event);
+                for result in midi_input_events(timing, &event.data, P::MIDI_INPUT_RAW) {
+                    match result {
+                        Ok(
+                            note_event @ (NoteEvent::NoteOn { .. }
+                            | NoteEvent::NoteOff { .. }
+                            | NoteEvent::AllNotesOff { .. }
+                            | NoteEvent::AllSoundOff { .. }
+                            | NoteEvent::PolyPressure { .. }
+                            | NoteEvent::MidiRaw { .. }),
+                        ) if P::MIDI_INPUT >= MidiConfig::Basic => {
+                            input_events.push_back(note_event);
+                        }
+                        Ok(note_event) if P::MIDI_INPUT >= MidiConfig::MidiCCs => {
+                            input_events.push_back(note_event);
+                        }
+                        Ok(_) => (),
+                        // The plugin may handle these messages itself through the raw events
+                        Err(_) if P::MIDI_INPUT_RAW != RawMidiConfig::None => (),
+                        Err(n) => nih_debug_assert_failure!("Unhandled MIDI message type {}", n),
                     }
-                    Ok(_) => (),
-                    Err(n) => nih_debug_assert_failure!("Unhandled MIDI message type {}", n),
-                };
+                }
             }
             (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_MIDI_SYSEX)
                 if P::MIDI_INPUT >= MidiConfig::Basic =>
@@ -1745,6 +2076,22 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
     }
 
+    pub fn set_tail_samples(&self, samples: u32) {
+        let old_tail = self.current_tail.swap(Some(samples));
+        if old_tail != Some(samples) {
+            // The host tail extension's `changed()` function may only be called from the audio
+            // thread. When this is called from the main thread the plugin is not processing audio,
+            // so the host will query the new tail length by itself.
+            if self.is_main_thread() {
+                return;
+            }
+
+            if let Some(host_tail) = &*self.host_tail.borrow() {
+                unsafe_clap_call! { host_tail=>changed(&*self.host_callback) };
+            }
+        }
+    }
+
     pub fn set_current_voice_capacity(&self, capacity: u32) {
         match P::CLAP_POLY_MODULATION_CONFIG {
             Some(config) => {
@@ -1769,6 +2116,55 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
     }
 
+    /// Run `task` `num_tasks` times using the host's thread pool, blocking until all tasks have
+    /// finished. Falls back to running the tasks sequentially if the host does not support the
+    /// thread pool extension, if the host rejects the request, or if this is called from within
+    /// another thread pool task.
+    pub fn execute_parallel(&self, num_tasks: u32, task: &(dyn Fn(u32) + Sync)) {
+        if num_tasks == 0 {
+            return;
+        }
+
+        let run_sequentially = || {
+            for task_index in 0..num_tasks {
+                task(task_index);
+            }
+        };
+
+        let host_thread_pool = self.host_thread_pool.borrow();
+        let thread_pool = match &*host_thread_pool {
+            Some(thread_pool) if num_tasks > 1 => thread_pool,
+            _ => return run_sequentially(),
+        };
+
+        // If this borrow fails or if a task is already set, then we're being called from within
+        // one of the thread pool's tasks
+        match self.thread_pool_task.try_borrow_mut() {
+            Ok(mut current_task) if current_task.is_none() => {
+                // SAFETY: The lifetime is erased here, but the task is removed again before this
+                //         function returns and the borrow ends
+                *current_task = Some(ThreadPoolTask(unsafe {
+                    mem::transmute::<
+                        *const (dyn Fn(u32) + Sync + '_),
+                        *const (dyn Fn(u32) + Sync + 'static),
+                    >(task as *const _)
+                }));
+            }
+            _ => return run_sequentially(),
+        }
+
+        let success = unsafe_clap_call! {
+            thread_pool=>request_exec(&*self.host_callback, num_tasks)
+        };
+        *self.thread_pool_task.borrow_mut() = None;
+
+        // The host is allowed to reject the request, in which case none of the tasks will have been
+        // run
+        if !success {
+            run_sequentially();
+        }
+    }
+
     /// Immediately set the plugin state. Returns `false` if the deserialization failed. The plugin
     /// state is set from a couple places, so this function aims to deduplicate that. Includes
     /// `permit_alloc()`s around the deserialization and initialization for the use case where
@@ -1851,10 +2247,25 @@ impl<P: ClapPlugin> Wrapper<P> {
             &wrapper.host_callback,
             CLAP_EXT_VOICE_INFO,
         );
+        *wrapper.host_tail.borrow_mut() =
+            query_host_extension::<clap_host_tail>(&wrapper.host_callback, CLAP_EXT_TAIL);
         *wrapper.host_thread_check.borrow_mut() = query_host_extension::<clap_host_thread_check>(
             &wrapper.host_callback,
             CLAP_EXT_THREAD_CHECK,
         );
+        *wrapper.host_thread_pool.borrow_mut() = query_host_extension::<clap_host_thread_pool>(
+            &wrapper.host_callback,
+            CLAP_EXT_THREAD_POOL,
+        );
+
+        let host_logger = query_host_extension::<clap_host_log>(
+            &wrapper.host_callback,
+            CLAP_EXT_LOG,
+        )
+        .map(|host_log| {
+            Box::new(ClapHostLogger::new(&*wrapper.host_callback, host_log)) as Box<dyn HostLogger>
+        });
+        *wrapper.logger_registration.borrow_mut() = Some(logger::register(host_logger));
 
         true
     }
@@ -1877,12 +2288,13 @@ impl<P: ClapPlugin> Wrapper<P> {
         let wrapper = &*((*plugin).plugin_data as *const Self);
 
         let audio_io_layout = wrapper.current_audio_io_layout.load();
-        let buffer_config = BufferConfig {
+        // If the plugin uses a fixed block size, then it only ever sees blocks of that size
+        let buffer_config = plugin_buffer_config::<P>(BufferConfig {
             sample_rate: sample_rate as f32,
             min_buffer_size: Some(min_frames_count),
             max_buffer_size: max_frames_count,
             process_mode: wrapper.current_process_mode.load(),
-        };
+        });
 
         // Before initializing the plugin, make sure all smoothers are set the the default values
         for param in wrapper.param_by_hash.values() {
@@ -1891,6 +2303,7 @@ impl<P: ClapPlugin> Wrapper<P> {
 
         // NOTE: This needs to be dropped after the `plugin` lock to avoid deadlocks
         let mut init_context = wrapper.make_init_context();
+        let _logger_guard = wrapper.enter_logger();
         let mut plugin = wrapper.plugin.lock();
         if plugin.initialize(&audio_io_layout, &buffer_config, &mut init_context) {
             // NOTE: `Plugin::reset()` is called in `clap_plugin::start_processing()` instead of in
@@ -1900,9 +2313,16 @@ impl<P: ClapPlugin> Wrapper<P> {
             // pointers into a set of `Buffer` objects for the plugin's main and auxiliary IO
             *wrapper.buffer_manager.borrow_mut() =
                 BufferManager::for_audio_io_layout(max_frames_count as usize, audio_io_layout);
+            *wrapper.fixed_block_buffers.borrow_mut() = P::FIXED_BLOCK_SIZE.map(|block_size| {
+                FixedBlockBuffers::new(block_size.get() as usize, audio_io_layout)
+            });
 
             // Also store this for later, so we can reinitialize the plugin after restoring state
-            wrapper.current_buffer_config.store(Some(buffer_config));
+            let old_buffer_config = wrapper.current_buffer_config.swap(Some(buffer_config));
+            if old_buffer_config != Some(buffer_config) {
+                let task_posted = wrapper.schedule_gui(Task::BufferConfigChanged(buffer_config));
+                nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+            }
 
             true
         } else {
@@ -1926,9 +2346,14 @@ impl<P: ClapPlugin> Wrapper<P> {
         // Always reset the processing status when the plugin gets activated or deactivated
         wrapper.last_process_status.store(ProcessStatus::Normal);
         wrapper.is_processing.store(true, Ordering::SeqCst);
+        if let Some(fixed_block_buffers) = wrapper.fixed_block_buffers.borrow_mut().as_mut() {
+            fixed_block_buffers.reset();
+        }
+        *wrapper.transport_jump_detector.borrow_mut() = TransportJumpDetector::default();
 
         // To be consistent with the VST3 wrapper, we'll also reset the buffers here in addition to
         // the dedicated `reset()` function.
+        let _logger_guard = wrapper.enter_logger();
         process_wrapper(|| wrapper.plugin.lock().reset());
 
         true
@@ -1945,6 +2370,10 @@ impl<P: ClapPlugin> Wrapper<P> {
         check_null_ptr!((), plugin, (*plugin).plugin_data);
         let wrapper = &*((*plugin).plugin_data as *const Self);
 
+        if let Some(fixed_block_buffers) = wrapper.fixed_block_buffers.borrow_mut().as_mut() {
+            fixed_block_buffers.reset();
+        }
+        let _logger_guard = wrapper.enter_logger();
         process_wrapper(|| wrapper.plugin.lock().reset());
     }
 
@@ -1955,6 +2384,11 @@ impl<P: ClapPlugin> Wrapper<P> {
         check_null_ptr!(CLAP_PROCESS_ERROR, plugin, (*plugin).plugin_data, process);
         let wrapper = &*((*plugin).plugin_data as *const Self);
 
+        wrapper
+            .automation_recorder
+            .begin_block((*process).frames_count as usize);
+        let _logger_guard = wrapper.enter_logger();
+
         // Panic on allocations if the `assert_process_allocs` feature has been enabled, and make
         // sure that FTZ is set up correctly
         process_wrapper(|| {
@@ -1963,6 +2397,14 @@ impl<P: ClapPlugin> Wrapper<P> {
             // we'll process every incoming event.
             let process = &*process;
             let total_buffer_len = process.frames_count as usize;
+            let _process_load_measurement = wrapper.process_load.start_measurement(
+                total_buffer_len,
+                wrapper
+                    .current_buffer_config
+                    .load()
+                    .map(|c| c.sample_rate)
+                    .unwrap_or_default(),
+            );
 
             let current_audio_io_layout = wrapper.current_audio_io_layout.load();
             let has_main_input = current_audio_io_layout.main_input_channels.is_some();
@@ -1971,17 +2413,34 @@ impl<P: ClapPlugin> Wrapper<P> {
             let aux_output_start_idx = if has_main_output { 1 } else { 0 };
 
             // If `P::SAMPLE_ACCURATE_AUTOMATION` is set, then we'll split up the audio buffer into
-            // chunks whenever a parameter change occurs
+            // chunks whenever a parameter change occurs. The buffer is also split if the host sends
+            // more samples than the maximum buffer size it promised, and at the end of every block
+            // when the plugin uses a fixed block size.
+            let max_buffer_size = wrapper
+                .current_buffer_config
+                .load()
+                .map(|c| c.max_buffer_size)
+                .unwrap_or(process.frames_count);
             let mut block_start = 0;
-            let mut block_end = total_buffer_len;
+            let mut block_end;
             let mut event_start_idx = 0;
+            // The sample index of the next input event that has not yet been handled and that
+            // should split the buffer. Until `block_start` reaches this index there are no new
+            // input events to handle.
+            let mut next_event_split_idx = 0;
 
             // The host may send new transport information as an event. In that case we'll also
             // split the buffer.
             let mut transport_info = process.transport;
 
             let result = loop {
-                if !process.in_events.is_null() {
+                let mut max_block_end =
+                    max_block_end(block_start, total_buffer_len, max_buffer_size);
+                if let Some(fixed_block_buffers) = wrapper.fixed_block_buffers.borrow().as_ref() {
+                    max_block_end =
+                        max_block_end.min(block_start + fixed_block_buffers.samples_remaining());
+                }
+                if !process.in_events.is_null() && block_start >= next_event_split_idx {
                     let split_result = wrapper.handle_in_events_until(
                         &*process.in_events,
                         &mut transport_info,
@@ -1989,6 +2448,14 @@ impl<P: ClapPlugin> Wrapper<P> {
                         total_buffer_len,
                         event_start_idx,
                         |next_event| {
+                            // Events past the maximum block size need to be handled in the next
+                            // block
+                            if max_block_end < total_buffer_len
+                                && (*next_event).time as usize >= max_block_end
+                            {
+                                return true;
+                            }
+
                             // Always split the buffer on transport information changes (tempo, time
                             // signature, or position changes), and also split on parameter value
                             // changes after the current sample if sample accurate automation is
@@ -2025,15 +2492,24 @@ impl<P: ClapPlugin> Wrapper<P> {
                     // process all audio until the end of the buffer.
                     match split_result {
                         Some((next_param_change_sample_idx, next_param_change_event_idx)) => {
-                            block_end = next_param_change_sample_idx;
+                            next_event_split_idx = next_param_change_sample_idx;
                             event_start_idx = next_param_change_event_idx;
                         }
-                        None => block_end = total_buffer_len,
+                        None => next_event_split_idx = total_buffer_len,
+                    }
+                } else {
+                    // Either the host didn't send any events, or this block was split off because
+                    // the previous block exceeded the maximum buffer size and there are no new
+                    // events for it. The events for the previous block should not be sent again.
+                    wrapper.input_events.borrow_mut().clear();
+                    if process.in_events.is_null() {
+                        next_event_split_idx = total_buffer_len;
                     }
                 }
 
                 // After processing the events we now know where/if the block should be split, and
                 // we can start preparing audio processing
+                block_end = next_event_split_idx.min(max_block_end);
                 let block_len = block_end - block_start;
 
                 // The buffer manager preallocated buffer slices for all the IO and storage for any
@@ -2171,11 +2647,9 @@ impl<P: ClapPlugin> Wrapper<P> {
 
                         // This is a bit messy, but we'll try to compensate for the block splitting.
                         // We can't use the functions on the transport information object for this
-                        // because we don't have any sample information.
-                        if P::SAMPLE_ACCURATE_AUTOMATION
-                            && block_start > 0
-                            && (context.flags & CLAP_TRANSPORT_HAS_TEMPO != 0)
-                        {
+                        // because we don't have any sample information. Buffers are also split
+                        // without sample accurate automation, so this always needs to be done.
+                        if block_start > 0 && (context.flags & CLAP_TRANSPORT_HAS_TEMPO != 0) {
                             transport.pos_beats = Some(
                                 beats
                                     + (block_start as f64 / sample_rate as f64 / 60.0
@@ -2188,11 +2662,8 @@ impl<P: ClapPlugin> Wrapper<P> {
                     if context.flags & CLAP_TRANSPORT_HAS_SECONDS_TIMELINE != 0 {
                         let seconds = context.song_pos_seconds as f64 / CLAP_SECTIME_FACTOR as f64;
 
-                        // Same here
-                        if P::SAMPLE_ACCURATE_AUTOMATION
-                            && block_start > 0
-                            && (context.flags & CLAP_TRANSPORT_HAS_TEMPO != 0)
-                        {
+                        // Same here, but this doesn't depend on the tempo
+                        if block_start > 0 {
                             transport.pos_seconds =
                                 Some(seconds + (block_start as f64 / sample_rate as f64));
                         } else {
@@ -2200,7 +2671,7 @@ impl<P: ClapPlugin> Wrapper<P> {
                         }
                     }
                     // TODO: CLAP does not mention whether this is behind a flag or not
-                    if P::SAMPLE_ACCURATE_AUTOMATION && block_start > 0 {
+                    if block_start > 0 {
                         transport.bar_start_pos_beats = match transport.bar_start_pos_beats() {
                             Some(updated) => Some(updated),
                             None => Some(context.bar_start as f64 / CLAP_BEATTIME_FACTOR as f64),
@@ -2234,34 +2705,148 @@ impl<P: ClapPlugin> Wrapper<P> {
                         ));
                     }
                 }
+                let mut transport_jump_detector = wrapper.transport_jump_detector.borrow_mut();
+                transport_jump_detector.update(&mut transport, block_len);
+                // Synthesizers should not keep playing notes after the host's transport stops
+                if P::MIDI_INPUT >= MidiConfig::Basic && transport_jump_detector.playback_stopped()
+                {
+                    wrapper
+                        .input_events
+                        .borrow_mut()
+                        .push_front(NoteEvent::AllNotesOff {
+                            timing: 0,
+                            channel: None,
+                        });
+                }
+                drop(transport_jump_detector);
+
+                let mut fixed_block_buffers = wrapper.fixed_block_buffers.borrow_mut();
+                let result = match (buffer_is_valid, fixed_block_buffers.as_mut()) {
+                    (true, None) => {
+                        let mut plugin = wrapper.plugin.lock();
+                        // SAFETY: Shortening these borrows is safe as even if the plugin overwrites
+                        //         the slices (which it cannot do without using unsafe code), then
+                        //         they would still be reset on the next iteration
+                        let mut aux = AuxiliaryBuffers {
+                            inputs: buffers.aux_inputs,
+                            outputs: buffers.aux_outputs,
+                        };
+                        let mut context = wrapper.make_process_context(transport);
+                        let result = plugin.process(buffers.main_buffer, &mut aux, &mut context);
+                        wrapper.last_process_status.store(result);
+                        result
+                    }
+                    // With a fixed block size the audio and note events are buffered until a full
+                    // block is available. The plugin's output events are delayed along with the
+                    // audio, and they're moved to `output_events` as the host receives that audio.
+                    (true, Some(fixed_block_buffers)) => {
+                        for event in wrapper.input_events.borrow_mut().drain(..) {
+                            fixed_block_buffers.queue_event(event);
+                        }
 
-                let result = if buffer_is_valid {
-                    let mut plugin = wrapper.plugin.lock();
-                    // SAFETY: Shortening these borrows is safe as even if the plugin overwrites the
-                    //         slices (which it cannot do without using unsafe code), then they
-                    //         would still be reset on the next iteration
-                    let mut aux = AuxiliaryBuffers {
-                        inputs: buffers.aux_inputs,
-                        outputs: buffers.aux_outputs,
-                    };
-                    let mut context = wrapper.make_process_context(transport);
-                    let result = plugin.process(buffers.main_buffer, &mut aux, &mut context);
-                    wrapper.last_process_status.store(result);
-                    result
-                } else {
-                    ProcessStatus::Normal
+                        fixed_block_buffers
+                            .process(
+                                buffers.main_buffer,
+                                buffers.aux_inputs,
+                                buffers.aux_outputs,
+                                block_len,
+                                |event| wrapper.output_events.borrow_mut().push_back(event),
+                                |block_buffers, block_input_events, block_output_events| {
+                                    // The process context reads and writes the note events from
+                                    // `input_events` and `output_events`
+                                    std::mem::swap(
+                                        &mut *wrapper.input_events.borrow_mut(),
+                                        block_input_events,
+                                    );
+                                    std::mem::swap(
+                                        &mut *wrapper.output_events.borrow_mut(),
+                                        block_output_events,
+                                    );
+
+                                    let mut plugin = wrapper.plugin.lock();
+                                    let mut aux = AuxiliaryBuffers {
+                                        inputs: block_buffers.aux_inputs,
+                                        outputs: block_buffers.aux_outputs,
+                                    };
+                                    let mut context =
+                                        wrapper.make_process_context(transport.clone());
+                                    let result = plugin.process(
+                                        block_buffers.main_buffer,
+                                        &mut aux,
+                                        &mut context,
+                                    );
+                                    drop(context);
+
+                                    std::mem::swap(
+                                        &mut *wrapper.input_events.borrow_mut(),
+                                        block_input_events,
+                                    );
+                                    std::mem::swap(
+                                        &mut *wrapper.output_events.borrow_mut(),
+                                        block_output_events,
+                                    );
+                                    wrapper.last_process_status.store(result);
+                                    result
+                                },
+                            )
+                            .unwrap_or_else(|| wrapper.last_process_status.load())
+                    }
+                    (false, _) => ProcessStatus::Normal,
                 };
+                drop(fixed_block_buffers);
 
-                let clap_result = match result {
-                    ProcessStatus::Error(err) => {
-                        nih_debug_assert_failure!("Process error: {}", err);
+                if let ProcessStatus::Error(err) = result {
+                    nih_debug_assert_failure!("Process error: {}", err);
 
-                        return CLAP_PROCESS_ERROR;
-                    }
-                    ProcessStatus::Normal => CLAP_PROCESS_CONTINUE_IF_NOT_QUIET,
-                    ProcessStatus::Tail(_) => CLAP_PROCESS_CONTINUE,
-                    ProcessStatus::KeepAlive => CLAP_PROCESS_CONTINUE,
-                };
+                    return CLAP_PROCESS_ERROR;
+                }
+                let clap_result = to_clap_process_status(result);
+
+                // Gestures started by the editor that haven't seen any changes in a while are
+                // ended here so the host doesn't end up with stuck automation lanes
+                wrapper
+                    .gesture_watchdog
+                    .tick(block_len as u32, sample_rate, |param_hash| {
+                        permit_alloc(|| {
+                            nih_warn!(
+                                "The editor never ended the gesture for parameter '{}', ending it \
+                                 now",
+                                wrapper
+                                    .param_id_by_hash
+                                    .get(&param_hash)
+                                    .map(String::as_str)
+                                    .unwrap_or("?")
+                            )
+                        });
+
+                        let success = wrapper
+                            .queue_parameter_event(OutputParamEvent::EndGesture { param_hash });
+                        nih_debug_assert!(
+                            success,
+                            "Parameter output event queue was full, parameter change will not be \
+                             sent to the host"
+                        );
+                    });
+
+                // The gain reduction parameter is a read-only output parameter, so its value is
+                // simply sent to the host without any gestures
+                if let Some(update) = wrapper
+                    .gain_reduction_meter
+                    .tick(block_len as u32, sample_rate)
+                {
+                    let clap_plain_value = update.normalized_value as f64
+                        * update.param_ptr.step_count().unwrap_or(1) as f64;
+                    let success = wrapper.queue_parameter_event(OutputParamEvent::SetValue {
+                        param_hash: update.param_hash,
+                        clap_plain_value,
+                        reset_smoother: true,
+                    });
+                    nih_debug_assert!(
+                        success,
+                        "Parameter output event queue was full, parameter change will not be sent \
+                         to the host"
+                    );
+                }
 
                 // After processing audio, send all spooled events to the host. This include note
                 // events.
@@ -2316,6 +2901,8 @@ impl<P: ClapPlugin> Wrapper<P> {
             &wrapper.clap_plugin_audio_ports_config as *const _ as *const c_void
         } else if id == CLAP_EXT_AUDIO_PORTS {
             &wrapper.clap_plugin_audio_ports as *const _ as *const c_void
+        } else if id == CLAP_EXT_CONTEXT_MENU {
+            &wrapper.clap_plugin_context_menu as *const _ as *const c_void
         } else if id == CLAP_EXT_GUI && wrapper.editor.borrow().is_some() {
             // Only report that we support this extension if the plugin has an editor
             &wrapper.clap_plugin_gui as *const _ as *const c_void
@@ -2325,6 +2912,8 @@ impl<P: ClapPlugin> Wrapper<P> {
             && (P::MIDI_INPUT >= MidiConfig::Basic || P::MIDI_OUTPUT >= MidiConfig::Basic)
         {
             &wrapper.clap_plugin_note_ports as *const _ as *const c_void
+        } else if id == CLAP_EXT_PARAM_INDICATION {
+            &wrapper.clap_plugin_param_indication as *const _ as *const c_void
         } else if id == CLAP_EXT_PARAMS {
             &wrapper.clap_plugin_params as *const _ as *const c_void
         } else if id == CLAP_EXT_REMOTE_CONTROLS {
@@ -2333,8 +2922,21 @@ impl<P: ClapPlugin> Wrapper<P> {
             &wrapper.clap_plugin_render as *const _ as *const c_void
         } else if id == CLAP_EXT_STATE {
             &wrapper.clap_plugin_state as *const _ as *const c_void
+        } else if id == CLAP_EXT_SURROUND
+            && P::AUDIO_IO_LAYOUTS.iter().any(|audio_io_layout| {
+                audio_io_layout
+                    .main_input_layout()
+                    .into_iter()
+                    .chain(audio_io_layout.main_output_layout())
+                    .any(|channel_layout| channel_layout.is_surround())
+            })
+        {
+            // Only report that we support this extension if the plugin has a surround layout
+            &wrapper.clap_plugin_surround as *const _ as *const c_void
         } else if id == CLAP_EXT_TAIL {
             &wrapper.clap_plugin_tail as *const _ as *const c_void
+        } else if id == CLAP_EXT_THREAD_POOL {
+            &wrapper.clap_plugin_thread_pool as *const _ as *const c_void
         } else if id == CLAP_EXT_VOICE_INFO && P::CLAP_POLY_MODULATION_CONFIG.is_some() {
             &wrapper.clap_plugin_voice_info as *const _ as *const c_void
         } else {
@@ -2376,16 +2978,8 @@ impl<P: ClapPlugin> Wrapper<P> {
                 let main_input_channels = audio_io_layout.main_input_channels.map(NonZeroU32::get);
                 let main_output_channels =
                     audio_io_layout.main_output_channels.map(NonZeroU32::get);
-                let input_port_type = match main_input_channels {
-                    Some(1) => CLAP_PORT_MONO.as_ptr(),
-                    Some(2) => CLAP_PORT_STEREO.as_ptr(),
-                    _ => std::ptr::null(),
-                };
-                let output_port_type = match main_output_channels {
-                    Some(1) => CLAP_PORT_MONO.as_ptr(),
-                    Some(2) => CLAP_PORT_STEREO.as_ptr(),
-                    _ => std::ptr::null(),
-                };
+                let input_port_type = to_clap_port_type(audio_io_layout.main_input_layout());
+                let output_port_type = to_clap_port_type(audio_io_layout.main_output_layout());
 
                 *config = std::mem::zeroed();
 
@@ -2531,11 +3125,17 @@ impl<P: ClapPlugin> Wrapper<P> {
             (n, false) => current_audio_io_layout.aux_output_ports[n as usize].get(),
         };
 
-        let port_type = match channel_count {
-            1 => CLAP_PORT_MONO.as_ptr(),
-            2 => CLAP_PORT_STEREO.as_ptr(),
-            _ => std::ptr::null(),
+        // Auxiliary ports don't have channel layouts, so those are only mono or stereo
+        let channel_layout = match (is_input, is_main_port) {
+            (true, true) => current_audio_io_layout.main_input_layout(),
+            (false, true) => current_audio_io_layout.main_output_layout(),
+            (_, false) => match channel_count {
+                1 => Some(ChannelLayout::Mono),
+                2 => Some(ChannelLayout::Stereo),
+                _ => None,
+            },
         };
+        let port_type = to_clap_port_type(channel_layout);
 
         *info = std::mem::zeroed();
 
@@ -2575,6 +3175,54 @@ impl<P: ClapPlugin> Wrapper<P> {
         true
     }
 
+    unsafe extern "C" fn ext_context_menu_populate(
+        plugin: *const clap_plugin,
+        target: *const clap_context_menu_target,
+        builder: *const clap_context_menu_builder,
+    ) -> bool {
+        check_null_ptr!(false, plugin, (*plugin).plugin_data, builder);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        // A null target means the host wants the plugin's global context menu
+        let items = if target.is_null() || (*target).kind == CLAP_CONTEXT_MENU_TARGET_KIND_GLOBAL {
+            &wrapper.context_menu_entries.global
+        } else if (*target).kind == CLAP_CONTEXT_MENU_TARGET_KIND_PARAM {
+            match wrapper
+                .context_menu_entries
+                .by_param_hash
+                .get(&(*target).id)
+            {
+                Some(items) => items,
+                None => return true,
+            }
+        } else {
+            return false;
+        };
+
+        populate_context_menu(builder, items)
+    }
+
+    unsafe extern "C" fn ext_context_menu_perform(
+        plugin: *const clap_plugin,
+        _target: *const clap_context_menu_target,
+        action_id: clap_id,
+    ) -> bool {
+        check_null_ptr!(false, plugin, (*plugin).plugin_data);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        // Action IDs are unique across all menus, so the target is not needed here
+        match wrapper.context_menu_entries.actions.get(action_id as usize) {
+            Some(action) => {
+                action();
+                true
+            }
+            None => {
+                nih_debug_assert_failure!("Unknown context menu action ID {}", action_id);
+                false
+            }
+        }
+    }
+
     unsafe extern "C" fn ext_gui_is_api_supported(
         _plugin: *const clap_plugin,
         api: *const c_char,
@@ -2659,6 +3307,9 @@ impl<P: ClapPlugin> Wrapper<P> {
         let mut editor_handle = wrapper.editor_handle.lock();
         if editor_handle.is_some() {
             *editor_handle = None;
+            if let Some(editor) = wrapper.editor.borrow().as_ref() {
+                editor.lock().on_close();
+            }
         } else {
             nih_debug_assert_failure!("Tried destroying editor while the editor was not active");
         }
@@ -2797,15 +3448,13 @@ impl<P: ClapPlugin> Wrapper<P> {
                 };
 
                 // This extension is only exposed when we have an editor
-                *editor_handle = Some(
-                    wrapper
-                        .editor
-                        .borrow()
-                        .as_ref()
-                        .unwrap()
-                        .lock()
-                        .spawn(parent_handle, wrapper.clone().make_gui_context()),
-                );
+                let editor = wrapper.editor.borrow();
+                let editor = editor.as_ref().unwrap().lock();
+                *editor_handle = Some(EditorHandle::new(
+                    editor.spawn(parent_handle, wrapper.clone().make_gui_context()),
+                    wrapper.editor_open.clone(),
+                ));
+                editor.on_open();
 
                 true
             } else {
@@ -2850,7 +3499,7 @@ impl<P: ClapPlugin> Wrapper<P> {
         check_null_ptr!(0, plugin, (*plugin).plugin_data);
         let wrapper = &*((*plugin).plugin_data as *const Self);
 
-        wrapper.current_latency.load(Ordering::SeqCst)
+        wrapper.current_latency.load(Ordering::SeqCst) + fixed_block_latency::<P>()
     }
 
     unsafe extern "C" fn ext_note_ports_count(_plugin: *const clap_plugin, is_input: bool) -> u32 {
@@ -2875,8 +3524,8 @@ impl<P: ClapPlugin> Wrapper<P> {
                 info.id = 0;
                 // NOTE: REAPER won't send us SysEx if we don't support the MIDI dialect
                 // TODO: Implement MPE (would just be a toggle for the plugin to expose it) and MIDI2
-                info.supported_dialects = CLAP_NOTE_DIALECT_CLAP | CLAP_NOTE_DIALECT_MIDI;
-                info.preferred_dialect = CLAP_NOTE_DIALECT_CLAP;
+                (info.supported_dialects, info.preferred_dialect) =
+                    to_clap_note_dialects(P::CLAP_NOTE_INPUT_DIALECTS);
                 strlcpy(&mut info.name, "Note Input");
 
                 true
@@ -2889,8 +3538,8 @@ impl<P: ClapPlugin> Wrapper<P> {
                 // If `P::MIDI_OUTPUT < MidiConfig::MidiCCs` we'll throw away MIDI CCs, pitch bend
                 // messages, and other messages that are not basic note on, off and polyphonic
                 // pressure messages. This way the behavior is the same as the VST3 wrapper.
-                info.supported_dialects = CLAP_NOTE_DIALECT_CLAP | CLAP_NOTE_DIALECT_MIDI;
-                info.preferred_dialect = CLAP_NOTE_DIALECT_CLAP;
+                (info.supported_dialects, info.preferred_dialect) =
+                    to_clap_note_dialects(P::CLAP_NOTE_OUTPUT_DIALECTS);
                 strlcpy(&mut info.name, "Note Output");
 
                 true
@@ -2923,10 +3572,6 @@ impl<P: ClapPlugin> Wrapper<P> {
         let param_ptr = &wrapper.param_by_hash[param_hash];
         let default_value = param_ptr.default_normalized_value();
         let step_count = param_ptr.step_count();
-        let flags = param_ptr.flags();
-        let automatable = !flags.contains(ParamFlags::NON_AUTOMATABLE);
-        let hidden = flags.contains(ParamFlags::HIDDEN);
-        let is_bypass = flags.contains(ParamFlags::BYPASS);
 
         *param_info = std::mem::zeroed();
 
@@ -2934,23 +3579,11 @@ impl<P: ClapPlugin> Wrapper<P> {
         //       hashmap lookup, but for now we'll stay consistent with the VST3 implementation.
         let param_info = &mut *param_info;
         param_info.id = *param_hash;
-        // TODO: Somehow expose per note/channel/port modulation
-        param_info.flags = 0;
-        if automatable && !hidden {
-            param_info.flags |= CLAP_PARAM_IS_AUTOMATABLE | CLAP_PARAM_IS_MODULATABLE;
-            if wrapper.poly_mod_ids_by_hash.contains_key(param_hash) {
-                param_info.flags |= CLAP_PARAM_IS_MODULATABLE_PER_NOTE_ID;
-            }
-        }
-        if hidden {
-            param_info.flags |= CLAP_PARAM_IS_HIDDEN | CLAP_PARAM_IS_READONLY;
-        }
-        if is_bypass {
-            param_info.flags |= CLAP_PARAM_IS_BYPASS
-        }
-        if step_count.is_some() {
-            param_info.flags |= CLAP_PARAM_IS_STEPPED
-        }
+        param_info.flags = to_clap_param_info_flags(
+            param_ptr.flags(),
+            step_count.is_some(),
+            wrapper.poly_mod_ids_by_hash.contains_key(param_hash),
+        );
         param_info.cookie = std::ptr::null_mut();
         strlcpy(&mut param_info.name, param_ptr.name());
         strlcpy(&mut param_info.module, param_group);
@@ -2977,8 +3610,15 @@ impl<P: ClapPlugin> Wrapper<P> {
 
         match wrapper.param_by_hash.get(&param_id) {
             Some(param_ptr) => {
-                *value = param_ptr.modulated_normalized_value() as f64
-                    * param_ptr.step_count().unwrap_or(1) as f64;
+                // Stepped parameters are always reported in whole steps. See `handle_out_events()`.
+                let step_count = param_ptr.step_count();
+                let clap_plain_value =
+                    param_ptr.modulated_normalized_value() as f64 * step_count.unwrap_or(1) as f64;
+                *value = if step_count.is_some() {
+                    clap_plain_value.round()
+                } else {
+                    clap_plain_value
+                };
 
                 true
             }
@@ -3060,6 +3700,82 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
     }
 
+    unsafe extern "C" fn ext_param_indication_set_mapping(
+        plugin: *const clap_plugin,
+        param_id: clap_id,
+        has_mapping: bool,
+        color: *const clap_color,
+        _label: *const c_char,
+        _description: *const c_char,
+    ) {
+        check_null_ptr!((), plugin, (*plugin).plugin_data);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        // The label and description are not exposed to the plugin since they would need to be
+        // stored in a way that can be read from the editor without locking
+        match wrapper.param_indications.get(&param_id) {
+            Some(indication) => {
+                let mut new_indication = indication.load();
+                new_indication.mapped = has_mapping;
+                new_indication.mapping_color = if has_mapping {
+                    clap_color_to_indication_color(color)
+                } else {
+                    None
+                };
+                indication.store(new_indication);
+
+                let task_posted = wrapper.schedule_gui(Task::ParameterValuesChanged);
+                nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+            }
+            None => nih_debug_assert_failure!(
+                "The host set a mapping indication for an unknown parameter: {}",
+                param_id
+            ),
+        }
+    }
+
+    unsafe extern "C" fn ext_param_indication_set_automation(
+        plugin: *const clap_plugin,
+        param_id: clap_id,
+        automation_state: u32,
+        color: *const clap_color,
+    ) {
+        check_null_ptr!((), plugin, (*plugin).plugin_data);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        let automation = match automation_state {
+            CLAP_PARAM_INDICATION_AUTOMATION_NONE => AutomationIndication::None,
+            CLAP_PARAM_INDICATION_AUTOMATION_PRESENT => AutomationIndication::Present,
+            CLAP_PARAM_INDICATION_AUTOMATION_PLAYING => AutomationIndication::Playing,
+            CLAP_PARAM_INDICATION_AUTOMATION_RECORDING => AutomationIndication::Recording,
+            CLAP_PARAM_INDICATION_AUTOMATION_OVERRIDING => AutomationIndication::Overriding,
+            n => {
+                nih_debug_assert_failure!("Unknown automation indication state '{}'", n);
+                AutomationIndication::None
+            }
+        };
+
+        match wrapper.param_indications.get(&param_id) {
+            Some(indication) => {
+                let mut new_indication = indication.load();
+                new_indication.automation = automation;
+                new_indication.automation_color = if automation != AutomationIndication::None {
+                    clap_color_to_indication_color(color)
+                } else {
+                    None
+                };
+                indication.store(new_indication);
+
+                let task_posted = wrapper.schedule_gui(Task::ParameterValuesChanged);
+                nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+            }
+            None => nih_debug_assert_failure!(
+                "The host set an automation indication for an unknown parameter: {}",
+                param_id
+            ),
+        }
+    }
+
     unsafe extern "C" fn ext_remote_controls_count(plugin: *const clap_plugin) -> u32 {
         check_null_ptr!(0, plugin, (*plugin).plugin_data);
         let wrapper = &*((*plugin).plugin_data as *const Self);
@@ -3192,15 +3908,80 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
     }
 
-    unsafe extern "C" fn ext_tail_get(plugin: *const clap_plugin) -> u32 {
-        check_null_ptr!(0, plugin, (*plugin).plugin_data);
-        let wrapper = &*((*plugin).plugin_data as *const Self);
+    unsafe extern "C" fn ext_surround_is_channel_mask_supported(
+        plugin: *const clap_plugin,
+        channel_mask: u64,
+    ) -> bool {
+        check_null_ptr!(false, plugin, (*plugin).plugin_data);
 
-        match wrapper.last_process_status.load() {
-            ProcessStatus::Tail(samples) => samples,
-            ProcessStatus::KeepAlive => u32::MAX,
-            _ => 0,
-        }
+        // Like the audio ports config, this only depends on `P::AUDIO_IO_LAYOUTS`
+        P::AUDIO_IO_LAYOUTS.iter().any(|audio_io_layout| {
+            audio_io_layout
+                .main_input_layout()
+                .into_iter()
+                .chain(audio_io_layout.main_output_layout())
+                .any(|channel_layout| {
+                    channel_layout.is_surround()
+                        && to_clap_surround_channel_mask(channel_layout) == channel_mask
+                })
+        })
+    }
+
+    unsafe extern "C" fn ext_surround_get_channel_map(
+        plugin: *const clap_plugin,
+        is_input: bool,
+        port_index: u32,
+        channel_map: *mut u8,
+        channel_map_capacity: u32,
+    ) -> u32 {
+        check_null_ptr!(0, plugin, (*plugin).plugin_data, channel_map);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        // Only the main ports can have surround layouts
+        let current_audio_io_layout = wrapper.current_audio_io_layout.load();
+        let channel_layout = match (is_input, port_index) {
+            (true, 0) => current_audio_io_layout.main_input_layout(),
+            (false, 0) => current_audio_io_layout.main_output_layout(),
+            _ => None,
+        };
+        let speakers = match channel_layout {
+            Some(channel_layout) if channel_layout.is_surround() => channel_layout.speakers(),
+            _ => return 0,
+        };
+
+        let channel_map =
+            std::slice::from_raw_parts_mut(channel_map, channel_map_capacity as usize);
+        for (position, &speaker) in channel_map.iter_mut().zip(speakers) {
+            *position = to_clap_surround_position(speaker);
+        }
+
+        speakers.len().min(channel_map_capacity as usize) as u32
+    }
+
+    unsafe extern "C" fn ext_tail_get(plugin: *const clap_plugin) -> u32 {
+        check_null_ptr!(0, plugin, (*plugin).plugin_data);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        tail_length_samples(
+            wrapper.current_tail.load(),
+            wrapper.last_process_status.load(),
+        )
+    }
+
+    unsafe extern "C" fn ext_thread_pool_exec(plugin: *const clap_plugin, task_index: u32) {
+        check_null_ptr!((), plugin, (*plugin).plugin_data);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        let _logger_guard = wrapper.enter_logger();
+        match &*wrapper.thread_pool_task.borrow() {
+            // SAFETY: The pointer is only set while `execute_parallel()` is blocked on the host's
+            //         `request_exec()` call, so the closure it points to is still alive
+            Some(ThreadPoolTask(task)) => process_wrapper(|| (**task)(task_index)),
+            None => nih_debug_assert_failure!(
+                "The host called 'clap_plugin_thread_pool::exec()' outside of a 'request_exec()' \
+                 call"
+            ),
+        }
     }
 
     unsafe extern "C" fn ext_voice_info_get(
@@ -3229,6 +4010,57 @@ impl<P: ClapPlugin> Wrapper<P> {
     }
 }
 
+/// Add `items` to a host's context menu. Returns `false` if the host failed to add one of the items.
+///
+/// # Safety
+///
+/// `builder` must be a valid pointer.
+unsafe fn populate_context_menu(
+    builder: *const clap_context_menu_builder,
+    items: &[ContextMenuItem],
+) -> bool {
+    for item in items {
+        let success = match item {
+            ContextMenuItem::Entry { label, action_id } => {
+                let entry = clap_context_menu_entry {
+                    label: label.as_ptr(),
+                    is_enabled: action_id.is_some(),
+                    action_id: action_id.unwrap_or(CLAP_INVALID_ID),
+                };
+
+                clap_call! { builder=>add_item(builder, CLAP_CONTEXT_MENU_ITEM_ENTRY, &entry as *const _ as *const c_void) }
+            }
+            ContextMenuItem::Title(title) => {
+                let title = clap_context_menu_item_title {
+                    title: title.as_ptr(),
+                    is_enabled: true,
+                };
+
+                clap_call! { builder=>add_item(builder, CLAP_CONTEXT_MENU_ITEM_TITLE, &title as *const _ as *const c_void) }
+            }
+            ContextMenuItem::Separator => {
+                clap_call! { builder=>add_item(builder, CLAP_CONTEXT_MENU_ITEM_SEPARATOR, std::ptr::null()) }
+            }
+            ContextMenuItem::Submenu { label, items } => {
+                let submenu = clap_context_menu_submenu {
+                    label: label.as_ptr(),
+                    is_enabled: true,
+                };
+
+                clap_call! { builder=>add_item(builder, CLAP_CONTEXT_MENU_ITEM_BEGIN_SUBMENU, &submenu as *const _ as *const c_void) }
+                &&populate_context_menu(builder, items)
+                    && clap_call! { builder=>add_item(builder, CLAP_CONTEXT_MENU_ITEM_END_SUBMENU, std::ptr::null()) }
+            }
+        };
+
+        if !success {
+            return false;
+        }
+    }
+
+    true
+}
+
 /// Convenience function to query an extension from the host.
 ///
 /// # Safety
@@ -3246,3 +4078,1332 @@ unsafe fn query_host_extension<T>(
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use clap_sys::audio_buffer::clap_audio_buffer;
+    use clap_sys::ext::context_menu::clap_context_menu_item_kind;
+    use clap_sys::ext::note_ports::{CLAP_NOTE_DIALECT_CLAP, CLAP_NOTE_DIALECT_MIDI};
+    use clap_sys::version::CLAP_VERSION;
+
+    use super::*;
+    use crate::prelude::*;
+    use crate::testing::{EditorCall, ManyParamsPlugin, TestParams, TestPlugin};
+
+    /// The calls the wrapper made to the host.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum HostCall {
+        RequestRestart,
+        RequestProcess,
+        RequestCallback,
+        ParamsRescan(u32),
+        ParamsClear(clap_id),
+        ParamsRequestFlush,
+    }
+
+    /// The parameter events the wrapper sent to the host through a `clap_output_events` list.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum OutputEvent {
+        GestureBegin(clap_id),
+        Value(clap_id, f64),
+        GestureEnd(clap_id),
+        /// Any other event, identified by its type.
+        Other(u16),
+    }
+
+    /// Stands in for the host. `clap_host::host_data` points to this object, so it's boxed to keep
+    /// it at the same address for as long as the wrapper exists.
+    struct TestHost {
+        clap_host: clap_host,
+        clap_host_params: clap_host_params,
+        calls: Mutex<Vec<HostCall>>,
+    }
+
+    impl TestHost {
+        fn new() -> Box<Self> {
+            let mut host = Box::new(Self {
+                clap_host: clap_host {
+                    clap_version: CLAP_VERSION,
+                    host_data: std::ptr::null_mut(),
+                    name: c"Simulated Host".as_ptr(),
+                    vendor: c"NIH-plug".as_ptr(),
+                    url: c"".as_ptr(),
+                    version: c"1.0.0".as_ptr(),
+                    get_extension: Some(Self::get_extension),
+                    request_restart: Some(Self::request_restart),
+                    request_process: Some(Self::request_process),
+                    request_callback: Some(Self::request_callback),
+                },
+                clap_host_params: clap_host_params {
+                    rescan: Some(Self::params_rescan),
+                    clear: Some(Self::params_clear),
+                    request_flush: Some(Self::params_request_flush),
+                },
+                calls: Mutex::new(Vec::new()),
+            });
+            host.clap_host.host_data = &*host as *const Self as *mut c_void;
+
+            host
+        }
+
+        /// Return the calls made to the host so far, and forget about them.
+        fn take_calls(&self) -> Vec<HostCall> {
+            mem::take(&mut *self.calls.lock())
+        }
+
+        unsafe fn from_clap_host<'a>(host: *const clap_host) -> &'a Self {
+            &*((*host).host_data as *const Self)
+        }
+
+        unsafe extern "C" fn get_extension(
+            host: *const clap_host,
+            extension_id: *const c_char,
+        ) -> *const c_void {
+            let this = Self::from_clap_host(host);
+            if CStr::from_ptr(extension_id) == CLAP_EXT_PARAMS {
+                &this.clap_host_params as *const _ as *const c_void
+            } else {
+                std::ptr::null()
+            }
+        }
+
+        unsafe extern "C" fn request_restart(host: *const clap_host) {
+            Self::from_clap_host(host)
+                .calls
+                .lock()
+                .push(HostCall::RequestRestart);
+        }
+
+        unsafe extern "C" fn request_process(host: *const clap_host) {
+            Self::from_clap_host(host)
+                .calls
+                .lock()
+                .push(HostCall::RequestProcess);
+        }
+
+        unsafe extern "C" fn request_callback(host: *const clap_host) {
+            Self::from_clap_host(host)
+                .calls
+                .lock()
+                .push(HostCall::RequestCallback);
+        }
+
+        unsafe extern "C" fn params_rescan(host: *const clap_host, flags: u32) {
+            Self::from_clap_host(host)
+                .calls
+                .lock()
+                .push(HostCall::ParamsRescan(flags));
+        }
+
+        unsafe extern "C" fn params_clear(host: *const clap_host, param_id: clap_id, _flags: u32) {
+            Self::from_clap_host(host)
+                .calls
+                .lock()
+                .push(HostCall::ParamsClear(param_id));
+        }
+
+        unsafe extern "C" fn params_request_flush(host: *const clap_host) {
+            Self::from_clap_host(host)
+                .calls
+                .lock()
+                .push(HostCall::ParamsRequestFlush);
+        }
+    }
+
+    /// The `MidiConfig`s `MidiTestPlugin`'s const generics index into.
+    const MIDI_CONFIGS: [MidiConfig; 3] =
+        [MidiConfig::None, MidiConfig::Basic, MidiConfig::MidiCCs];
+    /// The `RawMidiConfig`s `MidiTestPlugin`'s `RAW` const generic indexes into.
+    const RAW_MIDI_CONFIGS: [RawMidiConfig; 3] = [
+        RawMidiConfig::None,
+        RawMidiConfig::Alongside,
+        RawMidiConfig::Only,
+    ];
+
+    /// A plugin with `MIDI_CONFIGS[INPUT]` as its MIDI input config, `MIDI_CONFIGS[OUTPUT]` as its
+    /// MIDI output config, and `RAW_MIDI_CONFIGS[RAW]` as its raw MIDI input config. The note
+    /// output port only uses the MIDI dialect. SysEx messages are received as raw bytes.
+    #[derive(Default)]
+    struct MidiTestPlugin<const INPUT: usize, const OUTPUT: usize, const RAW: usize = 0> {
+        params: Arc<TestParams>,
+    }
+
+    impl<const INPUT: usize, const OUTPUT: usize, const RAW: usize> Plugin
+        for MidiTestPlugin<INPUT, OUTPUT, RAW>
+    {
+        const NAME: &'static str = "MIDI Test Plugin";
+        const VENDOR: &'static str = "NIH-plug";
+        const URL: &'static str = "";
+        const EMAIL: &'static str = "";
+        const VERSION: &'static str = "0.1.0";
+
+        const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[];
+        const MIDI_INPUT: MidiConfig = MIDI_CONFIGS[INPUT];
+        const MIDI_INPUT_RAW: RawMidiConfig = RAW_MIDI_CONFIGS[RAW];
+        const MIDI_OUTPUT: MidiConfig = MIDI_CONFIGS[OUTPUT];
+
+        type SysExMessage = RawSysEx<16>;
+        type BackgroundTask = ();
+
+        fn params(&self) -> Arc<dyn Params> {
+            self.params.clone()
+        }
+
+        fn process(
+            &mut self,
+            _buffer: &mut Buffer,
+            _aux: &mut AuxiliaryBuffers,
+            _context: &mut impl ProcessContext<Self>,
+        ) -> ProcessStatus {
+            ProcessStatus::Normal
+        }
+    }
+
+    impl<const INPUT: usize, const OUTPUT: usize, const RAW: usize> ClapPlugin
+        for MidiTestPlugin<INPUT, OUTPUT, RAW>
+    {
+        const CLAP_ID: &'static str = "com.nih-plug.test-midi";
+        const CLAP_DESCRIPTION: Option<&'static str> = None;
+        const CLAP_MANUAL_URL: Option<&'static str> = None;
+        const CLAP_SUPPORT_URL: Option<&'static str> = None;
+        const CLAP_FEATURES: &'static [ClapFeature] = &[ClapFeature::NoteEffect];
+
+        const CLAP_NOTE_OUTPUT_DIALECTS: ClapNoteDialects = ClapNoteDialects::MidiOnly;
+    }
+
+    /// The `(id, supported_dialects, preferred_dialect, name)` of every note input or output port
+    /// `P` exposes through the note ports extension.
+    fn note_ports<P: ClapPlugin>(is_input: bool) -> Vec<(clap_id, u32, u32, String)> {
+        // The note ports extension doesn't use the plugin instance
+        let plugin = std::ptr::null();
+        let num_ports = unsafe { Wrapper::<P>::ext_note_ports_count(plugin, is_input) };
+
+        let mut ports = Vec::new();
+        let mut info: clap_note_port_info = unsafe { mem::zeroed() };
+        for index in 0..num_ports {
+            assert!(unsafe {
+                Wrapper::<P>::ext_note_ports_get(plugin, index, is_input, &mut info)
+            });
+            let name = unsafe { CStr::from_ptr(info.name.as_ptr()) };
+            ports.push((
+                info.id,
+                info.supported_dialects,
+                info.preferred_dialect,
+                name.to_str().unwrap().to_owned(),
+            ));
+        }
+        assert!(!unsafe {
+            Wrapper::<P>::ext_note_ports_get(plugin, num_ports, is_input, &mut info)
+        });
+
+        ports
+    }
+
+    /// Send events to the wrapper the same way a host would during processing. Returns the note
+    /// events the plugin would receive.
+    ///
+    /// # Safety
+    ///
+    /// The event pointers need to point to valid events.
+    unsafe fn input_events<P: ClapPlugin>(
+        wrapper: &Wrapper<P>,
+        events: &[*const clap_event_header],
+    ) -> Vec<PluginNoteEvent<P>> {
+        let mut input_events = wrapper.input_events.borrow_mut();
+        input_events.clear();
+        for &event in events {
+            wrapper.handle_in_event(event, &mut input_events, None, 0, events.len());
+        }
+
+        input_events.drain(..).collect()
+    }
+
+    /// Send MIDI messages to the wrapper, one sample apart. Returns the note events the plugin
+    /// would receive.
+    fn midi_input<P: ClapPlugin>(
+        wrapper: &Wrapper<P>,
+        messages: &[[u8; 3]],
+    ) -> Vec<PluginNoteEvent<P>> {
+        let events: Vec<clap_event_midi> = messages
+            .iter()
+            .enumerate()
+            .map(|(time, &data)| clap_event_midi {
+                header: clap_event_header {
+                    size: mem::size_of::<clap_event_midi>() as u32,
+                    time: time as u32,
+                    space_id: CLAP_CORE_EVENT_SPACE_ID,
+                    type_: CLAP_EVENT_MIDI,
+                    flags: 0,
+                },
+                port_index: 0,
+                data,
+            })
+            .collect();
+        let headers: Vec<*const clap_event_header> = events
+            .iter()
+            .map(|event| &event.header as *const _)
+            .collect();
+
+        unsafe { input_events(wrapper, &headers) }
+    }
+
+    /// Process `num_samples` samples of silence with a stereo input and output like a host would,
+    /// optionally using `transport` as the transport information and `in_events` as the input
+    /// events. The plugin needs to be activated first.
+    fn process_silence<P: ClapPlugin>(
+        plugin: *const clap_plugin,
+        num_samples: usize,
+        transport: Option<&clap_event_transport>,
+        in_events: Option<&clap_input_events>,
+    ) -> clap_process_status {
+        let mut input = vec![vec![0.0f32; num_samples]; 2];
+        let mut output = vec![vec![0.0f32; num_samples]; 2];
+        let mut input_ptrs: Vec<*mut f32> = input.iter_mut().map(|c| c.as_mut_ptr()).collect();
+        let mut output_ptrs: Vec<*mut f32> = output.iter_mut().map(|c| c.as_mut_ptr()).collect();
+        let audio_input = clap_audio_buffer {
+            data32: input_ptrs.as_mut_ptr(),
+            data64: std::ptr::null_mut(),
+            channel_count: 2,
+            latency: 0,
+            constant_mask: 0,
+        };
+        let mut audio_output = clap_audio_buffer {
+            data32: output_ptrs.as_mut_ptr(),
+            data64: std::ptr::null_mut(),
+            channel_count: 2,
+            latency: 0,
+            constant_mask: 0,
+        };
+        let process = clap_process {
+            steady_time: -1,
+            frames_count: num_samples as u32,
+            transport: transport.map_or(std::ptr::null(), |transport| transport as *const _),
+            audio_inputs: &audio_input,
+            audio_outputs: &mut audio_output,
+            audio_inputs_count: 1,
+            audio_outputs_count: 1,
+            in_events: in_events.map_or(std::ptr::null(), |in_events| in_events as *const _),
+            out_events: std::ptr::null(),
+        };
+
+        unsafe { Wrapper::<P>::process(plugin, &process) }
+    }
+
+    /// Parameter value events at the start of the buffer, like a host would send for automation.
+    struct ParamValueEvents(Vec<clap_event_param_value>);
+
+    impl ParamValueEvents {
+        /// Set the parameters with these hashes to these CLAP plain values.
+        fn new(values: &[(clap_id, f64)]) -> Self {
+            Self(
+                values
+                    .iter()
+                    .map(|&(param_id, value)| clap_event_param_value {
+                        header: clap_event_header {
+                            size: mem::size_of::<clap_event_param_value>() as u32,
+                            time: 0,
+                            space_id: CLAP_CORE_EVENT_SPACE_ID,
+                            type_: CLAP_EVENT_PARAM_VALUE,
+                            flags: 0,
+                        },
+                        param_id,
+                        cookie: std::ptr::null_mut(),
+                        note_id: -1,
+                        port_index: -1,
+                        channel: -1,
+                        key: -1,
+                        value,
+                    })
+                    .collect(),
+            )
+        }
+
+        /// A `clap_input_events` list containing these events. The list points to this object, so
+        /// it must not outlive it.
+        fn as_clap_input_events(&self) -> clap_input_events {
+            clap_input_events {
+                ctx: self as *const Self as *mut c_void,
+                size: Some(Self::size),
+                get: Some(Self::get),
+            }
+        }
+
+        unsafe extern "C" fn size(list: *const clap_input_events) -> u32 {
+            (*((*list).ctx as *const Self)).0.len() as u32
+        }
+
+        unsafe extern "C" fn get(
+            list: *const clap_input_events,
+            index: u32,
+        ) -> *const clap_event_header {
+            &(*((*list).ctx as *const Self)).0[index as usize].header
+        }
+    }
+
+    /// Create a wrapper for `host` and initialize it like a host would. Returns the wrapper and the
+    /// `clap_plugin` pointer the host would use. `host` must outlive the wrapper.
+    fn initialized_wrapper<P: ClapPlugin>(
+        host: &TestHost,
+    ) -> (Arc<Wrapper<P>>, *const clap_plugin) {
+        let wrapper = unsafe { Wrapper::<P>::new(&host.clap_host) };
+        let plugin = wrapper.clap_plugin.as_ptr() as *const clap_plugin;
+        assert!(unsafe { Wrapper::<P>::init(plugin) });
+
+        (wrapper, plugin)
+    }
+
+    unsafe extern "C" fn record_output_event(
+        list: *const clap_output_events,
+        event: *const clap_event_header,
+    ) -> bool {
+        let events = &mut *((*list).ctx as *mut Vec<OutputEvent>);
+        let event = match ((*event).space_id, (*event).type_) {
+            (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_PARAM_GESTURE_BEGIN) => {
+                OutputEvent::GestureBegin((*(event as *const clap_event_param_gesture)).param_id)
+            }
+            (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_PARAM_VALUE) => {
+                let event = &*(event as *const clap_event_param_value);
+                OutputEvent::Value(event.param_id, event.value)
+            }
+            (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_PARAM_GESTURE_END) => {
+                OutputEvent::GestureEnd((*(event as *const clap_event_param_gesture)).param_id)
+            }
+            (_, type_) => OutputEvent::Other(type_),
+        };
+        events.push(event);
+
+        true
+    }
+
+    /// Call `clap_plugin_params::flush()` without any input events, like a host would after the
+    /// plugin requested a flush. Returns the events the plugin sent to the host.
+    fn flush<P: ClapPlugin>(plugin: *const clap_plugin) -> Vec<OutputEvent> {
+        let mut events: Vec<OutputEvent> = Vec::new();
+        let out = clap_output_events {
+            ctx: &mut events as *mut Vec<OutputEvent> as *mut c_void,
+            try_push: Some(record_output_event),
+        };
+        unsafe { Wrapper::<P>::ext_params_flush(plugin, std::ptr::null(), &out) };
+
+        events
+    }
+
+    #[test]
+    fn stepped_float_params_report_whole_steps() {
+        let host = TestHost::new();
+        let (wrapper, plugin) = initialized_wrapper::<TestPlugin>(&host);
+        let context = wrapper.clone().make_gui_context();
+        let params = wrapper.plugin.lock().params.clone();
+        let hash = hash_param_id("pitch");
+
+        let setter = ParamSetter::new(context.as_ref());
+        setter.begin_set_parameter(&params.pitch);
+        setter.set_parameter(&params.pitch, 1.5);
+        setter.end_set_parameter(&params.pitch);
+        assert!(host.take_calls().contains(&HostCall::ParamsRequestFlush));
+
+        // The parameter stays continuous, but the host only ever sees whole steps
+        assert_eq!(
+            flush::<TestPlugin>(plugin),
+            [
+                OutputEvent::GestureBegin(hash),
+                OutputEvent::Value(hash, 2.0),
+                OutputEvent::GestureEnd(hash),
+            ]
+        );
+        assert_eq!(params.pitch.value(), 1.5);
+
+        let mut value = 0.0;
+        assert!(unsafe { Wrapper::<TestPlugin>::ext_params_get_value(plugin, hash, &mut value) });
+        assert_eq!(value, 2.0);
+    }
+
+    #[test]
+    fn batch_parameter_changes_are_flushed_at_once() {
+        let host = TestHost::new();
+        let (wrapper, plugin) = initialized_wrapper::<ManyParamsPlugin>(&host);
+        let context = wrapper.clone().make_gui_context();
+        let params = wrapper.plugin.lock().params.clone();
+        host.take_calls();
+
+        let preset: Vec<(ParamPtr, f32)> = params
+            .params
+            .iter()
+            .map(|param| (param.as_ptr(), 0.5))
+            .collect();
+        ParamSetter::new(context.as_ref()).set_parameters_normalized(&preset, true);
+
+        // Loading the preset only requests a single flush, and every parameter is sent to the host
+        // in its own gesture
+        assert_eq!(host.take_calls(), [HostCall::ParamsRequestFlush]);
+        let expected_events: Vec<OutputEvent> = (0..params.params.len())
+            .flat_map(|i| {
+                let hash = hash_param_id(&format!("param_{i}"));
+                [
+                    OutputEvent::GestureBegin(hash),
+                    OutputEvent::Value(hash, 0.5),
+                    OutputEvent::GestureEnd(hash),
+                ]
+            })
+            .collect();
+        assert_eq!(flush::<ManyParamsPlugin>(plugin), expected_events);
+        assert!(params.params.iter().all(|param| param.value() == 0.5));
+
+        // Everything was sent in that one flush
+        assert!(flush::<ManyParamsPlugin>(plugin).is_empty());
+        assert!(host.take_calls().is_empty());
+    }
+
+    #[test]
+    fn batch_parameter_changes_are_not_partially_queued() {
+        let host = TestHost::new();
+        let (wrapper, plugin) = initialized_wrapper::<ManyParamsPlugin>(&host);
+        let params = wrapper.plugin.lock().params.clone();
+        let hash = hash_param_id("param_0");
+
+        // Leave room for only part of the batch's events
+        while wrapper.output_parameter_events.len() < OUTPUT_EVENT_QUEUE_CAPACITY - 10 {
+            assert!(wrapper.queue_parameter_event(OutputParamEvent::SetValue {
+                param_hash: hash,
+                clap_plain_value: 0.25,
+                reset_smoother: false,
+            }));
+        }
+
+        let batch: Vec<OutputParamEvent> = (0..params.params.len())
+            .flat_map(|i| {
+                let param_hash = hash_param_id(&format!("param_{i}"));
+                [
+                    OutputParamEvent::BeginGesture { param_hash },
+                    OutputParamEvent::SetValue {
+                        param_hash,
+                        clap_plain_value: 0.5,
+                        reset_smoother: false,
+                    },
+                    OutputParamEvent::EndGesture { param_hash },
+                ]
+            })
+            .collect();
+        assert!(!wrapper.queue_parameter_events(batch));
+
+        // None of the batch's gestures may be left dangling
+        let events = flush::<ManyParamsPlugin>(plugin);
+        assert_eq!(events.len(), OUTPUT_EVENT_QUEUE_CAPACITY - 10);
+        assert!(events
+            .iter()
+            .all(|event| *event == OutputEvent::Value(hash, 0.25)));
+        assert!(params.params[1..].iter().all(|param| param.value() == 0.0));
+
+        // Once there's room again the batch can be queued as a whole
+        let batch = [
+            OutputParamEvent::BeginGesture { param_hash: hash },
+            OutputParamEvent::SetValue {
+                param_hash: hash,
+                clap_plain_value: 0.5,
+                reset_smoother: false,
+            },
+            OutputParamEvent::EndGesture { param_hash: hash },
+        ];
+        assert!(wrapper.queue_parameter_events(batch));
+        assert_eq!(
+            flush::<ManyParamsPlugin>(plugin),
+            [
+                OutputEvent::GestureBegin(hash),
+                OutputEvent::Value(hash, 0.5),
+                OutputEvent::GestureEnd(hash),
+            ]
+        );
+    }
+
+    #[test]
+    fn param_indications() {
+        let host = TestHost::new();
+        let (wrapper, plugin) = initialized_wrapper::<TestPlugin>(&host);
+        let context = wrapper.clone().make_gui_context();
+        let params = wrapper.plugin.lock().params.clone();
+        let gain_hash = hash_param_id("gain");
+        let color = clap_color {
+            alpha: 255,
+            red: 200,
+            green: 100,
+            blue: 0,
+        };
+        let indication_color = IndicationColor {
+            red: 200,
+            green: 100,
+            blue: 0,
+            alpha: 255,
+        };
+        assert!(context.param_indication(params.gain.as_ptr()).is_none());
+
+        unsafe {
+            Wrapper::<TestPlugin>::ext_param_indication_set_mapping(
+                plugin,
+                gain_hash,
+                true,
+                &color,
+                c"Knob 1".as_ptr(),
+                std::ptr::null(),
+            );
+            Wrapper::<TestPlugin>::ext_param_indication_set_automation(
+                plugin,
+                gain_hash,
+                CLAP_PARAM_INDICATION_AUTOMATION_PLAYING,
+                std::ptr::null(),
+            );
+        }
+        assert_eq!(
+            context.param_indication(params.gain.as_ptr()),
+            ParamIndication {
+                automation: AutomationIndication::Playing,
+                automation_color: None,
+                mapped: true,
+                mapping_color: Some(indication_color),
+            }
+        );
+        // The other parameters are not affected
+        assert!(context.param_indication(params.pitch.as_ptr()).is_none());
+
+        // Removing the mapping or the automation also clears the colors
+        unsafe {
+            Wrapper::<TestPlugin>::ext_param_indication_set_automation(
+                plugin,
+                gain_hash,
+                CLAP_PARAM_INDICATION_AUTOMATION_RECORDING,
+                &color,
+            );
+            Wrapper::<TestPlugin>::ext_param_indication_set_mapping(
+                plugin,
+                gain_hash,
+                false,
+                &color,
+                std::ptr::null(),
+                std::ptr::null(),
+            );
+        }
+        assert_eq!(
+            context.param_indication(params.gain.as_ptr()),
+            ParamIndication {
+                automation: AutomationIndication::Recording,
+                automation_color: Some(indication_color),
+                mapped: false,
+                mapping_color: None,
+            }
+        );
+
+        unsafe {
+            Wrapper::<TestPlugin>::ext_param_indication_set_automation(
+                plugin,
+                gain_hash,
+                CLAP_PARAM_INDICATION_AUTOMATION_NONE,
+                &color,
+            );
+        }
+        assert_eq!(
+            context.param_indication(params.gain.as_ptr()),
+            ParamIndication::default()
+        );
+    }
+
+    #[test]
+    fn note_ports_for_midi_configs() {
+        let input_port = (
+            0,
+            CLAP_NOTE_DIALECT_CLAP | CLAP_NOTE_DIALECT_MIDI,
+            CLAP_NOTE_DIALECT_CLAP,
+            String::from("Note Input"),
+        );
+        let output_port = (
+            0,
+            CLAP_NOTE_DIALECT_MIDI,
+            CLAP_NOTE_DIALECT_MIDI,
+            String::from("Note Output"),
+        );
+
+        // `MidiConfig::None` doesn't get a port, and the other configs all get the same port
+        assert!(note_ports::<MidiTestPlugin<0, 0>>(true).is_empty());
+        assert!(note_ports::<MidiTestPlugin<0, 0>>(false).is_empty());
+
+        assert_eq!(
+            note_ports::<MidiTestPlugin<1, 0>>(true),
+            [input_port.clone()]
+        );
+        assert!(note_ports::<MidiTestPlugin<1, 0>>(false).is_empty());
+        assert!(note_ports::<MidiTestPlugin<0, 1>>(true).is_empty());
+        assert_eq!(
+            note_ports::<MidiTestPlugin<0, 1>>(false),
+            [output_port.clone()]
+        );
+
+        assert_eq!(note_ports::<MidiTestPlugin<2, 2>>(true), [input_port]);
+        assert_eq!(note_ports::<MidiTestPlugin<2, 2>>(false), [output_port]);
+    }
+
+    #[test]
+    fn raw_midi_input() {
+        let host = TestHost::new();
+        // A note on, a CC, and a MIDI message NIH-plug doesn't parse
+        let messages = [[0x91, 60, 127], [0xb0, 1, 127], [0xf8, 0, 0]];
+        let note_on = NoteEvent::NoteOn {
+            timing: 0,
+            voice_id: None,
+            channel: 1,
+            note: 60,
+            velocity: 1.0,
+        };
+        let raw_events = messages
+            .iter()
+            .enumerate()
+            .map(|(timing, &data)| NoteEvent::MidiRaw {
+                timing: timing as u32,
+                data,
+            });
+
+        // Without raw MIDI, only the parsed events are sent
+        let (wrapper, _) = initialized_wrapper::<MidiTestPlugin<2, 0>>(&host);
+        assert_eq!(
+            midi_input(&wrapper, &messages[..2]),
+            [
+                note_on,
+                NoteEvent::MidiCC {
+                    timing: 1,
+                    channel: 0,
+                    cc: 1,
+                    value: 1.0,
+                },
+            ]
+        );
+
+        // With `RawMidiConfig::Alongside`, the raw event comes directly before the parsed event.
+        // The CC is not parsed because the plugin only uses `MidiConfig::Basic`.
+        let (wrapper, _) = initialized_wrapper::<MidiTestPlugin<1, 0, 1>>(&host);
+        let mut expected_events: Vec<_> = raw_events.clone().collect();
+        expected_events.insert(1, note_on);
+        assert_eq!(midi_input(&wrapper, &messages), expected_events);
+
+        // With `RawMidiConfig::Only`, messages are never parsed
+        let (wrapper, _) = initialized_wrapper::<MidiTestPlugin<1, 0, 2>>(&host);
+        assert_eq!(
+            midi_input(&wrapper, &messages),
+            raw_events.collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn raw_sysex_input() {
+        let host = TestHost::new();
+        let sysex_data = [0xf0, 0x41, 0x10, 0x42, 0x12, 0x40, 0x00, 0x41, 0xf7];
+        let sysex_event = clap_event_midi_sysex {
+            header: clap_event_header {
+                size: mem::size_of::<clap_event_midi_sysex>() as u32,
+                time: 0,
+                space_id: CLAP_CORE_EVENT_SPACE_ID,
+                type_: CLAP_EVENT_MIDI_SYSEX,
+                flags: 0,
+            },
+            port_index: 0,
+            buffer: sysex_data.as_ptr(),
+            size: sysex_data.len() as u32,
+        };
+        let midi_event = clap_event_midi {
+            header: clap_event_header {
+                size: mem::size_of::<clap_event_midi>() as u32,
+                time: 0,
+                space_id: CLAP_CORE_EVENT_SPACE_ID,
+                type_: CLAP_EVENT_MIDI,
+                flags: 0,
+            },
+            port_index: 0,
+            data: [0xb0, 1, 127],
+        };
+
+        // The SysEx message's bytes arrive unchanged and in the same order as the other messages,
+        // even when the plugin doesn't parse any MIDI
+        let (wrapper, _) = initialized_wrapper::<MidiTestPlugin<1, 0, 2>>(&host);
+        let headers: [*const clap_event_header; 3] =
+            [&sysex_event.header, &midi_event.header, &sysex_event.header];
+        let events = unsafe { input_events(&wrapper, &headers) };
+        let sysex = NoteEvent::MidiSysEx {
+            timing: 0,
+            message: RawSysEx::new(&sysex_data).unwrap(),
+        };
+        assert_eq!(
+            events,
+            [
+                sysex,
+                NoteEvent::MidiRaw {
+                    timing: 0,
+                    data: [0xb0, 1, 127],
+                },
+                sysex,
+            ]
+        );
+        match events[0] {
+            NoteEvent::MidiSysEx { message, .. } => assert_eq!(message.as_bytes(), sysex_data),
+            event => panic!("Unexpected event: {event:?}"),
+        }
+    }
+
+    #[test]
+    fn all_notes_off_and_all_sound_off() {
+        let host = TestHost::new();
+        // MIDI CC 120 and 123 on the second channel
+        let messages = [[0xb1, 120, 0], [0xb1, 123, 0]];
+        let expected_events = [
+            NoteEvent::AllSoundOff {
+                timing: 0,
+                channel: Some(1),
+            },
+            NoteEvent::AllNotesOff {
+                timing: 1,
+                channel: Some(1),
+            },
+        ];
+
+        // These are sent to plugins that don't receive MIDI CCs, and plugins that do receive MIDI
+        // CCs no longer receive them as `MidiCC` events
+        let (wrapper, _) = initialized_wrapper::<MidiTestPlugin<1, 0>>(&host);
+        assert_eq!(midi_input(&wrapper, &messages), expected_events);
+        let (wrapper, _) = initialized_wrapper::<MidiTestPlugin<2, 0>>(&host);
+        assert_eq!(midi_input(&wrapper, &messages), expected_events);
+
+        // CLAP note off and choke events that target all keys are translated to the same events
+        let note_event = |type_, channel| clap_event_note {
+            header: clap_event_header {
+                size: mem::size_of::<clap_event_note>() as u32,
+                time: 0,
+                space_id: CLAP_CORE_EVENT_SPACE_ID,
+                type_,
+                flags: 0,
+            },
+            note_id: -1,
+            port_index: 0,
+            channel,
+            key: -1,
+            velocity: 0.0,
+        };
+        let events = [
+            note_event(CLAP_EVENT_NOTE_OFF, -1),
+            note_event(CLAP_EVENT_NOTE_CHOKE, 3),
+        ];
+        let headers = events.each_ref().map(|event| &event.header as *const _);
+        assert_eq!(
+            unsafe { input_events(&wrapper, &headers) },
+            [
+                NoteEvent::AllNotesOff {
+                    timing: 0,
+                    channel: None,
+                },
+                NoteEvent::AllSoundOff {
+                    timing: 0,
+                    channel: Some(3),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn split_blocks_continue_the_transport_position() {
+        let host = TestHost::new();
+        let (wrapper, plugin) = initialized_wrapper::<TestPlugin>(&host);
+        unsafe {
+            assert!(Wrapper::<TestPlugin>::activate(plugin, 48000.0, 1, 64));
+            assert!(Wrapper::<TestPlugin>::start_processing(plugin));
+        }
+
+        // Playing at 120 BPM, starting two seconds in
+        let mut transport: clap_event_transport = unsafe { mem::zeroed() };
+        transport.flags = CLAP_TRANSPORT_IS_PLAYING
+            | CLAP_TRANSPORT_HAS_TEMPO
+            | CLAP_TRANSPORT_HAS_BEATS_TIMELINE
+            | CLAP_TRANSPORT_HAS_SECONDS_TIMELINE;
+        transport.tempo = 120.0;
+        transport.song_pos_beats = 4 * CLAP_BEATTIME_FACTOR;
+        transport.song_pos_seconds = 2 * CLAP_SECTIME_FACTOR;
+
+        // The plugin doesn't use sample accurate automation, but the buffer is still split in two
+        // because it's larger than the maximum buffer size. The second block should continue where
+        // the first block ended instead of being reported as a jump back to the start.
+        process_silence::<TestPlugin>(plugin, 128, Some(&transport), None);
+        let plugin = wrapper.plugin.lock();
+        assert_eq!(plugin.process_calls, [(2, 64), (2, 64)]);
+        assert_eq!(plugin.process_transports.len(), 2);
+
+        let first_block = &plugin.process_transports[0];
+        assert_eq!(first_block.pos_seconds, Some(2.0));
+        assert_eq!(first_block.pos_beats, Some(4.0));
+        assert_eq!(first_block.jump, None);
+
+        let second_block = &plugin.process_transports[1];
+        approx::assert_relative_eq!(second_block.pos_seconds.unwrap(), 2.0 + (64.0 / 48000.0));
+        approx::assert_relative_eq!(second_block.pos_beats.unwrap(), 4.0 + (64.0 / 24000.0));
+        assert_eq!(second_block.jump, None);
+    }
+
+    #[test]
+    fn buffer_config_changes_reach_the_editor() {
+        let host = TestHost::new();
+        let (wrapper, plugin) = initialized_wrapper::<TestPlugin>(&host);
+        let editor_calls = wrapper.plugin.lock().editor_calls.clone();
+
+        let mut window: clap_window = unsafe { mem::zeroed() };
+        window.api = CLAP_WINDOW_API_X11.as_ptr();
+        assert!(unsafe { Wrapper::<TestPlugin>::ext_gui_set_parent(plugin, &window) });
+
+        // Reactivating the plugin with the same buffer config doesn't notify the editor
+        for sample_rate in [44100.0, 48000.0, 48000.0] {
+            unsafe {
+                assert!(Wrapper::<TestPlugin>::activate(plugin, sample_rate, 1, 512));
+                Wrapper::<TestPlugin>::deactivate(plugin);
+            }
+        }
+
+        let initialize_calls = wrapper.plugin.lock().initialize_calls.clone();
+        assert_eq!(initialize_calls.len(), 3);
+        assert_eq!(initialize_calls[0].1.sample_rate, 44100.0);
+        assert_eq!(initialize_calls[1].1.sample_rate, 48000.0);
+        assert_eq!(
+            *editor_calls.lock(),
+            [
+                EditorCall::Spawn,
+                EditorCall::BufferConfigChanged(initialize_calls[0].1),
+                EditorCall::BufferConfigChanged(initialize_calls[1].1),
+            ]
+        );
+    }
+
+    #[test]
+    fn oversized_buffers_are_split() {
+        let host = TestHost::new();
+        let (wrapper, plugin) = initialized_wrapper::<TestPlugin>(&host);
+        unsafe {
+            assert!(Wrapper::<TestPlugin>::activate(plugin, 48000.0, 1, 64));
+            assert!(Wrapper::<TestPlugin>::start_processing(plugin));
+        }
+
+        // The host promised to never send more than 64 samples at a time
+        process_silence::<TestPlugin>(plugin, 32, None, None);
+        process_silence::<TestPlugin>(plugin, 150, None, None);
+        assert_eq!(
+            wrapper.plugin.lock().process_calls,
+            [(2, 32), (2, 64), (2, 64), (2, 22)]
+        );
+    }
+
+    #[test]
+    fn tail_follows_parameter_changes() {
+        let host = TestHost::new();
+        let (wrapper, plugin) = initialized_wrapper::<TestPlugin>(&host);
+        wrapper.plugin.lock().report_tail = true;
+        unsafe {
+            assert!(Wrapper::<TestPlugin>::activate(plugin, 48000.0, 1, 64));
+            assert!(Wrapper::<TestPlugin>::start_processing(plugin));
+        }
+
+        // The plugin reports a tail of `gain * 1000` samples
+        for (gain, tail) in [(0.75, 750), (0.25, 250)] {
+            let events = ParamValueEvents::new(&[(hash_param_id("gain"), gain)]);
+            process_silence::<TestPlugin>(plugin, 32, None, Some(&events.as_clap_input_events()));
+            assert_eq!(unsafe { Wrapper::<TestPlugin>::ext_tail_get(plugin) }, tail);
+        }
+    }
+
+    #[test]
+    fn render_mode_is_reported_to_process() {
+        let host = TestHost::new();
+        let (wrapper, plugin) = initialized_wrapper::<TestPlugin>(&host);
+        unsafe {
+            assert!(Wrapper::<TestPlugin>::activate(plugin, 48000.0, 1, 64));
+            assert!(Wrapper::<TestPlugin>::start_processing(plugin));
+        }
+
+        // The host switches to offline rendering for a bounce and back again while the plugin
+        // stays active
+        process_silence::<TestPlugin>(plugin, 32, None, None);
+        assert!(unsafe { Wrapper::<TestPlugin>::ext_render_set(plugin, CLAP_RENDER_OFFLINE) });
+        process_silence::<TestPlugin>(plugin, 32, None, None);
+        assert!(unsafe { Wrapper::<TestPlugin>::ext_render_set(plugin, CLAP_RENDER_REALTIME) });
+        process_silence::<TestPlugin>(plugin, 32, None, None);
+
+        assert_eq!(
+            wrapper.plugin.lock().process_modes,
+            [
+                ProcessMode::Realtime,
+                ProcessMode::Offline,
+                ProcessMode::Realtime
+            ]
+        );
+    }
+
+    #[test]
+    fn automation_is_reported_as_changed_params() {
+        let host = TestHost::new();
+        let (wrapper, plugin) = initialized_wrapper::<TestPlugin>(&host);
+        unsafe {
+            assert!(Wrapper::<TestPlugin>::activate(plugin, 48000.0, 1, 64));
+            assert!(Wrapper::<TestPlugin>::start_processing(plugin));
+        }
+
+        // The first block reports every parameter as changed. The automation in the second block
+        // sets the pitch parameter to its current value, so only the gain parameter changed. The
+        // third block doesn't contain any automation.
+        process_silence::<TestPlugin>(plugin, 32, None, None);
+        let events =
+            ParamValueEvents::new(&[(hash_param_id("gain"), 0.75), (hash_param_id("pitch"), 0.0)]);
+        process_silence::<TestPlugin>(plugin, 32, None, Some(&events.as_clap_input_events()));
+        process_silence::<TestPlugin>(plugin, 32, None, None);
+
+        let plugin = wrapper.plugin.lock();
+        assert_eq!(
+            plugin.process_changed_params,
+            [vec!["gain", "gr", "pitch"], vec!["gain"], vec![]]
+        );
+        assert_eq!(plugin.params.gain.value(), 0.75);
+    }
+
+    /// Parameters with a group in the middle and parameters that should be left out of the remote
+    /// control pages.
+    struct GroupedParams {
+        gain: FloatParam,
+        bypass: BoolParam,
+        hidden: FloatParam,
+        cutoff: FloatParam,
+        resonance: FloatParam,
+        mix: FloatParam,
+    }
+
+    unsafe impl Params for GroupedParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            vec![
+                (String::from("gain"), self.gain.as_ptr(), String::new()),
+                (String::from("bypass"), self.bypass.as_ptr(), String::new()),
+                (String::from("hidden"), self.hidden.as_ptr(), String::new()),
+                (
+                    String::from("cutoff"),
+                    self.cutoff.as_ptr(),
+                    String::from("Filter"),
+                ),
+                (
+                    String::from("res"),
+                    self.resonance.as_ptr(),
+                    String::from("Filter"),
+                ),
+                (String::from("mix"), self.mix.as_ptr(), String::new()),
+            ]
+        }
+    }
+
+    impl Default for GroupedParams {
+        fn default() -> Self {
+            let range = FloatRange::Linear { min: 0.0, max: 1.0 };
+
+            Self {
+                gain: FloatParam::new("Gain", 0.5, range),
+                bypass: BoolParam::new("Bypass", false).make_bypass(),
+                hidden: FloatParam::new("Hidden", 0.5, range).hide(),
+                cutoff: FloatParam::new("Cutoff", 0.5, range),
+                resonance: FloatParam::new("Resonance", 0.5, range),
+                mix: FloatParam::new("Mix", 1.0, range),
+            }
+        }
+    }
+
+    /// A plugin with [`GroupedParams`] that doesn't define its own remote control pages.
+    #[derive(Default)]
+    struct GroupedParamsPlugin {
+        params: Arc<GroupedParams>,
+    }
+
+    impl Plugin for GroupedParamsPlugin {
+        const NAME: &'static str = "Grouped Params Test Plugin";
+        const VENDOR: &'static str = "NIH-plug";
+        const URL: &'static str = "";
+        const EMAIL: &'static str = "";
+        const VERSION: &'static str = "0.1.0";
+
+        const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[];
+
+        type SysExMessage = ();
+        type BackgroundTask = ();
+
+        fn params(&self) -> Arc<dyn Params> {
+            self.params.clone()
+        }
+
+        fn process(
+            &mut self,
+            _buffer: &mut Buffer,
+            _aux: &mut AuxiliaryBuffers,
+            _context: &mut impl ProcessContext<Self>,
+        ) -> ProcessStatus {
+            ProcessStatus::Normal
+        }
+    }
+
+    impl ClapPlugin for GroupedParamsPlugin {
+        const CLAP_ID: &'static str = "com.nih-plug.test-grouped-params";
+        const CLAP_DESCRIPTION: Option<&'static str> = None;
+        const CLAP_MANUAL_URL: Option<&'static str> = None;
+        const CLAP_SUPPORT_URL: Option<&'static str> = None;
+        const CLAP_FEATURES: &'static [ClapFeature] = &[ClapFeature::AudioEffect];
+    }
+
+    /// The `(section_name, page_name, param_ids)` of every remote control page `P` exposes through
+    /// the remote controls extension. Empty parameter slots are left out of `param_ids`.
+    fn remote_control_pages<P: ClapPlugin>(
+        plugin: *const clap_plugin,
+    ) -> Vec<(String, String, Vec<clap_id>)> {
+        let num_pages = unsafe { Wrapper::<P>::ext_remote_controls_count(plugin) };
+
+        let mut pages = Vec::new();
+        let mut page: clap_remote_controls_page = unsafe { mem::zeroed() };
+        for index in 0..num_pages {
+            assert!(unsafe { Wrapper::<P>::ext_remote_controls_get(plugin, index, &mut page) });
+            assert_eq!(page.page_id, index);
+            let section_name = unsafe { CStr::from_ptr(page.section_name.as_ptr()) };
+            let page_name = unsafe { CStr::from_ptr(page.page_name.as_ptr()) };
+            pages.push((
+                section_name.to_str().unwrap().to_owned(),
+                page_name.to_str().unwrap().to_owned(),
+                page.param_ids
+                    .into_iter()
+                    .filter(|&param_id| param_id != CLAP_INVALID_ID)
+                    .collect(),
+            ));
+        }
+
+        pages
+    }
+
+    #[test]
+    fn default_remote_control_pages() {
+        let host = TestHost::new();
+        let (_wrapper, plugin) = initialized_wrapper::<GroupedParamsPlugin>(&host);
+
+        // Every run of parameters in the same group gets its own section, and the bypass and hidden
+        // parameters are skipped
+        let plugin_name = String::from("Grouped Params Test Plugin");
+        assert_eq!(
+            remote_control_pages::<GroupedParamsPlugin>(plugin),
+            [
+                (
+                    plugin_name.clone(),
+                    plugin_name.clone(),
+                    vec![hash_param_id("gain")],
+                ),
+                (
+                    String::from("Filter"),
+                    String::from("Filter"),
+                    vec![hash_param_id("cutoff"), hash_param_id("res")],
+                ),
+                (plugin_name.clone(), plugin_name, vec![hash_param_id("mix")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn default_remote_control_pages_are_split() {
+        let host = TestHost::new();
+        let (_wrapper, plugin) = initialized_wrapper::<ManyParamsPlugin>(&host);
+
+        // The 50 parameters are split into pages of eight parameters in declaration order
+        let pages = remote_control_pages::<ManyParamsPlugin>(plugin);
+        assert_eq!(pages.len(), 7);
+        for (page_idx, (section_name, page_name, param_ids)) in pages.into_iter().enumerate() {
+            let expected_param_ids: Vec<clap_id> = (page_idx * 8..(page_idx * 8 + 8).min(50))
+                .map(|i| hash_param_id(&format!("param_{i}")))
+                .collect();
+
+            assert_eq!(section_name, "Many Params Test Plugin");
+            assert_eq!(
+                page_name,
+                format!("Many Params Test Plugin {}", page_idx + 1)
+            );
+            assert_eq!(param_ids, expected_param_ids);
+        }
+    }
+
+    /// A plugin with [`TestParams`] that adds entries to the host's context menus. Selecting an
+    /// entry records its label in `selected`.
+    #[derive(Default)]
+    struct ContextMenuPlugin {
+        params: Arc<TestParams>,
+        selected: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    impl Plugin for ContextMenuPlugin {
+        const NAME: &'static str = "Context Menu Test Plugin";
+        const VENDOR: &'static str = "NIH-plug";
+        const URL: &'static str = "";
+        const EMAIL: &'static str = "";
+        const VERSION: &'static str = "0.1.0";
+
+        const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[];
+
+        type SysExMessage = ();
+        type BackgroundTask = ();
+
+        fn params(&self) -> Arc<dyn Params> {
+            self.params.clone()
+        }
+
+        fn process(
+            &mut self,
+            _buffer: &mut Buffer,
+            _aux: &mut AuxiliaryBuffers,
+            _context: &mut impl ProcessContext<Self>,
+        ) -> ProcessStatus {
+            ProcessStatus::Normal
+        }
+    }
+
+    impl ClapPlugin for ContextMenuPlugin {
+        const CLAP_ID: &'static str = "com.nih-plug.test-context-menu";
+        const CLAP_DESCRIPTION: Option<&'static str> = None;
+        const CLAP_MANUAL_URL: Option<&'static str> = None;
+        const CLAP_SUPPORT_URL: Option<&'static str> = None;
+        const CLAP_FEATURES: &'static [ClapFeature] = &[ClapFeature::AudioEffect];
+
+        fn context_menu(&self, context: &mut impl ContextMenuContext) {
+            let select = |label: &'static str| {
+                let selected = self.selected.clone();
+                move || selected.lock().push(label)
+            };
+
+            context.add_param_entries(&self.params.gain, |menu| {
+                menu.add_entry("Reset Gain", select("Reset Gain"));
+                menu.add_disabled_entry("Learn MIDI CC");
+                menu.add_submenu("Presets", |menu| {
+                    menu.add_entry("Unity Gain", select("Unity Gain"));
+                });
+                menu.add_submenu("Empty", |_| ());
+            });
+            context.add_global_entries(|menu| {
+                menu.add_title("Test Plugin");
+                menu.add_separator();
+                menu.add_entry("Copy State", select("Copy State"));
+            });
+        }
+    }
+
+    /// An item the plugin added to a host's context menu, with the labels converted to strings.
+    #[derive(Debug, PartialEq)]
+    enum HostMenuItem {
+        Entry {
+            label: String,
+            is_enabled: bool,
+            action_id: clap_id,
+        },
+        Title(String),
+        Separator,
+        BeginSubmenu(String),
+        EndSubmenu,
+    }
+
+    /// Let the plugin populate the host's context menu for `target` like a host would, and return
+    /// the items the plugin added. A `None` target requests the plugin's global context menu.
+    fn host_context_menu<P: ClapPlugin>(
+        plugin: *const clap_plugin,
+        target: Option<&clap_context_menu_target>,
+    ) -> Vec<HostMenuItem> {
+        let mut items: Vec<HostMenuItem> = Vec::new();
+        let builder = clap_context_menu_builder {
+            ctx: &mut items as *mut _ as *mut c_void,
+            add_item: Some(record_context_menu_item),
+            supports: Some(supports_context_menu_item),
+        };
+        let target = target.map_or(std::ptr::null(), |target| target as *const _);
+        assert!(unsafe { Wrapper::<P>::ext_context_menu_populate(plugin, target, &builder) });
+
+        items
+    }
+
+    unsafe extern "C" fn record_context_menu_item(
+        builder: *const clap_context_menu_builder,
+        item_kind: clap_context_menu_item_kind,
+        item_data: *const c_void,
+    ) -> bool {
+        let items = &mut *((*builder).ctx as *mut Vec<HostMenuItem>);
+        let to_string = |label: *const c_char| CStr::from_ptr(label).to_str().unwrap().to_owned();
+        let item = match item_kind {
+            CLAP_CONTEXT_MENU_ITEM_ENTRY => {
+                let entry = &*(item_data as *const clap_context_menu_entry);
+                HostMenuItem::Entry {
+                    label: to_string(entry.label),
+                    is_enabled: entry.is_enabled,
+                    action_id: entry.action_id,
+                }
+            }
+            CLAP_CONTEXT_MENU_ITEM_TITLE => HostMenuItem::Title(to_string(
+                (*(item_data as *const clap_context_menu_item_title)).title,
+            )),
+            CLAP_CONTEXT_MENU_ITEM_SEPARATOR => HostMenuItem::Separator,
+            CLAP_CONTEXT_MENU_ITEM_BEGIN_SUBMENU => HostMenuItem::BeginSubmenu(to_string(
+                (*(item_data as *const clap_context_menu_submenu)).label,
+            )),
+            CLAP_CONTEXT_MENU_ITEM_END_SUBMENU => HostMenuItem::EndSubmenu,
+            _ => return false,
+        };
+        items.push(item);
+
+        true
+    }
+
+    unsafe extern "C" fn supports_context_menu_item(
+        _builder: *const clap_context_menu_builder,
+        _item_kind: clap_context_menu_item_kind,
+    ) -> bool {
+        true
+    }
+
+    #[test]
+    fn context_menu_entries() {
+        let host = TestHost::new();
+        let (wrapper, plugin) = initialized_wrapper::<ContextMenuPlugin>(&host);
+        let param_target = |param_id: &str| clap_context_menu_target {
+            kind: CLAP_CONTEXT_MENU_TARGET_KIND_PARAM,
+            id: hash_param_id(param_id),
+        };
+        let entry = |label: &str, action_id: Option<clap_id>| HostMenuItem::Entry {
+            label: String::from(label),
+            is_enabled: action_id.is_some(),
+            action_id: action_id.unwrap_or(CLAP_INVALID_ID),
+        };
+
+        // Empty submenus are left out, and the action IDs are unique across all menus
+        assert_eq!(
+            host_context_menu::<ContextMenuPlugin>(plugin, Some(&param_target("gain"))),
+            [
+                entry("Reset Gain", Some(0)),
+                entry("Learn MIDI CC", None),
+                HostMenuItem::BeginSubmenu(String::from("Presets")),
+                entry("Unity Gain", Some(1)),
+                HostMenuItem::EndSubmenu,
+            ]
+        );
+        assert!(
+            host_context_menu::<ContextMenuPlugin>(plugin, Some(&param_target("gr"))).is_empty()
+        );
+        assert_eq!(
+            host_context_menu::<ContextMenuPlugin>(plugin, None),
+            [
+                HostMenuItem::Title(String::from("Test Plugin")),
+                HostMenuItem::Separator,
+                entry("Copy State", Some(2)),
+            ]
+        );
+
+        // The host passes the selected entry's action ID back to the plugin
+        let selected = wrapper.plugin.lock().selected.clone();
+        for action_id in [2, 1] {
+            assert!(unsafe {
+                Wrapper::<ContextMenuPlugin>::ext_context_menu_perform(
+                    plugin,
+                    &param_target("gain"),
+                    action_id,
+                )
+            });
+        }
+        assert_eq!(*selected.lock(), ["Copy State", "Unity Gain"]);
+    }
+}
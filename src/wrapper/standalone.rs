@@ -2,18 +2,27 @@
 //! of relying on a plugin host. This is mostly useful for quickly testing GUI changes.
 
 use clap::{CommandFactory, FromArgMatches};
+use std::fs;
 
 use self::backend::Backend;
 use self::config::WrapperConfig;
 use self::wrapper::{Wrapper, WrapperError};
 use super::util::setup_logger;
+use crate::plugin::capabilities::{PluginCapabilities, PluginFormat};
 use crate::prelude::Plugin;
 
 mod backend;
 mod config;
 mod context;
+#[cfg(feature = "standalone_osc")]
+mod osc;
+mod test_signal;
+mod vstpreset;
+mod wav;
 mod wrapper;
 
+pub use self::config::StandaloneConfig;
+
 /// Open an NIH-plug plugin as a standalone application. If the plugin has an editor, this will open
 /// the editor and block until the editor is closed. Otherwise this will block until SIGINT is
 /// received. This is mainly useful for quickly testing plugin GUIs. In order to use this, you will
@@ -57,34 +66,78 @@ pub fn nih_export_standalone<P: Plugin>() -> bool {
 /// of using [`std::env::args()`].
 pub fn nih_export_standalone_with_args<P: Plugin, Args: IntoIterator<Item = String>>(
     args: Args,
+) -> bool {
+    run_standalone::<P, Args>(args, StandaloneConfig::default())
+}
+
+/// The same as [`nih_export_standalone()`], but with additional settings for the standalone
+/// application like the editor window's title.
+///
+/// ```ignore
+/// // src/main.rs
+///
+/// use nih_plug::prelude::*;
+///
+/// use plugin_name::PluginName;
+///
+/// fn main() {
+///     nih_export_standalone_with_config::<PluginName>(StandaloneConfig {
+///         window_title: Some(String::from("Plugin Name (Standalone)")),
+///         ..Default::default()
+///     });
+/// }
+/// ```
+pub fn nih_export_standalone_with_config<P: Plugin>(standalone_config: StandaloneConfig) -> bool {
+    run_standalone::<P, _>(std::env::args(), standalone_config)
+}
+
+fn run_standalone<P: Plugin, Args: IntoIterator<Item = String>>(
+    args: Args,
+    standalone_config: StandaloneConfig,
 ) -> bool {
     setup_logger();
 
     // Instead of parsing this directly, we need to take a bit of a roundabout approach to get the
     // plugin's name and vendor in here since they'd otherwise be taken from NIH-plug's own
     // `Cargo.toml` file.
-    let config = WrapperConfig::from_arg_matches(
-        &WrapperConfig::command()
-            .name(P::NAME)
-            .author(P::VENDOR)
-            .get_matches_from(args),
-    )
-    .unwrap_or_else(|err| err.exit());
+    let config = WrapperConfig {
+        standalone: standalone_config,
+        ..WrapperConfig::from_arg_matches(
+            &WrapperConfig::command()
+                .name(P::NAME)
+                .author(P::VENDOR)
+                .get_matches_from(args),
+        )
+        .unwrap_or_else(|err| err.exit())
+    };
+
+    // The summary should not depend on the available audio devices
+    if config.info {
+        return print_capabilities::<P>(config);
+    }
+    if config.list_params {
+        let capabilities = PluginCapabilities::new(&mut P::default())
+            .with_formats([PluginFormat::Standalone])
+            .with_formats(config.standalone.formats.iter().cloned());
+        print!("{}", capabilities.to_table());
+        return true;
+    }
+
+    // Offline rendering doesn't go through an actual audio device
+    if config.render {
+        nih_log!("Rendering offline using the dummy backend");
+        return run_wrapper::<P, _>(backend::Dummy::new::<P>(config.clone()), config);
+    }
 
     match config.backend {
         config::BackendType::Auto => {
-            let result = backend::Jack::new::<P>(config.clone()).map(|backend| {
-                nih_log!("Using the JACK backend");
-                run_wrapper::<P, _>(backend, config.clone())
-            });
+            let result = backend::Jack::new::<P>(config.clone())
+                .map(|backend| run_wrapper::<P, _>(backend, config.clone()));
 
             #[cfg(target_os = "linux")]
             let result = result.or_else(|_| {
                 match backend::CpalMidir::new::<P>(config.clone(), cpal::HostId::Alsa) {
-                    Ok(backend) => {
-                        nih_log!("Using the ALSA backend");
-                        Ok(run_wrapper::<P, _>(backend, config.clone()))
-                    }
+                    Ok(backend) => Ok(run_wrapper::<P, _>(backend, config.clone())),
                     Err(err) => {
                         nih_error!(
                             "Could not initialize either the JACK or the ALSA backends, falling \
@@ -97,10 +150,7 @@ pub fn nih_export_standalone_with_args<P: Plugin, Args: IntoIterator<Item = Stri
             #[cfg(target_os = "macos")]
             let result = result.or_else(|_| {
                 match backend::CpalMidir::new::<P>(config.clone(), cpal::HostId::CoreAudio) {
-                    Ok(backend) => {
-                        nih_log!("Using the CoreAudio backend");
-                        Ok(run_wrapper::<P, _>(backend, config.clone()))
-                    }
+                    Ok(backend) => Ok(run_wrapper::<P, _>(backend, config.clone())),
                     Err(err) => {
                         nih_error!(
                             "Could not initialize either the JACK or the CoreAudio backends, \
@@ -113,10 +163,7 @@ pub fn nih_export_standalone_with_args<P: Plugin, Args: IntoIterator<Item = Stri
             #[cfg(target_os = "windows")]
             let result = result.or_else(|_| {
                 match backend::CpalMidir::new::<P>(config.clone(), cpal::HostId::Wasapi) {
-                    Ok(backend) => {
-                        nih_log!("Using the WASAPI backend");
-                        Ok(run_wrapper::<P, _>(backend, config.clone()))
-                    }
+                    Ok(backend) => Ok(run_wrapper::<P, _>(backend, config.clone())),
                     Err(err) => {
                         nih_error!(
                             "Could not initialize either the JACK or the WASAPI backends, falling \
@@ -176,6 +223,8 @@ pub fn nih_export_standalone_with_args<P: Plugin, Args: IntoIterator<Item = Stri
 }
 
 fn run_wrapper<P: Plugin, B: Backend<P>>(backend: B, config: WrapperConfig) -> bool {
+    nih_log!("Using the {} backend", backend.name());
+
     let wrapper = match Wrapper::<P, _>::new(backend, config) {
         Ok(wrapper) => wrapper,
         Err(err) => {
@@ -194,10 +243,48 @@ fn run_wrapper<P: Plugin, B: Backend<P>>(backend: B, config: WrapperConfig) -> b
     }
 }
 
+/// Initialize the plugin using the dummy backend and print its capabilities to STDOUT.
+fn print_capabilities<P: Plugin>(config: WrapperConfig) -> bool {
+    // The config is moved into the wrapper
+    let info_file = config.info_file.clone();
+    match Wrapper::<P, _>::new(backend::Dummy::new::<P>(config.clone()), config) {
+        Ok(wrapper) => {
+            let json = wrapper.capabilities().to_json();
+            match &info_file {
+                Some(path) => match fs::write(path, json) {
+                    Ok(()) => true,
+                    Err(err) => {
+                        nih_error!("Could not write the summary to '{}': {err}", path.display());
+                        false
+                    }
+                },
+                None => {
+                    println!("{json}");
+                    true
+                }
+            }
+        }
+        Err(err) => {
+            print_error(err);
+            false
+        }
+    }
+}
+
 fn print_error(error: WrapperError) {
     match error {
         WrapperError::InitializationFailed => {
             nih_error!("The plugin failed to initialize");
         }
+        WrapperError::LoadStateFailed => {
+            nih_error!("The plugin state passed to '--load-state' could not be loaded");
+        }
+        WrapperError::LoadAutomationFailed => {
+            nih_error!("The automation recording passed to '--automation' could not be loaded");
+        }
+        #[cfg(feature = "standalone_osc")]
+        WrapperError::OscServerFailed => {
+            nih_error!("The OSC server could not be started");
+        }
     }
 }
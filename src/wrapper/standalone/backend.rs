@@ -12,6 +12,9 @@ pub use crate::plugin::Plugin;
 
 /// An audio+MIDI backend for the standalone wrapper.
 pub trait Backend<P: Plugin>: 'static + Send + Sync {
+    /// The backend's human readable name, like `JACK` or `ALSA`.
+    fn name(&self) -> &'static str;
+
     /// Start processing audio and MIDI on this thread. The process callback will be called whenever
     /// there's a new block of audio to be processed. The process callback receives the audio
     /// buffers for the wrapped plugin's outputs. Any inputs will have already been copied to this
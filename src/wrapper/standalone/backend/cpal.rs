@@ -3,31 +3,35 @@ use cpal::{
     traits::*, Device, FromSample, InputCallbackInfo, OutputCallbackInfo, Sample, SampleFormat,
     Stream, StreamConfig,
 };
+use crossbeam::channel::RecvTimeoutError;
 use crossbeam::sync::{Parker, Unparker};
-use midir::{
-    MidiInput, MidiInputConnection, MidiInputPort, MidiOutput, MidiOutputConnection, MidiOutputPort,
-};
+use midir::{MidiIO, MidiInput, MidiInputConnection, MidiOutput, MidiOutputConnection};
 use parking_lot::Mutex;
 use rtrb::RingBuffer;
 use std::borrow::Borrow;
 use std::num::NonZeroU32;
 use std::ptr::NonNull;
+use std::sync::Arc;
 use std::thread::ScopedJoinHandle;
+use std::time::{Duration, Instant};
 
 use super::super::config::WrapperConfig;
 use super::Backend;
 use crate::midi::MidiResult;
 use crate::prelude::{
-    AudioIOLayout, AuxiliaryBuffers, Buffer, MidiConfig, NoteEvent, Plugin, PluginNoteEvent,
-    Transport,
+    AudioIOLayout, AuxiliaryBuffers, Buffer, MidiConfig, Plugin, PluginNoteEvent, Transport,
 };
 use crate::wrapper::util::buffer_management::{BufferManager, ChannelPointers};
+use crate::wrapper::util::midi_input_events;
 
 const MIDI_EVENT_QUEUE_CAPACITY: usize = 2048;
+/// How often the MIDI threads check whether their devices have been disconnected or reconnected.
+const MIDI_DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
 /// Uses CPAL for audio and midir for MIDI.
 pub struct CpalMidir {
     config: WrapperConfig,
+    host_id: cpal::HostId,
     audio_io_layout: AudioIOLayout,
 
     input: Option<CpalDevice>,
@@ -44,28 +48,30 @@ struct CpalDevice {
     pub sample_format: SampleFormat,
 }
 
-/// All data needed to create a Midir input stream.
+/// All data needed to create a Midir input stream. The port is looked up by name when connecting
+/// so the device can be reconnected after it has been unplugged.
 struct MidirInputDevice {
     pub backend: MidiInput,
-    pub port: MidiInputPort,
+    pub port_name: String,
 }
 
-/// An active `MidirInputDevice`. Transformed back and from this during the `.run()` function.
-struct ActiveMidirInputDevice {
-    pub connection: MidiInputConnection<()>,
-    pub port: MidiInputPort,
+/// The connection state of the MIDI input thread. Midir's connection types consume the backend,
+/// and closing the connection returns it again.
+enum MidirInputState {
+    Disconnected(MidiInput),
+    Connected(MidiInputConnection<()>),
 }
 
-/// All data needed to create a Midir output stream.
+/// All data needed to create a Midir output stream. See [`MidirInputDevice`].
 struct MidirOutputDevice {
     pub backend: MidiOutput,
-    pub port: MidiOutputPort,
+    pub port_name: String,
 }
 
-/// An active `MidirOutputDevice`. Transformed back and from this during the `.run()` function.
-struct ActiveMidirOutputDevice {
-    pub connection: MidiOutputConnection,
-    pub port: MidiOutputPort,
+/// The connection state of the MIDI output thread. See [`MidirInputState`].
+enum MidirOutputState {
+    Disconnected(MidiOutput),
+    Connected(MidiOutputConnection),
 }
 
 /// Send+Sync wrapper for `Vec<*mut f32>` so we can preallocate channel pointer vectors for use with
@@ -92,6 +98,10 @@ enum MidiOutputTask<P: Plugin> {
 }
 
 impl<P: Plugin> Backend<P> for CpalMidir {
+    fn name(&self) -> &'static str {
+        self.host_id.name()
+    }
+
     fn run(
         &mut self,
         cb: impl FnMut(
@@ -121,10 +131,10 @@ impl<P: Plugin> Backend<P> for CpalMidir {
         // events then those are sent over another ringbuffer to a thread that handles MIDI output.
         // Both MIDI input and MIDI output are disabled by default.
         //
-        // The thread scope is needed to accomodate the midir MIDI output API. Outputting MIDI is
-        // realtime unsafe, and to be able to output MIDI with midir you need to transform between
-        // `MidiOutputPort` and `MidiOutputPortConnection` types by taking values out of an
-        // `Option`.
+        // The thread scope is needed to accomodate the midir MIDI APIs. Outputting MIDI is
+        // realtime unsafe, and midir's connection types consume the backend they were created
+        // from. Both MIDI devices are managed on their own threads that poll for the device
+        // being unplugged and plugged back in.
         std::thread::scope(|s| {
             let mut _input_stream: Option<Stream> = None;
             let mut input_rb_consumer: Option<rtrb::Consumer<f32>> = None;
@@ -182,34 +192,28 @@ impl<P: Plugin> Backend<P> for CpalMidir {
                 input_parker.park()
             }
 
-            // The output callback can read input events from this ringbuffer
+            // The output callback can read input events from this ringbuffer. The MIDI input
+            // connection is managed on its own thread so the device can be reconnected when it gets
+            // unplugged and plugged back in. Midir's callbacks run on yet another thread, and the
+            // lock around the producer is only needed because a new callback is created for every
+            // reconnection.
             let mut midi_input_rb_consumer: Option<rtrb::Consumer<PluginNoteEvent<P>>> = None;
-            let midi_input_connection: Option<ActiveMidirInputDevice> =
-                self.midi_input.lock().take().and_then(|midi_input| {
+            let (midi_input_terminate_sender, midi_input_terminate_receiver) =
+                crossbeam::channel::bounded(1);
+            let midi_input_thread: Option<ScopedJoinHandle<MidirInputDevice>> =
+                self.midi_input.lock().take().map(|midi_input| {
                     // Data is sent to the output data callback using a wait-free ring buffer
                     let (rb_producer, rb_consumer) = RingBuffer::new(MIDI_EVENT_QUEUE_CAPACITY);
                     midi_input_rb_consumer = Some(rb_consumer);
 
-                    let result = midi_input.backend.connect(
-                        &midi_input.port,
-                        "MIDI input",
-                        self.build_midi_input_thread::<P>(rb_producer),
-                        (),
-                    );
-
-                    match result {
-                        Ok(connection) => Some(ActiveMidirInputDevice {
-                            connection,
-                            port: midi_input.port,
-                        }),
-                        Err(err) => {
-                            // We won't retry once this fails
-                            nih_error!("Could not create the MIDI input connection: {err:#}");
-                            midi_input_rb_consumer = None;
-
-                            None
-                        }
-                    }
+                    let rb_producer = Arc::new(Mutex::new(rb_producer));
+                    s.spawn(move || {
+                        run_midi_input_thread::<P>(
+                            midi_input,
+                            rb_producer,
+                            midi_input_terminate_receiver,
+                        )
+                    })
                 });
 
             // The output callback can also emit MIDI events. To handle these we'll need to spawn
@@ -221,59 +225,14 @@ impl<P: Plugin> Backend<P> for CpalMidir {
             //        do.
             let mut midi_output_rb_producer: Option<crossbeam::channel::Sender<MidiOutputTask<P>>> =
                 None;
-            let midi_output_connection: Option<ScopedJoinHandle<ActiveMidirOutputDevice>> =
-                self.midi_output.lock().take().and_then(|midi_output| {
+            let midi_output_thread: Option<ScopedJoinHandle<MidirOutputDevice>> =
+                self.midi_output.lock().take().map(|midi_output| {
                     // This uses crossbeam channels for the reason mentioned above, but to keep
                     // things cohesive we'll use the same naming scheme as we use for rtrb
                     let (sender, receiver) = crossbeam::channel::bounded(MIDI_EVENT_QUEUE_CAPACITY);
                     midi_output_rb_producer = Some(sender);
 
-                    let result = midi_output
-                        .backend
-                        .connect(&midi_output.port, "MIDI output");
-
-                    match result {
-                        Ok(mut connection) => Some(s.spawn(move || {
-                            while let Ok(task) = receiver.recv() {
-                                match task {
-                                    MidiOutputTask::Send(event) => match event.as_midi() {
-                                        Some(MidiResult::Basic(midi_data)) => {
-                                            if let Err(err) = connection.send(&midi_data) {
-                                                nih_error!("Could not send MIDI event: {err}");
-                                            }
-                                        }
-                                        Some(MidiResult::SysEx(padded_sysex_buffer, length)) => {
-                                            // The SysEx buffer may contain padding
-                                            let padded_sysex_buffer = padded_sysex_buffer.borrow();
-                                            nih_debug_assert!(length <= padded_sysex_buffer.len());
-
-                                            if let Err(err) =
-                                                connection.send(&padded_sysex_buffer[..length])
-                                            {
-                                                nih_error!("Could not send MIDI event: {err}");
-                                            }
-                                        }
-                                        None => (),
-                                    },
-                                    MidiOutputTask::Terminate => break,
-                                }
-                            }
-
-                            // We'll return the same value from the join handle as what ends up
-                            // being stored in `midi_input_connection` to keep this symmetrical with
-                            // the input handling
-                            ActiveMidirOutputDevice {
-                                connection,
-                                port: midi_output.port,
-                            }
-                        })),
-                        Err(err) => {
-                            nih_error!("Could not create the MIDI output connection: {err:#}");
-                            midi_output_rb_producer = None;
-
-                            None
-                        }
-                    }
+                    s.spawn(move || run_midi_output_thread::<P>(midi_output, receiver))
                 });
 
             // This thread needs to be blocked until audio processing ends as CPAL processes the
@@ -332,30 +291,27 @@ impl<P: Plugin> Backend<P> for CpalMidir {
             // Wait for the audio thread to exit
             parker.park();
 
-            // The Midir API requires us to take things out of Options and transform between these
-            // structs
-            *self.midi_input.lock() =
-                midi_input_connection.map(|midi_input_connection| MidirInputDevice {
-                    backend: midi_input_connection.connection.close().0,
-                    port: midi_input_connection.port,
-                });
-            *self.midi_output.lock() =
-                midi_output_connection.map(move |midi_output_connection_handle| {
-                    // The thread needs to be terminated first
-                    midi_output_rb_producer
-                        .expect("Inconsistent internal MIDI output state")
-                        .send(MidiOutputTask::Terminate)
-                        .expect("Could not terminate the MIDI output thread");
-
-                    let midi_output_connection = midi_output_connection_handle
-                        .join()
-                        .expect("MIDI output thread panicked");
-
-                    MidirOutputDevice {
-                        backend: midi_output_connection.connection.close(),
-                        port: midi_output_connection.port,
-                    }
-                });
+            // The MIDI threads close their connections and hand the devices back when they're
+            // terminated so `run()` can be called again
+            *self.midi_input.lock() = midi_input_thread.map(|midi_input_thread| {
+                midi_input_terminate_sender
+                    .send(())
+                    .expect("Could not terminate the MIDI input thread");
+
+                midi_input_thread
+                    .join()
+                    .expect("MIDI input thread panicked")
+            });
+            *self.midi_output.lock() = midi_output_thread.map(move |midi_output_thread| {
+                midi_output_rb_producer
+                    .expect("Inconsistent internal MIDI output state")
+                    .send(MidiOutputTask::Terminate)
+                    .expect("Could not terminate the MIDI output thread");
+
+                midi_output_thread
+                    .join()
+                    .expect("MIDI output thread panicked")
+            });
         });
     }
 }
@@ -554,9 +510,9 @@ impl CpalMidir {
                 };
 
                 match found_port {
-                    Some(port) => Some(MidirInputDevice {
+                    Some(_) => Some(MidirInputDevice {
                         backend: midi_backend,
-                        port: port.clone(),
+                        port_name: midi_input_name.clone(),
                     }),
                     None => {
                         let mut message = format!(
@@ -591,9 +547,9 @@ impl CpalMidir {
                 };
 
                 match found_port {
-                    Some(port) => Some(MidirOutputDevice {
+                    Some(_) => Some(MidirOutputDevice {
                         backend: midi_backend,
-                        port: port.clone(),
+                        port_name: midi_output_name.clone(),
                     }),
                     None => {
                         let mut message = format!(
@@ -616,6 +572,7 @@ impl CpalMidir {
 
         Ok(CpalMidir {
             config,
+            host_id: cpal_host_id,
             audio_io_layout,
 
             input,
@@ -653,22 +610,6 @@ impl CpalMidir {
         }
     }
 
-    fn build_midi_input_thread<P: Plugin>(
-        &self,
-        mut midi_input_rb_producer: rtrb::Producer<PluginNoteEvent<P>>,
-    ) -> impl FnMut(u64, &[u8], &mut ()) + Send + 'static {
-        // This callback parses the received MIDI bytes and sends them to a ring buffer
-        move |_timing, midi_data, _data| {
-            // Since this is system MIDI there's no real useful timing information and we'll set all
-            // the timings to the first sample in the buffer
-            if let Ok(event) = NoteEvent::from_midi(0, midi_data) {
-                if midi_input_rb_producer.push(event).is_err() {
-                    nih_error!("The MIDI input event queue was full, dropping event");
-                }
-            }
-        }
-    }
-
     fn build_output_data_callback<P, T>(
         &self,
         unparker: Unparker,
@@ -884,9 +825,11 @@ impl CpalMidir {
                     })
                 };
 
+                // All events that arrived since the last period are sent to the first sample in
+                // the buffer
                 midi_input_events.clear();
                 if let Some(input_event_rb_consumer) = &mut input_event_rb_consumer {
-                    if let Ok(event) = input_event_rb_consumer.pop() {
+                    while let Ok(event) = input_event_rb_consumer.pop() {
                         midi_input_events.push(event);
                     }
                 }
@@ -942,3 +885,210 @@ impl CpalMidir {
         }
     }
 }
+
+/// Find a MIDI port by its name.
+fn find_midi_port<T: MidiIO>(backend: &T, port_name: &str) -> Option<T::Port> {
+    backend
+        .ports()
+        .into_iter()
+        .find(|port| backend.port_name(port).as_deref() == Ok(port_name))
+}
+
+/// Whether a MIDI port with this name currently exists. If `watcher` could not be created, then
+/// the port is assumed to still exist.
+fn midi_port_exists<T: MidiIO>(watcher: &Option<T>, port_name: &str) -> bool {
+    match watcher {
+        Some(watcher) => find_midi_port(watcher, port_name).is_some(),
+        None => true,
+    }
+}
+
+/// Keeps the MIDI input device connected until a message is received on `terminate_receiver`.
+/// Incoming events are sent to `midi_input_rb_producer`. The device is polled periodically so it
+/// can be reconnected after it has been unplugged. Returns the device again after closing the
+/// connection.
+fn run_midi_input_thread<P: Plugin>(
+    midi_input: MidirInputDevice,
+    midi_input_rb_producer: Arc<Mutex<rtrb::Producer<PluginNoteEvent<P>>>>,
+    terminate_receiver: crossbeam::channel::Receiver<()>,
+) -> MidirInputDevice {
+    let MidirInputDevice { backend, port_name } = midi_input;
+
+    // The backend is consumed while connected, so a second client is used to list the ports
+    let watcher = MidiInput::new(&format!("{} (device watcher)", P::NAME)).ok();
+    let mut state = MidirInputState::Disconnected(backend);
+    let mut was_connected = false;
+    // Connecting is retried every poll interval, so the error is only logged once until the
+    // connection succeeds
+    let mut connection_error_logged = false;
+    loop {
+        state = match state {
+            MidirInputState::Disconnected(backend) => match find_midi_port(&backend, &port_name) {
+                Some(port) => {
+                    let midi_input_rb_producer = midi_input_rb_producer.clone();
+                    let result = backend.connect(
+                        &port,
+                        "MIDI input",
+                        move |_timing, midi_data, _data| {
+                            // Since this is system MIDI there's no real useful timing information
+                            // and we'll set all the timings to the first sample in the buffer
+                            for event in midi_input_events(0, midi_data, P::MIDI_INPUT_RAW)
+                                .filter_map(Result::ok)
+                            {
+                                if midi_input_rb_producer.lock().push(event).is_err() {
+                                    nih_error!(
+                                        "The MIDI input event queue was full, dropping event"
+                                    );
+                                }
+                            }
+                        },
+                        (),
+                    );
+
+                    match result {
+                        Ok(connection) => {
+                            if was_connected {
+                                nih_log!("Reconnected to the MIDI input device '{port_name}'");
+                            }
+                            was_connected = true;
+                            connection_error_logged = false;
+
+                            MidirInputState::Connected(connection)
+                        }
+                        Err(err) => {
+                            if !connection_error_logged {
+                                nih_error!("Could not create the MIDI input connection: {err}");
+                                connection_error_logged = true;
+                            }
+                            MidirInputState::Disconnected(err.into_inner())
+                        }
+                    }
+                }
+                None => MidirInputState::Disconnected(backend),
+            },
+            MidirInputState::Connected(connection) => {
+                if midi_port_exists(&watcher, &port_name) {
+                    MidirInputState::Connected(connection)
+                } else {
+                    nih_warn!(
+                        "The MIDI input device '{port_name}' has been disconnected, it will be \
+                         reconnected when it becomes available again"
+                    );
+                    MidirInputState::Disconnected(connection.close().0)
+                }
+            }
+        };
+
+        match terminate_receiver.recv_timeout(MIDI_DEVICE_POLL_INTERVAL) {
+            Err(RecvTimeoutError::Timeout) => continue,
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    let backend = match state {
+        MidirInputState::Disconnected(backend) => backend,
+        MidirInputState::Connected(connection) => connection.close().0,
+    };
+
+    MidirInputDevice { backend, port_name }
+}
+
+/// Sends the events received on `receiver` to the MIDI output device until a
+/// [`MidiOutputTask::Terminate`] task is received. Like with [`run_midi_input_thread()`], the
+/// device is reconnected after it has been unplugged. Events sent while the device is
+/// disconnected are dropped. Returns the device again after closing the connection.
+fn run_midi_output_thread<P: Plugin>(
+    midi_output: MidirOutputDevice,
+    receiver: crossbeam::channel::Receiver<MidiOutputTask<P>>,
+) -> MidirOutputDevice {
+    let MidirOutputDevice { backend, port_name } = midi_output;
+
+    let watcher = MidiOutput::new(&format!("{} (device watcher)", P::NAME)).ok();
+    let mut state = MidirOutputState::Disconnected(backend);
+    let mut was_connected = false;
+    let mut connection_error_logged = false;
+    // The same goes for errors while sending events, which are logged once per connection
+    let mut send_error_logged = false;
+    let mut next_poll = Instant::now();
+    loop {
+        if Instant::now() >= next_poll {
+            state = match state {
+                MidirOutputState::Disconnected(backend) => {
+                    match find_midi_port(&backend, &port_name) {
+                        Some(port) => match backend.connect(&port, "MIDI output") {
+                            Ok(connection) => {
+                                if was_connected {
+                                    nih_log!("Reconnected to the MIDI output device '{port_name}'");
+                                }
+                                was_connected = true;
+                                connection_error_logged = false;
+                                send_error_logged = false;
+
+                                MidirOutputState::Connected(connection)
+                            }
+                            Err(err) => {
+                                if !connection_error_logged {
+                                    nih_error!(
+                                        "Could not create the MIDI output connection: {err}"
+                                    );
+                                    connection_error_logged = true;
+                                }
+                                MidirOutputState::Disconnected(err.into_inner())
+                            }
+                        },
+                        None => MidirOutputState::Disconnected(backend),
+                    }
+                }
+                MidirOutputState::Connected(connection) => {
+                    if midi_port_exists(&watcher, &port_name) {
+                        MidirOutputState::Connected(connection)
+                    } else {
+                        nih_warn!(
+                            "The MIDI output device '{port_name}' has been disconnected, it will \
+                             be reconnected when it becomes available again"
+                        );
+                        MidirOutputState::Disconnected(connection.close())
+                    }
+                }
+            };
+
+            next_poll = Instant::now() + MIDI_DEVICE_POLL_INTERVAL;
+        }
+
+        match receiver.recv_deadline(next_poll) {
+            Ok(MidiOutputTask::Send(event)) => {
+                let connection = match &mut state {
+                    MidirOutputState::Connected(connection) => connection,
+                    MidirOutputState::Disconnected(_) => continue,
+                };
+
+                let result = match event.as_midi() {
+                    Some(MidiResult::Basic(midi_data)) => connection.send(&midi_data),
+                    Some(MidiResult::SysEx(padded_sysex_buffer, length)) => {
+                        // The SysEx buffer may contain padding
+                        let padded_sysex_buffer = padded_sysex_buffer.borrow();
+                        nih_debug_assert!(length <= padded_sysex_buffer.len());
+
+                        connection.send(&padded_sysex_buffer[..length])
+                    }
+                    None => Ok(()),
+                };
+                if let Err(err) = result {
+                    if !send_error_logged {
+                        nih_error!("Could not send MIDI event: {err}");
+                        send_error_logged = true;
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => (),
+            Ok(MidiOutputTask::Terminate) | Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    let backend = match state {
+        MidirOutputState::Disconnected(backend) => backend,
+        MidirOutputState::Connected(connection) => connection.close(),
+    };
+
+    MidirOutputDevice { backend, port_name }
+}
@@ -3,19 +3,25 @@ use std::ptr::NonNull;
 use std::time::{Duration, Instant};
 
 use super::super::config::WrapperConfig;
+use super::super::wav;
 use super::Backend;
 use crate::prelude::{AudioIOLayout, AuxiliaryBuffers, Buffer, Plugin, PluginNoteEvent, Transport};
 use crate::wrapper::util::buffer_management::{BufferManager, ChannelPointers};
 
 /// This backend doesn't input or output any audio or MIDI. It only exists so the standalone
 /// application can continue to run even when there is no audio backend available. This can be
-/// useful for testing plugin GUIs.
+/// useful for testing plugin GUIs. When rendering offline, this backend can also feed a test signal
+/// to the plugin and write the plugin's output to a WAV file.
 pub struct Dummy {
     config: WrapperConfig,
     audio_io_layout: AudioIOLayout,
 }
 
 impl<P: Plugin> Backend<P> for Dummy {
+    fn name(&self) -> &'static str {
+        "dummy"
+    }
+
     fn run(
         &mut self,
         mut cb: impl FnMut(
@@ -46,6 +52,35 @@ impl<P: Plugin> Backend<P> for Dummy {
             .unwrap_or_default() as usize;
         let mut main_io_storage = vec![vec![0.0f32; num_samples]; num_output_channels];
 
+        // When rendering a test signal, the input is generated instead of being silent, and
+        // rendering stops after the entire signal has been processed unless a different length was
+        // specified
+        let mut test_signal = self
+            .config
+            .signal
+            .as_ref()
+            .map(|signal| signal.generator(self.config.sample_rate));
+        if let Some(signal) = &self.config.signal {
+            nih_log!("Rendering the '{signal}' test signal");
+        }
+        let render_length = match (self.config.length, &test_signal) {
+            (Some(length), _) => Some((length * self.config.sample_rate).round() as usize),
+            (None, Some(test_signal)) => Some(test_signal.num_samples()),
+            (None, None) => None,
+        };
+
+        // The command line parser only allows '--output' together with a render length, so the
+        // entire recording can be allocated up front instead of growing it on the audio thread
+        let mut recorded_output: Option<Vec<Vec<f32>>> = match (&self.config.output, render_length)
+        {
+            (Some(_), Some(render_length)) => Some(
+                (0..num_output_channels)
+                    .map(|_| Vec::with_capacity(render_length))
+                    .collect(),
+            ),
+            _ => None,
+        };
+
         // We'll do the same thing for auxiliary inputs and outputs, so the plugin always gets the
         // buffers it expects
         let mut aux_input_storage: Vec<Vec<Vec<f32>>> = Vec::new();
@@ -109,6 +144,18 @@ impl<P: Plugin> Backend<P> for Dummy {
             for channel in &mut main_io_storage {
                 channel.fill(0.0);
             }
+            if let Some(test_signal) = &mut test_signal {
+                // The same signal is sent to every input channel
+                let num_input_channels = num_input_channels.min(main_io_storage.len());
+                if let Some((first_channel, other_channels)) =
+                    main_io_storage[..num_input_channels].split_first_mut()
+                {
+                    test_signal.fill(first_channel);
+                    for channel in other_channels {
+                        channel.copy_from_slice(first_channel);
+                    }
+                }
+            }
             for aux_buffer in &mut aux_input_storage {
                 for channel in aux_buffer {
                     channel.fill(0.0);
@@ -170,10 +217,35 @@ impl<P: Plugin> Backend<P> for Dummy {
                 break;
             }
 
+            if let (Some(recorded_output), Some(render_length)) =
+                (&mut recorded_output, render_length)
+            {
+                // The last period may extend past the end of the render
+                let num_recorded_samples =
+                    num_samples.min(render_length.saturating_sub(num_processed_samples));
+                for (recorded_channel, channel) in recorded_output.iter_mut().zip(&main_io_storage)
+                {
+                    recorded_channel.extend_from_slice(&channel[..num_recorded_samples]);
+                }
+            }
+
             num_processed_samples += num_samples;
+            if render_length.is_some_and(|render_length| num_processed_samples >= render_length) {
+                break;
+            }
 
-            let period_end = Instant::now();
-            std::thread::sleep((period_start + interval).saturating_duration_since(period_end));
+            // When rendering offline we'll process the next period right away
+            if !self.config.render {
+                let period_end = Instant::now();
+                std::thread::sleep((period_start + interval).saturating_duration_since(period_end));
+            }
+        }
+
+        if let (Some(output), Some(recorded_output)) = (&self.config.output, recorded_output) {
+            match wav::write_wav_file(output, self.config.sample_rate, &recorded_output) {
+                Ok(()) => nih_log!("Wrote the rendered output to '{}'", output.display()),
+                Err(err) => nih_error!("Could not write the rendered output: {err:#}"),
+            }
         }
     }
 }
@@ -19,7 +19,10 @@ use crate::prelude::{
     Transport,
 };
 use crate::wrapper::util::buffer_management::{BufferManager, ChannelPointers};
-use crate::wrapper::util::{clamp_input_event_timing, clamp_output_event_timing};
+use crate::wrapper::util::logger::AudioThreadGuard;
+use crate::wrapper::util::{
+    clamp_input_event_timing, clamp_output_event_timing, midi_input_events,
+};
 
 /// Uses JACK audio and MIDI.
 pub struct Jack {
@@ -53,6 +56,10 @@ impl ChannelPointerVec {
 }
 
 impl<P: Plugin> Backend<P> for Jack {
+    fn name(&self) -> &'static str {
+        "JACK"
+    }
+
     fn run(
         &mut self,
         mut cb: impl FnMut(
@@ -115,7 +122,15 @@ impl<P: Plugin> Backend<P> for Jack {
         let aux_output_ports = self.aux_output_ports.clone();
         let midi_input = self.midi_input.clone();
         let midi_output = self.midi_output.clone();
+        // Used to log when a JACK timebase master appears or disappears
+        let mut had_timebase_master = None;
+        // Used to send an all notes off event when the transport stops
+        let mut was_playing = false;
         let process_handler = ClosureProcessHandler::new(move |client, ps| {
+            // Messages logged from here are written from the logger's flush thread so they don't
+            // block or allocate on JACK's realtime thread
+            let _logger_guard = AudioThreadGuard::enter();
+
             // In theory we could handle `num_frames <= buffer_size`, but JACK will never chop up
             // buffers like that so we'll just make it easier for ourselves by not supporting that
             let num_frames = ps.n_frames();
@@ -133,21 +148,33 @@ impl<P: Plugin> Backend<P> for Jack {
             transport.time_sig_numerator = Some(config.timesig_num as i32);
             transport.time_sig_denominator = Some(config.timesig_denom as i32);
 
+            // JACK's transport state and position are always available, but the musical
+            // position is only available when some client acts as the timebase master. Without
+            // one, the tempo and time signature from the command line options are used instead.
             if let Ok(jack_transport) = client.transport().query() {
                 transport.pos_samples = Some(jack_transport.pos.frame() as i64);
                 transport.playing = jack_transport.state == jack::TransportState::Rolling;
 
-                if let Some(bbt) = jack_transport.pos.bbt() {
-                    transport.tempo = Some(bbt.bpm);
-                    transport.time_sig_numerator = Some(bbt.sig_num as i32);
-                    transport.time_sig_denominator = Some(bbt.sig_denom as i32);
-
-                    transport.pos_beats = Some(
-                        (bbt.bar as f64 * 4.0)
-                            + (bbt.beat as f64 / bbt.sig_denom as f64 * 4.0)
-                            + (bbt.tick as f64 / bbt.ticks_per_beat),
-                    );
-                    transport.bar_number = Some(bbt.bar as i32);
+                let bbt = jack_transport.pos.bbt();
+                if let Some(bbt) = &bbt {
+                    apply_jack_bbt(&mut transport, bbt);
+                }
+
+                let has_timebase_master = bbt.is_some();
+                if had_timebase_master != Some(has_timebase_master) {
+                    if has_timebase_master {
+                        nih_log!("Following the JACK timebase master's tempo and position");
+                    } else {
+                        nih_log!(
+                            "There is no JACK timebase master, using a tempo of {} BPM and a time \
+                             signature of {}/{}",
+                            config.tempo,
+                            config.timesig_num,
+                            config.timesig_denom
+                        );
+                    }
+
+                    had_timebase_master = Some(has_timebase_master);
                 }
             }
 
@@ -240,11 +267,19 @@ impl<P: Plugin> Backend<P> for Jack {
             };
 
             input_events.clear();
+            // Synthesizers should not keep playing notes after JACK's transport stops
+            if midi_input.is_some() && was_playing && !transport.playing {
+                input_events.push(NoteEvent::AllNotesOff {
+                    timing: 0,
+                    channel: None,
+                });
+            }
+            was_playing = transport.playing;
             if let Some(midi_input) = &midi_input {
-                input_events.extend(midi_input.iter(ps).filter_map(|midi| {
+                input_events.extend(midi_input.iter(ps).flat_map(|midi| {
                     let timing = clamp_input_event_timing(midi.time, num_frames);
 
-                    NoteEvent::from_midi(timing, midi.bytes).ok()
+                    midi_input_events(timing, midi.bytes, P::MIDI_INPUT_RAW).filter_map(Result::ok)
                 }));
             }
 
@@ -483,3 +518,74 @@ impl Jack {
         Ok(())
     }
 }
+
+/// Fill in the tempo, time signature, and musical position from JACK's bar, beat, and tick based
+/// position. JACK's bars and beats start at one, and a beat is a `1/sig_denom` note rather than a
+/// quarter note.
+fn apply_jack_bbt(transport: &mut Transport, bbt: &jack::TransportBBT) {
+    let quarter_notes_per_beat = 4.0 / bbt.sig_denom as f64;
+    let bar_number = bbt.bar.saturating_sub(1);
+    let bar_start_pos_beats = bar_number as f64 * bbt.sig_num as f64 * quarter_notes_per_beat;
+    let beats_in_bar = bbt.beat.saturating_sub(1) as f64 + (bbt.tick as f64 / bbt.ticks_per_beat);
+
+    transport.tempo = Some(bbt.bpm);
+    transport.time_sig_numerator = Some(bbt.sig_num as i32);
+    transport.time_sig_denominator = Some(bbt.sig_denom as i32);
+    transport.pos_beats = Some(bar_start_pos_beats + beats_in_bar * quarter_notes_per_beat);
+    transport.bar_start_pos_beats = Some(bar_start_pos_beats);
+    transport.bar_number = Some(bar_number as i32);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bbt(
+        bar: usize,
+        beat: usize,
+        tick: usize,
+        sig_num: f32,
+        sig_denom: f32,
+    ) -> jack::TransportBBT {
+        jack::TransportBBT {
+            bar,
+            beat,
+            tick,
+            sig_num,
+            sig_denom,
+            ticks_per_beat: 1920.0,
+            bpm: 140.0,
+            bar_start_tick: 0.0,
+        }
+    }
+
+    #[test]
+    fn bbt_start_of_song() {
+        let mut transport = Transport::new(48_000.0);
+        apply_jack_bbt(&mut transport, &bbt(1, 1, 0, 4.0, 4.0));
+
+        assert_eq!(transport.tempo, Some(140.0));
+        assert_eq!(transport.pos_beats(), Some(0.0));
+        assert_eq!(transport.bar_start_pos_beats(), Some(0.0));
+        assert_eq!(transport.bar_number(), Some(0));
+    }
+
+    #[test]
+    fn bbt_position() {
+        // The third bar, on the second beat plus half a beat
+        let mut transport = Transport::new(48_000.0);
+        apply_jack_bbt(&mut transport, &bbt(3, 2, 960, 4.0, 4.0));
+        assert_eq!(transport.pos_beats(), Some(9.5));
+        assert_eq!(transport.bar_start_pos_beats(), Some(8.0));
+        assert_eq!(transport.bar_number(), Some(2));
+
+        // In 6/8 a bar is three quarter notes long, and a beat is an eighth note
+        let mut transport = Transport::new(48_000.0);
+        apply_jack_bbt(&mut transport, &bbt(2, 4, 960, 6.0, 8.0));
+        assert_eq!(transport.time_sig_numerator, Some(6));
+        assert_eq!(transport.time_sig_denominator, Some(8));
+        assert_eq!(transport.pos_beats(), Some(4.75));
+        assert_eq!(transport.bar_start_pos_beats(), Some(3.0));
+        assert_eq!(transport.bar_number(), Some(1));
+    }
+}
@@ -1,11 +1,17 @@
-use clap::{Parser, ValueEnum};
+use clap::{ArgGroup, Parser, ValueEnum};
+#[cfg(feature = "standalone_osc")]
+use std::net::{IpAddr, SocketAddr};
 use std::num::NonZeroU32;
+use std::path::PathBuf;
 
+use super::test_signal::{self, TestSignal};
+use crate::plugin::capabilities::PluginFormat;
 use crate::prelude::{AudioIOLayout, Plugin};
 
 /// Configuration for a standalone plugin that would normally be provided by the DAW.
 #[derive(Debug, Clone, Parser)]
 #[clap(about = None, long_about = None)]
+#[clap(group(ArgGroup::new("render_length").args(["signal", "length"]).multiple(true)))]
 pub struct WrapperConfig {
     /// The audio and MIDI backend to use.
     ///
@@ -34,6 +40,64 @@ pub struct WrapperConfig {
     /// latency if your audio device supports smaller buffers.
     #[clap(value_parser, short = 'p', long, default_value = "2048")]
     pub period_size: u32,
+    /// Print a JSON summary of the plugin's capabilities and parameters and exit.
+    ///
+    /// The plugin is initialized using the dummy backend and the other options, so the summary
+    /// also includes the plugin's latency.
+    #[clap(long)]
+    pub info: bool,
+    /// Write the summary printed by '--info' to this file instead of to STDOUT.
+    ///
+    /// This is useful for tools that parse the summary, since the plugin may also print other
+    /// messages to STDOUT.
+    #[clap(value_parser, long, requires = "info")]
+    pub info_file: Option<PathBuf>,
+    /// Print a table of the plugin's parameters, including their IDs and hashes, and exit.
+    ///
+    /// Unlike '--info', this does not initialize the plugin.
+    #[clap(long, conflicts_with = "info")]
+    pub list_params: bool,
+    /// Process audio offline as fast as possible instead of in realtime.
+    ///
+    /// This always uses the dummy backend, and the plugin will be told that it is being rendered
+    /// offline.
+    #[clap(long)]
+    pub render: bool,
+    /// Feed a generated test signal to the plugin's main input when rendering offline, and stop
+    /// rendering once the entire signal has been processed unless '--length' is also used.
+    ///
+    /// The supported signals are 'sine:<frequency>', 'sweep:<start>:<end>', 'impulse', 'noise',
+    /// and 'pink'. Every signal takes an optional length as its last argument, for instance
+    /// 'sweep:20:20000:5s' or 'impulse:500ms'. The default length is one second.
+    #[clap(value_parser, long, requires = "render")]
+    pub signal: Option<TestSignal>,
+    /// Write the plugin's main output to this 32-bit floating point WAV file when rendering
+    /// offline.
+    ///
+    /// Rendering needs to stop at some point for the file to be written, so this requires either
+    /// '--signal' or '--length'. The file has the same length as the rendered audio.
+    #[clap(value_parser, short = 'o', long, requires = "render_length")]
+    pub output: Option<PathBuf>,
+    /// Stop rendering after this amount of audio has been processed, for instance '10s' or
+    /// '500ms'. This defaults to the test signal's length when '--signal' is used. If this is
+    /// longer than the test signal, then the plugin's input is silent after the signal ends.
+    #[clap(value_parser = test_signal::parse_length, long, requires = "render")]
+    pub length: Option<f32>,
+    /// Load the plugin's state from a file before starting.
+    ///
+    /// This can be either a VST3 preset ('.vstpreset') file or a JSON state file as produced by
+    /// the plugin's own preset management.
+    #[clap(value_parser, long)]
+    pub load_state: Option<PathBuf>,
+    /// Replay a parameter automation recording.
+    ///
+    /// These recordings are made by setting the 'NIH_RECORD_AUTOMATION' environment variable to a
+    /// file path while running a CLAP or VST3 plugin built with NIH-plug's 'automation_recording'
+    /// feature. Parameter changes are applied at the start of the period they occur in, so use a
+    /// small '--period-size' for more precise timing. This is most useful together with
+    /// '--render'.
+    #[clap(value_parser, long)]
+    pub automation: Option<PathBuf>,
 
     /// The input device for the ALSA, CoreAudio, and WASAPI backends. No input will be connected if
     /// this is not specified.
@@ -48,13 +112,15 @@ pub struct WrapperConfig {
     pub output_device: Option<String>,
     /// The input MIDI device for the ALSA, CoreAudio, and WASAPI backends.
     ///
-    /// Specifying an empty string or other invalid value will list all available MIDI inputs.
-    #[clap(value_parser, long)]
+    /// Specifying an empty string or other invalid value will list all available MIDI inputs. The
+    /// device is reconnected automatically if it gets unplugged and plugged back in.
+    #[clap(value_parser, long, visible_alias = "midi-in")]
     pub midi_input: Option<String>,
     /// The output output device for the ALSA, CoreAudio, and WASAPI backends.
     ///
-    /// Specifying an empty string or other invalid value will list all available MIDI output.
-    #[clap(value_parser, long)]
+    /// Specifying an empty string or other invalid value will list all available MIDI output. The
+    /// device is reconnected automatically if it gets unplugged and plugged back in.
+    #[clap(value_parser, long, visible_alias = "midi-out")]
     pub midi_output: Option<String>,
 
     /// If set to a port name ('foo:bar_1'), then all all inputs will be connected to that port. If
@@ -87,7 +153,34 @@ pub struct WrapperConfig {
     #[clap(value_parser, long, default_value = "1.0")]
     pub dpi_scale: f32,
 
+    /// Listen for OSC messages on this UDP port.
+    ///
+    /// '/param/<id> <value>' sets a parameter to a normalized value between 0 and 1, and
+    /// '/param/<id>/plain <value>' sets it to a plain value in the parameter's own range. Sending
+    /// either address without a value replies with the parameter's current value.
+    #[cfg(feature = "standalone_osc")]
+    #[clap(value_parser, long)]
+    pub osc_port: Option<u16>,
+    /// The network address the OSC server listens on.
+    ///
+    /// By default only messages from the same computer are accepted. Use '0.0.0.0' to accept
+    /// messages from other computers. This is also needed to use '--osc-send' with another computer
+    /// while '--osc-port' is set, since the same socket is used for sending.
+    #[cfg(feature = "standalone_osc")]
+    #[clap(value_parser, long, default_value = "127.0.0.1")]
+    pub osc_bind: IpAddr,
+    /// Send OSC messages to this address ('127.0.0.1:9001').
+    ///
+    /// Parameter changes are sent using the same addresses that are used to set them, and the main
+    /// output's peak levels are sent to '/meter/output' about 30 times per second.
+    #[cfg(feature = "standalone_osc")]
+    #[clap(value_parser, long)]
+    pub osc_send: Option<SocketAddr>,
+
     /// The transport's tempo.
+    ///
+    /// The JACK backend follows JACK's transport instead. The tempo and time signature options are
+    /// only used there when no JACK client is acting as the timebase master.
     #[clap(value_parser, long, default_value = "120")]
     pub tempo: f32,
     /// The time signature's numerator.
@@ -96,6 +189,35 @@ pub struct WrapperConfig {
     /// The time signature's denominator.
     #[clap(value_parser, long, default_value = "4")]
     pub timesig_denom: u32,
+
+    /// Settings passed to
+    /// [`nih_export_standalone_with_config()`][super::nih_export_standalone_with_config()]. These
+    /// cannot be set from the command line.
+    #[clap(skip)]
+    pub standalone: StandaloneConfig,
+}
+
+/// Settings for a standalone application that are set by the plugin's author instead of by the
+/// user. Pass this to
+/// [`nih_export_standalone_with_config()`][super::nih_export_standalone_with_config()].
+///
+/// The editor window is always opened at the size returned by
+/// [`Editor::size()`][crate::prelude::Editor::size()]. Setting a window icon is not supported yet
+/// because baseview cannot set window icons.
+#[derive(Debug, Clone, Default)]
+pub struct StandaloneConfig {
+    /// The title of the editor's window. Defaults to the plugin's name followed by its version.
+    pub window_title: Option<String>,
+    /// The plugin's VST3 class ID, usually `Vst3Plugin::VST3_CLASS_ID`. Loading a `.vstpreset`
+    /// file for another plugin with `--load-state` fails. When this is not set, the class ID from
+    /// the [`PluginFormat::vst3()`] entry in [`formats`][Self::formats] is used instead. If neither
+    /// is set, then `.vstpreset` files cannot be loaded at all since there is no way to tell which
+    /// plugin they belong to.
+    pub vst3_class_id: Option<[u8; 16]>,
+    /// The other formats the plugin is exported as. These are listed in the capabilities summary
+    /// printed by `--info` together with the standalone format itself. Use
+    /// [`PluginFormat::clap()`] and [`PluginFormat::vst3()`] to create these.
+    pub formats: Vec<PluginFormat>,
 }
 
 /// Determines which audio and MIDI backend should be used.
@@ -120,6 +242,29 @@ pub enum BackendType {
     Dummy,
 }
 
+impl StandaloneConfig {
+    /// The editor window's title for plugin `P`.
+    pub fn window_title<P: Plugin>(&self) -> String {
+        match &self.window_title {
+            Some(window_title) => window_title.clone(),
+            None => format!("{} {}", P::NAME, P::VERSION),
+        }
+    }
+
+    /// The VST3 class ID `.vstpreset` files loaded with `--load-state` need to match. See
+    /// [`vst3_class_id`][Self::vst3_class_id].
+    pub fn expected_vst3_class_id(&self) -> Option<[u8; 16]> {
+        self.vst3_class_id.or_else(|| {
+            self.formats.iter().find_map(|format| match format {
+                PluginFormat::Vst3 { class_id, .. } => {
+                    super::vstpreset::parse_class_id(class_id.as_bytes())
+                }
+                _ => None,
+            })
+        })
+    }
+}
+
 impl WrapperConfig {
     /// Get the audio IO layout for a plugin based on this configuration. Exits the application if
     /// the IO layout could not be parsed from the config. This doesn't return a `Result` to be able to differentiate between backend-specific errors and config parsing errors.
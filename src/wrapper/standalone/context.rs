@@ -1,11 +1,13 @@
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use super::backend::Backend;
 use super::wrapper::{Task, Wrapper};
 use crate::prelude::{
-    GuiContext, InitContext, ParamPtr, Plugin, PluginApi, PluginNoteEvent, ProcessContext,
-    Transport,
+    GuiContext, HostInfo, InitContext, ParamPtr, Plugin, PluginApi, PluginNoteEvent,
+    ProcessContext, ProcessMode, Transport,
 };
+use crate::util::ProcessLoad;
 
 /// An [`InitContext`] implementation for the standalone wrapper.
 pub(crate) struct WrapperInitContext<'a, P: Plugin, B: Backend<P>> {
@@ -41,6 +43,11 @@ impl<P: Plugin, B: Backend<P>> InitContext<P> for WrapperInitContext<'_, P, B> {
         PluginApi::Standalone
     }
 
+    fn host_info(&self) -> Option<HostInfo> {
+        // There is no host
+        None
+    }
+
     fn execute(&self, task: P::BackgroundTask) {
         (self.wrapper.task_executor.lock())(task);
     }
@@ -49,6 +56,10 @@ impl<P: Plugin, B: Backend<P>> InitContext<P> for WrapperInitContext<'_, P, B> {
         self.wrapper.set_latency_samples(samples)
     }
 
+    fn set_tail_samples(&self, _samples: u32) {
+        // The standalone wrapper processes audio until it's closed, so the tail length is not used
+    }
+
     fn set_current_voice_capacity(&self, _capacity: u32) {
         // This is only supported by CLAP
     }
@@ -74,6 +85,14 @@ impl<P: Plugin, B: Backend<P>> ProcessContext<P> for WrapperProcessContext<'_, P
         &self.transport
     }
 
+    fn process_mode(&self) -> ProcessMode {
+        self.wrapper.process_mode()
+    }
+
+    fn is_editor_open(&self) -> bool {
+        self.wrapper.editor_open.load(Ordering::SeqCst)
+    }
+
     fn next_event(&mut self) -> Option<PluginNoteEvent<P>> {
         // We'll pretend we're a queue, choo choo
         if self.input_events_idx < self.input_events.len() {
@@ -94,9 +113,25 @@ impl<P: Plugin, B: Backend<P>> ProcessContext<P> for WrapperProcessContext<'_, P
         self.wrapper.set_latency_samples(samples)
     }
 
+    fn set_tail_samples(&self, _samples: u32) {
+        // The standalone wrapper processes audio until it's closed, so the tail length is not used
+    }
+
+    fn set_gain_reduction(&self, gain_reduction_db: f32) {
+        // There's no host to show this, but the parameter is still updated for the editor
+        self.wrapper.gain_reduction_meter.report(gain_reduction_db)
+    }
+
     fn set_current_voice_capacity(&self, _capacity: u32) {
         // This is only supported by CLAP
     }
+
+    fn execute_parallel(&self, num_tasks: u32, task: &(dyn Fn(u32) + Sync)) {
+        // There's no host thread pool here, so we'll just run these tasks sequentially
+        for task_index in 0..num_tasks {
+            task(task_index);
+        }
+    }
 }
 
 impl<P: Plugin, B: Backend<P>> GuiContext for WrapperGuiContext<P, B> {
@@ -125,7 +160,7 @@ impl<P: Plugin, B: Backend<P>> GuiContext for WrapperGuiContext<P, B> {
     }
 
     unsafe fn raw_set_parameter_normalized(&self, param: ParamPtr, normalized: f32) {
-        self.wrapper.set_parameter(param, normalized);
+        self.wrapper.set_parameter(param, normalized, false);
 
         #[cfg(debug_assertions)]
         match self.wrapper.param_id_from_ptr(param) {
@@ -152,6 +187,18 @@ impl<P: Plugin, B: Backend<P>> GuiContext for WrapperGuiContext<P, B> {
         }
     }
 
+    unsafe fn raw_set_parameters_normalized(
+        &self,
+        values: &[(ParamPtr, f32)],
+        reset_smoothers: bool,
+    ) {
+        // There are no gestures or host notifications to coalesce here
+        for &(param, normalized) in values {
+            self.wrapper
+                .set_parameter(param, normalized, reset_smoothers);
+        }
+    }
+
     fn get_state(&self) -> crate::wrapper::state::PluginState {
         self.wrapper.get_state_object()
     }
@@ -159,4 +206,8 @@ impl<P: Plugin, B: Backend<P>> GuiContext for WrapperGuiContext<P, B> {
     fn set_state(&self, state: crate::wrapper::state::PluginState) {
         self.wrapper.set_state_object_from_gui(state)
     }
+
+    fn process_load(&self) -> Option<Arc<ProcessLoad>> {
+        ProcessLoad::ENABLED.then(|| self.wrapper.process_load.clone())
+    }
 }
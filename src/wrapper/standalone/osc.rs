@@ -0,0 +1,543 @@
+//! A minimal OSC server for controlling the standalone application's parameters over the network.
+//! Only enabled with the `standalone_osc` feature.
+//!
+//! # Address scheme
+//!
+//! Incoming messages, received on the port passed to `--osc-port`. The server only listens on the
+//! loopback interface unless another address is passed to `--osc-bind`:
+//!
+//! - `/param/<id> <value>` sets the parameter with string ID `<id>` to a normalized `[0, 1]` value.
+//!   Values outside of that range are clamped.
+//! - `/param/<id>/plain <value>` sets the parameter to a plain value, like `440.0` for a frequency
+//!   parameter in Hertz. The value is clamped to the parameter's range and snapped to the nearest
+//!   step for stepped parameters.
+//! - Sending either of these addresses without an argument replies to the sender with the
+//!   parameter's current value using the same address.
+//!
+//! Values can be 32-bit or 64-bit floats, 32-bit integers, or the `T` and `F` booleans. Bundles are
+//! supported, but their time tags are ignored and their messages are applied immediately.
+//!
+//! Outgoing messages, sent to the address passed to `--osc-send`:
+//!
+//! - `/param/<id> <normalized>` and `/param/<id>/plain <plain>` whenever a parameter's value
+//!   changes, regardless of whether the change came from OSC, the editor, or a loaded state. All
+//!   parameters are sent once when the server starts.
+//! - `/meter/output <peak>...` with the main output's peak amplitude per channel since the last
+//!   message, sent about 30 times per second.
+//! - `/meter/process_load <load>` with the process function's last measured load, sent at the same
+//!   rate. Only sent when the `process_profiling` feature is enabled.
+
+use anyhow::{bail, Context, Result};
+use atomic_float::AtomicF32;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::prelude::{Buffer, ParamPtr};
+use crate::util::ProcessLoad;
+
+/// How often the meters are sent. This is also the socket's read timeout, so this also determines
+/// how quickly the server thread notices that it should terminate.
+const METER_INTERVAL: Duration = Duration::from_millis(33);
+/// The largest packet we can receive. Parameter messages are tiny, so this is plenty.
+const MAX_PACKET_SIZE: usize = 4096;
+
+/// The OSC server's socket and the meter values that are sent with it. The audio thread only
+/// touches the atomic peak values.
+pub struct OscServer {
+    socket: UdpSocket,
+    /// Whether incoming messages should be handled. This is `false` if the socket is only used to
+    /// send messages.
+    listening: bool,
+    /// Where outgoing messages are sent to, if anywhere.
+    send_target: Option<SocketAddr>,
+    /// The main output's peak amplitude per channel since the meters were last sent.
+    output_peaks: Vec<AtomicF32>,
+}
+
+/// An argument in an incoming OSC message. Only the types that can be used as a parameter value
+/// are supported.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OscArg {
+    Float(f32),
+    Double(f64),
+    Int(i32),
+    Bool(bool),
+}
+
+/// A decoded OSC message.
+#[derive(Debug, Clone, PartialEq)]
+struct OscMessage<'a> {
+    address: &'a str,
+    args: Vec<OscArg>,
+}
+
+impl OscArg {
+    fn as_f32(self) -> f32 {
+        match self {
+            OscArg::Float(value) => value,
+            OscArg::Double(value) => value as f32,
+            OscArg::Int(value) => value as f32,
+            OscArg::Bool(value) => value as u8 as f32,
+        }
+    }
+}
+
+impl OscServer {
+    /// Bind the server's socket. If `port` is set, then the server listens on that port on
+    /// `bind_address`. Otherwise a random port is used, and the socket is only used to send
+    /// messages to `send_target`. Messages received on that port are ignored.
+    pub fn new(
+        bind_address: IpAddr,
+        port: Option<u16>,
+        send_target: Option<SocketAddr>,
+        num_output_channels: usize,
+    ) -> Result<Self> {
+        let bind_address = match port {
+            Some(port) => SocketAddr::new(bind_address, port),
+            None => SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0),
+        };
+        let socket = UdpSocket::bind(bind_address)
+            .with_context(|| format!("Could not bind the OSC server to {bind_address}"))?;
+        socket
+            .set_read_timeout(Some(METER_INTERVAL))
+            .context("Could not configure the OSC server's socket")?;
+
+        if port.is_some() {
+            nih_log!("Listening for OSC messages on {bind_address}");
+        }
+        if let Some(send_target) = send_target {
+            nih_log!("Sending OSC messages to {send_target}");
+        }
+
+        Ok(Self {
+            socket,
+            listening: port.is_some(),
+            send_target,
+            output_peaks: (0..num_output_channels)
+                .map(|_| AtomicF32::new(0.0))
+                .collect(),
+        })
+    }
+
+    /// Handle incoming messages and send the meters until `should_terminate` is set. Parameters
+    /// are looked up by their ID in `param_id_to_ptr`, and parameter changes are passed to
+    /// `set_parameter` as normalized values.
+    pub fn run(
+        &self,
+        param_id_to_ptr: &HashMap<String, ParamPtr>,
+        process_load: &ProcessLoad,
+        should_terminate: &AtomicBool,
+        set_parameter: impl Fn(ParamPtr, f32) -> bool,
+    ) {
+        let mut packet = [0u8; MAX_PACKET_SIZE];
+        let mut last_meter_update = Instant::now();
+        while !should_terminate.load(Ordering::SeqCst) {
+            // The read timeout makes this return regularly even if nothing is being received
+            if let Ok((size, sender)) = self.socket.recv_from(&mut packet) {
+                // Without a listening port the socket is only used for sending
+                if self.listening {
+                    match decode_packet(&packet[..size]) {
+                        Ok(messages) => {
+                            for message in messages {
+                                self.handle_message(
+                                    param_id_to_ptr,
+                                    &message,
+                                    sender,
+                                    &set_parameter,
+                                );
+                            }
+                        }
+                        Err(err) => {
+                            nih_warn!("Ignoring an invalid OSC packet from {sender}: {err:#}")
+                        }
+                    }
+                }
+            }
+
+            if last_meter_update.elapsed() >= METER_INTERVAL {
+                self.send_meters(process_load);
+                last_meter_update = Instant::now();
+            }
+        }
+    }
+
+    /// Send a parameter's new value to the send target, if there is one.
+    pub fn send_param_value(&self, param_id: &str, param_ptr: ParamPtr, normalized_value: f32) {
+        if let Some(send_target) = self.send_target {
+            self.send_param_value_to(send_target, param_id, param_ptr, normalized_value);
+        }
+    }
+
+    /// Keep track of the main output's peak amplitude for the meters. Called from the audio thread
+    /// after the plugin has processed `buffer`.
+    pub fn update_output_peaks(&self, buffer: &Buffer) {
+        for (channel, peak) in buffer.as_slice_immutable().iter().zip(&self.output_peaks) {
+            let channel_peak = channel
+                .iter()
+                .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+            peak.fetch_max(channel_peak, Ordering::Relaxed);
+        }
+    }
+
+    fn handle_message(
+        &self,
+        param_id_to_ptr: &HashMap<String, ParamPtr>,
+        message: &OscMessage,
+        sender: SocketAddr,
+        set_parameter: &impl Fn(ParamPtr, f32) -> bool,
+    ) {
+        let Some(param_address) = message.address.strip_prefix("/param/") else {
+            nih_warn!(
+                "Ignoring OSC message for unknown address '{}'",
+                message.address
+            );
+            return;
+        };
+        let (param_id, is_plain) = match param_address.strip_suffix("/plain") {
+            Some(param_id) => (param_id, true),
+            None => (param_address, false),
+        };
+        let Some(&param_ptr) = param_id_to_ptr.get(param_id) else {
+            nih_warn!("Ignoring OSC message for unknown parameter '{param_id}'");
+            return;
+        };
+
+        match message.args.first() {
+            Some(value) => {
+                let normalized_value = if is_plain {
+                    unsafe { param_ptr.preview_normalized(value.as_f32()) }
+                } else {
+                    value.as_f32().clamp(0.0, 1.0)
+                };
+                if !set_parameter(param_ptr, normalized_value) {
+                    nih_warn!("Could not set parameter '{param_id}' from OSC");
+                }
+            }
+            // Without an argument, the message is a query for the parameter's current value
+            None => {
+                let normalized_value = unsafe { param_ptr.unmodulated_normalized_value() };
+                let reply = if is_plain {
+                    unsafe { param_ptr.preview_plain(normalized_value) }
+                } else {
+                    normalized_value
+                };
+                self.send(sender, &encode_message(message.address, &[reply]));
+            }
+        }
+    }
+
+    fn send_param_value_to(
+        &self,
+        target: SocketAddr,
+        param_id: &str,
+        param_ptr: ParamPtr,
+        normalized_value: f32,
+    ) {
+        let plain_value = unsafe { param_ptr.preview_plain(normalized_value) };
+        self.send(
+            target,
+            &encode_message(&format!("/param/{param_id}"), &[normalized_value]),
+        );
+        self.send(
+            target,
+            &encode_message(&format!("/param/{param_id}/plain"), &[plain_value]),
+        );
+    }
+
+    fn send_meters(&self, process_load: &ProcessLoad) {
+        let Some(send_target) = self.send_target else {
+            return;
+        };
+
+        let output_peaks: Vec<f32> = self
+            .output_peaks
+            .iter()
+            .map(|peak| peak.swap(0.0, Ordering::Relaxed))
+            .collect();
+        self.send(send_target, &encode_message("/meter/output", &output_peaks));
+
+        if ProcessLoad::ENABLED {
+            self.send(
+                send_target,
+                &encode_message("/meter/process_load", &[process_load.last()]),
+            );
+        }
+    }
+
+    fn send(&self, target: SocketAddr, packet: &[u8]) {
+        // Nothing may be listening on the other end, so failing to send is not an error
+        if let Err(err) = self.socket.send_to(packet, target) {
+            nih_trace!("Could not send an OSC message to {target}: {err}");
+        }
+    }
+}
+
+/// Send all of the plugin's current parameter values to the server's send target. Used when the
+/// server starts and when the plugin's state has been restored.
+pub fn send_all_param_values<'a>(
+    server: &OscServer,
+    params: impl IntoIterator<Item = (&'a String, &'a ParamPtr)>,
+) {
+    for (param_id, param_ptr) in params {
+        server.send_param_value(param_id, *param_ptr, unsafe {
+            param_ptr.unmodulated_normalized_value()
+        });
+    }
+}
+
+/// Decode an OSC packet containing either a single message or a bundle. The messages in nested
+/// bundles are flattened.
+fn decode_packet(packet: &[u8]) -> Result<Vec<OscMessage<'_>>> {
+    let mut messages = Vec::new();
+    decode_packet_into(packet, &mut messages)?;
+
+    Ok(messages)
+}
+
+fn decode_packet_into<'a>(packet: &'a [u8], messages: &mut Vec<OscMessage<'a>>) -> Result<()> {
+    if let Some(elements) = packet.strip_prefix(b"#bundle\0") {
+        // The 8-byte time tag is ignored, after which we get a list of size-prefixed elements
+        let mut offset = 8;
+        while offset < elements.len() {
+            let size = read_i32(elements, &mut offset)?;
+            let element = usize::try_from(size)
+                .ok()
+                .and_then(|size| elements.get(offset..offset + size))
+                .context("Bundle element size out of bounds")?;
+            decode_packet_into(element, messages)?;
+            offset += element.len();
+        }
+
+        return Ok(());
+    }
+
+    let mut offset = 0;
+    let address = read_string(packet, &mut offset)?;
+    if !address.starts_with('/') {
+        bail!("Invalid OSC address '{address}'");
+    }
+
+    // Very old implementations may omit the type tag string if there are no arguments
+    let mut args = Vec::new();
+    if offset < packet.len() {
+        let type_tags = read_string(packet, &mut offset)?;
+        let Some(type_tags) = type_tags.strip_prefix(',') else {
+            bail!("Missing type tag string");
+        };
+
+        for type_tag in type_tags.chars() {
+            args.push(match type_tag {
+                'f' => OscArg::Float(f32::from_bits(read_i32(packet, &mut offset)? as u32)),
+                'd' => OscArg::Double(f64::from_bits(read_i64(packet, &mut offset)? as u64)),
+                'i' => OscArg::Int(read_i32(packet, &mut offset)?),
+                'T' => OscArg::Bool(true),
+                'F' => OscArg::Bool(false),
+                _ => bail!("Unsupported argument type '{type_tag}'"),
+            });
+        }
+    }
+
+    messages.push(OscMessage { address, args });
+
+    Ok(())
+}
+
+/// Encode an OSC message with any number of float arguments.
+fn encode_message(address: &str, args: &[f32]) -> Vec<u8> {
+    let mut packet = Vec::new();
+    write_string(&mut packet, address);
+    write_string(&mut packet, &format!(",{}", "f".repeat(args.len())));
+    for arg in args {
+        packet.extend_from_slice(&arg.to_be_bytes());
+    }
+
+    packet
+}
+
+/// Read a null terminated string padded to a multiple of four bytes.
+fn read_string<'a>(packet: &'a [u8], offset: &mut usize) -> Result<&'a str> {
+    let remaining = packet.get(*offset..).unwrap_or_default();
+    let length = remaining
+        .iter()
+        .position(|&byte| byte == 0)
+        .context("Unterminated string")?;
+    let string = std::str::from_utf8(&remaining[..length]).context("Invalid UTF-8 in string")?;
+    *offset += padded_length(length + 1);
+
+    Ok(string)
+}
+
+fn read_i32(packet: &[u8], offset: &mut usize) -> Result<i32> {
+    let bytes = packet
+        .get(*offset..*offset + 4)
+        .context("Unexpected end of packet")?;
+    *offset += 4;
+
+    Ok(i32::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_i64(packet: &[u8], offset: &mut usize) -> Result<i64> {
+    let bytes = packet
+        .get(*offset..*offset + 8)
+        .context("Unexpected end of packet")?;
+    *offset += 8;
+
+    Ok(i64::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+/// Write a null terminated string padded to a multiple of four bytes.
+fn write_string(packet: &mut Vec<u8>, string: &str) {
+    packet.extend_from_slice(string.as_bytes());
+    packet.resize(
+        packet.len() + padded_length(string.len() + 1) - string.len(),
+        0,
+    );
+}
+
+fn padded_length(length: usize) -> usize {
+    (length + 3) & !3
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::prelude::{Param, Params};
+    use crate::testing::TestParams;
+
+    #[test]
+    fn encode_and_decode() {
+        let packet = encode_message("/param/gain", &[0.5]);
+        assert_eq!(packet.len(), 20);
+        assert_eq!(&packet[..12], b"/param/gain\0");
+        assert_eq!(&packet[12..16], b",f\0\0");
+
+        assert_eq!(
+            decode_packet(&packet).unwrap(),
+            vec![OscMessage {
+                address: "/param/gain",
+                args: vec![OscArg::Float(0.5)]
+            }]
+        );
+    }
+
+    #[test]
+    fn decode_argument_types() {
+        let mut packet = Vec::new();
+        write_string(&mut packet, "/param/freq/plain");
+        write_string(&mut packet, ",difT");
+        packet.extend_from_slice(&440.0f64.to_be_bytes());
+        packet.extend_from_slice(&(-3i32).to_be_bytes());
+        packet.extend_from_slice(&0.25f32.to_be_bytes());
+
+        let messages = decode_packet(&packet).unwrap();
+        assert_eq!(messages[0].address, "/param/freq/plain");
+        assert_eq!(
+            messages[0]
+                .args
+                .iter()
+                .map(|arg| arg.as_f32())
+                .collect::<Vec<_>>(),
+            [440.0, -3.0, 0.25, 1.0]
+        );
+
+        // Queries don't have any arguments, and old implementations may omit the type tags
+        assert!(
+            decode_packet(&encode_message("/param/gain", &[])).unwrap()[0]
+                .args
+                .is_empty()
+        );
+        assert!(decode_packet(b"/param/gain\0").unwrap()[0].args.is_empty());
+    }
+
+    #[test]
+    fn decode_bundle() {
+        let first = encode_message("/param/gain", &[0.5]);
+        let second = encode_message("/param/mix", &[1.0]);
+
+        let mut packet = b"#bundle\0".to_vec();
+        packet.extend_from_slice(&1u64.to_be_bytes());
+        for message in [&first, &second] {
+            packet.extend_from_slice(&(message.len() as i32).to_be_bytes());
+            packet.extend_from_slice(message);
+        }
+
+        let messages = decode_packet(&packet).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].address, "/param/gain");
+        assert_eq!(messages[1].address, "/param/mix");
+    }
+
+    #[test]
+    fn decode_invalid_packets() {
+        assert!(decode_packet(b"").is_err());
+        assert!(decode_packet(b"/param/gain").is_err());
+        assert!(decode_packet(b"param\0\0\0,f\0\0\0\0\0\0").is_err());
+        // The argument is missing
+        assert!(decode_packet(b"/gain\0\0\0,f\0\0").is_err());
+        // Strings are not supported as parameter values
+        assert!(decode_packet(b"/gain\0\0\0,s\0\0foo\0").is_err());
+
+        let mut bundle = b"#bundle\0".to_vec();
+        bundle.extend_from_slice(&1u64.to_be_bytes());
+        bundle.extend_from_slice(&100i32.to_be_bytes());
+        assert!(decode_packet(&bundle).is_err());
+    }
+
+    #[test]
+    fn bind_address() {
+        let server = OscServer::new(Ipv4Addr::LOCALHOST.into(), Some(0), None, 2).unwrap();
+        assert!(server.listening);
+        assert!(server.socket.local_addr().unwrap().ip().is_loopback());
+
+        // Without a port the socket is only used for sending
+        let server = OscServer::new(Ipv4Addr::LOCALHOST.into(), None, None, 2).unwrap();
+        assert!(!server.listening);
+    }
+
+    #[test]
+    fn set_params_by_id() {
+        let params = TestParams::default();
+        let param_id_to_ptr: HashMap<String, ParamPtr> = params
+            .param_map()
+            .into_iter()
+            .map(|(id, ptr, _)| (id, ptr))
+            .collect();
+        let server = OscServer::new(Ipv4Addr::LOCALHOST.into(), Some(0), None, 2).unwrap();
+        let sender = server.socket.local_addr().unwrap();
+
+        let param_changes = RefCell::new(Vec::new());
+        let set_parameter = |param_ptr: ParamPtr, normalized_value: f32| {
+            param_changes
+                .borrow_mut()
+                .push((param_ptr, normalized_value));
+            true
+        };
+        for (address, value) in [
+            ("/param/gain", 0.25),
+            ("/param/pitch", 2.0),
+            ("/param/gr/plain", 0.5),
+            ("/param/unknown", 0.5),
+            ("/param/Gain", 0.5),
+        ] {
+            let message = OscMessage {
+                address,
+                args: vec![OscArg::Float(value)],
+            };
+            server.handle_message(&param_id_to_ptr, &message, sender, &set_parameter);
+        }
+
+        // Normalized values are clamped, and unknown or differently cased IDs are ignored
+        assert_eq!(
+            param_changes.into_inner(),
+            [
+                (params.gain.as_ptr(), 0.25),
+                (params.pitch.as_ptr(), 1.0),
+                (params.gain_reduction.as_ptr(), 0.5)
+            ]
+        );
+    }
+}
@@ -0,0 +1,286 @@
+//! Deterministic test signals for the `--signal` option. These are fed to the plugin's main input
+//! when rendering offline, which makes it possible to measure a plugin's impulse and frequency
+//! responses from the command line.
+
+use std::f64::consts::TAU;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::util::{PinkNoise, Rng};
+
+/// The length of a test signal if it does not specify one, in seconds.
+const DEFAULT_LENGTH: f32 = 1.0;
+/// The gain applied to the sine waves, sweeps, and noise. This leaves some headroom for plugins that
+/// add gain. Impulses are always generated at full scale.
+const SIGNAL_GAIN: f32 = 0.5;
+/// The seed for the noise signals, so renders using noise are reproducible.
+const NOISE_SEED: u64 = 0;
+
+/// A test signal parsed from the `--signal` option's value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TestSignal {
+    /// A sine wave at a fixed frequency. `sine:<frequency>[:<length>]`.
+    Sine { frequency: f32, length: f32 },
+    /// An exponential sine sweep from `start` to `end` Hz. `sweep:<start>:<end>[:<length>]`.
+    Sweep { start: f32, end: f32, length: f32 },
+    /// A single full-scale sample followed by silence. `impulse[:<length>]`.
+    Impulse { length: f32 },
+    /// White noise. `noise[:<length>]` or `white[:<length>]`.
+    WhiteNoise { length: f32 },
+    /// Pink noise. `pink[:<length>]`.
+    PinkNoise { length: f32 },
+}
+
+/// Generates the samples for a [`TestSignal`].
+pub struct TestSignalGenerator {
+    signal: TestSignal,
+    sample_rate: f32,
+    /// The total length of the signal, in samples.
+    num_samples: usize,
+    /// The index of the next sample.
+    pos: usize,
+    /// The sine wave's phase in `[0, 1)`.
+    phase: f64,
+    rng: Rng,
+    pink_noise: PinkNoise,
+}
+
+impl FromStr for TestSignal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':');
+        let kind = parts.next().unwrap_or_default();
+        let args: Vec<&str> = parts.collect();
+
+        let (num_required_args, signal_syntax) = match kind {
+            "sine" => (1, "sine:<frequency>[:<length>]"),
+            "sweep" => (2, "sweep:<start>:<end>[:<length>]"),
+            "impulse" => (0, "impulse[:<length>]"),
+            "noise" | "white" => (0, "noise[:<length>]"),
+            "pink" => (0, "pink[:<length>]"),
+            _ => {
+                return Err(format!(
+                    "Unknown signal '{kind}', expected one of 'sine', 'sweep', 'impulse', \
+                     'noise', or 'pink'"
+                ))
+            }
+        };
+        if args.len() != num_required_args && args.len() != num_required_args + 1 {
+            return Err(format!("Expected '{signal_syntax}'"));
+        }
+
+        let length = match args.get(num_required_args) {
+            Some(length) => parse_length(length)?,
+            None => DEFAULT_LENGTH,
+        };
+        Ok(match kind {
+            "sine" => TestSignal::Sine {
+                frequency: parse_frequency(args[0])?,
+                length,
+            },
+            "sweep" => TestSignal::Sweep {
+                start: parse_frequency(args[0])?,
+                end: parse_frequency(args[1])?,
+                length,
+            },
+            "impulse" => TestSignal::Impulse { length },
+            "noise" | "white" => TestSignal::WhiteNoise { length },
+            "pink" => TestSignal::PinkNoise { length },
+            _ => unreachable!(),
+        })
+    }
+}
+
+impl fmt::Display for TestSignal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TestSignal::Sine { frequency, length } => write!(f, "sine:{frequency}:{length}s"),
+            TestSignal::Sweep { start, end, length } => {
+                write!(f, "sweep:{start}:{end}:{length}s")
+            }
+            TestSignal::Impulse { length } => write!(f, "impulse:{length}s"),
+            TestSignal::WhiteNoise { length } => write!(f, "noise:{length}s"),
+            TestSignal::PinkNoise { length } => write!(f, "pink:{length}s"),
+        }
+    }
+}
+
+impl TestSignal {
+    /// The signal's length in seconds.
+    pub fn length(&self) -> f32 {
+        match self {
+            TestSignal::Sine { length, .. }
+            | TestSignal::Sweep { length, .. }
+            | TestSignal::Impulse { length }
+            | TestSignal::WhiteNoise { length }
+            | TestSignal::PinkNoise { length } => *length,
+        }
+    }
+
+    /// Create a generator for this signal at the specified sample rate.
+    pub fn generator(&self, sample_rate: f32) -> TestSignalGenerator {
+        let rng = Rng::new(NOISE_SEED);
+
+        TestSignalGenerator {
+            signal: self.clone(),
+            sample_rate,
+            num_samples: (self.length() * sample_rate).round() as usize,
+            pos: 0,
+            phase: 0.0,
+            pink_noise: PinkNoise::new(rng.clone()),
+            rng,
+        }
+    }
+}
+
+impl TestSignalGenerator {
+    /// The total length of the signal, in samples.
+    pub fn num_samples(&self) -> usize {
+        self.num_samples
+    }
+
+    /// Whether the entire signal has been generated.
+    pub fn is_finished(&self) -> bool {
+        self.pos >= self.num_samples
+    }
+
+    /// Fill `output` with the next samples. Anything past the end of the signal is silent.
+    pub fn fill(&mut self, output: &mut [f32]) {
+        for sample in output {
+            *sample = if self.is_finished() {
+                0.0
+            } else {
+                self.next_sample()
+            };
+            self.pos += 1;
+        }
+    }
+
+    fn next_sample(&mut self) -> f32 {
+        match self.signal {
+            TestSignal::Sine { frequency, .. } => {
+                let sample = (self.phase * TAU).sin() as f32;
+                self.phase = (self.phase + (frequency as f64 / self.sample_rate as f64)).fract();
+
+                sample * SIGNAL_GAIN
+            }
+            TestSignal::Sweep { start, end, length } => {
+                // The frequency increases exponentially, so every octave takes the same amount of
+                // time. The phase is computed directly from the time to avoid accumulating errors.
+                let start = start as f64;
+                let rate = (end as f64 / start).ln() / length as f64;
+                let time = self.pos as f64 / self.sample_rate as f64;
+                let phase = if rate == 0.0 {
+                    start * time
+                } else {
+                    start * ((rate * time).exp() - 1.0) / rate
+                };
+
+                (phase.fract() * TAU).sin() as f32 * SIGNAL_GAIN
+            }
+            TestSignal::Impulse { .. } => {
+                if self.pos == 0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            TestSignal::WhiteNoise { .. } => self.rng.next_bipolar_f32() * SIGNAL_GAIN,
+            TestSignal::PinkNoise { .. } => self.pink_noise.next_sample() * SIGNAL_GAIN,
+        }
+    }
+}
+
+/// Parse a frequency in Hz. The unit is optional.
+fn parse_frequency(s: &str) -> Result<f32, String> {
+    let frequency: f32 = s
+        .trim_end_matches("Hz")
+        .parse()
+        .map_err(|_| format!("'{s}' is not a valid frequency"))?;
+    if frequency > 0.0 && frequency.is_finite() {
+        Ok(frequency)
+    } else {
+        Err(format!("'{s}' is not a valid frequency"))
+    }
+}
+
+/// Parse a length, either in seconds (`5` or `5s`) or in milliseconds (`500ms`).
+pub fn parse_length(s: &str) -> Result<f32, String> {
+    let length = match s.strip_suffix("ms") {
+        Some(milliseconds) => milliseconds.parse::<f32>().map(|length| length / 1000.0),
+        None => s.trim_end_matches('s').parse::<f32>(),
+    }
+    .map_err(|_| format!("'{s}' is not a valid length"))?;
+    if length > 0.0 && length.is_finite() {
+        Ok(length)
+    } else {
+        Err(format!("'{s}' is not a valid length"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_signals() {
+        assert_eq!(
+            "sine:1000".parse(),
+            Ok(TestSignal::Sine {
+                frequency: 1000.0,
+                length: DEFAULT_LENGTH
+            })
+        );
+        assert_eq!(
+            "sweep:20:20000:5s".parse(),
+            Ok(TestSignal::Sweep {
+                start: 20.0,
+                end: 20000.0,
+                length: 5.0
+            })
+        );
+        assert_eq!(
+            "impulse:500ms".parse(),
+            Ok(TestSignal::Impulse { length: 0.5 })
+        );
+        assert_eq!(
+            "white".parse(),
+            Ok(TestSignal::WhiteNoise {
+                length: DEFAULT_LENGTH
+            })
+        );
+
+        assert!("sine".parse::<TestSignal>().is_err());
+        assert!("sine:-5".parse::<TestSignal>().is_err());
+        assert!("sweep:20:20000:5s:1".parse::<TestSignal>().is_err());
+        assert!("impulse:0s".parse::<TestSignal>().is_err());
+        assert!("square:100".parse::<TestSignal>().is_err());
+    }
+
+    #[test]
+    fn impulse() {
+        let mut generator = TestSignal::Impulse { length: 0.01 }.generator(1000.0);
+        let mut output = [0.5; 16];
+        generator.fill(&mut output);
+
+        assert_eq!(generator.num_samples(), 10);
+        assert!(generator.is_finished());
+        assert_eq!(output[0], 1.0);
+        assert!(output[1..].iter().all(|sample| *sample == 0.0));
+    }
+
+    #[test]
+    fn deterministic() {
+        let signal: TestSignal = "sweep:20:20000:0.1".parse().unwrap();
+        let mut output1 = [0.0; 4800];
+        let mut output2 = [0.0; 4800];
+        signal.generator(48000.0).fill(&mut output1);
+        signal.generator(48000.0).fill(&mut output2);
+
+        assert_eq!(output1, output2);
+        assert!(output1
+            .iter()
+            .all(|sample| sample.abs() <= SIGNAL_GAIN && sample.is_finite()));
+    }
+}
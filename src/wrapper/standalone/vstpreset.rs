@@ -0,0 +1,278 @@
+//! Reading plugin state from VST3 preset (`.vstpreset`) files and from NIH-plug's own JSON state
+//! files for the `--load-state` option.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use crate::wrapper::state::{self, PluginState};
+
+/// The magic bytes at the start of every `.vstpreset` file.
+const HEADER_MAGIC: &[u8; 4] = b"VST3";
+/// The header consists of the magic bytes, a 32-bit version number, the class ID as 32 ASCII hex
+/// characters, and a 64-bit offset to the chunk list.
+const HEADER_SIZE: usize = 4 + 4 + 32 + 8;
+/// The magic bytes at the start of the chunk list.
+const CHUNK_LIST_MAGIC: &[u8; 4] = b"List";
+/// Every chunk list entry consists of a chunk ID, a 64-bit offset, and a 64-bit size.
+const CHUNK_LIST_ENTRY_SIZE: usize = 4 + 8 + 8;
+/// The chunk containing the component's state. NIH-plug stores the plugin's entire state here.
+const COMPONENT_STATE_CHUNK_ID: &[u8; 4] = b"Comp";
+/// The chunk containing the edit controller's state. This is empty for NIH-plug plugins.
+const CONTROLLER_STATE_CHUNK_ID: &[u8; 4] = b"Cont";
+
+/// The contents of a `.vstpreset` file.
+#[derive(Debug)]
+pub struct VstPreset<'a> {
+    /// The plugin's class ID, in the same byte order as `Vst3Plugin::VST3_CLASS_ID`.
+    pub class_id: [u8; 16],
+    pub component_state: &'a [u8],
+    pub controller_state: Option<&'a [u8]>,
+}
+
+/// Read a plugin state file passed to `--load-state`. This can be either a `.vstpreset` file or a
+/// JSON state file as produced by [`GuiContext::get_state()`][crate::prelude::GuiContext::get_state()],
+/// which are told apart by their magic bytes. VST3 presets for other plugins are rejected, and
+/// without an `expected_class_id` all VST3 presets are rejected. Presets written on Windows store
+/// the class ID in COM byte order, so both byte orders are accepted.
+pub fn read_state_file(path: &Path, expected_class_id: Option<[u8; 16]>) -> Result<PluginState> {
+    let data =
+        std::fs::read(path).with_context(|| format!("Could not read '{}'", path.display()))?;
+
+    let state_data = if data.starts_with(HEADER_MAGIC) {
+        let preset = VstPreset::parse(&data)
+            .with_context(|| format!("Could not parse '{}'", path.display()))?;
+        match expected_class_id {
+            Some(expected_class_id)
+                if preset.class_id != expected_class_id
+                    && preset.class_id != com_byte_order(&expected_class_id) =>
+            {
+                anyhow::bail!(
+                    "'{}' is a preset for a different plugin (class ID {}, expected {})",
+                    path.display(),
+                    format_class_id(&preset.class_id),
+                    format_class_id(&expected_class_id)
+                )
+            }
+            Some(_) => (),
+            None => anyhow::bail!(
+                "Cannot check whether '{}' belongs to this plugin because neither \
+                 'StandaloneConfig::vst3_class_id' nor a VST3 entry in \
+                 'StandaloneConfig::formats' has been set",
+                path.display()
+            ),
+        }
+        if preset
+            .controller_state
+            .is_some_and(|state| !state.is_empty())
+        {
+            nih_warn!("Ignoring the preset's edit controller state");
+        }
+
+        preset.component_state
+    } else {
+        &data
+    };
+
+    unsafe { state::deserialize_json(state_data) }
+        .with_context(|| format!("'{}' does not contain a valid plugin state", path.display()))
+}
+
+impl<'a> VstPreset<'a> {
+    /// Parse the contents of a `.vstpreset` file.
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        if data.len() < HEADER_SIZE || !data.starts_with(HEADER_MAGIC) {
+            anyhow::bail!("Not a VST3 preset file");
+        }
+
+        let class_id = parse_class_id(&data[8..40]).context("Invalid class ID in the header")?;
+        let chunk_list_offset = read_u64(data, 40)? as usize;
+        let chunk_list = data
+            .get(chunk_list_offset..)
+            .filter(|chunk_list| chunk_list.starts_with(CHUNK_LIST_MAGIC))
+            .context("Missing chunk list")?;
+        let num_entries = read_u32(chunk_list, 4)? as usize;
+
+        let mut component_state = None;
+        let mut controller_state = None;
+        for entry_idx in 0..num_entries {
+            let entry_offset = 8 + entry_idx * CHUNK_LIST_ENTRY_SIZE;
+            let chunk_id = chunk_list
+                .get(entry_offset..entry_offset + 4)
+                .context("Truncated chunk list")?;
+            let chunk_offset = read_u64(chunk_list, entry_offset + 4)? as usize;
+            let chunk_size = read_u64(chunk_list, entry_offset + 12)? as usize;
+            let chunk = chunk_offset
+                .checked_add(chunk_size)
+                .and_then(|chunk_end| data.get(chunk_offset..chunk_end))
+                .with_context(|| {
+                    format!(
+                        "The '{}' chunk is out of bounds",
+                        String::from_utf8_lossy(chunk_id)
+                    )
+                })?;
+
+            if chunk_id == COMPONENT_STATE_CHUNK_ID {
+                component_state = Some(chunk);
+            } else if chunk_id == CONTROLLER_STATE_CHUNK_ID {
+                controller_state = Some(chunk);
+            }
+        }
+
+        Ok(Self {
+            class_id,
+            component_state: component_state.context("The preset has no component state")?,
+            controller_state,
+        })
+    }
+}
+
+/// Parse a class ID stored as 32 hexadecimal ASCII characters.
+pub fn parse_class_id(hex: &[u8]) -> Option<[u8; 16]> {
+    let hex = std::str::from_utf8(hex).ok()?;
+    let mut class_id = [0u8; 16];
+    for (byte_idx, byte) in class_id.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(byte_idx * 2..byte_idx * 2 + 2)?, 16).ok()?;
+    }
+
+    Some(class_id)
+}
+
+/// Convert a class ID to the byte order used by COM-compatible FUIDs on Windows, where the first
+/// three fields are stored as little-endian 32-bit, 16-bit, and 16-bit integers. This conversion
+/// is its own inverse.
+fn com_byte_order(class_id: &[u8; 16]) -> [u8; 16] {
+    let mut com_class_id = *class_id;
+    com_class_id[0..4].reverse();
+    com_class_id[4..6].reverse();
+    com_class_id[6..8].reverse();
+
+    com_class_id
+}
+
+/// Format a class ID the same way it's stored in `.vstpreset` files.
+fn format_class_id(class_id: &[u8; 16]) -> String {
+    class_id.iter().map(|byte| format!("{byte:02X}")).collect()
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    let bytes = data
+        .get(offset..offset + 4)
+        .context("Unexpected end of file")?;
+
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64> {
+    let bytes = data
+        .get(offset..offset + 8)
+        .context("Unexpected end of file")?;
+
+    Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin::capabilities::PluginFormat;
+    use crate::wrapper::standalone::StandaloneConfig;
+
+    const CLASS_ID: [u8; 16] = *b"NihPlugTestPlug!";
+
+    /// Create a `.vstpreset` file the same way the VST3 SDK's `PresetFile` would.
+    fn make_preset(class_id: &[u8; 16], component_state: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(HEADER_MAGIC);
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(format_class_id(class_id).as_bytes());
+        let chunk_list_offset = HEADER_SIZE + component_state.len();
+        data.extend_from_slice(&(chunk_list_offset as u64).to_le_bytes());
+        data.extend_from_slice(component_state);
+
+        data.extend_from_slice(CHUNK_LIST_MAGIC);
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(COMPONENT_STATE_CHUNK_ID);
+        data.extend_from_slice(&(HEADER_SIZE as u64).to_le_bytes());
+        data.extend_from_slice(&(component_state.len() as u64).to_le_bytes());
+        data.extend_from_slice(CONTROLLER_STATE_CHUNK_ID);
+        data.extend_from_slice(&(chunk_list_offset as u64).to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+
+        data
+    }
+
+    #[test]
+    fn parse_preset() {
+        let data = make_preset(&CLASS_ID, b"{}");
+        let preset = VstPreset::parse(&data).unwrap();
+
+        assert_eq!(preset.class_id, CLASS_ID);
+        assert_eq!(preset.component_state, b"{}");
+        assert_eq!(preset.controller_state, Some(&[][..]));
+    }
+
+    #[test]
+    fn parse_truncated_preset() {
+        let data = make_preset(&CLASS_ID, b"{}");
+
+        assert!(VstPreset::parse(&data[..HEADER_SIZE - 1]).is_err());
+        assert!(VstPreset::parse(&data[..data.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn reject_other_plugins() {
+        let state = br#"{"version":"","params":{},"fields":{}}"#;
+        let path = std::env::temp_dir().join(format!(
+            "nih_plug_reject_other_plugins_{}.vstpreset",
+            std::process::id()
+        ));
+        std::fs::write(&path, make_preset(b"SomeOtherPlugin!", state)).unwrap();
+
+        let result = read_state_file(&path, Some(CLASS_ID));
+        let other_result = read_state_file(&path, Some(*b"SomeOtherPlugin!"));
+        let unchecked_result = read_state_file(&path, None);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(result.is_err());
+        assert!(other_result.is_ok());
+        assert!(unchecked_result.is_err());
+    }
+
+    #[test]
+    fn class_id_from_formats() {
+        let mut config = StandaloneConfig {
+            formats: vec![
+                PluginFormat::Standalone,
+                PluginFormat::Vst3 {
+                    class_id: format_class_id(&CLASS_ID),
+                    subcategories: String::from("Fx"),
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(config.expected_vst3_class_id(), Some(CLASS_ID));
+
+        // An explicitly set class ID takes precedence
+        config.vst3_class_id = Some(*b"SomeOtherPlugin!");
+        assert_eq!(config.expected_vst3_class_id(), Some(*b"SomeOtherPlugin!"));
+
+        assert_eq!(StandaloneConfig::default().expected_vst3_class_id(), None);
+    }
+
+    #[test]
+    fn accept_com_byte_order() {
+        let state = br#"{"version":"","params":{},"fields":{}}"#;
+        let path = std::env::temp_dir().join(format!(
+            "nih_plug_accept_com_byte_order_{}.vstpreset",
+            std::process::id()
+        ));
+        let com_class_id = com_byte_order(&CLASS_ID);
+        assert_eq!(&com_class_id[..8], b"PhiNulTg");
+        assert_eq!(com_class_id[8..], CLASS_ID[8..]);
+        std::fs::write(&path, make_preset(&com_class_id, state)).unwrap();
+
+        let result = read_state_file(&path, Some(CLASS_ID));
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(result.is_ok());
+    }
+}
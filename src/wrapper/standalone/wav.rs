@@ -0,0 +1,83 @@
+//! A minimal WAV writer for the `--output` option.
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// The format tag for IEEE floating point samples.
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+/// The size of the `fmt ` chunk's contents.
+const FMT_CHUNK_SIZE: u32 = 16;
+
+/// Write `channels` to `path` as a 32-bit floating point WAV file. All channels must have the same
+/// length.
+pub fn write_wav_file(path: &Path, sample_rate: f32, channels: &[Vec<f32>]) -> Result<()> {
+    let file =
+        File::create(path).with_context(|| format!("Could not create '{}'", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    write_wav(&mut writer, sample_rate, channels)
+        .and_then(|()| writer.flush())
+        .with_context(|| format!("Could not write to '{}'", path.display()))
+}
+
+/// Write `channels` to `writer` as a 32-bit floating point WAV file.
+fn write_wav(
+    writer: &mut impl Write,
+    sample_rate: f32,
+    channels: &[Vec<f32>],
+) -> std::io::Result<()> {
+    let num_channels = channels.len();
+    let num_samples = channels.first().map(Vec::len).unwrap_or_default();
+    assert!(channels.iter().all(|channel| channel.len() == num_samples));
+
+    let sample_rate = sample_rate.round() as u32;
+    let block_align = (num_channels * std::mem::size_of::<f32>()) as u16;
+    let data_size = (num_samples * block_align as usize) as u32;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(4 + (8 + FMT_CHUNK_SIZE) + (8 + data_size)).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&FMT_CHUNK_SIZE.to_le_bytes())?;
+    writer.write_all(&WAVE_FORMAT_IEEE_FLOAT.to_le_bytes())?;
+    writer.write_all(&(num_channels as u16).to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&32u16.to_le_bytes())?;
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())?;
+    for sample_idx in 0..num_samples {
+        for channel in channels {
+            writer.write_all(&channel[sample_idx].to_le_bytes())?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stereo_header() {
+        let mut data = Vec::new();
+        write_wav(&mut data, 48000.0, &[vec![0.0; 10], vec![1.0; 10]]).unwrap();
+
+        assert_eq!(data.len(), 44 + (10 * 2 * 4));
+        assert_eq!(&data[0..4], b"RIFF");
+        assert_eq!(
+            u32::from_le_bytes(data[4..8].try_into().unwrap()) as usize,
+            data.len() - 8
+        );
+        assert_eq!(u16::from_le_bytes(data[22..24].try_into().unwrap()), 2);
+        assert_eq!(u32::from_le_bytes(data[24..28].try_into().unwrap()), 48000);
+        assert_eq!(&data[36..40], b"data");
+        // The second sample frame's right channel
+        assert_eq!(f32::from_le_bytes(data[56..60].try_into().unwrap()), 1.0);
+    }
+}
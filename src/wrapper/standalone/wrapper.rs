@@ -4,8 +4,8 @@ use crossbeam::channel::{self, Sender};
 use crossbeam::queue::ArrayQueue;
 use parking_lot::Mutex;
 use raw_window_handle::HasRawWindowHandle;
-use std::any::Any;
 use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
 use std::thread;
@@ -13,15 +13,24 @@ use std::thread;
 use super::backend::Backend;
 use super::config::WrapperConfig;
 use super::context::{WrapperGuiContext, WrapperInitContext, WrapperProcessContext};
+#[cfg(feature = "standalone_osc")]
+use super::osc::{self, OscServer};
+use super::vstpreset;
 use crate::event_loop::{EventLoop, MainThreadExecutor, OsEventLoop};
+use crate::plugin::capabilities::{PluginCapabilities, PluginFormat};
 use crate::prelude::{
-    AsyncExecutor, AudioIOLayout, BufferConfig, Editor, ParamFlags, ParamPtr, Params,
-    ParentWindowHandle, Plugin, PluginNoteEvent, ProcessMode, ProcessStatus, TaskExecutor,
-    Transport,
+    AsyncExecutor, AudioIOLayout, AuxiliaryBuffers, Buffer, BufferConfig, Editor, ParamFlags,
+    ParamPtr, Params, ParentWindowHandle, Plugin, PluginNoteEvent, ProcessMode, ProcessStatus,
+    TaskExecutor, Transport,
 };
-use crate::util::permit_alloc;
+use crate::util::{permit_alloc, ProcessLoad};
 use crate::wrapper::state::{self, PluginState};
-use crate::wrapper::util::process_wrapper;
+use crate::wrapper::util::automation;
+use crate::wrapper::util::fixed_block::{plugin_buffer_config, FixedBlockBuffers};
+use crate::wrapper::util::gain_reduction::GainReductionMeter;
+use crate::wrapper::util::logger::{self, LoggerRegistration};
+use crate::wrapper::util::transport_jumps::TransportJumpDetector;
+use crate::wrapper::util::{hash_param_id, process_wrapper, EditorHandle};
 
 /// How many parameter changes we can store in our unprocessed parameter change queue. Storing more
 /// than this many parameters at a time will cause changes to get lost.
@@ -43,6 +52,9 @@ pub struct Wrapper<P: Plugin, B: Backend<P>> {
     /// to instantiate this in advance so we don't need to lock the entire [`Plugin`] object when
     /// creating an editor. Wrapped in an `AtomicRefCell` because it needs to be initialized late.
     pub editor: AtomicRefCell<Option<Arc<Mutex<Box<dyn Editor>>>>>,
+    /// Whether the editor is currently open. This is managed by [`EditorHandle`] and it can be
+    /// queried through the [`ProcessContext`][crate::prelude::ProcessContext].
+    pub editor_open: Arc<AtomicBool>,
     /// A channel for sending tasks to the GUI window, if the plugin has a GUI. Set in `run()`.
     gui_tasks_sender: AtomicRefCell<Option<Sender<GuiTask>>>,
 
@@ -51,8 +63,8 @@ pub struct Wrapper<P: Plugin, B: Backend<P>> {
     /// the way it does.
     event_loop: AtomicRefCell<Option<OsEventLoop<Task<P>, Self>>>,
 
-    /// This is used to grab the DPI scaling config. Not used on macOS.
-    #[allow(unused)]
+    /// This is used to grab the DPI scaling config and the window title. The DPI scaling factor is
+    /// not used on macOS.
     config: WrapperConfig,
 
     /// A mapping from parameter pointers to string parameter IDs. This is used as part of
@@ -71,7 +83,8 @@ pub struct Wrapper<P: Plugin, B: Backend<P>> {
     /// Parameter changes that have been output by the GUI that have not yet been set in the plugin.
     /// This queue will be flushed at the end of every processing cycle, just like in the plugin
     /// versions.
-    unprocessed_param_changes: ArrayQueue<(ParamPtr, f32)>,
+    /// The boolean indicates whether the parameter's smoother should be reset to the new value.
+    unprocessed_param_changes: ArrayQueue<(ParamPtr, f32, bool)>,
     /// The plugin is able to restore state through a method on the `GuiContext`. To avoid changing
     /// parameters mid-processing and running into garbled data if the host also tries to load state
     /// at the same time the restoring happens at the end of each processing call. If this zero
@@ -88,6 +101,21 @@ pub struct Wrapper<P: Plugin, B: Backend<P>> {
     /// still kept track of to avoid firing debug assertions multiple times for the same latency
     /// value.
     current_latency: AtomicU32,
+    /// Measures how long the process function takes. Only updated when the `process_profiling`
+    /// feature is enabled.
+    pub process_load: Arc<ProcessLoad>,
+    /// The parameter changes from the automation recording passed to `--automation`, as
+    /// `(sample_time, param_ptr, normalized_value)` tuples sorted by their sample times.
+    automation_events: Vec<(u64, ParamPtr, f32)>,
+    /// Updates the plugin's gain reduction parameter with the gain reduction reported by the
+    /// plugin. See [`GainReductionMeter`] for more information.
+    pub gain_reduction_meter: GainReductionMeter,
+    /// The OSC server started with `--osc-port` or `--osc-send`, if any. See the `osc` module for
+    /// the address scheme.
+    #[cfg(feature = "standalone_osc")]
+    osc_server: Option<OscServer>,
+    /// Keeps the thread that writes messages logged from the audio thread alive.
+    _logger_registration: LoggerRegistration,
 }
 
 /// Tasks that can be sent from the plugin to be executed on the main thread in a non-blocking
@@ -111,12 +139,19 @@ pub enum Task<P: Plugin> {
 pub enum WrapperError {
     /// The plugin returned `false` during initialization.
     InitializationFailed,
+    /// The state file passed to `--load-state` could not be loaded.
+    LoadStateFailed,
+    /// The automation recording passed to `--automation` could not be loaded.
+    LoadAutomationFailed,
+    /// The OSC server's socket could not be bound.
+    #[cfg(feature = "standalone_osc")]
+    OscServerFailed,
 }
 
 struct WrapperWindowHandler {
     /// The editor handle for the plugin's open editor. The editor should clean itself up when it
     /// gets dropped.
-    _editor_handle: Box<dyn Any>,
+    _editor_handle: EditorHandle,
 
     /// This is used to communicate with the wrapper from the audio thread and from within the
     /// baseview window handler on the GUI thread.
@@ -159,14 +194,24 @@ impl<P: Plugin, B: Backend<P>> MainThreadExecutor<Task<P>> for Wrapper<P, B> {
                 if let Some(editor) = self.editor.borrow().as_ref() {
                     editor.lock().param_values_changed();
                 }
+
+                #[cfg(feature = "standalone_osc")]
+                if let Some(osc_server) = &self.osc_server {
+                    osc::send_all_param_values(osc_server, &self.param_id_to_ptr);
+                }
             }
             Task::ParameterValueChanged(param_ptr, normalized_value) => {
+                let param_id = &self.param_ptr_to_id[&param_ptr];
                 if let Some(editor) = self.editor.borrow().as_ref() {
-                    let param_id = &self.param_ptr_to_id[&param_ptr];
                     editor
                         .lock()
                         .param_value_changed(param_id, normalized_value);
                 }
+
+                #[cfg(feature = "standalone_osc")]
+                if let Some(osc_server) = &self.osc_server {
+                    osc_server.send_param_value(param_id, param_ptr, normalized_value);
+                }
             }
         }
     }
@@ -216,6 +261,39 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
             }
         }
 
+        let automation_events = match &config.automation {
+            Some(automation_path) => load_automation(automation_path, &param_map)?,
+            None => Vec::new(),
+        };
+
+        #[cfg(feature = "standalone_osc")]
+        let osc_server = if config.osc_port.is_some() || config.osc_send.is_some() {
+            let num_output_channels = audio_io_layout
+                .main_output_channels
+                .map(|channels| channels.get())
+                .unwrap_or_default();
+            let osc_server = OscServer::new(
+                config.osc_bind,
+                config.osc_port,
+                config.osc_send,
+                num_output_channels as usize,
+            )
+            .map_err(|err| {
+                nih_error!("{err:#}");
+                WrapperError::OscServerFailed
+            })?;
+
+            Some(osc_server)
+        } else {
+            None
+        };
+
+        let gain_reduction_meter = GainReductionMeter::new(
+            param_map
+                .iter()
+                .map(|(param_id, param_ptr, _)| (hash_param_id(param_id), *param_ptr)),
+        );
+
         let wrapper = Arc::new(Wrapper {
             backend: AtomicRefCell::new(backend),
 
@@ -224,6 +302,7 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
             params,
             // Initialized later as it needs a reference to the wrapper for the async executor
             editor: AtomicRefCell::new(None),
+            editor_open: Arc::new(AtomicBool::new(false)),
             // Set in `run()`
             gui_tasks_sender: AtomicRefCell::new(None),
 
@@ -240,19 +319,30 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
                 .collect(),
 
             audio_io_layout,
-            buffer_config: BufferConfig {
+            // If the plugin uses a fixed block size, then it only ever sees blocks of that size
+            buffer_config: plugin_buffer_config::<P>(BufferConfig {
                 sample_rate: config.sample_rate,
                 min_buffer_size: None,
                 max_buffer_size: config.period_size,
                 // TODO: Detect JACK freewheeling and report it here
-                process_mode: ProcessMode::Realtime,
-            },
+                process_mode: if config.render {
+                    ProcessMode::Offline
+                } else {
+                    ProcessMode::Realtime
+                },
+            }),
             config,
 
             unprocessed_param_changes: ArrayQueue::new(EVENT_QUEUE_CAPACITY),
             updated_state_sender,
             updated_state_receiver,
             current_latency: AtomicU32::new(0),
+            process_load: Arc::new(ProcessLoad::default()),
+            automation_events,
+            gain_reduction_meter,
+            #[cfg(feature = "standalone_osc")]
+            osc_server,
+            _logger_registration: logger::register(None),
         });
 
         *wrapper.event_loop.borrow_mut() =
@@ -287,6 +377,33 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
             unsafe { param.update_smoother(wrapper.buffer_config.sample_rate, true) };
         }
 
+        // Just like when a host restores a plugin's state, this happens before initializing the
+        // plugin. This also resets the smoothers to the loaded values.
+        if let Some(state_path) = &wrapper.config.load_state {
+            let mut state = vstpreset::read_state_file(
+                state_path,
+                wrapper.config.standalone.expected_vst3_class_id(),
+            )
+            .map_err(|err| {
+                nih_error!("{err:#}");
+                WrapperError::LoadStateFailed
+            })?;
+
+            let success = unsafe {
+                state::deserialize_object::<P>(
+                    &mut state,
+                    wrapper.params.clone(),
+                    |param_id| wrapper.param_id_to_ptr.get(param_id).copied(),
+                    Some(&wrapper.buffer_config),
+                )
+            };
+            if !success {
+                return Err(WrapperError::LoadStateFailed);
+            }
+
+            nih_log!("Loaded the plugin's state from '{}'", state_path.display());
+        }
+
         {
             let mut plugin = wrapper.plugin.lock();
             if !plugin.initialize(
@@ -302,6 +419,18 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
         Ok(wrapper)
     }
 
+    /// A summary of the plugin's capabilities. Since the plugin has already been initialized at
+    /// this point, this also includes the plugin's latency.
+    pub fn capabilities(&self) -> PluginCapabilities {
+        PluginCapabilities::from_parts::<P>(
+            self.params.as_ref(),
+            self.editor.borrow().is_some(),
+            Some(self.current_latency.load(Ordering::SeqCst)),
+        )
+        .with_formats([PluginFormat::Standalone])
+        .with_formats(self.config.standalone.formats.iter().cloned())
+    }
+
     /// Open the editor, start processing audio, and block this thread until the editor is closed.
     /// If the plugin does not have an editor, then this will block until SIGINT is received.
     ///
@@ -320,6 +449,28 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
             thread::spawn(move || this.run_audio_thread(terminate_audio_thread, gui_task_sender))
         };
 
+        // The OSC server runs on its own thread and stops together with the audio thread
+        #[cfg(feature = "standalone_osc")]
+        let osc_thread = self.osc_server.is_some().then(|| {
+            let this = self.clone();
+            let terminate_osc_thread = terminate_audio_thread.clone();
+            thread::spawn(move || this.run_osc_thread(&terminate_osc_thread))
+        });
+
+        // Test signals have a fixed length, so the application exits once the signal has been
+        // rendered instead of waiting for the editor to be closed
+        if self.config.signal.is_some() {
+            audio_thread.join().unwrap();
+            #[cfg(feature = "standalone_osc")]
+            if let Some(osc_thread) = osc_thread {
+                terminate_audio_thread.store(true, Ordering::SeqCst);
+                osc_thread.join().unwrap();
+            }
+            self.plugin.lock().deactivate();
+
+            return Ok(());
+        }
+
         match self.editor.borrow().clone() {
             Some(editor) => {
                 let context = self.clone().make_gui_context();
@@ -334,9 +485,15 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
                 };
 
                 let (width, height) = editor.lock().size();
+                let editor_open = self.editor_open.clone();
+                let window_editor = editor.clone();
                 Window::open_blocking(
                     WindowOpenOptions {
-                        title: String::from(P::NAME),
+                        title: self.config.standalone.window_title::<P>(),
+                        // TODO: baseview does not support setting a window icon yet
+                        // TODO: The window only contains the plugin's editor, so the MIDI devices
+                        //       can only be selected with the `--midi-input` and `--midi-output`
+                        //       options. A device selector needs a GUI of the wrapper's own.
                         size: baseview::Size {
                             width: width as f64,
                             height: height as f64,
@@ -365,14 +522,20 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
                         //       baseview does not support this yet. Once this is added, we should
                         //       immediately close the parent window when this happens so the loop
                         //       can exit.
-                        let editor_handle = editor.lock().spawn(parent_handle, context);
+                        let window_editor = window_editor.lock();
+                        let editor_handle = window_editor.spawn(parent_handle, context);
+                        let editor_handle = EditorHandle::new(editor_handle, editor_open);
+                        window_editor.on_open();
 
                         WrapperWindowHandler {
                             _editor_handle: editor_handle,
                             gui_task_receiver,
                         }
                     },
-                )
+                );
+
+                // The window handler, and thus the editor handle, has been dropped at this point
+                editor.lock().on_close();
             }
             None => {
                 // TODO: Properly block until SIGINT is received if the plugin does not have an editor
@@ -384,6 +547,10 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
 
         terminate_audio_thread.store(true, Ordering::SeqCst);
         audio_thread.join().unwrap();
+        #[cfg(feature = "standalone_osc")]
+        if let Some(osc_thread) = osc_thread {
+            osc_thread.join().unwrap();
+        }
 
         // Some plugins may use this to clean up resources. Should not be needed for the standalone
         // application, but it seems like a good idea to stay consistent.
@@ -399,20 +566,27 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
         self.param_ptr_to_id.get(&param).map(|s| s.as_str())
     }
 
+    /// The process mode the plugin was initialized with. This is either offline or realtime
+    /// depending on the `--render` option.
+    pub fn process_mode(&self) -> ProcessMode {
+        self.buffer_config.process_mode
+    }
+
     /// Set a parameter based on a `ParamPtr`. The value will be updated at the end of the next
     /// processing cycle, and this won't do anything if the parameter has not been registered by the
-    /// plugin.
+    /// plugin. If `reset_smoother` is set, then the parameter's smoother will jump to the new value
+    /// instead of moving towards it.
     ///
     /// This returns false if the parameter was not set because the `ParamPtr` was either unknown or
     /// the queue is full.
-    pub fn set_parameter(&self, param: ParamPtr, normalized: f32) -> bool {
+    pub fn set_parameter(&self, param: ParamPtr, normalized: f32, reset_smoother: bool) -> bool {
         if !self.param_ptr_to_id.contains_key(&param) {
             return false;
         }
 
         let push_successful = self
             .unprocessed_param_changes
-            .push((param, normalized))
+            .push((param, normalized, reset_smoother))
             .is_ok();
         nih_debug_assert!(push_successful, "The parameter change queue was full");
 
@@ -505,24 +679,64 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
         should_terminate: Arc<AtomicBool>,
         gui_task_sender: channel::Sender<GuiTask>,
     ) {
+        let mut fixed_block_buffers = P::FIXED_BLOCK_SIZE.map(|block_size| {
+            FixedBlockBuffers::new(block_size.get() as usize, self.audio_io_layout)
+        });
+        let mut block_output_events = Vec::with_capacity(512);
+        let mut num_processed_samples = 0u64;
+        let mut next_automation_event_idx = 0;
+        let mut transport_jump_detector = TransportJumpDetector::default();
+
         self.clone().backend.borrow_mut().run(
-            move |buffer, aux, transport, input_events, output_events| {
+            move |buffer, aux, mut transport, input_events, output_events| {
                 // TODO: This process wrapper should actually be in the backends (since the backends
                 //       should also not allocate in their audio callbacks), but that's a bit more
                 //       error prone
                 process_wrapper(|| {
+                    let _process_load_measurement = self
+                        .process_load
+                        .start_measurement(buffer.samples(), self.buffer_config.sample_rate);
                     if should_terminate.load(Ordering::SeqCst) {
                         return false;
                     }
 
-                    let sample_rate = self.buffer_config.sample_rate;
+                    // Recorded automation is applied at the start of the period it occurs in
+                    let period_end = num_processed_samples + buffer.samples() as u64;
+                    while let Some((sample_time, param_ptr, normalized_value)) =
+                        self.automation_events.get(next_automation_event_idx)
                     {
-                        let mut plugin = self.plugin.lock();
-                        if let ProcessStatus::Error(err) = plugin.process(
-                            buffer,
-                            aux,
-                            &mut self.make_process_context(transport, input_events, output_events),
-                        ) {
+                        if *sample_time >= period_end {
+                            break;
+                        }
+
+                        self.apply_param_change(*param_ptr, *normalized_value, false);
+                        next_automation_event_idx += 1;
+                    }
+                    num_processed_samples = period_end;
+                    transport_jump_detector.update(&mut transport, buffer.samples());
+
+                    {
+                        let status = match fixed_block_buffers.as_mut() {
+                            Some(fixed_block_buffers) => self.process_fixed_blocks(
+                                fixed_block_buffers,
+                                buffer,
+                                aux,
+                                transport,
+                                input_events,
+                                output_events,
+                                &mut block_output_events,
+                            ),
+                            None => self.plugin.lock().process(
+                                buffer,
+                                aux,
+                                &mut self.make_process_context(
+                                    transport,
+                                    input_events,
+                                    output_events,
+                                ),
+                            ),
+                        };
+                        if let ProcessStatus::Error(err) = status {
                             nih_error!("The plugin returned an error while processing:");
                             nih_error!("{}", err);
 
@@ -536,25 +750,26 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
                         }
                     }
 
+                    #[cfg(feature = "standalone_osc")]
+                    if let Some(osc_server) = &self.osc_server {
+                        osc_server.update_output_peaks(buffer);
+                    }
+
                     // Any output note events are now in a vector that can be processed by the
                     // audio/MIDI backend
 
                     // We'll always write these events to the first sample, so even when we add note
                     // output we shouldn't have to think about interleaving events here
-                    while let Some((param_ptr, normalized_value)) =
+                    while let Some((param_ptr, normalized_value, reset_smoother)) =
                         self.unprocessed_param_changes.pop()
                     {
-                        if unsafe { param_ptr.set_normalized_value(normalized_value) } {
-                            unsafe { param_ptr.update_smoother(sample_rate, false) };
-                            let task_posted = self.schedule_gui(Task::ParameterValueChanged(
-                                param_ptr,
-                                normalized_value,
-                            ));
-                            nih_debug_assert!(
-                                task_posted,
-                                "The task queue is full, dropping task..."
-                            );
-                        }
+                        self.apply_param_change(param_ptr, normalized_value, reset_smoother);
+                    }
+                    if let Some(update) = self
+                        .gain_reduction_meter
+                        .tick(buffer.samples() as u32, self.buffer_config.sample_rate)
+                    {
+                        self.apply_param_change(update.param_ptr, update.normalized_value, true);
                     }
 
                     // After processing audio, we'll check if the editor has sent us updated plugin
@@ -583,6 +798,83 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
         );
     }
 
+    /// Handle the OSC server's incoming messages until `should_terminate` is `true`. Incoming
+    /// parameter changes are applied the same way as the editor's parameter changes.
+    #[cfg(feature = "standalone_osc")]
+    fn run_osc_thread(&self, should_terminate: &AtomicBool) {
+        let osc_server = self.osc_server.as_ref().unwrap();
+
+        // This lets the other side know the initial values without having to query them
+        osc::send_all_param_values(osc_server, &self.param_id_to_ptr);
+        osc_server.run(
+            &self.param_id_to_ptr,
+            &self.process_load,
+            should_terminate,
+            |param_ptr, normalized_value| self.set_parameter(param_ptr, normalized_value, false),
+        );
+    }
+
+    /// Set a parameter's value from the audio thread and inform the editor about the change.
+    fn apply_param_change(&self, param_ptr: ParamPtr, normalized_value: f32, reset_smoother: bool) {
+        if unsafe { param_ptr.set_normalized_value(normalized_value) } {
+            unsafe { param_ptr.update_smoother(self.buffer_config.sample_rate, reset_smoother) };
+            let task_posted =
+                self.schedule_gui(Task::ParameterValueChanged(param_ptr, normalized_value));
+            nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+        }
+    }
+
+    /// Process the backend's buffer for a plugin that uses [`Plugin::FIXED_BLOCK_SIZE`]. The
+    /// plugin processes every block that completes during this buffer, and its output events are
+    /// added to `output_events` together with the delayed audio. `block_output_events` is scratch
+    /// storage for the plugin's output events. Returns the status of the last processed block, or
+    /// the first error that occurred.
+    #[allow(clippy::too_many_arguments)]
+    fn process_fixed_blocks(
+        &self,
+        fixed_block_buffers: &mut FixedBlockBuffers<P::SysExMessage>,
+        buffer: &mut Buffer,
+        aux: &mut AuxiliaryBuffers,
+        transport: Transport,
+        input_events: &[PluginNoteEvent<P>],
+        output_events: &mut Vec<PluginNoteEvent<P>>,
+        block_output_events: &mut Vec<PluginNoteEvent<P>>,
+    ) -> ProcessStatus {
+        for event in input_events {
+            fixed_block_buffers.queue_event(event.clone());
+        }
+
+        let num_samples = buffer.samples();
+        fixed_block_buffers
+            .process(
+                buffer,
+                aux.inputs,
+                aux.outputs,
+                num_samples,
+                |event| output_events.push(event),
+                |block_buffers, block_input_events, block_output_queue| {
+                    let mut aux = AuxiliaryBuffers {
+                        inputs: block_buffers.aux_inputs,
+                        outputs: block_buffers.aux_outputs,
+                    };
+
+                    let status = self.plugin.lock().process(
+                        block_buffers.main_buffer,
+                        &mut aux,
+                        &mut self.make_process_context(
+                            transport.clone(),
+                            block_input_events.make_contiguous(),
+                            block_output_events,
+                        ),
+                    );
+                    block_output_queue.extend(block_output_events.drain(..));
+
+                    status
+                },
+            )
+            .unwrap_or(ProcessStatus::Normal)
+    }
+
     fn make_gui_context(self: Arc<Self>) -> Arc<WrapperGuiContext<P, B>> {
         Arc::new(WrapperGuiContext {
             wrapper: self,
@@ -677,3 +969,45 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
         success
     }
 }
+
+/// Read the automation recording passed to `--automation` and match the recorded parameter hashes
+/// to the plugin's parameters. Changes for unknown parameters are skipped.
+fn load_automation(
+    path: &Path,
+    param_map: &[(String, ParamPtr, String)],
+) -> Result<Vec<(u64, ParamPtr, f32)>, WrapperError> {
+    let events = automation::read_automation_file(path).map_err(|err| {
+        nih_error!("{err:#}");
+        WrapperError::LoadAutomationFailed
+    })?;
+
+    let param_by_hash: HashMap<u32, ParamPtr> = param_map
+        .iter()
+        .map(|(param_id, param_ptr, _)| (hash_param_id(param_id), *param_ptr))
+        .collect();
+    let mut unknown_hashes = HashSet::new();
+    let automation_events: Vec<_> = events
+        .into_iter()
+        .filter_map(|event| match param_by_hash.get(&event.param_hash) {
+            Some(param_ptr) => Some((event.sample_time, *param_ptr, event.normalized_value)),
+            None => {
+                if unknown_hashes.insert(event.param_hash) {
+                    nih_warn!(
+                        "Skipping the recorded changes for unknown parameter hash {}",
+                        event.param_hash
+                    );
+                }
+
+                None
+            }
+        })
+        .collect();
+
+    nih_log!(
+        "Loaded {} parameter changes from '{}'",
+        automation_events.len(),
+        path.display()
+    );
+
+    Ok(automation_events)
+}
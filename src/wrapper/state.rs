@@ -1,7 +1,7 @@
 //! Utilities for saving a [`crate::plugin::Plugin`]'s state. The actual state object is also exposed
 //! to plugins through the [`GuiContext`][crate::prelude::GuiContext].
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
@@ -13,7 +13,7 @@ use crate::prelude::{BufferConfig, Param, ParamPtr, Params, Plugin};
 // management
 
 /// A plain, unnormalized value for a parameter.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ParamValue {
     F32(f32),
@@ -27,7 +27,7 @@ pub enum ParamValue {
 /// deserialized using serde.
 ///
 /// The fields are stored as `BTreeMap`s so the order in the serialized file is consistent.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PluginState {
     /// The plugin version this state was saved with. Right now this is not used, but later versions
     /// of NIH-plug may allow you to modify the plugin state object directly before it is loaded to
@@ -52,6 +52,116 @@ pub struct PluginState {
     pub fields: BTreeMap<String, String>,
 }
 
+/// The value of [`ClipboardState::kind`]. Used to tell plugin states apart from other text on the
+/// clipboard.
+const CLIPBOARD_STATE_KIND: &str = "nih_plug_state";
+/// The current version of the clipboard format. Bump this when making incompatible changes to
+/// [`ClipboardState`].
+const CLIPBOARD_FORMAT_VERSION: u32 = 1;
+
+/// A [`PluginState`] together with the information needed to validate it before it's pasted into
+/// another plugin instance. See [`PluginState::to_clipboard_string()`].
+#[derive(Debug, Serialize, Deserialize)]
+struct ClipboardState {
+    #[serde(rename = "type")]
+    kind: String,
+    format_version: u32,
+    vendor: String,
+    plugin: String,
+    state: PluginState,
+}
+
+impl PluginState {
+    /// Serialize this state to a JSON string that can be put on the clipboard, for instance to let
+    /// users copy a patch from one plugin instance to another. The string also contains `P`'s name
+    /// and vendor so [`from_clipboard_string()`][Self::from_clipboard_string()] can reject states
+    /// from other plugins.
+    pub fn to_clipboard_string<P: Plugin>(&self) -> String {
+        let clipboard_state = ClipboardState {
+            kind: String::from(CLIPBOARD_STATE_KIND),
+            format_version: CLIPBOARD_FORMAT_VERSION,
+            vendor: String::from(P::VENDOR),
+            plugin: String::from(P::NAME),
+            state: self.clone(),
+        };
+
+        // This only contains strings, maps, and numbers, so serialization cannot fail
+        serde_json::to_string(&clipboard_state).expect("Could not serialize the plugin state")
+    }
+
+    /// Parse a state created by [`to_clipboard_string()`][Self::to_clipboard_string()]. This
+    /// returns an error describing the problem if `data` is not a plugin state, if it's a state for
+    /// a different plugin, or if it was saved with a newer version of `P` than the one that's
+    /// currently running. The state is only validated here, so it can be passed to
+    /// [`GuiContext::set_state()`][crate::prelude::GuiContext::set_state()] to load it in one go.
+    pub fn from_clipboard_string<P: Plugin>(data: &str) -> Result<Self> {
+        let clipboard_state: ClipboardState = serde_json::from_str(data.trim())
+            .context("The clipboard does not contain a plugin state")?;
+        if clipboard_state.kind != CLIPBOARD_STATE_KIND {
+            bail!("The clipboard does not contain a plugin state");
+        }
+        if clipboard_state.format_version > CLIPBOARD_FORMAT_VERSION {
+            bail!(
+                "The plugin state uses a newer clipboard format (version {}, expected {} or lower)",
+                clipboard_state.format_version,
+                CLIPBOARD_FORMAT_VERSION
+            );
+        }
+        if clipboard_state.vendor != P::VENDOR || clipboard_state.plugin != P::NAME {
+            bail!(
+                "The plugin state belongs to {} by {}, not {} by {}",
+                clipboard_state.plugin,
+                clipboard_state.vendor,
+                P::NAME,
+                P::VENDOR
+            );
+        }
+        if is_newer_version(&clipboard_state.state.version, P::VERSION) {
+            bail!(
+                "The plugin state was saved with {} version {}, which is newer than the current \
+                 version {}",
+                P::NAME,
+                clipboard_state.state.version,
+                P::VERSION
+            );
+        }
+
+        Ok(clipboard_state.state)
+    }
+}
+
+/// Whether `version` is a newer version than `current_version`. Versions are compared by their
+/// numeric dot separated components, so `1.10.0` is newer than `1.9.3`, and missing components
+/// count as zero, so `1.9.0` and `1.9` are the same version. Versions that cannot be compared this
+/// way, like empty versions from old states, are never considered to be newer.
+fn is_newer_version(version: &str, current_version: &str) -> bool {
+    fn parse(version: &str) -> Option<Vec<u64>> {
+        version
+            .split('.')
+            .map(|component| {
+                // Pre-release and build metadata suffixes like `-alpha` are ignored
+                let digits: String = component
+                    .chars()
+                    .take_while(|c| c.is_ascii_digit())
+                    .collect();
+                digits.parse().ok()
+            })
+            .collect()
+    }
+
+    match (parse(version), parse(current_version)) {
+        (Some(mut version), Some(mut current_version)) => {
+            // Missing components are treated as zeroes, so `1.9` and `1.9.0` are the same version
+            let num_components = version.len().max(current_version.len());
+            version.resize(num_components, 0);
+            current_version.resize(num_components, 0);
+
+            version > current_version
+        }
+        _ => false,
+    }
+}
+
 /// Create a parameters iterator from the hashtables stored in the plugin wrappers. This avoids
 /// having to call `.param_map()` again, which may include expensive user written code.
 pub(crate) fn make_params_iter<'a>(
@@ -292,3 +402,94 @@ pub(crate) unsafe fn deserialize_json(state: &[u8]) -> Option<PluginState> {
 
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use crate::testing::TestPlugin;
+
+    #[test]
+    fn state_load_resets_smoothers() {
+        let plugin = TestPlugin::default();
+        let params = plugin.params();
+        let param_map: HashMap<String, ParamPtr> = params
+            .param_map()
+            .into_iter()
+            .map(|(id, param_ptr, _)| (id, param_ptr))
+            .collect();
+        let buffer_config = BufferConfig {
+            sample_rate: 44_100.0,
+            min_buffer_size: None,
+            max_buffer_size: 512,
+            process_mode: ProcessMode::Realtime,
+        };
+
+        let mut state = PluginState {
+            version: String::new(),
+            params: BTreeMap::from([(String::from("gain"), ParamValue::F32(0.75))]),
+            fields: BTreeMap::new(),
+        };
+        let success = unsafe {
+            deserialize_object::<TestPlugin>(
+                &mut state,
+                params,
+                |param_id| param_map.get(param_id).copied(),
+                Some(&buffer_config),
+            )
+        };
+        assert!(success);
+
+        // The loaded value should take effect immediately instead of gliding towards it
+        assert_eq!(plugin.params.gain.value(), 0.75);
+        assert!(!plugin.params.gain.smoothed.is_smoothing());
+        assert_eq!(plugin.params.gain.smoothed.next(), 0.75);
+    }
+
+    #[test]
+    fn clipboard_state_roundtrip() {
+        let state = PluginState {
+            version: String::from("0.1.0"),
+            params: BTreeMap::from([(String::from("gain"), ParamValue::F32(0.75))]),
+            fields: BTreeMap::from([(String::from("editor"), String::from("{}"))]),
+        };
+
+        let data = state.to_clipboard_string::<TestPlugin>();
+        let parsed = PluginState::from_clipboard_string::<TestPlugin>(&data).unwrap();
+        assert!(matches!(parsed.params["gain"], ParamValue::F32(value) if value == 0.75));
+        assert_eq!(parsed.fields, state.fields);
+    }
+
+    #[test]
+    fn clipboard_state_validation() {
+        let state = PluginState {
+            version: String::from("0.1.0"),
+            params: BTreeMap::new(),
+            fields: BTreeMap::new(),
+        };
+        let data = state.to_clipboard_string::<TestPlugin>();
+
+        assert!(PluginState::from_clipboard_string::<TestPlugin>("Hello, world!").is_err());
+        assert!(PluginState::from_clipboard_string::<TestPlugin>(
+            &data.replace("Test Plugin", "Other Plugin")
+        )
+        .is_err());
+        assert!(PluginState::from_clipboard_string::<TestPlugin>(
+            &data.replace("\"version\":\"0.1.0\"", "\"version\":\"0.2.0\"")
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn version_comparison() {
+        assert!(is_newer_version("1.10.0", "1.9.3"));
+        assert!(is_newer_version("2.0.0", "1.9"));
+        assert!(!is_newer_version("1.9.3", "1.9.3"));
+        assert!(!is_newer_version("1.9.0", "1.9"));
+        assert!(!is_newer_version("1.9", "1.9.0"));
+        assert!(is_newer_version("1.9.1", "1.9"));
+        assert!(!is_newer_version("1.9", "1.9.1"));
+        assert!(!is_newer_version("1.2.0-alpha", "1.2.0"));
+        assert!(!is_newer_version("", "1.0.0"));
+    }
+}
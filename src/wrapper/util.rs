@@ -1,13 +1,23 @@
 use backtrace::Backtrace;
+use std::any::Any;
 use std::cmp;
 use std::marker::PhantomData;
 use std::os::raw::c_char;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
+use crate::prelude::{NoteEvent, ProcessStatus, RawMidiConfig, SysExMessage, INFINITE_TAIL};
 use crate::util::permit_alloc;
 
+pub(crate) mod automation;
 pub(crate) mod buffer_management;
 #[cfg(debug_assertions)]
 pub(crate) mod context_checks;
+pub(crate) mod fixed_block;
+pub(crate) mod gain_reduction;
+pub(crate) mod gestures;
+pub(crate) mod logger;
+pub(crate) mod transport_jumps;
 
 /// The bit that controls flush-to-zero behavior for denormals in 32 and 64-bit floating point
 /// numbers on x86 family architectures. Rust 1.75 deprecated the built in functions for controlling
@@ -98,10 +108,90 @@ pub fn clamp_output_event_timing(timing: u32, total_buffer_len: u32) -> u32 {
     timing.min(last_valid_index)
 }
 
+/// The events a MIDI message received from the host or a MIDI device translates to. Depending on
+/// `raw_midi_config` this yields a [`NoteEvent::MidiRaw`] event followed by the result of parsing
+/// the message, so the raw event always comes before the parsed event with the same timing. Parsing
+/// errors are yielded as `Err(event_type)`, see [`NoteEvent::from_midi()`]. This does not allocate.
+pub fn midi_input_events<S: SysExMessage>(
+    timing: u32,
+    midi_data: &[u8],
+    raw_midi_config: RawMidiConfig,
+) -> impl Iterator<Item = Result<NoteEvent<S>, u8>> {
+    let raw_event = match raw_midi_config {
+        RawMidiConfig::None => None,
+        RawMidiConfig::Alongside | RawMidiConfig::Only => {
+            NoteEvent::from_raw_midi(timing, midi_data).map(Ok)
+        }
+    };
+    let parsed_event = match raw_midi_config {
+        RawMidiConfig::None | RawMidiConfig::Alongside => {
+            Some(NoteEvent::from_midi(timing, midi_data))
+        }
+        RawMidiConfig::Only => None,
+    };
+
+    raw_event.into_iter().chain(parsed_event)
+}
+
+/// Wraps the handle returned by [`Editor::spawn()`][crate::prelude::Editor::spawn()] to keep track
+/// of whether the plugin's editor is open. `editor_open` is set to `true` when this object is
+/// created and it's set back to `false` when this object is dropped, regardless of whether the
+/// editor was closed normally or the wrapper dropped it because the host destroyed the editor or
+/// the plugin instance.
+pub struct EditorHandle {
+    editor_open: Arc<AtomicBool>,
+    /// The editor closes itself when this handle is dropped.
+    _handle: Box<dyn Any + Send>,
+}
+
+impl EditorHandle {
+    pub fn new(handle: Box<dyn Any + Send>, editor_open: Arc<AtomicBool>) -> Self {
+        editor_open.store(true, Ordering::SeqCst);
+
+        Self {
+            editor_open,
+            _handle: handle,
+        }
+    }
+}
+
+impl Drop for EditorHandle {
+    fn drop(&mut self) {
+        self.editor_open.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Compute the tail length in samples that should be reported to the host. `current_tail` is the
+/// value from the last `set_tail_samples()` call, if the plugin ever made one. This takes
+/// precedence over [`ProcessStatus::Tail`], but [`ProcessStatus::KeepAlive`] always results in an
+/// infinite tail and `ProcessStatus::Tail(0)` always means that the tail has ended.
+pub fn tail_length_samples(current_tail: Option<u32>, last_process_status: ProcessStatus) -> u32 {
+    match (last_process_status, current_tail) {
+        (ProcessStatus::KeepAlive, _) => INFINITE_TAIL,
+        (ProcessStatus::Tail(0), _) => 0,
+        (_, Some(samples)) => samples,
+        (ProcessStatus::Tail(samples), None) => samples,
+        _ => 0,
+    }
+}
+
+/// The sample index at which the block starting at `block_start` must end at the latest. Hosts are
+/// not supposed to send buffers larger than the maximum buffer size they reported, but some do.
+/// The wrappers use this to split those buffers up so `Plugin::process()` never receives more than
+/// [`BufferConfig::max_buffer_size`][crate::prelude::BufferConfig::max_buffer_size] samples at a
+/// time.
+pub fn max_block_end(block_start: usize, total_buffer_len: usize, max_buffer_size: u32) -> usize {
+    let max_block_len = cmp::max(max_buffer_size as usize, 1);
+
+    cmp::min(block_start + max_block_len, total_buffer_len)
+}
+
 /// Set up the logger so that the `nih_*!()` logging and assertion macros log output to a
 /// centralized location and panics also get written there. By default this logs to STDERR. If a
 /// Windows debugger is attached, then messages will be sent there instead. This uses
 /// [NIH-log](https://github.com/robbert-vdh/nih-log). See the readme there for more information.
+/// When the host provides a logging extension, the CLAP wrapper sends messages from the `nih_*!()`
+/// macros to the host instead.
 ///
 /// In short, NIH-log's behavior can be controlled by setting the `NIH_LOG` environment variable to:
 ///
@@ -179,6 +269,9 @@ fn log_panics() {
                     )
                 }
             }
+
+            // The process may not survive long enough for the flush thread to write this
+            logger::flush();
         })
     }));
 }
@@ -189,6 +282,8 @@ fn log_panics() {
 pub fn process_wrapper<T, F: FnOnce() -> T>(f: F) -> T {
     // Make sure FTZ is always enabled, even if the host doesn't do it for us
     let _ftz_guard = ScopedFtz::enable();
+    // Messages logged from here on are written from a background thread instead
+    let _logger_guard = logger::AudioThreadGuard::enter();
 
     cfg_if::cfg_if! {
         if #[cfg(all(debug_assertions, feature = "assert_process_allocs"))] {
@@ -283,6 +378,177 @@ impl Drop for ScopedFtz {
     }
 }
 
+#[cfg(test)]
+mod block_splitting {
+    use super::*;
+
+    /// Split a buffer into blocks the same way the wrappers do when there are no events.
+    fn split(total_buffer_len: usize, max_buffer_size: u32) -> Vec<usize> {
+        let mut block_lens = Vec::new();
+        let mut block_start = 0;
+        while block_start < total_buffer_len {
+            let block_end = max_block_end(block_start, total_buffer_len, max_buffer_size);
+            block_lens.push(block_end - block_start);
+            block_start = block_end;
+        }
+
+        block_lens
+    }
+
+    #[test]
+    fn within_limits() {
+        assert_eq!(split(512, 512), [512]);
+        assert_eq!(split(100, 512), [100]);
+    }
+
+    #[test]
+    fn oversized_host_buffer() {
+        // The host promised 256 sample buffers and sent 1000 samples anyways
+        assert_eq!(split(1000, 256), [256, 256, 256, 232]);
+        assert!(split(1000, 256).iter().all(|&len| len <= 256));
+    }
+
+    #[test]
+    fn zero_max_buffer_size() {
+        assert_eq!(split(3, 0), [1, 1, 1]);
+    }
+}
+
+#[cfg(test)]
+mod midi_input {
+    use super::*;
+
+    fn events(midi_data: &[u8], raw_midi_config: RawMidiConfig) -> Vec<Result<NoteEvent<()>, u8>> {
+        midi_input_events(10, midi_data, raw_midi_config).collect()
+    }
+
+    #[test]
+    fn parsed_only() {
+        assert_eq!(
+            events(&[0x91, 60, 127], RawMidiConfig::None),
+            [Ok(NoteEvent::NoteOn {
+                timing: 10,
+                voice_id: None,
+                channel: 1,
+                note: 60,
+                velocity: 1.0,
+            })]
+        );
+    }
+
+    #[test]
+    fn raw_before_parsed() {
+        assert_eq!(
+            events(&[0xb0, 74, 64], RawMidiConfig::Alongside),
+            [
+                Ok(NoteEvent::MidiRaw {
+                    timing: 10,
+                    data: [0xb0, 74, 64],
+                }),
+                Ok(NoteEvent::MidiCC {
+                    timing: 10,
+                    channel: 0,
+                    cc: 74,
+                    value: 64.0 / 127.0,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn raw_only() {
+        assert_eq!(
+            events(&[0xc2, 5], RawMidiConfig::Only),
+            [Ok(NoteEvent::MidiRaw {
+                timing: 10,
+                data: [0xc2, 5, 0],
+            })]
+        );
+    }
+
+    #[test]
+    fn unparseable_message() {
+        // Song position pointers are not parsed, but they are still sent as raw messages
+        assert_eq!(
+            events(&[0xf2, 0x10, 0x20], RawMidiConfig::Alongside),
+            [
+                Ok(NoteEvent::MidiRaw {
+                    timing: 10,
+                    data: [0xf2, 0x10, 0x20],
+                }),
+                Err(0xf0),
+            ]
+        );
+        assert_eq!(
+            events(&[0xf2, 0x10, 0x20], RawMidiConfig::None),
+            [Err(0xf0)]
+        );
+    }
+
+    #[test]
+    fn sysex_is_not_raw() {
+        assert!(events(&[0xf0, 0x7e, 0xf7], RawMidiConfig::Only).is_empty());
+    }
+}
+
+#[cfg(test)]
+mod editor_handle {
+    use super::*;
+
+    #[test]
+    fn tracks_editor_open() {
+        let editor_open = Arc::new(AtomicBool::new(false));
+
+        let handle = EditorHandle::new(Box::new(()), editor_open.clone());
+        assert!(editor_open.load(Ordering::SeqCst));
+
+        drop(handle);
+        assert!(!editor_open.load(Ordering::SeqCst));
+    }
+}
+
+#[cfg(test)]
+mod tail {
+    use super::*;
+
+    #[test]
+    fn from_process_status() {
+        assert_eq!(tail_length_samples(None, ProcessStatus::Normal), 0);
+        assert_eq!(tail_length_samples(None, ProcessStatus::Tail(512)), 512);
+        assert_eq!(tail_length_samples(None, ProcessStatus::Tail(0)), 0);
+        assert_eq!(
+            tail_length_samples(None, ProcessStatus::KeepAlive),
+            INFINITE_TAIL
+        );
+    }
+
+    #[test]
+    fn tail_ended() {
+        // An explicit `Tail(0)` means the tail has ended, even if the plugin set a tail length
+        assert_eq!(tail_length_samples(Some(48_000), ProcessStatus::Tail(0)), 0);
+        assert_eq!(
+            tail_length_samples(Some(INFINITE_TAIL), ProcessStatus::Tail(0)),
+            0
+        );
+    }
+
+    #[test]
+    fn set_tail_takes_precedence() {
+        assert_eq!(
+            tail_length_samples(Some(48_000), ProcessStatus::Normal),
+            48_000
+        );
+        assert_eq!(
+            tail_length_samples(Some(48_000), ProcessStatus::Tail(64)),
+            48_000
+        );
+        assert_eq!(
+            tail_length_samples(Some(48_000), ProcessStatus::KeepAlive),
+            INFINITE_TAIL
+        );
+    }
+}
+
 #[cfg(test)]
 mod miri {
     use std::ffi::CStr;
@@ -0,0 +1,320 @@
+//! Recording and reading the parameter automation sent by the host. When NIH-plug is compiled with
+//! the `automation_recording` feature and the `NIH_RECORD_AUTOMATION` environment variable is set
+//! to a file path, the CLAP and VST3 wrappers write every parameter change the host sends to that
+//! file. The standalone application can then replay the recording with its `--automation` option
+//! to reproduce the host's automation deterministically.
+//!
+//! Recordings are plain text files. The first line is the [`FILE_HEADER`], lines starting with a
+//! `#` are comments, and every other line contains a single parameter change as three
+//! whitespace separated values:
+//!
+//! ```text
+//! <sample_time> <param_hash> <normalized_value>
+//! ```
+//!
+//! The sample time counts the samples processed since the plugin instance was created, starting
+//! at zero. The parameter hash is the same hash the wrappers use as the parameter's CLAP or VST3
+//! ID, and the value is the parameter's new normalized value. Changes are sorted by their sample
+//! times.
+
+// Recordings are only written and read when either of these features is enabled
+#![cfg_attr(
+    not(any(feature = "automation_recording", feature = "standalone")),
+    allow(dead_code)
+)]
+
+use anyhow::{Context, Result};
+use std::path::Path;
+
+#[cfg(feature = "automation_recording")]
+pub use self::recording::AutomationRecorder;
+
+/// The first line of every automation recording. This also contains the format's version.
+pub const FILE_HEADER: &str = "# nih-plug automation v1";
+
+/// A recorded parameter change.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutomationEvent {
+    /// The number of samples processed by the plugin before the change happened.
+    pub sample_time: u64,
+    /// The parameter's hash, see [`hash_param_id()`][super::hash_param_id()].
+    pub param_hash: u32,
+    pub normalized_value: f32,
+}
+
+/// A no-op stand-in for the recorder when the `automation_recording` feature is disabled.
+#[cfg(not(feature = "automation_recording"))]
+pub struct AutomationRecorder;
+
+#[cfg(not(feature = "automation_recording"))]
+impl AutomationRecorder {
+    pub fn from_env() -> Self {
+        Self
+    }
+
+    #[inline]
+    pub fn begin_block(&self, _num_samples: usize) {}
+
+    #[inline]
+    pub fn record(&self, _timing: u32, _param_hash: u32, _normalized_value: f32) {}
+}
+
+/// Read an automation recording. Returns an error if the file is not a valid recording.
+pub fn read_automation_file(path: &Path) -> Result<Vec<AutomationEvent>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Could not read '{}'", path.display()))?;
+
+    parse_automation(&contents).with_context(|| format!("Could not parse '{}'", path.display()))
+}
+
+fn parse_automation(contents: &str) -> Result<Vec<AutomationEvent>> {
+    let mut lines = contents.lines().enumerate();
+    match lines.next() {
+        Some((_, header)) if header.trim_end() == FILE_HEADER => (),
+        _ => anyhow::bail!("The file does not start with '{FILE_HEADER}'"),
+    }
+
+    let mut events = Vec::new();
+    for (line_idx, line) in lines {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let parse_line = || -> Option<AutomationEvent> {
+            let mut fields = line.split_whitespace();
+            let event = AutomationEvent {
+                sample_time: fields.next()?.parse().ok()?,
+                param_hash: fields.next()?.parse().ok()?,
+                normalized_value: fields.next()?.parse().ok()?,
+            };
+
+            fields.next().is_none().then_some(event)
+        };
+        match parse_line() {
+            Some(event) => events.push(event),
+            None => anyhow::bail!(
+                "Invalid automation event on line {}: '{line}'",
+                line_idx + 1
+            ),
+        }
+    }
+
+    // The recorder writes the events in order, but hand-edited files may not be sorted
+    events.sort_by_key(|event| event.sample_time);
+
+    Ok(events)
+}
+
+#[cfg(feature = "automation_recording")]
+mod recording {
+    use crossbeam::queue::ArrayQueue;
+    use std::fs::File;
+    use std::io::{BufWriter, Write};
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+    use std::sync::Arc;
+    use std::thread::{self, JoinHandle};
+    use std::time::Duration;
+
+    use super::{AutomationEvent, FILE_HEADER};
+
+    /// The environment variable containing the path the automation should be recorded to.
+    const PATH_ENV_VAR: &str = "NIH_RECORD_AUTOMATION";
+    /// The number of parameter changes that can be waiting to be written. Any changes past this
+    /// are dropped.
+    const QUEUE_CAPACITY: usize = 16384;
+    /// How often the writer thread writes the queued changes to the file.
+    const WRITE_INTERVAL: Duration = Duration::from_millis(50);
+
+    /// Only used to give every plugin instance its own file.
+    static NEXT_INSTANCE_IDX: AtomicU64 = AtomicU64::new(0);
+
+    /// Records the host's parameter changes to the file from the `NIH_RECORD_AUTOMATION`
+    /// environment variable. Recording is lock-free and it does not allocate, so it can happen on
+    /// the audio thread. The changes are written to the file from a background thread.
+    pub struct AutomationRecorder {
+        /// Set to `None` if the environment variable is not set or if the file could not be
+        /// created.
+        inner: Option<RecorderInner>,
+    }
+
+    struct RecorderInner {
+        queue: Arc<ArrayQueue<AutomationEvent>>,
+        /// The sample time of the current process call's first sample.
+        block_start: AtomicU64,
+        /// The sample time of the next process call's first sample.
+        next_block_start: AtomicU64,
+        /// The number of changes that were dropped because the queue was full.
+        num_dropped: AtomicU64,
+
+        stop_writer: Arc<AtomicBool>,
+        writer_thread: Option<JoinHandle<()>>,
+    }
+
+    impl AutomationRecorder {
+        /// Start recording if the `NIH_RECORD_AUTOMATION` environment variable is set. If
+        /// multiple plugin instances are recording at the same time, then every instance after
+        /// the first one appends a number to the file name.
+        pub fn from_env() -> Self {
+            let Some(path) = std::env::var_os(PATH_ENV_VAR) else {
+                return Self { inner: None };
+            };
+
+            let mut path = PathBuf::from(path);
+            let instance_idx = NEXT_INSTANCE_IDX.fetch_add(1, Ordering::Relaxed);
+            if instance_idx > 0 {
+                let mut file_name = path.file_name().unwrap_or_default().to_owned();
+                file_name.push(format!(".{instance_idx}"));
+                path.set_file_name(file_name);
+            }
+
+            let mut writer = match File::create(&path) {
+                Ok(file) => BufWriter::new(file),
+                Err(err) => {
+                    nih_error!(
+                        "Could not create the automation recording '{}': {err}",
+                        path.display()
+                    );
+                    return Self { inner: None };
+                }
+            };
+            nih_log!("Recording parameter automation to '{}'", path.display());
+
+            let queue: Arc<ArrayQueue<AutomationEvent>> = Arc::new(ArrayQueue::new(QUEUE_CAPACITY));
+            let stop_writer = Arc::new(AtomicBool::new(false));
+            let writer_thread = thread::Builder::new()
+                .name(String::from("nih-automation"))
+                .spawn({
+                    let queue = queue.clone();
+                    let stop_writer = stop_writer.clone();
+                    move || {
+                        let header_result = writeln!(writer, "{FILE_HEADER}");
+                        let mut write_events = || -> std::io::Result<()> {
+                            while let Some(event) = queue.pop() {
+                                writeln!(
+                                    writer,
+                                    "{} {} {}",
+                                    event.sample_time, event.param_hash, event.normalized_value
+                                )?;
+                            }
+
+                            writer.flush()
+                        };
+
+                        let mut result = header_result;
+                        while result.is_ok() && !stop_writer.load(Ordering::Relaxed) {
+                            result = write_events();
+                            thread::sleep(WRITE_INTERVAL);
+                        }
+                        if let Err(err) = result.and_then(|()| write_events()) {
+                            nih_error!("Could not write the automation recording: {err}");
+                        }
+                    }
+                });
+            let writer_thread = match writer_thread {
+                Ok(handle) => handle,
+                Err(err) => {
+                    nih_error!("Could not start the automation recording thread: {err}");
+                    return Self { inner: None };
+                }
+            };
+
+            Self {
+                inner: Some(RecorderInner {
+                    queue,
+                    block_start: AtomicU64::new(0),
+                    next_block_start: AtomicU64::new(0),
+                    num_dropped: AtomicU64::new(0),
+
+                    stop_writer,
+                    writer_thread: Some(writer_thread),
+                }),
+            }
+        }
+
+        /// Must be called at the start of every process call. Changes recorded during the process
+        /// call are relative to the start of this block.
+        #[inline]
+        pub fn begin_block(&self, num_samples: usize) {
+            if let Some(inner) = &self.inner {
+                let block_start = inner
+                    .next_block_start
+                    .fetch_add(num_samples as u64, Ordering::Relaxed);
+                inner.block_start.store(block_start, Ordering::Relaxed);
+            }
+        }
+
+        /// Record a parameter change that happens `timing` samples into the current block.
+        #[inline]
+        pub fn record(&self, timing: u32, param_hash: u32, normalized_value: f32) {
+            if let Some(inner) = &self.inner {
+                let event = AutomationEvent {
+                    sample_time: inner.block_start.load(Ordering::Relaxed) + timing as u64,
+                    param_hash,
+                    normalized_value,
+                };
+                if inner.queue.push(event).is_err() {
+                    inner.num_dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    impl Drop for RecorderInner {
+        fn drop(&mut self) {
+            self.stop_writer.store(true, Ordering::Relaxed);
+            if let Some(writer_thread) = self.writer_thread.take() {
+                let _ = writer_thread.join();
+            }
+
+            let num_dropped = self.num_dropped.load(Ordering::Relaxed);
+            if num_dropped > 0 {
+                nih_warn!(
+                    "{num_dropped} parameter changes were not recorded because the recording \
+                     queue was full"
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_recording() {
+        let recording =
+            format!("{FILE_HEADER}\n# A comment\n0 1234 0.5\n\n512 42 1\n128 1234 0.25\n");
+        let events = parse_automation(&recording).unwrap();
+
+        assert_eq!(
+            events,
+            [
+                AutomationEvent {
+                    sample_time: 0,
+                    param_hash: 1234,
+                    normalized_value: 0.5
+                },
+                AutomationEvent {
+                    sample_time: 128,
+                    param_hash: 1234,
+                    normalized_value: 0.25
+                },
+                AutomationEvent {
+                    sample_time: 512,
+                    param_hash: 42,
+                    normalized_value: 1.0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_invalid_recordings() {
+        assert!(parse_automation("0 1234 0.5\n").is_err());
+        assert!(parse_automation(&format!("{FILE_HEADER}\n0 1234\n")).is_err());
+        assert!(parse_automation(&format!("{FILE_HEADER}\n0 1234 0.5 1\n")).is_err());
+    }
+}
@@ -390,6 +390,8 @@ mod miri {
         main_output_channels: Some(new_nonzero_u32(NUM_MAIN_OUTPUT_CHANNELS as u32)),
         aux_input_ports: &[new_nonzero_u32(NUM_AUX_CHANNELS as u32); NUM_AUX_PORTS],
         aux_output_ports: &[new_nonzero_u32(NUM_AUX_CHANNELS as u32); NUM_AUX_PORTS],
+        main_input_channel_layout: None,
+        main_output_channel_layout: None,
         names: PortNames::const_default(),
     };
 
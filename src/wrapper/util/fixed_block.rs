@@ -0,0 +1,519 @@
+//! Buffering for plugins that process audio in fixed size blocks. See
+//! [`Plugin::FIXED_BLOCK_SIZE`].
+
+use std::collections::VecDeque;
+use std::num::NonZeroU32;
+use std::ptr::NonNull;
+
+use super::buffer_management::{BufferManager, Buffers, ChannelPointers};
+use crate::prelude::{
+    AudioIOLayout, Buffer, BufferConfig, NoteEvent, Plugin, ProcessStatus, SysExMessage,
+};
+
+/// The latency in samples added by the fixed block size buffering. This is zero if the plugin
+/// doesn't use a fixed block size.
+pub fn fixed_block_latency<P: Plugin>() -> u32 {
+    P::FIXED_BLOCK_SIZE.map(NonZeroU32::get).unwrap_or(0)
+}
+
+/// The buffer config that should be passed to the plugin. If the plugin uses a fixed block size,
+/// then both the minimum and the maximum buffer size are set to that block size.
+pub fn plugin_buffer_config<P: Plugin>(buffer_config: BufferConfig) -> BufferConfig {
+    match P::FIXED_BLOCK_SIZE {
+        Some(block_size) => BufferConfig {
+            min_buffer_size: Some(block_size.get()),
+            max_buffer_size: block_size.get(),
+            ..buffer_config
+        },
+        None => buffer_config,
+    }
+}
+
+/// Accumulates the host's audio and note events into blocks of exactly `block_size` samples for
+/// plugins that set [`Plugin::FIXED_BLOCK_SIZE`]. The wrapper queues the input events with
+/// [`queue_event()`][Self::queue_event()] and then passes every (sub)block of audio from the host
+/// through [`process()`][Self::process()], which stores the input, replaces it with the output
+/// from the previously processed block, and runs the plugin whenever a block is full. This delays
+/// the output, including the plugin's output note events, by exactly `block_size` samples.
+pub struct FixedBlockBuffers<S: SysExMessage> {
+    block_size: usize,
+    /// The position in the current block. This is always less than `block_size`.
+    position: usize,
+
+    /// The main IO for the block that's currently being filled. The plugin processes this in
+    /// place.
+    main_io: Vec<Vec<f32>>,
+    /// The plugin's main output for the previous block. This is what's written back to the
+    /// host's buffers in [`exchange()`][Self::exchange()].
+    main_io_delayed: Vec<Vec<f32>>,
+    aux_inputs: Vec<Vec<Vec<f32>>>,
+    aux_outputs: Vec<Vec<Vec<f32>>>,
+    aux_outputs_delayed: Vec<Vec<Vec<f32>>>,
+    /// Note events for the block that's currently being filled, with timings relative to the start
+    /// of the block.
+    pending_events: VecDeque<NoteEvent<S>>,
+    /// Events queued for blocks after the current one, moved here while the current block is
+    /// processed. Preallocated so this doesn't allocate on the audio thread.
+    next_events: VecDeque<NoteEvent<S>>,
+    /// The note events the plugin output for the previous block, with timings relative to the
+    /// start of that block. These are sent to the host together with the delayed audio.
+    output_events_delayed: VecDeque<NoteEvent<S>>,
+
+    /// Used to create the [`Buffer`]s for the plugin's process function from the storage above.
+    buffer_manager: BufferManager,
+    /// Scratch storage for the channel pointers passed to `buffer_manager`.
+    main_io_pointers: Vec<*mut f32>,
+    aux_input_pointers: Vec<Vec<*mut f32>>,
+    aux_output_pointers: Vec<Vec<*mut f32>>,
+}
+
+// SAFETY: The raw pointers are only used as scratch storage inside of `process_block()`
+unsafe impl<S: SysExMessage> Send for FixedBlockBuffers<S> {}
+unsafe impl<S: SysExMessage> Sync for FixedBlockBuffers<S> {}
+
+impl<S: SysExMessage> FixedBlockBuffers<S> {
+    /// Allocate the buffers for processing blocks of `block_size` samples with this audio IO
+    /// layout.
+    pub fn new(block_size: usize, audio_io_layout: AudioIOLayout) -> Self {
+        let num_main_channels = audio_io_layout
+            .main_output_channels
+            .map(NonZeroU32::get)
+            .unwrap_or(0) as usize;
+        let port_storage = |ports: &[NonZeroU32]| -> Vec<Vec<Vec<f32>>> {
+            ports
+                .iter()
+                .map(|num_channels| vec![vec![0.0; block_size]; num_channels.get() as usize])
+                .collect()
+        };
+
+        Self {
+            block_size,
+            position: 0,
+
+            main_io: vec![vec![0.0; block_size]; num_main_channels],
+            main_io_delayed: vec![vec![0.0; block_size]; num_main_channels],
+            aux_inputs: port_storage(audio_io_layout.aux_input_ports),
+            aux_outputs: port_storage(audio_io_layout.aux_output_ports),
+            aux_outputs_delayed: port_storage(audio_io_layout.aux_output_ports),
+            pending_events: VecDeque::with_capacity(512),
+            next_events: VecDeque::with_capacity(512),
+            output_events_delayed: VecDeque::with_capacity(512),
+
+            // The main input data is already stored in `main_io`, so the buffer manager only needs
+            // to know about the outputs
+            buffer_manager: BufferManager::for_audio_io_layout(
+                block_size,
+                AudioIOLayout {
+                    main_input_channels: None,
+                    ..audio_io_layout
+                },
+            ),
+            main_io_pointers: Vec::with_capacity(num_main_channels),
+            aux_input_pointers: audio_io_layout
+                .aux_input_ports
+                .iter()
+                .map(|num_channels| Vec::with_capacity(num_channels.get() as usize))
+                .collect(),
+            aux_output_pointers: audio_io_layout
+                .aux_output_ports
+                .iter()
+                .map(|num_channels| Vec::with_capacity(num_channels.get() as usize))
+                .collect(),
+        }
+    }
+
+    /// The number of samples that can be passed to [`process()`][Self::process()] before the
+    /// current block is full. The wrappers split the host's buffers at this point so the plugin's
+    /// blocks line up with the transport information for the (sub)block.
+    pub fn samples_remaining(&self) -> usize {
+        self.block_size - self.position
+    }
+
+    /// Clear all buffered audio and events. Called whenever the plugin is reset.
+    pub fn reset(&mut self) {
+        self.position = 0;
+        for channel in self
+            .main_io
+            .iter_mut()
+            .chain(self.main_io_delayed.iter_mut())
+            .chain(self.aux_inputs.iter_mut().flatten())
+            .chain(self.aux_outputs.iter_mut().flatten())
+            .chain(self.aux_outputs_delayed.iter_mut().flatten())
+        {
+            channel.fill(0.0);
+        }
+        self.pending_events.clear();
+        self.next_events.clear();
+        self.output_events_delayed.clear();
+    }
+
+    /// Add a note event to the current block. The event's timing should be relative to the start
+    /// of the host buffers that are passed to the next [`process()`][Self::process()] call. Events
+    /// past the end of the current block are delivered with the block they fall in.
+    pub fn queue_event(&mut self, mut event: NoteEvent<S>) {
+        event.add_timing(self.position as u32);
+        self.pending_events.push_back(event);
+    }
+
+    /// Pass the first `num_samples` samples of the host's buffers through the fixed size blocks.
+    /// The input is stored in the current block and replaced with the delayed output from the
+    /// previous block, and `process_cb` is called with the block's buffers, input events, and an
+    /// empty output event queue every time a block is full. The output events the plugin produced
+    /// for the previous block are passed to `output_event_cb` with timings relative to the start
+    /// of the host's buffers, so they line up with the delayed audio. Returns the status of the
+    /// last processed block or the first error, or `None` if no block was completed.
+    pub fn process(
+        &mut self,
+        main_buffer: &mut Buffer,
+        aux_inputs: &mut [Buffer],
+        aux_outputs: &mut [Buffer],
+        num_samples: usize,
+        mut output_event_cb: impl FnMut(NoteEvent<S>),
+        mut process_cb: impl FnMut(
+            Buffers,
+            &mut VecDeque<NoteEvent<S>>,
+            &mut VecDeque<NoteEvent<S>>,
+        ) -> ProcessStatus,
+    ) -> Option<ProcessStatus> {
+        let mut status = None;
+        let mut sample_offset = 0;
+        while sample_offset < num_samples {
+            let chunk_len = self.samples_remaining().min(num_samples - sample_offset);
+            self.take_output_events(sample_offset, chunk_len, &mut output_event_cb);
+
+            if self.exchange(
+                main_buffer,
+                aux_inputs,
+                aux_outputs,
+                sample_offset,
+                chunk_len,
+            ) {
+                let result = self.process_block(&mut process_cb);
+                status = Some(result);
+                if let ProcessStatus::Error(_) = result {
+                    break;
+                }
+            }
+
+            sample_offset += chunk_len;
+        }
+
+        status
+    }
+
+    /// Send the previous block's output events that fall within the next `num_samples` samples of
+    /// the current block to `output_event_cb`. Their timings are translated to the host buffer
+    /// slice starting at `sample_offset`. Out of bounds events are sent at the end of the block.
+    fn take_output_events(
+        &mut self,
+        sample_offset: usize,
+        num_samples: usize,
+        output_event_cb: &mut impl FnMut(NoteEvent<S>),
+    ) {
+        let chunk_end = self.position + num_samples;
+        let is_last_chunk = chunk_end == self.block_size;
+        while let Some(event) = self.output_events_delayed.front() {
+            let timing = event.timing() as usize;
+            if timing >= chunk_end && !is_last_chunk {
+                break;
+            }
+
+            let mut event = self.output_events_delayed.pop_front().unwrap();
+            let host_timing = timing.clamp(self.position, chunk_end - 1) - self.position;
+            event.subtract_timing(timing as u32);
+            event.add_timing((sample_offset + host_timing) as u32);
+            output_event_cb(event);
+        }
+    }
+
+    /// Store `num_samples` samples starting at `sample_offset` from the host's buffers in the
+    /// current block, and replace them with the delayed output from the previous block. Returns
+    /// `true` if the block is now full and [`process_block()`][Self::process_block()] should be
+    /// called. `num_samples` may not exceed [`samples_remaining()`][Self::samples_remaining()].
+    fn exchange(
+        &mut self,
+        main_buffer: &mut Buffer,
+        aux_inputs: &mut [Buffer],
+        aux_outputs: &mut [Buffer],
+        sample_offset: usize,
+        num_samples: usize,
+    ) -> bool {
+        nih_debug_assert!(num_samples <= self.samples_remaining());
+        let num_samples = num_samples.min(self.samples_remaining());
+        let host_range = sample_offset..sample_offset + num_samples;
+        let block_range = self.position..self.position + num_samples;
+
+        for ((host_channel, channel), delayed_channel) in main_buffer
+            .as_slice()
+            .iter_mut()
+            .zip(self.main_io.iter_mut())
+            .zip(self.main_io_delayed.iter())
+        {
+            channel[block_range.clone()].copy_from_slice(&host_channel[host_range.clone()]);
+            host_channel[host_range.clone()].copy_from_slice(&delayed_channel[block_range.clone()]);
+        }
+
+        for (host_buffer, port) in aux_inputs.iter_mut().zip(self.aux_inputs.iter_mut()) {
+            for (host_channel, channel) in host_buffer.as_slice().iter().zip(port.iter_mut()) {
+                channel[block_range.clone()].copy_from_slice(&host_channel[host_range.clone()]);
+            }
+        }
+
+        for (host_buffer, port) in aux_outputs.iter_mut().zip(self.aux_outputs_delayed.iter()) {
+            for (host_channel, channel) in host_buffer.as_slice().iter_mut().zip(port.iter()) {
+                host_channel[host_range.clone()].copy_from_slice(&channel[block_range.clone()]);
+            }
+        }
+
+        self.position += num_samples;
+        self.position == self.block_size
+    }
+
+    /// Run `process_cb` on the completed block. The callback receives the block's buffers, the
+    /// note events for this block, and a queue for the plugin's output events. Afterwards the
+    /// block's output will be written back to the host by the next [`exchange()`][Self::exchange()]
+    /// calls, and a new block is started.
+    fn process_block<T, F>(&mut self, process_cb: F) -> T
+    where
+        F: FnOnce(Buffers, &mut VecDeque<NoteEvent<S>>, &mut VecDeque<NoteEvent<S>>) -> T,
+    {
+        nih_debug_assert_eq!(self.position, self.block_size);
+
+        // Events for later blocks are held back until this block has been processed. The events
+        // are queued in order, so these are all at the back of the queue.
+        while let Some(event) = self.pending_events.back() {
+            if (event.timing() as usize) < self.block_size {
+                break;
+            }
+
+            let mut event = self.pending_events.pop_back().unwrap();
+            event.subtract_timing(self.block_size as u32);
+            self.next_events.push_front(event);
+        }
+        nih_debug_assert!(self.output_events_delayed.is_empty());
+        self.output_events_delayed.clear();
+
+        self.main_io_pointers.clear();
+        self.main_io_pointers
+            .extend(self.main_io.iter_mut().map(|channel| channel.as_mut_ptr()));
+        for (pointers, port) in self
+            .aux_input_pointers
+            .iter_mut()
+            .zip(self.aux_inputs.iter_mut())
+            .chain(
+                self.aux_output_pointers
+                    .iter_mut()
+                    .zip(self.aux_outputs.iter_mut()),
+            )
+        {
+            pointers.clear();
+            pointers.extend(port.iter_mut().map(|channel| channel.as_mut_ptr()));
+        }
+
+        let channel_pointers = |pointers: &mut Vec<*mut f32>| {
+            NonNull::new(pointers.as_mut_ptr()).map(|ptrs| ChannelPointers {
+                ptrs,
+                num_channels: pointers.len(),
+            })
+        };
+
+        // SAFETY: All of these pointers point to the vectors stored on this object, which are not
+        //         touched until the buffers are dropped again
+        let buffers = unsafe {
+            self.buffer_manager
+                .create_buffers(0, self.block_size, |buffer_source| {
+                    *buffer_source.main_output_channel_pointers =
+                        channel_pointers(&mut self.main_io_pointers);
+                    for (source, pointers) in buffer_source
+                        .aux_input_channel_pointers
+                        .iter_mut()
+                        .zip(self.aux_input_pointers.iter_mut())
+                    {
+                        *source = channel_pointers(pointers);
+                    }
+                    for (source, pointers) in buffer_source
+                        .aux_output_channel_pointers
+                        .iter_mut()
+                        .zip(self.aux_output_pointers.iter_mut())
+                    {
+                        *source = channel_pointers(pointers);
+                    }
+                })
+        };
+
+        let result = process_cb(
+            buffers,
+            &mut self.pending_events,
+            &mut self.output_events_delayed,
+        );
+        self.pending_events.clear();
+        std::mem::swap(&mut self.pending_events, &mut self.next_events);
+
+        // The processed block is written back to the host while the next block is being filled
+        std::mem::swap(&mut self.main_io, &mut self.main_io_delayed);
+        std::mem::swap(&mut self.aux_outputs, &mut self.aux_outputs_delayed);
+        self.position = 0;
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::PortNames;
+
+    const MONO: AudioIOLayout = AudioIOLayout {
+        main_input_channels: NonZeroU32::new(1),
+        main_output_channels: NonZeroU32::new(1),
+        aux_input_ports: &[],
+        aux_output_ports: &[],
+        main_input_channel_layout: None,
+        main_output_channel_layout: None,
+        names: PortNames::const_default(),
+    };
+
+    /// Run `input` through the fixed block buffers in host buffers of `host_block_size` samples.
+    /// The plugin adds 100 to every sample. Returns the output and the length of every processed
+    /// block.
+    fn process(input: &[f32], host_block_size: usize) -> (Vec<f32>, Vec<usize>) {
+        let mut fixed_block = FixedBlockBuffers::<()>::new(4, MONO);
+        let mut output = input.to_vec();
+        let mut block_lens = Vec::new();
+
+        for host_block in output.chunks_mut(host_block_size) {
+            let host_block_len = host_block.len();
+            let mut main_buffer = Buffer::default();
+            unsafe {
+                main_buffer.set_slices(host_block_len, |slices| {
+                    slices.push(&mut *(host_block as *mut [f32]));
+                })
+            };
+
+            fixed_block.process(
+                &mut main_buffer,
+                &mut [],
+                &mut [],
+                host_block_len,
+                |_| (),
+                |buffers, _, _| {
+                    block_lens.push(buffers.main_buffer.samples());
+                    for sample in buffers.main_buffer.as_slice()[0].iter_mut() {
+                        *sample += 100.0;
+                    }
+
+                    ProcessStatus::Normal
+                },
+            );
+        }
+
+        (output, block_lens)
+    }
+
+    #[test]
+    fn ragged_host_buffers() {
+        let input: Vec<f32> = (1..=16).map(|i| i as f32).collect();
+        for host_block_size in [1, 3, 4, 7, 16] {
+            let (output, block_lens) = process(&input, host_block_size);
+
+            assert_eq!(block_lens, [4; 4], "host block size {host_block_size}");
+
+            // The first block is silent, and the rest of the output is the processed input
+            // delayed by one block
+            assert_eq!(&output[..4], [0.0; 4]);
+            for (i, sample) in output.iter().enumerate().skip(4) {
+                assert_eq!(
+                    *sample,
+                    input[i - 4] + 100.0,
+                    "host block size {host_block_size}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn event_timings() {
+        let mut fixed_block = FixedBlockBuffers::<()>::new(4, MONO);
+        let mut storage = vec![0.0; 3];
+        let mut main_buffer = Buffer::default();
+        unsafe {
+            main_buffer.set_slices(3, |slices| {
+                slices.push(&mut *(storage.as_mut_slice() as *mut [f32]));
+            })
+        };
+
+        // The first three samples of the block
+        fixed_block.queue_event(NoteEvent::NoteOn {
+            timing: 1,
+            voice_id: None,
+            channel: 0,
+            note: 60,
+            velocity: 1.0,
+        });
+        assert!(!fixed_block.exchange(&mut main_buffer, &mut [], &mut [], 0, 3));
+        assert_eq!(fixed_block.samples_remaining(), 1);
+
+        // And the last sample, which also has an event
+        fixed_block.queue_event(NoteEvent::NoteOff {
+            timing: 0,
+            voice_id: None,
+            channel: 0,
+            note: 60,
+            velocity: 1.0,
+        });
+        assert!(fixed_block.exchange(&mut main_buffer, &mut [], &mut [], 0, 1));
+
+        let timings = fixed_block.process_block(|_, events, _| {
+            events
+                .iter()
+                .map(|event| event.timing())
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(timings, [1, 3]);
+        assert_eq!(fixed_block.samples_remaining(), 4);
+    }
+
+    #[test]
+    fn output_event_timings() {
+        let mut fixed_block = FixedBlockBuffers::<()>::new(4, MONO);
+        let mut storage = vec![0.0; 3];
+        let mut output_timings = Vec::new();
+
+        // The plugin outputs a note on the third sample of every block. The host receives those
+        // together with the block's audio, which is delayed by one block.
+        for _ in 0..4 {
+            let mut main_buffer = Buffer::default();
+            unsafe {
+                main_buffer.set_slices(3, |slices| {
+                    slices.push(&mut *(storage.as_mut_slice() as *mut [f32]));
+                })
+            };
+
+            let mut host_timings = Vec::new();
+            fixed_block.process(
+                &mut main_buffer,
+                &mut [],
+                &mut [],
+                3,
+                |event| host_timings.push(event.timing()),
+                |_, _, output_events| {
+                    output_events.push_back(NoteEvent::NoteOn {
+                        timing: 2,
+                        voice_id: None,
+                        channel: 0,
+                        note: 60,
+                        velocity: 1.0,
+                    });
+
+                    ProcessStatus::Normal
+                },
+            );
+            output_timings.push(host_timings);
+        }
+
+        // The first block's event ends up at host sample 4 + 2 = 6, which is the first sample of
+        // the third host buffer. The second block's event is at host sample 10, which is the second
+        // sample of the fourth host buffer.
+        assert_eq!(output_timings, [vec![], vec![], vec![0], vec![1]]);
+    }
+}
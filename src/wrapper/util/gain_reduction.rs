@@ -0,0 +1,184 @@
+//! Reporting a dynamics plugin's gain reduction to the host.
+
+use atomic_float::AtomicF32;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::prelude::{ParamFlags, ParamPtr};
+
+/// The maximum number of times per second the gain reduction is sent to the host. Hosts only
+/// redraw their meters at around this rate, and sending a parameter change for every block would
+/// needlessly flood the host's parameter queues.
+pub const MAX_UPDATES_PER_SECOND: f32 = 30.0;
+/// The gain reduction is only sent to the host when it has changed by at least this many
+/// decibels since the last update.
+pub const MIN_CHANGE_DB: f32 = 0.1;
+
+/// Used for `peak_db` when the plugin has not reported any gain reduction since the last update.
+const NO_REPORT: f32 = -1.0;
+
+/// Keeps track of the gain reduction reported through
+/// [`ProcessContext::set_gain_reduction()`][crate::prelude::ProcessContext::set_gain_reduction()]
+/// and decides when the plugin's [`ParamFlags::GAIN_REDUCTION`] parameter should be updated. The
+/// highest gain reduction reported during an update interval is sent, so short peaks still show up
+/// on the host's meter.
+///
+/// Both functions are called from the audio thread, and neither of them allocates.
+#[derive(Debug)]
+pub struct GainReductionMeter {
+    /// The hash and pointer of the plugin's gain reduction parameter, if it has one.
+    param: Option<(u32, ParamPtr)>,
+    /// The highest gain reduction reported since the last update, in decibels. Set to
+    /// [`NO_REPORT`] after every update.
+    peak_db: AtomicF32,
+    /// The gain reduction that was last sent to the host, in decibels.
+    last_sent_db: AtomicF32,
+    /// The number of samples processed since the last update.
+    samples_since_update: AtomicU32,
+}
+
+/// A new value for the gain reduction parameter, returned by [`GainReductionMeter::tick()`].
+#[derive(Debug, Clone, Copy)]
+pub struct GainReductionUpdate {
+    pub param_hash: u32,
+    pub param_ptr: ParamPtr,
+    pub normalized_value: f32,
+}
+
+impl GainReductionMeter {
+    /// Create a meter for the first parameter with the [`ParamFlags::GAIN_REDUCTION`] flag. If the
+    /// plugin doesn't have such a parameter, then reported gain reduction is ignored.
+    pub fn new(params: impl IntoIterator<Item = (u32, ParamPtr)>) -> Self {
+        Self {
+            param: params
+                .into_iter()
+                .find(|(_, ptr)| unsafe { ptr.flags() }.contains(ParamFlags::GAIN_REDUCTION)),
+            peak_db: AtomicF32::new(NO_REPORT),
+            last_sent_db: AtomicF32::new(0.0),
+            samples_since_update: AtomicU32::new(0),
+        }
+    }
+
+    /// Report the current gain reduction in decibels. Negative values and NaNs are treated as no
+    /// gain reduction.
+    pub fn report(&self, gain_reduction_db: f32) {
+        if self.param.is_none() {
+            return;
+        }
+
+        // `f32::max()` returns the other value when one of the values is NaN
+        let gain_reduction_db = gain_reduction_db.max(0.0);
+        if gain_reduction_db > self.peak_db.load(Ordering::Relaxed) {
+            self.peak_db.store(gain_reduction_db, Ordering::Relaxed);
+        }
+    }
+
+    /// Advance the meter by `num_samples` samples. Returns the gain reduction parameter's new value
+    /// if the wrapper should send it to the host now. Updates are sent at most
+    /// [`MAX_UPDATES_PER_SECOND`] times per second, and only if the gain reduction changed by at
+    /// least [`MIN_CHANGE_DB`] decibels or if it dropped back to zero.
+    pub fn tick(&self, num_samples: u32, sample_rate: f32) -> Option<GainReductionUpdate> {
+        let (param_hash, param_ptr) = self.param?;
+
+        let update_interval = (sample_rate / MAX_UPDATES_PER_SECOND) as u32;
+        let samples_since_update = self
+            .samples_since_update
+            .fetch_add(num_samples, Ordering::Relaxed)
+            .saturating_add(num_samples);
+        if samples_since_update < update_interval {
+            return None;
+        }
+        self.samples_since_update.store(0, Ordering::Relaxed);
+
+        let peak_db = self.peak_db.swap(NO_REPORT, Ordering::Relaxed);
+        let last_sent_db = self.last_sent_db.load(Ordering::Relaxed);
+        let changed = (peak_db - last_sent_db).abs() >= MIN_CHANGE_DB
+            || (peak_db == 0.0 && last_sent_db != 0.0);
+        if peak_db == NO_REPORT || !changed {
+            return None;
+        }
+
+        self.last_sent_db.store(peak_db, Ordering::Relaxed);
+        Some(GainReductionUpdate {
+            param_hash,
+            param_ptr,
+            normalized_value: unsafe { param_ptr.preview_normalized(peak_db) },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::{FloatParam, FloatRange, Param};
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+    const BLOCK_SIZE: u32 = 64;
+
+    fn gain_reduction_param() -> FloatParam {
+        FloatParam::new(
+            "Gain Reduction",
+            0.0,
+            FloatRange::Linear {
+                min: 0.0,
+                max: 24.0,
+            },
+        )
+        .make_gain_reduction_meter()
+    }
+
+    /// Report `gain_reduction_db` for every block during one update interval, returning the update
+    /// sent at the end of that interval.
+    fn report_for_interval(
+        meter: &GainReductionMeter,
+        gain_reduction_db: f32,
+    ) -> Option<GainReductionUpdate> {
+        let num_blocks = (SAMPLE_RATE / MAX_UPDATES_PER_SECOND) as u32 / BLOCK_SIZE + 1;
+        for _ in 0..num_blocks {
+            meter.report(gain_reduction_db);
+            if let Some(update) = meter.tick(BLOCK_SIZE, SAMPLE_RATE) {
+                return Some(update);
+            }
+        }
+
+        None
+    }
+
+    #[test]
+    fn throttled_updates() {
+        let param = gain_reduction_param();
+        let meter = GainReductionMeter::new([(1, param.as_ptr())]);
+
+        meter.report(6.0);
+        assert!(meter.tick(BLOCK_SIZE, SAMPLE_RATE).is_none());
+
+        let update = report_for_interval(&meter, 6.0).unwrap();
+        assert_eq!(update.param_hash, 1);
+        assert_eq!(update.normalized_value, 0.25);
+
+        // Tiny changes are not sent, but returning to zero is
+        assert!(report_for_interval(&meter, 6.05).is_none());
+        assert!(report_for_interval(&meter, 12.0).is_some());
+        assert!(report_for_interval(&meter, 0.05).is_some());
+        let update = report_for_interval(&meter, 0.0).unwrap();
+        assert_eq!(update.normalized_value, 0.0);
+        assert!(report_for_interval(&meter, -3.0).is_none());
+    }
+
+    #[test]
+    fn peak_is_sent() {
+        let param = gain_reduction_param();
+        let meter = GainReductionMeter::new([(1, param.as_ptr())]);
+
+        meter.report(12.0);
+        let update = report_for_interval(&meter, 3.0).unwrap();
+        assert_eq!(update.normalized_value, 0.5);
+    }
+
+    #[test]
+    fn no_gain_reduction_param() {
+        let param = FloatParam::new("Gain", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 });
+        let meter = GainReductionMeter::new([(1, param.as_ptr())]);
+
+        assert!(report_for_interval(&meter, 6.0).is_none());
+    }
+}
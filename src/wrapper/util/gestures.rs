@@ -0,0 +1,265 @@
+//! A safety net for parameter gestures sent from the editor.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, AtomicU8, AtomicUsize, Ordering};
+
+/// A parameter gesture that has neither been started nor been ended.
+const IDLE: u8 = 0;
+/// A parameter gesture that has been started and that hasn't been ended yet.
+const ACTIVE: u8 = 1;
+/// A parameter gesture that was ended by [`GestureWatchdog::tick()`] because it did not receive
+/// any value changes for [`GESTURE_TIMEOUT_SECS`] seconds.
+const AUTO_ENDED: u8 = 2;
+
+/// The number of seconds of audio a parameter gesture can go without any value changes before it
+/// gets ended automatically.
+pub const GESTURE_TIMEOUT_SECS: f32 = 5.0;
+
+/// Keeps track of the parameter gestures started by the plugin's editor, and automatically ends
+/// gestures that have been left dangling. Some hosts keep an automation lane in touch/latch mode
+/// until the gesture has ended, so a single missed `end_set_parameter()` call (for instance
+/// because the editor lost mouse capture in the middle of a drag) would otherwise result in a
+/// stuck automation lane.
+///
+/// The begin, set, and end functions are called from the GUI thread, while
+/// [`tick()`][Self::tick()] is called from the audio thread. The parameter set is fixed when this
+/// object is created so none of these functions allocate or lock.
+#[derive(Debug)]
+pub struct GestureWatchdog {
+    /// The gesture state for each parameter, indexed by the parameter's hash.
+    params: HashMap<u32, GestureState>,
+    /// The number of parameters in the `ACTIVE` state. Used to avoid iterating over all parameters
+    /// in every process call when no gestures are active. This may briefly be higher than the
+    /// actual number of active gestures, but it will never be lower.
+    num_active: AtomicUsize,
+}
+
+#[derive(Debug)]
+struct GestureState {
+    /// One of `IDLE`, `ACTIVE`, or `AUTO_ENDED`.
+    state: AtomicU8,
+    /// The number of samples processed since the last gesture event for this parameter.
+    idle_samples: AtomicU32,
+}
+
+impl GestureWatchdog {
+    /// Create a watchdog for the parameters with these hashes.
+    pub fn new(param_hashes: impl IntoIterator<Item = u32>) -> Self {
+        Self {
+            params: param_hashes
+                .into_iter()
+                .map(|hash| {
+                    (
+                        hash,
+                        GestureState {
+                            state: AtomicU8::new(IDLE),
+                            idle_samples: AtomicU32::new(0),
+                        },
+                    )
+                })
+                .collect(),
+            num_active: AtomicUsize::new(0),
+        }
+    }
+
+    /// Called when the editor starts a gesture for a parameter. Returns `true` if the begin
+    /// gesture should be sent to the host. This returns `false` if the gesture is already active,
+    /// in which case the existing gesture is continued instead of starting a nested one.
+    pub fn begin(&self, param_hash: u32) -> bool {
+        let Some(param) = self.params.get(&param_hash) else {
+            return true;
+        };
+
+        param.idle_samples.store(0, Ordering::Relaxed);
+        self.num_active.fetch_add(1, Ordering::SeqCst);
+        if param.state.swap(ACTIVE, Ordering::SeqCst) == ACTIVE {
+            self.num_active.fetch_sub(1, Ordering::SeqCst);
+            false
+        } else {
+            true
+        }
+    }
+
+    /// Called when the editor changes a parameter's value. Returns `true` if the gesture was
+    /// previously ended by [`tick()`][Self::tick()], in which case a new begin gesture needs to be
+    /// sent to the host before sending the new value.
+    pub fn set(&self, param_hash: u32) -> bool {
+        let Some(param) = self.params.get(&param_hash) else {
+            return false;
+        };
+
+        param.idle_samples.store(0, Ordering::Relaxed);
+        self.num_active.fetch_add(1, Ordering::SeqCst);
+        if param
+            .state
+            .compare_exchange(AUTO_ENDED, ACTIVE, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            true
+        } else {
+            self.num_active.fetch_sub(1, Ordering::SeqCst);
+            false
+        }
+    }
+
+    /// Called when the editor ends a gesture for a parameter. Returns `true` if the end gesture
+    /// should be sent to the host. This returns `false` if there is no active gesture, or if the
+    /// gesture has already been ended by [`tick()`][Self::tick()].
+    pub fn end(&self, param_hash: u32) -> bool {
+        let Some(param) = self.params.get(&param_hash) else {
+            return true;
+        };
+
+        if param.state.swap(IDLE, Ordering::SeqCst) == ACTIVE {
+            self.num_active.fetch_sub(1, Ordering::SeqCst);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Advance the timers for all active gestures by `num_samples` samples. `end_gesture` is called
+    /// with the parameter's hash for every gesture that has not seen any changes for
+    /// [`GESTURE_TIMEOUT_SECS`] seconds. The wrapper should then send an end gesture for that
+    /// parameter to the host. Does not allocate, and returns immediately if there are no active
+    /// gestures.
+    pub fn tick(&self, num_samples: u32, sample_rate: f32, mut end_gesture: impl FnMut(u32)) {
+        if self.num_active.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+
+        let timeout_samples = (GESTURE_TIMEOUT_SECS * sample_rate) as u32;
+        for (hash, param) in &self.params {
+            if param.state.load(Ordering::SeqCst) != ACTIVE {
+                continue;
+            }
+
+            let idle_samples = param
+                .idle_samples
+                .fetch_add(num_samples, Ordering::Relaxed)
+                .saturating_add(num_samples);
+            if idle_samples >= timeout_samples
+                && param
+                    .state
+                    .compare_exchange(ACTIVE, AUTO_ENDED, Ordering::SeqCst, Ordering::SeqCst)
+                    .is_ok()
+            {
+                self.num_active.fetch_sub(1, Ordering::SeqCst);
+                end_gesture(*hash);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+    const BLOCK_SIZE: u32 = 512;
+
+    /// Process enough blocks to exceed the gesture timeout, returning the hashes of all auto-ended
+    /// gestures.
+    fn tick_past_timeout(watchdog: &GestureWatchdog) -> Vec<u32> {
+        let num_blocks = (GESTURE_TIMEOUT_SECS * SAMPLE_RATE) as u32 / BLOCK_SIZE + 1;
+        let mut ended = Vec::new();
+        for _ in 0..num_blocks {
+            watchdog.tick(BLOCK_SIZE, SAMPLE_RATE, |hash| ended.push(hash));
+        }
+
+        ended
+    }
+
+    #[test]
+    fn regular_gesture() {
+        let watchdog = GestureWatchdog::new([1, 2]);
+
+        assert!(watchdog.begin(1));
+        assert!(!watchdog.set(1));
+        assert!(!watchdog.set(1));
+        assert!(watchdog.end(1));
+        assert!(tick_past_timeout(&watchdog).is_empty());
+    }
+
+    #[test]
+    fn duplicate_begin_and_end() {
+        let watchdog = GestureWatchdog::new([1]);
+
+        assert!(watchdog.begin(1));
+        assert!(!watchdog.begin(1));
+        assert!(watchdog.end(1));
+        assert!(!watchdog.end(1));
+    }
+
+    #[test]
+    fn interrupted_gesture_is_ended() {
+        // This simulates a drag where the editor never receives the mouse up event
+        let watchdog = GestureWatchdog::new([1, 2]);
+
+        assert!(watchdog.begin(1));
+        assert!(!watchdog.set(1));
+        assert_eq!(tick_past_timeout(&watchdog), [1]);
+
+        // The gesture has already been ended, so the editor's own end should not be forwarded
+        assert!(!watchdog.end(1));
+        assert!(tick_past_timeout(&watchdog).is_empty());
+    }
+
+    #[test]
+    fn changes_keep_gesture_alive() {
+        let watchdog = GestureWatchdog::new([1]);
+
+        assert!(watchdog.begin(1));
+        let num_blocks = (GESTURE_TIMEOUT_SECS * SAMPLE_RATE) as u32 / BLOCK_SIZE * 4;
+        for _ in 0..num_blocks {
+            assert!(!watchdog.set(1));
+            watchdog.tick(BLOCK_SIZE, SAMPLE_RATE, |hash| {
+                panic!("Gesture for {hash} ended while it was still receiving changes")
+            });
+        }
+        assert!(watchdog.end(1));
+    }
+
+    #[test]
+    fn resumed_after_auto_end() {
+        let watchdog = GestureWatchdog::new([1]);
+
+        assert!(watchdog.begin(1));
+        assert_eq!(tick_past_timeout(&watchdog), [1]);
+
+        // If the editor continues changing the value, then a new gesture needs to be started
+        assert!(watchdog.set(1));
+        assert!(!watchdog.set(1));
+        assert!(watchdog.end(1));
+    }
+
+    #[test]
+    fn interrupted_then_new_drag() {
+        // The first drag never ended, and the editor then starts a new drag on the same parameter
+        let watchdog = GestureWatchdog::new([1]);
+
+        assert!(watchdog.begin(1));
+        assert!(!watchdog.set(1));
+        assert!(!watchdog.begin(1));
+        assert!(!watchdog.set(1));
+        assert!(watchdog.end(1));
+        assert!(tick_past_timeout(&watchdog).is_empty());
+    }
+
+    #[test]
+    fn set_without_gesture() {
+        let watchdog = GestureWatchdog::new([1]);
+
+        assert!(!watchdog.set(1));
+        assert!(tick_past_timeout(&watchdog).is_empty());
+    }
+
+    #[test]
+    fn unknown_parameter() {
+        let watchdog = GestureWatchdog::new([1]);
+
+        assert!(watchdog.begin(2));
+        assert!(!watchdog.set(2));
+        assert!(watchdog.end(2));
+    }
+}
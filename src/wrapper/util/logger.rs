@@ -0,0 +1,439 @@
+//! Routing for the messages logged using the `nih_*!()` macros. Messages are sent to the host's
+//! logging facilities when a plugin instance has registered a [`HostLogger`], and to the logger set
+//! up by [`setup_logger()`][super::setup_logger()] otherwise. Messages logged from the audio thread
+//! are queued and written from a background thread so logging never blocks or allocates on the
+//! audio thread.
+//!
+//! The logging macros don't know which plugin instance they're called from, so the wrappers
+//! [enter][LoggerRegistration::enter()] their instance's registration whenever they call into the
+//! plugin. Messages logged outside of those calls are written to the regular logger.
+
+use crossbeam::queue::ArrayQueue;
+use log::Level;
+use parking_lot::Mutex;
+use std::cell::Cell;
+use std::fmt::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// The maximum number of messages logged from the audio thread that can be waiting to be written.
+/// When the queue is full, the oldest messages are dropped.
+const AUDIO_THREAD_QUEUE_CAPACITY: usize = 512;
+/// The maximum length in bytes of a message logged from the audio thread. Longer messages are
+/// truncated.
+const AUDIO_THREAD_MESSAGE_CAPACITY: usize = 1024;
+/// The maximum length in bytes of the target of a message logged from the audio thread.
+const AUDIO_THREAD_TARGET_CAPACITY: usize = 128;
+/// How often the flush thread writes the queued messages.
+const FLUSH_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Something that can write log messages to the host. These are registered with [`register()`].
+pub trait HostLogger: Send + Sync {
+    /// Write a formatted message to the host's log. This is never called from the audio thread.
+    fn log(&self, level: Level, message: &str);
+}
+
+/// Keeps the flush thread running, and if a [`HostLogger`] was passed to [`register()`], routes
+/// log messages to that logger. Messages are written to the regular logger again once every
+/// registration has been dropped.
+#[must_use]
+pub struct LoggerRegistration {
+    id: u64,
+}
+
+/// Routes the messages logged on the current thread to a registration's host logger until this is
+/// dropped. See [`LoggerRegistration::enter()`].
+pub struct InstanceGuard {
+    previous_instance: Option<u64>,
+}
+
+/// Marks the current thread as an audio thread until this is dropped. See
+/// [`AudioThreadGuard::enter()`].
+pub struct AudioThreadGuard {
+    was_audio_thread: bool,
+}
+
+/// A message logged from the audio thread, waiting to be written by the flush thread. The queue's
+/// slots are allocated up front, so queueing a message does not allocate.
+struct QueuedMessage {
+    level: Level,
+    /// The registration ID of the plugin instance that logged the message, if any.
+    instance: Option<u64>,
+    target: FixedString<AUDIO_THREAD_TARGET_CAPACITY>,
+    module_path: &'static str,
+    file: &'static str,
+    line: u32,
+    message: FixedString<AUDIO_THREAD_MESSAGE_CAPACITY>,
+}
+
+/// A string stored inline in a fixed size buffer. Writes past the end of the buffer are truncated
+/// at a character boundary.
+struct FixedString<const N: usize> {
+    buffer: [u8; N],
+    len: usize,
+}
+
+struct FlushThread {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+struct FlushThreadState {
+    num_registrations: usize,
+    flush_thread: Option<FlushThread>,
+}
+
+thread_local! {
+    static IS_AUDIO_THREAD: Cell<bool> = const { Cell::new(false) };
+    /// The registration ID of the plugin instance the wrapper is currently calling into on this
+    /// thread. Set using [`LoggerRegistration::enter()`].
+    static CURRENT_INSTANCE: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+static AUDIO_THREAD_QUEUE: LazyLock<ArrayQueue<QueuedMessage>> =
+    LazyLock::new(|| ArrayQueue::new(AUDIO_THREAD_QUEUE_CAPACITY));
+/// Whether messages from the audio thread should be queued. If the flush thread is not running,
+/// then they are written directly instead.
+static FLUSH_THREAD_RUNNING: AtomicBool = AtomicBool::new(false);
+static FLUSH_THREAD_STATE: Mutex<FlushThreadState> = Mutex::new(FlushThreadState {
+    num_registrations: 0,
+    flush_thread: None,
+});
+
+static NEXT_REGISTRATION_ID: AtomicU64 = AtomicU64::new(0);
+/// The registered host loggers, along with their registration IDs. A message is sent to the logger
+/// of the instance that logged it. This lock is held while writing to a host logger so the host
+/// logger cannot be unregistered in the meantime.
+static HOST_LOGGERS: Mutex<Vec<(u64, Box<dyn HostLogger>)>> = Mutex::new(Vec::new());
+
+/// Start the flush thread if it's not already running, and optionally start routing log messages
+/// to the host. This should be called when creating a plugin instance, and the registration should
+/// be dropped together with the instance.
+pub fn register(host_logger: Option<Box<dyn HostLogger>>) -> LoggerRegistration {
+    let id = NEXT_REGISTRATION_ID.fetch_add(1, Ordering::Relaxed);
+    if let Some(host_logger) = host_logger {
+        HOST_LOGGERS.lock().push((id, host_logger));
+    }
+
+    let mut state = FLUSH_THREAD_STATE.lock();
+    state.num_registrations += 1;
+    if state.flush_thread.is_none() {
+        // The queue should never be initialized from the audio thread
+        let _ = LazyLock::force(&AUDIO_THREAD_QUEUE);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = thread::Builder::new()
+            .name(String::from("nih-log-flush"))
+            .spawn({
+                let stop = stop.clone();
+                move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        flush();
+                        thread::sleep(FLUSH_INTERVAL);
+                    }
+                    flush();
+                }
+            });
+
+        // If the thread could not be spawned, then messages are simply written directly
+        if let Ok(handle) = handle {
+            state.flush_thread = Some(FlushThread { stop, handle });
+            FLUSH_THREAD_RUNNING.store(true, Ordering::Relaxed);
+        }
+    }
+
+    LoggerRegistration { id }
+}
+
+impl LoggerRegistration {
+    /// Route the messages logged on the current thread to this registration's host logger until
+    /// the returned guard is dropped. If this registration does not have a host logger, then the
+    /// messages are written to the regular logger.
+    pub fn enter(&self) -> InstanceGuard {
+        InstanceGuard {
+            previous_instance: CURRENT_INSTANCE.replace(Some(self.id)),
+        }
+    }
+}
+
+impl Drop for LoggerRegistration {
+    fn drop(&mut self) {
+        HOST_LOGGERS.lock().retain(|(id, _)| *id != self.id);
+
+        let mut state = FLUSH_THREAD_STATE.lock();
+        state.num_registrations -= 1;
+        if state.num_registrations == 0 {
+            if let Some(FlushThread { stop, handle }) = state.flush_thread.take() {
+                FLUSH_THREAD_RUNNING.store(false, Ordering::Relaxed);
+                stop.store(true, Ordering::Relaxed);
+                drop(state);
+
+                let _ = handle.join();
+            }
+        }
+    }
+}
+
+impl Drop for InstanceGuard {
+    fn drop(&mut self) {
+        CURRENT_INSTANCE.set(self.previous_instance);
+    }
+}
+
+impl AudioThreadGuard {
+    /// Mark the current thread as an audio thread. Messages logged while the guard is alive are
+    /// queued instead of being written directly.
+    pub fn enter() -> Self {
+        Self {
+            was_audio_thread: IS_AUDIO_THREAD.replace(true),
+        }
+    }
+}
+
+impl Drop for AudioThreadGuard {
+    fn drop(&mut self) {
+        IS_AUDIO_THREAD.set(self.was_audio_thread);
+    }
+}
+
+/// Log a message. This is called by the `nih_*!()` macros, which have already checked the log
+/// level.
+pub fn log(
+    level: Level,
+    target: &str,
+    module_path: &'static str,
+    file: &'static str,
+    line: u32,
+    args: fmt::Arguments,
+) {
+    let instance = CURRENT_INSTANCE.get();
+    if IS_AUDIO_THREAD.get() && FLUSH_THREAD_RUNNING.load(Ordering::Relaxed) {
+        let mut queued_target = FixedString::new();
+        let _ = queued_target.write_str(target);
+        let mut queued_message = FixedString::new();
+        let _ = queued_message.write_fmt(args);
+
+        // If the queue is full, then the oldest message gets dropped
+        let _ = AUDIO_THREAD_QUEUE.force_push(QueuedMessage {
+            level,
+            instance,
+            target: queued_target,
+            module_path,
+            file,
+            line,
+            message: queued_message,
+        });
+    } else {
+        write(level, instance, target, module_path, file, line, args);
+    }
+}
+
+/// Write all messages that were logged from the audio thread. This happens periodically on the
+/// flush thread, but this can also be called directly to make sure messages are not lost, for
+/// instance when the plugin panics.
+pub fn flush() {
+    while let Some(message) = AUDIO_THREAD_QUEUE.pop() {
+        write(
+            message.level,
+            message.instance,
+            message.target.as_str(),
+            message.module_path,
+            message.file,
+            message.line,
+            format_args!("{}", message.message.as_str()),
+        );
+    }
+}
+
+fn write(
+    level: Level,
+    instance: Option<u64>,
+    target: &str,
+    module_path: &'static str,
+    file: &'static str,
+    line: u32,
+    args: fmt::Arguments,
+) {
+    // The message is formatted before taking the lock in case formatting the message also logs
+    // something
+    let message = if cfg!(debug_assertions) {
+        format!("[{target}] {args}")
+    } else {
+        args.to_string()
+    };
+
+    // When this is called from the panic hook the panic may have happened while this thread was
+    // holding the lock, in which case locking it again would deadlock. The message then goes to
+    // the regular logger instead.
+    let host_loggers = if thread::panicking() {
+        HOST_LOGGERS.try_lock()
+    } else {
+        Some(HOST_LOGGERS.lock())
+    };
+    let host_logger = host_loggers
+        .as_ref()
+        .zip(instance)
+        .and_then(|(host_loggers, instance)| {
+            host_loggers
+                .iter()
+                .find(|(id, _)| *id == instance)
+                .map(|(_, host_logger)| host_logger)
+        });
+    match host_logger {
+        Some(host_logger) => host_logger.log(level, &message),
+        None => {
+            drop(host_loggers);
+
+            log::logger().log(
+                &log::Record::builder()
+                    .args(args)
+                    .level(level)
+                    .target(target)
+                    .module_path_static(Some(module_path))
+                    .file_static(Some(file))
+                    .line(Some(line))
+                    .build(),
+            );
+        }
+    }
+}
+
+impl<const N: usize> FixedString<N> {
+    fn new() -> Self {
+        Self {
+            buffer: [0; N],
+            len: 0,
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        // Only whole characters are ever copied to the buffer
+        std::str::from_utf8(&self.buffer[..self.len]).unwrap_or_default()
+    }
+}
+
+impl<const N: usize> Write for FixedString<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut num_bytes = s.len().min(N - self.len);
+        while !s.is_char_boundary(num_bytes) {
+            num_bytes -= 1;
+        }
+
+        self.buffer[self.len..self.len + num_bytes].copy_from_slice(&s.as_bytes()[..num_bytes]);
+        self.len += num_bytes;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    /// Records the messages written to it.
+    struct RecordingLogger {
+        messages: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl HostLogger for RecordingLogger {
+        fn log(&self, _level: Level, message: &str) {
+            self.messages.lock().push(message.to_owned());
+        }
+    }
+
+    fn recording_logger() -> (Box<dyn HostLogger>, Arc<Mutex<Vec<String>>>) {
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let host_logger = Box::new(RecordingLogger {
+            messages: messages.clone(),
+        });
+
+        (host_logger, messages)
+    }
+
+    fn log_info(args: fmt::Arguments) {
+        log(Level::Info, "test", module_path!(), file!(), line!(), args);
+    }
+
+    #[test]
+    fn messages_are_routed_to_the_logging_instance() {
+        let (first_logger, first_messages) = recording_logger();
+        let (second_logger, second_messages) = recording_logger();
+        let first_registration = register(Some(first_logger));
+        let second_registration = register(Some(second_logger));
+
+        {
+            let _instance_guard = first_registration.enter();
+            log_info(format_args!("first"));
+
+            let _instance_guard = second_registration.enter();
+            log_info(format_args!("second"));
+        }
+        // This isn't logged by either instance
+        log_info(format_args!("neither"));
+
+        // Messages from the audio thread are written by the flush thread, which needs to know
+        // which instance logged them
+        {
+            let _instance_guard = first_registration.enter();
+            let _audio_thread_guard = AudioThreadGuard::enter();
+            log_info(format_args!("first from the audio thread"));
+        }
+        flush();
+        let start = Instant::now();
+        while first_messages.lock().len() < 2 && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(FLUSH_INTERVAL);
+        }
+
+        let strip_target = |messages: &Mutex<Vec<String>>| -> Vec<String> {
+            messages
+                .lock()
+                .iter()
+                .map(|message| message.trim_start_matches("[test] ").to_owned())
+                .collect()
+        };
+        assert_eq!(
+            strip_target(&first_messages),
+            ["first", "first from the audio thread"]
+        );
+        assert_eq!(strip_target(&second_messages), ["second"]);
+    }
+
+    #[test]
+    fn panicking_while_holding_the_host_loggers_does_not_deadlock() {
+        // Logging while unwinding goes through the same path as logging from the panic hook
+        struct LogOnDrop;
+
+        impl Drop for LogOnDrop {
+            fn drop(&mut self) {
+                log_info(format_args!("unwinding"));
+            }
+        }
+
+        let (host_logger, messages) = recording_logger();
+        let registration = register(Some(host_logger));
+        let _instance_guard = registration.enter();
+
+        let result = std::panic::catch_unwind(|| {
+            let _host_loggers = HOST_LOGGERS.lock();
+            let _log_on_drop = LogOnDrop;
+            panic!("panicked while holding the host loggers");
+        });
+        assert!(result.is_err());
+        assert!(messages.lock().is_empty());
+    }
+
+    #[test]
+    fn fixed_strings_truncate_at_character_boundaries() {
+        let mut string = FixedString::<5>::new();
+        write!(string, "abc{}", "défg").unwrap();
+        assert_eq!(string.as_str(), "abcd");
+
+        let mut string = FixedString::<6>::new();
+        write!(string, "abc{}", "défg").unwrap();
+        assert_eq!(string.as_str(), "abcdé");
+    }
+}
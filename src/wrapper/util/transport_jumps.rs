@@ -0,0 +1,258 @@
+//! Detecting loops and other jumps in the host's transport position.
+
+use crate::prelude::{Transport, TransportJump};
+
+/// The maximum difference in samples between the expected and the reported playback position
+/// before it's considered to be a jump. Positions computed from seconds or beats are rounded, so
+/// these can be off by a sample.
+const TOLERANCE_SAMPLES: f64 = 1.0;
+
+/// Compares the transport's playback position to the position at the end of the previous block to
+/// fill in [`Transport::jump`]. This also keeps track of when the transport stops playing so the
+/// wrapper can send [`NoteEvent::AllNotesOff`][crate::prelude::NoteEvent::AllNotesOff] events. This
+/// is called from the audio thread for every block the plugin processes.
+#[derive(Debug, Default)]
+pub struct TransportJumpDetector {
+    /// Where the playback position should be at the start of the next block if the transport
+    /// keeps playing without any jumps. `None` if the transport was not playing during the previous
+    /// block or if the host did not report a position.
+    expected_pos: Option<Position>,
+    /// Whether the transport was playing during the previous block.
+    was_playing: bool,
+    /// Whether the transport was playing during the previous block, but not during the current
+    /// block.
+    playback_stopped: bool,
+}
+
+/// A playback position. Positions are compared in samples when the host reports the position in
+/// samples or seconds. If the host only reports the position in beats, then the position is also
+/// compared in beats since converting that to samples would cause tempo changes to look like
+/// jumps.
+#[derive(Debug, Clone, Copy)]
+enum Position {
+    Samples(f64),
+    Beats { beats: f64, tempo: f64 },
+}
+
+impl TransportJumpDetector {
+    /// Set `transport.jump` based on the position reported for the previous block, and store the
+    /// position the next block of audio should start at assuming the current block is
+    /// `num_samples` samples long.
+    pub fn update(&mut self, transport: &mut Transport, num_samples: usize) {
+        self.playback_stopped = self.was_playing && !transport.playing;
+        self.was_playing = transport.playing;
+
+        let current_pos = if !transport.playing {
+            None
+        } else if transport.pos_samples.is_some() || transport.pos_seconds.is_some() {
+            transport
+                .pos_samples()
+                .map(|pos| Position::Samples(pos as f64))
+        } else {
+            match (transport.pos_beats, transport.tempo) {
+                (Some(beats), Some(tempo)) => Some(Position::Beats { beats, tempo }),
+                _ => None,
+            }
+        };
+
+        transport.jump = match (self.expected_pos, current_pos) {
+            (Some(Position::Samples(expected)), Some(Position::Samples(current))) => {
+                let loop_range = transport
+                    .loop_range_samples()
+                    .map(|(start, end)| (start as f64, end as f64));
+
+                detect_jump(expected, current, loop_range, TOLERANCE_SAMPLES)
+            }
+            (
+                Some(Position::Beats {
+                    beats: expected, ..
+                }),
+                Some(Position::Beats {
+                    beats: current,
+                    tempo,
+                }),
+            ) => {
+                let tolerance = TOLERANCE_SAMPLES / transport.sample_rate as f64 / 60.0 * tempo;
+
+                detect_jump(expected, current, transport.loop_range_beats(), tolerance)
+            }
+            _ => None,
+        };
+
+        self.expected_pos = current_pos.map(|pos| match pos {
+            Position::Samples(pos) => Position::Samples(pos + num_samples as f64),
+            Position::Beats { beats, tempo } => Position::Beats {
+                beats: beats + (num_samples as f64 / transport.sample_rate as f64 / 60.0 * tempo),
+                tempo,
+            },
+        });
+    }
+
+    /// Whether the transport stopped playing at the start of the block passed to the last
+    /// [`update()`][Self::update()] call.
+    pub fn playback_stopped(&self) -> bool {
+        self.playback_stopped
+    }
+}
+
+/// Determine whether the transport jumped based on the `expected` and `current` positions and the
+/// loop range, all in the same unit. If the host does not split blocks at the loop boundary, then
+/// the position will have wrapped around by the same amount the expected position overshoots the
+/// loop's end.
+fn detect_jump(
+    expected: f64,
+    current: f64,
+    loop_range: Option<(f64, f64)>,
+    tolerance: f64,
+) -> Option<TransportJump> {
+    if (current - expected).abs() <= tolerance {
+        return None;
+    }
+
+    match loop_range {
+        Some((start, end))
+            if expected >= end - tolerance
+                && (current - (start + (expected - end))).abs() <= tolerance =>
+        {
+            Some(TransportJump::Looped)
+        }
+        _ => Some(TransportJump::Jumped),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+    const BLOCK_SIZE: i64 = 512;
+
+    /// Simulates a host that plays a loop region from `loop_start` to `loop_end`, splitting blocks
+    /// at the loop's end. Returns the jumps reported for every block along with that block's start
+    /// position.
+    fn simulate_loop(
+        loop_start: i64,
+        loop_end: i64,
+        num_blocks: usize,
+        report_loop: bool,
+    ) -> Vec<(i64, Option<TransportJump>)> {
+        let mut detector = TransportJumpDetector::default();
+        let mut pos = loop_start;
+        let mut jumps = Vec::new();
+        for _ in 0..num_blocks {
+            let num_samples = BLOCK_SIZE.min(loop_end - pos);
+
+            let mut transport = Transport::new(SAMPLE_RATE);
+            transport.playing = true;
+            transport.pos_samples = Some(pos);
+            if report_loop {
+                transport.loop_range_samples = Some((loop_start, loop_end));
+            }
+            detector.update(&mut transport, num_samples as usize);
+            jumps.push((pos, transport.jump));
+
+            pos += num_samples;
+            if pos >= loop_end {
+                pos = loop_start;
+            }
+        }
+
+        jumps
+    }
+
+    #[test]
+    fn loop_wraps() {
+        // The loop is 1200 samples long, so with 512 sample blocks it wraps every 3 blocks
+        let jumps = simulate_loop(4800, 6000, 8, true);
+        for (idx, (pos, jump)) in jumps.into_iter().enumerate() {
+            if idx == 3 || idx == 6 {
+                assert_eq!((pos, jump), (4800, Some(TransportJump::Looped)));
+            } else {
+                assert_eq!(jump, None, "Unexpected jump at {pos}");
+            }
+        }
+    }
+
+    #[test]
+    fn loop_without_loop_info() {
+        let jumps = simulate_loop(4800, 6000, 4, false);
+        assert_eq!(jumps[2].1, None);
+        assert_eq!(jumps[3], (4800, Some(TransportJump::Jumped)));
+    }
+
+    #[test]
+    fn unsplit_loop_wrap() {
+        // Hosts that don't split blocks at the loop boundary report a position past the loop's
+        // start
+        let mut detector = TransportJumpDetector::default();
+        for (pos, expected_jump) in [
+            (5000, None),
+            (5512, None),
+            (4800 + (6024 - 6000), Some(TransportJump::Looped)),
+        ] {
+            let mut transport = Transport::new(SAMPLE_RATE);
+            transport.playing = true;
+            transport.pos_samples = Some(pos);
+            transport.loop_range_samples = Some((4800, 6000));
+            detector.update(&mut transport, BLOCK_SIZE as usize);
+            assert_eq!(transport.jump, expected_jump);
+        }
+    }
+
+    #[test]
+    fn seek_and_stop() {
+        let mut detector = TransportJumpDetector::default();
+        let mut update = |playing: bool, pos: Option<i64>| {
+            let mut transport = Transport::new(SAMPLE_RATE);
+            transport.playing = playing;
+            transport.pos_samples = pos;
+            detector.update(&mut transport, BLOCK_SIZE as usize);
+
+            transport.jump
+        };
+
+        assert_eq!(update(true, Some(0)), None);
+        assert_eq!(update(true, Some(BLOCK_SIZE)), None);
+        assert_eq!(update(true, Some(96_000)), Some(TransportJump::Jumped));
+        // Stopping and starting playback somewhere else is not a jump
+        assert_eq!(update(false, Some(0)), None);
+        assert_eq!(update(true, Some(0)), None);
+        // And neither is not knowing the position
+        assert_eq!(update(true, None), None);
+        assert_eq!(update(true, Some(0)), None);
+    }
+
+    #[test]
+    fn playback_stopped() {
+        let mut detector = TransportJumpDetector::default();
+        let mut update = |playing: bool| {
+            let mut transport = Transport::new(SAMPLE_RATE);
+            transport.playing = playing;
+            detector.update(&mut transport, BLOCK_SIZE as usize);
+
+            detector.playback_stopped()
+        };
+
+        assert!(!update(false));
+        assert!(!update(true));
+        assert!(!update(true));
+        assert!(update(false));
+        assert!(!update(false));
+    }
+
+    #[test]
+    fn beats_with_tempo_change() {
+        let mut detector = TransportJumpDetector::default();
+        let mut beats = 0.0;
+        for tempo in [120.0, 90.0, 140.0, 60.0] {
+            let mut transport = Transport::new(SAMPLE_RATE);
+            transport.playing = true;
+            transport.tempo = Some(tempo);
+            transport.pos_beats = Some(beats);
+            detector.update(&mut transport, BLOCK_SIZE as usize);
+            assert_eq!(transport.jump, None);
+
+            beats += BLOCK_SIZE as f64 / SAMPLE_RATE as f64 / 60.0 * tempo;
+        }
+    }
+}
@@ -1,3 +1,28 @@
+//! The VST3 wrapper. VST3 splits a plugin into an audio processor component and an edit
+//! controller that may live in separate objects. NIH-plug uses a single object that implements
+//! both, so the processor and the controller share the same parameter objects and there's no
+//! separate state to keep in sync. Parameter changes flow as follows:
+//!
+//! - **GUI edits**: the editor's `GuiContext` calls `IComponentHandler::beginEdit()`,
+//!   `performEdit()`, and `endEdit()` so the host can record automation. If the plugin is not
+//!   processing audio, then the parameter is also set right away. Otherwise the host sends the
+//!   edit back to the plugin as a parameter change in the next `IAudioProcessor::process()` call,
+//!   so the value never changes in the middle of a process call.
+//! - **Host automation**: changes sent to `IAudioProcessor::process()` and
+//!   `IEditController::setParamNormalized()` set the parameter and notify the editor through
+//!   `Editor::param_value_changed()`. These are never reported back to the host.
+//!   `setParamNormalized()` ignores changes while processing since the host will also send those
+//!   to the process function.
+//! - **State restores**: `IComponent::setState()` sets all parameters and notifies the editor
+//!   through `Editor::param_values_changed()`. `IEditController::setComponentState()` and
+//!   `IEditController::setState()` don't do anything since the component has already restored
+//!   the state. States restored from the GUI through `GuiContext::set_state()` are loaded at the
+//!   end of the current process call if the plugin is processing audio, and the host is then told
+//!   to rescan all parameter values using `IComponentHandler::restartComponent()`.
+//!
+//! The tests at the bottom of `wrapper.rs` exercise these paths with a stand-in for the host's
+//! component handler.
+
 #[macro_use]
 mod util;
 
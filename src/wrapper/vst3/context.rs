@@ -3,12 +3,13 @@ use std::cell::Cell;
 use std::collections::VecDeque;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use vst3_sys::vst::IComponentHandler;
+use vst3_sys::vst::{IComponentHandler, IComponentHandler2};
 
 use crate::prelude::{
-    GuiContext, InitContext, ParamPtr, PluginApi, PluginNoteEvent, PluginState, ProcessContext,
-    Transport, Vst3Plugin,
+    GuiContext, HostInfo, InitContext, ParamPtr, PluginApi, PluginNoteEvent, PluginState,
+    ProcessContext, ProcessMode, Transport, Vst3Plugin,
 };
+use crate::util::ProcessLoad;
 
 use super::inner::{Task, WrapperInner};
 
@@ -32,6 +33,8 @@ pub(crate) struct WrapperInitContext<'a, P: Vst3Plugin> {
 pub(crate) struct PendingInitContextRequests {
     /// The value of the last `.set_latency_samples()` call.
     latency_changed: Cell<Option<u32>>,
+    /// The value of the last `.set_tail_samples()` call.
+    tail_changed: Cell<Option<u32>>,
 }
 
 /// A [`ProcessContext`] implementation for the wrapper. This is a separate object so it can hold on
@@ -59,6 +62,9 @@ impl<P: Vst3Plugin> Drop for WrapperInitContext<'_, P> {
         if let Some(samples) = self.pending_requests.latency_changed.take() {
             self.inner.set_latency_samples(samples)
         }
+        if let Some(samples) = self.pending_requests.tail_changed.take() {
+            self.inner.set_tail_samples(samples)
+        }
     }
 }
 
@@ -67,6 +73,10 @@ impl<P: Vst3Plugin> InitContext<P> for WrapperInitContext<'_, P> {
         PluginApi::Vst3
     }
 
+    fn host_info(&self) -> Option<HostInfo> {
+        self.inner.host_info.borrow().clone()
+    }
+
     fn execute(&self, task: P::BackgroundTask) {
         (self.inner.task_executor.lock())(task);
     }
@@ -76,6 +86,11 @@ impl<P: Vst3Plugin> InitContext<P> for WrapperInitContext<'_, P> {
         self.pending_requests.latency_changed.set(Some(samples));
     }
 
+    fn set_tail_samples(&self, samples: u32) {
+        // See this struct's docstring
+        self.pending_requests.tail_changed.set(Some(samples));
+    }
+
     fn set_current_voice_capacity(&self, _capacity: u32) {
         // This is only supported by CLAP
     }
@@ -101,6 +116,14 @@ impl<P: Vst3Plugin> ProcessContext<P> for WrapperProcessContext<'_, P> {
         &self.transport
     }
 
+    fn process_mode(&self) -> ProcessMode {
+        self.inner.current_process_mode.load()
+    }
+
+    fn is_editor_open(&self) -> bool {
+        self.inner.editor_open.load(Ordering::SeqCst)
+    }
+
     fn next_event(&mut self) -> Option<PluginNoteEvent<P>> {
         self.input_events_guard.pop_front()
     }
@@ -113,9 +136,24 @@ impl<P: Vst3Plugin> ProcessContext<P> for WrapperProcessContext<'_, P> {
         self.inner.set_latency_samples(samples)
     }
 
+    fn set_tail_samples(&self, samples: u32) {
+        self.inner.set_tail_samples(samples)
+    }
+
+    fn set_gain_reduction(&self, gain_reduction_db: f32) {
+        self.inner.gain_reduction_meter.report(gain_reduction_db)
+    }
+
     fn set_current_voice_capacity(&self, _capacity: u32) {
         // This is only supported by CLAP
     }
+
+    fn execute_parallel(&self, num_tasks: u32, task: &(dyn Fn(u32) + Sync)) {
+        // VST3 doesn't have a thread pool, so we'll just run these tasks sequentially
+        for task_index in 0..num_tasks {
+            task(task_index);
+        }
+    }
 }
 
 impl<P: Vst3Plugin> GuiContext for WrapperGuiContext<P> {
@@ -137,9 +175,12 @@ impl<P: Vst3Plugin> GuiContext for WrapperGuiContext<P> {
     unsafe fn raw_begin_set_parameter(&self, param: ParamPtr) {
         match &*self.inner.component_handler.borrow() {
             Some(handler) => match self.inner.param_ptr_to_hash.get(&param) {
-                Some(hash) => {
+                // Duplicate begins can happen when a previous drag was interrupted. In that case
+                // the existing gesture is continued.
+                Some(hash) if self.inner.gesture_watchdog.begin(*hash) => {
                     handler.begin_edit(*hash);
                 }
+                Some(_) => (),
                 None => nih_debug_assert_failure!("Unknown parameter: {:?}", param),
             },
             None => nih_debug_assert_failure!("Component handler not yet set"),
@@ -179,6 +220,11 @@ impl<P: Vst3Plugin> GuiContext for WrapperGuiContext<P> {
                         );
                     }
 
+                    // If the gesture was ended automatically because it didn't receive any changes
+                    // for a while, then it needs to be restarted first
+                    if self.inner.gesture_watchdog.set(*hash) {
+                        handler.begin_edit(*hash);
+                    }
                     handler.perform_edit(*hash, normalized as f64);
                 }
                 None => nih_debug_assert_failure!("Unknown parameter: {:?}", param),
@@ -201,9 +247,11 @@ impl<P: Vst3Plugin> GuiContext for WrapperGuiContext<P> {
     unsafe fn raw_end_set_parameter(&self, param: ParamPtr) {
         match &*self.inner.component_handler.borrow() {
             Some(handler) => match self.inner.param_ptr_to_hash.get(&param) {
-                Some(hash) => {
+                // The gesture may have already been ended automatically by the wrapper
+                Some(hash) if self.inner.gesture_watchdog.end(*hash) => {
                     handler.end_edit(*hash);
                 }
+                Some(_) => (),
                 None => nih_debug_assert_failure!("Unknown parameter: {:?}", param),
             },
             None => nih_debug_assert_failure!("Component handler not yet set"),
@@ -221,6 +269,64 @@ impl<P: Vst3Plugin> GuiContext for WrapperGuiContext<P> {
         }
     }
 
+    unsafe fn raw_set_parameters_normalized(
+        &self,
+        values: &[(ParamPtr, f32)],
+        reset_smoothers: bool,
+    ) {
+        match &*self.inner.component_handler.borrow() {
+            Some(handler) => {
+                // See `raw_set_parameter_normalized()` for why the values are only set here when
+                // the plugin is not processing audio
+                let is_processing = self.inner.is_processing.load(Ordering::SeqCst);
+                let sample_rate = self
+                    .inner
+                    .current_buffer_config
+                    .load()
+                    .map(|c| c.sample_rate);
+
+                // Like the CLAP wrapper's single parameter flush, hosts that support
+                // `IComponentHandler2` get the entire batch as a single group edit
+                let group_handler = handler.cast::<dyn IComponentHandler2>();
+                if let Some(group_handler) = &group_handler {
+                    group_handler.start_group_edit();
+                }
+
+                for &(param, normalized) in values {
+                    match self.inner.param_ptr_to_hash.get(&param) {
+                        Some(hash) => {
+                            if !is_processing {
+                                self.inner.set_normalized_value_by_hash(
+                                    *hash,
+                                    normalized,
+                                    sample_rate,
+                                );
+                                if let (true, Some(sample_rate)) = (reset_smoothers, sample_rate) {
+                                    param.update_smoother(sample_rate, true);
+                                }
+                            }
+
+                            let gesture_started = self.inner.gesture_watchdog.begin(*hash);
+                            if gesture_started {
+                                handler.begin_edit(*hash);
+                            }
+                            handler.perform_edit(*hash, normalized as f64);
+                            if gesture_started && self.inner.gesture_watchdog.end(*hash) {
+                                handler.end_edit(*hash);
+                            }
+                        }
+                        None => nih_debug_assert_failure!("Unknown parameter: {:?}", param),
+                    }
+                }
+
+                if let Some(group_handler) = &group_handler {
+                    group_handler.finish_group_edit();
+                }
+            }
+            None => nih_debug_assert_failure!("Component handler not yet set"),
+        }
+    }
+
     fn get_state(&self) -> PluginState {
         self.inner.get_state_object()
     }
@@ -228,4 +334,8 @@ impl<P: Vst3Plugin> GuiContext for WrapperGuiContext<P> {
     fn set_state(&self, state: PluginState) {
         self.inner.set_state_object_from_gui(state)
     }
+
+    fn process_load(&self) -> Option<Arc<ProcessLoad>> {
+        ProcessLoad::ENABLED.then(|| self.inner.process_load.clone())
+    }
 }
@@ -16,13 +16,20 @@ use super::util::{ObjectPtr, VstPtr, VST3_MIDI_PARAMS_END, VST3_MIDI_PARAMS_STAR
 use super::view::WrapperView;
 use crate::event_loop::{EventLoop, MainThreadExecutor, OsEventLoop};
 use crate::prelude::{
-    AsyncExecutor, AudioIOLayout, BufferConfig, Editor, MidiConfig, ParamFlags, ParamPtr, Params,
-    Plugin, PluginNoteEvent, ProcessMode, ProcessStatus, TaskExecutor, Transport, Vst3Plugin,
+    AsyncExecutor, AudioIOLayout, BufferConfig, Editor, HostInfo, MidiConfig, ParamFlags, ParamPtr,
+    Params, Plugin, PluginNoteEvent, ProcessMode, ProcessStatus, TaskExecutor, Transport,
+    Vst3Plugin,
 };
-use crate::util::permit_alloc;
+use crate::util::{permit_alloc, ProcessLoad};
 use crate::wrapper::state::{self, PluginState};
+use crate::wrapper::util::automation::AutomationRecorder;
 use crate::wrapper::util::buffer_management::BufferManager;
-use crate::wrapper::util::{hash_param_id, process_wrapper};
+use crate::wrapper::util::fixed_block::FixedBlockBuffers;
+use crate::wrapper::util::gain_reduction::GainReductionMeter;
+use crate::wrapper::util::gestures::GestureWatchdog;
+use crate::wrapper::util::logger::{self, LoggerRegistration};
+use crate::wrapper::util::transport_jumps::TransportJumpDetector;
+use crate::wrapper::util::{hash_param_id, process_wrapper, tail_length_samples};
 
 /// The actual wrapper bits. We need this as an `Arc<T>` so we can safely use our event loop API.
 /// Since we can't combine that with VST3's interior reference counting this just has to be moved to
@@ -40,6 +47,15 @@ pub(crate) struct WrapperInner<P: Vst3Plugin> {
     /// to instantiate this in advance so we don't need to lock the entire [`Plugin`] object when
     /// creating an editor. Wrapped in an `AtomicRefCell` because it needs to be initialized late.
     pub editor: AtomicRefCell<Option<Arc<Mutex<Box<dyn Editor>>>>>,
+    /// Whether the editor is currently open. This is managed by the
+    /// [`EditorHandle`][crate::wrapper::util::EditorHandle] stored in
+    /// [`WrapperView`][super::WrapperView], and it can be queried through the
+    /// [`ProcessContext`][crate::prelude::ProcessContext].
+    pub editor_open: Arc<AtomicBool>,
+
+    /// The host's name, queried from the `IHostApplication` instance passed to
+    /// [`IPluginBase::initialize()`][vst3_sys::base::IPluginBase::initialize()].
+    pub host_info: AtomicRefCell<Option<HostInfo>>,
 
     /// The host's [`IComponentHandler`] instance, if passed through
     /// [`IEditController::set_component_handler`].
@@ -63,6 +79,13 @@ pub(crate) struct WrapperInner<P: Vst3Plugin> {
     /// Whether the plugin is currently processing audio. In other words, the last state
     /// `IAudioProcessor::setActive()` has been called with.
     pub is_processing: AtomicBool,
+    /// Whether the plugin has been activated through `IComponent::setActive()` and successfully
+    /// initialized.
+    pub is_active: AtomicBool,
+    /// Set when the host changes the bus arrangement or the processing setup while the plugin is
+    /// processing audio. The plugin outputs silence until it gets reinitialized when processing is
+    /// resumed in `IAudioProcessor::setProcessing()`.
+    pub needs_reinitialization: AtomicBool,
     /// The current audio IO layout. Modified through `IAudioProcessor::setBusArrangements()` after
     /// matching the proposed bus arrangement to one of the supported ones. The plugin's first audio
     /// IO layout is chosen as the default. Because of the way VST3 works it's not possible to
@@ -71,6 +94,9 @@ pub(crate) struct WrapperInner<P: Vst3Plugin> {
     /// The current buffer configuration, containing the sample rate and the maximum block size.
     /// Will be set in `IAudioProcessor::setupProcessing()`.
     pub current_buffer_config: AtomicCell<Option<BufferConfig>>,
+    /// The buffer configuration the plugin was last initialized with. Used to notify the editor
+    /// when this changes.
+    pub initialized_buffer_config: AtomicCell<Option<BufferConfig>>,
     /// The current audio processing mode. Set in `IAudioProcessor::setup_processing()`.
     pub current_process_mode: AtomicCell<ProcessMode>,
     /// The last process status returned by the plugin. This is used for tail handling.
@@ -78,9 +104,32 @@ pub(crate) struct WrapperInner<P: Vst3Plugin> {
     /// The current latency in samples, as set by the plugin through the [`InitContext`] and the
     /// [`ProcessContext`].
     pub current_latency: AtomicU32,
+    /// Measures how long the process function takes. Only updated when the `process_profiling`
+    /// feature is enabled.
+    pub process_load: Arc<ProcessLoad>,
+    /// Records the parameter changes sent by the host when the `automation_recording` feature is
+    /// enabled and the `NIH_RECORD_AUTOMATION` environment variable is set.
+    pub automation_recorder: AutomationRecorder,
+    /// The current tail length in samples, if the plugin has set one through the [`InitContext`] or
+    /// the [`ProcessContext`]. If this is `None`, then the tail length is derived from the last
+    /// process status instead.
+    pub current_tail: AtomicCell<Option<u32>>,
+    /// The tail length that was last reported to the host through
+    /// `IAudioProcessor::getTailSamples()`. A tail change only restarts the plugin if the new tail
+    /// differs from this.
+    pub reported_tail: AtomicU32,
+    /// Set while a [`Task::TailChanged`] is queued. Plugins may update their tail length every
+    /// block, so this ensures that there's at most one pending tail change at any time.
+    pub tail_change_pending: AtomicBool,
     /// A data structure that helps manage and create buffers for all of the plugin's inputs and
     /// outputs based on channel pointers provided by the host.
     pub buffer_manager: AtomicRefCell<BufferManager>,
+    /// Buffers the host's audio into blocks of [`Plugin::FIXED_BLOCK_SIZE`] samples. This is only
+    /// set if the plugin uses a fixed block size, and it's created when the plugin is initialized.
+    pub fixed_block_buffers: AtomicRefCell<Option<FixedBlockBuffers<P::SysExMessage>>>,
+    /// Fills in [`Transport::jump`] by comparing the host's playback position to the previous
+    /// block's position.
+    pub transport_jump_detector: AtomicRefCell<TransportJumpDetector>,
     /// The incoming events for the plugin, if `P::ACCEPTS_MIDI` is set. If
     /// `P::SAMPLE_ACCURATE_AUTOMATION`, this is also read in lockstep with the parameter change
     /// block splitting.
@@ -137,6 +186,15 @@ pub(crate) struct WrapperInner<P: Vst3Plugin> {
     /// having to add a setter function to the parameter (or even worse, have it be completely
     /// untyped).
     pub param_ptr_to_hash: HashMap<ParamPtr, u32>,
+    /// Keeps track of the gestures started by the editor so dangling gestures can be ended
+    /// automatically. See [`GestureWatchdog`] for more information.
+    pub gesture_watchdog: GestureWatchdog,
+    /// Sends the gain reduction reported by the plugin to the host through the plugin's gain
+    /// reduction parameter. See [`GainReductionMeter`] for more information.
+    pub gain_reduction_meter: GainReductionMeter,
+    /// Keeps the thread that writes messages logged from the audio thread alive. VST3 does not have
+    /// a way to send log messages to the host.
+    _logger_registration: LoggerRegistration,
 }
 
 /// Tasks that can be sent from the plugin to be executed on the main thread in a non-blocking
@@ -154,9 +212,17 @@ pub enum Task<P: Plugin> {
     /// Trigger a restart with the given restart flags. This is a bit set of the flags from
     /// [`vst3_sys::vst::RestartFlags`].
     TriggerRestart(i32),
+    /// The plugin has changed its tail length. If the tail length reported to the host has changed
+    /// by the time this task runs, then this triggers a restart so the host queries the new value.
+    TailChanged,
     /// Request the editor to be resized according to its current size. Right now there is no way to
     /// handle "denied resize" requests yet.
     RequestResize,
+    /// End a parameter gesture the editor has left dangling. This uses the parameter hashes since
+    /// the task will be created from the audio thread.
+    EndParamGesture(u32),
+    /// Inform the editor that the plugin has been initialized with a new buffer config.
+    BufferConfigChanged(BufferConfig),
 }
 
 /// VST3 makes audio processing pretty complicated. In order to support both block splitting for
@@ -222,6 +288,19 @@ impl<P: Vst3Plugin> WrapperInner<P> {
                  6 character parameter IDs to avoid collisions."
             );
 
+            // The plugin APIs only see the hashes, so two distinct IDs must also not hash to the
+            // same value. This includes the prefixed IDs from nested parameter structs.
+            let param_hashes: HashSet<u32> = param_id_hashes_ptrs_groups
+                .iter()
+                .map(|(_, hash, _, _)| *hash)
+                .collect();
+            nih_debug_assert_eq!(
+                param_ids.len(),
+                param_hashes.len(),
+                "Some of the plugin's parameter IDs have the same hash, weird things may happen. \
+                 Try renaming the parameters with colliding IDs."
+            );
+
             let mut bypass_param_exists = false;
             for (id, hash, ptr, _) in &param_id_hashes_ptrs_groups {
                 let flags = unsafe { ptr.flags() };
@@ -247,10 +326,16 @@ impl<P: Vst3Plugin> WrapperInner<P> {
             }
         }
 
-        let param_hashes = param_id_hashes_ptrs_groups
+        let param_hashes: Vec<u32> = param_id_hashes_ptrs_groups
             .iter()
             .map(|(_, hash, _, _)| *hash)
             .collect();
+        let gesture_watchdog = GestureWatchdog::new(param_hashes.iter().copied());
+        let gain_reduction_meter = GainReductionMeter::new(
+            param_id_hashes_ptrs_groups
+                .iter()
+                .map(|(_, hash, ptr, _)| (*hash, *ptr)),
+        );
         let param_by_hash = param_id_hashes_ptrs_groups
             .iter()
             .map(|(_, hash, ptr, _)| (*hash, *ptr))
@@ -280,6 +365,9 @@ impl<P: Vst3Plugin> WrapperInner<P> {
             params,
             // Initialized later as it needs a reference to the wrapper for the async executor
             editor: AtomicRefCell::new(None),
+            editor_open: Arc::new(AtomicBool::new(false)),
+
+            host_info: AtomicRefCell::new(None),
 
             component_handler: AtomicRefCell::new(None),
 
@@ -288,6 +376,8 @@ impl<P: Vst3Plugin> WrapperInner<P> {
             event_loop: AtomicRefCell::new(None),
 
             is_processing: AtomicBool::new(false),
+            is_active: AtomicBool::new(false),
+            needs_reinitialization: AtomicBool::new(false),
             // Some hosts, like the current version of Bitwig and Ardour at the time of writing,
             // will try using the plugin's default not yet initialized bus arrangement. Because of
             // that, we'll always initialize this configuration even before the host requests a
@@ -296,15 +386,23 @@ impl<P: Vst3Plugin> WrapperInner<P> {
                 P::AUDIO_IO_LAYOUTS.first().copied().unwrap_or_default(),
             ),
             current_buffer_config: AtomicCell::new(None),
+            initialized_buffer_config: AtomicCell::new(None),
             current_process_mode: AtomicCell::new(ProcessMode::Realtime),
             last_process_status: AtomicCell::new(ProcessStatus::Normal),
             current_latency: AtomicU32::new(0),
+            process_load: Arc::new(ProcessLoad::default()),
+            automation_recorder: AutomationRecorder::from_env(),
+            current_tail: AtomicCell::new(None),
+            reported_tail: AtomicU32::new(0),
+            tail_change_pending: AtomicBool::new(false),
             // This is initialized just before calling `Plugin::initialize()` so that during the
             // process call buffers can be initialized without any allocations
             buffer_manager: AtomicRefCell::new(BufferManager::for_audio_io_layout(
                 0,
                 AudioIOLayout::default(),
             )),
+            fixed_block_buffers: AtomicRefCell::new(None),
+            transport_jump_detector: AtomicRefCell::new(TransportJumpDetector::default()),
             input_events: AtomicRefCell::new(VecDeque::with_capacity(1024)),
             output_events: AtomicRefCell::new(VecDeque::with_capacity(1024)),
             note_expression_controller: AtomicRefCell::new(NoteExpressionController::default()),
@@ -318,6 +416,9 @@ impl<P: Vst3Plugin> WrapperInner<P> {
             param_units,
             param_id_to_hash,
             param_ptr_to_hash,
+            gesture_watchdog,
+            gain_reduction_meter,
+            _logger_registration: logger::register(None),
         });
 
         // FIXME: Right now this is safe, but if we are going to have a singleton main thread queue
@@ -536,6 +637,20 @@ impl<P: Vst3Plugin> WrapperInner<P> {
         }
     }
 
+    pub fn set_tail_samples(&self, samples: u32) {
+        // Whether this needs a restart is decided on the main thread when the task runs. Only
+        // posting a new task when the last one has been handled prevents plugins that change their
+        // tail every block from flooding the task queue.
+        let old_tail = self.current_tail.swap(Some(samples));
+        if old_tail != Some(samples) && !self.tail_change_pending.swap(true, Ordering::SeqCst) {
+            let task_posted = self.schedule_gui(Task::TailChanged);
+            nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+            if !task_posted {
+                self.tail_change_pending.store(false, Ordering::SeqCst);
+            }
+        }
+    }
+
     /// Immediately set the plugin state. Returns `false` if the deserialization failed. The plugin
     /// state is set from a couple places, so this function aims to deduplicate that. Includes
     /// `permit_alloc()`s around the deserialization and initialization for the use case where
@@ -617,6 +732,13 @@ impl<P: Vst3Plugin> MainThreadExecutor<Task<P>> for WrapperInner<P> {
                     }
                 }
             }
+            Task::BufferConfigChanged(buffer_config) => {
+                if self.plug_view.read().is_some() {
+                    if let Some(editor) = self.editor.borrow().as_ref() {
+                        editor.lock().buffer_config_changed(&buffer_config);
+                    }
+                }
+            }
             Task::ParameterValueChanged(param_hash, normalized_value) => {
                 if self.plug_view.read().is_some() {
                     if let Some(editor) = self.editor.borrow().as_ref() {
@@ -640,6 +762,23 @@ impl<P: Vst3Plugin> MainThreadExecutor<Task<P>> for WrapperInner<P> {
                 },
                 None => nih_debug_assert_failure!("Component handler not yet set"),
             },
+            Task::TailChanged => {
+                self.tail_change_pending.store(false, Ordering::SeqCst);
+
+                // VST3 does not have a dedicated restart flag for tail changes. The host re-queries
+                // `IAudioProcessor::getTailSamples()` after a latency change restart, so we'll use
+                // that flag instead. The restart is only needed when the value reported to the host
+                // changes, which is not the case while the plugin is returning
+                // `ProcessStatus::KeepAlive`.
+                let tail =
+                    tail_length_samples(self.current_tail.load(), self.last_process_status.load());
+                if self.reported_tail.swap(tail, Ordering::SeqCst) != tail {
+                    self.execute(
+                        Task::TriggerRestart(RestartFlags::kLatencyChanged as i32),
+                        is_gui_thread,
+                    );
+                }
+            }
             Task::RequestResize => match &*self.plug_view.read() {
                 Some(plug_view) => unsafe {
                     nih_debug_assert!(is_gui_thread);
@@ -648,6 +787,13 @@ impl<P: Vst3Plugin> MainThreadExecutor<Task<P>> for WrapperInner<P> {
                 },
                 None => nih_debug_assert_failure!("Can't resize a closed editor"),
             },
+            Task::EndParamGesture(param_hash) => match &*self.component_handler.borrow() {
+                Some(handler) => unsafe {
+                    nih_debug_assert!(is_gui_thread);
+                    handler.end_edit(param_hash);
+                },
+                None => nih_debug_assert_failure!("Component handler not yet set"),
+            },
         }
     }
 }
@@ -1,10 +1,12 @@
 use std::cmp;
 use std::ops::Deref;
 use vst3_sys::interfaces::IUnknown;
-use vst3_sys::vst::TChar;
+use vst3_sys::vst::{ParameterFlags, ProcessData, ProcessModes, SpeakerArrangement, TChar};
 use vst3_sys::ComInterface;
 use widestring::U16CString;
 
+use crate::prelude::{ChannelLayout, ParamFlags, ProcessMode, Speaker};
+
 /// When `Plugin::MIDI_INPUT` is set to `MidiConfig::MidiCCs` or higher then we'll register 130*16
 /// additional parameters to handle MIDI CCs, channel pressure, and pitch bend, in that order.
 /// vst3-sys doesn't expose these constants.
@@ -116,6 +118,198 @@ unsafe impl<T: ComInterface + ?Sized> Sync for VstPtr<T> {}
 unsafe impl<T: IUnknown> Send for ObjectPtr<T> {}
 unsafe impl<T: IUnknown> Sync for ObjectPtr<T> {}
 
+/// vst3-sys doesn't expose the `kIsHidden` parameter flag.
+pub const VST3_PARAM_IS_HIDDEN: i32 = 1 << 4;
+
+/// Translate a parameter's [`ParamFlags`] to the flags in its VST3 `ParameterInfo`.
+pub fn to_vst3_parameter_flags(flags: ParamFlags) -> i32 {
+    let automatable = !flags.contains(ParamFlags::NON_AUTOMATABLE);
+    let hidden = flags.contains(ParamFlags::HIDDEN);
+    let is_bypass = flags.contains(ParamFlags::BYPASS);
+    let is_gain_reduction = flags.contains(ParamFlags::GAIN_REDUCTION);
+
+    let mut vst3_flags = 0;
+    if automatable && !hidden && !is_gain_reduction {
+        vst3_flags |= ParameterFlags::kCanAutomate as i32;
+    }
+    if hidden {
+        vst3_flags |= ParameterFlags::kIsReadOnly as i32 | VST3_PARAM_IS_HIDDEN;
+    }
+    if is_gain_reduction {
+        vst3_flags |= ParameterFlags::kIsReadOnly as i32;
+    }
+    if is_bypass {
+        vst3_flags |= ParameterFlags::kIsBypass as i32;
+    }
+
+    vst3_flags
+}
+
+/// Convert a VST3 `ProcessModes` value as found in `ProcessSetup` and `ProcessData` to a
+/// [`ProcessMode`]. Unknown modes are treated as realtime.
+pub fn process_mode_from_vst3(mode: i32) -> ProcessMode {
+    match mode {
+        n if n == ProcessModes::kRealtime as i32 => ProcessMode::Realtime,
+        n if n == ProcessModes::kPrefetch as i32 => ProcessMode::Buffered,
+        n if n == ProcessModes::kOffline as i32 => ProcessMode::Offline,
+        n => {
+            nih_debug_assert_failure!("Unknown rendering mode '{}', defaulting to realtime", n);
+            ProcessMode::Realtime
+        }
+    }
+}
+
+/// Clear all of the host's output buffers and mark them as silent. Used when the plugin cannot
+/// process audio in a process call.
+///
+/// # Safety
+///
+/// The pointers in `data` need to be valid for `data.num_samples` samples.
+pub unsafe fn output_silence(data: &ProcessData) {
+    if data.outputs.is_null() || data.num_samples <= 0 {
+        return;
+    }
+
+    for output_idx in 0..data.num_outputs.max(0) as usize {
+        let audio_output = &mut *data.outputs.add(output_idx);
+        if audio_output.buffers.is_null() {
+            continue;
+        }
+
+        let num_channels = audio_output.num_channels.max(0) as usize;
+        for channel_idx in 0..num_channels {
+            let channel = *(audio_output.buffers as *mut *mut f32).add(channel_idx);
+            if !channel.is_null() {
+                std::ptr::write_bytes(channel, 0, data.num_samples as usize);
+            }
+        }
+        audio_output.silence_flags = if num_channels >= 64 {
+            u64::MAX
+        } else {
+            (1 << num_channels) - 1
+        };
+    }
+}
+
+/// Convert a [`ChannelLayout`] to a VST3 speaker arrangement. The speaker bits are defined in the
+/// VST3 SDK's `vstspeaker.h`, which vst3-sys doesn't expose. In 7.x layouts VST3 uses its `Ls` and
+/// `Rs` speakers for the rear surround channels and `Sl` and `Sr` for the side surround channels,
+/// which is consistent with NIH-plug's [`Speaker`] definitions.
+pub fn vst3_speaker_arrangement(layout: ChannelLayout) -> SpeakerArrangement {
+    if layout == ChannelLayout::Mono {
+        return vst3_sys::vst::kMono;
+    }
+
+    layout.speakers().iter().fold(0, |arrangement, speaker| {
+        let bit = match speaker {
+            Speaker::FrontLeft => 0,
+            Speaker::FrontRight => 1,
+            Speaker::FrontCenter => 2,
+            Speaker::Lfe => 3,
+            Speaker::BackLeft => 4,
+            Speaker::BackRight => 5,
+            Speaker::SideLeft => 9,
+            Speaker::SideRight => 10,
+            Speaker::TopFrontLeft => 12,
+            Speaker::TopFrontRight => 14,
+            Speaker::TopBackLeft => 15,
+            Speaker::TopBackRight => 17,
+        };
+
+        arrangement | (1 << bit)
+    })
+}
+
+/// Whether a speaker arrangement requested by the host can be used for a port with `num_channels`
+/// channels. If the plugin declared a channel layout for the port, then the arrangement needs to
+/// match that layout exactly. Otherwise only the channel count is compared.
+pub fn speaker_arrangement_matches(
+    arrangement: SpeakerArrangement,
+    num_channels: u32,
+    declared_layout: Option<ChannelLayout>,
+) -> bool {
+    match declared_layout {
+        Some(layout) => arrangement == vst3_speaker_arrangement(layout),
+        None => arrangement.count_ones() == num_channels,
+    }
+}
+
+#[cfg(test)]
+mod parameter_flags {
+    use super::*;
+
+    #[test]
+    fn non_automatable() {
+        assert_eq!(
+            to_vst3_parameter_flags(ParamFlags::empty()),
+            ParameterFlags::kCanAutomate as i32
+        );
+        assert_eq!(to_vst3_parameter_flags(ParamFlags::NON_AUTOMATABLE), 0);
+        assert_eq!(
+            to_vst3_parameter_flags(ParamFlags::NON_AUTOMATABLE | ParamFlags::BYPASS),
+            ParameterFlags::kIsBypass as i32
+        );
+        assert_eq!(
+            to_vst3_parameter_flags(ParamFlags::HIDDEN),
+            ParameterFlags::kIsReadOnly as i32 | VST3_PARAM_IS_HIDDEN
+        );
+        assert_eq!(
+            to_vst3_parameter_flags(ParamFlags::GAIN_REDUCTION),
+            ParameterFlags::kIsReadOnly as i32
+        );
+    }
+}
+
+#[cfg(test)]
+mod speaker_arrangements {
+    use super::*;
+
+    #[test]
+    fn surround_layouts() {
+        assert_eq!(
+            vst3_speaker_arrangement(ChannelLayout::Mono),
+            vst3_sys::vst::kMono
+        );
+        assert_eq!(
+            vst3_speaker_arrangement(ChannelLayout::Stereo),
+            vst3_sys::vst::kStereo
+        );
+        assert_eq!(
+            vst3_speaker_arrangement(ChannelLayout::Surround51),
+            vst3_sys::vst::k51
+        );
+        // 7.1 is VST3's `k71Music` arrangement, not the SDDS `k71Cine` arrangement
+        assert_eq!(vst3_speaker_arrangement(ChannelLayout::Surround71), 0x63f);
+        assert_eq!(
+            vst3_speaker_arrangement(ChannelLayout::Surround714),
+            0x2d63f
+        );
+
+        for layout in ChannelLayout::ALL {
+            let arrangement = vst3_speaker_arrangement(layout);
+            assert_eq!(arrangement.count_ones(), layout.num_channels());
+            assert!(speaker_arrangement_matches(
+                arrangement,
+                layout.num_channels(),
+                Some(layout)
+            ));
+        }
+    }
+
+    #[test]
+    fn arrangement_matching() {
+        // Undeclared layouts only compare the channel counts
+        assert!(speaker_arrangement_matches(vst3_sys::vst::k51, 6, None));
+        assert!(!speaker_arrangement_matches(vst3_sys::vst::k51, 8, None));
+
+        assert!(!speaker_arrangement_matches(
+            vst3_sys::vst::k71Cine,
+            8,
+            Some(ChannelLayout::Surround71)
+        ));
+    }
+}
+
 #[cfg(test)]
 mod miri {
     use widestring::U16CStr;
@@ -1,6 +1,5 @@
 use atomic_float::AtomicF32;
 use parking_lot::{Mutex, RwLock};
-use std::any::Any;
 use std::ffi::{c_void, CStr};
 use std::mem;
 use std::sync::atomic::Ordering;
@@ -14,6 +13,7 @@ use super::inner::{Task, WrapperInner};
 use super::util::{ObjectPtr, VstPtr};
 use crate::plugin::vst3::Vst3Plugin;
 use crate::prelude::{Editor, ParentWindowHandle};
+use crate::wrapper::util::EditorHandle;
 
 // Alias needed for the VST3 attribute macro
 use vst3_sys as vst3_com;
@@ -53,7 +53,7 @@ struct RunLoopEventHandlerWrapper<P: Vst3Plugin>(std::marker::PhantomData<P>);
 pub(crate) struct WrapperView<P: Vst3Plugin> {
     inner: Arc<WrapperInner<P>>,
     editor: Arc<Mutex<Box<dyn Editor>>>,
-    editor_handle: RwLock<Option<Box<dyn Any>>>,
+    editor_handle: RwLock<Option<EditorHandle>>,
 
     /// The `IPlugFrame` instance passed by the host during [IPlugView::set_frame()].
     plug_frame: RwLock<Option<VstPtr<dyn IPlugFrame>>>,
@@ -299,12 +299,13 @@ impl<P: Vst3Plugin> IPlugView for WrapperView<P> {
                 }
             };
 
-            *editor_handle = Some(
-                self.editor
-                    .lock()
-                    .spawn(parent_handle, self.inner.clone().make_gui_context()),
-            );
+            let editor = self.editor.lock();
+            *editor_handle = Some(EditorHandle::new(
+                editor.spawn(parent_handle, self.inner.clone().make_gui_context()),
+                self.inner.editor_open.clone(),
+            ));
             *self.inner.plug_view.write() = Some(ObjectPtr::from(self));
+            editor.on_open();
 
             kResultOk
         } else {
@@ -321,6 +322,7 @@ impl<P: Vst3Plugin> IPlugView for WrapperView<P> {
         if editor_handle.is_some() {
             *self.inner.plug_view.write() = None;
             *editor_handle = None;
+            self.editor.lock().on_close();
 
             kResultOk
         } else {
@@ -5,35 +5,46 @@ use std::num::NonZeroU32;
 use std::ptr::NonNull;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use vst3_com::vst::{DataEvent, IProcessContextRequirementsFlags, ProcessModes};
+use vst3_com::vst::{DataEvent, IProcessContextRequirementsFlags};
 use vst3_sys::base::{kInvalidArgument, kNoInterface, kResultFalse, kResultOk, tresult, TBool};
 use vst3_sys::base::{IBStream, IPluginBase};
+use vst3_sys::interfaces::IUnknown;
 use vst3_sys::utils::SharedVstPtr;
 use vst3_sys::vst::{
     kNoParamId, kNoParentUnitId, kNoProgramListId, kRootUnitId, Event, EventTypes, IAudioProcessor,
-    IComponent, IEditController, IEventList, IMidiMapping, INoteExpressionController,
-    IParamValueQueue, IParameterChanges, IProcessContextRequirements, IUnitInfo,
-    LegacyMidiCCOutEvent, NoteExpressionTypeInfo, NoteExpressionValueDescription, NoteOffEvent,
-    NoteOnEvent, ParameterFlags, PolyPressureEvent, ProgramListInfo, TChar, UnitInfo,
+    IComponent, IEditController, IEventList, IHostApplication, IMidiMapping,
+    INoteExpressionController, IParamValueQueue, IParameterChanges, IProcessContextRequirements,
+    IUnitInfo, LegacyMidiCCOutEvent, NoteExpressionTypeInfo, NoteExpressionValueDescription,
+    NoteOffEvent, NoteOnEvent, ParameterFlags, PolyPressureEvent, ProgramListInfo, TChar, UnitInfo,
 };
 use vst3_sys::VST3;
 use widestring::U16CStr;
 
-use super::inner::{ProcessEvent, WrapperInner};
+use super::inner::{ProcessEvent, Task, WrapperInner};
 use super::note_expressions::{self, NoteExpressionController};
 use super::util::{
-    u16strlcpy, VstPtr, VST3_MIDI_CCS, VST3_MIDI_NUM_PARAMS, VST3_MIDI_PARAMS_START,
+    output_silence, process_mode_from_vst3, speaker_arrangement_matches, to_vst3_parameter_flags,
+    u16strlcpy, vst3_speaker_arrangement, VstPtr, VST3_MIDI_CCS, VST3_MIDI_NUM_PARAMS,
+    VST3_MIDI_PARAMS_START, VST3_PARAM_IS_HIDDEN,
 };
 use super::util::{VST3_MIDI_CHANNELS, VST3_MIDI_PARAMS_END};
 use super::view::WrapperView;
+use crate::midi::{ALL_NOTES_OFF_CC, ALL_SOUND_OFF_CC};
 use crate::prelude::{
-    AuxiliaryBuffers, BufferConfig, MidiConfig, NoteEvent, ParamFlags, ProcessMode, ProcessStatus,
-    SysExMessage, Transport, Vst3Plugin,
+    AuxiliaryBuffers, BufferConfig, HostInfo, MidiConfig, NoteEvent, ProcessStatus, SysExMessage,
+    Transport, Vst3Plugin,
 };
 use crate::util::permit_alloc;
 use crate::wrapper::state;
 use crate::wrapper::util::buffer_management::{BufferManager, ChannelPointers};
-use crate::wrapper::util::{clamp_input_event_timing, clamp_output_event_timing, process_wrapper};
+use crate::wrapper::util::fixed_block::{
+    fixed_block_latency, plugin_buffer_config, FixedBlockBuffers,
+};
+use crate::wrapper::util::transport_jumps::TransportJumpDetector;
+use crate::wrapper::util::{
+    clamp_input_event_timing, clamp_output_event_timing, hash_param_id, max_block_end,
+    process_wrapper, tail_length_samples,
+};
 
 // Alias needed for the VST3 attribute macro
 use vst3_sys as vst3_com;
@@ -55,6 +66,94 @@ impl<P: Vst3Plugin> Wrapper<P> {
     pub fn new() -> Box<Self> {
         Self::allocate(WrapperInner::new())
     }
+
+    /// Initialize the plugin with the current audio IO layout and the given buffer config, and
+    /// allocate the buffers needed for processing. Returns `false` if the plugin failed to
+    /// initialize.
+    fn initialize_plugin(&self, buffer_config: BufferConfig) -> bool {
+        self.inner
+            .needs_reinitialization
+            .store(false, Ordering::SeqCst);
+
+        // Before initializing the plugin, make sure all smoothers are set the the default values
+        for param in self.inner.param_by_hash.values() {
+            param.update_smoother(buffer_config.sample_rate, true);
+        }
+
+        // NOTE: This needs to be dropped after the `plugin` lock to avoid deadlocks
+        let mut init_context = self.inner.make_init_context();
+        let audio_io_layout = self.inner.current_audio_io_layout.load();
+        let mut plugin = self.inner.plugin.lock();
+        let initialized = plugin.initialize(&audio_io_layout, &buffer_config, &mut init_context);
+        if initialized {
+            // This preallocates enough space so we can transform all of the host's raw channel
+            // pointers into a set of `Buffer` objects for the plugin's main and auxiliary IO
+            *self.inner.buffer_manager.borrow_mut() = BufferManager::for_audio_io_layout(
+                buffer_config.max_buffer_size as usize,
+                audio_io_layout,
+            );
+            *self.inner.fixed_block_buffers.borrow_mut() = P::FIXED_BLOCK_SIZE.map(|block_size| {
+                FixedBlockBuffers::new(block_size.get() as usize, audio_io_layout)
+            });
+
+            let old_buffer_config = self
+                .inner
+                .initialized_buffer_config
+                .swap(Some(buffer_config));
+            if old_buffer_config != Some(buffer_config) {
+                let task_posted = self
+                    .inner
+                    .schedule_gui(Task::BufferConfigChanged(buffer_config));
+                nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+            }
+        }
+
+        self.inner.is_active.store(initialized, Ordering::SeqCst);
+        initialized
+    }
+
+    /// Called when the host changes the bus arrangement or the processing setup. VST3 only allows
+    /// this while the plugin is deactivated, but not all hosts adhere to that. If the plugin is
+    /// already active, then it's reinitialized with the new configuration so `Plugin::process()`
+    /// never runs with buffers sized for the old configuration. If the plugin is currently
+    /// processing audio, then this happens when the host resumes processing. Initializing the plugin
+    /// may allocate, so this can't happen on the audio thread. Until then the plugin outputs
+    /// silence.
+    fn configuration_changed(&self) {
+        if !self.inner.is_active.load(Ordering::SeqCst) {
+            return;
+        }
+
+        if self.inner.is_processing.load(Ordering::SeqCst) {
+            nih_warn!(
+                "The host changed the audio configuration while the plugin was processing audio, \
+                 the plugin will output silence until processing is restarted"
+            );
+            self.inner
+                .needs_reinitialization
+                .store(true, Ordering::SeqCst);
+        } else if let Some(buffer_config) = self.inner.current_buffer_config.load() {
+            self.initialize_plugin(buffer_config);
+        }
+    }
+
+    /// Reinitialize the plugin if [`configuration_changed()`][Self::configuration_changed()]
+    /// deferred that because the plugin was processing audio. Returns `None` if the plugin did not
+    /// need to be reinitialized, and otherwise whether the plugin was reinitialized successfully.
+    fn reinitialize_if_needed(&self) -> Option<bool> {
+        if !self
+            .inner
+            .needs_reinitialization
+            .swap(false, Ordering::SeqCst)
+        {
+            return None;
+        }
+
+        self.inner
+            .current_buffer_config
+            .load()
+            .map(|buffer_config| self.initialize_plugin(buffer_config))
+    }
 }
 
 impl<P: Vst3Plugin> Drop for Wrapper<P> {
@@ -64,8 +163,32 @@ impl<P: Vst3Plugin> Drop for Wrapper<P> {
 }
 
 impl<P: Vst3Plugin> IPluginBase for Wrapper<P> {
-    unsafe fn initialize(&self, _context: *mut c_void) -> tresult {
-        // We currently don't need or allow any initialization logic
+    unsafe fn initialize(&self, context: *mut c_void) -> tresult {
+        // The context is the host's `IHostApplication` instance, which we only use to get the
+        // host's name. The correct argument type is missing from the bindings.
+        let context: SharedVstPtr<dyn IUnknown> = mem::transmute(context);
+        *self.inner.host_info.borrow_mut() = context
+            .upgrade()
+            .and_then(|context| context.cast::<dyn IHostApplication>())
+            .and_then(|host_application| {
+                let mut name: [TChar; 128] = [0; 128];
+                if host_application.get_name(&mut name) != kResultOk {
+                    return None;
+                }
+
+                let name = U16CStr::from_ptr_truncate(name.as_ptr() as *const u16, name.len())
+                    .ok()?
+                    .to_string_lossy();
+                if name.is_empty() {
+                    None
+                } else {
+                    Some(HostInfo {
+                        name,
+                        version: None,
+                    })
+                }
+            });
+
         kResultOk
     }
 
@@ -373,28 +496,11 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
         // custom channel layout overrides we need to initialize here.
         match (state != 0, self.inner.current_buffer_config.load()) {
             (true, Some(buffer_config)) => {
-                // Before initializing the plugin, make sure all smoothers are set the the default values
-                for param in self.inner.param_by_hash.values() {
-                    param.update_smoother(buffer_config.sample_rate, true);
-                }
-
-                // NOTE: This needs to be dropped after the `plugin` lock to avoid deadlocks
-                let mut init_context = self.inner.make_init_context();
-                let audio_io_layout = self.inner.current_audio_io_layout.load();
-                let mut plugin = self.inner.plugin.lock();
-                if plugin.initialize(&audio_io_layout, &buffer_config, &mut init_context) {
-                    // NOTE: We don't call `Plugin::reset()` here. The call is done in `set_process()`
-                    //       instead. Otherwise we would call the function twice, and `set_process()` needs
-                    //       to be called after this function before the plugin may process audio again.
-
-                    // This preallocates enough space so we can transform all of the host's raw
-                    // channel pointers into a set of `Buffer` objects for the plugin's main and
-                    // auxiliary IO
-                    *self.inner.buffer_manager.borrow_mut() = BufferManager::for_audio_io_layout(
-                        buffer_config.max_buffer_size as usize,
-                        audio_io_layout,
-                    );
-
+                // NOTE: We don't call `Plugin::reset()` here. The call is done in `set_process()`
+                //       instead. Otherwise we would call the function twice, and `set_process()`
+                //       needs to be called after this function before the plugin may process
+                //       audio again.
+                if self.initialize_plugin(buffer_config) {
                     kResultOk
                 } else {
                     kResultFalse
@@ -402,6 +508,10 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
             }
             (true, None) => kResultFalse,
             (false, _) => {
+                self.inner.is_active.store(false, Ordering::SeqCst);
+                self.inner
+                    .needs_reinitialization
+                    .store(false, Ordering::SeqCst);
                 self.inner.plugin.lock().deactivate();
 
                 kResultOk
@@ -555,7 +665,7 @@ impl<P: Vst3Plugin> IEditController for Wrapper<P> {
             info.id = VST3_MIDI_PARAMS_START + midi_param_relative_idx;
             u16strlcpy(&mut info.title, &name);
             u16strlcpy(&mut info.short_title, &name);
-            info.flags = ParameterFlags::kIsReadOnly as i32 | (1 << 4); // kIsHidden
+            info.flags = ParameterFlags::kIsReadOnly as i32 | VST3_PARAM_IS_HIDDEN;
         } else {
             let param_hash = &self.inner.param_hashes[param_index as usize];
             let param_unit = &self
@@ -565,11 +675,6 @@ impl<P: Vst3Plugin> IEditController for Wrapper<P> {
                 .expect("Inconsistent parameter data");
             let param_ptr = &self.inner.param_by_hash[param_hash];
             let default_value = param_ptr.default_normalized_value();
-            let flags = param_ptr.flags();
-            let automatable = !flags.contains(ParamFlags::NON_AUTOMATABLE);
-            let hidden = flags.contains(ParamFlags::HIDDEN);
-            let is_bypass = flags.contains(ParamFlags::BYPASS);
-
             info.id = *param_hash;
             u16strlcpy(&mut info.title, param_ptr.name());
             u16strlcpy(&mut info.short_title, param_ptr.name());
@@ -577,16 +682,7 @@ impl<P: Vst3Plugin> IEditController for Wrapper<P> {
             info.step_count = param_ptr.step_count().unwrap_or(0) as i32;
             info.default_normalized_value = default_value as f64;
             info.unit_id = *param_unit;
-            info.flags = 0;
-            if automatable && !hidden {
-                info.flags |= ParameterFlags::kCanAutomate as i32;
-            }
-            if hidden {
-                info.flags |= ParameterFlags::kIsReadOnly as i32 | (1 << 4); // kIsHidden
-            }
-            if is_bypass {
-                info.flags |= ParameterFlags::kIsBypass as i32;
-            }
+            info.flags = to_vst3_parameter_flags(param_ptr.flags());
         }
 
         kResultOk
@@ -738,12 +834,16 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                     return false;
                 }
 
-                // NOTE: We completely ignore the speaker arrangements and only look at the channel
-                //       counts here. This may cause issues at some point, but it works for now.
+                // The speaker arrangements are only compared for main ports with a declared channel
+                // layout. Other ports only need to have the right number of channels.
                 let has_main_input = layout.main_input_channels.is_some();
-                let aux_input_start_idx = if has_main_input { 0 } else { 1 };
+                let aux_input_start_idx = if has_main_input { 1 } else { 0 };
                 if has_main_input
-                    && (*inputs).count_ones() != layout.main_input_channels.unwrap().get()
+                    && !speaker_arrangement_matches(
+                        *inputs,
+                        layout.main_input_channels.unwrap().get(),
+                        layout.main_input_channel_layout,
+                    )
                 {
                     return false;
                 }
@@ -756,12 +856,13 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                 }
 
                 let has_main_output = layout.main_output_channels.is_some();
-                let aux_output_start_idx = if has_main_output { 0 } else { 1 };
-                if (*outputs).count_ones()
-                    != layout
-                        .main_output_channels
-                        .map(NonZeroU32::get)
-                        .unwrap_or_default()
+                let aux_output_start_idx = if has_main_output { 1 } else { 0 };
+                if has_main_output
+                    && !speaker_arrangement_matches(
+                        *outputs,
+                        layout.main_output_channels.unwrap().get(),
+                        layout.main_output_channel_layout,
+                    )
                 {
                     return false;
                 }
@@ -781,7 +882,10 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
             Some(layout) => {
                 // This layout is used from hereon onwards, at least until this function is called
                 // again
-                self.inner.current_audio_io_layout.store(layout);
+                let old_layout = self.inner.current_audio_io_layout.swap(layout);
+                if layout != old_layout {
+                    self.configuration_changed();
+                }
 
                 kResultOk
             }
@@ -814,15 +918,22 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
             }
         };
 
+        // The main ports use their channel layouts if they have one
         let current_audio_io_layout = self.inner.current_audio_io_layout.load();
-        let num_channels = if dir == vst3_sys::vst::BusDirections::kInput as i32 {
+        let (num_channels, channel_layout) = if dir == vst3_sys::vst::BusDirections::kInput as i32 {
             let has_main_input = current_audio_io_layout.main_input_channels.is_some();
             let aux_input_start_idx = if has_main_input { 1 } else { 0 };
             let aux_input_idx = (index - aux_input_start_idx).max(0) as usize;
             if index == 0 && has_main_input {
-                current_audio_io_layout.main_input_channels.unwrap().get()
+                (
+                    current_audio_io_layout.main_input_channels.unwrap().get(),
+                    current_audio_io_layout.main_input_layout(),
+                )
             } else if aux_input_idx < current_audio_io_layout.aux_input_ports.len() {
-                current_audio_io_layout.aux_input_ports[aux_input_idx].get()
+                (
+                    current_audio_io_layout.aux_input_ports[aux_input_idx].get(),
+                    None,
+                )
             } else {
                 return kInvalidArgument;
             }
@@ -831,16 +942,25 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
             let aux_output_start_idx = if has_main_output { 1 } else { 0 };
             let aux_output_idx = (index - aux_output_start_idx).max(0) as usize;
             if index == 0 && has_main_output {
-                current_audio_io_layout.main_output_channels.unwrap().get()
+                (
+                    current_audio_io_layout.main_output_channels.unwrap().get(),
+                    current_audio_io_layout.main_output_layout(),
+                )
             } else if aux_output_idx < current_audio_io_layout.aux_output_ports.len() {
-                current_audio_io_layout.aux_output_ports[aux_output_idx].get()
+                (
+                    current_audio_io_layout.aux_output_ports[aux_output_idx].get(),
+                    None,
+                )
             } else {
                 return kInvalidArgument;
             }
         } else {
             return kInvalidArgument;
         };
-        let channel_map = channel_count_to_map(num_channels);
+        let channel_map = match channel_layout {
+            Some(channel_layout) => vst3_speaker_arrangement(channel_layout),
+            None => channel_count_to_map(num_channels),
+        };
 
         nih_debug_assert_eq!(num_channels, channel_map.count_ones());
         *arr = channel_map;
@@ -857,7 +977,7 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
     }
 
     unsafe fn get_latency_samples(&self) -> u32 {
-        self.inner.current_latency.load(Ordering::SeqCst)
+        self.inner.current_latency.load(Ordering::SeqCst) + fixed_block_latency::<P>()
     }
 
     unsafe fn setup_processing(&self, setup: *const vst3_sys::vst::ProcessSetup) -> tresult {
@@ -870,27 +990,25 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
             vst3_sys::vst::SymbolicSampleSizes::kSample32 as i32
         );
 
-        // This is needed when activating the plugin and when restoring state
-        self.inner.current_buffer_config.store(Some(BufferConfig {
+        let mode = process_mode_from_vst3(setup.process_mode);
+        self.inner.current_process_mode.store(mode);
+
+        // This is needed when activating the plugin and when restoring state. If the plugin uses a
+        // fixed block size, then it only ever sees blocks of that size.
+        let buffer_config = plugin_buffer_config::<P>(BufferConfig {
             sample_rate: setup.sample_rate as f32,
             min_buffer_size: None,
             max_buffer_size: setup.max_samples_per_block as u32,
-            process_mode: self.inner.current_process_mode.load(),
-        }));
-
-        let mode = match setup.process_mode {
-            n if n == ProcessModes::kRealtime as i32 => ProcessMode::Realtime,
-            n if n == ProcessModes::kPrefetch as i32 => ProcessMode::Buffered,
-            n if n == ProcessModes::kOffline as i32 => ProcessMode::Offline,
-            n => {
-                nih_debug_assert_failure!("Unknown rendering mode '{}', defaulting to realtime", n);
-                ProcessMode::Realtime
-            }
-        };
-        self.inner.current_process_mode.store(mode);
+            process_mode: mode,
+        });
+        let old_buffer_config = self.inner.current_buffer_config.swap(Some(buffer_config));
 
         // Initializing the plugin happens in `IAudioProcessor::set_active()` because the host may
-        // still change the channel layouts at this point
+        // still change the channel layouts at this point. If the plugin is already active, then it
+        // needs to be reinitialized with the new sample rate and maximum buffer size.
+        if old_buffer_config != Some(buffer_config) {
+            self.configuration_changed();
+        }
 
         kResultOk
     }
@@ -902,9 +1020,21 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
         self.inner.last_process_status.store(ProcessStatus::Normal);
         self.inner.is_processing.store(state, Ordering::SeqCst);
 
+        // If the host changed the configuration while the plugin was processing audio, then the
+        // plugin needs to be reinitialized before it can process audio again
+        if state && self.reinitialize_if_needed() == Some(false) {
+            return kResultFalse;
+        }
+
         // This function is also used to reset buffers on the plugin, so we should do the same
         // thing. We don't call `reset()` in `setup_processing()` for that same reason.
         if state {
+            if let Some(fixed_block_buffers) = self.inner.fixed_block_buffers.borrow_mut().as_mut()
+            {
+                fixed_block_buffers.reset();
+            }
+            *self.inner.transport_jump_detector.borrow_mut() = TransportJumpDetector::default();
+
             // HACK: See the comment in `IComponent::setActive()`. This is needed to work around
             //       Ardour bugs.
             let mut plugin = match self.inner.plugin.try_lock() {
@@ -933,6 +1063,19 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
     unsafe fn process(&self, data: *mut vst3_sys::vst::ProcessData) -> tresult {
         check_null_ptr!(data);
 
+        // Not all hosts pause processing before changing the bus arrangement or the processing
+        // setup. The plugin still has buffers sized for the old configuration at that point, and
+        // reinitializing it here would allocate on the audio thread. So the plugin stays silent
+        // until the host restarts processing and it can be reinitialized in `setProcessing()`.
+        if self.inner.needs_reinitialization.load(Ordering::SeqCst) {
+            output_silence(&*data);
+            return kResultOk;
+        }
+
+        self.inner
+            .automation_recorder
+            .begin_block((*data).num_samples.max(0) as usize);
+
         // Panic on allocations if the `assert_process_allocs` feature has been enabled, and make
         // sure that FTZ is set up correctly
         process_wrapper(|| {
@@ -944,6 +1087,10 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                 .load()
                 .expect("Process call without prior setup call")
                 .sample_rate;
+            let _process_load_measurement = self
+                .inner
+                .process_load
+                .start_measurement(data.num_samples.max(0) as usize, sample_rate);
 
             nih_debug_assert!(data.num_inputs >= 0 && data.num_outputs >= 0);
             nih_debug_assert_eq!(
@@ -952,6 +1099,12 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
             );
             nih_debug_assert!(data.num_samples >= 0);
 
+            // Hosts may switch between realtime and offline processing without going through
+            // `IAudioProcessor::setupProcessing()` again, so this is exposed on the process context
+            self.inner
+                .current_process_mode
+                .store(process_mode_from_vst3(data.process_mode));
+
             let total_buffer_len = data.num_samples as usize;
 
             let current_audio_io_layout = self.inner.current_audio_io_layout.load();
@@ -1038,6 +1191,14 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                                             channel: midi_channel,
                                             value,
                                         },
+                                        ALL_SOUND_OFF_CC => NoteEvent::AllSoundOff {
+                                            timing,
+                                            channel: Some(midi_channel),
+                                        },
+                                        ALL_NOTES_OFF_CC => NoteEvent::AllNotesOff {
+                                            timing,
+                                            channel: Some(midi_channel),
+                                        },
                                         n => NoteEvent::MidiCC {
                                             timing,
                                             channel: midi_channel,
@@ -1045,18 +1206,24 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                                             value,
                                         },
                                     }));
-                                } else if P::SAMPLE_ACCURATE_AUTOMATION {
-                                    process_events.push(ProcessEvent::ParameterChange {
-                                        timing,
-                                        hash: param_hash,
-                                        normalized_value: value,
-                                    });
                                 } else {
-                                    self.inner.set_normalized_value_by_hash(
-                                        param_hash,
-                                        value,
-                                        Some(sample_rate),
-                                    );
+                                    self.inner
+                                        .automation_recorder
+                                        .record(timing, param_hash, value);
+
+                                    if P::SAMPLE_ACCURATE_AUTOMATION {
+                                        process_events.push(ProcessEvent::ParameterChange {
+                                            timing,
+                                            hash: param_hash,
+                                            normalized_value: value,
+                                        });
+                                    } else {
+                                        self.inner.set_normalized_value_by_hash(
+                                            param_hash,
+                                            value,
+                                            Some(sample_rate),
+                                        );
+                                    }
                                 }
                             }
                         }
@@ -1170,6 +1337,14 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                 })
             });
 
+            // The buffer is also split if the host sends more samples than the maximum buffer size
+            // it promised, and at the end of every block when the plugin uses a fixed block size
+            let max_buffer_size = self
+                .inner
+                .current_buffer_config
+                .load()
+                .map(|c| c.max_buffer_size)
+                .unwrap_or(data.num_samples as u32);
             let mut block_start = 0usize;
             let mut block_end;
             let mut event_start_idx = 0;
@@ -1185,7 +1360,16 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                     let mut input_events = self.inner.input_events.borrow_mut();
                     input_events.clear();
 
-                    block_end = total_buffer_len;
+                    let mut max_block_end =
+                        max_block_end(block_start, total_buffer_len, max_buffer_size);
+                    if let Some(fixed_block_buffers) =
+                        self.inner.fixed_block_buffers.borrow().as_ref()
+                    {
+                        max_block_end = max_block_end
+                            .min(block_start + fixed_block_buffers.samples_remaining());
+                    }
+                    block_end = max_block_end;
+                    let mut next_event_idx = process_events.len();
                     for event_idx in event_start_idx..process_events.len() {
                         match &process_events[event_idx] {
                             ProcessEvent::ParameterChange {
@@ -1197,8 +1381,8 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                                 // we'll split the block here and handle this parameter change after
                                 // we've processed this block
                                 if *timing != block_start as u32 {
-                                    event_start_idx = event_idx;
-                                    block_end = *timing as usize;
+                                    next_event_idx = event_idx;
+                                    block_end = (*timing as usize).min(max_block_end);
                                     break;
                                 }
 
@@ -1209,6 +1393,14 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                                 );
                             }
                             ProcessEvent::NoteEvent(event) => {
+                                // Events past the maximum block size are handled in the next block
+                                if max_block_end < total_buffer_len
+                                    && event.timing() as usize >= max_block_end
+                                {
+                                    next_event_idx = event_idx;
+                                    break;
+                                }
+
                                 // We need to make sure to compensate the event for any block splitting,
                                 // since we had to create the event object beforehand
                                 let mut event = event.clone();
@@ -1217,6 +1409,7 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                             }
                         }
                     }
+                    event_start_idx = next_event_idx;
                 }
 
                 let result = if is_param_flush {
@@ -1388,22 +1581,98 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                                 Some((context.cycle_start_music, context.cycle_end_music));
                         }
                     }
+                    let mut transport_jump_detector =
+                        self.inner.transport_jump_detector.borrow_mut();
+                    transport_jump_detector.update(&mut transport, block_len);
+                    // Synthesizers should not keep playing notes after the host's transport stops
+                    if P::MIDI_INPUT >= MidiConfig::Basic
+                        && transport_jump_detector.playback_stopped()
+                    {
+                        self.inner
+                            .input_events
+                            .borrow_mut()
+                            .push_front(NoteEvent::AllNotesOff {
+                                timing: 0,
+                                channel: None,
+                            });
+                    }
+                    drop(transport_jump_detector);
+
+                    let mut fixed_block_buffers = self.inner.fixed_block_buffers.borrow_mut();
+                    let result = match (buffer_is_valid, fixed_block_buffers.as_mut()) {
+                        (true, None) => {
+                            // NOTE: `parking_lot`'s mutexes sometimes allocate because of their use
+                            //       of thread locals
+                            let mut plugin = permit_alloc(|| self.inner.plugin.lock());
+                            let mut aux = AuxiliaryBuffers {
+                                inputs: buffers.aux_inputs,
+                                outputs: buffers.aux_outputs,
+                            };
+                            let mut context = self.inner.make_process_context(transport);
+                            let result =
+                                plugin.process(buffers.main_buffer, &mut aux, &mut context);
+                            self.inner.last_process_status.store(result);
+                            result
+                        }
+                        // With a fixed block size the audio and note events are buffered until a
+                        // full block is available. The plugin's output events are delayed along
+                        // with the audio, and they're moved to `output_events` as the host receives
+                        // that audio.
+                        (true, Some(fixed_block_buffers)) => {
+                            for event in self.inner.input_events.borrow_mut().drain(..) {
+                                fixed_block_buffers.queue_event(event);
+                            }
 
-                    let result = if buffer_is_valid {
-                        // NOTE: `parking_lot`'s mutexes sometimes allocate because of their use of
-                        //       thread locals
-                        let mut plugin = permit_alloc(|| self.inner.plugin.lock());
-                        let mut aux = AuxiliaryBuffers {
-                            inputs: buffers.aux_inputs,
-                            outputs: buffers.aux_outputs,
-                        };
-                        let mut context = self.inner.make_process_context(transport);
-                        let result = plugin.process(buffers.main_buffer, &mut aux, &mut context);
-                        self.inner.last_process_status.store(result);
-                        result
-                    } else {
-                        ProcessStatus::Normal
+                            fixed_block_buffers
+                                .process(
+                                    buffers.main_buffer,
+                                    buffers.aux_inputs,
+                                    buffers.aux_outputs,
+                                    block_len,
+                                    |event| self.inner.output_events.borrow_mut().push_back(event),
+                                    |block_buffers, block_input_events, block_output_events| {
+                                        // The process context reads and writes the note events
+                                        // from `input_events` and `output_events`
+                                        std::mem::swap(
+                                            &mut *self.inner.input_events.borrow_mut(),
+                                            block_input_events,
+                                        );
+                                        std::mem::swap(
+                                            &mut *self.inner.output_events.borrow_mut(),
+                                            block_output_events,
+                                        );
+
+                                        let mut plugin = permit_alloc(|| self.inner.plugin.lock());
+                                        let mut aux = AuxiliaryBuffers {
+                                            inputs: block_buffers.aux_inputs,
+                                            outputs: block_buffers.aux_outputs,
+                                        };
+                                        let mut context =
+                                            self.inner.make_process_context(transport.clone());
+                                        let result = plugin.process(
+                                            block_buffers.main_buffer,
+                                            &mut aux,
+                                            &mut context,
+                                        );
+                                        drop(context);
+
+                                        std::mem::swap(
+                                            &mut *self.inner.input_events.borrow_mut(),
+                                            block_input_events,
+                                        );
+                                        std::mem::swap(
+                                            &mut *self.inner.output_events.borrow_mut(),
+                                            block_output_events,
+                                        );
+                                        self.inner.last_process_status.store(result);
+                                        result
+                                    },
+                                )
+                                .unwrap_or_else(|| self.inner.last_process_status.load())
+                        }
+                        (false, _) => ProcessStatus::Normal,
                     };
+                    drop(fixed_block_buffers);
 
                     match result {
                         ProcessStatus::Error(err) => {
@@ -1415,6 +1684,59 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                     }
                 };
 
+                // Gestures started by the editor that haven't seen any changes in a while are
+                // ended here so the host doesn't end up with stuck automation lanes
+                self.inner.gesture_watchdog.tick(
+                    (block_end - block_start) as u32,
+                    sample_rate,
+                    |param_hash| {
+                        permit_alloc(|| {
+                            nih_warn!(
+                                "The editor never ended the gesture for parameter '{}', ending it \
+                                 now",
+                                self.inner
+                                    .param_id_by_hash
+                                    .get(&param_hash)
+                                    .map(String::as_str)
+                                    .unwrap_or("?")
+                            )
+                        });
+
+                        let task_posted =
+                            self.inner.schedule_gui(Task::EndParamGesture(param_hash));
+                        nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+                    },
+                );
+
+                // vst3-sys doesn't expose `IParameterFunctionName`, so the gain reduction is only
+                // sent to the host as a regular read-only output parameter
+                if let Some(update) = self
+                    .inner
+                    .gain_reduction_meter
+                    .tick((block_end - block_start) as u32, sample_rate)
+                {
+                    self.inner.set_normalized_value_by_hash(
+                        update.param_hash,
+                        update.normalized_value,
+                        Some(sample_rate),
+                    );
+
+                    if let Some(param_changes) = data.output_param_changes.upgrade() {
+                        let mut queue_idx = 0;
+                        if let Some(param_change_queue) = param_changes
+                            .add_parameter_data(&update.param_hash, &mut queue_idx)
+                            .upgrade()
+                        {
+                            let mut point_idx = 0;
+                            param_change_queue.add_point(
+                                block_start as i32,
+                                update.normalized_value as f64,
+                                &mut point_idx,
+                            );
+                        }
+                    }
+                }
+
                 // Send any events output by the plugin during the process cycle
                 if let Some(events) = data.output_events.upgrade() {
                     let mut output_events = self.inner.output_events.borrow_mut();
@@ -1429,6 +1751,21 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                             total_buffer_len as u32,
                         ) as i32;
 
+                        // VST3 has no raw MIDI output, so raw MIDI messages are parsed and sent
+                        // using the equivalent VST3 events. Messages without one, like system
+                        // real-time messages, are dropped.
+                        let event = match event {
+                            NoteEvent::MidiRaw { timing, data }
+                                if P::MIDI_OUTPUT >= MidiConfig::MidiCCs =>
+                            {
+                                match NoteEvent::from_midi(timing, &data) {
+                                    Ok(event) => event,
+                                    Err(_) => continue,
+                                }
+                            }
+                            event => event,
+                        };
+
                         // `voice_id.unwrap_or(|| ...)` triggers
                         // https://github.com/rust-lang/rust-clippy/issues/8522
                         #[allow(clippy::unnecessary_lazy_evaluations)]
@@ -1589,6 +1926,33 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                                     value2: 0,
                                 };
                             }
+                            NoteEvent::AllNotesOff {
+                                timing: _,
+                                channel: Some(channel),
+                            } if P::MIDI_OUTPUT >= MidiConfig::Basic => {
+                                vst3_event.type_ = EventTypes::kLegacyMIDICCOutEvent as u16;
+                                vst3_event.event.legacy_midi_cc_out = LegacyMidiCCOutEvent {
+                                    control_number: ALL_NOTES_OFF_CC,
+                                    channel: channel as i8,
+                                    value: 0,
+                                    value2: 0,
+                                };
+                            }
+                            NoteEvent::AllSoundOff {
+                                timing: _,
+                                channel: Some(channel),
+                            } if P::MIDI_OUTPUT >= MidiConfig::Basic => {
+                                vst3_event.type_ = EventTypes::kLegacyMIDICCOutEvent as u16;
+                                vst3_event.event.legacy_midi_cc_out = LegacyMidiCCOutEvent {
+                                    control_number: ALL_SOUND_OFF_CC,
+                                    channel: channel as i8,
+                                    value: 0,
+                                    value2: 0,
+                                };
+                            }
+                            // VST3 has no way to target all channels at once
+                            NoteEvent::AllNotesOff { channel: None, .. }
+                            | NoteEvent::AllSoundOff { channel: None, .. } => continue,
                             NoteEvent::MidiProgramChange {
                                 timing: _,
                                 channel,
@@ -1672,11 +2036,14 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
 
     unsafe fn get_tail_samples(&self) -> u32 {
         // https://github.com/steinbergmedia/vst3_pluginterfaces/blob/2ad397ade5b51007860bedb3b01b8afd2c5f6fba/vst/ivstaudioprocessor.h#L145-L159
-        match self.inner.last_process_status.load() {
-            ProcessStatus::Tail(samples) => samples,
-            ProcessStatus::KeepAlive => u32::MAX, // kInfiniteTail
-            _ => 0,                               // kNoTail
-        }
+        // `INFINITE_TAIL` corresponds to `kInfiniteTail`, and 0 is `kNoTail`
+        let tail = tail_length_samples(
+            self.inner.current_tail.load(),
+            self.inner.last_process_status.load(),
+        );
+        self.inner.reported_tail.store(tail, Ordering::SeqCst);
+
+        tail
     }
 }
 
@@ -1688,6 +2055,32 @@ impl<P: Vst3Plugin> IMidiMapping for Wrapper<P> {
         midi_cc_number: vst3_com::vst::CtrlNumber,
         param_id: *mut vst3_com::vst::ParamID,
     ) -> tresult {
+        check_null_ptr!(param_id);
+
+        // Explicit assignments to the plugin's own parameters take precedence over the virtual
+        // parameters used to receive MIDI CCs as events
+        if bus_index == 0 {
+            let assignment = P::VST3_MIDI_CC_ASSIGNMENTS.iter().find(|assignment| {
+                assignment.cc as i16 == midi_cc_number
+                    && assignment
+                        .channel
+                        .map_or(true, |assigned_channel| assigned_channel as i16 == channel)
+            });
+            if let Some(assignment) = assignment {
+                let param_hash = hash_param_id(assignment.param_id);
+                if self.inner.param_by_hash.contains_key(&param_hash) {
+                    *param_id = param_hash;
+                    return kResultOk;
+                }
+
+                nih_debug_assert_failure!(
+                    "MIDI CC {} is assigned to the unknown parameter '{}'",
+                    assignment.cc,
+                    assignment.param_id
+                );
+            }
+        }
+
         if P::MIDI_INPUT < MidiConfig::MidiCCs
             || bus_index != 0
             || !(0..VST3_MIDI_CHANNELS as i16).contains(&channel)
@@ -1696,8 +2089,6 @@ impl<P: Vst3Plugin> IMidiMapping for Wrapper<P> {
             return kResultFalse;
         }
 
-        check_null_ptr!(param_id);
-
         // We reserve a contiguous parameter range right at the end of the allowed parameter indices
         // for these MIDI CC parameters
         *param_id =
@@ -1893,3 +2284,531 @@ impl<P: Vst3Plugin> IUnitInfo for Wrapper<P> {
         kInvalidArgument
     }
 }
+
+// Tasks scheduled from the GUI thread are executed immediately on Linux and Windows, but on macOS
+// the test thread is not the main thread and the tasks would be run asynchronously
+#[cfg(all(test, not(target_os = "macos")))]
+mod tests {
+    use parking_lot::Mutex;
+    use std::collections::BTreeMap;
+    use vst3_sys::vst::{
+        AudioBusBuffers, IComponentHandler, IComponentHandler2, ProcessData, ProcessModes,
+        ProcessSetup, RestartFlags, SymbolicSampleSizes,
+    };
+
+    use super::*;
+    use crate::prelude::*;
+    use crate::testing::TestPlugin;
+    use crate::wrapper::state::{ParamValue, PluginState};
+
+    /// The calls the wrapper made to the host's component handler.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum HandlerCall {
+        BeginEdit(u32),
+        PerformEdit(u32, f64),
+        EndEdit(u32),
+        RestartComponent(i32),
+        StartGroupEdit,
+        FinishGroupEdit,
+    }
+
+    /// Stands in for the host's side of the edit controller.
+    #[VST3(implements(IComponentHandler, IComponentHandler2))]
+    struct TestComponentHandler {
+        calls: Arc<Mutex<Vec<HandlerCall>>>,
+    }
+
+    impl IComponentHandler for TestComponentHandler {
+        unsafe fn begin_edit(&self, id: u32) -> tresult {
+            self.calls.lock().push(HandlerCall::BeginEdit(id));
+            kResultOk
+        }
+
+        unsafe fn perform_edit(&self, id: u32, value_normalized: f64) -> tresult {
+            self.calls
+                .lock()
+                .push(HandlerCall::PerformEdit(id, value_normalized));
+            kResultOk
+        }
+
+        unsafe fn end_edit(&self, id: u32) -> tresult {
+            self.calls.lock().push(HandlerCall::EndEdit(id));
+            kResultOk
+        }
+
+        unsafe fn restart_component(&self, flags: i32) -> tresult {
+            self.calls.lock().push(HandlerCall::RestartComponent(flags));
+            kResultOk
+        }
+    }
+
+    impl IComponentHandler2 for TestComponentHandler {
+        unsafe fn set_dirty(&self, _state: TBool) -> tresult {
+            kResultOk
+        }
+
+        unsafe fn request_open_editor(&self, _name: vst3_sys::base::FIDString) -> tresult {
+            kResultOk
+        }
+
+        unsafe fn start_group_edit(&self) -> tresult {
+            self.calls.lock().push(HandlerCall::StartGroupEdit);
+            kResultOk
+        }
+
+        unsafe fn finish_group_edit(&self) -> tresult {
+            self.calls.lock().push(HandlerCall::FinishGroupEdit);
+            kResultOk
+        }
+    }
+
+    /// Stands in for the host's `IHostApplication` instance passed to `IPluginBase::initialize()`.
+    #[VST3(implements(IHostApplication))]
+    struct TestHostApplication {}
+
+    impl IHostApplication for TestHostApplication {
+        unsafe fn get_name(&self, name: *mut [TChar; 128]) -> tresult {
+            for (c, name_c) in "Simulated Host"
+                .encode_utf16()
+                .chain([0])
+                .zip((*name).iter_mut())
+            {
+                *name_c = c as TChar;
+            }
+
+            kResultOk
+        }
+
+        unsafe fn create_instance(
+            &self,
+            _cid: *mut vst3_sys::IID,
+            _iid: *mut vst3_sys::IID,
+            _obj: *mut *mut c_void,
+        ) -> tresult {
+            kNoInterface
+        }
+    }
+
+    /// Create a wrapper that's connected to a [`TestComponentHandler`]. Returns the wrapper and the
+    /// calls made to the component handler.
+    fn connected_wrapper() -> (Box<Wrapper<TestPlugin>>, Arc<Mutex<Vec<HandlerCall>>>) {
+        let wrapper = Wrapper::<TestPlugin>::new();
+        let calls = Arc::new(Mutex::new(Vec::new()));
+
+        // The handler is leaked since it's reference counted by the wrapper. See `WrapperView` for
+        // why the `SharedVstPtr` is created this way.
+        let handler = Box::leak(TestComponentHandler::allocate(calls.clone()));
+        let handler: SharedVstPtr<dyn IComponentHandler> =
+            unsafe { mem::transmute(&handler.__icomponenthandlervptr as *const *const _) };
+        assert_eq!(unsafe { wrapper.set_component_handler(handler) }, kResultOk);
+
+        (wrapper, calls)
+    }
+
+    /// Call `IAudioProcessor::setupProcessing()` for realtime processing at the given sample rate
+    /// and maximum buffer size.
+    unsafe fn setup_processing(
+        wrapper: &Wrapper<TestPlugin>,
+        sample_rate: f64,
+        max_buffer_size: i32,
+    ) -> tresult {
+        wrapper.setup_processing(&ProcessSetup {
+            process_mode: ProcessModes::kRealtime as i32,
+            symbolic_sample_size: SymbolicSampleSizes::kSample32 as i32,
+            max_samples_per_block: max_buffer_size,
+            sample_rate,
+        })
+    }
+
+    /// Call `IAudioProcessor::process()` with a main input and output bus that share
+    /// `num_channels` channels of `num_samples` samples, like a host processing in place would.
+    unsafe fn process(
+        wrapper: &Wrapper<TestPlugin>,
+        num_channels: usize,
+        num_samples: usize,
+    ) -> tresult {
+        process_in_mode(wrapper, num_channels, num_samples, ProcessModes::kRealtime)
+    }
+
+    /// The same as [`process()`], but with a specific process mode in the `ProcessData`.
+    unsafe fn process_in_mode(
+        wrapper: &Wrapper<TestPlugin>,
+        num_channels: usize,
+        num_samples: usize,
+        process_mode: ProcessModes,
+    ) -> tresult {
+        let mut samples = vec![vec![0.0f32; num_samples]; num_channels];
+        let mut channel_ptrs: Vec<*mut f32> = samples
+            .iter_mut()
+            .map(|channel| channel.as_mut_ptr())
+            .collect();
+        let mut bus = AudioBusBuffers {
+            num_channels: num_channels as i32,
+            silence_flags: 0,
+            buffers: channel_ptrs.as_mut_ptr() as *mut *mut c_void,
+        };
+
+        // All event and parameter change lists are null pointers
+        let mut data: ProcessData = mem::zeroed();
+        data.process_mode = process_mode as i32;
+        data.symbolic_sample_size = SymbolicSampleSizes::kSample32 as i32;
+        data.num_samples = num_samples as i32;
+        data.num_inputs = 1;
+        data.num_outputs = 1;
+        data.inputs = &mut bus;
+        data.outputs = &mut bus;
+
+        wrapper.process(&mut data)
+    }
+
+    /// Assert that the plugin's value, the edit controller's value, and `expected` all agree.
+    fn assert_in_sync(wrapper: &Wrapper<TestPlugin>, expected: f32) {
+        let hash = hash_param_id("gain");
+        let params = wrapper.inner.plugin.lock().params.clone();
+
+        assert_eq!(params.gain.value(), expected);
+        assert_eq!(
+            unsafe { wrapper.get_param_normalized(hash) },
+            expected as f64
+        );
+    }
+
+    #[test]
+    fn gui_edit_reaches_host_and_plugin() {
+        let (wrapper, calls) = connected_wrapper();
+        let context = wrapper.inner.clone().make_gui_context();
+        let params = wrapper.inner.plugin.lock().params.clone();
+        let hash = hash_param_id("gain");
+
+        let setter = ParamSetter::new(context.as_ref());
+        setter.begin_set_parameter(&params.gain);
+        setter.set_parameter(&params.gain, 0.25);
+        setter.end_set_parameter(&params.gain);
+
+        assert_in_sync(&wrapper, 0.25);
+        assert_eq!(
+            *calls.lock(),
+            [
+                HandlerCall::BeginEdit(hash),
+                HandlerCall::PerformEdit(hash, 0.25),
+                HandlerCall::EndEdit(hash),
+            ]
+        );
+    }
+
+    #[test]
+    fn gui_edit_while_processing() {
+        let (wrapper, calls) = connected_wrapper();
+        let context = wrapper.inner.clone().make_gui_context();
+        let params = wrapper.inner.plugin.lock().params.clone();
+        let hash = hash_param_id("gain");
+
+        // While processing, the value is not changed from the GUI thread. The host instead sends
+        // the edit back to the plugin as a parameter change during the next process call.
+        wrapper.inner.is_processing.store(true, Ordering::SeqCst);
+        let setter = ParamSetter::new(context.as_ref());
+        setter.begin_set_parameter(&params.gain);
+        setter.set_parameter(&params.gain, 0.25);
+        setter.end_set_parameter(&params.gain);
+
+        assert_eq!(params.gain.value(), 0.5);
+        assert!(calls.lock().contains(&HandlerCall::PerformEdit(hash, 0.25)));
+
+        // This is what `IAudioProcessor::process()` does with the host's parameter changes
+        assert_eq!(
+            wrapper
+                .inner
+                .set_normalized_value_by_hash(hash, 0.25, Some(44_100.0)),
+            kResultOk
+        );
+        assert_in_sync(&wrapper, 0.25);
+    }
+
+    #[test]
+    fn batch_edits_are_grouped() {
+        let (wrapper, calls) = connected_wrapper();
+        let context = wrapper.inner.clone().make_gui_context();
+        let params = wrapper.inner.plugin.lock().params.clone();
+        let gain_hash = hash_param_id("gain");
+        let pitch_hash = hash_param_id("pitch");
+
+        ParamSetter::new(context.as_ref()).set_parameters_normalized(
+            &[(params.gain.as_ptr(), 0.25), (params.pitch.as_ptr(), 0.75)],
+            false,
+        );
+
+        assert_in_sync(&wrapper, 0.25);
+        assert_eq!(
+            *calls.lock(),
+            [
+                HandlerCall::StartGroupEdit,
+                HandlerCall::BeginEdit(gain_hash),
+                HandlerCall::PerformEdit(gain_hash, 0.25),
+                HandlerCall::EndEdit(gain_hash),
+                HandlerCall::BeginEdit(pitch_hash),
+                HandlerCall::PerformEdit(pitch_hash, 0.75),
+                HandlerCall::EndEdit(pitch_hash),
+                HandlerCall::FinishGroupEdit,
+            ]
+        );
+    }
+
+    #[test]
+    fn host_automation_updates_plugin() {
+        let (wrapper, calls) = connected_wrapper();
+        let hash = hash_param_id("gain");
+
+        assert_eq!(
+            unsafe { wrapper.set_param_normalized(hash, 0.75) },
+            kResultOk
+        );
+
+        assert_in_sync(&wrapper, 0.75);
+        // Changes made by the host should not be echoed back to the host
+        assert!(calls.lock().is_empty());
+    }
+
+    #[test]
+    fn state_restore_from_gui_notifies_host() {
+        let (wrapper, calls) = connected_wrapper();
+        let context = wrapper.inner.clone().make_gui_context();
+
+        context.set_state(PluginState {
+            version: String::new(),
+            params: BTreeMap::from([(String::from("gain"), ParamValue::F32(0.125))]),
+            fields: BTreeMap::new(),
+        });
+
+        assert_in_sync(&wrapper, 0.125);
+        assert_eq!(
+            *calls.lock(),
+            [HandlerCall::RestartComponent(
+                RestartFlags::kParamValuesChanged as i32
+            )]
+        );
+    }
+
+    #[test]
+    fn state_restore_from_host() {
+        let (wrapper, calls) = connected_wrapper();
+
+        let mut state = PluginState {
+            version: String::new(),
+            params: BTreeMap::from([(String::from("gain"), ParamValue::F32(0.125))]),
+            fields: BTreeMap::new(),
+        };
+        assert!(wrapper.inner.set_state_inner(&mut state));
+
+        assert_in_sync(&wrapper, 0.125);
+        // The host already knows about the new state, so it doesn't need to be notified
+        assert!(calls.lock().is_empty());
+    }
+
+    #[test]
+    fn configuration_change_while_processing() {
+        let (wrapper, _calls) = connected_wrapper();
+        let stereo_config = BufferConfig {
+            sample_rate: 44_100.0,
+            min_buffer_size: None,
+            max_buffer_size: 512,
+            process_mode: ProcessMode::Realtime,
+        };
+        let mono_config = BufferConfig {
+            sample_rate: 48_000.0,
+            max_buffer_size: 1024,
+            ..stereo_config
+        };
+
+        unsafe {
+            assert_eq!(setup_processing(&wrapper, 44_100.0, 512), kResultOk);
+            assert_eq!(wrapper.set_active(1), kResultOk);
+            assert_eq!(wrapper.set_processing(1), kResultOk);
+            assert_eq!(process(&wrapper, 2, 512), kResultOk);
+
+            // Some hosts change the configuration without pausing processing first. The plugin
+            // can't be reinitialized on the audio thread, so it stays silent until processing is
+            // restarted, and it's then reinitialized before it processes the first buffer in the
+            // new configuration.
+            let mut mono_input = vst3_sys::vst::kMono;
+            let mut mono_output = vst3_sys::vst::kMono;
+            assert_eq!(
+                wrapper.set_bus_arrangements(&mut mono_input, 1, &mut mono_output, 1),
+                kResultOk
+            );
+            assert_eq!(setup_processing(&wrapper, 48_000.0, 1024), kResultOk);
+            assert_eq!(process(&wrapper, 1, 1024), kResultOk);
+            assert_eq!(wrapper.inner.plugin.lock().initialize_calls.len(), 1);
+
+            assert_eq!(wrapper.set_processing(0), kResultOk);
+            assert_eq!(wrapper.set_processing(1), kResultOk);
+            assert_eq!(process(&wrapper, 1, 1024), kResultOk);
+        }
+
+        let plugin = wrapper.inner.plugin.lock();
+        assert_eq!(
+            plugin.initialize_calls,
+            [
+                (NonZeroU32::new(2), stereo_config),
+                (NonZeroU32::new(1), mono_config),
+            ]
+        );
+        assert_eq!(plugin.process_calls, [(2, 512), (1, 1024)]);
+    }
+
+    #[test]
+    fn oversized_host_buffers_are_split() {
+        let (wrapper, _calls) = connected_wrapper();
+
+        unsafe {
+            assert_eq!(setup_processing(&wrapper, 44_100.0, 256), kResultOk);
+            assert_eq!(wrapper.set_active(1), kResultOk);
+            assert_eq!(wrapper.set_processing(1), kResultOk);
+
+            // The host promised to never send more than 256 samples at a time
+            assert_eq!(process(&wrapper, 2, 1000), kResultOk);
+            assert_eq!(process(&wrapper, 2, 200), kResultOk);
+        }
+
+        assert_eq!(
+            wrapper.inner.plugin.lock().process_calls,
+            [(2, 256), (2, 256), (2, 256), (2, 232), (2, 200)]
+        );
+    }
+
+    #[test]
+    fn host_name_from_host_application() {
+        let wrapper = Wrapper::<TestPlugin>::new();
+        let host_application = Box::leak(TestHostApplication::allocate());
+        let context = &host_application.__ihostapplicationvptr as *const *const _ as *mut c_void;
+
+        assert_eq!(unsafe { wrapper.initialize(context) }, kResultOk);
+        assert_eq!(
+            *wrapper.inner.host_info.borrow(),
+            Some(HostInfo {
+                name: String::from("Simulated Host"),
+                version: None,
+            })
+        );
+
+        // The host application is optional
+        let wrapper = Wrapper::<TestPlugin>::new();
+        assert_eq!(
+            unsafe { wrapper.initialize(std::ptr::null_mut()) },
+            kResultOk
+        );
+        assert_eq!(*wrapper.inner.host_info.borrow(), None);
+    }
+
+    #[test]
+    fn tail_changes_notify_host() {
+        let (wrapper, calls) = connected_wrapper();
+        let latency_changed = HandlerCall::RestartComponent(RestartFlags::kLatencyChanged as i32);
+
+        wrapper.inner.set_tail_samples(512);
+        assert_eq!(unsafe { wrapper.get_tail_samples() }, 512);
+        assert_eq!(*calls.lock(), [latency_changed]);
+
+        // Setting the same tail length again does not restart the plugin
+        wrapper.inner.set_tail_samples(512);
+        assert_eq!(*calls.lock(), [latency_changed]);
+
+        // The tail is always reported as infinite while the plugin keeps itself alive, so changing
+        // the tail length doesn't change anything for the host
+        wrapper
+            .inner
+            .last_process_status
+            .store(ProcessStatus::KeepAlive);
+        assert_eq!(unsafe { wrapper.get_tail_samples() }, INFINITE_TAIL);
+        wrapper.inner.set_tail_samples(1024);
+        assert_eq!(unsafe { wrapper.get_tail_samples() }, INFINITE_TAIL);
+        assert_eq!(*calls.lock(), [latency_changed]);
+
+        wrapper
+            .inner
+            .last_process_status
+            .store(ProcessStatus::Normal);
+        wrapper.inner.set_tail_samples(256);
+        assert_eq!(unsafe { wrapper.get_tail_samples() }, 256);
+        assert_eq!(*calls.lock(), [latency_changed, latency_changed]);
+    }
+
+    #[test]
+    fn tail_follows_parameter_changes() {
+        let (wrapper, calls) = connected_wrapper();
+        let latency_changed = HandlerCall::RestartComponent(RestartFlags::kLatencyChanged as i32);
+        let hash = hash_param_id("gain");
+        wrapper.inner.plugin.lock().report_tail = true;
+
+        unsafe {
+            assert_eq!(setup_processing(&wrapper, 44_100.0, 512), kResultOk);
+            assert_eq!(wrapper.set_active(1), kResultOk);
+            assert_eq!(wrapper.set_processing(1), kResultOk);
+
+            // The plugin reports a tail of `gain * 1000` samples
+            assert_eq!(wrapper.set_param_normalized(hash, 0.75), kResultOk);
+            assert_eq!(process(&wrapper, 2, 512), kResultOk);
+            assert_eq!(wrapper.get_tail_samples(), 750);
+            assert_eq!(*calls.lock(), [latency_changed]);
+
+            // Reporting the same tail again doesn't restart the plugin
+            assert_eq!(process(&wrapper, 2, 512), kResultOk);
+            assert_eq!(*calls.lock(), [latency_changed]);
+
+            assert_eq!(wrapper.set_param_normalized(hash, 0.25), kResultOk);
+            assert_eq!(process(&wrapper, 2, 512), kResultOk);
+            assert_eq!(wrapper.get_tail_samples(), 250);
+            assert_eq!(*calls.lock(), [latency_changed, latency_changed]);
+        }
+    }
+
+    #[test]
+    fn process_mode_follows_process_data() {
+        let (wrapper, _calls) = connected_wrapper();
+
+        unsafe {
+            assert_eq!(setup_processing(&wrapper, 44_100.0, 512), kResultOk);
+            assert_eq!(wrapper.set_active(1), kResultOk);
+            assert_eq!(wrapper.set_processing(1), kResultOk);
+
+            // Hosts can switch to offline processing for a bounce and back without calling
+            // `setupProcessing()` again
+            for mode in [
+                ProcessModes::kRealtime,
+                ProcessModes::kOffline,
+                ProcessModes::kPrefetch,
+                ProcessModes::kRealtime,
+            ] {
+                assert_eq!(process_in_mode(&wrapper, 2, 512, mode), kResultOk);
+            }
+        }
+
+        assert_eq!(
+            wrapper.inner.plugin.lock().process_modes,
+            [
+                ProcessMode::Realtime,
+                ProcessMode::Offline,
+                ProcessMode::Buffered,
+                ProcessMode::Realtime
+            ]
+        );
+    }
+
+    #[test]
+    fn midi_cc_assignments() {
+        let wrapper = Wrapper::<TestPlugin>::new();
+        let assignment = |channel: i16, cc: i16| {
+            let mut param_id = 0;
+            let result =
+                unsafe { wrapper.get_midi_controller_assignment(0, channel, cc, &mut param_id) };
+
+            (result == kResultOk).then_some(param_id)
+        };
+
+        assert_eq!(assignment(0, 7), Some(hash_param_id("gain")));
+        assert_eq!(assignment(15, 7), Some(hash_param_id("gain")));
+        assert_eq!(assignment(1, 8), Some(hash_param_id("gain")));
+        assert_eq!(assignment(0, 8), None);
+        // The plugin doesn't accept MIDI CCs, so unassigned CCs are not mapped
+        assert_eq!(assignment(0, 1), None);
+    }
+}